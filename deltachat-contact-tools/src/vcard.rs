@@ -22,6 +22,8 @@ pub struct VcardContact {
     pub profile_image: Option<String>,
     /// The biography, stored in the vcard property `note`
     pub biography: Option<String>,
+    /// The contact's invite link, vcard property `url`
+    pub invite_link: Option<String>,
     /// The timestamp when the vcard was created / last updated, vcard property `rev`
     pub timestamp: Result<i64>,
 }
@@ -105,6 +107,9 @@ fn format_timestamp(c: &VcardContact) -> Option<String> {
         if let Some(biography) = &c.biography {
             res += &format!("NOTE:{}\r\n", escape(biography));
         }
+        if let Some(invite_link) = &c.invite_link {
+            res += &format!("URL:{}\r\n", escape(invite_link));
+        }
         if let Some(timestamp) = format_timestamp(c) {
             res += &format!("REV:{timestamp}\r\n");
         }
@@ -230,6 +235,7 @@ fn parse_datetime(datetime: &str) -> Result<i64> {
         let mut key = None;
         let mut photo = None;
         let mut biography = None;
+        let mut invite_link = None;
         let mut datetime = None;
 
         for mut line in lines.by_ref() {
@@ -251,6 +257,8 @@ fn parse_datetime(datetime: &str) -> Result<i64> {
                 photo.get_or_insert(p);
             } else if let Some((_params, bio)) = vcard_property(line, "note") {
                 biography.get_or_insert(bio);
+            } else if let Some((_params, url)) = vcard_property(line, "url") {
+                invite_link.get_or_insert(url);
             } else if let Some((_params, rev)) = vcard_property(line, "rev") {
                 datetime.get_or_insert(rev);
             } else if line.eq_ignore_ascii_case("END:VCARD") {
@@ -265,6 +273,7 @@ fn parse_datetime(datetime: &str) -> Result<i64> {
                     key: key.map(|s| s.to_string()),
                     profile_image: photo.map(|s| s.to_string()),
                     biography,
+                    invite_link,
                     timestamp: datetime
                         .as_deref()
                         .context("No timestamp in vcard")
@@ -92,6 +92,9 @@ fn test_make_and_parse_vcard() {
             key: Some("[base64-data]".to_string()),
             profile_image: Some("image in Base64".to_string()),
             biography: Some("Hi,\nI'm Alice; and this is a backslash: \\".to_string()),
+            invite_link: Some(
+                "https://i.delta.chat/#FPR&a=alice%40example.org&n=Alice".to_string(),
+            ),
             timestamp: Ok(1713465762),
         },
         VcardContact {
@@ -100,6 +103,7 @@ fn test_make_and_parse_vcard() {
             key: None,
             profile_image: None,
             biography: None,
+            invite_link: None,
             timestamp: Ok(0),
         },
     ];
@@ -111,6 +115,7 @@ fn test_make_and_parse_vcard() {
              KEY:data:application/pgp-keys;base64\\,[base64-data]\r\n\
              PHOTO:data:image/jpeg;base64\\,image in Base64\r\n\
              NOTE:Hi\\,\\nI'm Alice\\; and this is a backslash: \\\\\r\n\
+             URL:https://i.delta.chat/#FPR&a=alice%40example.org&n=Alice\r\n\
              REV:20240418T184242Z\r\n\
              END:VCARD\r\n",
         "BEGIN:VCARD\r\n\
@@ -135,6 +140,7 @@ fn test_make_and_parse_vcard() {
             assert_eq!(parsed[i].authname, contacts[i].authname);
             assert_eq!(parsed[i].key, contacts[i].key);
             assert_eq!(parsed[i].profile_image, contacts[i].profile_image);
+            assert_eq!(parsed[i].invite_link, contacts[i].invite_link);
             assert_eq!(
                 parsed[i].timestamp.as_ref().unwrap(),
                 contacts[i].timestamp.as_ref().unwrap()
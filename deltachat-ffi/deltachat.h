@@ -376,6 +376,26 @@ uint32_t        dc_get_id                    (dc_context_t* context);
 dc_event_emitter_t* dc_get_event_emitter(dc_context_t* context);
 
 
+/**
+ * Create an event emitter that first receives up to `n` last events
+ * emitted before its creation.
+ *
+ * This is useful to get e.g. errors emitted during startup
+ * if the event emitter is created late.
+ * Events are kept until the first event emitter is created
+ * and afterwards as long as an event emitter created with replay exists.
+ * At most 1000 last events and 1 MiB are kept,
+ * events with large payloads such as #DC_EVENT_WEBXDC_REALTIME_DATA are not kept.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as created by dc_context_new().
+ * @param n Maximum number of past events to receive.
+ * @return Returns the event emitter, NULL on errors.
+ *     Must be freed using dc_event_emitter_unref() after usage.
+ */
+dc_event_emitter_t* dc_get_event_emitter_with_replay(dc_context_t* context, int n);
+
+
 /**
  * Get the blob directory.
  *
@@ -395,6 +415,8 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    Instead, set `addr` and call dc_configure().
  * - `displayname`  = Own name to use when sending messages. MUAs are allowed to spread this way e.g. using CC, defaults to empty
  * - `selfstatus`   = Own status to display, e.g. in e-mail footers, defaults to empty
+ * - `email_signature` = Signature appended below the text of messages to classic e-mail recipients,
+ *                    replacing `selfstatus` there. Chat messages never carry it. Defaults to empty.
  * - `selfavatar`   = File containing avatar. Will immediately be copied to the 
  *                    `blobdir`; the original image will not be needed anymore.
  *                    NULL to remove the avatar.
@@ -408,11 +430,21 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    1=send a copy of outgoing messages to self (default).
  *                    Sending messages to self is needed for a proper multi-account setup,
  *                    however, on the other hand, may lead to unwanted notifications in non-delta clients.
+ * - `sent_folder_strategy` = How copies of messages sent to others are kept on the server:
+ *                    DC_SENT_FOLDER_STRATEGY_BCC_SELF (0) = send a copy to self if `bcc_self` is set (default),
+ *                    DC_SENT_FOLDER_STRATEGY_APPEND (1) = append a copy to the Sent folder,
+ *                    DC_SENT_FOLDER_STRATEGY_NONE (2) = do not keep a copy.
+ *                    Use 1 or 2 for servers saving sent messages themselves to avoid duplicates;
+ *                    other devices do not get the messages then.
+ *                    Chatmail servers only support DC_SENT_FOLDER_STRATEGY_BCC_SELF.
  * - `delete_device_after` = 0=do not delete messages from device automatically (default),
  *                    >=1=seconds, after which messages are deleted automatically from the device.
  *                    Messages in the "saved messages" chat (see dc_chat_is_self_talk()) are skipped.
  *                    Messages are deleted whether they were seen or not, the UI should clearly point that out.
  *                    See also dc_estimate_deletion_cnt().
+ * - `mark_expunged_msgs` = 1=mark messages that disappeared from the server without our involvement,
+ *                    e.g. because of retention policy, as removed from server, see dc_msg_is_removed_from_server(),
+ *                    0=only forget about them (default).
  * - `media_quality` = DC_MEDIA_QUALITY_BALANCED (0) =
  *                    good outgoing images/videos/voice quality at reasonable sizes (default)
  *                    DC_MEDIA_QUALITY_WORSE (1)
@@ -426,7 +458,10 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    adds Auto-Submitted header to outgoing messages,
  *                    accepts contact requests automatically (calling dc_accept_chat() is not needed),
  *                    does not cut large incoming text messages,
- *                    handles existing messages the same way as new ones if `fetch_existing_msgs=1`.
+ *                    handles existing messages the same way as new ones if `initial_fetch_count` or `initial_fetch_since_days` is set.
+ * - `save_mime_headers` = 1=retain the full MIME of all received messages,
+ *                    so that gateway bots can get it with dc_get_msg_raw_mime(),
+ *                    0=retain the full MIME only for messages with HTML parts (default).
  * - `last_msg_id` = database ID of the last message processed by the bot.
  *                   This ID and IDs below it are guaranteed not to be returned
  *                   by dc_get_next_msgs() and dc_wait_next_msgs().
@@ -437,12 +472,28 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                   For most bots calling `dc_markseen_msgs()` is the
  *                   recommended way to update this value
  *                   even for self-sent messages.
- * - `fetch_existing_msgs` = 0=do not fetch existing messages on configure (default),
- *                    1=fetch most recent existing messages on configure.
- *                    In both cases, existing recipients are added to the contact database.
+ * - `initial_fetch_count` = Number of most recent existing messages to fetch
+ *                    when a folder is selected for the first time, e.g. after configuring.
+ *                    0=no limit (default). Existing messages are not fetched at all
+ *                    if `initial_fetch_since_days` is 0 as well.
+ * - `initial_fetch_since_days` = Maximum age in days of existing messages to fetch
+ *                    when a folder is selected for the first time.
+ *                    0=no limit (default). Existing messages are not fetched at all
+ *                    if `initial_fetch_count` is 0 as well.
  * - `disable_idle` = 1=disable IMAP IDLE even if the server supports it,
  *                    0=use IMAP IDLE if the server supports it.
  *                    This is a developer option used for testing polling used as an IDLE fallback.
+ * - `watch_extra_folders` = Additional folders to fetch messages from, separated by newline,
+ *                    e.g. folders the server filters mail into.
+ *                    New messages there are fetched together with the watched folder, without IDLE.
+ * - `invite_link_domain` = Domain of the landing page
+ *                    the invite links returned by dc_get_securejoin_qr() point to,
+ *                    defaults to `i.delta.chat`.
+ *                    Must be a bare hostname without scheme, port or path.
+ *                    dc_check_qr() recognizes invite links using any domain.
+ * - `coalesce_member_changes` = 1=merge consecutive group member changes received within a few minutes
+ *                    into a single info message, the individual messages are hidden,
+ *                    0=show each member change (default).
  * - `download_limit` = Messages up to this number of bytes are downloaded automatically.
  *                    For messages with large attachments, two messages are sent:
  *                    a Pre-Message containing metadata and text and a Post-Message additionally
@@ -454,6 +505,51 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    always auto-downloaded.
  *                    0 = no limit (default).
  *                    Changes affect future messages only.
+ * - `data_saver` = 1=reduce network traffic, e.g. on metered connections:
+ *                    the avatar is only sent when it changes or to new contacts,
+ *                    messages larger than 160 KiB are not downloaded automatically,
+ *                    polling is done less often if IMAP IDLE is not available
+ *                    and statistics are not sent.
+ *                    UIs should not load link previews while this is enabled.
+ *                    0=normal operation (default).
+ * - `max_attachment_bytes` = Encrypted messages with attachments larger than this number of bytes
+ *                    get the attachment encrypted and uploaded to `attachment_upload_url`;
+ *                    the message only contains the download URL and the secret.
+ *                    Recipients download the attachment like a Post-Message.
+ *                    0 = no limit, never upload (default).
+ * - `attachment_upload_url` = HTTPS base URL of the attachment upload service.
+ *                    Attachments are uploaded using HTTP PUT to `<url>/<random id>`.
+ * - `attachment_upload_token` = Bearer token used to authenticate uploads, if needed.
+ * - `p2p_attachment_bytes` = Encrypted messages in 1:1 chats with a recently seen contact
+ *                    with attachments larger than this number of bytes
+ *                    get the attachment offered directly over iroh if `webxdc_realtime_enabled` is set;
+ *                    the Pre-Message contains the hash of the attachment and the sender's node address.
+ *                    Recipients download the attachment over iroh while the sender is online
+ *                    and download the Post-Message sent by email otherwise.
+ *                    0 = never offer attachments over iroh (default).
+ * - `lan_messaging` = 1 = Announce this device on the local network
+ *                    and deliver messages directly to contacts discovered there,
+ *                    e.g. when there is no internet connection.
+ *                    Messages are still sent by email once online.
+ *                    Requires `webxdc_realtime_enabled`.
+ *                    0 = do not use the local network (default).
+ * - `minimal_headers` = 1 = Reduce metadata in outgoing messages:
+ *                    the avatar is not sent, unencrypted messages do not carry the ephemeral timer
+ *                    and their sending time is randomized slightly.
+ *                    0 = send all optional headers (default).
+ * - `subject_mode` = DC_SUBJECT_MODE_THREAD_STABLE (0) =
+ *                    reuse the subject of the chat or the quoted message (default),
+ *                    DC_SUBJECT_MODE_FIRST_WORDS (1) = use the first words of the message text,
+ *                    DC_SUBJECT_MODE_FIXED (2) = always use `subject_text`,
+ *                    DC_SUBJECT_MODE_EMPTY_WHEN_ENCRYPTED (3) = use an empty subject for encrypted messages.
+ *                    Subjects set by dc_msg_set_subject() are always used.
+ * - `subject_text` = subject of outgoing messages if `subject_mode` is DC_SUBJECT_MODE_FIXED.
+ * - `quiet_hours` = daily quiet hours as `HH:MM-HH:MM`, e.g. `22:00-07:00`,
+ *                    optionally followed by a UTC offset, e.g. `22:00-07:00 +01:00`;
+ *                    without an offset, the local time is used.
+ *                    During quiet hours, no notifications should be shown for #DC_EVENT_INCOMING_MSG,
+ *                    see dc_is_quiet_hours(), and background work is deferred.
+ *                    Empty or unset = no quiet hours (default).
  * - `protect_autocrypt` = Enable Header Protection for Autocrypt header.
  *                    This is an experimental option not compatible to other MUAs
  *                    and older Delta Chat versions.
@@ -486,6 +582,9 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                       1 = Contacts (default, does not include contact requests),
  *                       2 = Nobody (calls never result in a notification).
  * - `force_encryption` = 1 (default) to force encryption, 0 to allow unencrypted messages.
+ * - `unified_push_endpoint` = UnifiedPush endpoint URL received from the distributor, must be an HTTPS URL.
+ *                    If set, the endpoint is registered on the chatmail server instead of the device token.
+ *                    Pass messages received for the endpoint to dc_accounts_handle_unified_push().
  *
  * Also, there are configs that are only needed
  * if you want to use the deprecated dc_configure() API, such as:
@@ -557,6 +656,42 @@ char*           dc_get_config                (dc_context_t* context, const char*
 int             dc_set_stock_translation(dc_context_t* context, uint32_t stock_id, const char* stock_msg);
 
 
+/**
+ * Set stock string translation for numbers of a plural category.
+ *
+ * Plural forms are used for stock strings containing a number,
+ * e.g. #DC_STR_MEMBER_CHANGES.
+ * If no translation is set for a category,
+ * the translation for #DC_PLURAL_OTHER is used,
+ * then the translation set by dc_set_stock_translation().
+ * The category of a number depends on the language set by dc_set_stock_language().
+ *
+ * The function will emit warnings if it returns an error state,
+ * e.g. if the translation contains placeholders that the default string does not have.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param stock_id The integer ID of the stock message, one of the @ref DC_STR constants.
+ * @param category The CLDR plural category, one of the DC_PLURAL_* constants.
+ * @param stock_msg The message to be used.
+ * @return int (==0 on error, 1 on success)
+ */
+int             dc_set_stock_translation_plural(dc_context_t* context, uint32_t stock_id, int category, const char* stock_msg);
+
+
+/**
+ * Set the language of the stock string translations.
+ *
+ * The language is used to select the plural forms
+ * set by dc_set_stock_translation_plural().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param language ISO 639-1 code of the language, e.g. `pl`.
+ */
+void            dc_set_stock_language        (dc_context_t* context, const char* language);
+
+
 /**
  * Set configuration values from a QR code.
  * Before this function is called, dc_check_qr() should be used to get the QR code type.
@@ -740,6 +875,20 @@ void            dc_configure                 (dc_context_t* context);
 int             dc_is_configured   (const dc_context_t* context);
 
 
+/**
+ * Check if the current time is within the quiet hours
+ * configured with the `quiet_hours` option, see dc_set_config().
+ *
+ * #DC_EVENT_INCOMING_MSG is still emitted during quiet hours,
+ * but no notification should be shown for it.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return 1=quiet hours are active, 0=quiet hours are not active or not configured.
+ */
+int             dc_is_quiet_hours  (const dc_context_t* context);
+
+
 /**
  * Start job and IMAP/SMTP tasks.
  * If IO is already running, nothing happens.
@@ -1355,6 +1504,23 @@ int             dc_was_device_msg_ever_added (dc_context_t* context, const char*
 dc_msg_t*       dc_get_draft                 (dc_context_t* context, uint32_t chat_id);
 
 
+/**
+ * Get a previous version of the draft for a chat.
+ * Up to 10 versions are saved when the draft is replaced or deleted.
+ * Versions are removed when they are sent.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to get the draft version for.
+ * @param index Index of the version, 0 for the most recent one.
+ * @return Message object.
+ *     Can be passed to dc_set_draft() to restore the version.
+ *     Must be freed using dc_msg_unref() after usage.
+ *     If there is no version with the given index, NULL is returned.
+ */
+dc_msg_t*       dc_get_draft_history         (dc_context_t* context, uint32_t chat_id, int index);
+
+
 #define         DC_GCM_ADDDAYMARKER          0x01
 
 
@@ -1382,6 +1548,28 @@ dc_msg_t*       dc_get_draft                 (dc_context_t* context, uint32_t ch
 dc_array_t*     dc_get_chat_msgs             (dc_context_t* context, uint32_t chat_id, uint32_t flags, uint32_t marker1before);
 
 
+/**
+ * Get the IDs of the messages around a given message.
+ *
+ * Returns up to `before` messages preceding the given message,
+ * the message itself and up to `after` messages following it,
+ * sorted the same way as dc_get_chat_msgs() does.
+ * Day markers are not added.
+ *
+ * This is useful to jump to a quoted message or a search result
+ * without loading the whole chat.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param msg_id The ID of the message to get the surrounding messages for.
+ * @param before The maximum number of messages to return before the given message.
+ * @param after The maximum number of messages to return after the given message.
+ * @return Array of message IDs, must be dc_array_unref()'d when no longer used.
+ *     On errors, e.g. if the message does not exist, an empty array is returned.
+ */
+dc_array_t*     dc_get_msgs_around           (dc_context_t* context, uint32_t msg_id, int before, int after);
+
+
 /**
  * Get the total number of messages in a chat.
  *
@@ -1413,6 +1601,23 @@ int             dc_get_msg_cnt               (dc_context_t* context, uint32_t ch
 int             dc_get_fresh_msg_cnt         (dc_context_t* context, uint32_t chat_id);
 
 
+/**
+ * Get the first unread message in a chat.
+ * The UI should show an "unread messages" divider before this message.
+ *
+ * The read marker is updated by dc_marknoticed_chat() and dc_markseen_msgs()
+ * and synchronized across own devices,
+ * so the divider is shown at the same place on all devices.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat to get the first unread message for.
+ * @return The ID of the first unread message.
+ *     0 if there are no unread messages or on errors.
+ */
+uint32_t        dc_get_first_unread_msg_id   (dc_context_t* context, uint32_t chat_id);
+
+
 /**
  * Returns a list of similar chats.
  *
@@ -1554,6 +1759,23 @@ void            dc_marknoticed_chat          (dc_context_t* context, uint32_t ch
 void            dc_markfresh_chat            (dc_context_t* context, uint32_t chat_id);
 
 
+/**
+ * Mark a chat as unread.
+ *
+ * Unlike dc_markfresh_chat(), this does not change the state of any message
+ * but sets a flag on the chat that is synchronized across own devices.
+ * While the flag is set, dc_get_fresh_msg_cnt() returns at least 1 for the chat
+ * and the chat is counted for the @ref dc_get_chatlist() "archive link" if archived.
+ *
+ * The flag is cleared by dc_marknoticed_chat().
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat ID to mark as unread.
+ */
+void            dc_markunread_chat           (dc_context_t* context, uint32_t chat_id);
+
+
 /**
  * Returns all message IDs of the given types in a given chat or any chat.
  * Typically used to show a gallery.
@@ -1645,6 +1867,41 @@ void            dc_block_chat                (dc_context_t* context, uint32_t ch
  */
 void            dc_accept_chat               (dc_context_t* context, uint32_t chat_id);
 
+
+/**
+ * Accept all contact request chats at once.
+ *
+ * All chats are accepted in a single database transaction
+ * and only one #DC_EVENT_CHATLIST_CHANGED is emitted,
+ * so this is suitable e.g. after restoring an old account with many pending requests.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param min_age Only accept requests whose last message is at least this many seconds old.
+ *     0 for no limit.
+ * @param max_age Only accept requests whose last message is at most this many seconds old.
+ *     0 for no limit.
+ * @return The number of accepted chats.
+ */
+int             dc_accept_all_requests       (dc_context_t* context, int64_t min_age, int64_t max_age);
+
+
+/**
+ * Block all contact request chats at once.
+ *
+ * Each chat is blocked as described at dc_block_chat(),
+ * however, only one #DC_EVENT_CHATLIST_CHANGED is emitted.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param min_age Only block requests whose last message is at least this many seconds old.
+ *     0 for no limit.
+ * @param max_age Only block requests whose last message is at most this many seconds old.
+ *     0 for no limit.
+ * @return The number of blocked chats.
+ */
+int             dc_block_all_requests        (dc_context_t* context, int64_t min_age, int64_t max_age);
+
 /**
  * Get the contact IDs belonging to a chat.
  *
@@ -1912,6 +2169,69 @@ int             dc_set_chat_profile_image    (dc_context_t* context, uint32_t ch
  */
 int             dc_set_chat_mute_duration             (dc_context_t* context, uint32_t chat_id, int64_t duration);
 
+
+/**
+ * Set a UI-specific property of a chat, e.g. the wallpaper dimming.
+ *
+ * The property is synchronized across own devices.
+ * In contrast to dc_set_config() with `ui.*` keys,
+ * the property is removed when the chat is deleted.
+ *
+ * Sends out #DC_EVENT_CHAT_UI_PROPERTY_CHANGED if the value changed.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to set the property for.
+ * @param key The key of the property, must be prefixed by `ui.`,
+ *     to avoid conflicts between different UIs,
+ *     it is recommended to use a prefix as `ui.android.` or `ui.desktop.` for UI-specific properties.
+ * @param value The value to set, NULL to remove the property.
+ * @return 1=success, 0=error
+ */
+int             dc_set_chat_ui_property               (dc_context_t* context, uint32_t chat_id, const char* key, const char* value);
+
+
+/**
+ * Get a UI-specific property of a chat set with dc_set_chat_ui_property().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to get the property for.
+ * @param key The key of the property, must be prefixed by `ui.`.
+ * @return The value of the property, an empty string if the property is not set.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_chat_ui_property               (dc_context_t* context, uint32_t chat_id, const char* key);
+
+
+/**
+ * Set the wallpaper image of a chat.
+ *
+ * The image is copied to the blob directory and recoded if it is too large.
+ * The wallpaper is synchronized across own devices and included in backups.
+ *
+ * Sends out #DC_EVENT_CHAT_MODIFIED.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to set the wallpaper for.
+ * @param image Full path of the image to use as wallpaper, NULL to remove the wallpaper.
+ * @return 1=success, 0=error
+ */
+int             dc_set_chat_wallpaper                 (dc_context_t* context, uint32_t chat_id, const char* image);
+
+
+/**
+ * Get the wallpaper image of a chat set with dc_set_chat_wallpaper().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to get the wallpaper for.
+ * @return Full path of the wallpaper image, NULL if no wallpaper is set.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_chat_wallpaper                 (dc_context_t* context, uint32_t chat_id);
+
 // handle messages
 
 /**
@@ -1979,6 +2299,24 @@ char*           dc_get_msg_info              (dc_context_t* context, uint32_t ms
 char*           dc_get_msg_html              (dc_context_t* context, uint32_t msg_id);
 
 
+/**
+ * Get the full MIME of a received message as it was decrypted,
+ * e.g. for bridging messages to other networks.
+ *
+ * The full MIME is retained for all messages received
+ * while the `save_mime_headers` option is enabled, see dc_set_config(),
+ * otherwise only for messages where dc_msg_has_html() is set.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The message ID.
+ * @return Full MIME of the message, invalid UTF-8 sequences are replaced with U+FFFD.
+ *     NULL if the full MIME is not retained or on errors.
+ *     The result must be released using dc_str_unref().
+ */
+char*           dc_get_msg_raw_mime          (dc_context_t* context, uint32_t msg_id);
+
+
 /**
   * Asks the core to start downloading a message fully.
   * This function is typically called when the user hits the "Download" button
@@ -2001,6 +2339,27 @@ char*           dc_get_msg_html              (dc_context_t* context, uint32_t ms
 void dc_download_full_msg (dc_context_t* context, int msg_id);
 
 
+/**
+  * Downloads the original message from the server again
+  * and restores its attachment,
+  * e.g. after the file was deleted locally to free space.
+  *
+  * This is only possible as long as the message is still on the server,
+  * i.e. it was not deleted there, e.g. because of `delete_server_after`.
+  * The function blocks until the attachment is restored.
+  *
+  * On success, a @ref DC_EVENT_MSGS_CHANGED event is emitted
+  * and the attachment can be accessed again using dc_msg_get_file().
+  *
+  * @memberof dc_context_t
+  * @param context The context object.
+  * @param msg_id The ID of the message to restore the attachment for.
+  * @return 1=success, 0=error, e.g. the message has no attachment
+  *     or is not available on the server anymore.
+  */
+int dc_redownload_msg (dc_context_t* context, uint32_t msg_id);
+
+
 /**
  * Delete messages. The messages are deleted on all devices and
  * on the IMAP server.
@@ -2060,6 +2419,52 @@ void            dc_forward_msgs              (dc_context_t* context, const uint3
 void            dc_save_msgs                 (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
 
 
+/**
+ * Set a reminder for a message, e.g. for a "Remind me later" option.
+ *
+ * At the given time, @ref DC_EVENT_MSG_REMINDER is emitted
+ * and an incoming message is marked as fresh again,
+ * so that it is returned by dc_get_fresh_msgs() and counted as unread.
+ * The reminder is synchronized to other devices.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message to remind about.
+ * @param timestamp Unix timestamp of the reminder, 0 removes the reminder.
+ * @return 1=success, 0=error
+ */
+int             dc_set_msg_reminder          (dc_context_t* context, uint32_t msg_id, int64_t timestamp);
+
+
+/**
+ * Get the time of the reminder set with dc_set_msg_reminder().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message.
+ * @return Unix timestamp of the reminder, 0 if no reminder is set.
+ */
+int64_t         dc_get_msg_reminder          (dc_context_t* context, uint32_t msg_id);
+
+
+/**
+ * Report a message of a broadcast channel or mailing list to its owner,
+ * e.g. because it is spam or abusive.
+ *
+ * For broadcast channels, the report is sent encrypted to the channel owner,
+ * whose device emits @ref DC_EVENT_INCOMING_ABUSE_REPORT.
+ * For mailing lists, the report is sent to the address from the `List-Owner` header;
+ * mailing lists without this header cannot be reported to and 0 is returned.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message to report.
+ * @param reason Free-form text explaining the report, shown to the owner.
+ * @return 1=success, 0=error
+ */
+int             dc_report_msg_to_owner       (dc_context_t* context, uint32_t msg_id, const char* reason);
+
+
 /**
  * Resend messages and make information available for newly added chat members.
  * Resending sends out the original message, however, recipients and webxdc-status may differ.
@@ -2284,6 +2689,72 @@ dc_array_t*     dc_get_contacts              (dc_context_t* context, uint32_t fl
 dc_array_t*     dc_get_blocked_contacts      (dc_context_t* context);
 
 
+/**
+ * Export the list of blocked contacts as JSON,
+ * e.g. to import it into another account using dc_import_blocked_contacts().
+ *
+ * Blocked mailing lists are not exported.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return JSON string, must be released using dc_str_unref() after usage.
+ *     Empty string on errors.
+ */
+char*           dc_export_blocked_contacts   (dc_context_t* context);
+
+
+/**
+ * Block all contacts of a JSON block list
+ * as returned by dc_export_blocked_contacts().
+ *
+ * Contacts that do not exist yet are created.
+ * As with dc_block_contact(), blocking is synchronized to other devices.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param json The JSON block list.
+ * @return The number of newly blocked contacts, -1 on errors.
+ */
+int             dc_import_blocked_contacts   (dc_context_t* context, const char* json);
+
+
+/**
+ * Block all contacts from a domain.
+ * Existing contacts from the domain are blocked immediately,
+ * new contacts from the domain are blocked when their messages are received.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param domain The domain to block, e.g. `spam.example`.
+ * @return 1=success, 0=error.
+ */
+int             dc_block_domain              (dc_context_t* context, const char* domain);
+
+
+/**
+ * Remove a domain from the list of blocked domains.
+ * Contacts blocked because of the domain stay blocked
+ * and have to be unblocked using dc_block_contact().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param domain The domain to unblock.
+ * @return 1=success, 0=error.
+ */
+int             dc_unblock_domain            (dc_context_t* context, const char* domain);
+
+
+/**
+ * Get the domains blocked using dc_block_domain().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return The blocked domains, separated by newline.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_blocked_domains       (dc_context_t* context);
+
+
 /**
  * Block or unblock a contact.
  * May result in a #DC_EVENT_CONTACTS_CHANGED event.
@@ -2816,6 +3287,21 @@ char*           dc_create_qr_svg             (const char* payload);
 char* dc_get_last_error (dc_context_t* context);
 
 
+/**
+ * Get code of the last error.
+ *
+ * The code belongs to the error returned by dc_get_last_error()
+ * and to the last #DC_EVENT_ERROR emitted.
+ * After a failed dc_configure(), the code of the configuration error is returned.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return One of the @ref DC_ERROR_CODE constants.
+ *     0 (#DC_ERROR_CODE_OTHER) if there is no last error.
+ */
+int dc_get_last_error_code (dc_context_t* context);
+
+
 /**
  * Release a string returned by another deltachat-core function.
  * - Strings returned by any deltachat-core-function
@@ -3253,6 +3739,43 @@ void           dc_accounts_stop_background_fetch (dc_accounts_t *accounts);
  */
 void           dc_accounts_set_push_device_token (dc_accounts_t* accounts, const char *token);
 
+
+/**
+ * Sets device token used only by the given account
+ * instead of the token set with dc_accounts_set_push_device_token().
+ *
+ * This allows apps running a process per account
+ * to use a separate token or topic for each account,
+ * so that notification servers wake up only the process of the relevant account.
+ * Returns immediately.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param account_id The account ID.
+ * @param token Device token, NULL to remove the token of the account.
+ */
+void           dc_accounts_set_push_device_token_for (dc_accounts_t* accounts, uint32_t account_id, const char *token);
+
+
+/**
+ * Handles a message received from the UnifiedPush distributor
+ * for the endpoint set with the `unified_push_endpoint` config of the given account.
+ *
+ * The message is decrypted to make sure it was sent by the chatmail server,
+ * then a background fetch is performed for this account only,
+ * see dc_accounts_background_fetch().
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param account_id The account ID.
+ * @param message The raw message as received from the distributor.
+ * @param message_bytes The length of the message in bytes.
+ * @param timeout The background fetch timeout in seconds.
+ * @return Return 1 if DC_EVENT_ACCOUNTS_BACKGROUND_FETCH_DONE was emitted and 0 otherwise,
+ *     e.g. if the message could not be decrypted.
+ */
+int            dc_accounts_handle_unified_push (dc_accounts_t* accounts, uint32_t account_id, const uint8_t* message, size_t message_bytes, uint64_t timeout);
+
 /**
  * Create the event emitter that is used to receive events.
  *
@@ -3275,6 +3798,22 @@ void           dc_accounts_set_push_device_token (dc_accounts_t* accounts, const
 dc_event_emitter_t* dc_accounts_get_event_emitter (dc_accounts_t* accounts);
 
 
+/**
+ * Create an event emitter for the account manager
+ * that first receives up to `n` last events emitted before its creation.
+ *
+ * This is similar to dc_get_event_emitter_with_replay(), which, however,
+ * must not be called for accounts handled by the account manager.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param n Maximum number of past events to receive.
+ * @return Returns the event emitter, NULL on errors.
+ *     Must be freed using dc_event_emitter_unref() after usage.
+ */
+dc_event_emitter_t* dc_accounts_get_event_emitter_with_replay (dc_accounts_t* accounts, int n);
+
+
 /**
  * @class dc_array_t
  *
@@ -3980,6 +4519,11 @@ int             dc_msg_get_viewtype           (const dc_msg_t* msg);
  * - @ref DC_STATE_OUT_DELIVERED - Outgoing message successfully delivered to server (one checkmark).
  *   Note, that already delivered messages may get into the state @ref DC_STATE_OUT_FAILED if we get such a hint from the server.
  *   If a sent message changes to this state, you will receive the event #DC_EVENT_MSG_DELIVERED.
+ * - @ref DC_STATE_OUT_SERVER_RCVD - Outgoing message reached the server of the recipient,
+ *   confirmed by a delivery receipt from that server (one checkmark, e.g. filled).
+ *   Only some servers send such receipts, so most messages go from @ref DC_STATE_OUT_DELIVERED
+ *   to @ref DC_STATE_OUT_MDN_RCVD directly.
+ *   If a sent message changes to this state, you will receive the event #DC_EVENT_MSGS_CHANGED.
  * - @ref DC_STATE_OUT_MDN_RCVD - Outgoing message read by the recipient
  *   (two checkmarks; this requires goodwill on the receiver's side)
  *   If a sent message changes to this state, you will receive the event #DC_EVENT_MSG_READ.
@@ -4049,6 +4593,23 @@ int64_t          dc_msg_get_received_timestamp (const dc_msg_t* msg);
 int64_t          dc_msg_get_sort_timestamp     (const dc_msg_t* msg);
 
 
+/**
+ * Get a key for ordering the message among other messages of the chat.
+ * Comparing the keys of two messages with strcmp() gives the same order
+ * as the list returned by dc_get_chat_msgs(),
+ * even if the messages have the same sort timestamp.
+ *
+ * UIs should use this function when inserting messages into loaded lists
+ * instead of comparing timestamps and message IDs themselves.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The sort key, never NULL.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*            dc_msg_get_sort_key           (const dc_msg_t* msg);
+
+
 /**
  * Get the text of the message.
  * If there is no text associated with the message, an empty string is returned.
@@ -4277,9 +4838,10 @@ int             dc_msg_get_duration           (const dc_msg_t* msg);
 int             dc_msg_get_showpadlock        (const dc_msg_t* msg);
 
 /**
- * Check if an incoming message is a bot message, i.e. automatically submitted.
+ * Check if a message is a bot message, i.e. automatically submitted.
  *
- * Return value for outgoing messages is unspecified.
+ * For outgoing messages, this is set by dc_msg_set_bot()
+ * or for all messages of accounts with the `bot` config option set.
  *
  * @memberof dc_msg_t
  * @param msg The message object.
@@ -4385,6 +4947,23 @@ char*           dc_msg_get_summarytext        (const dc_msg_t* msg, int approx_c
 char*           dc_msg_get_override_sender_name(const dc_msg_t* msg);
 
 
+/**
+ * Get the language detected in the text of a received message.
+ *
+ * The language is detected when the message is received
+ * and can be used to offer a translation
+ * only if the language differs from the language of the UI.
+ * The detection is not reliable for short texts
+ * and no language is detected for messages sent by the user.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return ISO 639-1 code of the language, e.g. `de`, or NULL if unknown.
+ *     The returned string must be released using dc_str_unref().
+ */
+char*           dc_msg_get_language          (const dc_msg_t* msg);
+
+
 
 /**
  * Check if a message has a deviating timestamp.
@@ -4461,6 +5040,37 @@ int             dc_msg_is_forwarded           (const dc_msg_t* msg);
  int             dc_msg_is_edited             (const dc_msg_t* msg);
 
 
+/**
+ * Check if the message was removed from the server without our involvement,
+ * e.g. because of retention policy of the server or by the administrator.
+ *
+ * Such messages can be marked by the UI,
+ * as their attachments cannot be downloaded again.
+ * Messages are only marked if the `mark_expunged_msgs` config is enabled,
+ * see dc_set_config().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=message was removed from the server, 0=otherwise.
+ */
+int             dc_msg_is_removed_from_server (const dc_msg_t* msg);
+
+
+/**
+ * Check if the message was shared by another member
+ * when the user joined the group via QR code.
+ *
+ * The original sender of such messages is not verified,
+ * they are only as trustworthy as the member who shared them.
+ * The UI may show such messages differently.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=message is from shared history, 0=otherwise.
+ */
+int             dc_msg_is_from_shared_history (const dc_msg_t* msg);
+
+
 /**
  * Check if the message is an informational message, created by the
  * device or by another users. Such messages are not "typed" by the user but
@@ -4657,6 +5267,20 @@ int dc_msg_has_html (dc_msg_t* msg);
 int dc_msg_get_download_state (const dc_msg_t* msg);
 
 
+/**
+ * Check if the attachment of a received message looks harmful.
+ *
+ * UIs may show a caution banner before the file is saved or opened.
+ * See also #DC_EVENT_ATTACHMENT_WARNING.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 0 if the attachment does not look suspicious,
+ *     otherwise one of the @ref DC_ATTACHMENT_WARNING values.
+ */
+int dc_msg_get_attachment_warning (const dc_msg_t* msg);
+
+
 /**
  * Set the text of a message object.
  * This does not alter any information in the database; this may be done by dc_send_msg() later.
@@ -4722,6 +5346,68 @@ void            dc_msg_set_subject            (dc_msg_t* msg, const char* subjec
 void            dc_msg_set_override_sender_name(dc_msg_t* msg, const char* name);
 
 
+/**
+ * Set an alternative text describing the attachment of a message,
+ * e.g. the content of an image, for users of screen readers.
+ *
+ * The text is sent along with the attachment
+ * and can be read by the recipients using dc_msg_get_alt_text().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param text The alternative text or NULL to remove it.
+ */
+void            dc_msg_set_alt_text          (dc_msg_t* msg, const char* text);
+
+
+/**
+ * Get the alternative text describing the attachment of a message,
+ * as set by the sender using dc_msg_set_alt_text().
+ *
+ * UIs should pass the text to screen readers
+ * and may show it e.g. when the image cannot be loaded.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The alternative text or NULL if there is none.
+ *     The returned string must be released using dc_str_unref().
+ */
+char*           dc_msg_get_alt_text          (const dc_msg_t* msg);
+
+
+/**
+ * Add a custom header to an outgoing message,
+ * e.g. for gateway bots bridging messages from other networks.
+ *
+ * Only `X-` headers not interpreted by the core are allowed,
+ * setting a header again replaces the previous value.
+ * In encrypted messages, the headers are encrypted as well.
+ * Recipients can read the headers from dc_get_msg_raw_mime().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param name The header name, e.g. `X-Bridge-Room`.
+ * @param value The header value, must not contain line breaks.
+ * @return 1=header set, 0=header name or value not allowed.
+ */
+int             dc_msg_set_extra_header      (dc_msg_t* msg, const char* name, const char* value);
+
+
+/**
+ * Mark an outgoing message as automatically submitted.
+ *
+ * The message is sent with the `Auto-Submitted: auto-generated` header,
+ * as all messages of accounts with the `bot` config option set,
+ * so that receiving bots and auto-responders do not reply to it.
+ * Recipients see the flag in dc_msg_is_bot().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param bot 1=mark the message as automatically submitted, 0=unmark it.
+ */
+void            dc_msg_set_bot               (dc_msg_t* msg, int bot);
+
+
 /**
  * Sets the file associated with a message.
  *
@@ -5579,6 +6265,11 @@ int64_t         dc_lot_get_timestamp     (const dc_lot_t* lot);
  */
 #define         DC_STATE_OUT_DELIVERED       26
 
+/**
+ * Outgoing message reached the server of the recipient. See dc_msg_get_state() for details.
+ */
+#define         DC_STATE_OUT_SERVER_RCVD     27
+
 /**
  * Outgoing message sent and seen by recipients(s). See dc_msg_get_state() for details.
  */
@@ -6098,6 +6789,16 @@ void dc_event_unref(dc_event_t* event);
  */
 #define DC_EVENT_DELETED_BLOB_FILE 151
 
+/**
+ * Inform about the progress of copying a large file into the blob directory,
+ * e.g. when a big video is attached to a message using dc_msg_set_file_and_deduplicate().
+ * Can be used to show a progress bar while the file is attached.
+ *
+ * @param data1 (int) Progress in permille, 1000=done.
+ * @param data2 (char*) Path of the file being copied.
+ */
+#define DC_EVENT_BLOB_COPY_PROGRESS 152
+
 /**
  * The library-user should write a warning string to the log.
  *
@@ -6121,6 +6822,9 @@ void dc_event_unref(dc_event_t* event);
  * failed (returned false). It should be sufficient to report only the _last_ error
  * in a message box then.
  *
+ * The category of the error is returned by dc_get_last_error_code().
+ * UIs should use it instead of matching the error string.
+ *
  * @param data1 0
  * @param data2 (char*) Error string, always set, never NULL.
  *     Some error strings are taken from dc_set_stock_translation(),
@@ -6179,6 +6883,17 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_INCOMING_REACTION        2002
 
 
+/**
+ * A subscriber reported a message of one's own broadcast channel
+ * using dc_report_msg_to_owner().
+ * The reports with the reasons are available through the JSON-RPC API.
+ *
+ * @param data1 (int) contact_id ID of the contact sending the report.
+ * @param data2 (int) msg_id ID of the reported message.
+ */
+#define DC_EVENT_INCOMING_ABUSE_REPORT    2004
+
+
 
 /**
  * A webxdc wants an info message or a changed summary to be notified.
@@ -6208,6 +6923,9 @@ void dc_event_unref(dc_event_t* event);
  * If the message is a webxdc info message,
  * dc_msg_get_parent() returns the webxdc instance the notification belongs to.
  *
+ * During quiet hours, see dc_is_quiet_hours(),
+ * the event is still emitted, but no notification should be shown.
+ *
  * @param data1 (int) chat_id
  * @param data2 (int) msg_id
  */
@@ -6227,6 +6945,20 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_INCOMING_MSG_BUNCH       2006
 
 
+/**
+ * A received message has an attachment that looks harmful,
+ * e.g. an executable or a file with a double extension.
+ * The UI may show a caution banner before the file is saved or opened,
+ * see dc_msg_get_attachment_warning() for the reason.
+ *
+ * The event is emitted in addition to the usual events for the message.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) msg_id
+ */
+#define DC_EVENT_ATTACHMENT_WARNING       2007
+
+
 /**
  * Messages were marked noticed or seen.
  * The UI may update badge counters or stop showing a chatlist-item with a bold font.
@@ -6281,6 +7013,18 @@ void dc_event_unref(dc_event_t* event);
  */
 #define DC_EVENT_MSG_DELETED              2016
 
+/**
+ * The aggregated delivery state of a message sent to a broadcast channel changed,
+ * i.e. it was sent out or another subscriber sent a read receipt.
+ *
+ * The number of subscribers the message was delivered to
+ * and the total number of subscribers are available in the JSON-RPC event.
+ *
+ * @param data1 (int) msg_id
+ * @param data2 (int) Number of subscribers who sent a read receipt.
+ */
+#define DC_EVENT_BROADCAST_DELIVERY_UPDATE 2017
+
 /**
  * Like @ref DC_EVENT_MSG_READ, but also fires on subsequent MDNs,
  * if there are multiple receivers, i.e. in groups and channels.
@@ -6291,6 +7035,17 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_MSG_READ_COUNT_CHANGED   2018
 
 
+/**
+ * A reminder set with dc_set_msg_reminder() is due.
+ * If the message is incoming, it is marked as fresh again.
+ * UI may show a notification for the message.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) msg_id
+ */
+#define DC_EVENT_MSG_REMINDER             2019
+
+
 /**
  * Chat changed. The name or the image of a chat group was changed or members were added or removed.
  * See dc_set_chat_name(), dc_set_chat_profile_image(), dc_add_contact_to_chat()
@@ -6310,6 +7065,16 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED 2021
 
 
+/**
+ * A UI-specific property of a chat was changed
+ * by dc_set_chat_ui_property() or on another device.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (char*) The key of the changed property.
+ */
+#define DC_EVENT_CHAT_UI_PROPERTY_CHANGED 2022
+
+
 /**
  * Chat was deleted.
  * This event is emitted in response to dc_delete_chat()
@@ -6373,6 +7138,28 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_IMEX_FILE_WRITTEN        2052
 
 
+/**
+ * Inform about the progress of searching older messages on the server
+ * started by the JSON-RPC API `fetch_older_messages()`.
+ * The found messages are downloaded in the background afterwards.
+ *
+ * @param data1 (int) 0=error, 1-999=progress in permille, 1000=success and done
+ * @param data2 0
+ */
+#define DC_EVENT_FETCH_OLDER_MSGS_PROGRESS 2055
+
+
+/**
+ * Inform about the progress of housekeeping.
+ * Housekeeping runs once a day when the app is not busy
+ * or when started by the JSON-RPC API `run_housekeeping_now()`.
+ *
+ * @param data1 (int) 1-999=progress in permille, 1000=done
+ * @param data2 0
+ */
+#define DC_EVENT_HOUSEKEEPING_PROGRESS 2056
+
+
 /**
  * Progress information of a secure-join handshake from the view of the inviter
  * (Alice, the person who shows the QR code).
@@ -6395,6 +7182,7 @@ void dc_event_unref(dc_event_t* event);
  *
  * @param data1 (int) The ID of the inviting contact.
  * @param data2 (int) The progress as:
+ *     0=inviter rejected the handshake, the reason is returned by dc_get_last_error_code()
  *     400=vg-/vc-request-with-auth sent, typically shown as "alice@addr verified, introducing myself."
  *     (Bob has verified alice and waits until Alice does the same for him)
  *     1000=vg-member-added/vc-contact-confirm received
@@ -6414,6 +7202,29 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CONNECTIVITY_CHANGED             2100
 
 
+/**
+ * Connections through the active proxy failed repeatedly
+ * and the next proxy configured in `proxy_url` is used now.
+ * `proxy_url` is reordered so that the proxy used now is the first one.
+ *
+ * @param data1 0
+ * @param data2 (char*) URL of the proxy used now.
+ */
+#define DC_EVENT_PROXY_SWITCHED                   2101
+
+
+/**
+ * The local clock differs from the server clock by more than a few minutes.
+ * Outgoing message timestamps are corrected by the measured difference,
+ * however, the UI may ask the user to check the date, time and time zone of the device.
+ *
+ * @param data1 (int) Seconds the local clock is ahead of the server clock,
+ *     negative if it is behind.
+ * @param data2 0
+ */
+#define DC_EVENT_CLOCK_SKEW_DETECTED              2102
+
+
 /**
  * The user's avatar changed.
  * You can get the new avatar file with `dc_get_config(context, "selfavatar")`.
@@ -6433,6 +7244,27 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CONFIG_SYNCED                    2111
 
 
+/**
+ * A config value was set by dc_set_config() or by a sync message from another device.
+ * In contrast to #DC_EVENT_CONFIG_SYNCED, this is emitted for all keys.
+ * The value isn't reported, you can get the new value with `dc_get_config(context, data2)`.
+ *
+ * @param data1 0
+ * @param data2 (char*) Configuration key.
+ */
+#define DC_EVENT_CONFIG_CHANGED                   2112
+
+
+/**
+ * The encrypted key backup on the server was stored or deleted,
+ * see the JSON-RPC methods `backup_key_to_server` and `delete_key_backup_from_server`.
+ *
+ * @param data1 (int) 1 if the backup was stored, 0 if it was deleted.
+ * @param data2 0
+ */
+#define DC_EVENT_KEY_BACKUP_CHANGED               2113
+
+
 /**
  * Webxdc status update received.
  * To get the received status update, use dc_get_webxdc_status_updates() with
@@ -6478,6 +7310,26 @@ void dc_event_unref(dc_event_t* event);
 
 #define DC_EVENT_WEBXDC_REALTIME_ADVERTISEMENT    2151
 
+/**
+ * Realtime data stored while there were no peers has been sent to peers.
+ * Only emitted for data sent with `send_webxdc_realtime_data_stored` JSON-RPC method.
+ *
+ * @param data1 (int) msg_id
+ * @param data2 (int) ID of the data returned when sending it.
+ */
+
+#define DC_EVENT_WEBXDC_REALTIME_DATA_FORWARDED   2152
+
+/**
+ * Realtime data stored while there were no peers has been dropped
+ * because no peer appeared in time or the storage limit was reached.
+ *
+ * @param data1 (int) msg_id
+ * @param data2 (int) ID of the data returned when sending it.
+ */
+
+#define DC_EVENT_WEBXDC_REALTIME_DATA_EXPIRED     2153
+
 /**
  * Tells that the Background fetch was completed (or timed out).
  *
@@ -6604,7 +7456,7 @@ void dc_event_unref(dc_event_t* event);
 
 
 #define DC_EVENT_DATA1_IS_STRING(e)  0    // not used anymore 
-#define DC_EVENT_DATA2_IS_STRING(e)  ((e)==DC_EVENT_CONFIGURE_PROGRESS || (e)==DC_EVENT_IMEX_FILE_WRITTEN || ((e)>=100 && (e)<=499))
+#define DC_EVENT_DATA2_IS_STRING(e)  ((e)==DC_EVENT_CONFIGURE_PROGRESS || (e)==DC_EVENT_CHAT_UI_PROPERTY_CHANGED || (e)==DC_EVENT_IMEX_FILE_WRITTEN || (e)==DC_EVENT_PROXY_SWITCHED || (e)==DC_EVENT_CONFIG_CHANGED || ((e)>=100 && (e)<=499))
 
 
 /*
@@ -6614,6 +7466,23 @@ void dc_event_unref(dc_event_t* event);
 #define DC_MEDIA_QUALITY_WORSE    1
 
 
+/*
+ * Values for dc_get|set_config("subject_mode")
+ */
+#define DC_SUBJECT_MODE_THREAD_STABLE        0
+#define DC_SUBJECT_MODE_FIRST_WORDS          1
+#define DC_SUBJECT_MODE_FIXED                2
+#define DC_SUBJECT_MODE_EMPTY_WHEN_ENCRYPTED 3
+
+
+/*
+ * Values for dc_get|set_config("sent_folder_strategy")
+ */
+#define DC_SENT_FOLDER_STRATEGY_BCC_SELF 0
+#define DC_SENT_FOLDER_STRATEGY_APPEND   1
+#define DC_SENT_FOLDER_STRATEGY_NONE     2
+
+
 /**
  * @defgroup DC_PROVIDER_STATUS DC_PROVIDER_STATUS
  *
@@ -6711,6 +7580,56 @@ void dc_event_unref(dc_event_t* event);
  */
 
 
+/**
+ * @defgroup DC_ERROR_CODE DC_ERROR_CODE
+ *
+ * These constants describe the category of an error.
+ * The error code of the last error is returned by dc_get_last_error_code().
+ *
+ * @addtogroup DC_ERROR_CODE
+ * @{
+ */
+
+/**
+ * The error does not belong to any of the other categories.
+ */
+#define         DC_ERROR_CODE_OTHER            0
+
+/**
+ * The server rejected the login credentials.
+ */
+#define         DC_ERROR_CODE_AUTH_FAILED      1
+
+/**
+ * The storage quota on the server is exceeded.
+ */
+#define         DC_ERROR_CODE_QUOTA_EXCEEDED   2
+
+/**
+ * The TLS certificate of the server is invalid.
+ */
+#define         DC_ERROR_CODE_TLS_INVALID      3
+
+/**
+ * The server refused the request because of rate limiting.
+ */
+#define         DC_ERROR_CODE_RATE_LIMITED     4
+
+/**
+ * The server is unreachable.
+ */
+#define         DC_ERROR_CODE_NETWORK          5
+
+/**
+ * The scanned single-use invite code was already used.
+ */
+#define         DC_ERROR_CODE_SECUREJOIN_CODE_USED 6
+
+/**
+ * @}
+ */
+
+
 /**
   * @defgroup DC_DOWNLOAD DC_DOWNLOAD
   *
@@ -6749,6 +7668,61 @@ void dc_event_unref(dc_event_t* event);
 
 
 
+/**
+ * @}
+ */
+
+
+/**
+ * @defgroup DC_ATTACHMENT_WARNING DC_ATTACHMENT_WARNING
+ *
+ * These constants describe why an attachment may be harmful,
+ * see dc_msg_get_attachment_warning().
+ *
+ * @addtogroup DC_ATTACHMENT_WARNING
+ * @{
+ */
+
+/**
+ * The file extension denotes an executable or a script, e.g. `.exe` or `.js`.
+ */
+#define DC_ATTACHMENT_WARNING_DANGEROUS_EXTENSION   1
+
+/**
+ * A dangerous extension is hidden behind a harmless one, e.g. `invoice.pdf.exe`.
+ */
+#define DC_ATTACHMENT_WARNING_DOUBLE_EXTENSION      2
+
+/**
+ * The content type of the attachment does not match its file extension.
+ */
+#define DC_ATTACHMENT_WARNING_CONTENT_TYPE_MISMATCH 3
+
+
+/**
+ * @}
+ */
+
+
+/**
+ * @defgroup DC_PLURAL DC_PLURAL
+ *
+ * These constants are CLDR plural categories,
+ * see dc_set_stock_translation_plural()
+ * and <https://cldr.unicode.org/index/cldr-spec/plural-rules>.
+ *
+ * @addtogroup DC_PLURAL
+ * @{
+ */
+
+#define DC_PLURAL_ZERO  0
+#define DC_PLURAL_ONE   1
+#define DC_PLURAL_TWO   2
+#define DC_PLURAL_FEW   3
+#define DC_PLURAL_MANY  4
+#define DC_PLURAL_OTHER 5
+
+
 /**
  * @}
  */
@@ -7427,6 +8401,40 @@ void dc_event_unref(dc_event_t* event);
 /// Used when creating text for the "Encryption Info" dialogs.
 #define DC_STR_MESSAGES_ARE_E2EE 242
 
+/// "%1$s changes to the member list."
+///
+/// Used as summary for consecutive group member changes
+/// if `coalesce_member_changes` is enabled.
+/// - %1$s will be replaced by the number of changes.
+///
+/// Supports plural forms, see dc_set_stock_translation_plural().
+#define DC_STR_MEMBER_CHANGES 243
+
+/// "iCloud requires an app-specific password. Create one at https://account.apple.com under "Sign-In and Security" and use it instead of your Apple Account password."
+///
+/// Appended to the error if configuring an iCloud account fails.
+#define DC_STR_APP_PASSWORD_HINT_ICLOUD 244
+
+/// "Microsoft Exchange may reject your regular password. If two-factor authentication is enabled, create an app password at https://account.microsoft.com/security, otherwise ask your administrator to enable IMAP and SMTP authentication."
+///
+/// Appended to the error if configuring a Microsoft Exchange account fails.
+#define DC_STR_APP_PASSWORD_HINT_EXCHANGE 245
+
+/// "Message size %1$s exceeds the limit of %2$s set by the server."
+///
+/// Used as error of messages that are too large to be sent.
+/// - %1$s will be replaced by the size of the message, e.g. "12 MiB"
+/// - %2$s will be replaced by the maximum size announced by the SMTP server
+#define DC_STR_MSG_TOO_LARGE 246
+
+/// "[Incomplete message, %1$s of %2$s parts received]"
+///
+/// Appended to the text of a message split into several parts
+/// as long as not all parts are received.
+/// - %1$s will be replaced by the number of parts received
+/// - %2$s will be replaced by the number of parts
+#define DC_STR_SPLIT_MSG_INCOMPLETE 247
+
 /**
  * @}
  */
@@ -22,7 +22,9 @@
 use std::time::{Duration, SystemTime};
 
 use anyhow::Context as _;
-use deltachat::chat::{ChatId, ChatVisibility, MessageListOptions, MuteDuration};
+use deltachat::chat::{
+    ChatId, ChatVisibility, DaymarkerTimezone, MessageListOptions, MuteDuration,
+};
 use deltachat::constants::DC_MSG_ID_LAST_SPECIAL;
 use deltachat::contact::{Contact, ContactId, Origin};
 use deltachat::context::{Context, ContextBuilder};
@@ -31,7 +33,7 @@
 use deltachat::key::preconfigure_keypair;
 use deltachat::message::MsgId;
 use deltachat::qr_code_generator::{create_qr_svg, generate_backup_qr, get_securejoin_qr_svg};
-use deltachat::stock_str::StockMessage;
+use deltachat::stock_str::{PluralCategory, StockMessage};
 use deltachat::webxdc::StatusUpdateSerial;
 use deltachat::*;
 use deltachat::{accounts::Accounts, log::LogExt};
@@ -318,6 +320,47 @@ fn spawn<T>(fut: T) -> JoinHandle<T::Output>
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_stock_translation_plural(
+    context: *mut dc_context_t,
+    stock_id: u32,
+    category: libc::c_int,
+    stock_msg: *mut libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || stock_msg.is_null() {
+        eprintln!("ignoring careless call to dc_set_stock_translation_plural()");
+        return 0;
+    }
+    let msg = to_string_lossy(stock_msg);
+    let ctx = &*context;
+
+    let id = StockMessage::from_u32(stock_id)
+        .with_context(|| format!("Invalid stock message ID {stock_id}"));
+    let category = PluralCategory::from_i32(category)
+        .with_context(|| format!("Invalid plural category {category}"));
+    match id.and_then(|id| Ok((id, category?))).log_err(ctx) {
+        Ok((id, category)) => ctx
+            .set_stock_translation_plural(id, category, msg)
+            .context("set_stock_translation_plural failed")
+            .log_err(ctx)
+            .is_ok() as libc::c_int,
+        Err(_) => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_stock_language(
+    context: *mut dc_context_t,
+    language: *const libc::c_char,
+) {
+    if context.is_null() || language.is_null() {
+        eprintln!("ignoring careless call to dc_set_stock_language()");
+        return;
+    }
+    let ctx = &*context;
+    ctx.set_stock_language(&to_string_lossy(language));
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_set_config_from_qr(
     context: *mut dc_context_t,
@@ -438,6 +481,16 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_is_quiet_hours(context: *mut dc_context_t) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_is_quiet_hours()");
+        return 0;
+    }
+    let ctx = &*context;
+    ctx.is_quiet_hours() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_start_io(context: *mut dc_context_t) {
     if context.is_null() {
@@ -488,38 +541,52 @@ fn spawn_configure(ctx: Context) {
         EventType::ImapInboxIdle => 106,
         EventType::NewBlobFile(_) => 150,
         EventType::DeletedBlobFile(_) => 151,
+        EventType::BlobCopyProgress { .. } => 152,
         EventType::Warning(_) => 300,
         EventType::Error(_) => 400,
         EventType::ErrorSelfNotInGroup(_) => 410,
         EventType::MsgsChanged { .. } => 2000,
         EventType::ReactionsChanged { .. } => 2001,
         EventType::IncomingReaction { .. } => 2002,
+        EventType::IncomingAbuseReport { .. } => 2004,
         EventType::IncomingWebxdcNotify { .. } => 2003,
         EventType::IncomingMsg { .. } => 2005,
         EventType::IncomingMsgBunch => 2006,
+        EventType::AttachmentWarning { .. } => 2007,
         EventType::MsgsNoticed { .. } => 2008,
         EventType::MsgDelivered { .. } => 2010,
         EventType::MsgFailed { .. } => 2012,
         EventType::MsgRead { .. } => 2015,
         EventType::MsgDeleted { .. } => 2016,
+        EventType::BroadcastDeliveryUpdate { .. } => 2017,
         EventType::MsgReadCountChanged { .. } => 2018,
+        EventType::MsgReminder { .. } => 2019,
         EventType::ChatModified(_) => 2020,
         EventType::ChatEphemeralTimerModified { .. } => 2021,
+        EventType::ChatUiPropertyChanged { .. } => 2022,
         EventType::ChatDeleted { .. } => 2023,
         EventType::ContactsChanged(_) => 2030,
         EventType::LocationChanged(_) => 2035,
         EventType::ConfigureProgress { .. } => 2041,
         EventType::ImexProgress(_) => 2051,
         EventType::ImexFileWritten(_) => 2052,
+        EventType::FetchOlderMsgsProgress(_) => 2055,
+        EventType::HousekeepingProgress(_) => 2056,
         EventType::SecurejoinInviterProgress { .. } => 2060,
         EventType::SecurejoinJoinerProgress { .. } => 2061,
         EventType::ConnectivityChanged => 2100,
+        EventType::ProxySwitched { .. } => 2101,
+        EventType::ClockSkewDetected { .. } => 2102,
         EventType::SelfavatarChanged => 2110,
         EventType::ConfigSynced { .. } => 2111,
+        EventType::ConfigChanged { .. } => 2112,
+        EventType::KeyBackupChanged { .. } => 2113,
         EventType::WebxdcStatusUpdate { .. } => 2120,
         EventType::WebxdcInstanceDeleted { .. } => 2121,
         EventType::WebxdcRealtimeData { .. } => 2150,
         EventType::WebxdcRealtimeAdvertisementReceived { .. } => 2151,
+        EventType::WebxdcRealtimeDataForwarded { .. } => 2152,
+        EventType::WebxdcRealtimeDataExpired { .. } => 2153,
         EventType::AccountsBackgroundFetchDone => 2200,
         EventType::ChatlistChanged => 2300,
         EventType::ChatlistItemChanged { .. } => 2301,
@@ -558,8 +625,10 @@ fn spawn_configure(ctx: Context) {
         | EventType::Warning(_)
         | EventType::Error(_)
         | EventType::ConnectivityChanged
+        | EventType::ProxySwitched { .. }
         | EventType::SelfavatarChanged
         | EventType::ConfigSynced { .. }
+        | EventType::ConfigChanged { .. }
         | EventType::IncomingMsgBunch
         | EventType::ErrorSelfNotInGroup(_)
         | EventType::AccountsBackgroundFetchDone
@@ -568,26 +637,33 @@ fn spawn_configure(ctx: Context) {
         | EventType::AccountsItemChanged
         | EventType::TransportsModified => 0,
         EventType::IncomingReaction { contact_id, .. }
-        | EventType::IncomingWebxdcNotify { contact_id, .. } => contact_id.to_u32() as libc::c_int,
+        | EventType::IncomingWebxdcNotify { contact_id, .. }
+        | EventType::IncomingAbuseReport { contact_id, .. } => contact_id.to_u32() as libc::c_int,
         EventType::MsgsChanged { chat_id, .. }
         | EventType::ReactionsChanged { chat_id, .. }
         | EventType::IncomingMsg { chat_id, .. }
+        | EventType::AttachmentWarning { chat_id, .. }
         | EventType::MsgsNoticed(chat_id)
         | EventType::MsgDelivered { chat_id, .. }
         | EventType::MsgFailed { chat_id, .. }
         | EventType::MsgRead { chat_id, .. }
         | EventType::MsgDeleted { chat_id, .. }
         | EventType::MsgReadCountChanged { chat_id, .. }
+        | EventType::MsgReminder { chat_id, .. }
         | EventType::ChatModified(chat_id)
         | EventType::ChatEphemeralTimerModified { chat_id, .. }
+        | EventType::ChatUiPropertyChanged { chat_id, .. }
         | EventType::ChatDeleted { chat_id } => chat_id.to_u32() as libc::c_int,
         EventType::ContactsChanged(id) | EventType::LocationChanged(id) => {
             let id = id.unwrap_or_default();
             id.to_u32() as libc::c_int
         }
-        EventType::ConfigureProgress { progress, .. } | EventType::ImexProgress(progress) => {
-            *progress as libc::c_int
-        }
+        EventType::BroadcastDeliveryUpdate { msg_id, .. } => msg_id.to_u32() as libc::c_int,
+        EventType::ConfigureProgress { progress, .. }
+        | EventType::BlobCopyProgress { progress, .. }
+        | EventType::ImexProgress(progress)
+        | EventType::FetchOlderMsgsProgress(progress)
+        | EventType::HousekeepingProgress(progress) => *progress as libc::c_int,
         EventType::ImexFileWritten(_) => 0,
         EventType::SecurejoinInviterProgress { contact_id, .. }
         | EventType::SecurejoinJoinerProgress { contact_id, .. } => {
@@ -596,6 +672,8 @@ fn spawn_configure(ctx: Context) {
         EventType::WebxdcRealtimeData { msg_id, .. }
         | EventType::WebxdcStatusUpdate { msg_id, .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { msg_id }
+        | EventType::WebxdcRealtimeDataForwarded { msg_id, .. }
+        | EventType::WebxdcRealtimeDataExpired { msg_id, .. }
         | EventType::WebxdcInstanceDeleted { msg_id, .. }
         | EventType::IncomingCall { msg_id, .. }
         | EventType::IncomingCallAccepted { msg_id, .. }
@@ -605,6 +683,10 @@ fn spawn_configure(ctx: Context) {
             chat_id.unwrap_or_default().to_u32() as libc::c_int
         }
         EventType::EventChannelOverflow { n } => *n as libc::c_int,
+        EventType::ClockSkewDetected { skew } => {
+            (*skew).clamp(libc::c_int::MIN.into(), libc::c_int::MAX.into()) as libc::c_int
+        }
+        EventType::KeyBackupChanged { stored } => *stored as libc::c_int,
         #[allow(unreachable_patterns)]
         #[cfg(test)]
         _ => unreachable!("This is just to silence a rust_analyzer false-positive"),
@@ -630,6 +712,7 @@ fn spawn_configure(ctx: Context) {
         | EventType::ImapInboxIdle
         | EventType::NewBlobFile(_)
         | EventType::DeletedBlobFile(_)
+        | EventType::BlobCopyProgress { .. }
         | EventType::Warning(_)
         | EventType::Error(_)
         | EventType::ErrorSelfNotInGroup(_)
@@ -637,9 +720,14 @@ fn spawn_configure(ctx: Context) {
         | EventType::LocationChanged(_)
         | EventType::ConfigureProgress { .. }
         | EventType::ImexProgress(_)
+        | EventType::FetchOlderMsgsProgress(_)
+        | EventType::HousekeepingProgress(_)
         | EventType::ImexFileWritten(_)
         | EventType::MsgsNoticed(_)
         | EventType::ConnectivityChanged
+        | EventType::ProxySwitched { .. }
+        | EventType::ClockSkewDetected { .. }
+        | EventType::KeyBackupChanged { .. }
         | EventType::WebxdcInstanceDeleted { .. }
         | EventType::IncomingMsgBunch
         | EventType::SelfavatarChanged
@@ -649,7 +737,9 @@ fn spawn_configure(ctx: Context) {
         | EventType::AccountsChanged
         | EventType::AccountsItemChanged
         | EventType::ConfigSynced { .. }
+        | EventType::ConfigChanged { .. }
         | EventType::ChatModified(_)
+        | EventType::ChatUiPropertyChanged { .. }
         | EventType::ChatDeleted { .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { .. }
         | EventType::OutgoingCallAccepted { .. }
@@ -660,12 +750,16 @@ fn spawn_configure(ctx: Context) {
         | EventType::ReactionsChanged { msg_id, .. }
         | EventType::IncomingReaction { msg_id, .. }
         | EventType::IncomingWebxdcNotify { msg_id, .. }
+        | EventType::IncomingAbuseReport { msg_id, .. }
         | EventType::IncomingMsg { msg_id, .. }
+        | EventType::AttachmentWarning { msg_id, .. }
         | EventType::MsgDelivered { msg_id, .. }
         | EventType::MsgFailed { msg_id, .. }
         | EventType::MsgRead { msg_id, .. }
         | EventType::MsgDeleted { msg_id, .. }
-        | EventType::MsgReadCountChanged { msg_id, .. } => msg_id.to_u32() as libc::c_int,
+        | EventType::MsgReadCountChanged { msg_id, .. }
+        | EventType::MsgReminder { msg_id, .. } => msg_id.to_u32() as libc::c_int,
+        EventType::BroadcastDeliveryUpdate { read, .. } => *read as libc::c_int,
         EventType::SecurejoinInviterProgress { progress, .. }
         | EventType::SecurejoinJoinerProgress { progress, .. } => *progress as libc::c_int,
         EventType::ChatEphemeralTimerModified { timer, .. } => timer.to_u32() as libc::c_int,
@@ -674,6 +768,8 @@ fn spawn_configure(ctx: Context) {
             ..
         } => status_update_serial.to_u32() as libc::c_int,
         EventType::WebxdcRealtimeData { data, .. } => data.len() as libc::c_int,
+        EventType::WebxdcRealtimeDataForwarded { data_id, .. }
+        | EventType::WebxdcRealtimeDataExpired { data_id, .. } => *data_id as libc::c_int,
         EventType::IncomingCall { has_video, .. } => *has_video as libc::c_int,
         EventType::IncomingCallAccepted {
             from_this_device, ..
@@ -726,13 +822,15 @@ fn spawn_configure(ctx: Context) {
         | EventType::DeletedBlobFile(msg)
         | EventType::Warning(msg)
         | EventType::Error(msg)
-        | EventType::ErrorSelfNotInGroup(msg) => {
+        | EventType::ErrorSelfNotInGroup(msg)
+        | EventType::BlobCopyProgress { path: msg, .. } => {
             let data2 = msg.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
         EventType::MsgsChanged { .. }
         | EventType::ReactionsChanged { .. }
         | EventType::IncomingMsg { .. }
+        | EventType::AttachmentWarning { .. }
         | EventType::ImapInboxIdle
         | EventType::MsgsNoticed(_)
         | EventType::MsgDelivered { .. }
@@ -740,13 +838,20 @@ fn spawn_configure(ctx: Context) {
         | EventType::MsgRead { .. }
         | EventType::MsgDeleted { .. }
         | EventType::MsgReadCountChanged { .. }
+        | EventType::MsgReminder { .. }
+        | EventType::BroadcastDeliveryUpdate { .. }
+        | EventType::IncomingAbuseReport { .. }
         | EventType::ChatModified(_)
         | EventType::ContactsChanged(_)
         | EventType::LocationChanged(_)
         | EventType::ImexProgress(_)
+        | EventType::FetchOlderMsgsProgress(_)
+        | EventType::HousekeepingProgress(_)
         | EventType::SecurejoinInviterProgress { .. }
         | EventType::SecurejoinJoinerProgress { .. }
         | EventType::ConnectivityChanged
+        | EventType::ClockSkewDetected { .. }
+        | EventType::KeyBackupChanged { .. }
         | EventType::SelfavatarChanged
         | EventType::WebxdcStatusUpdate { .. }
         | EventType::WebxdcInstanceDeleted { .. }
@@ -760,6 +865,8 @@ fn spawn_configure(ctx: Context) {
         | EventType::AccountsItemChanged
         | EventType::IncomingCallAccepted { .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { .. }
+        | EventType::WebxdcRealtimeDataForwarded { .. }
+        | EventType::WebxdcRealtimeDataExpired { .. }
         | EventType::TransportsModified => ptr::null_mut(),
         EventType::IncomingCall {
             place_call_info, ..
@@ -785,10 +892,18 @@ fn spawn_configure(ctx: Context) {
             let data2 = file.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
-        EventType::ConfigSynced { key } => {
+        EventType::ProxySwitched { url } => {
+            let data2 = url.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
+        EventType::ConfigSynced { key } | EventType::ConfigChanged { key } => {
             let data2 = key.to_string().to_c_string().unwrap_or_default();
             data2.into_raw()
         }
+        EventType::ChatUiPropertyChanged { key, .. } => {
+            let data2 = key.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
         EventType::WebxdcRealtimeData { data, .. } => {
             let ptr = libc::malloc(data.len());
             libc::memcpy(ptr, data.as_ptr() as *mut libc::c_void, data.len());
@@ -832,6 +947,20 @@ fn spawn_configure(ctx: Context) {
     Box::into_raw(Box::new(ctx.get_event_emitter()))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_event_emitter_with_replay(
+    context: *mut dc_context_t,
+    n: libc::c_int,
+) -> *mut dc_event_emitter_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_event_emitter_with_replay()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+    let n = usize::try_from(n).unwrap_or_default();
+    Box::into_raw(Box::new(ctx.get_event_emitter_with_replay(n)))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_event_emitter_unref(emitter: *mut dc_event_emitter_t) {
     if emitter.is_null() {
@@ -1302,6 +1431,35 @@ fn spawn_configure(ctx: Context) {
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_draft_history(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    index: libc::c_int,
+) -> *mut dc_msg_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_draft_history()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    let draft = block_on(ChatId::new(chat_id).get_draft_history(ctx))
+        .with_context(|| format!("Failed to get draft history for chat #{chat_id}"))
+        .unwrap_or_default()
+        .into_iter()
+        .nth(usize::try_from(index).unwrap_or(usize::MAX));
+    match draft {
+        Some(draft) => {
+            let ffi_msg = MessageWrapper {
+                context,
+                message: draft,
+            };
+            Box::into_raw(Box::new(ffi_msg))
+        }
+        None => ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_chat_msgs(
     context: *mut dc_context_t,
@@ -1321,7 +1479,10 @@ fn spawn_configure(ctx: Context) {
             chat::get_chat_msgs_ex(
                 ctx,
                 ChatId::new(chat_id),
-                MessageListOptions { add_daymarker },
+                MessageListOptions {
+                    add_daymarker,
+                    daymarker_timezone: DaymarkerTimezone::Local,
+                },
             )
             .await
             .unwrap_or_log_default(ctx, "failed to get chat msgs")
@@ -1330,6 +1491,34 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_msgs_around(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    before: libc::c_int,
+    after: libc::c_int,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_msgs_around()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        Box::into_raw(Box::new(
+            chat::get_msgs_around(
+                ctx,
+                MsgId::new(msg_id),
+                usize::try_from(before).unwrap_or_default(),
+                usize::try_from(after).unwrap_or_default(),
+            )
+            .await
+            .unwrap_or_log_default(ctx, "failed to get msgs around")
+            .into(),
+        ))
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_msg_cnt(context: *mut dc_context_t, chat_id: u32) -> libc::c_int {
     if context.is_null() {
@@ -1365,6 +1554,27 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_first_unread_msg_id(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> u32 {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_first_unread_msg_id()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        ChatId::new(chat_id)
+            .get_first_unread_msg(ctx)
+            .await
+            .unwrap_or_log_default(ctx, "failed to get first unread msg")
+            .map(|msg_id| msg_id.to_u32())
+            .unwrap_or_default()
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_similar_chatlist(
     context: *mut dc_context_t,
@@ -1493,6 +1703,24 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_markunread_chat(context: *mut dc_context_t, chat_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_markunread_chat()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        ChatId::new(chat_id)
+            .mark_unread(ctx)
+            .await
+            .context("Failed markunread chat")
+            .log_err(ctx)
+            .unwrap_or(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_markfresh_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1619,6 +1847,51 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+fn requests_filter(min_age: i64, max_age: i64) -> chat::RequestsFilter {
+    chat::RequestsFilter {
+        min_age: (min_age > 0).then_some(min_age),
+        max_age: (max_age > 0).then_some(max_age),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accept_all_requests(
+    context: *mut dc_context_t,
+    min_age: i64,
+    max_age: i64,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_accept_all_requests()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        ctx.accept_all_requests(requests_filter(min_age, max_age))
+            .await
+            .unwrap_or_log_default(ctx, "Failed to accept all requests") as libc::c_int
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_block_all_requests(
+    context: *mut dc_context_t,
+    min_age: i64,
+    max_age: i64,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_block_all_requests()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        ctx.block_all_requests(requests_filter(min_age, max_age))
+            .await
+            .unwrap_or_log_default(ctx, "Failed to block all requests") as libc::c_int
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accept_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1882,6 +2155,96 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_chat_ui_property(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    key: *const libc::c_char,
+    value: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || key.is_null() {
+        eprintln!("ignoring careless call to dc_set_chat_ui_property()");
+        return 0;
+    }
+    let ctx = &*context;
+    let key = to_string_lossy(key);
+    let value = to_opt_string_lossy(value);
+
+    block_on(async move {
+        ChatId::new(chat_id)
+            .set_ui_property(ctx, &key, value.as_deref())
+            .await
+            .with_context(|| format!("Can't set chat UI property {key} to {value:?}"))
+            .log_err(ctx)
+            .is_ok() as libc::c_int
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_chat_ui_property(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    key: *const libc::c_char,
+) -> *mut libc::c_char {
+    if context.is_null() || key.is_null() {
+        eprintln!("ignoring careless call to dc_get_chat_ui_property()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    let key = to_string_lossy(key);
+
+    block_on(ChatId::new(chat_id).get_ui_property(ctx, &key))
+        .context("Can't get chat UI property")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .unwrap_or_default()
+        .strdup()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_chat_wallpaper(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    image: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_chat_wallpaper()");
+        return 0;
+    }
+    let ctx = &*context;
+    let image = to_opt_string_lossy(image);
+
+    block_on(async move {
+        ChatId::new(chat_id)
+            .set_wallpaper(ctx, image.as_deref().map(std::path::Path::new))
+            .await
+            .context("Can't set chat wallpaper")
+            .log_err(ctx)
+            .is_ok() as libc::c_int
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_chat_wallpaper(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_chat_wallpaper()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    match block_on(ChatId::new(chat_id).get_wallpaper(ctx))
+        .context("Can't get chat wallpaper")
+        .log_err(ctx)
+        .unwrap_or_default()
+    {
+        Some(path) => path.to_string_lossy().strdup(),
+        None => ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_chat_encrinfo(
     context: *mut dc_context_t,
@@ -1976,6 +2339,23 @@ fn from_prim<S, T>(s: S) -> Option<T>
         .strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_msg_raw_mime(
+    context: *mut dc_context_t,
+    msg_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_msg_raw_mime()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(MsgId::new(msg_id).get_raw_mime(ctx))
+        .unwrap_or_log_default(ctx, "Failed get_msg_raw_mime")
+        .map(|mime| String::from_utf8_lossy(&mime).into_owned())
+        .strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_delete_msgs(
     context: *mut dc_context_t,
@@ -2040,6 +2420,62 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_msg_reminder(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    timestamp: i64,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_msg_reminder()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        MsgId::new(msg_id)
+            .set_reminder(ctx, timestamp)
+            .await
+            .map(|_| 1)
+            .unwrap_or_log_default(ctx, "Failed to set message reminder")
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_report_msg_to_owner(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    reason: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || reason.is_null() {
+        eprintln!("ignoring careless call to dc_report_msg_to_owner()");
+        return 0;
+    }
+    let ctx = &*context;
+    let reason = to_string_lossy(reason);
+
+    block_on(async move {
+        MsgId::new(msg_id)
+            .report_to_owner(ctx, &reason)
+            .await
+            .map(|_| 1)
+            .unwrap_or_log_default(ctx, "Failed to report message")
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_msg_reminder(context: *mut dc_context_t, msg_id: u32) -> i64 {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_msg_reminder()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(MsgId::new(msg_id).get_reminder(ctx))
+        .unwrap_or_log_default(ctx, "Failed to get message reminder")
+        .unwrap_or_default()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_resend_msgs(
     context: *mut dc_context_t,
@@ -2117,6 +2553,19 @@ fn from_prim<S, T>(s: S) -> Option<T>
         .ok();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_redownload_msg(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_redownload_msg()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(MsgId::new(msg_id).redownload(ctx))
+        .context("Failed to redownload message.")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_may_be_valid_addr(addr: *const libc::c_char) -> libc::c_int {
     if addr.is_null() {
@@ -2275,6 +2724,90 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_export_blocked_contacts(
+    context: *mut dc_context_t,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_export_blocked_contacts()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+
+    block_on(Contact::export_blocked(ctx))
+        .context("Can't export blocked contacts")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .strdup()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_import_blocked_contacts(
+    context: *mut dc_context_t,
+    json: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || json.is_null() {
+        eprintln!("ignoring careless call to dc_import_blocked_contacts()");
+        return -1;
+    }
+    let ctx = &*context;
+
+    block_on(Contact::import_blocked(ctx, &to_string_lossy(json)))
+        .context("Can't import blocked contacts")
+        .log_err(ctx)
+        .map_or(-1, |cnt| cnt as libc::c_int)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_block_domain(
+    context: *mut dc_context_t,
+    domain: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || domain.is_null() {
+        eprintln!("ignoring careless call to dc_block_domain()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.block_domain(&to_string_lossy(domain)))
+        .context("Can't block domain")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_unblock_domain(
+    context: *mut dc_context_t,
+    domain: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || domain.is_null() {
+        eprintln!("ignoring careless call to dc_unblock_domain()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.unblock_domain(&to_string_lossy(domain)))
+        .context("Can't unblock domain")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_blocked_domains(context: *mut dc_context_t) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_blocked_domains()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+
+    block_on(ctx.get_blocked_domains())
+        .context("Can't get blocked domains")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .join("\n")
+        .strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_block_contact(
     context: *mut dc_context_t,
@@ -2621,6 +3154,16 @@ fn spawn_imex(ctx: Context, what: imex::ImexMode, param1: String, passphrase: Op
     ctx.get_last_error().strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_last_error_code(context: *mut dc_context_t) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_last_error_code()");
+        return 0;
+    }
+    let ctx = &*context;
+    ctx.get_last_error_code() as libc::c_int
+}
+
 // dc_array_t
 
 pub type dc_array_t = dc_array::dc_array_t;
@@ -3322,6 +3865,19 @@ pub struct MessageWrapper {
     ffi_msg.message.download_state() as libc::c_int
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_attachment_warning(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_attachment_warning()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg
+        .message
+        .get_attachment_warning()
+        .map_or(0, |warning| warning as libc::c_int)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_get_timestamp(msg: *mut dc_msg_t) -> i64 {
     if msg.is_null() {
@@ -3352,6 +3908,16 @@ pub struct MessageWrapper {
     ffi_msg.message.get_sort_timestamp()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_sort_key(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_sort_key()");
+        return "".strdup();
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.get_sort_key().strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_get_text(msg: *mut dc_msg_t) -> *mut libc::c_char {
     if msg.is_null() {
@@ -3633,6 +4199,16 @@ pub struct MessageWrapper {
     ffi_msg.message.get_override_sender_name().strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_language(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_language()");
+        return "".strdup();
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.get_language().strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_has_deviating_timestamp(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3683,6 +4259,26 @@ pub struct MessageWrapper {
     ffi_msg.message.is_edited().into()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_removed_from_server(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_removed_from_server()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_removed_from_server().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_from_shared_history(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_from_shared_history()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_from_shared_history().into()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_is_info(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3768,6 +4364,56 @@ pub struct MessageWrapper {
     ffi_msg.message.set_subject(to_string_lossy(subject));
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_alt_text(msg: *mut dc_msg_t, text: *const libc::c_char) {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_alt_text()");
+        return;
+    }
+    let ffi_msg = &mut *msg;
+    ffi_msg.message.set_alt_text(to_opt_string_lossy(text))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_alt_text(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_alt_text()");
+        return "".strdup();
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.get_alt_text().strdup()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_extra_header(
+    msg: *mut dc_msg_t,
+    name: *const libc::c_char,
+    value: *const libc::c_char,
+) -> libc::c_int {
+    if msg.is_null() || name.is_null() || value.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_extra_header()");
+        return 0;
+    }
+    let ffi_msg = &mut *msg;
+    let ctx = &*ffi_msg.context;
+    ffi_msg
+        .message
+        .set_extra_header(&to_string_lossy(name), &to_string_lossy(value))
+        .context("Failed to set extra header")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_bot(msg: *mut dc_msg_t, bot: libc::c_int) {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_bot()");
+        return;
+    }
+    let ffi_msg = &mut *msg;
+    ffi_msg.message.set_bot(bot != 0)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_set_override_sender_name(
     msg: *mut dc_msg_t,
@@ -5028,6 +5674,67 @@ fn convert_and_prune_message_ids(msg_ids: *const u32, msg_cnt: libc::c_int) -> V
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_set_push_device_token_for(
+    accounts: *const dc_accounts_t,
+    account_id: u32,
+    token: *const libc::c_char,
+) {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_set_push_device_token_for()");
+        return;
+    }
+
+    let accounts = &*accounts;
+    let token = to_opt_string_lossy(token);
+
+    block_on(async move {
+        let accounts = accounts.read().await;
+        if let Err(err) = accounts
+            .set_push_device_token_for(account_id, token.as_deref())
+            .await
+        {
+            accounts.emit_event(EventType::Error(format!(
+                "Failed to set notify token for account {account_id}: {err:#}."
+            )));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_handle_unified_push(
+    accounts: *const dc_accounts_t,
+    account_id: u32,
+    message: *const u8,
+    message_bytes: libc::size_t,
+    timeout_in_seconds: u64,
+) -> libc::c_int {
+    if accounts.is_null() || message.is_null() || timeout_in_seconds <= 2 {
+        eprintln!("ignoring careless call to dc_accounts_handle_unified_push()");
+        return 0;
+    }
+
+    let accounts = &*accounts;
+    let message = std::slice::from_raw_parts(message, message_bytes);
+    let background_fetch_future = block_on(async move {
+        let accounts = accounts.read().await;
+        accounts
+            .handle_unified_push(account_id, message, Duration::from_secs(timeout_in_seconds))
+            .await
+            .map_err(|err| {
+                accounts.emit_event(EventType::Error(format!(
+                    "Failed to handle UnifiedPush message: {err:#}."
+                )));
+            })
+    });
+    // At this point account manager is not locked anymore.
+    let Ok(background_fetch_future) = background_fetch_future else {
+        return 0;
+    };
+    block_on(background_fetch_future);
+    1
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accounts_get_event_emitter(
     accounts: *const dc_accounts_t,
@@ -5043,6 +5750,23 @@ fn convert_and_prune_message_ids(msg_ids: *const u32, msg_cnt: libc::c_int) -> V
     Box::into_raw(Box::new(emitter))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_get_event_emitter_with_replay(
+    accounts: *const dc_accounts_t,
+    n: libc::c_int,
+) -> *mut dc_event_emitter_t {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_get_event_emitter_with_replay()");
+        return ptr::null_mut();
+    }
+
+    let accounts = &*accounts;
+    let n = usize::try_from(n).unwrap_or_default();
+    let emitter = block_on(accounts.read()).get_event_emitter_with_replay(n);
+
+    Box::into_raw(Box::new(emitter))
+}
+
 pub struct dc_jsonrpc_instance_t {
     receiver: OutReceiver,
     handle: RpcSession<CommandApi>,
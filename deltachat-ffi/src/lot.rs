@@ -234,6 +234,7 @@ pub enum LotState {
     MsgOutPending = 20,
     MsgOutFailed = 24,
     MsgOutDelivered = 26,
+    MsgOutServerRcvd = 27,
     MsgOutMdnRcvd = 28,
 }
 
@@ -249,6 +250,7 @@ fn from(s: MessageState) -> Self {
             OutPending => LotState::MsgOutPending,
             OutFailed => LotState::MsgOutFailed,
             OutDelivered => LotState::MsgOutDelivered,
+            OutServerRcvd => LotState::MsgOutServerRcvd,
             OutMdnRcvd => LotState::MsgOutMdnRcvd,
         }
     }
@@ -12,7 +12,8 @@
 use deltachat::chat::{
     self, add_contact_to_chat, forward_msgs, forward_msgs_2ctx, get_chat_media, get_chat_msgs,
     get_chat_msgs_ex, markfresh_chat, marknoticed_all_chats, marknoticed_chat,
-    remove_contact_from_chat, Chat, ChatId, ChatItem, MessageListOptions,
+    remove_contact_from_chat, Chat, ChatId, ChatItem, DaymarkerTimezone, MessageListOptions,
+    RequestsFilter,
 };
 use deltachat::chatlist::Chatlist;
 use deltachat::config::{get_all_ui_config_keys, Config};
@@ -28,14 +29,16 @@
 };
 use deltachat::peer_channels::{
     leave_webxdc_realtime, send_webxdc_realtime_advertisement, send_webxdc_realtime_data,
+    send_webxdc_realtime_data_stored,
 };
 use deltachat::provider::get_provider_info;
 use deltachat::qr::{self, Qr};
 use deltachat::qr_code_generator::{create_qr_svg, generate_backup_qr, get_securejoin_qr_svg};
 use deltachat::reaction::{get_msg_reactions, send_reaction};
 use deltachat::securejoin;
-use deltachat::stock_str::StockMessage;
+use deltachat::stock_str::{PluralCategory, StockMessage};
 use deltachat::storage_usage::{get_blobdir_storage_usage, get_storage_usage};
+use deltachat::transcript::TranscriptFormat;
 use deltachat::webxdc::StatusUpdateSerial;
 use deltachat::EventEmitter;
 use sanitize_filename::is_sanitized;
@@ -50,10 +53,16 @@
 use types::account::Account;
 use types::calls::JsonrpcCallInfo;
 use types::chat::FullChat;
+use types::connectivity::ConnectionConnectivity;
 use types::contact::{ContactObject, VcardContact};
-use types::events::Event;
+use types::events::{Event, JsonrpcErrorCode};
+use types::filters::{FilterAction, FilterField, MsgFilter};
 use types::http::HttpResponse;
-use types::message::{MessageData, MessageObject, MessageReadReceipt};
+use types::message::{
+    AbuseReport, MessageArchiveEntry, MessageData, MessageObject, MessageReadReceipt,
+};
+use types::network_profile::JsonrpcNetworkProfile;
+use types::notification::{NotificationItem, RenderedNotification};
 use types::notify_state::JsonrpcNotifyState;
 use types::provider_info::ProviderInfo;
 use types::reactions::JsonrpcReactions;
@@ -61,7 +70,10 @@
 
 use self::types::message::{MessageInfo, MessageLoadResult};
 use self::types::{
-    chat::{BasicChat, JsonrpcChatVisibility, MuteDuration},
+    chat::{
+        BasicChat, JsonrpcChatStatistics, JsonrpcChatVisibility, JsonrpcEncryptionPreference,
+        JsonrpcForeignChatFormat, MuteDuration,
+    },
     location::JsonrpcLocation,
     message::{
         JsonrpcMessageListItem, MessageNotificationInfo, MessageSearchResult, MessageViewtype,
@@ -233,6 +245,22 @@ async fn remove_account(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Wipes the account's keys, database and blobs and removes the account,
+    /// e.g. when a duress PIN is entered.
+    ///
+    /// Files are overwritten and removed,
+    /// with no guarantee that the data can't be recovered from SSDs or flash storage.
+    async fn secure_wipe_account(&self, account_id: u32) -> Result<()> {
+        let res = self
+            .accounts
+            .write()
+            .await
+            .secure_wipe_account(account_id)
+            .await;
+        self.states.lock().await.remove(&account_id);
+        res
+    }
+
     async fn get_all_account_ids(&self) -> Vec<u32> {
         self.accounts.read().await.get_all()
     }
@@ -300,6 +328,38 @@ async fn stop_background_fetch(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Handles a message received from the UnifiedPush distributor
+    /// for the endpoint set with `unified_push_endpoint` config of the given account.
+    ///
+    /// `message` is the raw message encoded as base64.
+    /// The message is decrypted to make sure it was sent by the chatmail server,
+    /// then background fetch is performed for this account only.
+    ///
+    /// The `AccountsBackgroundFetchDone` event is emitted at the end
+    /// unless the message could not be decrypted.
+    async fn handle_unified_push(
+        &self,
+        account_id: u32,
+        message: String,
+        timeout_in_seconds: f64,
+    ) -> Result<()> {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let message = general_purpose::STANDARD.decode(message)?;
+        let future = {
+            let lock = self.accounts.read().await;
+            lock.handle_unified_push(
+                account_id,
+                &message,
+                std::time::Duration::from_secs_f64(timeout_in_seconds),
+            )
+            .await?
+        };
+        // At this point account manager is not locked anymore.
+        future.await;
+        Ok(())
+    }
+
     // ---------------------------------------------
     // Methods that work on individual accounts
     // ---------------------------------------------
@@ -318,6 +378,31 @@ async fn stop_io(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Fetches messages not older than `days` days from `folder`
+    /// which existed on the server before the folder was selected for the first time.
+    ///
+    /// The progress is reported with `FetchOlderMsgsProgress` events.
+    /// The messages are downloaded in the background afterwards.
+    /// Returns the number of messages scheduled for download.
+    async fn fetch_older_messages(
+        &self,
+        account_id: u32,
+        folder: String,
+        days: u32,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.fetch_older_messages(&folder, days).await
+    }
+
+    /// Runs housekeeping now instead of waiting for it to be scheduled.
+    ///
+    /// Housekeeping removes unused files, prunes old data and optimizes the database.
+    /// The progress is reported with `HousekeepingProgress` events.
+    async fn run_housekeeping_now(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.run_housekeeping_now().await
+    }
+
     /// Get top-level info for an account.
     async fn get_account_info(&self, account_id: u32) -> Result<Account> {
         let context_option = self.accounts.read().await.get_account(account_id);
@@ -366,6 +451,16 @@ async fn is_configured(&self, account_id: u32) -> Result<bool> {
         ctx.is_configured().await
     }
 
+    /// Checks if the current time is within the quiet hours
+    /// configured with the `quiet_hours` option.
+    ///
+    /// [`types::events::EventType::IncomingMsg`] events are still emitted during quiet hours,
+    /// but no notification should be shown for them.
+    async fn is_quiet_hours(&self, account_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.is_quiet_hours())
+    }
+
     /// Get system info for an account.
     async fn get_info(&self, account_id: u32) -> Result<BTreeMap<&'static str, String>> {
         let ctx = self.get_context(account_id).await?;
@@ -379,6 +474,33 @@ async fn get_storage_usage_report_string(&self, account_id: u32) -> Result<Strin
         Ok(storage_usage.to_string())
     }
 
+    /// Get security report as formatted string
+    async fn get_security_report_string(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let security_report = ctx.get_security_report().await?;
+        Ok(security_report.to_string())
+    }
+
+    /// Checks the account with live connections to the servers
+    /// and returns the report as formatted string.
+    ///
+    /// Useful to find out why sending or receiving messages does not work.
+    async fn run_diagnostics_string(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let report = ctx.run_diagnostics().await?;
+        Ok(report.to_string())
+    }
+
+    /// Get the usage statistics as a JSON string.
+    ///
+    /// These are the statistics that are sent to the developers
+    /// if the `stats_sending` option is enabled,
+    /// getting them does not send anything.
+    async fn get_usage_stats(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.get_usage_stats().await
+    }
+
     /// Get the blob dir.
     async fn get_blob_dir(&self, account_id: u32) -> Result<Option<String>> {
         let ctx = self.get_context(account_id).await?;
@@ -469,6 +591,48 @@ async fn get_all_ui_config_keys(&self, account_id: u32) -> Result<Vec<String>> {
         get_all_ui_config_keys(&ctx).await
     }
 
+    /// Writes the non-secret settings and all `ui.*` keys to `path` as a JSON object.
+    async fn export_settings(&self, account_id: u32, path: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.export_settings(path.as_ref()).await
+    }
+
+    /// Applies settings written by `export_settings()`.
+    async fn import_settings(&self, account_id: u32, path: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.import_settings(path.as_ref()).await
+    }
+
+    /// Applies settings from a provisioning file and locks them against changes.
+    async fn import_managed_settings(&self, account_id: u32, path: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.import_managed_settings(path.as_ref()).await
+    }
+
+    /// Returns true if the config key is managed and cannot be changed.
+    async fn is_config_managed(&self, account_id: u32, key: String) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        let key = Config::from_str(&key).with_context(|| format!("unknown key {key:?}"))?;
+        ctx.is_config_managed(key).await
+    }
+
+    /// Sets the app-lock PIN, `null` removes it.
+    ///
+    /// The PIN is stored as an Argon2 hash.
+    async fn set_applock_pin(&self, account_id: u32, pin: Option<String>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.set_applock_pin(pin.as_deref()).await
+    }
+
+    /// Checks the app-lock PIN.
+    ///
+    /// Returns false for a wrong PIN or if no PIN is set.
+    /// Fails after too many wrong PINs until an increasing delay has passed.
+    async fn verify_applock_pin(&self, account_id: u32, pin: String) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.verify_applock_pin(&pin).await
+    }
+
     async fn set_stock_strings(&self, strings: HashMap<u32, String>) -> Result<()> {
         let accounts = self.accounts.read().await;
         for (stock_id, stock_message) in strings {
@@ -479,6 +643,32 @@ async fn set_stock_strings(&self, strings: HashMap<u32, String>) -> Result<()> {
         Ok(())
     }
 
+    /// Sets the language of the stock strings as ISO 639-1 code, e.g. `pl`,
+    /// and the plural forms of stock strings containing a number.
+    ///
+    /// `strings` maps stock string IDs to the translations
+    /// for the CLDR plural categories `zero`, `one`, `two`, `few`, `many` and `other`.
+    /// If no translation is set for a category, the translation for `other` is used,
+    /// then the translation set by `set_stock_strings()`.
+    async fn set_stock_plural_strings(
+        &self,
+        language: String,
+        strings: HashMap<u32, HashMap<String, String>>,
+    ) -> Result<()> {
+        let accounts = self.accounts.read().await;
+        accounts.set_stock_language(&language);
+        for (stock_id, plurals) in strings {
+            if let Some(stock_id) = StockMessage::from_u32(stock_id) {
+                for (category, stock_message) in plurals {
+                    let category = PluralCategory::from_str(&category)
+                        .with_context(|| format!("Invalid plural category {category:?}"))?;
+                    accounts.set_stock_translation_plural(stock_id, category, stock_message)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Configures this account with the currently set parameters.
     /// Setup the credential config before calling this.
     ///
@@ -662,6 +852,28 @@ async fn import_self_keys(
         .await
     }
 
+    /// Encrypts the self key with the passphrase and stores it on the server.
+    ///
+    /// Storing the key on the server is opt-in,
+    /// use `delete_key_backup_from_server` to remove it again.
+    async fn backup_key_to_server(&self, account_id: u32, passphrase: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.backup_key_to_server(&passphrase).await
+    }
+
+    /// Restores the self key from the backup on the server
+    /// and sets it as the default key.
+    async fn restore_key_from_server(&self, account_id: u32, passphrase: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.restore_key_from_server(&passphrase).await
+    }
+
+    /// Removes the key backup from the server.
+    async fn delete_key_backup_from_server(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.delete_key_backup_from_server().await
+    }
+
     /// Returns the message IDs of all _fresh_ messages of any chat.
     /// Typically used for implementing notification summaries
     /// or badge counters e.g. on the app icon.
@@ -683,6 +895,50 @@ async fn get_fresh_msgs(&self, account_id: u32) -> Result<Vec<u32>> {
             .collect())
     }
 
+    /// Returns the items to show as OS notifications,
+    /// added after the item with the token `since_token`.
+    ///
+    /// Pass 0 to get all items.
+    /// Pass the token of the last returned item on the next call
+    /// to get only new items.
+    ///
+    /// Meant for notification extensions,
+    /// which otherwise would have to combine `get_fresh_msgs()`
+    /// with several per-message lookups.
+    async fn get_notification_items(
+        &self,
+        account_id: u32,
+        since_token: u32,
+    ) -> Result<Vec<NotificationItem>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_notification_items(since_token)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Fetches the message with the given UID from the server
+    /// and renders a notification for it
+    /// without adding the message to the database.
+    ///
+    /// Meant for notification extensions
+    /// running while the main process may be receiving the same message.
+    /// Returns `null` if the message does not exist or should not be notified about.
+    async fn render_notification_for_uid(
+        &self,
+        account_id: u32,
+        folder: String,
+        uid: u32,
+    ) -> Result<Option<RenderedNotification>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .render_notification_for_uid(&folder, uid)
+            .await?
+            .map(Into::into))
+    }
+
     /// Get the number of _fresh_ messages in a chat.
     /// Typically used to implement a badge with a number in the chatlist.
     ///
@@ -694,6 +950,22 @@ async fn get_fresh_msg_cnt(&self, account_id: u32, chat_id: u32) -> Result<usize
         ChatId::new(chat_id).get_fresh_msg_cnt(&ctx).await
     }
 
+    /// Returns the ID of the first unread message in a chat
+    /// before which the "unread messages" divider should be shown,
+    /// or `null` if there are no unread messages.
+    ///
+    /// The read marker is updated by `marknoticed_chat()` and `markseen_msgs()`
+    /// and synchronized across own devices.
+    async fn get_first_unread_message_id(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Option<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = ChatId::new(chat_id).get_first_unread_msg(&ctx).await?;
+        Ok(msg_id.map(|msg_id| msg_id.to_u32()))
+    }
+
     /// (deprecated) Gets messages to be processed by the bot and returns their IDs.
     ///
     /// Only messages with database ID higher than `last_msg_id` config value
@@ -864,6 +1136,38 @@ async fn block_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         ChatId::new(chat_id).block(&ctx).await
     }
 
+    /// Accepts all contact requests.
+    ///
+    /// If `min_age` or `max_age` is set, only requests
+    /// whose last message is at least or at most that many seconds old are accepted.
+    /// Returns the number of accepted chats.
+    async fn accept_all_requests(
+        &self,
+        account_id: u32,
+        min_age: Option<i64>,
+        max_age: Option<i64>,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.accept_all_requests(RequestsFilter { min_age, max_age })
+            .await
+    }
+
+    /// Blocks all contact requests, see `block_chat()`.
+    ///
+    /// If `min_age` or `max_age` is set, only requests
+    /// whose last message is at least or at most that many seconds old are blocked.
+    /// Returns the number of blocked chats.
+    async fn block_all_requests(
+        &self,
+        account_id: u32,
+        min_age: Option<i64>,
+        max_age: Option<i64>,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.block_all_requests(RequestsFilter { min_age, max_age })
+            .await
+    }
+
     /// Delete a chat.
     ///
     /// Messages are deleted from the device and the chat database entry is deleted.
@@ -912,6 +1216,15 @@ async fn get_chat_securejoin_qr_code(
         Ok(qr)
     }
 
+    /// Get QR code text that will offer a setup-contact invitation usable only once.
+    ///
+    /// After the first successful setup-contact, later attempts to use it are rejected.
+    /// The QR code is only valid on this device.
+    async fn get_single_use_securejoin_qr_code(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        securejoin::get_securejoin_qr_single_use(&ctx).await
+    }
+
     /// Get QR code (text and SVG) that will offer a Setup-Contact or Verified-Group invitation.
     /// The QR code is compatible to the OPENPGP4FPR format
     /// so that a basic fingerprint comparison also works e.g. with OpenKeychain.
@@ -1309,6 +1622,16 @@ async fn markfresh_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         markfresh_chat(&ctx, ChatId::new(chat_id)).await
     }
 
+    /// Marks the chat as unread without changing the state of its messages.
+    ///
+    /// The flag is synchronized across own devices
+    /// and makes `get_fresh_msg_cnt()` return at least 1 for the chat
+    /// until `marknoticed_chat()` is called.
+    async fn markunread_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id).mark_unread(&ctx).await
+    }
+
     /// Returns the message that is immediately followed by the last seen
     /// message.
     /// From the point of view of the user this is effectively
@@ -1371,6 +1694,254 @@ async fn is_chat_muted(&self, account_id: u32, chat_id: u32) -> Result<bool> {
             .is_muted())
     }
 
+    /// Sets whether unencrypted messages sent to the chat carry an OpenPGP signature,
+    /// e.g. for posts to mailing lists.
+    ///
+    /// Sends out #DC_EVENT_CHAT_MODIFIED.
+    async fn set_chat_sign_unencrypted(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        sign: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::set_sign_unencrypted(&ctx, ChatId::new(chat_id), sign).await
+    }
+
+    /// Returns whether unencrypted messages sent to the chat are signed
+    /// (can be changed by set_chat_sign_unencrypted()).
+    async fn is_chat_sign_unencrypted(&self, account_id: u32, chat_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Chat::load_from_db(&ctx, ChatId::new(chat_id))
+            .await?
+            .is_sign_unencrypted())
+    }
+
+    /// Sets whether member keys are gossiped periodically in the chat.
+    ///
+    /// Suppressing gossip is useful for very large groups
+    /// where Autocrypt-Gossip headers make up most of each message.
+    /// Keys are still gossiped when members are added
+    /// and when requested with force_chat_gossip().
+    ///
+    /// Sends out #DC_EVENT_CHAT_MODIFIED.
+    async fn set_chat_gossip_suppressed(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        suppress: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::set_gossip_suppressed(&ctx, ChatId::new(chat_id), suppress).await
+    }
+
+    /// Returns whether periodic gossip is suppressed in the chat
+    /// (can be changed by set_chat_gossip_suppressed()).
+    async fn is_chat_gossip_suppressed(&self, account_id: u32, chat_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Chat::load_from_db(&ctx, ChatId::new(chat_id))
+            .await?
+            .is_gossip_suppressed())
+    }
+
+    /// Sets whether recent messages of the group are shared with new members
+    /// joining via QR code.
+    ///
+    /// The new member receives up to 50 recent text messages with their original timestamps.
+    /// The setting is synchronized across own devices.
+    ///
+    /// Sends out #DC_EVENT_CHAT_MODIFIED.
+    async fn set_chat_share_history(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        share: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::set_share_history(&ctx, ChatId::new(chat_id), share).await
+    }
+
+    /// Returns whether recent messages are shared with new members of the group
+    /// (can be changed by set_chat_share_history()).
+    async fn is_chat_history_shared(&self, account_id: u32, chat_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Chat::load_from_db(&ctx, ChatId::new(chat_id))
+            .await?
+            .is_history_shared())
+    }
+
+    /// Gossips the key of the chat member with the next message sent to the chat,
+    /// even if gossip is suppressed in the chat,
+    /// e.g. after verifying the member.
+    async fn force_chat_gossip(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        contact_id: u32,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        chat::force_gossip(&ctx, ChatId::new(chat_id), ContactId::new(contact_id)).await
+    }
+
+    /// Sets the encryption preference for outgoing messages of the chat.
+    ///
+    /// This is a workaround for recipients whose systems can't handle encrypted messages.
+    /// Unencrypted messages can be read and modified in transit,
+    /// and Delta Chat recipients will see them in a separate unencrypted chat,
+    /// so UIs should warn loudly before changing the preference.
+    ///
+    /// Sends out #DC_EVENT_CHAT_MODIFIED.
+    async fn set_chat_encryption_preference(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        preference: JsonrpcEncryptionPreference,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id)
+            .set_encryption_preference(&ctx, preference.into())
+            .await
+    }
+
+    /// Returns the encryption preference for outgoing messages of the chat
+    /// (can be changed by set_chat_encryption_preference()).
+    async fn get_chat_encryption_preference(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<JsonrpcEncryptionPreference> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Chat::load_from_db(&ctx, ChatId::new(chat_id))
+            .await?
+            .get_encryption_preference()
+            .into())
+    }
+
+    /// Sets a UI-specific property of the chat, e.g. the wallpaper dimming.
+    ///
+    /// The key must be prefixed with `ui.`.
+    /// Setting `value` to `null` removes the property.
+    /// The property is synchronized across own devices.
+    async fn set_chat_ui_property(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        key: String,
+        value: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id)
+            .set_ui_property(&ctx, &key, value.as_deref())
+            .await
+    }
+
+    /// Returns a UI-specific property of the chat set with `set_chat_ui_property()`.
+    async fn get_chat_ui_property(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        key: String,
+    ) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id).get_ui_property(&ctx, &key).await
+    }
+
+    /// Sets the wallpaper image of the chat, `null` removes it.
+    ///
+    /// The image is copied to the blobdir and recoded if it is too large.
+    /// The wallpaper is synchronized across own devices.
+    async fn set_chat_wallpaper(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        image_path: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id)
+            .set_wallpaper(&ctx, image_path.as_deref().map(Path::new))
+            .await
+    }
+
+    /// Returns the path to the wallpaper image set with `set_chat_wallpaper()`.
+    async fn get_chat_wallpaper(&self, account_id: u32, chat_id: u32) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        let path = ChatId::new(chat_id).get_wallpaper(&ctx).await?;
+        Ok(path.map(|path| path.to_string_lossy().into_owned()))
+    }
+
+    /// Returns statistics about the messages of the chat,
+    /// e.g. the number of messages per member and the busiest hours.
+    async fn get_chat_statistics(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<JsonrpcChatStatistics> {
+        let ctx = self.get_context(account_id).await?;
+        let statistics = ChatId::new(chat_id).get_statistics(&ctx).await?;
+        Ok(statistics.into())
+    }
+
+    /// Renders the messages of the chat sent between `timestamp_start` (inclusive)
+    /// and `timestamp_end` (exclusive) into a standalone HTML file at the given path,
+    /// e.g. for printing or archiving.
+    ///
+    /// Images and previews of other attachments are embedded as thumbnails.
+    async fn render_chat_transcript(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        timestamp_start: i64,
+        timestamp_end: i64,
+        path: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id)
+            .render_transcript(
+                &ctx,
+                timestamp_start..timestamp_end,
+                TranscriptFormat::Html,
+                Path::new(&path),
+            )
+            .await
+    }
+
+    /// Imports the messages of a chat exported from another messenger into the chat.
+    ///
+    /// `path` is the exported zip archive, a directory containing the export
+    /// or the exported text file.
+    /// Senders are mapped to the chat members with the same name.
+    ///
+    /// Returns the number of imported messages.
+    async fn import_foreign_chat(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        path: String,
+        format: JsonrpcForeignChatFormat,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let imported =
+            imex::import_foreign_chat(&ctx, Path::new(&path), format.into(), ChatId::new(chat_id))
+                .await?;
+        Ok(u32::try_from(imported)?)
+    }
+
+    /// Searches the server for older messages of the chat which were never downloaded
+    /// and downloads at most `limit` most recent of them in the background.
+    ///
+    /// Returns the number of messages scheduled for download.
+    async fn fetch_chat_history_from_server(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        limit: usize,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id)
+            .fetch_history_from_server(&ctx, limit)
+            .await
+    }
+
     // ---------------------------------------------
     // message list
     // ---------------------------------------------
@@ -1421,19 +1992,23 @@ async fn markseen_msgs(&self, account_id: u32, msg_ids: Vec<u32>) -> Result<()>
     /// * _info_only: Deprecated, pass `false` here.
     /// * `add_daymarker` - If `true`, add day markers as `DC_MSG_ID_DAYMARKER` to the result,
     ///   e.g. [1234, 1237, 9, 1239]. The day marker timestamp is the midnight one for the
-    ///   corresponding (following) day in the local timezone.
+    ///   corresponding (following) day in the given timezone.
+    /// * `timezone_offset` - Offset of the timezone for day markers east of UTC in seconds.
+    ///   If `null`, the timezone of the device running the core is used,
+    ///   taking daylight saving time changes into account.
     async fn get_message_ids(
         &self,
         account_id: u32,
         chat_id: u32,
         _info_only: bool,
         add_daymarker: bool,
+        timezone_offset: Option<i32>,
     ) -> Result<Vec<u32>> {
         let ctx = self.get_context(account_id).await?;
         let msg = get_chat_msgs_ex(
             &ctx,
             ChatId::new(chat_id),
-            MessageListOptions { add_daymarker },
+            message_list_options(add_daymarker, timezone_offset),
         )
         .await?;
         Ok(msg
@@ -1447,6 +2022,28 @@ async fn get_message_ids(
             .collect())
     }
 
+    /// Returns IDs of up to `before` messages preceding the given message,
+    /// the message itself and up to `after` messages following it.
+    ///
+    /// Useful to jump to a message without loading the whole chat.
+    async fn get_message_ids_around(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        before: u32,
+        after: u32,
+    ) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = deltachat::chat::get_msgs_around(
+            &ctx,
+            MsgId::new(msg_id),
+            before.try_into()?,
+            after.try_into()?,
+        )
+        .await?;
+        Ok(msg_ids.into_iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
     /// Checks if the messages with given IDs exist.
     ///
     /// Returns IDs of existing messages.
@@ -1477,12 +2074,13 @@ async fn get_message_list_items(
         chat_id: u32,
         _info_only: bool,
         add_daymarker: bool,
+        timezone_offset: Option<i32>,
     ) -> Result<Vec<JsonrpcMessageListItem>> {
         let ctx = self.get_context(account_id).await?;
         let msg = get_chat_msgs_ex(
             &ctx,
             ChatId::new(chat_id),
-            MessageListOptions { add_daymarker },
+            message_list_options(add_daymarker, timezone_offset),
         )
         .await?;
         Ok(msg
@@ -1506,6 +2104,36 @@ async fn get_message_html(&self, account_id: u32, message_id: u32) -> Result<Opt
         MsgId::new(message_id).get_html(&ctx).await
     }
 
+    /// Returns the full MIME of a received message as it was decrypted.
+    ///
+    /// The full MIME is retained for all messages received
+    /// while the `save_mime_headers` config option is enabled,
+    /// otherwise only for messages with HTML parts.
+    /// Invalid UTF-8 sequences are replaced with U+FFFD.
+    async fn get_message_raw_mime(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        let mime = MsgId::new(message_id).get_raw_mime(&ctx).await?;
+        Ok(mime.map(|mime| String::from_utf8_lossy(&mime).into_owned()))
+    }
+
+    /// Returns the headers of a received message as a list of name-value pairs
+    /// in their original order.
+    ///
+    /// Like `get_message_raw_mime`, this requires the full MIME to be retained
+    /// and returns `null` otherwise.
+    async fn get_message_headers(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<Option<Vec<(String, String)>>> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).get_headers(&ctx).await
+    }
+
     /// get multiple messages in one call,
     /// if loading one message fails the error is stored in the result object in it's place.
     ///
@@ -1571,6 +2199,93 @@ async fn get_message_info(&self, account_id: u32, message_id: u32) -> Result<Str
         MsgId::new(message_id).get_info(&ctx).await
     }
 
+    /// Sets a local annotation of the message, e.g. processing state of a bot.
+    ///
+    /// Annotations are never sent or synchronized.
+    /// Setting `value` to `null` removes the annotation.
+    async fn set_message_annotation(
+        &self,
+        account_id: u32,
+        message_id: u32,
+        key: String,
+        value: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id)
+            .set_annotation(&ctx, &key, value.as_deref())
+            .await
+    }
+
+    /// Returns local annotations of the message set with `set_message_annotation()`.
+    async fn get_message_annotations(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<BTreeMap<String, String>> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).get_annotations(&ctx).await
+    }
+
+    /// Sets a reminder for the message, e.g. for a "Remind me later" option.
+    ///
+    /// At `timestamp`, the `MsgReminder` event is emitted
+    /// and an incoming message is marked as fresh again.
+    /// The reminder is synchronized to other devices.
+    /// Setting `timestamp` to 0 removes the reminder.
+    async fn set_message_reminder(
+        &self,
+        account_id: u32,
+        message_id: u32,
+        timestamp: i64,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).set_reminder(&ctx, timestamp).await
+    }
+
+    /// Returns the timestamp of the reminder set with `set_message_reminder()`,
+    /// `null` if no reminder is set.
+    async fn get_message_reminder(&self, account_id: u32, message_id: u32) -> Result<Option<i64>> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).get_reminder(&ctx).await
+    }
+
+    /// Reports a message of a broadcast channel or mailing list to its owner,
+    /// e.g. because it is spam or abusive.
+    ///
+    /// `reason` is a free-form text shown to the owner.
+    async fn report_message_to_owner(
+        &self,
+        account_id: u32,
+        message_id: u32,
+        reason: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).report_to_owner(&ctx, &reason).await
+    }
+
+    /// Returns reports about messages of own broadcast channels, newest first.
+    ///
+    /// If `chat_id` is set, only reports about messages of this channel are returned.
+    async fn get_abuse_reports(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+    ) -> Result<Vec<AbuseReport>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_abuse_reports(chat_id.map(ChatId::new))
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Deletes the report with the given ID, e.g. after it was handled.
+    async fn delete_abuse_report(&self, account_id: u32, report_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.delete_abuse_report(report_id).await
+    }
+
     /// Returns additional information for single message.
     async fn get_message_info_object(
         &self,
@@ -1611,6 +2326,45 @@ async fn get_message_read_receipts(
         Ok(receipts)
     }
 
+    /// Lists the files contained in a zip or tar attachment without extracting them.
+    async fn list_archive_contents(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<Vec<MessageArchiveEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        let entries = MsgId::new(message_id)
+            .list_archive_contents(&ctx)
+            .await?
+            .into_iter()
+            .map(|entry| MessageArchiveEntry {
+                name: entry.name,
+                size: entry.size,
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// Exports the message together with a proof of its integrity
+    /// as a tar file to the given path.
+    ///
+    /// The bundle contains the original message, the signed decrypted message,
+    /// the key of the sender and a verification report,
+    /// so that a third party can verify who sent the message and when.
+    /// Fails if the message is not available on the server anymore
+    /// or is not signed by the sender.
+    async fn export_message_with_proof(
+        &self,
+        account_id: u32,
+        message_id: u32,
+        path: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id)
+            .export_with_proof(&ctx, Path::new(&path))
+            .await
+    }
+
     /// Asks the core to start downloading a message fully.
     /// This function is typically called when the user hits the "Download" button
     /// that is shown by the UI in case `download_state` is `'Available'` or `'Failure'`
@@ -1627,6 +2381,18 @@ async fn download_full_message(&self, account_id: u32, message_id: u32) -> Resul
         MsgId::new(message_id).download_full(&ctx).await
     }
 
+    /// Downloads the original message from the server again
+    /// and restores its attachment,
+    /// e.g. after the file was deleted locally to free space.
+    ///
+    /// Fails if the message has no attachment
+    /// or is not available on the server anymore.
+    /// On success, a @ref DC_EVENT_MSGS_CHANGED event is emitted.
+    async fn redownload_message(&self, account_id: u32, message_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        MsgId::new(message_id).redownload(&ctx).await
+    }
+
     /// Search messages containing the given query string.
     /// Searching can be done globally (chat_id=None) or in a specified chat only (chat_id set).
     ///
@@ -1753,6 +2519,82 @@ async fn get_blocked_contacts(&self, account_id: u32) -> Result<Vec<ContactObjec
         Ok(contacts)
     }
 
+    /// Exports the list of blocked contacts as JSON.
+    ///
+    /// Blocked mailing lists are not exported.
+    async fn export_blocked_contacts(&self, account_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        Contact::export_blocked(&ctx).await
+    }
+
+    /// Blocks the contacts from a JSON block list
+    /// returned by `export_blocked_contacts()`.
+    ///
+    /// Returns the number of newly blocked contacts.
+    async fn import_blocked_contacts(&self, account_id: u32, json: String) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        Contact::import_blocked(&ctx, &json).await
+    }
+
+    /// Blocks all contacts from the given domain.
+    ///
+    /// Existing contacts from the domain are blocked immediately,
+    /// new contacts from the domain are blocked when their messages are received.
+    async fn block_domain(&self, account_id: u32, domain: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.block_domain(&domain).await
+    }
+
+    /// Removes the domain from the list of blocked domains.
+    ///
+    /// Contacts blocked because of the domain stay blocked.
+    async fn unblock_domain(&self, account_id: u32, domain: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.unblock_domain(&domain).await
+    }
+
+    /// Returns the list of blocked domains.
+    async fn get_blocked_domains(&self, account_id: u32) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.get_blocked_domains().await
+    }
+
+    /// Adds a filter for incoming messages.
+    ///
+    /// `pattern` is a regular expression matched against the `field` of the message.
+    /// Filters are evaluated in the order they were added,
+    /// only the action of the first matching filter is applied.
+    ///
+    /// Returns the ID of the new filter.
+    async fn add_msg_filter(
+        &self,
+        account_id: u32,
+        field: FilterField,
+        pattern: String,
+        action: FilterAction,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.add_msg_filter(field.into(), &pattern, action.into())
+            .await
+    }
+
+    /// Returns all filters for incoming messages in the order they are evaluated.
+    async fn get_msg_filters(&self, account_id: u32) -> Result<Vec<MsgFilter>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_msg_filters()
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Deletes the filter for incoming messages with the given ID.
+    async fn delete_msg_filter(&self, account_id: u32, filter_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.delete_msg_filter(filter_id).await
+    }
+
     /// Returns ids of known and unblocked contacts.
     ///
     /// By default, key-contacts are listed.
@@ -1919,6 +2761,15 @@ async fn make_vcard(&self, account_id: u32, contacts: Vec<u32>) -> Result<String
         deltachat::contact::make_vcard(&ctx, &contacts).await
     }
 
+    /// Writes a contact card of the user to a file at the given path.
+    ///
+    /// The contact card is a vCard with the key, the avatar and an invite link of the user.
+    /// Others can scan or import it with `check_qr` to start a verified chat.
+    async fn export_self_contact_card(&self, account_id: u32, path: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.export_self_contact_card(Path::new(&path)).await
+    }
+
     // ---------------------------------------------
     //                   chat
     // ---------------------------------------------
@@ -2106,6 +2957,27 @@ async fn maybe_network(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Sets the type of the network the device is connected to.
+    ///
+    /// On metered networks the core reduces network usage,
+    /// while offline no connections to the servers are attempted.
+    /// UIs should call this whenever the network changes.
+    async fn set_network_profile(
+        &self,
+        account_id: u32,
+        profile: JsonrpcNetworkProfile,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.set_network_profile(profile.into()).await;
+        Ok(())
+    }
+
+    /// Returns the network profile set with `set_network_profile()`.
+    async fn get_network_profile(&self, account_id: u32) -> Result<JsonrpcNetworkProfile> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_network_profile().await.into())
+    }
+
     /// Get the current connectivity, i.e. whether the device is connected to the IMAP server.
     /// One of:
     /// - DC_CONNECTIVITY_NOT_CONNECTED (1000-1999): Show e.g. the string "Not connected" or a red dot
@@ -2125,6 +2997,36 @@ async fn get_connectivity(&self, account_id: u32) -> Result<u32> {
         Ok(ctx.get_connectivity() as u32)
     }
 
+    /// Returns the category of the last error,
+    /// i.e. of the last #DC_EVENT_ERROR or of the last failed configuration.
+    ///
+    /// UIs should use it instead of matching the error message.
+    async fn get_last_error_code(&self, account_id: u32) -> Result<JsonrpcErrorCode> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_last_error_code().into())
+    }
+
+    /// Get the connectivity of each connection separately,
+    /// together with the last error that happened on it.
+    ///
+    /// Contains one IMAP entry per watched folder and an SMTP entry
+    /// while I/O is started, and always an entry for the Iroh endpoint.
+    /// `connectivity` has the same values as returned by get_connectivity().
+    ///
+    /// If the connectivity changes, a #DC_EVENT_CONNECTIVITY_CHANGED will be emitted.
+    async fn get_connectivity_per_connection(
+        &self,
+        account_id: u32,
+    ) -> Result<Vec<ConnectionConnectivity>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_connectivity_per_connection()
+            .await
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     /// Get an overview of the current connectivity, and possibly more statistics.
     /// Meant to give the user more insight about the current status than
     /// the basic connectivity info returned by get_connectivity(); show this
@@ -2239,6 +3141,22 @@ async fn send_webxdc_realtime_data(
         send_webxdc_realtime_data(&ctx, MsgId::new(instance_msg_id), data).await
     }
 
+    /// Sends realtime data to the peers of the webxdc
+    /// or stores it until a peer appears.
+    ///
+    /// Returns an ID reported by the `WebxdcRealtimeDataForwarded` event
+    /// once the data is sent to peers
+    /// or by the `WebxdcRealtimeDataExpired` event if the data is dropped.
+    async fn send_webxdc_realtime_data_stored(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+        data: Vec<u8>,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        send_webxdc_realtime_data_stored(&ctx, MsgId::new(instance_msg_id), data).await
+    }
+
     async fn send_webxdc_realtime_advertisement(
         &self,
         account_id: u32,
@@ -2751,6 +3669,37 @@ async fn misc_send_msg(
     }
 
     // mimics the old desktop call, will get replaced with something better in the composer rewrite,
+    /// Returns previous versions of the draft of a chat, the most recent one first.
+    ///
+    /// A version can be restored with `misc_set_draft`.
+    async fn get_draft_history(&self, account_id: u32, chat_id: u32) -> Result<Vec<MessageData>> {
+        let ctx = self.get_context(account_id).await?;
+        let mut history = Vec::new();
+        for draft in ChatId::new(chat_id).get_draft_history(&ctx).await? {
+            let quoted_message_id = draft
+                .quoted_message(&ctx)
+                .await?
+                .map(|msg| msg.get_id().to_u32());
+            history.push(MessageData {
+                text: Some(draft.get_text()),
+                html: None,
+                viewtype: Some(draft.get_viewtype().into()),
+                file: draft
+                    .get_file(&ctx)
+                    .map(|path| path.to_string_lossy().into_owned()),
+                filename: draft.get_filename(),
+                location: None,
+                override_sender_name: None,
+                alt_text: None,
+                extra_headers: None,
+                is_bot: None,
+                quoted_message_id,
+                quoted_text: draft.quoted_text(),
+            });
+        }
+        Ok(history)
+    }
+
     // the better version should support:
     // - changing viewtype to enable/disable compression
     // - keeping same message id as long as attachment does not change for webxdc messages
@@ -2840,3 +3789,13 @@ async fn get_config(
             .await
     }
 }
+
+fn message_list_options(add_daymarker: bool, timezone_offset: Option<i32>) -> MessageListOptions {
+    MessageListOptions {
+        add_daymarker,
+        daymarker_timezone: match timezone_offset {
+            Some(offset) => DaymarkerTimezone::FixedOffset(offset),
+            None => DaymarkerTimezone::Local,
+        },
+    }
+}
@@ -20,6 +20,8 @@ pub enum Account {
         /// Optional tag as "Work", "Family".
         /// Meant to help profile owner to differ between profiles with similar names.
         private_tag: Option<String>,
+        /// Whether an app-lock PIN is set, see `set_applock_pin()`.
+        applock_enabled: bool,
     },
     #[serde(rename_all = "camelCase")]
     Unconfigured { id: u32 },
@@ -38,6 +40,7 @@ pub async fn from_context(ctx: &deltachat::context::Context, id: u32) -> Result<
                     .await?,
             );
             let private_tag = ctx.get_config(Config::PrivateTag).await?;
+            let applock_enabled = ctx.is_applock_enabled().await?;
             Ok(Account::Configured {
                 id,
                 display_name,
@@ -45,6 +48,7 @@ pub async fn from_context(ctx: &deltachat::context::Context, id: u32) -> Result<
                 profile_image,
                 color,
                 private_tag,
+                applock_enabled,
             })
         } else {
             Ok(Account::Unconfigured { id })
@@ -2,14 +2,17 @@
 
 use anyhow::{bail, Context as _, Result};
 use deltachat::chat::{self, get_chat_contacts, get_past_chat_contacts, ChatVisibility, get_admin_contact_id};
-use deltachat::chat::{Chat, ChatId};
+use deltachat::chat::{Chat, ChatId, EncryptionPreference};
+use deltachat::chat_statistics::ChatStatistics;
 use deltachat::constants::Chattype;
 use deltachat::contact::{Contact, ContactId};
 use deltachat::context::Context;
+use deltachat::imex::ForeignChatFormat;
 use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 use super::color_int_to_hex_string;
+use super::message::MessageViewtype;
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -264,6 +267,56 @@ pub fn into_core_type(self) -> ChatVisibility {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "EncryptionPreference")]
+pub enum JsonrpcEncryptionPreference {
+    /// Messages are encrypted if the chat is encrypted.
+    Default,
+    /// Messages are encrypted if keys of all recipients are available,
+    /// otherwise they are sent unencrypted.
+    Opportunistic,
+    /// Messages are never encrypted.
+    ForcePlain,
+}
+
+impl From<EncryptionPreference> for JsonrpcEncryptionPreference {
+    fn from(preference: EncryptionPreference) -> Self {
+        match preference {
+            EncryptionPreference::Default => JsonrpcEncryptionPreference::Default,
+            EncryptionPreference::Opportunistic => JsonrpcEncryptionPreference::Opportunistic,
+            EncryptionPreference::ForcePlain => JsonrpcEncryptionPreference::ForcePlain,
+        }
+    }
+}
+
+impl From<JsonrpcEncryptionPreference> for EncryptionPreference {
+    fn from(preference: JsonrpcEncryptionPreference) -> Self {
+        match preference {
+            JsonrpcEncryptionPreference::Default => EncryptionPreference::Default,
+            JsonrpcEncryptionPreference::Opportunistic => EncryptionPreference::Opportunistic,
+            JsonrpcEncryptionPreference::ForcePlain => EncryptionPreference::ForcePlain,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ForeignChatFormat")]
+pub enum JsonrpcForeignChatFormat {
+    /// WhatsApp chat export created by "Export chat".
+    WhatsApp,
+    /// Signal chat exported to Markdown by `signal-export`.
+    Signal,
+}
+
+impl From<JsonrpcForeignChatFormat> for ForeignChatFormat {
+    fn from(format: JsonrpcForeignChatFormat) -> Self {
+        match format {
+            JsonrpcForeignChatFormat::WhatsApp => ForeignChatFormat::WhatsApp,
+            JsonrpcForeignChatFormat::Signal => ForeignChatFormat::Signal,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, TypeDef, schemars::JsonSchema)]
 #[serde(rename = "ChatType")]
 pub enum JsonrpcChatType {
@@ -297,3 +350,58 @@ fn from(chattype: JsonrpcChatType) -> Self {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatStatisticsContactCount {
+    contact_id: u32,
+    count: usize,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatStatisticsViewtypeCount {
+    viewtype: MessageViewtype,
+    count: usize,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ChatStatistics", rename_all = "camelCase")]
+pub struct JsonrpcChatStatistics {
+    msg_count: usize,
+    /// Number of messages sent by each contact, the most active contact first.
+    msgs_per_contact: Vec<ChatStatisticsContactCount>,
+    /// Number of messages sent in each of the 24 hours of the day in local time.
+    msgs_per_hour: Vec<usize>,
+    /// Number of messages of each viewtype except text, the most frequent viewtype first.
+    media_counts: Vec<ChatStatisticsViewtypeCount>,
+    first_activity: Option<i64>,
+    last_activity: Option<i64>,
+}
+
+impl From<ChatStatistics> for JsonrpcChatStatistics {
+    fn from(statistics: ChatStatistics) -> Self {
+        Self {
+            msg_count: statistics.msg_count,
+            msgs_per_contact: statistics
+                .msgs_per_contact
+                .into_iter()
+                .map(|(contact_id, count)| ChatStatisticsContactCount {
+                    contact_id: contact_id.to_u32(),
+                    count,
+                })
+                .collect(),
+            msgs_per_hour: statistics.msgs_per_hour.to_vec(),
+            media_counts: statistics
+                .media_counts
+                .into_iter()
+                .map(|(viewtype, count)| ChatStatisticsViewtypeCount {
+                    viewtype: viewtype.into(),
+                    count,
+                })
+                .collect(),
+            first_activity: statistics.first_activity,
+            last_activity: statistics.last_activity,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use deltachat::context::{ConnectionConnectivity as CoreConnectionConnectivity, ConnectionKind};
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ConnectionKind", tag = "kind")]
+pub enum JsonrpcConnectionKind {
+    /// IMAP connection watching a folder.
+    Imap {
+        /// Address of the transport.
+        addr: String,
+
+        /// Watched folder, e.g. `INBOX`.
+        folder: String,
+    },
+
+    /// SMTP connection used to send messages.
+    Smtp,
+
+    /// Iroh endpoint used for realtime peer channels.
+    Iroh,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionConnectivity {
+    pub connection: JsonrpcConnectionKind,
+
+    /// Connectivity as returned by `get_connectivity()`.
+    pub connectivity: u32,
+
+    /// Last error that happened on the connection, if any.
+    pub last_error: Option<String>,
+}
+
+impl From<CoreConnectionConnectivity> for ConnectionConnectivity {
+    fn from(c: CoreConnectionConnectivity) -> Self {
+        let connection = match c.kind {
+            ConnectionKind::Imap { addr, folder } => JsonrpcConnectionKind::Imap { addr, folder },
+            ConnectionKind::Smtp => JsonrpcConnectionKind::Smtp,
+            ConnectionKind::Iroh => JsonrpcConnectionKind::Iroh,
+        };
+        Self {
+            connection,
+            connectivity: c.connectivity as u32,
+            last_error: c.last_error,
+        }
+    }
+}
@@ -122,6 +122,8 @@ pub struct VcardContact {
     profile_image: Option<String>,
     /// Contact color as hex string.
     color: String,
+    /// Invite link to start a verified chat.
+    invite_link: Option<String>,
     /// Last update timestamp.
     timestamp: Option<i64>,
 }
@@ -142,6 +144,7 @@ fn from(vc: deltachat_contact_tools::VcardContact) -> Self {
             key: vc.key,
             profile_image: vc.profile_image,
             color: color_int_to_hex_string(color),
+            invite_link: vc.invite_link,
             timestamp: vc.timestamp.ok(),
         }
     }
@@ -1,4 +1,4 @@
-use deltachat::{Event as CoreEvent, EventType as CoreEventType};
+use deltachat::{ErrorCode, Event as CoreEvent, EventType as CoreEventType};
 use serde::Serialize;
 use typescript_type_def::TypeDef;
 
@@ -56,6 +56,17 @@ pub enum EventType {
     /// Emitted when an file in the $BLOBDIR was deleted
     DeletedBlobFile { file: String },
 
+    /// Inform about the progress of copying a large file into the $BLOBDIR,
+    /// e.g. when a big video is attached to a message.
+    #[serde(rename_all = "camelCase")]
+    BlobCopyProgress {
+        /// Path of the file being copied.
+        path: String,
+
+        /// Progress in permille, 1000=done.
+        progress: u16,
+    },
+
     /// The library-user should write a warning string to the log.
     ///
     /// This event should *not* be reported to the end-user using a popup or something like
@@ -72,6 +83,8 @@ pub enum EventType {
     /// it might be better to delay showing these events until the function has really
     /// failed (returned false). It should be sufficient to report only the *last* error
     /// in a message box then.
+    ///
+    /// The category of the error is returned by getLastErrorCode().
     Error { msg: String },
 
     /// An action cannot be performed because the user is not in the group.
@@ -127,6 +140,20 @@ pub enum EventType {
         reaction: String,
     },
 
+    /// A subscriber reported a message of an outgoing broadcast channel,
+    /// see `getAbuseReports()`.
+    #[serde(rename_all = "camelCase")]
+    IncomingAbuseReport {
+        /// ID of the broadcast channel.
+        chat_id: u32,
+
+        /// ID of the reported message.
+        msg_id: u32,
+
+        /// ID of the contact who sent the report.
+        contact_id: u32,
+    },
+
     /// Incoming webxdc info or summary update, should be notified.
     #[serde(rename_all = "camelCase")]
     IncomingWebxdcNotify {
@@ -150,6 +177,9 @@ pub enum EventType {
     /// when receiving this message.
     ///
     /// There is no extra #DC_EVENT_MSGS_CHANGED event sent together with this event.
+    ///
+    /// During quiet hours, see `is_quiet_hours()`,
+    /// the event is still emitted, but no notification should be shown.
     #[serde(rename_all = "camelCase")]
     IncomingMsg {
         /// ID of the chat where the message is assigned.
@@ -165,6 +195,20 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     IncomingMsgBunch,
 
+    /// A received message has an attachment that looks harmful.
+    /// The UI may show a caution banner before the file is saved or opened,
+    /// `attachmentWarning` of the message contains the reason.
+    ///
+    /// Emitted in addition to the usual events for the message.
+    #[serde(rename_all = "camelCase")]
+    AttachmentWarning {
+        /// ID of the chat where the message is assigned.
+        chat_id: u32,
+
+        /// ID of the message.
+        msg_id: u32,
+    },
+
     /// Messages were seen or noticed.
     /// chat id is always set.
     #[serde(rename_all = "camelCase")]
@@ -214,6 +258,22 @@ pub enum EventType {
         msg_id: u32,
     },
 
+    /// Aggregated delivery state of a message sent to a broadcast channel changed.
+    #[serde(rename_all = "camelCase")]
+    BroadcastDeliveryUpdate {
+        /// ID of the message sent to the broadcast channel.
+        msg_id: u32,
+
+        /// Number of subscribers the message was delivered to.
+        delivered: u32,
+
+        /// Number of subscribers who sent a read receipt.
+        read: u32,
+
+        /// Number of subscribers of the channel.
+        total: u32,
+    },
+
     /// A single message was deleted.
     ///
     /// This event means that the message will no longer appear in the messagelist.
@@ -236,6 +296,19 @@ pub enum EventType {
         msg_id: u32,
     },
 
+    /// A reminder set with setMessageReminder() is due.
+    ///
+    /// If the message is incoming, it is marked as fresh again.
+    /// UI may show a notification for the message.
+    #[serde(rename_all = "camelCase")]
+    MsgReminder {
+        /// ID of the chat which the message belongs to.
+        chat_id: u32,
+
+        /// ID of the message to remind about.
+        msg_id: u32,
+    },
+
     /// Chat changed.  The name or the image of a chat group was changed or members were added or removed.
     /// See setChatName(), setChatProfileImage(), addContactToChat()
     /// and removeContactFromChat().
@@ -255,6 +328,17 @@ pub enum EventType {
         timer: u32,
     },
 
+    /// A UI-specific property of the chat changed,
+    /// see setChatUiProperty().
+    #[serde(rename_all = "camelCase")]
+    ChatUiPropertyChanged {
+        /// Chat ID.
+        chat_id: u32,
+
+        /// Key of the changed property.
+        key: String,
+    },
+
     /// Chat deleted.
     ChatDeleted {
         /// Chat ID.
@@ -306,6 +390,22 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ImexFileWritten { path: String },
 
+    /// Inform about the progress of searching older messages
+    /// started by fetchOlderMessages().
+    #[serde(rename_all = "camelCase")]
+    FetchOlderMsgsProgress {
+        /// 0=error, 1-999=progress in permille, 1000=success and done
+        progress: u16,
+    },
+
+    /// Inform about the progress of housekeeping,
+    /// either scheduled or started by runHousekeepingNow().
+    #[serde(rename_all = "camelCase")]
+    HousekeepingProgress {
+        /// 1-999=progress in permille, 1000=done
+        progress: u16,
+    },
+
     /// Progress event sent when SecureJoin protocol has finished
     /// from the view of the inviter (Alice, the person who shows the QR code).
     ///
@@ -337,6 +437,7 @@ pub enum EventType {
         contact_id: u32,
 
         /// Progress as:
+        /// 0=inviter rejected the handshake, the reason is returned by getLastErrorCode()
         /// 400=vg-/vc-request-with-auth sent, typically shown as "alice@addr verified, introducing myself."
         /// (Bob has verified alice and waits until Alice does the same for him)
         /// 1000=vg-member-added/vc-contact-confirm received
@@ -349,6 +450,20 @@ pub enum EventType {
     /// getConnectivityHtml() for details.
     ConnectivityChanged,
 
+    /// Connections through the active proxy failed repeatedly
+    /// and the next configured proxy is used now.
+    ProxySwitched {
+        /// URL of the proxy used now.
+        url: String,
+    },
+
+    /// The local clock differs from the server clock by more than a few minutes.
+    /// The UI may ask the user to check the date, time and time zone of the device.
+    ClockSkewDetected {
+        /// Seconds the local clock is ahead of the server clock, negative if it is behind.
+        skew: i64,
+    },
+
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
 
@@ -360,6 +475,19 @@ pub enum EventType {
         key: String,
     },
 
+    /// A config value was set, either locally or by a sync message from another device.
+    /// The value isn't here, use getConfig() to get it.
+    ConfigChanged {
+        /// Configuration key.
+        key: String,
+    },
+
+    /// The encrypted key backup on the server was stored or deleted.
+    KeyBackupChanged {
+        /// True if the backup was stored, false if it was deleted.
+        stored: bool,
+    },
+
     #[serde(rename_all = "camelCase")]
     WebxdcStatusUpdate {
         /// Message ID.
@@ -379,6 +507,27 @@ pub enum EventType {
         data: Vec<u8>,
     },
 
+    /// Realtime data stored while there were no peers has been sent to peers.
+    #[serde(rename_all = "camelCase")]
+    WebxdcRealtimeDataForwarded {
+        /// Message ID of the webxdc instance.
+        msg_id: u32,
+
+        /// ID returned by `sendWebxdcRealtimeDataStored()`.
+        data_id: u32,
+    },
+
+    /// Realtime data stored while there were no peers has been dropped
+    /// because no peer appeared in time or the storage limit was reached.
+    #[serde(rename_all = "camelCase")]
+    WebxdcRealtimeDataExpired {
+        /// Message ID of the webxdc instance.
+        msg_id: u32,
+
+        /// ID returned by `sendWebxdcRealtimeDataStored()`.
+        data_id: u32,
+    },
+
     /// Advertisement received over an ephemeral peer channel.
     /// This can be used by bots to initiate peer-to-peer communication from their side.
     #[serde(rename_all = "camelCase")]
@@ -497,6 +646,9 @@ fn from(event: CoreEventType) -> Self {
             CoreEventType::ImapInboxIdle => ImapInboxIdle,
             CoreEventType::NewBlobFile(file) => NewBlobFile { file },
             CoreEventType::DeletedBlobFile(file) => DeletedBlobFile { file },
+            CoreEventType::BlobCopyProgress { path, progress } => {
+                BlobCopyProgress { path, progress }
+            }
             CoreEventType::Warning(msg) => Warning { msg },
             CoreEventType::Error(msg) => Error { msg },
             CoreEventType::ErrorSelfNotInGroup(msg) => ErrorSelfNotInGroup { msg },
@@ -524,6 +676,15 @@ fn from(event: CoreEventType) -> Self {
                 msg_id: msg_id.to_u32(),
                 reaction: reaction.as_str().to_string(),
             },
+            CoreEventType::IncomingAbuseReport {
+                chat_id,
+                msg_id,
+                contact_id,
+            } => IncomingAbuseReport {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+                contact_id: contact_id.to_u32(),
+            },
             CoreEventType::IncomingWebxdcNotify {
                 chat_id,
                 contact_id,
@@ -542,6 +703,10 @@ fn from(event: CoreEventType) -> Self {
                 msg_id: msg_id.to_u32(),
             },
             CoreEventType::IncomingMsgBunch => IncomingMsgBunch,
+            CoreEventType::AttachmentWarning { chat_id, msg_id } => AttachmentWarning {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+            },
             CoreEventType::MsgsNoticed(chat_id) => MsgsNoticed {
                 chat_id: chat_id.to_u32(),
             },
@@ -565,6 +730,21 @@ fn from(event: CoreEventType) -> Self {
                 chat_id: chat_id.to_u32(),
                 msg_id: msg_id.to_u32(),
             },
+            CoreEventType::BroadcastDeliveryUpdate {
+                msg_id,
+                delivered,
+                read,
+                total,
+            } => BroadcastDeliveryUpdate {
+                msg_id: msg_id.to_u32(),
+                delivered,
+                read,
+                total,
+            },
+            CoreEventType::MsgReminder { chat_id, msg_id } => MsgReminder {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+            },
             CoreEventType::ChatModified(chat_id) => ChatModified {
                 chat_id: chat_id.to_u32(),
             },
@@ -574,6 +754,10 @@ fn from(event: CoreEventType) -> Self {
                     timer: timer.to_u32(),
                 }
             }
+            CoreEventType::ChatUiPropertyChanged { chat_id, key } => ChatUiPropertyChanged {
+                chat_id: chat_id.to_u32(),
+                key,
+            },
             CoreEventType::ChatDeleted { chat_id } => ChatDeleted {
                 chat_id: chat_id.to_u32(),
             },
@@ -590,6 +774,8 @@ fn from(event: CoreEventType) -> Self {
             CoreEventType::ImexFileWritten(path) => ImexFileWritten {
                 path: path.to_str().unwrap_or_default().to_owned(),
             },
+            CoreEventType::FetchOlderMsgsProgress(progress) => FetchOlderMsgsProgress { progress },
+            CoreEventType::HousekeepingProgress(progress) => HousekeepingProgress { progress },
             CoreEventType::SecurejoinInviterProgress {
                 contact_id,
                 chat_type,
@@ -609,10 +795,16 @@ fn from(event: CoreEventType) -> Self {
                 progress,
             },
             CoreEventType::ConnectivityChanged => ConnectivityChanged,
+            CoreEventType::ProxySwitched { url } => ProxySwitched { url },
+            CoreEventType::ClockSkewDetected { skew } => ClockSkewDetected { skew },
             CoreEventType::SelfavatarChanged => SelfavatarChanged,
             CoreEventType::ConfigSynced { key } => ConfigSynced {
                 key: key.to_string(),
             },
+            CoreEventType::ConfigChanged { key } => ConfigChanged {
+                key: key.to_string(),
+            },
+            CoreEventType::KeyBackupChanged { stored } => KeyBackupChanged { stored },
             CoreEventType::WebxdcStatusUpdate {
                 msg_id,
                 status_update_serial,
@@ -624,6 +816,18 @@ fn from(event: CoreEventType) -> Self {
                 msg_id: msg_id.to_u32(),
                 data,
             },
+            CoreEventType::WebxdcRealtimeDataForwarded { msg_id, data_id } => {
+                WebxdcRealtimeDataForwarded {
+                    msg_id: msg_id.to_u32(),
+                    data_id,
+                }
+            }
+            CoreEventType::WebxdcRealtimeDataExpired { msg_id, data_id } => {
+                WebxdcRealtimeDataExpired {
+                    msg_id: msg_id.to_u32(),
+                    data_id,
+                }
+            }
             CoreEventType::WebxdcRealtimeAdvertisementReceived { msg_id } => {
                 WebxdcRealtimeAdvertisementReceived {
                     msg_id: msg_id.to_u32(),
@@ -681,3 +885,42 @@ fn from(event: CoreEventType) -> Self {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ErrorCode")]
+pub enum JsonrpcErrorCode {
+    /// The error does not belong to any of the other categories.
+    Other,
+
+    /// The server rejected the login credentials.
+    AuthFailed,
+
+    /// The storage quota on the server is exceeded.
+    QuotaExceeded,
+
+    /// The TLS certificate of the server is invalid.
+    TlsInvalid,
+
+    /// The server refused the request because of rate limiting.
+    RateLimited,
+
+    /// The server is unreachable.
+    Network,
+
+    /// The scanned single-use invite code was already used.
+    SecurejoinCodeUsed,
+}
+
+impl From<ErrorCode> for JsonrpcErrorCode {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::Other => Self::Other,
+            ErrorCode::AuthFailed => Self::AuthFailed,
+            ErrorCode::QuotaExceeded => Self::QuotaExceeded,
+            ErrorCode::TlsInvalid => Self::TlsInvalid,
+            ErrorCode::RateLimited => Self::RateLimited,
+            ErrorCode::Network => Self::Network,
+            ErrorCode::SecurejoinCodeUsed => Self::SecurejoinCodeUsed,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use deltachat::filters::{
+    FilterAction as CoreFilterAction, FilterField as CoreFilterField, MsgFilter as CoreMsgFilter,
+};
+use serde::{Deserialize, Serialize};
+use typescript_type_def::TypeDef;
+
+#[derive(Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "FilterField")]
+pub enum FilterField {
+    /// Address of the sender.
+    Sender,
+
+    /// Subject of the message.
+    Subject,
+
+    /// Text of the message.
+    Text,
+
+    /// Value of the `Auto-Submitted` header, empty if the header is missing.
+    AutoSubmitted,
+}
+
+impl From<CoreFilterField> for FilterField {
+    fn from(field: CoreFilterField) -> Self {
+        match field {
+            CoreFilterField::Sender => Self::Sender,
+            CoreFilterField::Subject => Self::Subject,
+            CoreFilterField::Text => Self::Text,
+            CoreFilterField::AutoSubmitted => Self::AutoSubmitted,
+        }
+    }
+}
+
+impl From<FilterField> for CoreFilterField {
+    fn from(field: FilterField) -> Self {
+        match field {
+            FilterField::Sender => Self::Sender,
+            FilterField::Subject => Self::Subject,
+            FilterField::Text => Self::Text,
+            FilterField::AutoSubmitted => Self::AutoSubmitted,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "FilterAction")]
+pub enum FilterAction {
+    /// Add the message as noticed, so that no notification is shown.
+    MuteNotification,
+
+    /// Archive the chat the message is added to.
+    Archive,
+
+    /// Hide the message and move it to the Spam folder on the server.
+    MoveToSpam,
+
+    /// Delete the message locally and on the server.
+    Delete,
+}
+
+impl From<CoreFilterAction> for FilterAction {
+    fn from(action: CoreFilterAction) -> Self {
+        match action {
+            CoreFilterAction::MuteNotification => Self::MuteNotification,
+            CoreFilterAction::Archive => Self::Archive,
+            CoreFilterAction::MoveToSpam => Self::MoveToSpam,
+            CoreFilterAction::Delete => Self::Delete,
+        }
+    }
+}
+
+impl From<FilterAction> for CoreFilterAction {
+    fn from(action: FilterAction) -> Self {
+        match action {
+            FilterAction::MuteNotification => Self::MuteNotification,
+            FilterAction::Archive => Self::Archive,
+            FilterAction::MoveToSpam => Self::MoveToSpam,
+            FilterAction::Delete => Self::Delete,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MsgFilter {
+    pub id: u32,
+    pub field: FilterField,
+    /// Regular expression matched against the field.
+    pub pattern: String,
+    pub action: FilterAction,
+}
+
+impl From<CoreMsgFilter> for MsgFilter {
+    fn from(filter: CoreMsgFilter) -> Self {
+        Self {
+            id: filter.id,
+            field: filter.field.into(),
+            pattern: filter.pattern,
+            action: filter.action.into(),
+        }
+    }
+}
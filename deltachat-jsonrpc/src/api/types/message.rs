@@ -2,12 +2,14 @@
 
 use crate::api::VcardContact;
 use anyhow::{Context as _, Result};
+use base64::Engine as _;
 use deltachat::chat::Chat;
 use deltachat::chat::ChatItem;
 use deltachat::chat::ChatVisibility;
 use deltachat::contact::Contact;
 use deltachat::context::Context;
 use deltachat::download;
+use deltachat::message;
 use deltachat::message::Message;
 use deltachat::message::MsgId;
 use deltachat::message::Viewtype;
@@ -42,6 +44,14 @@ pub struct MessageObject {
 
     is_edited: bool,
 
+    /// True if the message was removed from the server without our involvement,
+    /// see `mark_expunged_msgs` config.
+    is_removed_from_server: bool,
+
+    /// True if the message was shared by another member when we joined the group.
+    /// The original sender of such messages is not verified.
+    is_from_shared_history: bool,
+
     /// Check if a message has a POI location bound to it.
     /// These locations are also returned by `get_locations` method.
     /// The UI may decide to display a special icon beside such messages.
@@ -55,6 +65,9 @@ pub struct MessageObject {
 
     timestamp: i64,
     sort_timestamp: i64,
+    /// Key for ordering the message, comparing the keys as strings
+    /// gives the same order as `get_message_ids()`.
+    sort_key: String,
     received_timestamp: i64,
     has_deviating_timestamp: bool,
 
@@ -87,6 +100,12 @@ pub struct MessageObject {
     override_sender_name: Option<String>,
     sender: ContactObject,
 
+    /// ISO 639-1 code of the language detected in the text of a received message, e.g. `"de"`.
+    language: Option<String>,
+
+    /// Alternative text describing the attachment for screen readers.
+    alt_text: Option<String>,
+
     file: Option<String>,
     file_mime: Option<String>,
 
@@ -99,6 +118,9 @@ pub struct MessageObject {
 
     download_state: DownloadState,
 
+    /// Why the attachment of a received message may be harmful, if it looks suspicious.
+    attachment_warning: Option<AttachmentWarning>,
+
     original_msg_id: Option<u32>,
 
     saved_message_id: Option<u32>,
@@ -111,8 +133,19 @@ pub struct MessageObject {
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(tag = "kind")]
 enum MessageQuote {
+    /// The quoted message is not available,
+    /// e.g. because it was deleted or never received.
+    ///
+    /// Metadata stored when quoting is returned if available.
+    #[serde(rename_all = "camelCase")]
     JustText {
         text: String,
+        author_display_name: Option<String>,
+        timestamp: Option<i64>,
+        /// Summary of the quoted message, such as "📷 Image – caption".
+        snippet: Option<String>,
+        /// JPEG micro-thumbnail of the quoted image as a `data:` URL.
+        image_thumbnail: Option<String>,
     },
     #[serde(rename_all = "camelCase")]
     WithMessage {
@@ -177,7 +210,18 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
                         view_type: quote.get_viewtype().into(),
                     })
                 }
-                None => Some(MessageQuote::JustText { text: quoted_text }),
+                None => Some(MessageQuote::JustText {
+                    text: quoted_text,
+                    author_display_name: message.get_quote_author(),
+                    timestamp: message.get_quote_timestamp(),
+                    snippet: message.get_quote_snippet(),
+                    image_thumbnail: message.get_quote_thumbnail().map(|thumbnail| {
+                        format!(
+                            "data:image/jpeg;base64,{}",
+                            base64::engine::general_purpose::STANDARD.encode(thumbnail)
+                        )
+                    }),
+                }),
             }
         } else {
             None
@@ -207,6 +251,8 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
             parent_id,
             text: message.get_text(),
             is_edited: message.is_edited(),
+            is_removed_from_server: message.is_removed_from_server(),
+            is_from_shared_history: message.is_from_shared_history(),
             has_location: message.has_location(),
             has_html: message.has_html(),
             view_type: message.get_viewtype().into(),
@@ -218,6 +264,7 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
 
             timestamp: message.get_timestamp(),
             sort_timestamp: message.get_sort_timestamp(),
+            sort_key: message.get_sort_key(),
             received_timestamp: message.get_received_timestamp(),
             has_deviating_timestamp: message.has_deviating_timestamp(),
 
@@ -239,6 +286,9 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
             override_sender_name,
             sender,
 
+            language: message.get_language().map(|language| language.to_string()),
+            alt_text: message.get_alt_text().map(|alt_text| alt_text.to_string()),
+
             file: match message.get_file(context) {
                 Some(path_buf) => path_buf.to_str().map(|s| s.to_owned()),
                 None => None,
@@ -253,6 +303,8 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
 
             download_state,
 
+            attachment_warning: message.get_attachment_warning().map(Into::into),
+
             original_msg_id: message
                 .get_original_msg_id(context)
                 .await?
@@ -376,6 +428,25 @@ fn from(state: download::DownloadState) -> Self {
     }
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum AttachmentWarning {
+    DangerousExtension,
+    DoubleExtension,
+    ContentTypeMismatch,
+}
+
+impl From<message::AttachmentWarning> for AttachmentWarning {
+    fn from(warning: message::AttachmentWarning) -> Self {
+        match warning {
+            message::AttachmentWarning::DangerousExtension => AttachmentWarning::DangerousExtension,
+            message::AttachmentWarning::DoubleExtension => AttachmentWarning::DoubleExtension,
+            message::AttachmentWarning::ContentTypeMismatch => {
+                AttachmentWarning::ContentTypeMismatch
+            }
+        }
+    }
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 pub enum SystemMessageType {
     Unknown,
@@ -425,6 +496,14 @@ pub enum SystemMessageType {
 
     CallAccepted,
     CallEnded,
+
+    /// Recent messages of a group sent to a new member.
+    /// These messages are not shown in the chat.
+    GroupHistory,
+
+    /// Report about a broadcast channel message sent to the channel owner.
+    /// These messages are not shown in the chat.
+    AbuseReport,
 }
 
 impl From<deltachat::mimeparser::SystemMessage> for SystemMessageType {
@@ -454,6 +533,8 @@ fn from(system_message_type: deltachat::mimeparser::SystemMessage) -> Self {
             SystemMessage::SecurejoinWaitTimeout => SystemMessageType::SecurejoinWaitTimeout,
             SystemMessage::CallAccepted => SystemMessageType::CallAccepted,
             SystemMessage::CallEnded => SystemMessageType::CallEnded,
+            SystemMessage::GroupHistory => SystemMessageType::GroupHistory,
+            SystemMessage::AbuseReport => SystemMessageType::AbuseReport,
         }
     }
 }
@@ -613,6 +694,13 @@ pub struct MessageData {
     pub filename: Option<String>,
     pub location: Option<(f64, f64)>,
     pub override_sender_name: Option<String>,
+    /// Alternative text describing the attachment for screen readers.
+    pub alt_text: Option<String>,
+    /// Custom `X-` headers to send, e.g. by gateway bots.
+    pub extra_headers: Option<Vec<(String, String)>>,
+    /// Send the message with `Auto-Submitted: auto-generated` header
+    /// even if the account is not configured as a bot.
+    pub is_bot: Option<bool>,
     /// Quoted message id. Takes preference over `quoted_text` (see below).
     pub quoted_message_id: Option<u32>,
     pub quoted_text: Option<String>,
@@ -634,6 +722,15 @@ pub(crate) async fn create_message(self, context: &Context) -> Result<Message> {
         if self.override_sender_name.is_some() {
             message.set_override_sender_name(self.override_sender_name);
         }
+        if self.alt_text.is_some() {
+            message.set_alt_text(self.alt_text);
+        }
+        for (name, value) in self.extra_headers.unwrap_or_default() {
+            message.set_extra_header(&name, &value)?;
+        }
+        if let Some(is_bot) = self.is_bot {
+            message.set_bot(is_bot);
+        }
         if let Some(file) = self.file {
             message.set_file_and_deduplicate(
                 context,
@@ -668,6 +765,38 @@ pub struct MessageReadReceipt {
     pub timestamp: i64,
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AbuseReport {
+    pub id: u32,
+    pub chat_id: u32,
+    pub msg_id: u32,
+    pub contact_id: u32,
+    /// Reason given by the subscriber.
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+impl From<deltachat::moderation::AbuseReport> for AbuseReport {
+    fn from(report: deltachat::moderation::AbuseReport) -> Self {
+        Self {
+            id: report.id,
+            chat_id: report.chat_id.to_u32(),
+            msg_id: report.msg_id.to_u32(),
+            contact_id: report.contact_id.to_u32(),
+            reason: report.reason,
+            timestamp: report.timestamp,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageArchiveEntry {
+    pub name: String,
+    pub size: u64,
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageInfo {
@@ -2,12 +2,16 @@
 pub mod calls;
 pub mod chat;
 pub mod chat_list;
+pub mod connectivity;
 pub mod contact;
 pub mod events;
+pub mod filters;
 pub mod http;
 pub mod location;
 pub mod login_param;
 pub mod message;
+pub mod network_profile;
+pub mod notification;
 pub mod notify_state;
 pub mod provider_info;
 pub mod qr;
@@ -0,0 +1,36 @@
+use deltachat::net::NetworkProfile;
+use serde::{Deserialize, Serialize};
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "NetworkProfile")]
+pub enum JsonrpcNetworkProfile {
+    /// Unmetered network, e.g. Wi-Fi.
+    Wifi,
+
+    /// Metered network, e.g. mobile data.
+    Metered,
+
+    /// No network.
+    Offline,
+}
+
+impl From<NetworkProfile> for JsonrpcNetworkProfile {
+    fn from(profile: NetworkProfile) -> Self {
+        match profile {
+            NetworkProfile::Wifi => Self::Wifi,
+            NetworkProfile::Metered => Self::Metered,
+            NetworkProfile::Offline => Self::Offline,
+        }
+    }
+}
+
+impl From<JsonrpcNetworkProfile> for NetworkProfile {
+    fn from(profile: JsonrpcNetworkProfile) -> Self {
+        match profile {
+            JsonrpcNetworkProfile::Wifi => Self::Wifi,
+            JsonrpcNetworkProfile::Metered => Self::Metered,
+            JsonrpcNetworkProfile::Offline => Self::Offline,
+        }
+    }
+}
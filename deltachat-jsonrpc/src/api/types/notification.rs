@@ -0,0 +1,92 @@
+use deltachat::notifications::{
+    NotificationItem as CoreNotificationItem, NotificationKind as CoreNotificationKind,
+    RenderedNotification as CoreRenderedNotification,
+};
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "NotificationKind")]
+pub enum NotificationKind {
+    /// Incoming message.
+    Message,
+
+    /// Incoming group message replying to a message sent by us.
+    Mention,
+
+    /// Reaction to a message sent by us.
+    Reaction,
+
+    /// Webxdc app asked to notify us.
+    WebxdcNotify,
+}
+
+impl From<CoreNotificationKind> for NotificationKind {
+    fn from(kind: CoreNotificationKind) -> Self {
+        match kind {
+            CoreNotificationKind::Message => Self::Message,
+            CoreNotificationKind::Mention => Self::Mention,
+            CoreNotificationKind::Reaction => Self::Reaction,
+            CoreNotificationKind::WebxdcNotify => Self::WebxdcNotify,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationItem {
+    /// Token to pass to `get_notification_items()`
+    /// to get only the items added after this one.
+    pub token: u32,
+    pub kind: NotificationKind,
+    pub chat_id: u32,
+    pub contact_id: u32,
+    pub msg_id: u32,
+    /// Text to show in the notification.
+    pub snippet: String,
+    pub timestamp: i64,
+}
+
+impl From<CoreNotificationItem> for NotificationItem {
+    fn from(item: CoreNotificationItem) -> Self {
+        Self {
+            token: item.token,
+            kind: item.kind.into(),
+            chat_id: item.chat_id.to_u32(),
+            contact_id: item.contact_id.to_u32(),
+            msg_id: item.msg_id.to_u32(),
+            snippet: item.snippet,
+            timestamp: item.timestamp,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedNotification {
+    /// Chat the message belongs to, if the chat exists already.
+    pub chat_id: Option<u32>,
+    /// Name of the chat, if known.
+    pub chat_name: Option<String>,
+    /// Contact who sent the message, if the contact exists already.
+    pub contact_id: Option<u32>,
+    pub sender_name: String,
+    pub sender_addr: String,
+    /// Text to show in the notification.
+    pub snippet: String,
+}
+
+impl From<CoreRenderedNotification> for RenderedNotification {
+    fn from(notification: CoreRenderedNotification) -> Self {
+        Self {
+            chat_id: notification.chat_id.map(|chat_id| chat_id.to_u32()),
+            chat_name: notification.chat_name,
+            contact_id: notification
+                .contact_id
+                .map(|contact_id| contact_id.to_u32()),
+            sender_name: notification.sender_name,
+            sender_addr: notification.sender_addr,
+            snippet: notification.snippet,
+        }
+    }
+}
@@ -95,6 +95,7 @@ describe("online tests", function () {
       chatIdOnAccountB,
       false,
       false,
+      null,
     );
 
     // There are 2 messages in the chat:
@@ -129,6 +130,7 @@ describe("online tests", function () {
       chatIdOnAccountB,
       false,
       false,
+      null,
     );
     const message = await dc.rpc.getMessage(
       accountId2,
@@ -142,7 +144,7 @@ describe("online tests", function () {
     await eventPromise2;
 
     const messageId = (
-      await dc.rpc.getMessageIds(accountId1, chatId, false, false)
+      await dc.rpc.getMessageIds(accountId1, chatId, false, false, null)
     ).reverse()[0];
     const message2 = await dc.rpc.getMessage(accountId1, messageId);
     expect(message2.text).equal("super secret message");
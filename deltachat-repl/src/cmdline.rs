@@ -622,6 +622,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                 sel_chat.get_id(),
                 chat::MessageListOptions {
                     add_daymarker: true,
+                    daymarker_timezone: chat::DaymarkerTimezone::Local,
                 },
             )
             .await?;
@@ -42,6 +42,7 @@ class EventType(str, Enum):
     IMAP_INBOX_IDLE = "ImapInboxIdle"
     NEW_BLOB_FILE = "NewBlobFile"
     DELETED_BLOB_FILE = "DeletedBlobFile"
+    BLOB_COPY_PROGRESS = "BlobCopyProgress"
     WARNING = "Warning"
     ERROR = "Error"
     ERROR_SELF_NOT_IN_GROUP = "ErrorSelfNotInGroup"
@@ -49,14 +50,19 @@ class EventType(str, Enum):
     REACTIONS_CHANGED = "ReactionsChanged"
     INCOMING_MSG = "IncomingMsg"
     INCOMING_MSG_BUNCH = "IncomingMsgBunch"
+    ATTACHMENT_WARNING = "AttachmentWarning"
     INCOMING_REACTION = "IncomingReaction"
+    INCOMING_ABUSE_REPORT = "IncomingAbuseReport"
     MSGS_NOTICED = "MsgsNoticed"
     MSG_DELIVERED = "MsgDelivered"
     MSG_FAILED = "MsgFailed"
     MSG_READ = "MsgRead"
     MSG_READ_COUNT_CHANGED = "MsgReadCountChanged"
     MSG_DELETED = "MsgDeleted"
+    BROADCAST_DELIVERY_UPDATE = "BroadcastDeliveryUpdate"
+    MSG_REMINDER = "MsgReminder"
     CHAT_MODIFIED = "ChatModified"
+    CHAT_UI_PROPERTY_CHANGED = "ChatUiPropertyChanged"
     CHAT_DELETED = "ChatDeleted"
     CHAT_EPHEMERAL_TIMER_MODIFIED = "ChatEphemeralTimerModified"
     CONTACTS_CHANGED = "ContactsChanged"
@@ -64,9 +70,13 @@ class EventType(str, Enum):
     CONFIGURE_PROGRESS = "ConfigureProgress"
     IMEX_PROGRESS = "ImexProgress"
     IMEX_FILE_WRITTEN = "ImexFileWritten"
+    FETCH_OLDER_MSGS_PROGRESS = "FetchOlderMsgsProgress"
+    HOUSEKEEPING_PROGRESS = "HousekeepingProgress"
     SECUREJOIN_INVITER_PROGRESS = "SecurejoinInviterProgress"
     SECUREJOIN_JOINER_PROGRESS = "SecurejoinJoinerProgress"
     CONNECTIVITY_CHANGED = "ConnectivityChanged"
+    PROXY_SWITCHED = "ProxySwitched"
+    CLOCK_SKEW_DETECTED = "ClockSkewDetected"
     SELFAVATAR_CHANGED = "SelfavatarChanged"
     WEBXDC_STATUS_UPDATE = "WebxdcStatusUpdate"
     WEBXDC_INSTANCE_DELETED = "WebxdcInstanceDeleted"
@@ -79,8 +89,12 @@ class EventType(str, Enum):
     OUTGOING_CALL_ACCEPTED = "OutgoingCallAccepted"
     CALL_ENDED = "CallEnded"
     CONFIG_SYNCED = "ConfigSynced"
+    CONFIG_CHANGED = "ConfigChanged"
+    KEY_BACKUP_CHANGED = "KeyBackupChanged"
     WEBXDC_REALTIME_DATA = "WebxdcRealtimeData"
     WEBXDC_REALTIME_ADVERTISEMENT_RECEIVED = "WebxdcRealtimeAdvertisementReceived"
+    WEBXDC_REALTIME_DATA_FORWARDED = "WebxdcRealtimeDataForwarded"
+    WEBXDC_REALTIME_DATA_EXPIRED = "WebxdcRealtimeDataExpired"
     TRANSPORTS_MODIFIED = "TransportsModified"
 
 
@@ -195,6 +209,7 @@ class MessageState(IntEnum):
     OUT_PENDING = 20
     OUT_FAILED = 24
     OUT_DELIVERED = 26
+    OUT_SERVER_RCVD = 27
     OUT_MDN_RCVD = 28
 
 
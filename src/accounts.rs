@@ -4,6 +4,7 @@
 use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 
 use anyhow::{Context as _, Result, bail, ensure};
 use async_channel::{self, Receiver, Sender};
@@ -22,7 +23,7 @@
 use tokio::time::{Duration, sleep};
 
 use crate::context::{Context, ContextBuilder};
-use crate::events::{Event, EventEmitter, EventType, Events};
+use crate::events::{Event, EventEmitter, EventKind, EventType, Events};
 use crate::location;
 use crate::log::warn;
 use crate::push::PushSubscriber;
@@ -203,6 +204,7 @@ pub async fn remove_account(&mut self, id: u32) -> Result<()> {
             .remove(&id)
             .with_context(|| format!("no account with id {id}"))?;
         ctx.stop_io().await;
+        self.push_subscriber.set_device_token_for(id, None).await;
 
         // Explicitly close the database
         // to make sure the database file is closed
@@ -231,6 +233,22 @@ pub async fn remove_account(&mut self, id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Wipes the account's data with [`Context::secure_wipe()`] and removes it.
+    ///
+    /// Files are overwritten and removed,
+    /// with no guarantee that the data can't be recovered from SSDs or flash storage.
+    ///
+    /// The account is removed even if wiping fails partially.
+    pub async fn secure_wipe_account(&mut self, id: u32) -> Result<()> {
+        let ctx = self
+            .accounts
+            .get(&id)
+            .with_context(|| format!("no account with id {id}"))?;
+        let res = ctx.secure_wipe().await;
+        self.remove_account(id).await?;
+        res
+    }
+
     /// Migrates an existing account into this structure.
     ///
     /// Returns the ID of new account.
@@ -487,6 +505,46 @@ pub fn background_fetch(
         timeout: std::time::Duration,
     ) -> impl Future<Output = ()> + use<> {
         let accounts: Vec<Context> = self.accounts.values().cloned().collect();
+        self.background_fetch_accounts(accounts, timeout)
+    }
+
+    /// Handles a message received from the UnifiedPush distributor
+    /// for the account with the given ID.
+    ///
+    /// The message is decrypted to make sure it was sent by the chatmail server,
+    /// then background fetch is performed for this account only.
+    /// Background fetch behaves the same way as [`Accounts::background_fetch`]
+    /// and `AccountsBackgroundFetchDone` event is emitted at the end.
+    ///
+    /// Returns an error if the message cannot be decrypted,
+    /// otherwise returns a future that resolves when background fetch is done.
+    pub async fn handle_unified_push(
+        &self,
+        id: u32,
+        message: &[u8],
+        timeout: std::time::Duration,
+    ) -> Result<impl Future<Output = ()> + use<>> {
+        let ctx = self
+            .get_account(id)
+            .with_context(|| format!("no account with id {id}"))?;
+        let payload = ctx
+            .decrypt_unified_push_message(message)
+            .await
+            .context("Failed to decrypt UnifiedPush message")?;
+        info!(
+            ctx,
+            "Received UnifiedPush message with {} bytes payload.",
+            payload.len()
+        );
+        Ok(self.background_fetch_accounts(vec![ctx], timeout))
+    }
+
+    /// Performs a background fetch for the given accounts with a timeout.
+    fn background_fetch_accounts(
+        &self,
+        accounts: Vec<Context>,
+        timeout: std::time::Duration,
+    ) -> impl Future<Output = ()> + use<> {
         let events = self.events.clone();
         let (sender, receiver) = async_channel::bounded(1);
         let receiver = {
@@ -533,12 +591,42 @@ pub fn get_event_emitter(&self) -> EventEmitter {
         self.events.get_emitter()
     }
 
+    /// Returns event emitter which first receives up to `n` events
+    /// emitted before its creation.
+    pub fn get_event_emitter_with_replay(&self, n: usize) -> EventEmitter {
+        self.events.get_emitter_with_replay(n)
+    }
+
+    /// Returns event emitter receiving only the events of the given kinds
+    /// from all accounts.
+    pub fn subscribe_events(&self, filter: impl IntoIterator<Item = EventKind>) -> EventEmitter {
+        self.events.subscribe(filter)
+    }
+
     /// Sets notification token for Apple Push Notification service.
     pub async fn set_push_device_token(&self, token: &str) -> Result<()> {
         self.push_subscriber.set_device_token(token).await;
         Ok(())
     }
 
+    /// Sets notification token used only by the account with the given ID
+    /// instead of the token set with [`Accounts::set_push_device_token`].
+    ///
+    /// This allows applications running a process per account
+    /// to use a separate token or topic for each account,
+    /// so that notification servers wake up only the process of the relevant account.
+    /// `None` removes the token of the account.
+    pub async fn set_push_device_token_for(&self, id: u32, token: Option<&str>) -> Result<()> {
+        let ctx = self
+            .accounts
+            .get(&id)
+            .with_context(|| format!("no account with id {id}"))?;
+        self.push_subscriber.set_device_token_for(id, token).await;
+        // Register the new token on the server.
+        ctx.push_subscribed.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
     /// Sets location for all accounts.
     ///
     /// Returns true if location should still be streamed.
@@ -988,6 +1076,29 @@ async fn test_account_new_empty_existing_dir() {
         assert_eq!(id, 1);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_secure_wipe_account() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p: PathBuf = dir.path().join("accounts");
+        let mut accounts = Accounts::new(p.clone(), true).await?;
+        let id = accounts.add_account().await?;
+        let ctx = accounts.get_account(id).unwrap();
+        ctx.set_config(crate::config::Config::Displayname, Some("Secret"))
+            .await?;
+        let blob = ctx.get_blobdir().join("secret.txt");
+        fs::write(&blob, b"secret").await?;
+        let dbfile = ctx.sql.dbfile.clone();
+        assert!(dbfile.exists());
+
+        accounts.secure_wipe_account(id).await?;
+        assert!(!dbfile.exists());
+        assert!(!blob.exists());
+        assert!(accounts.get_account(id).is_none());
+        assert!(accounts.get_all().is_empty());
+        assert!(accounts.secure_wipe_account(id).await.is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_account_new_open_conflict() {
         let dir = tempfile::tempdir().unwrap();
@@ -1054,6 +1165,46 @@ async fn test_accounts_remove_last() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_set_push_device_token_for() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p: PathBuf = dir.path().join("accounts");
+
+        let writable = true;
+        let mut accounts = Accounts::new(p.clone(), writable).await?;
+        let id1 = accounts.add_account().await?;
+        let id2 = accounts.add_account().await?;
+        let ctx1 = accounts.get_account(id1).unwrap();
+        let ctx2 = accounts.get_account(id2).unwrap();
+
+        accounts.set_push_device_token("shared-token").await?;
+        accounts
+            .set_push_device_token_for(id2, Some("account-token"))
+            .await?;
+        assert_eq!(
+            ctx1.push_subscriber.device_token(ctx1.id).await.unwrap(),
+            "shared-token"
+        );
+        assert_eq!(
+            ctx2.push_subscriber.device_token(ctx2.id).await.unwrap(),
+            "account-token"
+        );
+        assert!(
+            accounts
+                .set_push_device_token_for(id2 + 1, Some("token"))
+                .await
+                .is_err()
+        );
+
+        accounts.remove_account(id2).await?;
+        assert_eq!(
+            ctx2.push_subscriber.device_token(ctx2.id).await.unwrap(),
+            "shared-token"
+        );
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_migrate_account() {
         let dir = tempfile::tempdir().unwrap();
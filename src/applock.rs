@@ -0,0 +1,131 @@
+//! # App-lock PIN.
+//!
+//! The PIN is stored as an Argon2 hash,
+//! so that UIs don't need to store it in plaintext configs.
+//! It is independent of the database passphrase
+//! and only meant to lock the UI.
+
+use anyhow::{Context as _, Result, bail};
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::tools::time;
+
+/// Number of wrong PINs accepted without delay.
+const FREE_ATTEMPTS: u32 = 5;
+
+/// Delay after the first wrong PIN exceeding [`FREE_ATTEMPTS`], doubled for every further one.
+const BASE_DELAY: i64 = 30;
+
+/// Maximum delay between PIN attempts.
+const MAX_DELAY: i64 = 60 * 60;
+
+impl Context {
+    /// Sets the app-lock PIN, `None` removes it.
+    ///
+    /// The PIN is hashed with Argon2, the plaintext is not stored.
+    pub async fn set_applock_pin(&self, pin: Option<&str>) -> Result<()> {
+        let hash = match pin {
+            Some(pin) => {
+                if pin.is_empty() {
+                    bail!("App-lock PIN must not be empty");
+                }
+                let pin = pin.to_string();
+                let hash = tokio::task::spawn_blocking(move || hash_pin(&pin)).await??;
+                Some(hash)
+            }
+            None => None,
+        };
+        let _lock = self.applock_mutex.lock().await;
+        self.set_config_internal(Config::ApplockPinHash, hash.as_deref())
+            .await?;
+        self.set_config_internal(Config::ApplockFailedAttempts, None)
+            .await?;
+        self.set_config_internal(Config::ApplockLastFailedAttempt, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns true if an app-lock PIN is set.
+    pub async fn is_applock_enabled(&self) -> Result<bool> {
+        Ok(self.get_config(Config::ApplockPinHash).await?.is_some())
+    }
+
+    /// Checks the app-lock PIN.
+    ///
+    /// Returns `Ok(false)` for a wrong PIN or if no PIN is set.
+    /// After several wrong PINs, further attempts fail with an error
+    /// until an increasing delay has passed, this also applies to the correct PIN.
+    pub async fn verify_applock_pin(&self, pin: &str) -> Result<bool> {
+        // Checking the delay and counting the attempt must not be interleaved
+        // with other attempts.
+        let _lock = self.applock_mutex.lock().await;
+        let Some(hash) = self.get_config(Config::ApplockPinHash).await? else {
+            return Ok(false);
+        };
+        let failed_attempts = self.get_config_u32(Config::ApplockFailedAttempts).await?;
+        let last_failed_attempt = self
+            .get_config_i64(Config::ApplockLastFailedAttempt)
+            .await?;
+        let retry_at = last_failed_attempt.saturating_add(retry_delay(failed_attempts));
+        let now = time();
+        if now < retry_at {
+            bail!(
+                "Too many wrong app-lock PINs, retry in {} seconds",
+                retry_at.saturating_sub(now)
+            );
+        }
+
+        let pin = pin.to_string();
+        let valid = tokio::task::spawn_blocking(move || verify_pin(&pin, &hash)).await??;
+        if valid {
+            if failed_attempts > 0 {
+                self.set_config_internal(Config::ApplockFailedAttempts, None)
+                    .await?;
+                self.set_config_internal(Config::ApplockLastFailedAttempt, None)
+                    .await?;
+            }
+        } else {
+            info!(self, "Wrong app-lock PIN.");
+            let failed_attempts = failed_attempts.saturating_add(1).to_string();
+            self.set_config_internal(Config::ApplockFailedAttempts, Some(&failed_attempts))
+                .await?;
+            self.set_config_internal(Config::ApplockLastFailedAttempt, Some(&now.to_string()))
+                .await?;
+        }
+        Ok(valid)
+    }
+}
+
+/// Returns the delay in seconds required after the last wrong PIN.
+fn retry_delay(failed_attempts: u32) -> i64 {
+    match failed_attempts.checked_sub(FREE_ATTEMPTS) {
+        None => 0,
+        Some(exceeding) => BASE_DELAY
+            .saturating_mul(2_i64.saturating_pow(exceeding))
+            .min(MAX_DELAY),
+    }
+}
+
+fn hash_pin(pin: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|err| anyhow::anyhow!("Cannot encode salt: {err}"))?;
+    let hash = Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map_err(|err| anyhow::anyhow!("Cannot hash PIN: {err}"))?;
+    Ok(hash.to_string())
+}
+
+fn verify_pin(pin: &str, hash: &str) -> Result<bool> {
+    let hash = PasswordHash::new(hash)
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .context("Invalid app-lock PIN hash")?;
+    Ok(Argon2::default()
+        .verify_password(pin.as_bytes(), &hash)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod applock_tests;
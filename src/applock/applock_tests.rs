@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use super::*;
+use crate::test_utils::TestContext;
+use crate::tools::SystemTime;
+
+#[test]
+fn test_retry_delay() {
+    assert_eq!(retry_delay(0), 0);
+    assert_eq!(retry_delay(FREE_ATTEMPTS - 1), 0);
+    assert_eq!(retry_delay(FREE_ATTEMPTS), BASE_DELAY);
+    assert_eq!(retry_delay(FREE_ATTEMPTS + 1), 2 * BASE_DELAY);
+    assert_eq!(retry_delay(FREE_ATTEMPTS + 100), MAX_DELAY);
+    assert_eq!(retry_delay(u32::MAX), MAX_DELAY);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_applock_pin() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    assert!(!t.is_applock_enabled().await?);
+    assert!(!t.verify_applock_pin("1234").await?);
+
+    t.set_applock_pin(Some("1234")).await?;
+    assert!(t.is_applock_enabled().await?);
+    let hash = t.get_config(Config::ApplockPinHash).await?.unwrap();
+    assert!(hash.starts_with("$argon2"));
+    assert!(!hash.contains("1234"));
+
+    assert!(t.verify_applock_pin("1234").await?);
+    for _ in 0..FREE_ATTEMPTS {
+        assert!(!t.verify_applock_pin("0000").await?);
+    }
+    assert_eq!(
+        t.get_config_u32(Config::ApplockFailedAttempts).await?,
+        FREE_ATTEMPTS
+    );
+    // Even the correct PIN is rejected until the delay has passed.
+    assert!(t.verify_applock_pin("1234").await.is_err());
+
+    SystemTime::shift(Duration::from_secs(BASE_DELAY as u64));
+    assert!(t.verify_applock_pin("1234").await?);
+    assert_eq!(t.get_config_u32(Config::ApplockFailedAttempts).await?, 0);
+
+    t.set_applock_pin(None).await?;
+    assert!(!t.is_applock_enabled().await?);
+    assert!(t.set_applock_pin(Some("")).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_applock_config_protected() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    t.set_applock_pin(Some("1234")).await?;
+    for key in [
+        Config::ApplockPinHash,
+        Config::ApplockFailedAttempts,
+        Config::ApplockLastFailedAttempt,
+    ] {
+        assert!(t.set_config(key, None).await.is_err());
+    }
+    assert!(t.is_applock_enabled().await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_applock_concurrent_attempts() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    t.set_applock_pin(Some("1234")).await?;
+
+    let attempts = (0..FREE_ATTEMPTS + 3).map(|_| t.verify_applock_pin("0000"));
+    let results = futures::future::join_all(attempts).await;
+    let wrong = results
+        .iter()
+        .filter(|res| matches!(res, Ok(false)))
+        .count();
+    assert_eq!(wrong, FREE_ATTEMPTS as usize);
+    assert_eq!(
+        t.get_config_u32(Config::ApplockFailedAttempts).await?,
+        FREE_ATTEMPTS
+    );
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! # Introspection of archive attachments.
+//!
+//! Lists the files contained in zip and tar attachments without extracting them,
+//! so that UIs can show what is inside before the user saves a potentially unwanted archive.
+
+use anyhow::{Context as _, Result, bail};
+use async_zip::tokio::read::seek::ZipFileReader;
+use deltachat_contact_tools::sanitize_bidi_characters;
+use futures::TryStreamExt;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio_tar::Archive;
+
+use crate::context::Context;
+use crate::message::{Message, MsgId};
+
+/// Maximum number of entries returned by [`MsgId::list_archive_contents`].
+pub const MAX_ARCHIVE_ENTRIES: usize = 1000;
+
+/// Maximum length of an entry name in bytes, longer names are truncated.
+const MAX_NAME_BYTES: usize = 1000;
+
+/// Offset of the `ustar` magic in a tar header.
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// File contained in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file inside the archive.
+    pub name: String,
+
+    /// Uncompressed size of the file in bytes as declared by the archive.
+    pub size: u64,
+}
+
+impl MsgId {
+    /// Lists the files contained in a zip or tar attachment of the message.
+    ///
+    /// Nothing is extracted, sizes are taken from the archive headers.
+    /// Directories are not listed and at most [`MAX_ARCHIVE_ENTRIES`] entries are returned.
+    /// Fails if the attachment is not a supported archive.
+    pub async fn list_archive_contents(self, context: &Context) -> Result<Vec<ArchiveEntry>> {
+        let msg = Message::load_from_db(context, self).await?;
+        let path = msg.get_file(context).context("Message has no attachment")?;
+        let mut file = File::open(&path).await?;
+
+        let mut header = [0; TAR_MAGIC_OFFSET + 5];
+        let header_len = read_prefix(&mut file, &mut header).await?;
+        file.rewind().await?;
+        let header = header.get(..header_len).unwrap_or_default();
+
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            list_zip_entries(file).await
+        } else if header.get(TAR_MAGIC_OFFSET..) == Some(b"ustar") {
+            list_tar_entries(file).await
+        } else {
+            bail!("Attachment is not a zip or tar archive");
+        }
+    }
+}
+
+/// Reads as much of the beginning of the file as fits into `buf`.
+async fn read_prefix(file: &mut File, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while let Some(rest) = buf.get_mut(len..) {
+        let n = file.read(rest).await?;
+        if n == 0 {
+            break;
+        }
+        len = len.saturating_add(n);
+    }
+    Ok(len)
+}
+
+async fn list_zip_entries(file: File) -> Result<Vec<ArchiveEntry>> {
+    let archive = ZipFileReader::with_tokio(BufReader::new(file))
+        .await
+        .context("Cannot read zip archive")?;
+    let entries = archive
+        .file()
+        .entries()
+        .iter()
+        .filter(|entry| !entry.filename().as_bytes().ends_with(b"/"))
+        .take(MAX_ARCHIVE_ENTRIES)
+        .map(|entry| ArchiveEntry {
+            name: entry_name(entry.filename().as_bytes()),
+            size: entry.uncompressed_size(),
+        })
+        .collect();
+    Ok(entries)
+}
+
+async fn list_tar_entries(file: File) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = Archive::new(BufReader::new(file));
+    let mut tar_entries = archive.entries().context("Cannot read tar archive")?;
+    let mut entries = Vec::new();
+    while entries.len() < MAX_ARCHIVE_ENTRIES {
+        let Some(entry) = tar_entries.try_next().await? else {
+            break;
+        };
+        let header = entry.header();
+        if !header.entry_type().is_file() {
+            continue;
+        }
+        entries.push(ArchiveEntry {
+            name: entry_name(&entry.path_bytes()?),
+            size: header.size()?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Converts a raw entry name into a displayable string of bounded length.
+fn entry_name(raw: &[u8]) -> String {
+    let raw = raw.get(..MAX_NAME_BYTES).unwrap_or(raw);
+    sanitize_bidi_characters(&String::from_utf8_lossy(raw))
+}
+
+#[cfg(test)]
+mod archive_tests;
@@ -0,0 +1,111 @@
+use async_zip::tokio::write::ZipFileWriter;
+use async_zip::{Compression, ZipEntryBuilder};
+use futures::io::Cursor as FuturesCursor;
+use tokio_util::compat::FuturesAsyncWriteCompatExt;
+
+use super::*;
+use crate::message::Viewtype;
+use crate::test_utils::{TestContext, TestContextManager};
+
+async fn receive_file(alice: &TestContext, bob: &TestContext, name: &str, data: &[u8]) -> MsgId {
+    let chat = alice.create_chat(bob).await;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(alice, name, data, None).unwrap();
+    let sent = alice.send_msg(chat.id, &mut msg).await;
+    bob.recv_msg(&sent).await.id
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_list_zip_contents() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let mut buffer = FuturesCursor::new(Vec::new()).compat_write();
+    let mut writer = ZipFileWriter::with_tokio(&mut buffer);
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new("docs/".into(), Compression::Stored),
+            &[],
+        )
+        .await?;
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new("docs/readme.txt".into(), Compression::Deflate),
+            &[b'a'; 5000],
+        )
+        .await?;
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new("setup.exe".into(), Compression::Stored),
+            b"MZ",
+        )
+        .await?;
+    writer.close().await?;
+    let zip = buffer.into_inner().into_inner();
+
+    let msg_id = receive_file(alice, bob, "files.zip", &zip).await;
+    assert_eq!(
+        msg_id.list_archive_contents(bob).await?,
+        vec![
+            ArchiveEntry {
+                name: "docs/readme.txt".to_string(),
+                size: 5000,
+            },
+            ArchiveEntry {
+                name: "setup.exe".to_string(),
+                size: 2,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_list_tar_contents() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let mut builder = tokio_tar::Builder::new(Vec::new());
+    let mut header = tokio_tar::Header::new_gnu();
+    header.set_size(3);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "a.txt", &b"abc"[..])
+        .await?;
+    let entries = (0..MAX_ARCHIVE_ENTRIES + 5).map(|i| format!("{i}.txt"));
+    for name in entries {
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_data(&mut header, name, &[][..]).await?;
+    }
+    let tar = builder.into_inner().await?;
+
+    let msg_id = receive_file(alice, bob, "files.tar", &tar).await;
+    let entries = msg_id.list_archive_contents(bob).await?;
+    assert_eq!(entries.len(), MAX_ARCHIVE_ENTRIES);
+    assert_eq!(
+        entries[0],
+        ArchiveEntry {
+            name: "a.txt".to_string(),
+            size: 3,
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_list_non_archive_contents() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let msg_id = receive_file(alice, bob, "files.zip", b"not a zip").await;
+    assert!(msg_id.list_archive_contents(bob).await.is_err());
+
+    let msg = tcm.send_recv_accept(alice, bob, "hi").await;
+    assert!(msg.id.list_archive_contents(bob).await.is_err());
+    Ok(())
+}
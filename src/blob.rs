@@ -1,12 +1,13 @@
 //! # Blob directory management.
 
 use std::cmp::{max, min};
-use std::io::{Cursor, Seek};
+use std::io::{Cursor, Read, Seek, Write};
 use std::iter::FusedIterator;
 use std::mem;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context as _, Result, ensure, format_err};
+use async_channel as channel;
 use base64::Engine as _;
 use futures::StreamExt;
 use image::ImageReader;
@@ -17,11 +18,15 @@
 use tokio_stream::wrappers::ReadDirStream;
 
 use crate::config::Config;
-use crate::constants::{self, MediaQuality};
+use crate::constants::{self, DC_CHAT_ID_TRASH, MediaQuality};
 use crate::context::Context;
+use crate::download::{self, DownloadState};
 use crate::events::EventType;
+use crate::imap::Imap;
+use crate::imap::session::Session;
 use crate::log::{LogExt, warn};
-use crate::message::Viewtype;
+use crate::message::{Message, MsgId, Viewtype};
+use crate::param::{Param, Params};
 use crate::tools::sanitize_filename;
 
 /// Represents a file in the blob directory.
@@ -40,6 +45,12 @@ pub struct BlobObject<'a> {
     name: String,
 }
 
+/// Maximum width and height of micro-thumbnails, see [`create_micro_thumbnail`].
+const MICRO_THUMBNAIL_SIZE: u32 = 32;
+
+/// Files copied into the blobdir from this size on emit [`EventType::BlobCopyProgress`].
+const COPY_PROGRESS_MIN_SIZE: u64 = 1024 * 1024;
+
 #[derive(Debug, Clone)]
 enum ImageOutputFormat {
     Png,
@@ -57,6 +68,9 @@ impl<'a> BlobObject<'a> {
     ///
     /// This is done in a in way which avoids race-conditions when multiple files are
     /// concurrently created.
+    ///
+    /// Files outside the blobdir are hashed while they are copied, so they are only read once.
+    /// For large files, [`EventType::BlobCopyProgress`] events are emitted during the copy.
     pub fn create_and_deduplicate(
         context: &'a Context,
         src: &Path,
@@ -69,49 +83,55 @@ pub fn create_and_deduplicate(
         task::block_in_place(|| {
             let temp_path;
             let src_in_blobdir: &Path;
+            let hash;
             let blobdir = context.get_blobdir();
 
             if src.starts_with(blobdir) {
                 src_in_blobdir = src;
+                hash = file_hash(src_in_blobdir)?;
             } else {
                 info!(
                     context,
                     "Source file not in blobdir. Copying instead of moving in order to prevent moving a file that was still needed."
                 );
                 temp_path = blobdir.join(format!("tmp-{}", rand::random::<u64>()));
-                if std::fs::copy(src, &temp_path).is_err() {
-                    // Maybe the blobdir didn't exist
-                    std::fs::create_dir_all(blobdir).log_err(context).ok();
-                    std::fs::copy(src, &temp_path).context("Copying new blobfile failed")?;
+                hash = match copy_and_hash(context, src, &temp_path) {
+                    Ok(hash) => hash,
+                    Err(_) => {
+                        // Maybe the blobdir didn't exist
+                        std::fs::create_dir_all(blobdir).log_err(context).ok();
+                        copy_and_hash(context, src, &temp_path)
+                            .context("Copying new blobfile failed")?
+                    }
                 };
                 src_in_blobdir = &temp_path;
             }
+            BlobObject::rename_to_hash_name(context, src_in_blobdir, hash, original_name)
+        })
+    }
 
-            let hash = file_hash(src_in_blobdir)?.to_hex();
-            let hash = hash.as_str();
-            let hash = hash.get(0..31).unwrap_or(hash);
-            let new_file =
-                if let Some(extension) = original_name.extension().filter(|e| e.len() <= 32) {
-                    let extension = extension.to_string_lossy().to_lowercase();
-                    let extension = sanitize_filename(&extension);
-                    format!("$BLOBDIR/{hash}.{extension}")
-                } else {
-                    format!("$BLOBDIR/{hash}")
-                };
-
-            let blob = BlobObject {
-                blobdir,
-                name: new_file,
-            };
-            let new_path = blob.to_abs_path();
+    /// Moves a file that is already in the blobdir to `<hash>.<extension>`.
+    pub(crate) fn rename_to_hash_name(
+        context: &'a Context,
+        src_in_blobdir: &Path,
+        hash: blake3::Hash,
+        original_name: &Path,
+    ) -> Result<BlobObject<'a>> {
+        let hash = hash.to_hex();
+        let hash = hash.as_str();
+        let hash = hash.get(0..31).unwrap_or(hash);
+        let blob = BlobObject {
+            blobdir: context.get_blobdir(),
+            name: hash_name(hash, original_name),
+        };
+        let new_path = blob.to_abs_path();
 
-            // This will also replace an already-existing file.
-            // Renaming is atomic, so this will avoid race conditions.
-            std::fs::rename(src_in_blobdir, &new_path)?;
+        // This will also replace an already-existing file.
+        // Renaming is atomic, so this will avoid race conditions.
+        std::fs::rename(src_in_blobdir, &new_path)?;
 
-            context.emit_event(EventType::NewBlobFile(blob.as_name().to_string()));
-            Ok(blob)
-        })
+        context.emit_event(EventType::NewBlobFile(blob.as_name().to_string()));
+        Ok(blob)
     }
 
     /// Creates a new blob object with the file contents in `data`.
@@ -137,7 +157,8 @@ pub fn create_and_deduplicate_from_bytes(
                 std::fs::write(&temp_path, data).context("writing new blobfile failed")?;
             };
 
-            BlobObject::create_and_deduplicate(context, &temp_path, Path::new(original_name))
+            let hash = blake3::hash(data);
+            BlobObject::rename_to_hash_name(context, &temp_path, hash, Path::new(original_name))
         })
     }
 
@@ -178,6 +199,29 @@ pub fn from_name(context: &'a Context, name: &str) -> Result<BlobObject<'a>> {
         })
     }
 
+    /// Returns a [BlobObject] for an existing blob named `<hash>.<extension>`,
+    /// as created by [BlobObject::create_and_deduplicate].
+    ///
+    /// The file is not read, its content is trusted to match the hash in the name.
+    /// This avoids hashing big files again when they are resent or forwarded.
+    /// Fails if the name does not contain a hash or the file does not exist.
+    pub fn from_trusted_name(context: &'a Context, name: &str) -> Result<BlobObject<'a>> {
+        let blob = BlobObject::from_name(context, name)?;
+        ensure!(
+            blob.name_hash().is_some(),
+            "Blob name {name:?} is not a hash"
+        );
+        ensure!(blob.to_abs_path().is_file(), "Blob {name:?} does not exist");
+        Ok(blob)
+    }
+
+    /// Returns true if [BlobObject::create_and_deduplicate] would give the blob its current name
+    /// when called with `original_name`, i.e. the extension matches.
+    pub(crate) fn is_hash_named_for(&self, original_name: &Path) -> bool {
+        self.name_hash()
+            .is_some_and(|hash| self.name == hash_name(hash, original_name))
+    }
+
     /// Returns the absolute path to the blob in the filesystem.
     pub fn to_abs_path(&self) -> PathBuf {
         let fname = Path::new(&self.name).strip_prefix("$BLOBDIR/").unwrap();
@@ -209,6 +253,21 @@ pub fn suffix(&self) -> Option<&str> {
         if ext == Some(&self.name) { None } else { ext }
     }
 
+    /// Returns the content hash encoded in the blob name.
+    ///
+    /// Deduplicated blobs are named `<hash>.<extension>`,
+    /// where `<hash>` is the first 31 hex digits of the BLAKE3 hash of the content.
+    /// Returns `None` for blobs created before deduplication was introduced.
+    fn name_hash(&self) -> Option<&str> {
+        let name = self.name.strip_prefix("$BLOBDIR/")?;
+        let stem = name.split('.').next().unwrap_or(name);
+        if stem.len() == 31 && stem.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            Some(stem)
+        } else {
+            None
+        }
+    }
+
     /// Checks whether a name is a valid blob name.
     ///
     /// This is slightly less strict than stanitise_name, presumably
@@ -290,16 +349,7 @@ pub async fn check_or_recode_image(
         name: Option<String>,
         viewtype: &mut Viewtype,
     ) -> Result<String> {
-        let (max_wh, max_bytes) =
-            match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
-                .unwrap_or_default()
-            {
-                MediaQuality::Balanced => (
-                    constants::BALANCED_IMAGE_SIZE,
-                    constants::BALANCED_IMAGE_BYTES,
-                ),
-                MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
-            };
+        let (max_wh, max_bytes) = image_limits(context).await?;
         let is_avatar = false;
         self.check_or_recode_to_size(context, name, viewtype, max_wh, max_bytes, is_avatar)
     }
@@ -541,7 +591,181 @@ fn check_or_recode_to_size(
     }
 }
 
-fn file_hash(src: &Path) -> Result<blake3::Hash> {
+/// Problem found for a blob during [`Context::verify_blobs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobIssueKind {
+    /// The file does not exist in the blobdir.
+    Missing,
+
+    /// The file content does not match the hash encoded in the blob name.
+    Corrupted,
+
+    /// The file exists but cannot be read.
+    Unreadable,
+}
+
+/// Message with a damaged attachment, as reported by [`Context::verify_blobs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobIssue {
+    /// ID of the message referencing the blob.
+    pub msg_id: MsgId,
+
+    /// Blob name in the `$BLOBDIR/<name>` format.
+    pub blob_name: String,
+
+    /// What is wrong with the blob.
+    pub kind: BlobIssueKind,
+
+    /// Whether the attachment was downloaded from the server again
+    /// or, for messages sent as a Post-Message, scheduled for download.
+    pub redownloaded: bool,
+}
+
+/// Result of [`Context::verify_blobs`].
+#[derive(Debug, Clone, Default)]
+pub struct BlobVerification {
+    /// Number of message attachments checked.
+    pub checked: usize,
+
+    /// Attachments that are missing or corrupted.
+    pub issues: Vec<BlobIssue>,
+}
+
+impl Context {
+    /// Verifies that the files referenced by messages exist in the blobdir
+    /// and that their content still matches the hash encoded in the blob name.
+    ///
+    /// This detects bit rot and files damaged by sync tools.
+    /// Blobs with names that do not contain a hash are only checked for existence.
+    ///
+    /// If `redownload` is true, damaged attachments of messages
+    /// still available on the server are downloaded again.
+    /// Post-Messages are scheduled for download,
+    /// attachments of other messages are restored using [`MsgId::redownload`].
+    pub async fn verify_blobs(&self, redownload: bool) -> Result<BlobVerification> {
+        let rows = self
+            .sql
+            .query_map_vec(
+                "SELECT id, param, pre_rfc724_mid FROM msgs
+                 WHERE chat_id!=? AND type!=? AND download_state=?",
+                (DC_CHAT_ID_TRASH, Viewtype::Text, DownloadState::Done),
+                |row| {
+                    let msg_id: MsgId = row.get(0)?;
+                    let param: String = row.get(1)?;
+                    let pre_rfc724_mid: String = row.get(2)?;
+                    Ok((msg_id, param, pre_rfc724_mid))
+                },
+            )
+            .await
+            .context("Failed to load messages with attachments")?;
+
+        let mut report = BlobVerification::default();
+        // Connection used to restore attachments, created on first use.
+        let mut session = None;
+        for (msg_id, param, pre_rfc724_mid) in rows {
+            let param: Params = param.parse().unwrap_or_default();
+            let Some(blob_name) = param.get(Param::File) else {
+                continue;
+            };
+            let Ok(blob) = BlobObject::from_name(self, blob_name) else {
+                continue;
+            };
+            report.checked = report.checked.saturating_add(1);
+
+            let path = blob.to_abs_path();
+            let kind = if !path.exists() {
+                BlobIssueKind::Missing
+            } else if let Some(expected) = blob.name_hash() {
+                match task::block_in_place(|| file_hash(&path)) {
+                    Ok(hash) if hash.to_hex().starts_with(expected) => continue,
+                    Ok(_) => BlobIssueKind::Corrupted,
+                    Err(err) => {
+                        warn!(self, "Failed to hash blob {}: {err:#}.", path.display());
+                        BlobIssueKind::Unreadable
+                    }
+                }
+            } else {
+                continue;
+            };
+            warn!(self, "Blob {blob_name} of {msg_id} is {kind:?}.");
+
+            let redownloaded = if !redownload {
+                false
+            } else if pre_rfc724_mid.is_empty() {
+                self.redownload_blob(msg_id, &mut session).await
+            } else {
+                self.schedule_blob_redownload(msg_id).await?
+            };
+            report.issues.push(BlobIssue {
+                msg_id,
+                blob_name: blob_name.to_string(),
+                kind,
+                redownloaded,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Downloads the message `msg_id` from the server again
+    /// and restores its attachment.
+    ///
+    /// The connection is stored in `session` on first use.
+    /// Returns `false` if the attachment could not be restored.
+    async fn redownload_blob(&self, msg_id: MsgId, session: &mut Option<Session>) -> bool {
+        let res = async {
+            if !self.is_on_server(msg_id).await? {
+                return Ok(false);
+            }
+            let session = match session {
+                Some(session) => session,
+                None => {
+                    let mut imap = Imap::new_configured(self, channel::bounded(1).1).await?;
+                    session.insert(imap.prepare(self).await?)
+                }
+            };
+            let mut msg = Message::load_from_db(self, msg_id).await?;
+            download::redownload_attachment(self, session, &mut msg).await?;
+            Ok::<_, anyhow::Error>(true)
+        }
+        .await;
+        match res {
+            Ok(redownloaded) => redownloaded,
+            Err(err) => {
+                warn!(
+                    self,
+                    "Failed to download attachment of {msg_id} again: {err:#}."
+                );
+                false
+            }
+        }
+    }
+
+    /// Returns true if the message `msg_id` is still on the server.
+    async fn is_on_server(&self, msg_id: MsgId) -> Result<bool> {
+        self.sql
+            .exists(
+                "SELECT COUNT(*) FROM imap
+                 WHERE rfc724_mid=(SELECT rfc724_mid FROM msgs WHERE id=?) AND target!=''",
+                (msg_id,),
+            )
+            .await
+    }
+
+    /// Schedules download of the Post-Message for `msg_id`
+    /// if it is still on the server.
+    async fn schedule_blob_redownload(&self, msg_id: MsgId) -> Result<bool> {
+        if !self.is_on_server(msg_id).await? {
+            return Ok(false);
+        }
+        msg_id
+            .update_download_state(self, DownloadState::Available)
+            .await?;
+        msg_id.download_full(self).await?;
+        Ok(true)
+    }
+}
+
+pub(crate) fn file_hash(src: &Path) -> Result<blake3::Hash> {
     ensure!(
         !src.starts_with("$BLOBDIR/"),
         "Use `get_abs_path()` to get the absolute path of the blobfile"
@@ -556,6 +780,84 @@ fn file_hash(src: &Path) -> Result<blake3::Hash> {
     Ok(hash)
 }
 
+/// Returns the blob name `$BLOBDIR/<hash>.<extension>`,
+/// with the extension taken from `original_name`.
+fn hash_name(hash: &str, original_name: &Path) -> String {
+    if let Some(extension) = original_name.extension().filter(|e| e.len() <= 32) {
+        let extension = extension.to_string_lossy().to_lowercase();
+        let extension = sanitize_filename(&extension);
+        format!("$BLOBDIR/{hash}.{extension}")
+    } else {
+        format!("$BLOBDIR/{hash}")
+    }
+}
+
+/// Copies `src` to `dst` and returns the hash of the copied data,
+/// so that the data does not need to be read again for hashing.
+///
+/// Emits [`EventType::BlobCopyProgress`] if the file is large.
+fn copy_and_hash(context: &Context, src: &Path, dst: &Path) -> Result<blake3::Hash> {
+    let mut src_file = std::fs::File::open(src)
+        .with_context(|| format!("Failed to open file {}", src.display()))?;
+    let len = src_file.metadata()?.len();
+    let mut dst_file = std::fs::File::create(dst)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut copied: u64 = 0;
+    let mut last_progress: u64 = 0;
+    let path = src.to_string_lossy();
+    loop {
+        let n = match src_file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let data = buf.get(..n).context("Read more than the buffer size")?;
+        hasher.update(data);
+        dst_file.write_all(data)?;
+
+        copied = copied.saturating_add(u64::try_from(n)?);
+        if len >= COPY_PROGRESS_MIN_SIZE {
+            let progress = copied
+                .saturating_mul(1000)
+                .checked_div(len)
+                .unwrap_or_default()
+                .min(1000);
+            // Emit an event every 1%.
+            if progress >= last_progress.saturating_add(10) {
+                last_progress = progress;
+                context.emit_event(EventType::BlobCopyProgress {
+                    path: path.to_string(),
+                    progress: u16::try_from(progress)?,
+                });
+            }
+        }
+    }
+    if len >= COPY_PROGRESS_MIN_SIZE && last_progress < 1000 {
+        context.emit_event(EventType::BlobCopyProgress {
+            path: path.to_string(),
+            progress: 1000,
+        });
+    }
+    Ok(hasher.finalize())
+}
+
+/// Returns the maximum width/height and byte size of images
+/// for the configured [`Config::MediaQuality`].
+pub(crate) async fn image_limits(context: &Context) -> Result<(u32, usize)> {
+    let limits = match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
+        .unwrap_or_default()
+    {
+        MediaQuality::Balanced => (
+            constants::BALANCED_IMAGE_SIZE,
+            constants::BALANCED_IMAGE_BYTES,
+        ),
+        MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
+    };
+    Ok(limits)
+}
+
 /// Returns image file size and Exif.
 fn image_metadata(file: &std::fs::File) -> Result<(u64, Option<exif::Exif>)> {
     let len = file.metadata()?.len();
@@ -658,6 +960,27 @@ fn next(&mut self) -> Option<Self::Item> {
 
 impl FusedIterator for BlobDirIter<'_> {}
 
+/// Creates a tiny JPEG preview of the image at `path`, encoded as base64.
+///
+/// The preview is small enough to be stored in message parameters
+/// and can be shown e.g. in quotes when the image itself is not available anymore.
+pub(crate) fn create_micro_thumbnail(path: &Path) -> Result<String> {
+    create_thumbnail(path, MICRO_THUMBNAIL_SIZE)
+}
+
+/// Creates a JPEG preview of the image at `path` fitting into `size`x`size` pixels,
+/// encoded as base64.
+pub(crate) fn create_thumbnail(path: &Path, size: u32) -> Result<String> {
+    let img = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .context("image decode failure")?;
+    let img = img.thumbnail(size, size);
+    let mut encoded = Vec::new();
+    encode_img(&img, ImageOutputFormat::Jpeg { quality: 50 }, &mut encoded)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encoded))
+}
+
 fn encode_img(
     img: &DynamicImage,
     fmt: ImageOutputFormat,
@@ -773,6 +773,80 @@ async fn test_create_and_deduplicate() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_from_trusted_name() -> Result<()> {
+    let t = TestContext::new().await;
+    let blob = BlobObject::create_and_deduplicate_from_bytes(&t, b"bla", "file.txt")?;
+    let trusted = BlobObject::from_trusted_name(&t, blob.as_name())?;
+    assert_eq!(trusted.as_name(), blob.as_name());
+
+    assert!(BlobObject::from_trusted_name(&t, "$BLOBDIR/file.txt").is_err());
+    assert!(BlobObject::from_trusted_name(&t, "ce940175885d7b78f7b7e9f1396611e.txt").is_err());
+
+    // The content of a hash-named blob is not hashed again when attaching it.
+    let path = blob.to_abs_path();
+    fs::write(&path, b"changed").await?;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_and_deduplicate(&t, &path, Some("file.TXT"), None)?;
+    assert_eq!(msg.param.get(Param::File), Some(blob.as_name()));
+    assert_eq!(msg.param.get(Param::Filename), Some("file.TXT"));
+
+    // If the extension does not match, the file is renamed as usual.
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_and_deduplicate(&t, &path, Some("file.dat"), None)?;
+    let hash = blake3::hash(b"changed").to_hex();
+    assert_eq!(
+        msg.param.get(Param::File),
+        Some(format!("$BLOBDIR/{}.dat", &hash[..31]).as_str())
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_create_and_deduplicate_large_file() -> Result<()> {
+    let t = TestContext::new().await;
+    let data: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let path = t.dir.path().join("video.mp4");
+    fs::write(&path, &data).await?;
+
+    t.evtracker.clear_events();
+    let blob = BlobObject::create_and_deduplicate(&t, &path, &path)?;
+    let hash = blake3::hash(&data).to_hex();
+    assert_eq!(blob.name, format!("$BLOBDIR/{}.mp4", &hash[..31]));
+    assert_eq!(fs::read(blob.to_abs_path()).await?, data);
+
+    let mut last_progress = 0;
+    while last_progress < 1000 {
+        let EventType::BlobCopyProgress {
+            path: event_path,
+            progress,
+        } = t
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::BlobCopyProgress { .. }))
+            .await
+        else {
+            unreachable!();
+        };
+        assert_eq!(event_path, path.to_string_lossy());
+        assert!(progress > last_progress);
+        last_progress = progress;
+    }
+
+    // Small files do not report progress.
+    let small_path = t.dir.path().join("small.txt");
+    fs::write(&small_path, b"small").await?;
+    BlobObject::create_and_deduplicate(&t, &small_path, &small_path)?;
+    assert!(
+        t.evtracker
+            .get_matching_opt(&t, |evt| matches!(evt, EventType::BlobCopyProgress { .. }))
+            .await
+            .is_none()
+    );
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_create_and_deduplicate_from_bytes() -> Result<()> {
     let t = TestContext::new().await;
@@ -871,3 +945,49 @@ async fn test_recode_without_downscaling() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_verify_blobs() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    let chat = t.get_self_chat().await;
+
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(t, "foo.txt", FILE_BYTES, None)?;
+    let corrupted = t.send_msg(chat.id, &mut msg).await.sender_msg_id;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(t, "bar.txt", b"world", None)?;
+    let missing = t.send_msg(chat.id, &mut msg).await.sender_msg_id;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(t, "baz.txt", b"unreadable", None)?;
+    let unreadable = t.send_msg(chat.id, &mut msg).await.sender_msg_id;
+
+    let report = t.verify_blobs(false).await?;
+    assert_eq!(report.checked, 3);
+    assert!(report.issues.is_empty());
+
+    fs::write(t.get_blobdir().join(FILE_DEDUPLICATED), b"hellp").await?;
+    let msg = Message::load_from_db(t, missing).await?;
+    fs::remove_file(msg.get_file(t).unwrap()).await?;
+    // A directory exists, but cannot be read as a file.
+    let path = Message::load_from_db(t, unreadable)
+        .await?
+        .get_file(t)
+        .unwrap();
+    fs::remove_file(&path).await?;
+    fs::create_dir(&path).await?;
+
+    // The messages are not on the server, so they are not downloaded again.
+    let report = t.verify_blobs(true).await?;
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.issues.len(), 3);
+    for (msg_id, kind) in [
+        (corrupted, BlobIssueKind::Corrupted),
+        (missing, BlobIssueKind::Missing),
+        (unreadable, BlobIssueKind::Unreadable),
+    ] {
+        let issue = report.issues.iter().find(|i| i.msg_id == msg_id).unwrap();
+        assert_eq!(issue.kind, kind);
+        assert!(!issue.redownloaded);
+    }
+    Ok(())
+}
@@ -14,6 +14,7 @@
 use crate::message::{Message, MsgId, Viewtype, markseen_msgs};
 use crate::mimeparser::{MimeMessage, SystemMessage};
 use crate::net::dns::lookup_host_with_cache;
+use crate::notifications;
 use crate::param::Param;
 use crate::stock_str;
 use crate::tools::{normalize_text, time};
@@ -364,6 +365,7 @@ pub(crate) async fn handle_call_msg(
                     let missed_call_str = stock_str::missed_call(self);
                     call.update_text(self, &missed_call_str).await?;
                     self.emit_incoming_msg(call.msg.chat_id, call_id); // notify missed call
+                    notifications::add_msg_notification(self, call_id).await?;
                 } else {
                     let incoming_call_str =
                         stock_str::incoming_call(self, call.has_video_initially());
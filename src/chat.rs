@@ -9,14 +9,19 @@
 use std::time::Duration;
 
 use anyhow::{Context as _, Result, anyhow, bail, ensure};
+use base64::Engine as _;
 use chrono::TimeZone;
 use deltachat_contact_tools::{ContactAddress, sanitize_bidi_characters, sanitize_single_line};
 use humansize::{BINARY, format_size};
 use mail_builder::mime::MimePart;
+use num_traits::FromPrimitive;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
+use tokio::task;
+use tokio::time::sleep;
 
-use crate::blob::BlobObject;
+use crate::blob::{BlobObject, image_limits};
 use crate::chatlist::Chatlist;
 use crate::chatlist_events;
 use crate::color::str_to_color;
@@ -24,13 +29,13 @@
 use crate::constants::{
     self, Blocked, Chattype, DC_CHAT_ID_ALLDONE_HINT, DC_CHAT_ID_ARCHIVED_LINK,
     DC_CHAT_ID_LAST_SPECIAL, DC_CHAT_ID_TRASH, DC_RESEND_USER_AVATAR_DAYS, EDITED_PREFIX,
-    TIMESTAMP_SENT_TOLERANCE,
+    SentFolderStrategy, TIMESTAMP_SENT_TOLERANCE,
 };
 use crate::contact::{self, Contact, ContactId, Origin};
-use crate::context::Context;
+use crate::context::{Context, WeakContext};
 use crate::debug_logging::maybe_set_logging_xdc;
 use crate::download::{
-    DownloadState, PRE_MSG_ATTACHMENT_SIZE_THRESHOLD, PRE_MSG_SIZE_WARNING_THRESHOLD,
+    DownloadState, PRE_MSG_ATTACHMENT_SIZE_THRESHOLD, PRE_MSG_SIZE_WARNING_THRESHOLD, p2p, upload,
 };
 use crate::ensure_and_debug_assert_eq;
 use crate::ephemeral::{Timer as EphemeralTimer, start_chat_ephemeral_timers};
@@ -46,11 +51,12 @@
 use crate::pgp::addresses_from_public_key;
 use crate::receive_imf::ReceivedMsg;
 use crate::smtp::{self, send_msg_to_smtp};
+use crate::split_msg;
 use crate::stock_str;
 use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::{
-    IsNoneOrEmpty, SystemTime, buf_compress, create_broadcast_secret, create_id,
-    create_outgoing_rfc724_mid, get_abs_path, gm2local_offset, normalize_text, time,
+    self, IsNoneOrEmpty, SystemTime, buf_compress, corrected_time, create_broadcast_secret,
+    create_id, create_outgoing_rfc724_mid, get_abs_path, normalize_text, time, time_elapsed,
     truncate_msg_text,
 };
 use crate::webxdc::StatusUpdateSerial;
@@ -62,6 +68,13 @@
 /// Must not be a URL-safe base64 character (`A-Za-z0-9-_`)
 pub(crate) const ADMIN_GROUP_ID_SEPARATOR: char = ':';
 
+/// Number of previous draft versions kept for each chat.
+pub const DRAFT_HISTORY_LEN: usize = 10;
+
+/// Minimum interval between sync messages triggered by moving read markers,
+/// see [`set_last_read_msg_ex`].
+const LAST_READ_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Returns the admin fingerprint if this is an admin group.
 pub(crate) fn admin_group_fingerprint(grpid: &str) -> Option<&str> {
     grpid.split_once(ADMIN_GROUP_ID_SEPARATOR).map(|(fpr, _)| fpr)
@@ -590,6 +603,41 @@ pub(crate) async fn set_visibility_ex(
         Ok(())
     }
 
+    /// Sets the encryption preference for outgoing messages of the chat.
+    ///
+    /// This overrides encryption and [`Config::ForceEncryption`] for this chat only
+    /// and is meant as a workaround for recipients whose systems can't handle encrypted messages.
+    /// Unencrypted messages can be read and modified in transit,
+    /// and Delta Chat recipients will see them in a separate unencrypted chat,
+    /// so UIs should warn loudly before changing the preference.
+    pub async fn set_encryption_preference(
+        self,
+        context: &Context,
+        preference: EncryptionPreference,
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        let mut chat = Chat::load_from_db(context, self).await?;
+        ensure!(
+            matches!(chat.typ, Chattype::Single | Chattype::Group)
+                && !chat.is_self_talk()
+                && !chat.is_device_talk(),
+            "Cannot change encryption preference of {self}"
+        );
+        if preference == EncryptionPreference::Default {
+            chat.param.remove(Param::EncryptionPreference);
+        } else {
+            warn!(
+                context,
+                "Encryption preference of {self} is set to {preference:?}, messages may be sent unencrypted."
+            );
+            chat.param
+                .set_int(Param::EncryptionPreference, preference as i32);
+        }
+        chat.update_param(context).await?;
+        context.emit_event(EventType::ChatModified(self));
+        Ok(())
+    }
+
     /// Unarchives a chat that is archived and not muted.
     /// Needed after a message is added to a chat so that the chat gets a normal visibility again.
     /// `msg_state` is the state of the message. Matters only for incoming messages currently. For
@@ -700,6 +748,7 @@ pub(crate) async fn delete_ex(self, context: &Context, sync: sync::Sync) -> Resu
                     (DC_CHAT_ID_TRASH, self),
                 )?;
                 transaction.execute("DELETE FROM chats_contacts WHERE chat_id=?", (self,))?;
+                transaction.execute("DELETE FROM draft_history WHERE chat_id=?", (self,))?;
                 transaction.execute("DELETE FROM chats WHERE id=?", (self,))?;
                 Ok(())
             })
@@ -782,18 +831,159 @@ pub async fn get_draft(self, context: &Context) -> Result<Option<Message>> {
         }
     }
 
+    /// Returns previous versions of the draft, the most recent one first.
+    ///
+    /// Up to [`DRAFT_HISTORY_LEN`] versions are kept for each chat;
+    /// a version is saved whenever the draft is replaced or deleted.
+    /// Versions are removed when they are sent
+    /// and when messages with the same text are deleted.
+    /// The returned messages are not stored in the database,
+    /// pass one of them to [`ChatId::set_draft()`] to restore it.
+    pub async fn get_draft_history(self, context: &Context) -> Result<Vec<Message>> {
+        context
+            .sql
+            .query_map_vec(
+                "SELECT timestamp, type, txt, param, mime_in_reply_to
+                 FROM draft_history
+                 WHERE chat_id=?
+                 ORDER BY id DESC",
+                (self,),
+                |row| {
+                    let timestamp: i64 = row.get(0)?;
+                    let viewtype: Viewtype = row.get(1)?;
+                    let text: String = row.get(2)?;
+                    let param: String = row.get(3)?;
+                    let in_reply_to: String = row.get(4)?;
+                    let mut msg = Message::new(viewtype);
+                    msg.chat_id = self;
+                    msg.timestamp_sort = timestamp;
+                    msg.text = text;
+                    msg.param = param.parse().unwrap_or_default();
+                    msg.in_reply_to = Some(in_reply_to).filter(|s| !s.is_empty());
+                    Ok(msg)
+                },
+            )
+            .await
+    }
+
+    /// Saves the current draft to the draft history
+    /// and removes versions exceeding [`DRAFT_HISTORY_LEN`].
+    ///
+    /// The draft is not saved if it is equal to or a prefix of the last saved version
+    /// or if it was sent already.
+    /// If the last saved version is a prefix of the draft, it is replaced.
+    fn save_draft_to_history(self, transaction: &rusqlite::Transaction) -> rusqlite::Result<()> {
+        let Some((viewtype, text, param, in_reply_to)) = transaction
+            .query_row(
+                "SELECT type, txt, param, IFNULL(mime_in_reply_to, '')
+                 FROM msgs WHERE chat_id=? AND state=?",
+                (self, MessageState::OutDraft),
+                |row| {
+                    let viewtype: Viewtype = row.get(0)?;
+                    let text: String = row.get(1)?;
+                    let param: String = row.get(2)?;
+                    let in_reply_to: String = row.get(3)?;
+                    Ok((viewtype, text, param, in_reply_to))
+                },
+            )
+            .optional()?
+        else {
+            return Ok(());
+        };
+
+        let last_sent = transaction
+            .query_row(
+                "SELECT type, txt FROM msgs
+                 WHERE chat_id=? AND from_id=? AND state!=? AND hidden=0
+                 ORDER BY id DESC LIMIT 1",
+                (self, ContactId::SELF, MessageState::OutDraft),
+                |row| {
+                    let viewtype: Viewtype = row.get(0)?;
+                    let text: String = row.get(1)?;
+                    Ok((viewtype, text))
+                },
+            )
+            .optional()?;
+        if last_sent.is_some_and(|(last_viewtype, last_text)| {
+            last_viewtype == viewtype && last_text == text
+        }) {
+            return Ok(());
+        }
+
+        let last_saved = transaction
+            .query_row(
+                "SELECT id, type, txt, param, mime_in_reply_to FROM draft_history
+                 WHERE chat_id=?
+                 ORDER BY id DESC LIMIT 1",
+                (self,),
+                |row| {
+                    let id: i64 = row.get(0)?;
+                    let viewtype: Viewtype = row.get(1)?;
+                    let text: String = row.get(2)?;
+                    let param: String = row.get(3)?;
+                    let in_reply_to: String = row.get(4)?;
+                    Ok((id, viewtype, text, param, in_reply_to))
+                },
+            )
+            .optional()?;
+        if let Some((last_id, last_viewtype, last_text, last_param, last_in_reply_to)) = last_saved
+            && last_viewtype == viewtype
+            && last_param == param
+            && last_in_reply_to == in_reply_to
+        {
+            if last_text.starts_with(&text) {
+                return Ok(());
+            }
+            if text.starts_with(&last_text) {
+                transaction.execute("DELETE FROM draft_history WHERE id=?", (last_id,))?;
+            }
+        }
+
+        transaction.execute(
+            "INSERT INTO draft_history (chat_id, timestamp, type, txt, param, mime_in_reply_to)
+             SELECT chat_id, timestamp, type, txt, param, IFNULL(mime_in_reply_to, '')
+             FROM msgs WHERE chat_id=? AND state=?",
+            (self, MessageState::OutDraft),
+        )?;
+        transaction.execute(
+            "DELETE FROM draft_history
+             WHERE chat_id=?1
+             AND id NOT IN (
+                 SELECT id FROM draft_history WHERE chat_id=?1 ORDER BY id DESC LIMIT ?2
+             )",
+            (self, DRAFT_HISTORY_LEN),
+        )?;
+        Ok(())
+    }
+
+    /// Removes the versions of a sent draft from the draft history,
+    /// i.e. the versions with the same type whose text is a prefix of the sent text.
+    async fn delete_sent_draft_from_history(self, context: &Context, msg: &Message) -> Result<()> {
+        context
+            .sql
+            .execute(
+                "DELETE FROM draft_history
+                 WHERE chat_id=?1 AND type=?2 AND substr(?3, 1, length(txt))=txt",
+                (self, msg.viewtype, &msg.text),
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Deletes draft message, if there is one.
     ///
     /// Returns `true`, if message was deleted, `false` otherwise.
     async fn maybe_delete_draft(self, context: &Context) -> Result<bool> {
-        Ok(context
+        context
             .sql
-            .execute(
-                "DELETE FROM msgs WHERE chat_id=? AND state=?",
-                (self, MessageState::OutDraft),
-            )
-            .await?
-            > 0)
+            .transaction(|transaction| {
+                self.save_draft_to_history(transaction)?;
+                Ok(transaction.execute(
+                    "DELETE FROM msgs WHERE chat_id=? AND state=?",
+                    (self, MessageState::OutDraft),
+                )? > 0)
+            })
+            .await
     }
 
     /// Set provided message as draft message for specified chat.
@@ -839,8 +1029,18 @@ async fn do_set_draft(self, context: &Context, msg: &mut Message) -> Result<bool
             && old_draft.chat_id == self
             && old_draft.state == MessageState::OutDraft
         {
+            if old_draft.viewtype == msg.viewtype
+                && old_draft.text == msg.text
+                && old_draft.param == msg.param
+                && old_draft.in_reply_to.as_deref().unwrap_or_default()
+                    == msg.in_reply_to.as_deref().unwrap_or_default()
+            {
+                return Ok(false);
+            }
             let affected_rows = context
-                        .sql.execute(
+                        .sql.transaction(|transaction| {
+                            self.save_draft_to_history(transaction)?;
+                            Ok(transaction.execute(
                                 "UPDATE msgs
                                 SET timestamp=?1,type=?2,txt=?3,txt_normalized=?4,param=?5,mime_in_reply_to=?6
                                 WHERE id=?7
@@ -858,7 +1058,8 @@ async fn do_set_draft(self, context: &Context, msg: &mut Message) -> Result<bool
                                     msg.in_reply_to.as_deref().unwrap_or_default(),
                                     msg.id,
                                 ),
-                            ).await?;
+                            )?)
+                        }).await?;
             return Ok(affected_rows > 0);
         }
 
@@ -866,6 +1067,7 @@ async fn do_set_draft(self, context: &Context, msg: &mut Message) -> Result<bool
             .sql
             .transaction(|transaction| {
                 // Delete existing draft if it exists.
+                self.save_draft_to_history(transaction)?;
                 transaction.execute(
                     "DELETE FROM msgs WHERE chat_id=? AND state=?",
                     (self, MessageState::OutDraft),
@@ -933,7 +1135,7 @@ pub async fn get_fresh_msg_cnt(self, context: &Context) -> Result<usize> {
         // and have to be multiplied by the number of items shown at once on the chatlist,
         // so savings up to 2 seconds are possible on older devices - newer ones will feel "snappier" :)
         let count = if self.is_archived_link() {
-            context
+            let fresh_chats = context
                 .sql
                 .count(
                     "SELECT COUNT(DISTINCT(m.chat_id))
@@ -947,9 +1149,23 @@ pub async fn get_fresh_msg_cnt(self, context: &Context) -> Result<usize> {
                     ",
                     (),
                 )
-                .await?
+                .await?;
+            // Chats marked as unread are few, so counting them separately is cheap.
+            let marked_unread_chats = context
+                .sql
+                .count(
+                    "SELECT COUNT(*)
+                    FROM chats c
+                    WHERE c.marked_unread=1
+                    AND c.blocked=0
+                    AND c.archived=1
+                    AND NOT EXISTS (SELECT 1 FROM msgs m WHERE m.state=? AND m.hidden=0 AND m.chat_id=c.id)",
+                    (MessageState::InFresh,),
+                )
+                .await?;
+            fresh_chats.saturating_add(marked_unread_chats)
         } else {
-            context
+            let count = context
                 .sql
                 .count(
                     "SELECT COUNT(*)
@@ -959,11 +1175,247 @@ pub async fn get_fresh_msg_cnt(self, context: &Context) -> Result<usize> {
                 AND chat_id=?;",
                     (MessageState::InFresh, self),
                 )
-                .await?
+                .await?;
+            if count == 0 && self.is_marked_unread(context).await? {
+                1
+            } else {
+                count
+            }
         };
         Ok(count)
     }
 
+    /// Returns the first unread message of the chat
+    /// before which the "unread messages" divider should be shown.
+    ///
+    /// This is the first fresh message following the last read message.
+    /// The last read message is updated by [`marknoticed_chat()`]
+    /// and [`message::markseen_msgs()`] and synchronized across own devices,
+    /// so the divider is at the same place on all devices.
+    pub async fn get_first_unread_msg(self, context: &Context) -> Result<Option<MsgId>> {
+        let (timestamp, last_read_msg_id) = context
+            .sql
+            .query_row_optional(
+                "SELECT m.timestamp, m.id
+                 FROM chats c INNER JOIN msgs m ON m.id=c.last_read_msg_id AND m.chat_id=c.id
+                 WHERE c.id=?",
+                (self,),
+                |row| {
+                    let timestamp: i64 = row.get(0)?;
+                    let msg_id: u32 = row.get(1)?;
+                    Ok((timestamp, msg_id))
+                },
+            )
+            .await?
+            .unwrap_or_default();
+        context
+            .sql
+            .query_row_optional(
+                "SELECT id FROM msgs
+                 WHERE chat_id=? AND state=? AND hidden=0 AND (timestamp, id)>(?, ?)
+                 ORDER BY timestamp, id
+                 LIMIT 1",
+                (self, MessageState::InFresh, timestamp, last_read_msg_id),
+                |row| {
+                    let msg_id: MsgId = row.get(0)?;
+                    Ok(msg_id)
+                },
+            )
+            .await
+    }
+
+    /// Marks the chat as unread.
+    ///
+    /// This is a manual flag that does not change the state of the messages,
+    /// the chat counts as having one fresh message in [`ChatId::get_fresh_msg_cnt()`]
+    /// until [`marknoticed_chat()`] is called.
+    /// The flag is synchronized across own devices.
+    pub async fn mark_unread(self, context: &Context) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        self.set_marked_unread_ex(context, Sync, true).await
+    }
+
+    /// Returns true if the chat was marked as unread with [`ChatId::mark_unread()`].
+    pub async fn is_marked_unread(self, context: &Context) -> Result<bool> {
+        let marked_unread = context
+            .sql
+            .query_get_value("SELECT marked_unread FROM chats WHERE id=?", (self,))
+            .await?
+            .unwrap_or_default();
+        Ok(marked_unread)
+    }
+
+    pub(crate) async fn set_marked_unread_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        marked_unread: bool,
+    ) -> Result<()> {
+        let updated = context
+            .sql
+            .execute(
+                "UPDATE chats SET marked_unread=?1 WHERE id=?2 AND marked_unread!=?1",
+                (marked_unread, self),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(());
+        }
+
+        if marked_unread {
+            context.emit_msgs_changed_without_msg_id(self);
+        } else {
+            context.emit_event(EventType::MsgsNoticed(self));
+        }
+        chatlist_events::emit_chatlist_item_changed(context, self);
+        context.on_archived_chats_maybe_noticed();
+        if sync.into() {
+            let chat = Chat::load_from_db(context, self).await?;
+            chat.sync(context, SyncAction::SetMarkedUnread(marked_unread))
+                .await
+                .log_err(context)
+                .ok();
+        }
+        Ok(())
+    }
+
+    /// Sets a UI-specific property of the chat, e.g. the wallpaper dimming.
+    ///
+    /// The key must be prefixed with `ui.` like the keys of [`Context::set_ui_config()`].
+    /// Setting `value` to `None` removes the property.
+    /// The property is synchronized across own devices.
+    pub async fn set_ui_property(
+        self,
+        context: &Context,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        ensure!(key.starts_with("ui."), "set_ui_property(): prefix missing.");
+        self.set_ui_property_ex(context, Sync, key, value).await
+    }
+
+    pub(crate) async fn set_ui_property_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<()> {
+        let updated = if let Some(value) = value {
+            context
+                .sql
+                .execute(
+                    "INSERT INTO chats_ui_properties (chat_id, key, value) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (chat_id, key) DO UPDATE SET value=excluded.value
+                     WHERE value!=excluded.value",
+                    (self, key, value),
+                )
+                .await?
+        } else {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM chats_ui_properties WHERE chat_id=? AND key=?",
+                    (self, key),
+                )
+                .await?
+        };
+        if updated == 0 {
+            return Ok(());
+        }
+
+        context.emit_event(EventType::ChatUiPropertyChanged {
+            chat_id: self,
+            key: key.to_string(),
+        });
+        if sync.into() {
+            let chat = Chat::load_from_db(context, self).await?;
+            chat.sync(
+                context,
+                SyncAction::SetUiProperty {
+                    key: key.to_string(),
+                    value: value.map(|value| value.to_string()),
+                },
+            )
+            .await
+            .log_err(context)
+            .ok();
+        }
+        Ok(())
+    }
+
+    /// Returns a UI-specific property of the chat set with [`ChatId::set_ui_property()`].
+    pub async fn get_ui_property(self, context: &Context, key: &str) -> Result<Option<String>> {
+        ensure!(key.starts_with("ui."), "get_ui_property(): prefix missing.");
+        context
+            .sql
+            .query_get_value(
+                "SELECT value FROM chats_ui_properties WHERE chat_id=? AND key=?",
+                (self, key),
+            )
+            .await
+    }
+
+    /// Sets the wallpaper image of the chat, `None` removes it.
+    ///
+    /// The image is copied to the blobdir and recoded if it is too large.
+    /// The wallpaper is synchronized across own devices.
+    pub async fn set_wallpaper(self, context: &Context, path: Option<&Path>) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        self.set_wallpaper_ex(context, Sync, path).await
+    }
+
+    pub(crate) async fn set_wallpaper_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        path: Option<&Path>,
+    ) -> Result<()> {
+        let mut chat = Chat::load_from_db(context, self).await?;
+        let blob = match path {
+            Some(path) => {
+                let path = get_abs_path(context, path);
+                let mut blob = BlobObject::create_and_deduplicate(context, &path, &path)?;
+                let mut viewtype = Viewtype::Image;
+                blob.check_or_recode_image(context, None, &mut viewtype)
+                    .await?;
+                ensure!(viewtype == Viewtype::Image, "Wallpaper is not an image");
+                chat.param.set(Param::Wallpaper, blob.as_name());
+                Some(blob)
+            }
+            None => {
+                chat.param.remove(Param::Wallpaper);
+                None
+            }
+        };
+        chat.update_param(context).await?;
+        context.emit_event(EventType::ChatModified(self));
+        if sync.into() {
+            let data = match blob {
+                Some(blob) => {
+                    let buf = tokio::fs::read(blob.to_abs_path()).await?;
+                    base64::engine::general_purpose::STANDARD.encode(buf)
+                }
+                None => String::new(),
+            };
+            chat.sync(context, SyncAction::SetWallpaper(data))
+                .await
+                .log_err(context)
+                .ok();
+        }
+        Ok(())
+    }
+
+    /// Returns the path to the wallpaper image set with [`ChatId::set_wallpaper()`].
+    pub async fn get_wallpaper(self, context: &Context) -> Result<Option<PathBuf>> {
+        let chat = Chat::load_from_db(context, self).await?;
+        Ok(chat
+            .param
+            .get(Param::Wallpaper)
+            .map(|name| get_abs_path(context, Path::new(name))))
+    }
+
     pub(crate) async fn created_timestamp(self, context: &Context) -> Result<i64> {
         Ok(context
             .sql
@@ -1276,7 +1728,7 @@ pub(crate) async fn calc_sort_timestamp(
         message_timestamp: i64,
         always_sort_to_bottom: bool,
     ) -> Result<i64> {
-        let mut sort_timestamp = cmp::min(message_timestamp, time());
+        let mut sort_timestamp = cmp::min(message_timestamp, corrected_time(context).await?);
 
         let last_msg_time: Option<i64> = if always_sort_to_bottom {
             // get newest message for this chat
@@ -1471,6 +1923,37 @@ pub fn is_mailing_list(&self) -> bool {
         self.typ == Chattype::Mailinglist
     }
 
+    /// Returns true if unencrypted messages sent to the chat are signed,
+    /// see [`set_sign_unencrypted`].
+    pub fn is_sign_unencrypted(&self) -> bool {
+        self.param
+            .get_bool(Param::SignUnencrypted)
+            .unwrap_or_default()
+    }
+
+    /// Returns the encryption preference for outgoing messages,
+    /// see [`ChatId::set_encryption_preference`].
+    pub fn get_encryption_preference(&self) -> EncryptionPreference {
+        self.param
+            .get_int(Param::EncryptionPreference)
+            .and_then(EncryptionPreference::from_i32)
+            .unwrap_or_default()
+    }
+
+    /// Returns true if periodic gossip of member keys is suppressed in the chat,
+    /// see [`set_gossip_suppressed`].
+    pub fn is_gossip_suppressed(&self) -> bool {
+        self.param
+            .get_bool(Param::SuppressGossip)
+            .unwrap_or_default()
+    }
+
+    /// Returns true if recent messages are shared with new members of the group,
+    /// see [`set_share_history`].
+    pub fn is_history_shared(&self) -> bool {
+        self.param.get_bool(Param::ShareHistory).unwrap_or_default()
+    }
+
     /// Returns None if user can send messages to this chat.
     ///
     /// Otherwise returns a reason useful for logging.
@@ -1813,8 +2296,9 @@ async fn prepare_msg_raw(
             self.update_param(context).await?;
         }
 
-        let is_bot = context.get_config_bool(Config::Bot).await?;
-        msg.param.set_optional(Param::Bot, is_bot.then_some("1"));
+        if context.get_config_bool(Config::Bot).await? {
+            msg.set_bot(true);
+        }
 
         // Set "In-Reply-To:" to identify the message to which the composed message is a reply.
         // Set "References:" to identify the "thread" of the conversation.
@@ -2157,6 +2641,25 @@ pub(crate) async fn sync(context: &Context, id: SyncId, action: SyncAction) -> R
     Ok(())
 }
 
+/// Per-chat override of encryption of outgoing messages,
+/// see [`ChatId::set_encryption_preference`].
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, FromPrimitive, ToPrimitive,
+)]
+#[repr(u8)]
+pub enum EncryptionPreference {
+    /// Messages are encrypted if the chat is encrypted.
+    #[default]
+    Default = 0,
+
+    /// Messages are encrypted if keys of all recipients are available,
+    /// otherwise they are sent unencrypted.
+    Opportunistic = 1,
+
+    /// Messages are never encrypted.
+    ForcePlain = 2,
+}
+
 /// Whether the chat is pinned or archived.
 #[derive(Debug, Copy, Eq, PartialEq, Clone, Serialize, Deserialize, EnumIter, Default)]
 #[repr(i8)]
@@ -2515,11 +3018,26 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
             msg.try_set_vcard(context, &blob.to_abs_path()).await?;
         }
         if msg.viewtype == Viewtype::File && maybe_image || msg.viewtype == Viewtype::Image {
-            let new_name = blob
-                .check_or_recode_image(context, msg.get_filename(), &mut msg.viewtype)
-                .await?;
-            msg.param.set(Param::Filename, new_name);
-            msg.param.set(Param::File, blob.as_name());
+            let (max_wh, _) = image_limits(context).await?;
+            let already_recoded = msg.viewtype == Viewtype::Image
+                && msg
+                    .param
+                    .get_int(Param::ImageRecodedWh)
+                    .and_then(|wh| u32::try_from(wh).ok())
+                    .is_some_and(|wh| wh <= max_wh);
+            if already_recoded {
+                info!(context, "Image {} is already recoded.", blob.as_name());
+            } else {
+                let new_name = blob
+                    .check_or_recode_image(context, msg.get_filename(), &mut msg.viewtype)
+                    .await?;
+                msg.param.set(Param::Filename, new_name);
+                msg.param.set(Param::File, blob.as_name());
+                // Images sent as files keep their original quality and are not recoded.
+                if viewtype_orig == Viewtype::Image && msg.viewtype == Viewtype::Image {
+                    msg.param.set_int(Param::ImageRecodedWh, max_wh.try_into()?);
+                }
+            }
         }
 
         if !msg.param.exists(Param::MimeType)
@@ -2740,6 +3258,7 @@ async fn prepare_send_msg(
     if msg.state == MessageState::Undefined
         // Legacy SecureJoin "v*-request" messages are unencrypted.
         && msg.param.get_cmd() != SystemMessage::SecurejoinMessage
+        && chat.get_encryption_preference() == EncryptionPreference::Default
         && chat.is_encrypted(context).await?
     {
         msg.param.set_int(Param::GuaranteeE2ee, 1);
@@ -2749,12 +3268,15 @@ async fn prepare_send_msg(
     }
     msg.state = MessageState::OutPending;
 
-    msg.timestamp_sort = time();
+    msg.timestamp_sort = corrected_time(context).await?;
     prepare_msg_blob(context, msg).await?;
     if !msg.hidden {
         chat_id.unarchive_if_not_muted(context, msg.state).await?;
     }
     chat.prepare_msg_raw(context, msg, update_msg_id).await?;
+    if !msg.hidden {
+        chat_id.delete_sent_draft_from_history(context, msg).await?;
+    }
 
     let row_ids = create_send_msg_jobs(context, msg)
         .await
@@ -2776,7 +3298,13 @@ async fn render_mime_message_and_pre_message(
     msg: &mut Message,
     mimefactory: MimeFactory,
 ) -> Result<(Option<RenderedEmail>, RenderedEmail)> {
+    let mut mimefactory = mimefactory;
+    if mimefactory.will_be_encrypted() && msg.param.exists(Param::UploadUrl) {
+        mimefactory.set_uploaded_attachment(msg);
+    }
+
     let needs_pre_message = msg.viewtype.has_file()
+        && !msg.param.exists(Param::UploadUrl)
         && mimefactory.will_be_encrypted() // unencrypted is likely email, we don't want to spam by sending multiple messages
         && msg
             .get_filebytes(context)
@@ -2789,6 +3317,9 @@ async fn render_mime_message_and_pre_message(
             context,
             "Message {} is large and will be split into pre- and post-messages.", msg.id,
         );
+        // The Post-Message is sent by email anyway,
+        // so the recipient can still download it if the offer is gone.
+        Box::pin(p2p::maybe_offer_attachment(context, msg)).await?;
 
         let mut mimefactory_post_msg = mimefactory.clone();
         mimefactory_post_msg.set_as_post_message();
@@ -2824,6 +3355,9 @@ async fn render_mime_message_and_pre_message(
 /// is added to the outgoing queue as encrypted or not.
 ///
 /// Returns row ids if `smtp` table jobs were created or an empty `Vec` otherwise.
+/// If the attachment has to be uploaded first,
+/// the message is queued in the `uploads` table instead
+/// and the jobs are created by the SMTP loop after uploading.
 ///
 /// The caller has to interrupt SMTP loop or otherwise process new rows.
 pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -> Result<Vec<i64>> {
@@ -2842,12 +3376,17 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
             .await?;
     }
 
+    // A non-default encryption preference of the chat overrides `Config::ForceEncryption`.
     let needs_encryption = msg.param.get_bool(Param::GuaranteeE2ee).unwrap_or_default()
         || (!msg
             .param
             .get_bool(Param::ForcePlaintext)
             .unwrap_or_default()
-            && context.get_config_bool(Config::ForceEncryption).await?);
+            && context.get_config_bool(Config::ForceEncryption).await?
+            && Chat::load_from_db(context, msg.chat_id)
+                .await?
+                .get_encryption_preference()
+                == EncryptionPreference::Default);
     let mimefactory = match MimeFactory::from_msg(context, msg.clone()).await {
         Ok(mf) => mf,
         Err(err) => {
@@ -2882,7 +3421,16 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         return Ok(Vec::new());
     }
 
-    let (rendered_pre_msg, rendered_msg) =
+    if mimefactory.will_be_encrypted() && upload::needs_upload(context, msg).await? {
+        // The attachment is uploaded by the SMTP loop,
+        // which creates the jobs afterwards.
+        upload::schedule_upload(context, msg.id).await?;
+        context.emit_msgs_changed(msg.chat_id, msg.id);
+        context.scheduler.interrupt_smtp().await;
+        return Ok(Vec::new());
+    }
+
+    let (rendered_pre_msg, mut rendered_msg) =
         match render_mime_message_and_pre_message(context, msg, mimefactory).await {
             Ok(res) => Ok(res),
             Err(err) => {
@@ -2909,7 +3457,41 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         );
     }
 
-    if context.get_config_bool(Config::BccSelf).await? {
+    let max_size = smtp::get_max_message_size(context).await?;
+    let mut rendered_split_parts = Vec::new();
+    if max_size > 0 && u64::try_from(rendered_msg.message.len()).unwrap_or(u64::MAX) > max_size {
+        match split_msg::render_split_parts(context, msg, max_size).await {
+            Ok(Some((first_part, other_parts))) => {
+                rendered_msg = first_part;
+                rendered_split_parts = other_parts;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                message::set_msg_failed(context, msg, &err.to_string()).await?;
+                return Err(err);
+            }
+        }
+    }
+    if max_size > 0 && u64::try_from(rendered_msg.message.len()).unwrap_or(u64::MAX) > max_size {
+        let text = stock_str::msg_too_large(
+            context,
+            &format_size(rendered_msg.message.len(), BINARY),
+            &format_size(max_size, BINARY),
+        );
+        message::set_msg_failed(context, msg, &text).await?;
+        error!(context, "{text}");
+        bail!(text);
+    }
+
+    // Messages only to self are always sent to self, e.g. sync messages.
+    let sent_folder_strategy = if recipients.is_empty() {
+        SentFolderStrategy::BccSelf
+    } else {
+        context.get_sent_folder_strategy().await?
+    };
+    if sent_folder_strategy == SentFolderStrategy::BccSelf
+        && context.get_config_bool(Config::BccSelf).await?
+    {
         smtp::add_self_recipients(context, &mut recipients, rendered_msg.is_encrypted).await?;
     }
 
@@ -2969,8 +3551,8 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
 UPDATE msgs SET
     timestamp=(
         SELECT MAX(timestamp) FROM msgs INDEXED BY msgs_index7 WHERE
-            -- From `InFresh` to `OutDelivered` inclusive, except `OutDraft`.
-            state IN(10,13,16,18,20,24,26) AND
+            -- From `InFresh` to `OutServerRcvd` inclusive, except `OutDraft`.
+            state IN(10,13,16,18,20,24,26,27) AND
             hidden IN(0,1) AND
             chat_id=? AND
             id<=?
@@ -2999,6 +3581,12 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
                 (),
             )?;
         }
+        if sent_folder_strategy == SentFolderStrategy::Append {
+            t.execute(
+                "INSERT INTO imap_append (addr, mime) VALUES (?, ?)",
+                (&from, &rendered_msg.message),
+            )?;
+        }
         let mut stmt = t.prepare(
             "INSERT INTO smtp (rfc724_mid, recipients, mime, msg_id)
             VALUES            (?1,         ?2,         ?3,   ?4)",
@@ -3021,10 +3609,23 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
                 msg.id,
             ))?;
             row_ids.push(row_id.try_into()?);
+            for split_part in &rendered_split_parts {
+                let row_id = stmt.execute((
+                    &split_part.rfc724_mid,
+                    &recipients_chunk,
+                    &split_part.message,
+                    msg.id,
+                ))?;
+                row_ids.push(row_id.try_into()?);
+            }
         }
         Ok(row_ids)
     };
-    context.sql.transaction(trans_fn).await
+    let row_ids = context.sql.transaction(trans_fn).await?;
+    if sent_folder_strategy == SentFolderStrategy::Append {
+        context.scheduler.interrupt_inbox().await;
+    }
+    Ok(row_ids)
 }
 
 /// Sends a text message to the given chat.
@@ -3130,11 +3731,25 @@ async fn donation_request_maybe(context: &Context) -> Result<()> {
         .await
 }
 
+/// Timezone used to decide which day a message belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DaymarkerTimezone {
+    /// Timezone of the device, taking daylight saving time changes into account.
+    #[default]
+    Local,
+
+    /// Fixed offset east of UTC in seconds.
+    FixedOffset(i32),
+}
+
 /// Chat message list request options.
 #[derive(Debug)]
 pub struct MessageListOptions {
-    /// Add day markers before each date regarding the local timezone.
+    /// Add day markers before each date regarding the timezone given in `daymarker_timezone`.
     pub add_daymarker: bool,
+
+    /// Timezone for day markers, ignored if `add_daymarker` is false.
+    pub daymarker_timezone: DaymarkerTimezone,
 }
 
 /// Returns all messages belonging to the chat.
@@ -3144,6 +3759,7 @@ pub async fn get_chat_msgs(context: &Context, chat_id: ChatId) -> Result<Vec<Cha
         chat_id,
         MessageListOptions {
             add_daymarker: false,
+            daymarker_timezone: DaymarkerTimezone::Local,
         },
     )
     .await
@@ -3151,13 +3767,27 @@ pub async fn get_chat_msgs(context: &Context, chat_id: ChatId) -> Result<Vec<Cha
 
 /// Returns messages belonging to the chat according to the given options,
 /// sorted by oldest message first.
-#[expect(clippy::arithmetic_side_effects)]
+///
+/// Day markers are inserted before the first message of each day
+/// and carry the timestamp of the beginning of that day.
+/// Each message is assigned to a day using the UTC offset valid at the time it was sent,
+/// so messages sent before and after a daylight saving time change are sorted in correctly.
 pub async fn get_chat_msgs_ex(
     context: &Context,
     chat_id: ChatId,
     options: MessageListOptions,
 ) -> Result<Vec<ChatItem>> {
-    let MessageListOptions { add_daymarker } = options;
+    let MessageListOptions {
+        add_daymarker,
+        daymarker_timezone,
+    } = options;
+    let fixed_offset = match daymarker_timezone {
+        DaymarkerTimezone::Local => None,
+        DaymarkerTimezone::FixedOffset(offset) => Some(
+            chrono::FixedOffset::east_opt(offset)
+                .with_context(|| format!("Invalid timezone offset {offset}"))?,
+        ),
+    };
     let process_row = |row: &rusqlite::Row| {
         Ok((
             row.get::<_, i64>("timestamp")?,
@@ -3178,19 +3808,19 @@ pub async fn get_chat_msgs_ex(
         sorted_rows.sort_unstable();
 
         let mut ret = Vec::new();
-        let mut last_day = 0;
-        let cnv_to_local = gm2local_offset();
+        let mut last_day = None;
 
         for (ts, curr_id) in sorted_rows {
             if add_daymarker {
-                let curr_local_timestamp = ts + cnv_to_local;
-                let secs_in_day = 86400;
-                let curr_day = curr_local_timestamp / secs_in_day;
-                if curr_day != last_day {
-                    ret.push(ChatItem::DayMarker {
-                        timestamp: curr_day * secs_in_day - cnv_to_local,
-                    });
-                    last_day = curr_day;
+                let day = match fixed_offset {
+                    Some(offset) => day_start(&offset, ts),
+                    None => day_start(&chrono::Local, ts),
+                };
+                if let Some((curr_day, timestamp)) = day
+                    && last_day != Some(curr_day)
+                {
+                    ret.push(ChatItem::DayMarker { timestamp });
+                    last_day = Some(curr_day);
                 }
             }
             ret.push(ChatItem::Message { msg_id: curr_id });
@@ -3213,6 +3843,74 @@ pub async fn get_chat_msgs_ex(
     Ok(items)
 }
 
+/// Returns the date of `timestamp` in the timezone `tz`
+/// together with the timestamp of the beginning of that day.
+fn day_start<Tz: TimeZone>(tz: &Tz, timestamp: i64) -> Option<(chrono::NaiveDate, i64)> {
+    let date = tz.timestamp_opt(timestamp, 0).single()?.date_naive();
+    // If a daylight saving time change skips midnight,
+    // the day starts at the first local time that exists.
+    let start = [(0, 0), (0, 30), (1, 0)]
+        .into_iter()
+        .find_map(|(hour, min)| {
+            tz.from_local_datetime(&date.and_hms_opt(hour, min, 0)?)
+                .earliest()
+        })?;
+    Some((date, start.timestamp()))
+}
+
+/// Returns a window of message IDs around `msg_id` in its chat:
+/// up to `before` messages preceding it, `msg_id` itself
+/// and up to `after` messages following it.
+///
+/// Messages are ordered like in [`get_chat_msgs()`], day markers are not added.
+/// This is useful to jump to a quoted message or a search result
+/// without loading the whole chat.
+pub async fn get_msgs_around(
+    context: &Context,
+    msg_id: MsgId,
+    before: usize,
+    after: usize,
+) -> Result<Vec<MsgId>> {
+    let (chat_id, timestamp): (ChatId, i64) = context
+        .sql
+        .query_row_optional(
+            "SELECT chat_id, timestamp FROM msgs WHERE id=? AND hidden=0",
+            (msg_id,),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .await?
+        .with_context(|| format!("Message {msg_id} does not exist or is hidden"))?;
+    ensure!(!chat_id.is_trash(), "Message {msg_id} is deleted");
+
+    let mut msg_ids = context
+        .sql
+        .query_map_vec(
+            "SELECT id FROM msgs
+             WHERE chat_id=? AND hidden=0 AND (timestamp, id)<(?, ?)
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?",
+            (chat_id, timestamp, msg_id, i64::try_from(before)?),
+            |row| Ok(row.get::<_, MsgId>(0)?),
+        )
+        .await?;
+    msg_ids.reverse();
+    msg_ids.push(msg_id);
+    msg_ids.extend(
+        context
+            .sql
+            .query_map_vec(
+                "SELECT id FROM msgs
+                 WHERE chat_id=? AND hidden=0 AND (timestamp, id)>(?, ?)
+                 ORDER BY timestamp, id
+                 LIMIT ?",
+                (chat_id, timestamp, msg_id, i64::try_from(after)?),
+                |row| Ok(row.get::<_, MsgId>(0)?),
+            )
+            .await?,
+    );
+    Ok(msg_ids)
+}
+
 /// Marks all unread messages in all chats as noticed.
 /// Ignores messages from blocked contacts, but does not ignore messages in muted chats.
 pub async fn marknoticed_all_chats(context: &Context) -> Result<()> {
@@ -3262,6 +3960,22 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
                 },
             )
             .await?;
+        let marked_unread_chat_ids = context
+            .sql
+            .query_map_vec(
+                "SELECT id FROM chats WHERE marked_unread=1 AND archived=1",
+                (),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    Ok(chat_id)
+                },
+            )
+            .await?;
+        for marked_unread_chat_id in marked_unread_chat_ids {
+            marked_unread_chat_id
+                .set_marked_unread_ex(context, Sync, false)
+                .await?;
+        }
         if chat_ids_in_archive.is_empty() {
             return Ok(());
         }
@@ -3280,11 +3994,18 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
             .await?;
 
         for chat_id_in_archive in chat_ids_in_archive {
+            if let Some(last_msg_id) = get_last_visible_msg(context, chat_id_in_archive).await? {
+                set_last_read_msg_ex(context, Sync, chat_id_in_archive, last_msg_id).await?;
+            }
             start_chat_ephemeral_timers(context, chat_id_in_archive).await?;
             context.emit_event(EventType::MsgsNoticed(chat_id_in_archive));
             chatlist_events::emit_chatlist_item_changed(context, chat_id_in_archive);
         }
     } else {
+        chat_id.set_marked_unread_ex(context, Sync, false).await?;
+        if let Some(last_msg_id) = get_last_visible_msg(context, chat_id).await? {
+            set_last_read_msg_ex(context, Sync, chat_id, last_msg_id).await?;
+        }
         start_chat_ephemeral_timers(context, chat_id).await?;
 
         let noticed_msgs_count = context
@@ -3328,6 +4049,124 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
     Ok(())
 }
 
+/// Moves the read marker of the chat to `msg_id`
+/// unless it already points to the same or a later message.
+///
+/// See [`ChatId::get_first_unread_msg()`].
+pub(crate) async fn set_last_read_msg_ex(
+    context: &Context,
+    sync: sync::Sync,
+    chat_id: ChatId,
+    msg_id: MsgId,
+) -> Result<()> {
+    let updated = context
+        .sql
+        .execute(
+            "UPDATE chats SET last_read_msg_id=?1
+             WHERE id=?2
+             AND EXISTS (SELECT 1 FROM msgs WHERE id=?1 AND chat_id=?2 AND hidden=0)
+             AND NOT EXISTS (
+                 SELECT 1 FROM msgs cur, msgs new
+                 WHERE cur.id=chats.last_read_msg_id AND cur.chat_id=?2 AND new.id=?1
+                 AND (cur.timestamp, cur.id)>=(new.timestamp, new.id)
+             )",
+            (msg_id, chat_id),
+        )
+        .await?;
+    if updated == 0 || !bool::from(sync) {
+        return Ok(());
+    }
+
+    let Some((from_id, rfc724_mid)) = context
+        .sql
+        .query_row_optional(
+            "SELECT from_id, rfc724_mid FROM msgs WHERE id=?",
+            (msg_id,),
+            |row| {
+                let from_id: ContactId = row.get(0)?;
+                let rfc724_mid: String = row.get(1)?;
+                Ok((from_id, rfc724_mid))
+            },
+        )
+        .await?
+    else {
+        return Ok(());
+    };
+    // Locally added messages do not exist on other devices.
+    if from_id == ContactId::INFO || from_id == ContactId::DEVICE {
+        return Ok(());
+    }
+    sync_last_read(context, chat_id, rfc724_mid)
+        .await
+        .log_err(context)
+        .ok();
+    Ok(())
+}
+
+/// Synchronises the read marker of the chat to other devices.
+///
+/// Only the latest read marker of each chat is queued
+/// and sending the queue is triggered at most once per [`LAST_READ_SYNC_INTERVAL`]
+/// so that reading a chat message by message does not result in a sync message per message.
+async fn sync_last_read(context: &Context, chat_id: ChatId, rfc724_mid: String) -> Result<()> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    let Some(id) = chat.get_sync_id(context).await? else {
+        return Ok(());
+    };
+    context.replace_last_read_sync_item(id, rfc724_mid).await?;
+
+    let delay = {
+        let mut last_read_sync = context.last_read_sync.lock();
+        let (last_trigger, scheduled) = &mut *last_read_sync;
+        if *scheduled {
+            return Ok(());
+        }
+        match last_trigger.as_ref().map(time_elapsed) {
+            Some(elapsed) if elapsed < LAST_READ_SYNC_INTERVAL => {
+                *scheduled = true;
+                LAST_READ_SYNC_INTERVAL.saturating_sub(elapsed)
+            }
+            _ => {
+                *last_trigger = Some(tools::Time::now());
+                Duration::ZERO
+            }
+        }
+    };
+    if delay.is_zero() {
+        context.scheduler.interrupt_smtp().await;
+    } else {
+        task::spawn(trigger_last_read_sync(context.get_weak_context(), delay));
+    }
+    Ok(())
+}
+
+/// Triggers sending of the queued read markers after `delay`.
+async fn trigger_last_read_sync(context: WeakContext, delay: Duration) -> Result<()> {
+    sleep(delay).await;
+    let context = context.upgrade()?;
+    *context.last_read_sync.lock() = (Some(tools::Time::now()), false);
+    context.scheduler.interrupt_smtp().await;
+    Ok(())
+}
+
+/// Returns the last visible message of the chat.
+async fn get_last_visible_msg(context: &Context, chat_id: ChatId) -> Result<Option<MsgId>> {
+    context
+        .sql
+        .query_row_optional(
+            "SELECT id FROM msgs
+             WHERE chat_id=? AND hidden=0
+             ORDER BY timestamp DESC, id DESC
+             LIMIT 1",
+            (chat_id,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                Ok(msg_id)
+            },
+        )
+        .await
+}
+
 /// Marks messages preceding outgoing messages as noticed.
 ///
 /// In a chat, if there is an outgoing message, it can be assumed that all previous
@@ -4098,9 +4937,18 @@ async fn resend_last_msgs(context: &Context, chat_id: ChatId, to_contact: &Conta
 /// This function does not check if the avatar is set.
 /// If avatar is not set and this function returns `true`,
 /// a `Chat-User-Avatar: 0` header should be sent to reset the avatar.
+///
+/// If [`Config::DataSaver`] is enabled or the network is metered,
+/// the avatar is not re-sent periodically,
+/// only if it has changed or was never sent to some chat member.
 #[expect(clippy::arithmetic_side_effects)]
 pub(crate) async fn shall_attach_selfavatar(context: &Context, chat_id: ChatId) -> Result<bool> {
-    let timestamp_some_days_ago = time() - DC_RESEND_USER_AVATAR_DAYS * 24 * 60 * 60;
+    let timestamp_some_days_ago = if context.is_data_saving().await? {
+        // `selfavatar_sent` is reset to 0 when the avatar changes.
+        1
+    } else {
+        time() - DC_RESEND_USER_AVATAR_DAYS * 24 * 60 * 60
+    };
     let needs_attach = context
         .sql
         .query_map(
@@ -4208,6 +5056,116 @@ pub(crate) async fn set_muted_ex(
     Ok(())
 }
 
+/// Sets whether unencrypted messages sent to the chat are signed.
+///
+/// Signing is useful e.g. for mailing lists
+/// so that other list members can verify the posts
+/// while normal unencrypted chats are not cluttered with signatures.
+pub async fn set_sign_unencrypted(context: &Context, chat_id: ChatId, sign: bool) -> Result<()> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    let mut chat = Chat::load_from_db(context, chat_id).await?;
+    if sign {
+        chat.param.set_int(Param::SignUnencrypted, 1);
+    } else {
+        chat.param.remove(Param::SignUnencrypted);
+    }
+    chat.update_param(context).await?;
+    context.emit_event(EventType::ChatModified(chat_id));
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    Ok(())
+}
+
+/// Sets whether member keys are gossiped periodically in the chat.
+///
+/// Suppressing gossip is useful for very large groups
+/// where `Autocrypt-Gossip` headers make up most of each message.
+/// Keys are still gossiped when members are added
+/// and when requested with [`force_gossip`].
+pub async fn set_gossip_suppressed(
+    context: &Context,
+    chat_id: ChatId,
+    suppress: bool,
+) -> Result<()> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    let mut chat = Chat::load_from_db(context, chat_id).await?;
+    if suppress {
+        chat.param.set_int(Param::SuppressGossip, 1);
+    } else {
+        chat.param.remove(Param::SuppressGossip);
+    }
+    chat.update_param(context).await?;
+    context.emit_event(EventType::ChatModified(chat_id));
+    Ok(())
+}
+
+/// Sets whether recent messages of the group are shared with new members.
+///
+/// If enabled, the device handling a QR code invitation to the group
+/// sends up to 50 recent text messages of the group
+/// to the new member, encrypted to the new member only.
+/// The setting is synchronized across own devices.
+pub async fn set_share_history(context: &Context, chat_id: ChatId, share: bool) -> Result<()> {
+    set_share_history_ex(context, Sync, chat_id, share).await
+}
+
+pub(crate) async fn set_share_history_ex(
+    context: &Context,
+    sync: sync::Sync,
+    chat_id: ChatId,
+    share: bool,
+) -> Result<()> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    let mut chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(
+        chat.typ == Chattype::Group,
+        "History can only be shared in groups"
+    );
+    if share {
+        chat.param.set_int(Param::ShareHistory, 1);
+    } else {
+        chat.param.remove(Param::ShareHistory);
+    }
+    chat.update_param(context).await?;
+    context.emit_event(EventType::ChatModified(chat_id));
+    if sync.into() {
+        chat.sync(context, SyncAction::SetShareHistory(share))
+            .await
+            .log_err(context)
+            .ok();
+    }
+    Ok(())
+}
+
+/// Gossips the key of the chat member with the next message sent to the chat,
+/// even if gossip is suppressed in the chat.
+///
+/// This is useful e.g. after verifying the member
+/// so that other members learn the verified key right away.
+pub async fn force_gossip(context: &Context, chat_id: ChatId, contact_id: ContactId) -> Result<()> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    ensure!(
+        is_contact_in_chat(context, chat_id, contact_id).await?,
+        "{contact_id} is not a member of {chat_id}"
+    );
+    let contact = Contact::get_by_id(context, contact_id).await?;
+    let fingerprint = contact
+        .fingerprint()
+        .with_context(|| format!("{contact_id} has no key"))?
+        .hex();
+    // Zero timestamp forces gossip, see `MimeFactory::render()`.
+    context
+        .sql
+        .execute(
+            "INSERT INTO gossip_timestamp (chat_id, fingerprint, timestamp)
+             VALUES                       (?, ?, 0)
+             ON CONFLICT                  (chat_id, fingerprint)
+             DO UPDATE SET timestamp=0",
+            (chat_id, &fingerprint),
+        )
+        .await?;
+    Ok(())
+}
+
 /// Removes contact from the chat.
 pub async fn remove_contact_from_chat(
     context: &Context,
@@ -4663,8 +5621,13 @@ pub async fn forward_msgs_2ctx(
         msg.param.steal(param, Param::Height);
         msg.param.steal(param, Param::Duration);
         msg.param.steal(param, Param::MimeType);
+        msg.param.steal(param, Param::ImageRecodedWh);
         msg.param.steal(param, Param::ProtectQuote);
         msg.param.steal(param, Param::Quote);
+        msg.param.steal(param, Param::QuoteAuthor);
+        msg.param.steal(param, Param::QuoteTimestamp);
+        msg.param.steal(param, Param::QuoteSnippet);
+        msg.param.steal(param, Param::QuoteThumbnail);
         msg.param.steal(param, Param::Summary1);
         if msg.has_html() {
             msg.set_html(src_msg_id.get_html(ctx_src).await?);
@@ -4826,6 +5789,7 @@ pub(crate) async fn resend_msgs_ex(
             MessageState::OutPending
             | MessageState::OutFailed
             | MessageState::OutDelivered
+            | MessageState::OutServerRcvd
             | MessageState::OutMdnRcvd => {
                 // Broadcast owners shouldn't see spinners on messages being auto-re-sent to new
                 // subscribers (otherwise big channel owners will see spinners most of the time).
@@ -5320,6 +6284,18 @@ pub(crate) enum SyncAction {
     SetPgpContacts(Vec<(String, String)>),
     SetDescription(String),
     Delete,
+    /// Move the read marker to the message with the given Message-ID.
+    SetLastRead(String),
+    /// Set or clear the manual "marked as unread" flag of the chat.
+    SetMarkedUnread(bool),
+    /// Set a UI-specific property of the chat, see [`ChatId::set_ui_property()`].
+    SetUiProperty {
+        key: String,
+        value: Option<String>,
+    },
+    /// Set the wallpaper, the base64-encoded image or an empty string to remove it.
+    SetWallpaper(String),
+    SetShareHistory(bool),
 }
 
 impl Context {
@@ -5433,6 +6409,39 @@ pub(crate) async fn sync_alter_chat(&self, id: &SyncId, action: &SyncAction) ->
                 set_contacts_by_fingerprints(self, chat_id, fingerprint_addrs).await
             }
             SyncAction::Delete => chat_id.delete_ex(self, Nosync).await,
+            SyncAction::SetLastRead(rfc724_mid) => {
+                let msg_id = message::rfc724_mid_exists(self, rfc724_mid)
+                    .await?
+                    .with_context(|| format!("No message found for Message-ID {rfc724_mid:?}"))?;
+                set_last_read_msg_ex(self, Nosync, chat_id, msg_id).await?;
+                self.emit_event(EventType::MsgsNoticed(chat_id));
+                chatlist_events::emit_chatlist_item_changed(self, chat_id);
+                Ok(())
+            }
+            SyncAction::SetMarkedUnread(marked_unread) => {
+                chat_id
+                    .set_marked_unread_ex(self, Nosync, *marked_unread)
+                    .await
+            }
+            SyncAction::SetUiProperty { key, value } => {
+                ensure!(key.starts_with("ui."), "UI property prefix missing");
+                chat_id
+                    .set_ui_property_ex(self, Nosync, key, value.as_deref())
+                    .await
+            }
+            SyncAction::SetWallpaper(data) => {
+                let name = if data.is_empty() {
+                    None
+                } else {
+                    BlobObject::store_from_base64(self, data)?
+                };
+                chat_id
+                    .set_wallpaper_ex(self, Nosync, name.as_deref().map(Path::new))
+                    .await
+            }
+            SyncAction::SetShareHistory(share) => {
+                set_share_history_ex(self, Nosync, chat_id, *share).await
+            }
         }
     }
 
@@ -5443,6 +6452,142 @@ pub(crate) async fn sync_alter_chat(&self, id: &SyncId, action: &SyncAction) ->
     pub(crate) fn on_archived_chats_maybe_noticed(&self) {
         self.emit_msgs_changed_without_msg_id(DC_CHAT_ID_ARCHIVED_LINK);
     }
+
+    /// Accepts all contact requests matching `filter`.
+    ///
+    /// All chats are accepted in a single transaction
+    /// and only one chatlist event is emitted,
+    /// so this is suitable for accounts with hundreds of pending requests.
+    ///
+    /// Returns the number of accepted chats.
+    pub async fn accept_all_requests(&self, filter: RequestsFilter) -> Result<usize> {
+        let chats = self.get_requests(filter).await?;
+        self.sql
+            .transaction(|transaction| {
+                for (chat_id, typ) in &chats {
+                    transaction.execute(
+                        "UPDATE chats SET blocked=? WHERE id=?",
+                        (Blocked::Not, chat_id),
+                    )?;
+                    // See `ChatId::accept_ex()` for the choice of the origin.
+                    let origin = match typ {
+                        Chattype::Mailinglist => continue,
+                        Chattype::Group => Origin::IncomingTo,
+                        _ => Origin::CreateChat,
+                    };
+                    transaction.execute(
+                        "UPDATE contacts SET origin=?1
+                         WHERE origin<?1
+                         AND id IN (SELECT contact_id FROM chats_contacts WHERE chat_id=?2 AND contact_id>?3)",
+                        (origin, chat_id, ContactId::LAST_SPECIAL),
+                    )?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        for (chat_id, _) in &chats {
+            let chat = Chat::load_from_db(self, *chat_id).await?;
+            chat.sync(self, SyncAction::Accept).await.log_err(self).ok();
+        }
+        chatlist_events::emit_chatlist_changed(self);
+        Ok(chats.len())
+    }
+
+    /// Blocks all contact requests matching `filter`.
+    ///
+    /// Like [`ChatId::block()`], this blocks the contacts of 1:1 chats,
+    /// blocks mailing lists and channels and deletes groups.
+    /// Blocking is done in a single transaction,
+    /// groups are deleted one by one afterwards.
+    ///
+    /// Returns the number of blocked chats.
+    pub async fn block_all_requests(&self, filter: RequestsFilter) -> Result<usize> {
+        let chats = self.get_requests(filter).await?;
+        self.sql
+            .transaction(|transaction| {
+                for (chat_id, typ) in &chats {
+                    match typ {
+                        Chattype::Single => {
+                            transaction.execute(
+                                "UPDATE contacts SET blocked=1
+                                 WHERE id IN (SELECT contact_id FROM chats_contacts WHERE chat_id=? AND contact_id>?)",
+                                (chat_id, ContactId::LAST_SPECIAL),
+                            )?;
+                        }
+                        Chattype::Group => continue,
+                        Chattype::Mailinglist | Chattype::InBroadcast | Chattype::OutBroadcast => {}
+                    }
+                    transaction.execute(
+                        "UPDATE chats SET blocked=? WHERE id=?",
+                        (Blocked::Yes, chat_id),
+                    )?;
+                    transaction.execute(
+                        "UPDATE msgs SET state=? WHERE state=? AND chat_id=?",
+                        (MessageState::InNoticed, MessageState::InFresh, chat_id),
+                    )?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        for (chat_id, typ) in &chats {
+            if *typ == Chattype::Group {
+                info!(self, "Can't block groups yet, deleting the chat.");
+                chat_id.delete_ex(self, Sync).await?;
+                continue;
+            }
+            let chat = Chat::load_from_db(self, *chat_id).await?;
+            // NB: For a 1:1 chat this triggers `Contact::block()` on other devices.
+            chat.sync(self, SyncAction::Block).await.log_err(self).ok();
+        }
+        self.emit_event(EventType::ContactsChanged(None));
+        chatlist_events::emit_chatlist_changed(self);
+        Ok(chats.len())
+    }
+
+    /// Returns IDs and types of contact request chats matching `filter`.
+    async fn get_requests(&self, filter: RequestsFilter) -> Result<Vec<(ChatId, Chattype)>> {
+        let now = time();
+        let newer_than = filter
+            .max_age
+            .map_or(i64::MIN, |max_age| now.saturating_sub(max_age));
+        let older_than = filter
+            .min_age
+            .map_or(i64::MAX, |min_age| now.saturating_sub(min_age));
+        self.sql
+            .query_map_vec(
+                "SELECT c.id, c.type
+                 FROM chats c
+                 WHERE c.blocked=? AND c.id>?
+                 AND IFNULL((SELECT MAX(m.timestamp) FROM msgs m WHERE m.chat_id=c.id AND m.hidden=0), c.created_timestamp)
+                     BETWEEN ? AND ?",
+                (
+                    Blocked::Request,
+                    DC_CHAT_ID_LAST_SPECIAL,
+                    newer_than,
+                    older_than,
+                ),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let typ: Chattype = row.get(1)?;
+                    Ok((chat_id, typ))
+                },
+            )
+            .await
+    }
+}
+
+/// Filter for [`Context::accept_all_requests()`] and [`Context::block_all_requests()`].
+///
+/// The age of a contact request is the time since its last message was received.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RequestsFilter {
+    /// Only include requests at least this many seconds old.
+    pub min_age: Option<i64>,
+
+    /// Only include requests at most this many seconds old.
+    pub max_age: Option<i64>,
 }
 
 #[cfg(test)]
@@ -10,6 +10,7 @@
 use crate::headerdef::HeaderDef;
 use crate::imex::{ImexMode, has_backup, imex};
 use crate::message::{Message, MessengerMessage, delete_msgs};
+use crate::mimefactory::MimeFactory;
 use crate::mimeparser::{self, MimeMessage};
 use crate::qr::{Qr, check_qr};
 use crate::receive_imf::receive_imf;
@@ -20,6 +21,7 @@
     TimeShiftFalsePositiveNote, sync,
 };
 use crate::tools::SystemTime;
+use crate::transport::ConfiguredLoginParam;
 use pretty_assertions::assert_eq;
 use std::time::Duration;
 use strum::IntoEnumIterator;
@@ -120,6 +122,101 @@ async fn test_delete_draft() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_draft_history() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    let chat_id = create_group(&t, "abc").await?;
+    assert!(chat_id.get_draft_history(&t).await?.is_empty());
+
+    let mut msg = Message::new_text("first".to_string());
+    chat_id.set_draft(&t, Some(&mut msg)).await?;
+    assert!(chat_id.get_draft_history(&t).await?.is_empty());
+
+    // Updating the draft in place saves the previous version.
+    msg.set_text("second".to_string());
+    chat_id.set_draft(&t, Some(&mut msg)).await?;
+    // Setting the same draft again does not.
+    chat_id.set_draft(&t, Some(&mut msg)).await?;
+    // Replacing the draft with another message saves the previous version as well.
+    let mut msg = Message::new_text("third".to_string());
+    chat_id.set_draft(&t, Some(&mut msg)).await?;
+    let history = chat_id.get_draft_history(&t).await?;
+    let texts: Vec<String> = history.iter().map(|msg| msg.get_text()).collect();
+    assert_eq!(texts, ["second", "first"]);
+
+    // Deleting the draft saves it.
+    chat_id.set_draft(&t, None).await?;
+    let mut history = chat_id.get_draft_history(&t).await?;
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[0].get_text(), "third");
+
+    // A previous version can be restored.
+    chat_id.set_draft(&t, Some(&mut history[2])).await?;
+    let draft = chat_id.get_draft(&t).await?.unwrap();
+    assert_eq!(draft.get_text(), "first");
+
+    for i in 0..2 * DRAFT_HISTORY_LEN {
+        let mut msg = Message::new_text(i.to_string());
+        chat_id.set_draft(&t, Some(&mut msg)).await?;
+    }
+    let history = chat_id.get_draft_history(&t).await?;
+    assert_eq!(history.len(), DRAFT_HISTORY_LEN);
+    assert_eq!(
+        history[0].get_text(),
+        (2 * DRAFT_HISTORY_LEN - 2).to_string()
+    );
+
+    chat_id.delete(&t).await?;
+    assert!(chat_id.get_draft_history(&t).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_draft_history_prefixes() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    let chat_id = create_group(&t, "abc").await?;
+    let history_texts = async || -> Result<Vec<String>> {
+        let history = chat_id.get_draft_history(&t).await?;
+        Ok(history.iter().map(|msg| msg.get_text()).collect())
+    };
+    let set_draft = async |text: &str| {
+        let mut msg = Message::new_text(text.to_string());
+        chat_id.set_draft(&t, Some(&mut msg)).await
+    };
+
+    // Typing more replaces the last saved version.
+    set_draft("Hel").await?;
+    set_draft("Hello").await?;
+    set_draft("Hello world").await?;
+    assert_eq!(history_texts().await?, ["Hello"]);
+    set_draft("Hello").await?;
+    assert_eq!(history_texts().await?, ["Hello world"]);
+
+    // Prefixes of the last saved version are not saved.
+    set_draft("Hell").await?;
+    assert_eq!(history_texts().await?, ["Hello world"]);
+
+    // Sending removes the sent versions.
+    set_draft("Hello world!").await?;
+    t.send_text(chat_id, "Hello world!").await;
+    assert!(history_texts().await?.is_empty());
+    // Removing the sent draft does not save it.
+    chat_id.set_draft(&t, None).await?;
+    assert!(history_texts().await?.is_empty());
+
+    // Deleting the draft clears the history.
+    set_draft("first").await?;
+    set_draft("second").await?;
+    assert_eq!(history_texts().await?, ["first"]);
+    let draft = chat_id.get_draft(&t).await?.unwrap();
+    delete_msgs(&t, &[draft.id]).await?;
+    assert!(chat_id.get_draft(&t).await?.is_none());
+    assert!(history_texts().await?.is_empty());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_forwarding_draft_failing() -> Result<()> {
     let t = TestContext::new_alice().await;
@@ -1248,6 +1345,117 @@ async fn test_unarchive_if_muted() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_msgs_around() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    let chat_id = t.get_self_chat().await.id;
+    let mut msg_ids = Vec::new();
+    for i in 0..6 {
+        msg_ids.push(send_text_msg(t, chat_id, format!("msg {i}")).await?);
+    }
+
+    assert_eq!(get_msgs_around(t, msg_ids[3], 2, 1).await?, msg_ids[1..5]);
+    assert_eq!(get_msgs_around(t, msg_ids[1], 5, 0).await?, msg_ids[0..2]);
+    assert_eq!(get_msgs_around(t, msg_ids[4], 0, 5).await?, msg_ids[4..]);
+    assert_eq!(
+        get_msgs_around(t, msg_ids[2], 0, 0).await?,
+        vec![msg_ids[2]]
+    );
+
+    delete_msgs(t, &[msg_ids[2]]).await?;
+    assert_eq!(
+        get_msgs_around(t, msg_ids[3], 1, 0).await?,
+        vec![msg_ids[1], msg_ids[3]]
+    );
+    assert!(get_msgs_around(t, msg_ids[2], 1, 1).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_daymarker_timezone() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    let chat_id = t.get_self_chat().await.id;
+    let msg1 = send_text_msg(t, chat_id, "evening".to_string()).await?;
+    let msg2 = send_text_msg(t, chat_id, "after midnight".to_string()).await?;
+    let msg3 = send_text_msg(t, chat_id, "morning".to_string()).await?;
+
+    // 2024-03-30 22:30 UTC, 23:30 UTC and 2024-03-31 10:00 UTC.
+    let day_start = 1711756800;
+    for (msg_id, timestamp) in [
+        (None, day_start + 22 * 3600 + 1800),
+        (Some(msg2), day_start + 23 * 3600 + 1800),
+        (Some(msg3), day_start + 34 * 3600),
+    ] {
+        match msg_id {
+            None => {
+                t.sql
+                    .execute(
+                        "UPDATE msgs SET timestamp=? WHERE chat_id=?",
+                        (timestamp, chat_id),
+                    )
+                    .await?
+            }
+            Some(msg_id) => {
+                t.sql
+                    .execute(
+                        "UPDATE msgs SET timestamp=? WHERE id=?",
+                        (timestamp, msg_id),
+                    )
+                    .await?
+            }
+        };
+    }
+
+    let get_items = |offset| async move {
+        let items = get_chat_msgs_ex(
+            t,
+            chat_id,
+            MessageListOptions {
+                add_daymarker: true,
+                daymarker_timezone: DaymarkerTimezone::FixedOffset(offset),
+            },
+        )
+        .await?;
+        // Skip info messages added when the chat was created.
+        let first = items
+            .iter()
+            .position(|item| *item == ChatItem::Message { msg_id: msg1 })
+            .unwrap();
+        anyhow::Ok(items.get(first..).unwrap().to_vec())
+    };
+
+    // In UTC, all messages except the last one are sent on the same day.
+    assert_eq!(
+        get_items(0).await?,
+        vec![
+            ChatItem::Message { msg_id: msg1 },
+            ChatItem::Message { msg_id: msg2 },
+            ChatItem::DayMarker {
+                timestamp: day_start + 24 * 3600
+            },
+            ChatItem::Message { msg_id: msg3 },
+        ]
+    );
+
+    // In UTC+1, the second message is sent at 00:30 on the next day.
+    assert_eq!(
+        get_items(3600).await?,
+        vec![
+            ChatItem::Message { msg_id: msg1 },
+            ChatItem::DayMarker {
+                timestamp: day_start + 23 * 3600
+            },
+            ChatItem::Message { msg_id: msg2 },
+            ChatItem::Message { msg_id: msg3 },
+        ]
+    );
+
+    assert!(get_items(24 * 3600).await.is_err());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_marknoticed_all_chats() -> Result<()> {
     let mut tcm = TestContextManager::new();
@@ -1703,6 +1911,18 @@ async fn test_shall_attach_selfavatar() -> Result<()> {
 
     alice.set_config(Config::Selfavatar, None).await?; // setting to None also forces re-sending
     assert!(shall_attach_selfavatar(alice, chat_id).await?);
+
+    // In data saver mode the avatar is not re-sent periodically.
+    alice.set_config_bool(Config::DataSaver, true).await?;
+    chat_id
+        .set_selfavatar_timestamp(
+            alice,
+            time() - DC_RESEND_USER_AVATAR_DAYS * 24 * 60 * 60 - 1,
+        )
+        .await?;
+    assert!(!shall_attach_selfavatar(alice, chat_id).await?);
+    alice.set_config_bool(Config::DataSaver, false).await?;
+    assert!(shall_attach_selfavatar(alice, chat_id).await?);
     Ok(())
 }
 
@@ -2265,6 +2485,63 @@ async fn test_forward_basic() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_forward_recoded_image() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let alice_chat_id = alice.create_chat(bob).await.id;
+    let self_chat_id = alice.get_self_chat().await.id;
+
+    let bytes = include_bytes!("../../test-data/image/rectangle2000x1800-rotated.jpg");
+    let file = alice.get_blobdir().join("rectangle.jpg");
+    tokio::fs::write(&file, bytes).await?;
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_and_deduplicate(alice, &file, None, None)?;
+    let sent = alice.send_msg(alice_chat_id, &mut msg).await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(
+        msg.param.get_int(Param::ImageRecodedWh),
+        Some(constants::BALANCED_IMAGE_SIZE as i32)
+    );
+    assert_eq!(msg.get_width(), 1800);
+    assert_eq!(msg.get_height(), 2000);
+
+    forward_msgs(alice, &[msg.id], self_chat_id).await?;
+    let fwd_msg = alice.get_last_msg_in(self_chat_id).await;
+    assert_eq!(
+        fwd_msg.param.get_int(Param::ImageRecodedWh),
+        Some(constants::BALANCED_IMAGE_SIZE as i32)
+    );
+    assert_eq!(fwd_msg.get_file(alice), msg.get_file(alice));
+
+    // An image marked as already recoded is sent as is.
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_from_bytes(alice, "rectangle.jpg", bytes, None)?;
+    msg.param
+        .set_int(Param::ImageRecodedWh, constants::BALANCED_IMAGE_SIZE as i32);
+    let sent = alice.send_msg(alice_chat_id, &mut msg).await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(
+        tokio::fs::read(msg.get_file(alice).unwrap()).await?,
+        bytes.as_slice()
+    );
+
+    // With worse media quality, the image is recoded again.
+    alice.set_config(Config::MediaQuality, Some("1")).await?;
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_from_bytes(alice, "rectangle.jpg", bytes, None)?;
+    msg.param
+        .set_int(Param::ImageRecodedWh, constants::BALANCED_IMAGE_SIZE as i32);
+    let sent = alice.send_msg(alice_chat_id, &mut msg).await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(
+        msg.param.get_int(Param::ImageRecodedWh),
+        Some(constants::WORSE_IMAGE_SIZE as i32)
+    );
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_forward_info_msg() -> Result<()> {
     let mut tcm = TestContextManager::new();
@@ -3103,6 +3380,69 @@ async fn test_broadcast_resend_to_new_member() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_broadcast_delivery_update() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let alice_bc_id = create_broadcast(alice, "Channel".to_string()).await?;
+    let qr = get_securejoin_qr(alice, Some(alice_bc_id)).await.unwrap();
+    tcm.exec_securejoin_qr(bob, alice, &qr).await;
+    tcm.exec_securejoin_qr(fiona, alice, &qr).await;
+
+    alice.evtracker.clear_events();
+    let sent = alice.send_text(alice_bc_id, "News").await;
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::BroadcastDeliveryUpdate { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::BroadcastDeliveryUpdate {
+            msg_id: sent.sender_msg_id,
+            delivered: 2,
+            read: 0,
+            total: 2,
+        }
+    );
+
+    let bob_msg = bob.recv_msg(&sent).await;
+    let mdn = MimeFactory::from_mdn(bob, bob_msg.from_id, bob_msg.rfc724_mid, vec![])
+        .await?
+        .render(bob)
+        .await?;
+    receive_imf(alice, mdn.message.as_bytes(), false).await?;
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::BroadcastDeliveryUpdate { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::BroadcastDeliveryUpdate {
+            msg_id: sent.sender_msg_id,
+            delivered: 2,
+            read: 1,
+            total: 2,
+        }
+    );
+
+    // No aggregated events for other chats.
+    let alice_chat_id = alice.create_chat_id(bob).await;
+    alice.send_text(alice_chat_id, "Hi").await;
+    assert!(
+        alice
+            .evtracker
+            .get_matching_opt(alice, |evt| {
+                matches!(evt, EventType::BroadcastDeliveryUpdate { .. })
+            })
+            .await
+            .is_none()
+    );
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_broadcast_resend_failed_msg_to_new_member() -> Result<()> {
     let mut tcm = TestContextManager::new();
@@ -4330,6 +4670,175 @@ async fn test_out_failed_on_all_keys_missing() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_out_failed_on_smtp_size_limit() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+    let (transport_id, _) = ConfiguredLoginParam::load(alice).await?.unwrap();
+    crate::smtp::set_max_message_size(alice, transport_id, 10000).await?;
+
+    // Random text is not reduced by compression.
+    let text: String = (0..2000).map(|_| crate::tools::create_id()).collect();
+    let mut msg = Message::new_text(text);
+    assert!(send_msg(alice, chat_id, &mut msg).await.is_err());
+    assert_eq!(msg.id.get_state(alice).await?, MessageState::OutFailed);
+
+    alice
+        .send_text(chat_id, "Small messages are still sent")
+        .await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_split_text_on_smtp_size_limit() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+    let (transport_id, _) = ConfiguredLoginParam::load(alice).await?.unwrap();
+    crate::smtp::set_max_message_size(alice, transport_id, 60000).await?;
+
+    let text = (0..1000)
+        .map(|_| {
+            (0..5)
+                .map(|_| crate::tools::create_id())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut msg = Message::new_text(text.clone());
+    send_msg(alice, chat_id, &mut msg).await?;
+    assert_eq!(msg.id.get_state(alice).await?, MessageState::OutPending);
+    let mut sent = Vec::new();
+    while let Some(sent_msg) = alice.pop_sent_msg_ex(false).await {
+        assert!(sent_msg.payload.len() <= 60000);
+        sent.push(sent_msg);
+    }
+    assert!(sent.len() > 1);
+    let count = u32::try_from(sent.len())?;
+
+    // The last part arrives first.
+    // It is not shown as there is a gap between the parts.
+    let last = sent.pop().unwrap();
+    bob.recv_msg_trash(&last).await;
+    let rcvd = bob.recv_msg(&sent[0]).await;
+    let incomplete = stock_str::split_msg_incomplete(bob, 1, count);
+    let rcvd_text = rcvd.get_text();
+    let first_text = rcvd_text
+        .strip_suffix(&format!("\n\n{incomplete}"))
+        .unwrap();
+    assert!(text.starts_with(first_text));
+    for part in &sent[1..] {
+        bob.recv_msg_trash(part).await;
+    }
+    let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+    assert_eq!(rcvd.get_text(), text);
+    Ok(())
+}
+
+/// Tests that parts with another encryption than the first part are ignored.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_split_text_unencrypted_part() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+    let (transport_id, _) = ConfiguredLoginParam::load(alice).await?.unwrap();
+    crate::smtp::set_max_message_size(alice, transport_id, 60000).await?;
+
+    let text = (0..1000)
+        .map(|_| {
+            (0..5)
+                .map(|_| crate::tools::create_id())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut msg = Message::new_text(text.clone());
+    send_msg(alice, chat_id, &mut msg).await?;
+    let mut sent = Vec::new();
+    while let Some(sent_msg) = alice.pop_sent_msg_ex(false).await {
+        sent.push(sent_msg);
+    }
+    let count = sent.len();
+    assert!(count > 1);
+
+    let rcvd = bob.recv_msg(&sent[0]).await;
+    let incomplete = stock_str::split_msg_incomplete(bob, 1, u32::try_from(count)?);
+    assert!(rcvd.get_text().ends_with(&incomplete));
+
+    // An unencrypted part claiming to be from Alice is not appended.
+    receive_imf(
+        bob,
+        format!(
+            "From: alice@example.org\n\
+             To: bob@example.net\n\
+             Subject: Forged\n\
+             Message-ID: <forged@example.org>\n\
+             Chat-Version: 1.0\n\
+             Chat-Split-Part: 2/{count}\n\
+             Chat-Split-Of: <{}>\n\
+             Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+             \n\
+             forged\n",
+            rcvd.rfc724_mid
+        )
+        .as_bytes(),
+        false,
+    )
+    .await?;
+    let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+    assert!(!rcvd.get_text().contains("forged"));
+    assert!(rcvd.get_text().ends_with(&incomplete));
+
+    for part in &sent[1..] {
+        bob.recv_msg_trash(part).await;
+    }
+    let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+    assert_eq!(rcvd.get_text(), text);
+    Ok(())
+}
+
+/// Tests that no line breaks are inserted where an over-long line is split.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_split_text_long_line() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+    let (transport_id, _) = ConfiguredLoginParam::load(alice).await?.unwrap();
+    crate::smtp::set_max_message_size(alice, transport_id, 60000).await?;
+
+    let random_word = |len| {
+        let mut word = String::new();
+        while word.len() < len {
+            word.push_str(&crate::tools::create_id());
+        }
+        word.truncate(len);
+        word
+    };
+    // The line is cut right after the space and inside the second word.
+    let long_line = format!("{} {}", random_word(19_999), random_word(45_000));
+    let text = format!("First line\n{long_line}\nLast line");
+    let mut msg = Message::new_text(text.clone());
+    send_msg(alice, chat_id, &mut msg).await?;
+    let mut sent = Vec::new();
+    while let Some(sent_msg) = alice.pop_sent_msg_ex(false).await {
+        sent.push(sent_msg);
+    }
+    assert!(sent.len() > 2);
+
+    let rcvd = bob.recv_msg(&sent[0]).await;
+    for part in &sent[1..] {
+        bob.recv_msg_trash(part).await;
+    }
+    let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+    assert_eq!(rcvd.get_text(), text);
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_get_chat_media() -> Result<()> {
     let t = TestContext::new_alice().await;
@@ -4912,6 +5421,328 @@ async fn test_sync_muted() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_first_unread_msg() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    let bob = &tcm.bob().await;
+    let bob_chat_id = bob.create_chat(alice0).await.id;
+
+    let mut a0_msgs = Vec::new();
+    let mut a1_msgs = Vec::new();
+    for i in 0..3 {
+        let sent = bob.send_text(bob_chat_id, &format!("msg {i}")).await;
+        a0_msgs.push(alice0.recv_msg(&sent).await);
+        a1_msgs.push(alice1.recv_msg(&sent).await);
+    }
+    let a0_chat_id = a0_msgs[0].chat_id;
+    let a1_chat_id = a1_msgs[0].chat_id;
+    a0_chat_id.accept(alice0).await?;
+    a1_chat_id.accept(alice1).await?;
+    assert_eq!(
+        a0_chat_id.get_first_unread_msg(alice0).await?,
+        Some(a0_msgs[0].id)
+    );
+
+    message::markseen_msgs(alice0, vec![a0_msgs[0].id]).await?;
+    assert_eq!(
+        a0_chat_id.get_first_unread_msg(alice0).await?,
+        Some(a0_msgs[1].id)
+    );
+
+    // The read marker is synchronized, so the divider moves on the other device as well.
+    sync(alice0, alice1).await;
+    assert_eq!(
+        a1_chat_id.get_first_unread_msg(alice1).await?,
+        Some(a1_msgs[1].id)
+    );
+
+    marknoticed_chat(alice0, a0_chat_id).await?;
+    assert_eq!(a0_chat_id.get_first_unread_msg(alice0).await?, None);
+    sync(alice0, alice1).await;
+    assert_eq!(a1_chat_id.get_first_unread_msg(alice1).await?, None);
+
+    // The read marker does not move backwards.
+    message::markseen_msgs(alice0, vec![a0_msgs[0].id]).await?;
+    let sent = bob.send_text(bob_chat_id, "new").await;
+    let a0_msg = alice0.recv_msg(&sent).await;
+    assert_eq!(
+        a0_chat_id.get_first_unread_msg(alice0).await?,
+        Some(a0_msg.id)
+    );
+
+    Ok(())
+}
+
+/// Tests that reading a chat message by message queues only the latest read marker for syncing.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_last_read_sync_replaced() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    let bob = &tcm.bob().await;
+    let bob_chat_id = bob.create_chat(alice0).await.id;
+
+    let mut a0_msgs = Vec::new();
+    let mut a1_msgs = Vec::new();
+    for i in 0..3 {
+        let sent = bob.send_text(bob_chat_id, &format!("msg {i}")).await;
+        a0_msgs.push(alice0.recv_msg(&sent).await);
+        a1_msgs.push(alice1.recv_msg(&sent).await);
+    }
+    let a1_chat_id = a1_msgs[0].chat_id;
+    a0_msgs[0].chat_id.accept(alice0).await?;
+    a1_chat_id.accept(alice1).await?;
+    alice0.send_sync_msg().await?;
+    alice0.pop_sent_msg().await;
+
+    for msg in &a0_msgs[..2] {
+        message::markseen_msgs(alice0, vec![msg.id]).await?;
+    }
+    let (json, _) = alice0.build_sync_json().await?.unwrap();
+    assert_eq!(json.matches("SetLastRead").count(), 1);
+    assert!(json.contains(&a0_msgs[1].rfc724_mid));
+
+    sync(alice0, alice1).await;
+    assert_eq!(
+        a1_chat_id.get_first_unread_msg(alice1).await?,
+        Some(a1_msgs[2].id)
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_mark_unread() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    let bob = &tcm.bob().await;
+    let a0_chat_id = alice0.create_chat(bob).await.id;
+    let a1_chat_id = alice1.create_chat(bob).await.id;
+    let sent = bob.send_text(bob.create_chat(alice0).await.id, "Hi").await;
+    let msg = alice0.recv_msg(&sent).await;
+    alice1.recv_msg(&sent).await;
+    marknoticed_chat(alice0, a0_chat_id).await?;
+    marknoticed_chat(alice1, a1_chat_id).await?;
+    assert_eq!(a0_chat_id.get_fresh_msg_cnt(alice0).await?, 0);
+
+    a0_chat_id.mark_unread(alice0).await?;
+    assert!(a0_chat_id.is_marked_unread(alice0).await?);
+    assert_eq!(a0_chat_id.get_fresh_msg_cnt(alice0).await?, 1);
+    // Message state is not changed.
+    let msg = Message::load_from_db(alice0, msg.id).await?;
+    assert_eq!(msg.state, MessageState::InNoticed);
+
+    sync(alice0, alice1).await;
+    assert!(a1_chat_id.is_marked_unread(alice1).await?);
+    assert_eq!(a1_chat_id.get_fresh_msg_cnt(alice1).await?, 1);
+
+    // Archived chats marked as unread are counted in the archive link.
+    a1_chat_id
+        .set_visibility(alice1, ChatVisibility::Archived)
+        .await?;
+    assert_eq!(DC_CHAT_ID_ARCHIVED_LINK.get_fresh_msg_cnt(alice1).await?, 1);
+    marknoticed_chat(alice1, DC_CHAT_ID_ARCHIVED_LINK).await?;
+    assert!(!a1_chat_id.is_marked_unread(alice1).await?);
+    assert_eq!(DC_CHAT_ID_ARCHIVED_LINK.get_fresh_msg_cnt(alice1).await?, 0);
+
+    // Reading the chat on one device clears the flag on the other one.
+    alice1.send_sync_msg().await?;
+    let sync_msg = alice1.pop_sent_msg().await;
+    alice0.recv_msg_trash(&sync_msg).await;
+    assert!(!a0_chat_id.is_marked_unread(alice0).await?);
+    assert_eq!(a0_chat_id.get_fresh_msg_cnt(alice0).await?, 0);
+
+    assert!(DC_CHAT_ID_ARCHIVED_LINK.mark_unread(alice0).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_ui_property() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    let bob = &tcm.bob().await;
+    let a0_chat_id = alice0.create_chat(bob).await.id;
+    let a1_chat_id = alice1.create_chat(bob).await.id;
+
+    assert!(
+        a0_chat_id
+            .set_ui_property(alice0, "wallpaper", Some("x"))
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        a0_chat_id.get_ui_property(alice0, "ui.wallpaper").await?,
+        None
+    );
+
+    alice0.evtracker.clear_events();
+    a0_chat_id
+        .set_ui_property(alice0, "ui.wallpaper", Some("stars.png"))
+        .await?;
+    alice0
+        .evtracker
+        .get_matching(|evt| {
+            matches!(
+                evt,
+                EventType::ChatUiPropertyChanged { chat_id, key }
+                    if *chat_id == a0_chat_id && key == "ui.wallpaper"
+            )
+        })
+        .await;
+    assert_eq!(
+        a0_chat_id.get_ui_property(alice0, "ui.wallpaper").await?,
+        Some("stars.png".to_string())
+    );
+
+    sync(alice0, alice1).await;
+    assert_eq!(
+        a1_chat_id.get_ui_property(alice1, "ui.wallpaper").await?,
+        Some("stars.png".to_string())
+    );
+
+    a1_chat_id
+        .set_ui_property(alice1, "ui.wallpaper", None)
+        .await?;
+    assert_eq!(
+        a1_chat_id.get_ui_property(alice1, "ui.wallpaper").await?,
+        None
+    );
+    sync(alice1, alice0).await;
+    assert_eq!(
+        a0_chat_id.get_ui_property(alice0, "ui.wallpaper").await?,
+        None
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_wallpaper() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    let bob = &tcm.bob().await;
+    let a0_chat_id = alice0.create_chat(bob).await.id;
+    let a1_chat_id = alice1.create_chat(bob).await.id;
+    assert_eq!(a0_chat_id.get_wallpaper(alice0).await?, None);
+
+    let image = include_bytes!("../../test-data/image/avatar64x64.png");
+    let file = alice0.get_blobdir().join("wallpaper.png");
+    fs::write(&file, image).await?;
+    a0_chat_id.set_wallpaper(alice0, Some(&file)).await?;
+    let wallpaper = a0_chat_id.get_wallpaper(alice0).await?.unwrap();
+    assert_eq!(fs::read(&wallpaper).await?, image);
+
+    // The wallpaper is not removed by housekeeping.
+    SystemTime::shift(Duration::from_secs(65 * 60));
+    crate::sql::housekeeping(alice0).await?;
+    assert!(wallpaper.exists());
+
+    sync(alice0, alice1).await;
+    let wallpaper = a1_chat_id.get_wallpaper(alice1).await?.unwrap();
+    assert_eq!(fs::read(&wallpaper).await?, image);
+
+    a1_chat_id.set_wallpaper(alice1, None).await?;
+    assert_eq!(a1_chat_id.get_wallpaper(alice1).await?, None);
+    sync(alice1, alice0).await;
+    assert_eq!(a0_chat_id.get_wallpaper(alice0).await?, None);
+
+    // Only images can be set as wallpaper.
+    let file = alice0.get_blobdir().join("wallpaper.txt");
+    fs::write(&file, "not an image").await?;
+    assert!(a0_chat_id.set_wallpaper(alice0, Some(&file)).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_accept_block_all_requests() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let charlie = &tcm.charlie().await;
+    let fiona = &tcm.fiona().await;
+
+    let bob_msg = tcm.send_recv(bob, alice, "Hi from Bob").await;
+    SystemTime::shift(Duration::from_secs(2 * 24 * 3600));
+    let charlie_msg = tcm.send_recv(charlie, alice, "Hi from Charlie").await;
+    let fiona_msg = tcm.send_recv(fiona, alice, "Hi from Fiona").await;
+    for msg in [&bob_msg, &charlie_msg, &fiona_msg] {
+        assert_eq!(msg.chat_blocked, Blocked::Request);
+    }
+
+    let old_requests = RequestsFilter {
+        min_age: Some(24 * 3600),
+        ..Default::default()
+    };
+    assert_eq!(alice.accept_all_requests(old_requests).await?, 1);
+    let chat = Chat::load_from_db(alice, bob_msg.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Not);
+    let bob_contact = Contact::get_by_id(alice, bob_msg.from_id).await?;
+    assert_eq!(bob_contact.origin, Origin::CreateChat);
+    assert_eq!(
+        Chat::load_from_db(alice, charlie_msg.chat_id)
+            .await?
+            .blocked,
+        Blocked::Request
+    );
+
+    let charlie_grp_id = charlie.create_group_with_members("Group", &[alice]).await;
+    let sent = charlie.send_text(charlie_grp_id, "Hi from the group").await;
+    let grp_msg = alice.recv_msg(&sent).await;
+    assert_eq!(grp_msg.chat_blocked, Blocked::Request);
+
+    alice.set_config_bool(Config::SyncMsgs, true).await?;
+    alice.evtracker.clear_events();
+    assert_eq!(
+        alice.block_all_requests(RequestsFilter::default()).await?,
+        3
+    );
+    for msg in [&charlie_msg, &fiona_msg] {
+        let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+        assert_eq!(chat.blocked, Blocked::Yes);
+        assert!(Contact::get_by_id(alice, msg.from_id).await?.is_blocked());
+    }
+    assert!(Chat::load_from_db(alice, grp_msg.chat_id).await.is_err());
+    // The group is deleted on other devices, not blocked.
+    let (json, _) = alice.build_sync_json().await?.unwrap();
+    assert_eq!(json.matches("\"Block\"").count(), 2);
+    assert_eq!(json.matches("\"Delete\"").count(), 1);
+    alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::ChatlistChanged))
+        .await;
+    assert_eq!(
+        alice.block_all_requests(RequestsFilter::default()).await?,
+        0
+    );
+    assert!(
+        !Contact::get_by_id(alice, bob_msg.from_id)
+            .await?
+            .is_blocked()
+    );
+
+    Ok(())
+}
+
 /// Tests that synchronizing broadcast channels via sync-messages works
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_sync_broadcast_and_send_message() -> Result<()> {
@@ -6576,3 +7407,162 @@ async fn test_unpromoted_group_start_message() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_gossip_suppressed() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+    alice.set_config(Config::GossipPeriod, Some("0")).await?;
+
+    let chat_id = alice
+        .create_group_with_members("Large group", &[bob, fiona])
+        .await;
+    let sent = alice.send_text(chat_id, "Hi all!").await;
+    assert_eq!(bob.parse_msg(&sent).await.gossiped_keys.len(), 2);
+
+    set_gossip_suppressed(alice, chat_id, true).await?;
+    assert!(
+        Chat::load_from_db(alice, chat_id)
+            .await?
+            .is_gossip_suppressed()
+    );
+    let sent = alice.send_text(chat_id, "No gossip").await;
+    assert!(bob.parse_msg(&sent).await.gossiped_keys.is_empty());
+
+    let fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+    force_gossip(alice, chat_id, fiona_id).await?;
+    let sent = alice.send_text(chat_id, "Fiona's key").await;
+    let gossiped_keys = bob.parse_msg(&sent).await.gossiped_keys;
+    assert_eq!(gossiped_keys.len(), 1);
+    assert!(gossiped_keys.contains_key("fiona@example.net"));
+
+    // Forced gossip is sent only once.
+    let sent = alice.send_text(chat_id, "No gossip again").await;
+    assert!(bob.parse_msg(&sent).await.gossiped_keys.is_empty());
+
+    let charlie_id = alice.add_or_lookup_contact_id(&tcm.charlie().await).await;
+    assert!(force_gossip(alice, chat_id, charlie_id).await.is_err());
+
+    set_gossip_suppressed(alice, chat_id, false).await?;
+    let sent = alice.send_text(chat_id, "Gossip again").await;
+    assert_eq!(bob.parse_msg(&sent).await.gossiped_keys.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_encryption_preference() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let chat_id = alice.create_chat(bob).await.id;
+    chat_id
+        .set_encryption_preference(alice, EncryptionPreference::ForcePlain)
+        .await?;
+    assert_eq!(
+        Chat::load_from_db(alice, chat_id)
+            .await?
+            .get_encryption_preference(),
+        EncryptionPreference::ForcePlain
+    );
+    let sent = alice.send_text(chat_id, "Plain").await;
+    assert!(!sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+
+    chat_id
+        .set_encryption_preference(alice, EncryptionPreference::Default)
+        .await?;
+    let sent = alice.send_text(chat_id, "Encrypted").await;
+    assert!(sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+
+    // Without Fiona's key, the message is sent unencrypted to all members
+    // instead of leaving Fiona out.
+    let group_id = alice
+        .create_group_with_members("Group", &[bob, fiona])
+        .await;
+    alice
+        .sql
+        .execute(
+            "DELETE FROM public_keys WHERE fingerprint=
+             (SELECT fingerprint FROM contacts WHERE addr='fiona@example.net')",
+            (),
+        )
+        .await?;
+    let sent = alice.send_text(group_id, "Encrypted to Bob only").await;
+    assert!(sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+    assert!(!sent.recipients.contains("fiona@example.net"));
+
+    group_id
+        .set_encryption_preference(alice, EncryptionPreference::Opportunistic)
+        .await?;
+    let sent = alice.send_text(group_id, "Plain to all").await;
+    assert!(!sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+    assert!(sent.recipients.contains("fiona@example.net"));
+
+    let self_chat = alice.get_self_chat().await;
+    assert!(
+        self_chat
+            .id
+            .set_encryption_preference(alice, EncryptionPreference::ForcePlain)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sent_folder_strategy() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice.set_config_bool(Config::BccSelf, true).await?;
+    let chat_id = alice.create_chat(bob).await.id;
+
+    let sent = alice.send_text(chat_id, "Copy to self").await;
+    assert!(sent.recipients.contains("alice@example.org"));
+
+    alice
+        .set_config(Config::SentFolderStrategy, Some("2"))
+        .await?;
+    let sent = alice.send_text(chat_id, "No copy").await;
+    assert!(!sent.recipients.contains("alice@example.org"));
+    assert!(sent.recipients.contains("bob@example.net"));
+
+    alice
+        .set_config(Config::SentFolderStrategy, Some("1"))
+        .await?;
+    let sent = alice.send_text(chat_id, "Copy to Sent folder").await;
+    assert!(!sent.recipients.contains("alice@example.org"));
+    let mime: String = alice
+        .sql
+        .query_get_value("SELECT mime FROM imap_append", ())
+        .await?
+        .unwrap();
+    assert_eq!(mime, sent.payload);
+
+    // Messages only to self are still sent to self.
+    let self_chat = alice.get_self_chat().await;
+    let sent = alice.send_text(self_chat.id, "Note").await;
+    assert!(sent.recipients.contains("alice@example.org"));
+
+    assert!(
+        alice
+            .set_config(Config::SentFolderStrategy, Some("3"))
+            .await
+            .is_err()
+    );
+    alice.set_config_bool(Config::IsChatmail, true).await?;
+    assert!(
+        alice
+            .set_config(Config::SentFolderStrategy, Some("1"))
+            .await
+            .is_err()
+    );
+    alice.set_config(Config::SentFolderStrategy, None).await?;
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! # Chat statistics.
+//!
+//! Counts the messages of a chat per member, hour of the day and viewtype,
+//! e.g. for group info screens or bot analytics.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, ensure};
+
+use crate::chat::ChatId;
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::message::Viewtype;
+use crate::tools::gm2local_offset;
+
+/// Statistics about the messages of a chat, see [`ChatId::get_statistics()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatStatistics {
+    /// Total number of messages.
+    pub msg_count: usize,
+
+    /// Number of messages sent by each contact, including [`ContactId::SELF`],
+    /// the most active contact first.
+    pub msgs_per_contact: Vec<(ContactId, usize)>,
+
+    /// Number of messages sent in each hour of the day in local time,
+    /// the first entry is for messages sent between 0:00 and 1:00.
+    pub msgs_per_hour: [usize; 24],
+
+    /// Number of messages of each viewtype except [`Viewtype::Text`],
+    /// the most frequent viewtype first.
+    pub media_counts: Vec<(Viewtype, usize)>,
+
+    /// Timestamp of the first message, `None` if there are no messages.
+    pub first_activity: Option<i64>,
+
+    /// Timestamp of the last message, `None` if there are no messages.
+    pub last_activity: Option<i64>,
+}
+
+/// Key to check if cached statistics are still valid:
+/// the number of messages, the largest message ID and the local time offset.
+pub(crate) type ChatStatisticsKey = (usize, u32, i64);
+
+impl ChatId {
+    /// Returns statistics about the messages of the chat.
+    ///
+    /// Info messages and hidden messages are not counted.
+    /// The statistics are cached until messages are added to or removed from the chat.
+    pub async fn get_statistics(self, context: &Context) -> Result<ChatStatistics> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        let offset = gm2local_offset();
+        let (msg_count, max_msg_id) = context
+            .sql
+            .query_row(
+                "SELECT COUNT(*), IFNULL(MAX(id), 0) FROM msgs
+                 WHERE chat_id=? AND hidden=0 AND from_id!=?",
+                (self, ContactId::INFO),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .await?;
+        let key = (msg_count, max_msg_id, offset);
+        if let Some((cached_key, statistics)) = context.chat_statistics.read().await.get(&self)
+            && *cached_key == key
+        {
+            return Ok(statistics.clone());
+        }
+
+        let statistics = context
+            .sql
+            .query_map(
+                "SELECT from_id, type, timestamp FROM msgs
+                 WHERE chat_id=? AND hidden=0 AND from_id!=?",
+                (self, ContactId::INFO),
+                |row| {
+                    let from_id: ContactId = row.get(0)?;
+                    let viewtype: Viewtype = row.get(1)?;
+                    let timestamp: i64 = row.get(2)?;
+                    Ok((from_id, viewtype, timestamp))
+                },
+                |rows| {
+                    let mut statistics = ChatStatistics::default();
+                    let mut msgs_per_contact = HashMap::new();
+                    let mut media_counts = Vec::<(Viewtype, usize)>::new();
+                    for row in rows {
+                        let (from_id, viewtype, timestamp) = row?;
+                        statistics.msg_count = statistics.msg_count.saturating_add(1);
+                        let count = msgs_per_contact.entry(from_id).or_insert(0usize);
+                        *count = count.saturating_add(1);
+                        if let Some(count) = statistics
+                            .msgs_per_hour
+                            .get_mut(local_hour(timestamp, offset))
+                        {
+                            *count = count.saturating_add(1);
+                        }
+                        if viewtype != Viewtype::Text {
+                            match media_counts.iter_mut().find(|(v, _)| *v == viewtype) {
+                                Some((_, count)) => *count = count.saturating_add(1),
+                                None => media_counts.push((viewtype, 1)),
+                            }
+                        }
+                        statistics.first_activity = Some(
+                            statistics
+                                .first_activity
+                                .map_or(timestamp, |t| t.min(timestamp)),
+                        );
+                        statistics.last_activity = Some(
+                            statistics
+                                .last_activity
+                                .map_or(timestamp, |t| t.max(timestamp)),
+                        );
+                    }
+                    statistics.msgs_per_contact = msgs_per_contact.into_iter().collect();
+                    statistics
+                        .msgs_per_contact
+                        .sort_by(|(id1, n1), (id2, n2)| n2.cmp(n1).then(id1.cmp(id2)));
+                    media_counts.sort_by(|(_, n1), (_, n2)| n2.cmp(n1));
+                    statistics.media_counts = media_counts;
+                    Ok(statistics)
+                },
+            )
+            .await?;
+
+        context
+            .chat_statistics
+            .write()
+            .await
+            .insert(self, (key, statistics.clone()));
+        Ok(statistics)
+    }
+}
+
+/// Returns the hour of the day in local time.
+fn local_hour(timestamp: i64, offset: i64) -> usize {
+    let seconds_of_day = timestamp.saturating_add(offset).rem_euclid(24 * 60 * 60);
+    usize::try_from(seconds_of_day / (60 * 60)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod chat_statistics_tests;
@@ -0,0 +1,60 @@
+use super::*;
+use crate::chat;
+use crate::message::Message;
+use crate::test_utils::TestContextManager;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_chat_statistics() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+
+    let statistics = chat_id.get_statistics(alice).await?;
+    assert_eq!(statistics.msg_count, 0);
+    assert_eq!(statistics.first_activity, None);
+
+    alice.send_text(chat_id, "Hi Bob").await;
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_from_bytes(
+        alice,
+        "avatar.png",
+        include_bytes!("../../test-data/image/avatar64x64.png"),
+        None,
+    )?;
+    alice.send_msg(chat_id, &mut msg).await;
+    let bob_chat_id = bob.create_chat(alice).await.id;
+    for text in ["Hi Alice", "How are you?", "Still there?"] {
+        let sent = bob.send_text(bob_chat_id, text).await;
+        alice.recv_msg(&sent).await;
+    }
+
+    let statistics = chat_id.get_statistics(alice).await?;
+    let bob_id = alice.add_or_lookup_contact_id(bob).await;
+    assert_eq!(statistics.msg_count, 5);
+    assert_eq!(
+        statistics.msgs_per_contact,
+        vec![(bob_id, 3), (ContactId::SELF, 2)]
+    );
+    assert_eq!(statistics.msgs_per_hour.iter().sum::<usize>(), 5);
+    assert_eq!(statistics.media_counts, vec![(Viewtype::Image, 1)]);
+    assert!(statistics.first_activity <= statistics.last_activity);
+
+    // The cache is updated when messages are added.
+    chat::send_text_msg(alice, chat_id, "Yes".to_string()).await?;
+    let statistics = chat_id.get_statistics(alice).await?;
+    assert_eq!(statistics.msg_count, 6);
+    assert_eq!(
+        statistics.msgs_per_contact,
+        vec![(ContactId::SELF, 3), (bob_id, 3)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_local_hour() {
+    assert_eq!(local_hour(0, 0), 0);
+    assert_eq!(local_hour(3600 * 25 + 1, 0), 1);
+    assert_eq!(local_hour(0, -3600), 23);
+    assert_eq!(local_hour(3600 * 22, 3 * 3600), 1);
+}
@@ -124,8 +124,8 @@ macro_rules! last_visible_msg_id_in {
     state=19 AND hidden=1 AND chat_id=",
                     $chat_id,
                     " OR
-    -- `InFresh`...`OutDelivered` inclusive, except `OutDraft`.
-    state IN (10,13,16,20,24,26) AND hidden=0 AND chat_id=",
+    -- `InFresh`...`OutServerRcvd` inclusive, except `OutDraft`.
+    state IN (10,13,16,20,24,26,27) AND hidden=0 AND chat_id=",
                     $chat_id,
                     "
 ORDER BY timestamp DESC, id DESC LIMIT 1)"
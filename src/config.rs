@@ -1,23 +1,29 @@
 //! # Key-value configuration management.
 
+use std::collections::BTreeMap;
 use std::env;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::Ordering;
 
 use anyhow::{Context as _, Result, bail, ensure};
 use base64::Engine as _;
 use deltachat_contact_tools::{addr_cmp, sanitize_single_line};
+use num_traits::FromPrimitive;
 use serde::{Deserialize, Serialize};
 use strum::{EnumProperty, IntoEnumIterator};
 use strum_macros::{AsRefStr, Display, EnumIter, EnumString};
 use tokio::fs;
 
 use crate::blob::BlobObject;
+use crate::constants::SentFolderStrategy;
 use crate::context::Context;
 use crate::events::EventType;
-use crate::log::LogExt;
+use crate::log::{LogExt, warn};
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
 use crate::provider::Provider;
+use crate::push;
+use crate::quiet_hours::QuietHours;
 use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::{get_abs_path, time};
 use crate::transport::{ConfiguredLoginParam, add_pseudo_transport, send_sync_transports};
@@ -129,6 +135,8 @@ pub enum Config {
     /// `socks5://` (SOCKS5) and `ss://` (Shadowsocks).
     ///
     /// May contain multiple URLs separated by newline, in which case the first one is used.
+    /// If connections through the first proxy fail repeatedly,
+    /// it is moved to the end of the list and the next one is used.
     ProxyUrl,
 
     /// True if SOCKS5 is enabled.
@@ -164,6 +172,12 @@ pub enum Config {
     /// Own status to display, sent in message footer.
     Selfstatus,
 
+    /// Signature appended below the text of messages to classic email recipients.
+    ///
+    /// Replaces the own status in the footer of unencrypted messages,
+    /// chat messages never carry it.
+    EmailSignature,
+
     /// Own avatar filename.
     Selfavatar,
 
@@ -176,6 +190,15 @@ pub enum Config {
     #[strum(props(default = "0"))]
     BccSelf,
 
+    /// How copies of sent messages to other recipients are kept on the server.
+    ///
+    /// The options are from the `SentFolderStrategy` enum.
+    /// Use `Append` or `None` for servers saving sent messages themselves
+    /// to avoid duplicates; then other devices don't get the messages via the Inbox.
+    /// Chatmail servers only support `BccSelf`.
+    #[strum(props(default = "0"))] // also change SentFolderStrategy.default() on changes
+    SentFolderStrategy,
+
     /// True if Message Delivery Notifications (read receipts) should
     /// be sent and requested.
     #[strum(props(default = "1"))]
@@ -193,6 +216,12 @@ pub enum Config {
     #[strum(props(default = "0"))]
     DeleteDeviceAfter,
 
+    /// Mark local messages as removed from server
+    /// when they disappear from all server folders without our involvement,
+    /// e.g. because of retention policy or deletion by the administrator.
+    #[strum(props(default = "0"))]
+    MarkExpungedMsgs,
+
     /// The primary email address.
     ConfiguredAddr,
 
@@ -338,6 +367,17 @@ pub enum Config {
     /// True if it is a bot account.
     Bot,
 
+    /// Retain the full MIME of all received messages,
+    /// so that it can be retrieved with [`MsgId::get_raw_mime()`],
+    /// e.g. by gateway bots bridging messages to other networks.
+    ///
+    /// Otherwise the full MIME is only retained for messages with HTML parts
+    /// or parts not shown, see [`crate::message::Message::has_html()`].
+    ///
+    /// [`MsgId::get_raw_mime()`]: crate::message::MsgId::get_raw_mime
+    #[strum(props(default = "0"))]
+    SaveMimeHeaders,
+
     /// True when to skip initial start messages in groups.
     #[strum(props(default = "0"))]
     SkipStartMessages,
@@ -353,15 +393,91 @@ pub enum Config {
     /// Timestamp of the last `CantDecryptOutgoingMsgs` notification.
     LastCantDecryptOutgoingMsgs,
 
+    /// Seconds the local clock is ahead of the server clock, negative if it is behind.
+    ///
+    /// Measured regularly by comparing the local time with the `Date` header of an HTTPS response
+    /// of the primary IMAP server host.
+    /// Skews of a few seconds are measurement noise and stored as 0.
+    #[strum(props(default = "0"))]
+    ClockSkew,
+
+    /// Timestamp of the last clock skew measurement.
+    LastClockSkewCheck,
+
     /// Whether to avoid using IMAP IDLE even if the server supports it.
     ///
     /// This is a developer option for testing "fake idle".
+    /// It is also set during configuration
+    /// for providers with known unreliable IDLE.
     #[strum(props(default = "0"))]
     DisableIdle,
 
+    /// Additional folders to fetch messages from, separated by newline.
+    ///
+    /// Messages in these folders are processed like messages in the watched folder,
+    /// so mail filtered into custom folders on the server is still received.
+    /// The folders are not IDLEd on, new messages there are fetched
+    /// whenever the watched folder is fetched.
+    WatchExtraFolders,
+
+    /// Number of most recent existing messages to fetch
+    /// when a folder is selected for the first time, e.g. after configuring.
+    ///
+    /// 0 means not to limit the number of messages,
+    /// existing messages are not fetched at all
+    /// if [`Config::InitialFetchSinceDays`] is 0 as well.
+    #[strum(props(default = "0"))]
+    InitialFetchCount,
+
+    /// Maximum age in days of existing messages to fetch
+    /// when a folder is selected for the first time.
+    ///
+    /// 0 means not to limit the age,
+    /// existing messages are not fetched at all
+    /// if [`Config::InitialFetchCount`] is 0 as well.
+    #[strum(props(default = "0"))]
+    InitialFetchSinceDays,
+
+    /// Space-separated list of keys locked by [`Context::import_managed_settings()`].
+    ///
+    /// Managed keys cannot be changed by `set_config()`, `set_ui_config()`,
+    /// sync messages or [`Context::import_settings()`].
+    /// This key itself cannot be changed by `set_config()` either.
+    ManagedKeys,
+
+    /// Argon2 hash of the app-lock PIN in PHC string format,
+    /// see `Context::set_applock_pin()`.
+    ApplockPinHash,
+
+    /// Number of consecutive wrong app-lock PINs.
+    #[strum(props(default = "0"))]
+    ApplockFailedAttempts,
+
+    /// Timestamp of the last wrong app-lock PIN.
+    #[strum(props(default = "0"))]
+    ApplockLastFailedAttempt,
+
     /// Timestamp of the next check for donation request need.
     DonationRequestNextCheck,
 
+    /// Domain of the landing page invite links generated by `get_securejoin_qr()` point to.
+    ///
+    /// Must be a bare hostname such as `i.delta.chat`, without scheme, port or path.
+    ///
+    /// The invite itself is contained in the fragment of the link,
+    /// so it is not sent to the landing page server.
+    /// Invite links using any domain are recognized by `check_qr()`.
+    #[strum(props(default = "i.delta.chat"))]
+    InviteLinkDomain,
+
+    /// Whether to merge consecutive group member changes received within a short time
+    /// into a single summarizing info message.
+    ///
+    /// The individual messages are hidden
+    /// and can be retrieved with `MsgId::get_coalesced_msgs()`.
+    #[strum(props(default = "0"))]
+    CoalesceMemberChanges,
+
     /// Defines the max. size (in bytes) of messages downloaded automatically.
     ///
     /// For messages with large attachments, two messages are sent:
@@ -370,13 +486,98 @@ pub enum Config {
     /// encryption keys is stripped from post-messages to save traffic.
     /// Pre-Messages are shown as placeholder messages. They can be downloaded fully using
     /// `MsgId::download_full()` later. Post-Messages are automatically downloaded if they are
-    /// smaller than the download_limit. Other messages are always auto-downloaded.
+    /// smaller than the download_limit.
+    /// Classic emails exceeding the download_limit are downloaded partially:
+    /// text parts are fetched right away, while a single large attachment
+    /// can be downloaded later using `MsgId::download_attachment()`.
+    /// Other messages are always auto-downloaded.
     ///
     /// 0 = no limit.
     /// Changes only affect future messages.
     #[strum(props(default = "655360"))]
     DownloadLimit,
 
+    /// Reduce network traffic, e.g. on metered connections.
+    ///
+    /// If enabled, the avatar is only sent when it changes or to new contacts,
+    /// messages larger than [`download::DATA_SAVER_DOWNLOAD_LIMIT`]
+    /// are not downloaded automatically regardless of [`Config::DownloadLimit`],
+    /// polling is done less often if IMAP IDLE is not available
+    /// and statistics are not sent.
+    /// UIs should not load link previews while this is enabled.
+    /// The same applies while the network profile is [`NetworkProfile::Metered`].
+    ///
+    /// [`NetworkProfile::Metered`]: crate::net::NetworkProfile::Metered
+    /// [`download::DATA_SAVER_DOWNLOAD_LIMIT`]: crate::download::DATA_SAVER_DOWNLOAD_LIMIT
+    #[strum(props(default = "0"))]
+    DataSaver,
+
+    /// Max. size (in bytes) of attachments sent by email.
+    ///
+    /// Larger attachments of encrypted messages are encrypted with a random secret
+    /// and uploaded to [`Config::AttachmentUploadUrl`] instead,
+    /// the message then only contains the download URL and the secret.
+    /// Recipients download and decrypt the attachment
+    /// like a Post-Message, see [`Config::DownloadLimit`].
+    ///
+    /// 0 = no limit, attachments are never uploaded.
+    #[strum(props(default = "0"))]
+    MaxAttachmentBytes,
+
+    /// Base URL of the attachment upload service used for [`Config::MaxAttachmentBytes`].
+    ///
+    /// Attachments are uploaded using HTTP PUT to `<url>/<random id>`
+    /// and downloaded by the recipients from the same URL.
+    /// Only HTTPS URLs are supported.
+    AttachmentUploadUrl,
+
+    /// Token sent as `Authorization: Bearer <token>` when uploading attachments
+    /// to [`Config::AttachmentUploadUrl`].
+    AttachmentUploadToken,
+
+    /// Min. size (in bytes) of attachments additionally offered over iroh.
+    ///
+    /// Applies to encrypted messages with a Post-Message in 1:1 chats
+    /// with a contact seen recently if [`Config::WebxdcRealtimeEnabled`] is set.
+    /// The Pre-Message then contains the BLAKE3 hash of the attachment
+    /// and the iroh node address of the sender.
+    /// The recipient downloads the attachment directly from the sender
+    /// while the sender is online and falls back to the Post-Message otherwise.
+    ///
+    /// 0 = attachments are never offered over iroh.
+    #[strum(props(default = "0"))]
+    P2pAttachmentBytes,
+
+    /// Deliver messages directly to contacts on the same local network.
+    ///
+    /// Requires [`Config::WebxdcRealtimeEnabled`] as messages are delivered over iroh.
+    /// Messages are still sent by email, see [`crate::lan`] for details.
+    #[strum(props(default = "0"))]
+    LanMessaging,
+
+    /// Reduce metadata in outgoing messages for users prioritizing privacy.
+    ///
+    /// Optional headers are omitted where the receiver can do without them,
+    /// see `MimeFactory::render()` for the affected headers.
+    #[strum(props(default = "0"))]
+    MinimalHeaders,
+
+    /// How subjects of outgoing messages are chosen.
+    ///
+    /// The options are from the `SubjectMode` enum.
+    /// Subjects set explicitly for a message are always used.
+    #[strum(props(default = "0"))] // also change SubjectMode.default() on changes
+    SubjectMode,
+
+    /// Subject of outgoing messages if [`Config::SubjectMode`] is `Fixed`.
+    SubjectText,
+
+    /// Daily quiet hours, e.g. `22:00-07:00` or `22:00-07:00 +01:00`.
+    ///
+    /// UIs should not notify about incoming messages during quiet hours
+    /// and background work is deferred, see [`crate::quiet_hours`] for details.
+    QuietHours,
+
     /// Enable sending and executing (applying) sync messages. Sending requires `BccSelf` to be set
     /// and `Bot` unset.
     ///
@@ -443,6 +644,20 @@ pub enum Config {
     /// storing the same token multiple times on the server.
     EncryptedDeviceToken,
 
+    /// UnifiedPush endpoint URL received from the distributor.
+    ///
+    /// If set, the endpoint is registered on the chatmail server
+    /// instead of the device token
+    /// and the server sends encrypted web push messages to it.
+    UnifiedPushEndpoint,
+
+    /// P-256 private key used to decrypt UnifiedPush messages,
+    /// base64url-encoded.
+    UnifiedPushPrivateKey,
+
+    /// Web push authentication secret, base64url-encoded.
+    UnifiedPushAuth,
+
     /// Return an error from `receive_imf_inner()`. For tests.
     SimulateReceiveImfError,
 
@@ -481,10 +696,54 @@ pub(crate) fn is_synced(&self) -> bool {
                 | Self::MdnsEnabled
                 | Self::Selfavatar
                 | Self::Selfstatus
+                | Self::EmailSignature
                 | Self::ForceEncryption,
         )
     }
 
+    /// Whether the config option is included in [`Context::export_settings()`].
+    ///
+    /// Only user preferences are exported, no credentials, keys, file paths
+    /// or values which are set automatically.
+    pub(crate) fn is_exported(&self) -> bool {
+        matches!(
+            self,
+            Self::Displayname
+                | Self::Selfstatus
+                | Self::EmailSignature
+                | Self::BccSelf
+                | Self::SentFolderStrategy
+                | Self::MdnsEnabled
+                | Self::MediaQuality
+                | Self::DeleteDeviceAfter
+                | Self::PrivateTag
+                | Self::SkipStartMessages
+                | Self::WatchExtraFolders
+                | Self::InitialFetchCount
+                | Self::InitialFetchSinceDays
+                | Self::InviteLinkDomain
+                | Self::CoalesceMemberChanges
+                | Self::DownloadLimit
+                | Self::DataSaver
+                | Self::MaxAttachmentBytes
+                | Self::MinimalHeaders
+                | Self::SubjectMode
+                | Self::SubjectText
+                | Self::QuietHours
+                | Self::SyncMsgs
+                | Self::WebxdcRealtimeEnabled
+                | Self::WhoCanCallMe
+                | Self::ForceEncryption
+        )
+    }
+
+    /// Whether the config option can be locked by [`Context::import_managed_settings()`].
+    ///
+    /// In addition to the exported settings, organizations may enforce a proxy.
+    fn is_manageable(&self) -> bool {
+        self.is_exported() || matches!(self, Self::ProxyEnabled | Self::ProxyUrl)
+    }
+
     /// Whether the config option needs an IO scheduler restart to take effect.
     pub(crate) fn needs_io_restart(&self) -> bool {
         matches!(self, Config::ConfiguredAddr)
@@ -614,6 +873,14 @@ pub(crate) async fn should_send_mdns(&self) -> Result<bool> {
         self.get_config_bool(Config::MdnsEnabled).await
     }
 
+    /// Returns how copies of sent messages are kept on the server.
+    pub(crate) async fn get_sent_folder_strategy(&self) -> Result<SentFolderStrategy> {
+        Ok(
+            SentFolderStrategy::from_i32(self.get_config_int(Config::SentFolderStrategy).await?)
+                .unwrap_or_default(),
+        )
+    }
+
     /// Gets the configured provider.
     ///
     /// The provider is determined by the current primary transport.
@@ -635,6 +902,19 @@ pub async fn get_config_delete_device_after(&self) -> Result<Option<i64>> {
         }
     }
 
+    /// Returns the folders configured with [`Config::WatchExtraFolders`].
+    pub(crate) async fn get_watch_extra_folders(&self) -> Result<Vec<String>> {
+        Ok(self
+            .get_config(Config::WatchExtraFolders)
+            .await?
+            .unwrap_or_default()
+            .lines()
+            .map(|folder| folder.trim())
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| folder.to_string())
+            .collect())
+    }
+
     /// Executes [`SyncData::Config`] item sent by other device.
     pub(crate) async fn sync_config(&self, key: &Config, value: &str) -> Result<()> {
         let config_value;
@@ -646,10 +926,10 @@ pub(crate) async fn sync_config(&self, key: &Config, value: &str) -> Result<()>
             }
             _ => Some(value),
         };
-        match key.is_synced() {
-            true => self.set_config_ex(Nosync, *key, value).await,
-            false => Ok(()),
+        if !key.is_synced() || self.is_config_managed(*key).await? {
+            return Ok(());
         }
+        self.set_config_ex(Nosync, *key, value).await
     }
 
     fn check_config(key: Config, value: Option<&str>) -> Result<()> {
@@ -660,14 +940,29 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
             | Config::MdnsEnabled
             | Config::Configured
             | Config::Bot
+            | Config::SaveMimeHeaders
             | Config::NotifyAboutWrongPw
             | Config::SyncMsgs
-            | Config::DisableIdle => {
+            | Config::DisableIdle
+            | Config::MarkExpungedMsgs => {
                 ensure!(
                     matches!(value, None | Some("0") | Some("1")),
                     "Boolean value must be either 0 or 1"
                 );
             }
+            Config::QuietHours => {
+                if let Some(value) = value.filter(|value| !value.is_empty()) {
+                    value.parse::<QuietHours>()?;
+                }
+            }
+            Config::InviteLinkDomain => {
+                if let Some(value) = value.filter(|value| !value.is_empty()) {
+                    ensure!(
+                        is_hostname(value),
+                        "Invite link domain {value:?} is not a hostname"
+                    );
+                }
+            }
             _ => (),
         }
         Ok(())
@@ -678,6 +973,19 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
     /// set to the default if there is one.
     pub async fn set_config(&self, key: Config, value: Option<&str>) -> Result<()> {
         Self::check_config(key, value)?;
+        ensure!(
+            key != Config::ManagedKeys && !self.is_config_managed(key).await?,
+            "{key} is managed and cannot be changed"
+        );
+        ensure!(
+            !matches!(
+                key,
+                Config::ApplockPinHash
+                    | Config::ApplockFailedAttempts
+                    | Config::ApplockLastFailedAttempt
+            ),
+            "{key} can only be changed with set_applock_pin()"
+        );
 
         let _pause = match key.needs_io_restart() {
             true => self.scheduler.pause(self).await?,
@@ -739,12 +1047,50 @@ pub(crate) async fn set_config_ex(
                 }
                 self.emit_event(EventType::SelfavatarChanged);
             }
+            Config::SentFolderStrategy => {
+                let strategy = match value {
+                    Some(value) => value
+                        .parse()
+                        .ok()
+                        .and_then(SentFolderStrategy::from_i32)
+                        .with_context(|| format!("Invalid sent folder strategy {value:?}"))?,
+                    None => SentFolderStrategy::default(),
+                };
+                // Chatmail servers have no Sent folder
+                // and do not save sent messages.
+                ensure!(
+                    strategy == SentFolderStrategy::BccSelf || !self.is_chatmail().await?,
+                    "Chatmail servers only support the bcc_self sent folder strategy"
+                );
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+            }
+            Config::UnifiedPushEndpoint => {
+                if let Some(endpoint) = value {
+                    ensure!(
+                        endpoint.starts_with("https://"),
+                        "UnifiedPush endpoint must be an HTTPS URL"
+                    );
+                    ensure!(
+                        endpoint.len() <= push::MAX_UNIFIED_PUSH_ENDPOINT_LEN,
+                        "UnifiedPush endpoint is too long"
+                    );
+                    self.ensure_unified_push_keys().await?;
+                }
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // Register the new endpoint on the server next time.
+                self.push_subscribed.store(false, Ordering::Relaxed);
+                self.scheduler.interrupt_inbox().await;
+            }
             Config::DeleteDeviceAfter => {
                 let ret = self.sql.set_raw_config(key.as_ref(), value).await;
                 // Interrupt ephemeral loop to delete old messages immediately.
                 self.scheduler.interrupt_ephemeral_task().await;
                 ret?
             }
+            Config::QuietHours => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                self.update_quiet_hours().await?;
+            }
             Config::Displayname => {
                 if let Some(v) = value {
                     better_value = sanitize_single_line(v);
@@ -834,6 +1180,7 @@ pub(crate) async fn set_config_ex(
         if key.is_synced() {
             self.emit_event(EventType::ConfigSynced { key });
         }
+        self.emit_event(EventType::ConfigChanged { key });
         if !sync {
             return Ok(());
         }
@@ -871,6 +1218,10 @@ pub async fn set_config_bool(&self, key: Config, value: bool) -> Result<()> {
     /// eg. `ui.desktop.linux.foo`, `ui.desktop.macos.bar`, `ui.ios.foobar`.
     pub async fn set_ui_config(&self, key: &str, value: Option<&str>) -> Result<()> {
         ensure!(key.starts_with("ui."), "set_ui_config(): prefix missing.");
+        ensure!(
+            !self.is_key_managed(key).await?,
+            "{key} is managed and cannot be changed"
+        );
         self.sql.set_raw_config(key, value).await
     }
 
@@ -879,6 +1230,108 @@ pub async fn get_ui_config(&self, key: &str) -> Result<Option<String>> {
         ensure!(key.starts_with("ui."), "get_ui_config(): prefix missing.");
         self.sql.get_raw_config(key).await
     }
+
+    /// Writes the non-secret settings to `path` as a JSON object,
+    /// e.g. for provisioning other profiles or for re-setup after a reinstall.
+    ///
+    /// Only settings differing from the defaults and all `ui.*` keys are written,
+    /// see [`Config::is_exported()`] for the included keys.
+    pub async fn export_settings(&self, path: &Path) -> Result<()> {
+        let mut settings = BTreeMap::new();
+        for key in Config::iter().filter(Config::is_exported) {
+            if let Some(value) = self.sql.get_raw_config(key.as_ref()).await? {
+                settings.insert(key.to_string(), value);
+            }
+        }
+        for key in get_all_ui_config_keys(self).await? {
+            if let Some(value) = self.get_ui_config(&key).await? {
+                settings.insert(key, value);
+            }
+        }
+        let json = serde_json::to_string_pretty(&settings)?;
+        fs::write(path, json)
+            .await
+            .with_context(|| format!("Cannot write settings to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Applies settings written by [`Context::export_settings()`].
+    ///
+    /// Keys which are unknown or not exportable are skipped with a warning,
+    /// so that settings written by newer versions can still be imported.
+    /// Managed keys are skipped as well.
+    pub async fn import_settings(&self, path: &Path) -> Result<()> {
+        for (key, value) in read_settings(path).await? {
+            if self.is_key_managed(&key).await? {
+                warn!(self, "import_settings: Skipping managed key {key:?}.");
+                continue;
+            }
+            if key.starts_with("ui.") {
+                self.set_ui_config(&key, Some(&value)).await?;
+                continue;
+            }
+            match Config::from_str(&key) {
+                Ok(config) if config.is_exported() => {
+                    self.set_config(config, Some(&value))
+                        .await
+                        .with_context(|| format!("Cannot set {key} to {value:?}"))?;
+                }
+                _ => warn!(self, "import_settings: Skipping key {key:?}."),
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies settings from a provisioning file and locks them,
+    /// so that organizations can enforce policies.
+    ///
+    /// The file has the format written by [`Context::export_settings()`],
+    /// additionally [`Config::ProxyEnabled`] and [`Config::ProxyUrl`] may be set.
+    /// Afterwards the keys are reported by [`Context::is_config_managed()`]
+    /// and cannot be changed anymore except by another call to this function,
+    /// which replaces the set of managed keys.
+    pub async fn import_managed_settings(&self, path: &Path) -> Result<()> {
+        let mut managed_keys = Vec::new();
+        for (key, value) in read_settings(path).await? {
+            if key.starts_with("ui.") {
+                self.sql.set_raw_config(&key, Some(&value)).await?;
+                managed_keys.push(key);
+                continue;
+            }
+            match Config::from_str(&key) {
+                Ok(config) if config.is_manageable() => {
+                    self.set_config_internal(config, Some(&value))
+                        .await
+                        .with_context(|| format!("Cannot set {key} to {value:?}"))?;
+                    managed_keys.push(key);
+                }
+                _ => warn!(self, "import_managed_settings: Skipping key {key:?}."),
+            }
+        }
+        self.sql
+            .set_raw_config(Config::ManagedKeys.as_ref(), Some(&managed_keys.join(" ")))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns true if the key was locked by [`Context::import_managed_settings()`]
+    /// and cannot be changed by the user.
+    pub async fn is_config_managed(&self, key: Config) -> Result<bool> {
+        self.is_key_managed(key.as_ref()).await
+    }
+
+    async fn is_key_managed(&self, key: &str) -> Result<bool> {
+        let managed_keys = self.get_config(Config::ManagedKeys).await?;
+        Ok(managed_keys.is_some_and(|keys| keys.split_whitespace().any(|k| k == key)))
+    }
+}
+
+/// Reads a settings file written by [`Context::export_settings()`].
+async fn read_settings(path: &Path) -> Result<BTreeMap<String, String>> {
+    let json = fs::read(path)
+        .await
+        .with_context(|| format!("Cannot read settings from {}", path.display()))?;
+    serde_json::from_slice(&json).context("Invalid settings file")
 }
 
 /// Returns a value for use in `Context::set_config_*()` for the given `bool`.
@@ -1009,5 +1462,18 @@ pub async fn get_all_ui_config_keys(context: &Context) -> Result<Vec<String>> {
     Ok(ui_keys)
 }
 
+/// Returns true if `s` is a bare hostname such as `i.delta.chat`,
+/// without scheme, port, path or other parts of a URL.
+fn is_hostname(s: &str) -> bool {
+    s.len() <= 253
+        && s.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
 #[cfg(test)]
 mod config_tests;
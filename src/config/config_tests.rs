@@ -60,6 +60,23 @@ async fn test_media_quality_config_option() {
     assert_eq!(media_quality, constants::MediaQuality::Worse);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_extra_folders() -> Result<()> {
+    let t = TestContext::new().await;
+    assert!(t.get_watch_extra_folders().await?.is_empty());
+
+    t.set_config(
+        Config::WatchExtraFolders,
+        Some("Newsletters\n\n  Work, Projects \n"),
+    )
+    .await?;
+    assert_eq!(
+        t.get_watch_extra_folders().await?,
+        vec!["Newsletters".to_string(), "Work, Projects".to_string()]
+    );
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_ui_config() -> Result<()> {
     let t = TestContext::new().await;
@@ -342,3 +359,147 @@ async fn test_event_config_synced() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_event_config_changed() -> Result<()> {
+    let alice0 = TestContext::new_alice().await;
+    let alice1 = TestContext::new_alice().await;
+    for a in [&alice0, &alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+
+    // Not synced, but still reported.
+    alice0.set_config(Config::MediaQuality, Some("1")).await?;
+    alice0
+        .evtracker
+        .get_matching(|e| {
+            matches!(
+                e,
+                EventType::ConfigChanged {
+                    key: Config::MediaQuality
+                }
+            )
+        })
+        .await;
+
+    // Changes applied from another device are reported as well.
+    alice1.evtracker.clear_events();
+    alice0
+        .set_config(Config::Displayname, Some("Alice Sync"))
+        .await?;
+    sync(&alice0, &alice1).await;
+    alice1
+        .evtracker
+        .get_matching(|e| {
+            matches!(
+                e,
+                EventType::ConfigChanged {
+                    key: Config::Displayname
+                }
+            )
+        })
+        .await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_export_import_settings() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    alice
+        .set_config(Config::Displayname, Some("Alice Export"))
+        .await?;
+    alice.set_config_u32(Config::DownloadLimit, 12345).await?;
+    alice
+        .set_ui_config("ui.desktop.theme", Some("dark"))
+        .await?;
+    let path = alice.get_blobdir().join("settings.json");
+    alice.export_settings(&path).await?;
+
+    let json = tokio::fs::read_to_string(&path).await?;
+    assert!(json.contains("Alice Export"));
+    assert!(!json.contains("configured_addr"));
+    assert!(!json.contains("key_id"));
+
+    bob.import_settings(&path).await?;
+    assert_eq!(
+        bob.get_config(Config::Displayname).await?,
+        Some("Alice Export".to_string())
+    );
+    assert_eq!(bob.get_config_int(Config::DownloadLimit).await?, 12345);
+    assert_eq!(
+        bob.get_ui_config("ui.desktop.theme").await?,
+        Some("dark".to_string())
+    );
+    assert_eq!(
+        bob.get_config(Config::ConfiguredAddr).await?,
+        Some("bob@example.net".to_string())
+    );
+
+    // Secret or unknown keys are not imported.
+    tokio::fs::write(
+        &path,
+        r#"{"configured_addr": "mallory@example.org", "unknown_key": "1"}"#,
+    )
+    .await?;
+    bob.import_settings(&path).await?;
+    assert_eq!(
+        bob.get_config(Config::ConfiguredAddr).await?,
+        Some("bob@example.net".to_string())
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_managed_settings() -> Result<()> {
+    let alice0 = TestContext::new_alice().await;
+    let alice1 = TestContext::new_alice().await;
+    for a in [&alice0, &alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+
+    let path = alice1.get_blobdir().join("provisioning.json");
+    tokio::fs::write(
+        &path,
+        r#"{"download_limit": "0", "displayname": "Alice Managed", "ui.lock": "1", "key_id": "5"}"#,
+    )
+    .await?;
+    alice1.import_managed_settings(&path).await?;
+    assert!(alice1.is_config_managed(Config::DownloadLimit).await?);
+    assert!(alice1.is_config_managed(Config::Displayname).await?);
+    assert!(!alice1.is_config_managed(Config::KeyId).await?);
+    assert!(!alice1.is_config_managed(Config::MediaQuality).await?);
+    assert_eq!(alice1.get_config_int(Config::DownloadLimit).await?, 0);
+
+    assert!(
+        alice1
+            .set_config_u32(Config::DownloadLimit, 1000)
+            .await
+            .is_err()
+    );
+    assert!(alice1.set_ui_config("ui.lock", Some("0")).await.is_err());
+    assert!(alice1.set_config(Config::ManagedKeys, None).await.is_err());
+    alice1.set_config_u32(Config::MediaQuality, 1).await?;
+
+    // Managed keys are not changed by other devices.
+    alice0
+        .set_config(Config::Displayname, Some("Alice Unmanaged"))
+        .await?;
+    sync(&alice0, &alice1).await;
+    assert_eq!(
+        alice1.get_config(Config::Displayname).await?,
+        Some("Alice Managed".to_string())
+    );
+
+    // Nor by importing normal settings.
+    tokio::fs::write(&path, r#"{"download_limit": "1000", "media_quality": "0"}"#).await?;
+    alice1.import_settings(&path).await?;
+    assert_eq!(alice1.get_config_int(Config::DownloadLimit).await?, 0);
+    assert_eq!(alice1.get_config_int(Config::MediaQuality).await?, 0);
+
+    Ok(())
+}
@@ -11,6 +11,7 @@
 
 mod auto_mozilla;
 mod auto_outlook;
+mod quirks;
 pub(crate) mod server_params;
 
 use anyhow::{Context as _, Result, bail, ensure, format_err};
@@ -41,7 +42,7 @@
     ConfiguredCertificateChecks, ConfiguredLoginParam, ConfiguredServerLoginParam,
     ConnectionCandidate, send_sync_transports,
 };
-use crate::{EventType, stock_str};
+use crate::{ErrorCode, EventType, stock_str};
 use crate::{chat, provider};
 
 /// Maximum number of relays.
@@ -162,6 +163,7 @@ pub(crate) async fn add_transport_inner(&self, param: &mut EnteredLoginParam) ->
             // We are using Anyhow's .context() and to show the
             // inner error, too, we need the {:#}:
             let error_msg = stock_str::configuration_failed(self, &format!("{err:#}"));
+            self.set_last_error_with_code(ErrorCode::from_error(err), &error_msg);
             progress!(self, 0, Some(error_msg.clone()));
             bail!(error_msg);
         } else {
@@ -607,7 +609,23 @@ async fn get_configured_param(
 async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Option<&'static Provider>> {
     progress!(ctx, 1);
 
-    let configured_param = get_configured_param(ctx, param).await?;
+    let mut configured_param = get_configured_param(ctx, param).await?;
+    let domain = EmailAddress::new(&configured_param.addr)
+        .context("Bad email-address")?
+        .domain;
+    let quirk = quirks::get_quirk(&domain, &configured_param.imap);
+    if let Some(quirk) = quirk
+        && let Some(folder) = &configured_param.imap_folder
+    {
+        let prefixed_folder = quirk.apply_folder_prefix(folder);
+        if &prefixed_folder != folder {
+            info!(
+                ctx,
+                "Using folder {prefixed_folder:?} instead of {folder:?}."
+            );
+            configured_param.imap_folder = Some(prefixed_folder);
+        }
+    }
     let proxy_config = ProxyConfig::load(ctx).await?;
     let strict_tls = configured_param.strict_tls(proxy_config.is_some());
 
@@ -647,7 +665,13 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Option<&'
     let imap_session = match imap.connect(ctx, configuring).await {
         Ok(imap_session) => imap_session,
         Err(err) => {
-            bail!("{}", nicer_configuration_error(ctx, format!("{err:#}")));
+            let error_msg = nicer_configuration_error(ctx, format!("{err:#}"));
+            if error_msg != stock_str::error_no_network(ctx)
+                && let Some(hint) = quirk.and_then(|quirk| quirk.app_password_hint)
+            {
+                bail!("{error_msg}\n\n{}", hint(ctx));
+            }
+            bail!("{error_msg}");
         }
     };
 
@@ -674,6 +698,17 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Option<&'
 
     progress!(ctx, 910);
 
+    if quirk.is_some_and(|quirk| quirk.disable_idle)
+        && !ctx.config_exists(Config::DisableIdle).await?
+    {
+        info!(
+            ctx,
+            "IMAP IDLE is unreliable with this provider, disabling it."
+        );
+        ctx.set_config_ex(Nosync, Config::DisableIdle, Some("1"))
+            .await?;
+    }
+
     let provider = configured_param.provider;
     configured_param
         .clone()
@@ -0,0 +1,149 @@
+//! Provider-specific configuration quirks.
+//!
+//! Some providers need workarounds that do not fit into the [provider database],
+//! e.g. because they are keyed by the IMAP server rather than by the email domain
+//! or because they only adjust how configuration errors are reported.
+//! Quirks are expressed as data here and applied during configuration.
+//!
+//! [provider database]: crate::provider
+
+use crate::context::Context;
+use crate::stock_str;
+use crate::transport::ConfiguredServerLoginParam;
+
+/// Provider-specific workarounds applied during configuration.
+#[derive(Debug)]
+pub(crate) struct Quirk {
+    /// Email address domains the quirk applies to.
+    ///
+    /// Patterns starting with `*` match all subdomains.
+    domains: &'static [&'static str],
+
+    /// IMAP server hostnames the quirk applies to.
+    ///
+    /// Patterns starting with `*` match all subdomains.
+    imap_hosts: &'static [&'static str],
+
+    /// Hint appended to the error if IMAP login fails,
+    /// e.g. because the provider requires an app-specific password.
+    pub app_password_hint: Option<fn(&Context) -> String>,
+
+    /// Prefix of all folders except INBOX, e.g. `INBOX.`.
+    pub folder_prefix: Option<&'static str>,
+
+    /// True if IMAP IDLE is known to be unreliable with the provider.
+    pub disable_idle: bool,
+}
+
+const QUIRKS: &[Quirk] = &[
+    // iCloud
+    Quirk {
+        domains: &["icloud.com", "me.com", "mac.com"],
+        imap_hosts: &["imap.mail.me.com"],
+        app_password_hint: Some(stock_str::app_password_hint_icloud),
+        folder_prefix: None,
+        disable_idle: false,
+    },
+    // Microsoft Exchange Online
+    Quirk {
+        domains: &[],
+        imap_hosts: &["outlook.office365.com", "*.outlook.com"],
+        app_password_hint: Some(stock_str::app_password_hint_exchange),
+        folder_prefix: None,
+        disable_idle: true,
+    },
+    // GoDaddy Workspace Email, folders are below INBOX.
+    Quirk {
+        domains: &[],
+        imap_hosts: &["*.secureserver.net"],
+        app_password_hint: None,
+        folder_prefix: Some("INBOX."),
+        disable_idle: false,
+    },
+];
+
+/// Returns true if `name` matches the domain `pattern`.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        name.ends_with(suffix)
+    } else {
+        pattern == name
+    }
+}
+
+/// Finds the quirk applying to the email `domain` or any of the IMAP servers.
+pub(crate) fn get_quirk(
+    domain: &str,
+    imap_servers: &[ConfiguredServerLoginParam],
+) -> Option<&'static Quirk> {
+    let domain = domain.to_lowercase();
+    QUIRKS.iter().find(|quirk| {
+        quirk
+            .domains
+            .iter()
+            .any(|pattern| matches_pattern(pattern, &domain))
+            || imap_servers.iter().any(|server| {
+                let host = server.connection.host.to_lowercase();
+                quirk
+                    .imap_hosts
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, &host))
+            })
+    })
+}
+
+impl Quirk {
+    /// Returns the watched folder name with the folder prefix applied.
+    ///
+    /// INBOX is never prefixed.
+    pub(crate) fn apply_folder_prefix(&self, folder: &str) -> String {
+        match self.folder_prefix {
+            Some(prefix)
+                if !folder.eq_ignore_ascii_case("INBOX") && !folder.starts_with(prefix) =>
+            {
+                format!("{prefix}{folder}")
+            }
+            _ => folder.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{ConnectionCandidate, ConnectionSecurity};
+
+    fn imap_server(host: &str) -> ConfiguredServerLoginParam {
+        ConfiguredServerLoginParam {
+            connection: ConnectionCandidate {
+                host: host.to_string(),
+                port: 993,
+                security: ConnectionSecurity::Tls,
+            },
+            user: "alice".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_get_quirk() {
+        let quirk = get_quirk("ICloud.com", &[]).unwrap();
+        assert!(quirk.app_password_hint.is_some());
+        assert!(!quirk.disable_idle);
+
+        let quirk = get_quirk("example.org", &[imap_server("outlook.office365.com")]).unwrap();
+        assert!(quirk.disable_idle);
+
+        assert!(get_quirk("example.org", &[imap_server("imap.example.org")]).is_none());
+    }
+
+    #[test]
+    fn test_apply_folder_prefix() {
+        let quirk = get_quirk("example.org", &[imap_server("imap.secureserver.net")]).unwrap();
+        assert_eq!(quirk.apply_folder_prefix("DeltaChat"), "INBOX.DeltaChat");
+        assert_eq!(
+            quirk.apply_folder_prefix("INBOX.DeltaChat"),
+            "INBOX.DeltaChat"
+        );
+        assert_eq!(quirk.apply_folder_prefix("INBOX"), "INBOX");
+    }
+}
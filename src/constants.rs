@@ -46,6 +46,45 @@ pub enum MediaQuality {
     Worse = 1,
 }
 
+/// How subjects of outgoing messages are chosen, see [`crate::config::Config::SubjectMode`].
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum SubjectMode {
+    /// "Re: " and the subject of the replied message,
+    /// the group name or "Message from <name>".
+    #[default] // also change Config.SubjectMode props(default) on changes
+    ThreadStable = 0,
+
+    /// First words of the message text.
+    FirstWords = 1,
+
+    /// Fixed string set in `Config::SubjectText`.
+    Fixed = 2,
+
+    /// Empty subject for encrypted messages, thread-stable subject otherwise.
+    EmptyWhenEncrypted = 3,
+}
+
+/// How copies of sent messages are kept on the server,
+/// see [`crate::config::Config::SentFolderStrategy`].
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum SentFolderStrategy {
+    /// Send a copy to self if `Config::BccSelf` is set.
+    #[default] // also change Config.SentFolderStrategy props(default) on changes
+    BccSelf = 0,
+
+    /// Append a copy to the Sent folder via IMAP.
+    Append = 1,
+
+    /// Do not keep a copy, for servers saving sent messages themselves.
+    None = 2,
+}
+
 pub const DC_HANDSHAKE_CONTINUE_NORMAL_PROCESSING: i32 = 0x01;
 pub const DC_HANDSHAKE_STOP_NORMAL_PROCESSING: i32 = 0x02;
 pub const DC_HANDSHAKE_ADD_DELETE_JOB: i32 = 0x04;
@@ -202,6 +241,18 @@ pub enum Chattype {
 /// Period between `sql::housekeeping()` runs.
 pub(crate) const HOUSEKEEPING_PERIOD: i64 = 24 * 60 * 60;
 
+/// Scheduled housekeeping does not run within this many seconds after IO was started,
+/// so that it does not slow down the app right after it is opened.
+pub(crate) const HOUSEKEEPING_START_IO_DELAY: i64 = 5 * 60;
+
+/// Scheduled housekeeping only runs if no message was sent or received
+/// for this many seconds.
+pub(crate) const HOUSEKEEPING_IDLE_PERIOD: i64 = 2 * 60;
+
+/// Scheduled housekeeping runs regardless of the app usage
+/// if it is overdue by this many seconds.
+pub(crate) const HOUSEKEEPING_MAX_POSTPONEMENT: i64 = HOUSEKEEPING_PERIOD;
+
 pub(crate) const BROADCAST_INCOMPATIBILITY_MSG: &str = r#"The up to now "experimental channels feature" is about to become an officially supported one. By that, privacy will be improved, it will become faster, and less traffic will be consumed.
 
 As we do not guarantee feature-stability for such experiments, this means, that you will need to create the channel again. 
@@ -248,4 +299,35 @@ fn test_mediaquality_values() {
         assert_eq!(MediaQuality::Balanced, MediaQuality::from_i32(0).unwrap());
         assert_eq!(MediaQuality::Worse, MediaQuality::from_i32(1).unwrap());
     }
+
+    #[test]
+    fn test_subjectmode_values() {
+        // values may be written to disk and must not change
+        assert_eq!(SubjectMode::ThreadStable, SubjectMode::default());
+        assert_eq!(SubjectMode::ThreadStable, SubjectMode::from_i32(0).unwrap());
+        assert_eq!(SubjectMode::FirstWords, SubjectMode::from_i32(1).unwrap());
+        assert_eq!(SubjectMode::Fixed, SubjectMode::from_i32(2).unwrap());
+        assert_eq!(
+            SubjectMode::EmptyWhenEncrypted,
+            SubjectMode::from_i32(3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sentfolderstrategy_values() {
+        // values may be written to disk and must not change
+        assert_eq!(SentFolderStrategy::BccSelf, SentFolderStrategy::default());
+        assert_eq!(
+            SentFolderStrategy::BccSelf,
+            SentFolderStrategy::from_i32(0).unwrap()
+        );
+        assert_eq!(
+            SentFolderStrategy::Append,
+            SentFolderStrategy::from_i32(1).unwrap()
+        );
+        assert_eq!(
+            SentFolderStrategy::None,
+            SentFolderStrategy::from_i32(2).unwrap()
+        );
+    }
 }
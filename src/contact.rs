@@ -38,7 +38,7 @@
 use crate::pgp::{addresses_from_public_key, merge_openpgp_certificates};
 use crate::sync::{self, Sync::*};
 use crate::tools::{SystemTime, duration_to_str, get_abs_path, normalize_text, time, to_lowercase};
-use crate::{chat, chatlist_events, ensure_and_debug_assert_ne, stock_str};
+use crate::{chat, chatlist_events, ensure_and_debug_assert_ne, securejoin, stock_str};
 
 /// Time during which a contact is considered as seen recently.
 const SEEN_RECENTLY_SECONDS: i64 = 600;
@@ -283,36 +283,67 @@ pub async fn make_vcard(context: &Context, contacts: &[ContactId]) -> Result<Str
     let now = time();
     let mut vcard_contacts = Vec::with_capacity(contacts.len());
     for id in contacts {
-        let c = Contact::get_by_id(context, *id).await?;
-        let key = c.public_key(context).await?.map(|k| k.to_base64());
-        let profile_image = match c.get_profile_image_ex(context, false).await? {
-            None => None,
-            Some(path) => tokio::fs::read(path)
-                .await
-                .log_err(context)
-                .ok()
-                .map(|data| base64::engine::general_purpose::STANDARD.encode(data)),
-        };
-        vcard_contacts.push(VcardContact {
-            addr: c.addr,
-            authname: c.authname,
-            key,
-            profile_image,
-            biography: Some(c.status).filter(|s| !s.is_empty()),
-            // Use the current time to not reveal our or contact's online time.
-            timestamp: Ok(now),
-        });
+        vcard_contacts.push(make_vcard_contact(context, *id, now).await?);
     }
+    Ok(make_vcard_string(&vcard_contacts))
+}
+
+/// Returns a vCard of the user containing the key, the avatar and an invite link,
+/// see [`Context::export_self_contact_card`].
+pub async fn make_self_contact_card(context: &Context) -> Result<String> {
+    let mut vcard_contact = make_vcard_contact(context, ContactId::SELF, time()).await?;
+    vcard_contact.invite_link = Some(securejoin::get_securejoin_qr(context, None).await?);
+    Ok(make_vcard_string(&[vcard_contact]))
+}
+
+impl Context {
+    /// Writes a contact card of the user to a file.
+    ///
+    /// The contact card is a vCard containing the address, the name, the key,
+    /// the avatar and an invite link of the user.
+    /// It can be published e.g. on a website,
+    /// other users can import it with [`crate::qr::check_qr`] to start a verified chat.
+    pub async fn export_self_contact_card(&self, path: &Path) -> Result<()> {
+        let vcard = make_self_contact_card(self).await?;
+        tokio::fs::write(path, vcard)
+            .await
+            .with_context(|| format!("Failed to write contact card to {}", path.display()))
+    }
+}
 
+async fn make_vcard_contact(context: &Context, id: ContactId, now: i64) -> Result<VcardContact> {
+    let c = Contact::get_by_id(context, id).await?;
+    let key = c.public_key(context).await?.map(|k| k.to_base64());
+    let profile_image = match c.get_profile_image_ex(context, false).await? {
+        None => None,
+        Some(path) => tokio::fs::read(path)
+            .await
+            .log_err(context)
+            .ok()
+            .map(|data| base64::engine::general_purpose::STANDARD.encode(data)),
+    };
+    Ok(VcardContact {
+        addr: c.addr,
+        authname: c.authname,
+        key,
+        profile_image,
+        biography: Some(c.status).filter(|s| !s.is_empty()),
+        invite_link: None,
+        // Use the current time to not reveal our or contact's online time.
+        timestamp: Ok(now),
+    })
+}
+
+fn make_vcard_string(vcard_contacts: &[VcardContact]) -> String {
     // XXX: newline at the end of vCard is trimmed
     // for compatibility with core <=1.155.3
     // Newer core should be able to deal with
     // trailing CRLF as the fix
     // <https://github.com/deltachat/deltachat-core-rust/pull/6522>
     // is merged.
-    Ok(contact_tools::make_vcard(&vcard_contacts)
+    contact_tools::make_vcard(vcard_contacts)
         .trim_end()
-        .to_string())
+        .to_string()
 }
 
 /// Imports public key into the public key store.
@@ -1376,6 +1407,67 @@ pub async fn get_all_blocked(context: &Context) -> Result<Vec<ContactId>> {
         Ok(list)
     }
 
+    /// Exports the blocked contacts as JSON,
+    /// to be imported with [`Contact::import_blocked()`].
+    ///
+    /// Blocked mailing lists are not exported.
+    pub async fn export_blocked(context: &Context) -> Result<String> {
+        let list = context
+            .sql
+            .query_map_vec(
+                "SELECT addr, fingerprint FROM contacts
+                 WHERE id>? AND blocked!=0 AND origin!=?
+                 ORDER BY id",
+                (ContactId::LAST_SPECIAL, Origin::MailinglistAddress),
+                |row| {
+                    let addr: String = row.get(0)?;
+                    let fingerprint: String = row.get(1)?;
+                    Ok(BlockedContact {
+                        addr,
+                        fingerprint: (!fingerprint.is_empty()).then_some(fingerprint),
+                    })
+                },
+            )
+            .await?;
+        Ok(serde_json::to_string(&list)?)
+    }
+
+    /// Blocks the contacts from a JSON block list
+    /// exported with [`Contact::export_blocked()`].
+    ///
+    /// Contacts that do not exist yet are created.
+    /// Blocking is synchronized to other devices as with [`Contact::block()`].
+    ///
+    /// Returns the number of newly blocked contacts.
+    pub async fn import_blocked(context: &Context, json: &str) -> Result<usize> {
+        let list: Vec<BlockedContact> = serde_json::from_str(json).context("Invalid block list")?;
+        let mut blocked_cnt: usize = 0;
+        for entry in list {
+            let contact_id = if let Some(fingerprint) = &entry.fingerprint {
+                Contact::add_or_lookup_ex(context, "", &entry.addr, fingerprint, Origin::Hidden)
+                    .await?
+                    .0
+            } else {
+                let Ok(addr) = ContactAddress::new(&entry.addr) else {
+                    warn!(
+                        context,
+                        "Skipping invalid address {:?} in block list.", entry.addr
+                    );
+                    continue;
+                };
+                Contact::add_or_lookup(context, "", &addr, Origin::Hidden)
+                    .await?
+                    .0
+            };
+            if contact_id.is_special() || Contact::get_by_id(context, contact_id).await?.blocked {
+                continue;
+            }
+            set_blocked(context, Sync, contact_id, true).await?;
+            blocked_cnt = blocked_cnt.saturating_add(1);
+        }
+        Ok(blocked_cnt)
+    }
+
     /// Returns a textual summary of the encryption state for the contact.
     ///
     /// This function returns a string explaining the encryption state
@@ -1833,6 +1925,100 @@ fn update_chat_names(
     Ok(())
 }
 
+impl Context {
+    /// Blocks all contacts from the given domain.
+    ///
+    /// Existing contacts from the domain are blocked immediately,
+    /// new contacts from the domain are blocked when their messages are received.
+    pub async fn block_domain(&self, domain: &str) -> Result<()> {
+        let domain = normalize_domain(domain)?;
+        self.sql
+            .execute(
+                "INSERT OR IGNORE INTO blocked_domains (domain) VALUES (?)",
+                (&domain,),
+            )
+            .await?;
+        let contact_ids = self
+            .sql
+            .query_map_vec(
+                "SELECT id FROM contacts
+                 WHERE id>? AND blocked=0
+                 AND substr(addr, instr(addr, '@')+1)=? COLLATE NOCASE",
+                (ContactId::LAST_SPECIAL, &domain),
+                |row| {
+                    let id: ContactId = row.get(0)?;
+                    Ok(id)
+                },
+            )
+            .await?;
+        for contact_id in contact_ids {
+            set_blocked(self, Sync, contact_id, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the domain from the list of blocked domains.
+    ///
+    /// Contacts blocked because of the domain stay blocked
+    /// and have to be unblocked with [`Contact::unblock()`].
+    pub async fn unblock_domain(&self, domain: &str) -> Result<()> {
+        let domain = normalize_domain(domain)?;
+        self.sql
+            .execute("DELETE FROM blocked_domains WHERE domain=?", (&domain,))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the list of blocked domains, sorted alphabetically.
+    pub async fn get_blocked_domains(&self) -> Result<Vec<String>> {
+        self.sql
+            .query_map_vec(
+                "SELECT domain FROM blocked_domains ORDER BY domain",
+                (),
+                |row| {
+                    let domain: String = row.get(0)?;
+                    Ok(domain)
+                },
+            )
+            .await
+    }
+}
+
+fn normalize_domain(domain: &str) -> Result<String> {
+    let domain = domain.trim().trim_start_matches('@').to_lowercase();
+    ensure!(
+        !domain.is_empty() && !domain.contains(['@', ' ']),
+        "Invalid domain {domain:?}"
+    );
+    Ok(domain)
+}
+
+/// Returns whether the domain of the address is blocked,
+/// see [`Context::block_domain()`].
+pub(crate) async fn is_addr_domain_blocked(context: &Context, addr: &str) -> Result<bool> {
+    let Some((_, domain)) = addr.rsplit_once('@') else {
+        return Ok(false);
+    };
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM blocked_domains WHERE domain=?",
+            (domain.to_lowercase(),),
+        )
+        .await
+}
+
+/// Entry of the JSON block list,
+/// see [`Contact::export_blocked()`] and [`Contact::import_blocked()`].
+#[derive(Debug, Serialize, Deserialize)]
+struct BlockedContact {
+    addr: String,
+
+    /// Fingerprint of the key-contact, hex-encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
+}
+
 pub(crate) async fn set_blocked(
     context: &Context,
     sync: sync::Sync,
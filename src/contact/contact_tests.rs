@@ -1437,3 +1437,46 @@ async fn test_name_changes() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_export_import_blocked() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let bob_id = alice0.add_or_lookup_contact_id(bob).await;
+    Contact::block(alice0, bob_id).await?;
+    let (spammer_id, _) = Contact::add_or_lookup(
+        alice0,
+        "",
+        &ContactAddress::new("spam@example.org")?,
+        Origin::IncomingUnknownFrom,
+    )
+    .await?;
+    Contact::block(alice0, spammer_id).await?;
+
+    let json = Contact::export_blocked(alice0).await?;
+    assert_eq!(Contact::import_blocked(alice1, &json).await?, 2);
+    let blocked = Contact::get_all_blocked(alice1).await?;
+    assert_eq!(blocked.len(), 2);
+    let mut addrs = Vec::new();
+    for contact_id in blocked {
+        let contact = Contact::get_by_id(alice1, contact_id).await?;
+        addrs.push((contact.get_addr().to_string(), contact.is_key_contact()));
+    }
+    addrs.sort();
+    assert_eq!(
+        addrs,
+        [
+            ("bob@example.net".to_string(), true),
+            ("spam@example.org".to_string(), false)
+        ]
+    );
+
+    // Already blocked contacts are not counted.
+    assert_eq!(Contact::import_blocked(alice1, &json).await?, 0);
+    assert!(Contact::import_blocked(alice1, "not json").await.is_err());
+
+    Ok(())
+}
@@ -4,38 +4,40 @@
 use std::ffi::OsString;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::{Arc, OnceLock, Weak};
 use std::time::Duration;
 
-use anyhow::{Result, bail, ensure};
+use anyhow::{Context as _, Result, bail, ensure};
 use async_channel::{self as channel, Receiver, Sender};
 use pgp::composed::SignedPublicKey;
 use ratelimit::Ratelimit;
 use tokio::sync::{Mutex, Notify, RwLock};
 
 use crate::chat::{ChatId, get_chat_cnt};
+use crate::chat_statistics::{ChatStatistics, ChatStatisticsKey};
 use crate::config::Config;
 use crate::constants::{self, DC_BACKGROUND_FETCH_QUOTA_CHECK_RATELIMIT, DC_VERSION_STR};
 use crate::contact::{Contact, ContactId};
 use crate::debug_logging::DebugLogging;
-use crate::events::{Event, EventEmitter, EventType, Events};
+use crate::events::{ErrorCode, Event, EventEmitter, EventKind, EventType, Events};
 use crate::imap::{Imap, ServerMetadata};
-use crate::log::warn;
+use crate::log::{LogExt, warn};
 use crate::logged_debug_assert;
 use crate::message::{self, MessageState, MsgId};
 use crate::net::tls::{SpkiHashStore, TlsSessionStore};
 use crate::peer_channels::Iroh;
 use crate::push::PushSubscriber;
+use crate::quiet_hours::QuietHours;
 use crate::quota::QuotaInfo;
 use crate::scheduler::{ConnectivityStore, SchedulerState};
 use crate::sql::Sql;
 use crate::stock_str::StockStrings;
 use crate::tools::{self, duration_to_str, time, time_elapsed};
 use crate::transport::ConfiguredLoginParam;
-use crate::{chatlist_events, stats};
+use crate::{chatlist_events, smtp, stats};
 
-pub use crate::scheduler::connectivity::Connectivity;
+pub use crate::scheduler::connectivity::{ConnectionConnectivity, ConnectionKind, Connectivity};
 
 /// Builder for the [`Context`].
 ///
@@ -235,6 +237,8 @@ pub struct InnerContext {
     pub(crate) wrong_pw_warning_mutex: Mutex<()>,
     /// Mutex to prevent running housekeeping from multiple threads at once.
     pub(crate) housekeeping_mutex: Mutex<()>,
+    /// Mutex to prevent concurrent app-lock PIN checks from getting extra attempts.
+    pub(crate) applock_mutex: Mutex<()>,
 
     /// Mutex to prevent multiple IMAP loops from fetching the messages at once.
     ///
@@ -264,6 +268,23 @@ pub struct InnerContext {
     /// <https://datatracker.ietf.org/doc/html/rfc2971>
     pub(crate) server_id: RwLock<Option<HashMap<String, String>>>,
 
+    /// Results of connections through proxies by proxy URL,
+    /// see [`Context::get_proxy_health`].
+    pub(crate) proxy_health: RwLock<HashMap<String, crate::net::ProxyHealth>>,
+
+    /// Cached results of [`ChatId::get_statistics`].
+    pub(crate) chat_statistics: RwLock<HashMap<ChatId, (ChatStatisticsKey, ChatStatistics)>>,
+
+    /// Cached incoming message filters with compiled patterns,
+    /// `None` if they need to be reloaded from the database.
+    pub(crate) msg_filters: RwLock<Option<Arc<Vec<crate::filters::CompiledFilter>>>>,
+
+    /// Network traffic not yet added to the daily totals in the database.
+    pub(crate) bandwidth: crate::net::bandwidth::BandwidthCounters,
+
+    /// Network profile set by the UI, see [`Context::set_network_profile`].
+    pub(crate) network_profile: RwLock<crate::net::NetworkProfile>,
+
     /// IMAP METADATA.
     pub(crate) metadata: RwLock<Option<ServerMetadata>>,
 
@@ -275,10 +296,11 @@ pub struct InnerContext {
 
     creation_time: tools::Time,
 
-    /// The text of the last error logged and emitted as an event.
+    /// The code and the text of the last error logged and emitted as an event
+    /// or of the last failed configuration.
     /// If the ui wants to display an error after a failure,
     /// `last_error` should be used to avoid races with the event thread.
-    pub(crate) last_error: parking_lot::RwLock<String>,
+    pub(crate) last_error: parking_lot::RwLock<(ErrorCode, String)>,
 
     /// It's not possible to emit migration errors as an event,
     /// because at the time of the migration, there is no event emitter yet.
@@ -293,6 +315,12 @@ pub struct InnerContext {
     /// because the lock is used from synchronous [`Context::emit_event`].
     pub(crate) debug_logging: std::sync::RwLock<Option<DebugLogging>>,
 
+    /// Parsed [`Config::QuietHours`], see [`Context::is_quiet_hours()`].
+    ///
+    /// Standard RwLock is used because the lock is used
+    /// from synchronous [`Context::is_quiet_hours()`].
+    pub(crate) quiet_hours: std::sync::RwLock<Option<QuietHours>>,
+
     /// Push subscriber to store device token
     /// and register for heartbeat notifications.
     pub(crate) push_subscriber: PushSubscriber,
@@ -300,6 +328,21 @@ pub struct InnerContext {
     /// True if account has subscribed to push notifications via IMAP.
     pub(crate) push_subscribed: AtomicBool,
 
+    /// Timestamp of the last [`Context::start_io`] call, 0 if IO was not started yet.
+    ///
+    /// Scheduled housekeeping is postponed for some time after IO is started.
+    pub(crate) io_start_timestamp: AtomicI64,
+
+    /// Timestamp of the last sent or received message.
+    ///
+    /// Scheduled housekeeping is postponed while messages are sent or received.
+    pub(crate) last_activity_timestamp: AtomicI64,
+
+    /// Time when sending of read markers to other devices was last triggered
+    /// and whether a delayed trigger is scheduled,
+    /// see [`crate::chat::set_last_read_msg_ex`].
+    pub(crate) last_read_sync: parking_lot::Mutex<(Option<tools::Time>, bool)>,
+
     /// TLS session resumption cache.
     pub(crate) tls_session_store: TlsSessionStore,
 
@@ -313,6 +356,10 @@ pub struct InnerContext {
     /// Iroh for realtime peer channels.
     pub(crate) iroh: Arc<RwLock<Option<Iroh>>>,
 
+    /// Connectivity of the Iroh endpoint,
+    /// see [`Context::get_connectivity_per_connection()`].
+    pub(crate) iroh_connectivity: ConnectivityStore,
+
     /// The own fingerprint, if it was computed already.
     /// tokio::sync::OnceCell would be possible to use, but overkill for our usecase;
     /// the standard library's OnceLock is enough, and it's a lot smaller in memory.
@@ -484,6 +531,7 @@ pub(crate) fn with_blobdir(
             sql: Sql::new(dbfile),
             wrong_pw_warning_mutex: Mutex::new(()),
             housekeeping_mutex: Mutex::new(()),
+            applock_mutex: Mutex::new(()),
             fetch_msgs_mutex: Mutex::new(()),
             translated_stockstrings: stockstrings,
             events,
@@ -492,16 +540,26 @@ pub(crate) fn with_blobdir(
             quota: RwLock::new(BTreeMap::new()),
             new_msgs_notify,
             server_id: RwLock::new(None),
+            proxy_health: RwLock::new(HashMap::new()),
+            chat_statistics: RwLock::new(HashMap::new()),
+            msg_filters: RwLock::new(None),
+            bandwidth: Default::default(),
+            network_profile: RwLock::new(Default::default()),
             metadata: RwLock::new(None),
             creation_time: tools::Time::now(),
-            last_error: parking_lot::RwLock::new("".to_string()),
+            last_error: parking_lot::RwLock::new((ErrorCode::Other, "".to_string())),
             migration_error: parking_lot::RwLock::new(None),
             debug_logging: std::sync::RwLock::new(None),
+            quiet_hours: std::sync::RwLock::new(None),
             push_subscriber,
             push_subscribed: AtomicBool::new(false),
+            io_start_timestamp: AtomicI64::new(0),
+            last_activity_timestamp: AtomicI64::new(0),
+            last_read_sync: parking_lot::Mutex::new((None, false)),
             tls_session_store: TlsSessionStore::new(),
             spki_hash_store: SpkiHashStore::new(),
             iroh: Arc::new(RwLock::new(None)),
+            iroh_connectivity: ConnectivityStore::default(),
             self_fingerprint: OnceLock::new(),
             self_public_key: Mutex::new(None),
             connectivities: parking_lot::Mutex::new(Vec::new()),
@@ -525,15 +583,21 @@ pub async fn start_io(&self) {
         // iOS starts a separate process for receiving notifications and if the user concurrently
         // starts the app, the UI process opens the database but waits with calling start_io()
         // until the notifications process finishes.
-        // Now, some configs may have changed, so, we need to invalidate the cache.
+        // Now, some configs or filters may have changed, so, we need to invalidate the caches.
         self.sql.config_cache.write().await.clear();
+        *self.msg_filters.write().await = None;
 
+        self.io_start_timestamp.store(time(), Ordering::Relaxed);
         self.scheduler.start(self).await;
     }
 
     /// Stops the IO scheduler.
     pub async fn stop_io(&self) {
         self.scheduler.stop(self).await;
+        crate::net::bandwidth::flush_bandwidth_stats(self)
+            .await
+            .log_err(self)
+            .ok();
         if let Some(iroh) = self.iroh.write().await.take() {
             // Close all QUIC connections.
 
@@ -549,6 +613,25 @@ pub async fn stop_io(&self) {
         }
     }
 
+    /// Runs housekeeping now, regardless of when it ran last.
+    ///
+    /// Housekeeping removes unused blob files, prunes old data and optimizes the database.
+    /// It is normally scheduled automatically once a day when the app is not busy;
+    /// this can be used to run it at a time convenient for the user instead.
+    /// [`EventType::HousekeepingProgress`] events are emitted while it runs.
+    ///
+    /// Does nothing if housekeeping is already running.
+    pub async fn run_housekeeping_now(&self) -> Result<()> {
+        crate::sql::housekeeping(self).await
+    }
+
+    /// Records that a message was sent or received,
+    /// so that scheduled housekeeping does not slow it down.
+    pub(crate) fn set_last_activity(&self) {
+        self.last_activity_timestamp
+            .store(time(), Ordering::Relaxed);
+    }
+
     /// Restarts the IO scheduler if it was running before
     /// when it is not running this is an no-op
     pub async fn restart_io_if_running(&self) {
@@ -560,6 +643,7 @@ pub async fn maybe_network(&self) {
         if let Some(ref iroh) = *self.iroh.read().await {
             iroh.network_change().await;
         }
+        tools::reset_clock_skew_check(self).await.log_err(self).ok();
         self.scheduler.maybe_network().await;
     }
 
@@ -763,6 +847,21 @@ pub fn get_event_emitter(&self) -> EventEmitter {
         self.events.get_emitter()
     }
 
+    /// Returns a receiver for emitted events
+    /// which first receives up to `n` events emitted before its creation.
+    pub fn get_event_emitter_with_replay(&self, n: usize) -> EventEmitter {
+        self.events.get_emitter_with_replay(n)
+    }
+
+    /// Returns a receiver for emitted events of the given kinds.
+    ///
+    /// Each receiver created this way receives all events of the selected kinds,
+    /// e.g. a notification process may only subscribe to
+    /// [`EventKind::IncomingMsg`] and [`EventKind::ConnectivityChanged`].
+    pub fn subscribe_events(&self, filter: impl IntoIterator<Item = EventKind>) -> EventEmitter {
+        self.events.subscribe(filter)
+    }
+
     /// Get the ID of this context.
     pub fn get_id(&self) -> u32 {
         self.id
@@ -874,6 +973,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
 
         // insert values
         res.insert("bot", self.get_config_int(Config::Bot).await?.to_string());
+        res.insert(
+            "save_mime_headers",
+            self.get_config_bool(Config::SaveMimeHeaders)
+                .await?
+                .to_string(),
+        );
         res.insert("number_of_chats", chats.to_string());
         res.insert("number_of_chat_messages", unblocked_msgs.to_string());
         res.insert("messages_in_contact_requests", request_msgs.to_string());
@@ -940,10 +1045,61 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "data_saver",
+            self.get_config_bool(Config::DataSaver).await?.to_string(),
+        );
+        res.insert(
+            "network_profile",
+            format!("{:?}", self.get_network_profile().await),
+        );
         res.insert("mdns_enabled", mdns_enabled.to_string());
         res.insert("bcc_self", bcc_self.to_string());
+        res.insert(
+            "sent_folder_strategy",
+            self.get_config_int(Config::SentFolderStrategy)
+                .await?
+                .to_string(),
+        );
         res.insert("sync_msgs", sync_msgs.to_string());
         res.insert("disable_idle", disable_idle.to_string());
+        res.insert(
+            "watch_extra_folders",
+            self.get_config(Config::WatchExtraFolders)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string())
+                .replace('\n', ","),
+        );
+        res.insert(
+            "managed_keys",
+            self.get_config(Config::ManagedKeys)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string()),
+        );
+        res.insert(
+            "applock_failed_attempts",
+            self.get_config_int(Config::ApplockFailedAttempts)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "applock_last_failed_attempt",
+            self.get_config_i64(Config::ApplockLastFailedAttempt)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "initial_fetch_count",
+            self.get_config_int(Config::InitialFetchCount)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "initial_fetch_since_days",
+            self.get_config_int(Config::InitialFetchSinceDays)
+                .await?
+                .to_string(),
+        );
         res.insert("private_key_count", prv_key_cnt.to_string());
         res.insert("public_key_count", pub_key_cnt.to_string());
         res.insert(
@@ -956,6 +1112,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "mark_expunged_msgs",
+            self.get_config_bool(Config::MarkExpungedMsgs)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "last_housekeeping",
             self.get_config_int(Config::LastHousekeeping)
@@ -968,10 +1130,26 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "clock_skew",
+            self.get_config_i64(Config::ClockSkew).await?.to_string(),
+        );
+        res.insert(
+            "last_clock_skew_check",
+            self.get_config_i64(Config::LastClockSkewCheck)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "debug_logging",
             self.get_config_int(Config::DebugLogging).await?.to_string(),
         );
+        res.insert(
+            "quiet_hours",
+            self.get_config(Config::QuietHours)
+                .await?
+                .unwrap_or_default(),
+        );
         res.insert(
             "last_msg_id",
             self.get_config_int(Config::LastMsgId).await?.to_string(),
@@ -986,6 +1164,56 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "smtp_max_message_size",
+            smtp::get_max_message_size(self).await?.to_string(),
+        );
+        res.insert(
+            "max_attachment_bytes",
+            self.get_config_u64(Config::MaxAttachmentBytes)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "attachment_upload_url",
+            self.get_config(Config::AttachmentUploadUrl)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string()),
+        );
+        res.insert(
+            "p2p_attachment_bytes",
+            self.get_config_u64(Config::P2pAttachmentBytes)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "lan_messaging",
+            self.get_config_bool(Config::LanMessaging)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "minimal_headers",
+            self.get_config_bool(Config::MinimalHeaders)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "subject_mode",
+            self.get_config_int(Config::SubjectMode).await?.to_string(),
+        );
+        res.insert(
+            "invite_link_domain",
+            self.get_config(Config::InviteLinkDomain)
+                .await?
+                .unwrap_or_default(),
+        );
+        res.insert(
+            "coalesce_member_changes",
+            self.get_config_bool(Config::CoalesceMemberChanges)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "donation_request_next_check",
             self.get_config_i64(Config::DonationRequestNextCheck)
@@ -1234,6 +1462,57 @@ pub async fn search_msgs(&self, chat_id: Option<ChatId>, query: &str) -> Result<
         Ok(list)
     }
 
+    /// Wipes the profile, e.g. when a duress PIN is entered.
+    ///
+    /// Private keys and transport credentials are deleted
+    /// while `secure_delete` makes SQLite overwrite them.
+    /// Then the first page of the database file,
+    /// containing the salt SQLCipher derives the encryption key with,
+    /// is overwritten with random bytes,
+    /// so the database can't be decrypted even with the passphrase.
+    /// Finally the database and all blobs are deleted.
+    /// Files are not overwritten completely so that this finishes quickly.
+    ///
+    /// Files are overwritten and removed, but there is no guarantee
+    /// that the data can't be recovered from SSDs or flash storage
+    /// as these may keep old copies of the overwritten blocks.
+    ///
+    /// The context is unusable afterwards.
+    /// Use `Accounts::secure_wipe_account()` to remove the account as well.
+    pub async fn secure_wipe(&self) -> Result<()> {
+        self.stop_io().await;
+        self.sql
+            .transaction(|transaction| {
+                transaction.execute("DELETE FROM keypairs", ())?;
+                transaction.execute("DELETE FROM transports", ())?;
+                Ok(())
+            })
+            .await
+            .log_err(self)
+            .ok();
+        self.sql.close().await;
+
+        let dbfile = &self.sql.dbfile;
+        let walfile = Self::derive_walfile(dbfile);
+        for path in [dbfile, &walfile] {
+            if let Err(err) = shred_header(path).await {
+                warn!(self, "Cannot overwrite {}: {err:#}.", path.display());
+            }
+        }
+        for path in [dbfile, &walfile] {
+            match tokio::fs::remove_file(path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err).context(format!("Cannot delete {}", path.display()));
+                }
+                _ => (),
+            }
+        }
+        tokio::fs::remove_dir_all(self.get_blobdir())
+            .await
+            .context("Cannot delete blobdir")?;
+        Ok(())
+    }
+
     pub(crate) fn derive_blobdir(dbfile: &Path) -> PathBuf {
         let mut blob_fname = OsString::new();
         blob_fname.push(dbfile.file_name().unwrap_or_default());
@@ -1249,5 +1528,22 @@ pub(crate) fn derive_walfile(dbfile: &Path) -> PathBuf {
     }
 }
 
+/// Overwrites the first page of the file with random bytes if the file exists.
+async fn shred_header(path: &Path) -> Result<()> {
+    use tokio::io::AsyncWriteExt as _;
+
+    let mut file = match tokio::fs::OpenOptions::new().write(true).open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let len = file.metadata().await?.len().min(4096);
+    let mut noise = vec![0u8; usize::try_from(len)?];
+    rand::fill(&mut noise[..]);
+    file.write_all(&noise).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod context_tests;
@@ -280,6 +280,8 @@ async fn test_get_info_completeness() {
         "mail_security",
         "notify_about_wrong_pw",
         "selfstatus",
+        "email_signature",
+        "subject_text",
         "send_server",
         "send_user",
         "send_pw",
@@ -296,9 +298,14 @@ async fn test_get_info_completeness() {
         "webxdc_integration",
         "device_token",
         "encrypted_device_token",
+        "unified_push_endpoint", // Capability URL, don't leak it to the logs.
+        "unified_push_private_key", // Secret, don't leak it to the logs.
+        "unified_push_auth",     // Secret, don't leak it to the logs.
         "stats_last_update",
         "stats_last_old_contact_id",
         "simulate_receive_imf_error", // only used in tests
+        "attachment_upload_token",    // Secret, don't leak it to the logs.
+        "applock_pin_hash",           // Secret, don't leak it to the logs.
     ];
     let t = TestContext::new().await;
     let info = t.get_info().await.unwrap();
@@ -228,8 +228,14 @@ fn try_decrypt_with_auth_token(
 ) -> Result<Option<PlainSessionKey>> {
     // ORDER BY id DESC to query the most-recently saved tokens are returned first.
     // This improves performance when Bob scans a QR code that was just created.
-    let mut stmt = conn.prepare("SELECT token FROM tokens WHERE namespc=? ORDER BY id DESC")?;
-    let mut rows = stmt.query((Namespace::Auth,))?;
+    // Used single-use tokens are included to report their reuse.
+    let mut stmt =
+        conn.prepare("SELECT token FROM tokens WHERE namespc IN (?, ?, ?) ORDER BY id DESC")?;
+    let mut rows = stmt.query((
+        Namespace::Auth,
+        Namespace::SingleUseAuth,
+        Namespace::UsedSingleUseAuth,
+    ))?;
     while let Some(row) = rows.next()? {
         let token: String = row.get(0)?;
         let shared_secret = format!("securejoin/{self_fingerprint}/{token}");
@@ -0,0 +1,206 @@
+//! # Account health check.
+//!
+//! Performs live checks of the configured transports
+//! to find out why sending or receiving messages does not work,
+//! e.g. for support requests.
+
+use anyhow::Result;
+
+use crate::context::Context;
+use crate::imap::{Imap, utf7};
+use crate::net::NetworkProfile;
+use crate::net::proxy::ProxyConfig;
+use crate::push::NotifyState;
+use crate::smtp::Smtp;
+use crate::tools::{measure_clock_skew, time};
+use crate::transport::ConfiguredLoginParam;
+
+/// Clock skew in seconds above which the local clock is reported as wrong.
+pub const MAX_CLOCK_SKEW: i64 = 5 * 60;
+
+/// Results of the checks of a single transport.
+#[derive(Debug)]
+pub struct TransportDiagnostics {
+    /// Email address of the transport.
+    pub addr: String,
+    /// Error of logging in to the IMAP server, `None` if login succeeded.
+    pub imap_error: Option<String>,
+    /// Error of logging in to the SMTP server, `None` if login succeeded.
+    pub smtp_error: Option<String>,
+    /// Watched folders that do not exist on the IMAP server.
+    /// Empty if the IMAP login failed.
+    pub missing_folders: Vec<String>,
+    /// Days until the TLS certificate of the IMAP server expires,
+    /// negative if it has expired already.
+    /// `None` if unknown, e.g. because certificate checks are disabled.
+    pub tls_cert_days_left: Option<i64>,
+}
+
+/// Account health check report, see [`Context::run_diagnostics`].
+#[derive(Debug)]
+pub struct DiagnosticsReport {
+    /// Results of the checks of all transports, the primary transport first.
+    pub transports: Vec<TransportDiagnostics>,
+    /// Seconds the local clock is ahead of the clock of the primary IMAP server host,
+    /// negative if it is behind.
+    /// `None` if the server time could not be determined.
+    pub clock_skew: Option<i64>,
+    /// Push notification state.
+    pub push_state: NotifyState,
+}
+
+impl std::fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Diagnostics:")?;
+        for transport in &self.transports {
+            writeln!(f, "[Transport]: {}", transport.addr)?;
+            match &transport.imap_error {
+                Some(err) => writeln!(f, "   IMAP login: failed: {err}")?,
+                None => writeln!(f, "   IMAP login: ok")?,
+            }
+            match &transport.smtp_error {
+                Some(err) => writeln!(f, "   SMTP login: failed: {err}")?,
+                None => writeln!(f, "   SMTP login: ok")?,
+            }
+            for folder in &transport.missing_folders {
+                writeln!(f, "   Missing folder: {folder}")?;
+            }
+            match transport.tls_cert_days_left {
+                Some(days) if days < 0 => writeln!(f, "   TLS certificate: expired")?,
+                Some(days) => writeln!(f, "   TLS certificate: expires in {days} days")?,
+                None => writeln!(f, "   TLS certificate: unknown")?,
+            }
+        }
+        match self.clock_skew {
+            Some(skew) if skew.abs() > MAX_CLOCK_SKEW => {
+                writeln!(f, "[Clock Skew]: {skew} seconds, check the device clock")?
+            }
+            Some(skew) => writeln!(f, "[Clock Skew]: {skew} seconds")?,
+            None => writeln!(f, "[Clock Skew]: unknown")?,
+        }
+        writeln!(f, "[Push Notifications]: {:?}", self.push_state)?;
+        Ok(())
+    }
+}
+
+impl Context {
+    /// Checks the configured transports with live connections
+    /// and returns a report that can be used to find out why the account does not work.
+    ///
+    /// This logs in to all IMAP and SMTP servers,
+    /// checks that the watched folders exist,
+    /// compares the local clock with the server clock
+    /// and reports TLS certificate expiration and push notification state.
+    ///
+    /// No connections are attempted if the network profile is [`NetworkProfile::Offline`].
+    pub async fn run_diagnostics(&self) -> Result<DiagnosticsReport> {
+        let offline = self.get_network_profile().await == NetworkProfile::Offline;
+
+        let mut params = ConfiguredLoginParam::load_all(self).await?;
+        let primary_addr = self.get_primary_self_addr().await?;
+        params.sort_by_key(|(_id, param)| param.addr != primary_addr);
+
+        let mut clock_skew = None;
+        let mut transports = Vec::with_capacity(params.len());
+        for (transport_id, param) in params {
+            if offline {
+                let err = "Network profile is offline.".to_string();
+                transports.push(TransportDiagnostics {
+                    addr: param.addr,
+                    imap_error: Some(err.clone()),
+                    smtp_error: Some(err),
+                    missing_folders: Vec::new(),
+                    tls_cert_days_left: None,
+                });
+                continue;
+            }
+            if transports.is_empty() {
+                clock_skew = match measure_clock_skew(self, &param).await {
+                    Ok(skew) => Some(skew),
+                    Err(err) => {
+                        info!(self, "Diagnostics: Cannot measure clock skew: {err:#}.");
+                        None
+                    }
+                };
+            }
+            transports.push(check_transport(self, transport_id, param).await?);
+        }
+
+        Ok(DiagnosticsReport {
+            transports,
+            clock_skew,
+            push_state: self.push_state().await,
+        })
+    }
+}
+
+/// Logs in to the IMAP and SMTP servers of a transport.
+async fn check_transport(
+    context: &Context,
+    transport_id: u32,
+    param: ConfiguredLoginParam,
+) -> Result<TransportDiagnostics> {
+    let addr = param.addr.clone();
+    let proxy_config = ProxyConfig::load(context).await?;
+    let strict_tls = param.strict_tls(proxy_config.is_some());
+
+    let mut smtp = Smtp::new();
+    let smtp_error = smtp
+        .connect(
+            context,
+            &param.smtp,
+            &param.smtp_password,
+            &proxy_config,
+            &param.addr,
+            strict_tls,
+        )
+        .await
+        .err()
+        .map(|err| format!("{err:#}"));
+    smtp.disconnect();
+
+    let imap_hosts: Vec<String> = param
+        .imap
+        .iter()
+        .map(|lp| lp.connection.host.clone())
+        .collect();
+    let (_idle_interrupt_sender, idle_interrupt_receiver) = async_channel::bounded(1);
+    let mut imap = Imap::new(context, transport_id, param, idle_interrupt_receiver).await?;
+    let configuring = false;
+    let mut missing_folders = Vec::new();
+    let imap_error = match imap.connect(context, configuring).await {
+        Ok(mut session) => match session.list_folders().await {
+            Ok(folders) => {
+                let folder = &imap.folder;
+                let exists = folders.iter().any(|name| {
+                    utf7::from_server_name(name.name()) == *folder
+                        || folder.eq_ignore_ascii_case("INBOX")
+                            && name.name().eq_ignore_ascii_case("INBOX")
+                });
+                if !exists {
+                    missing_folders.push(folder.clone());
+                }
+                None
+            }
+            Err(err) => Some(format!("{err:#}")),
+        },
+        Err(err) => Some(format!("{err:#}")),
+    };
+
+    let now = time();
+    let tls_cert_days_left = imap_hosts
+        .iter()
+        .find_map(|host| context.spki_hash_store.get_cert_expiry(host))
+        .map(|not_after| not_after.saturating_sub(now).div_euclid(24 * 60 * 60));
+
+    Ok(TransportDiagnostics {
+        addr,
+        imap_error,
+        smtp_error,
+        missing_folders,
+        tls_cert_days_left,
+    })
+}
+
+#[cfg(test)]
+mod diagnostics_tests;
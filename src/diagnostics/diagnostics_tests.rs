@@ -0,0 +1,45 @@
+use super::*;
+use crate::test_utils::TestContext;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_diagnostics_offline() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    t.set_network_profile(NetworkProfile::Offline).await;
+
+    let report = t.run_diagnostics().await?;
+    assert_eq!(report.transports.len(), 1);
+    let transport = &report.transports[0];
+    assert_eq!(transport.addr, "alice@example.org");
+    assert!(transport.imap_error.is_some());
+    assert!(transport.smtp_error.is_some());
+    assert!(transport.missing_folders.is_empty());
+    assert_eq!(report.clock_skew, None);
+    assert_eq!(report.push_state, NotifyState::NotConnected);
+
+    let text = report.to_string();
+    assert!(text.contains("[Transport]: alice@example.org"));
+    assert!(text.contains("IMAP login: failed"));
+    assert!(text.contains("[Clock Skew]: unknown"));
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_report_display() {
+    let report = DiagnosticsReport {
+        transports: vec![TransportDiagnostics {
+            addr: "alice@example.org".to_string(),
+            imap_error: None,
+            smtp_error: None,
+            missing_folders: vec!["DeltaChat".to_string()],
+            tls_cert_days_left: Some(-1),
+        }],
+        clock_skew: Some(MAX_CLOCK_SKEW + 1),
+        push_state: NotifyState::Heartbeat,
+    };
+    let text = report.to_string();
+    assert!(text.contains("IMAP login: ok"));
+    assert!(text.contains("Missing folder: DeltaChat"));
+    assert!(text.contains("TLS certificate: expired"));
+    assert!(text.contains("check the device clock"));
+    assert!(text.contains("[Push Notifications]: Heartbeat"));
+}
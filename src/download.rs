@@ -1,20 +1,29 @@
 //! # Download large messages manually.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use anyhow::{Result, anyhow, bail, ensure};
+use anyhow::{Context as _, Result, anyhow, bail, ensure};
+use async_channel as channel;
 use deltachat_derive::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 use crate::context::Context;
+use crate::imap::Imap;
 use crate::imap::session::Session;
 use crate::log::warn;
 use crate::message::{self, Message, MsgId, rfc724_mid_exists};
+use crate::mimeparser::MimeMessage;
+use crate::param::Param;
+use crate::receive_imf::ReceivedMsg;
 use crate::{EventType, chatlist_events, ephemeral};
 
+pub(crate) mod p2p;
+mod partial;
+pub(crate) use partial::has_skipped_attachment;
 pub(crate) mod post_msg_metadata;
 pub(crate) use post_msg_metadata::PostMsgMetadata;
+pub(crate) mod upload;
 
 /// From this point onward outgoing messages are considered large
 /// and get a Pre-Message, which announces the Post-Message.
@@ -26,6 +35,28 @@
 /// Max size for pre messages. A warning is emitted when this is exceeded.
 pub(crate) const PRE_MSG_SIZE_WARNING_THRESHOLD: usize = 150_000;
 
+/// Max. size (in bytes) of messages downloaded automatically
+/// if [`Config::DataSaver`] is enabled or the network is metered.
+pub const DATA_SAVER_DOWNLOAD_LIMIT: u32 = 163_840;
+
+/// Returns the max. size of messages downloaded automatically, `None` if unlimited.
+///
+/// This is [`Config::DownloadLimit`],
+/// lowered to [`DATA_SAVER_DOWNLOAD_LIMIT`] if [`Config::DataSaver`] is enabled
+/// or the network is metered.
+pub(crate) async fn download_limit(context: &Context) -> Result<Option<u32>> {
+    let download_limit: Option<u32> = context
+        .get_config_parsed(Config::DownloadLimit)
+        .await?
+        .filter(|&l| 0 < l);
+    if context.is_data_saving().await? {
+        let download_limit = download_limit.unwrap_or(u32::MAX);
+        Ok(Some(download_limit.min(DATA_SAVER_DOWNLOAD_LIMIT)))
+    } else {
+        Ok(download_limit)
+    }
+}
+
 /// Download state of the message.
 #[derive(
     Debug,
@@ -94,6 +125,28 @@ pub async fn download_full(self, context: &Context) -> Result<()> {
         Ok(())
     }
 
+    /// Downloads the original message from the server again
+    /// and restores its attachment,
+    /// e.g. after the blob was deleted locally to free space.
+    ///
+    /// The message is fetched on a dedicated connection
+    /// and is not received again, only its attachment is restored.
+    /// Fails if the message has no attachment
+    /// or is not available on the server anymore,
+    /// e.g. because it was deleted there because of `delete_server_after`.
+    pub async fn redownload(self, context: &Context) -> Result<()> {
+        let mut msg = Message::load_from_db(context, self).await?;
+        ensure!(
+            msg.download_state() == DownloadState::Done,
+            "Message is not downloaded fully."
+        );
+        ensure!(msg.param.exists(Param::File), "Message has no attachment.");
+
+        let mut connection = Imap::new_configured(context, channel::bounded(1).1).await?;
+        let mut session = connection.prepare(context).await?;
+        redownload_attachment(context, &mut session, &mut msg).await
+    }
+
     /// Updates the message download state. Returns `Ok` if the message doesn't exist anymore or has
     /// the download state up to date.
     pub(crate) async fn update_download_state(
@@ -141,30 +194,95 @@ pub(crate) async fn download_msg(
     rfc724_mid: String,
     session: &mut Session,
 ) -> Result<Option<()>> {
-    let transport_id = session.transport_id();
-    let row = context
+    let row = get_server_location(context, &rfc724_mid, session.transport_id()).await?;
+    let Some((server_uid, server_folder)) = row else {
+        // No IMAP record found, the message is not available on this transport.
+        delete_from_available_post_msgs(context, &rfc724_mid).await?;
+        return Ok(None);
+    };
+    if let Some(msg_id) = rfc724_mid_exists(context, &rfc724_mid).await?
+        && has_skipped_attachment(context, msg_id).await?
+    {
+        let _fetch_msgs_lock_guard = context.fetch_msgs_mutex.lock().await;
+        session
+            .fetch_msg_attachment(context, &server_folder, server_uid, msg_id)
+            .await?;
+    } else {
+        Box::pin(session.fetch_single_msg(context, &server_folder, server_uid, rfc724_mid)).await?;
+    }
+
+    maybe_interrupt_inbox_after_download(context, session).await?;
+
+    Ok(Some(()))
+}
+
+/// Returns the UID and folder of the message on the given transport,
+/// `None` if the message is not available there.
+pub(crate) async fn get_server_location(
+    context: &Context,
+    rfc724_mid: &str,
+    transport_id: u32,
+) -> Result<Option<(u32, String)>> {
+    context
         .sql
         .query_row_optional(
             "SELECT uid, folder FROM imap
              WHERE rfc724_mid=? AND target!=''
              AND transport_id=?
              LIMIT 1",
-            (&rfc724_mid, transport_id),
+            (rfc724_mid, transport_id),
             |row| {
                 let server_uid: u32 = row.get(0)?;
                 let server_folder: String = row.get(1)?;
                 Ok((server_uid, server_folder))
             },
         )
-        .await?;
+        .await
+}
 
-    let Some((server_uid, server_folder)) = row else {
-        // No IMAP record found, the message is not available on this transport.
-        delete_from_available_post_msgs(context, &rfc724_mid).await?;
-        return Ok(None);
+/// Downloads the original message of `msg` from the server again using `session`
+/// and restores its attachment, see [`MsgId::redownload()`].
+pub(crate) async fn redownload_attachment(
+    context: &Context,
+    session: &mut Session,
+    msg: &mut Message,
+) -> Result<()> {
+    let (server_uid, server_folder) =
+        get_server_location(context, msg.rfc724_mid(), session.transport_id())
+            .await?
+            .context("Message is not available on the server anymore.")?;
+    let body = session
+        .peek_single_msg(context, &server_folder, server_uid)
+        .await?
+        .context("Message is not available on the server anymore.")?;
+    restore_attachment(context, msg, &body).await
+}
+
+/// Restores the attachment of `msg` from the raw original message `body`.
+async fn restore_attachment(context: &Context, msg: &mut Message, body: &[u8]) -> Result<()> {
+    let mime_message = MimeMessage::from_bytes(context, body).await?;
+    let filename = msg.param.get(Param::Filename);
+    let mut attachments = mime_message
+        .parts
+        .iter()
+        .filter(|part| part.param.exists(Param::File));
+    let part = if filename.is_some() {
+        attachments.find(|part| part.param.get(Param::Filename) == filename)
+    } else {
+        attachments.next()
     };
-    Box::pin(session.fetch_single_msg(context, &server_folder, server_uid, rfc724_mid)).await?;
+    let blob_name = part
+        .and_then(|part| part.param.get(Param::File))
+        .context("Attachment not found in the original message.")?;
+    info!(context, "Restored attachment of {} to {blob_name}.", msg.id);
+
+    msg.param.set(Param::File, blob_name);
+    msg.update_param(context).await?;
+    context.emit_msgs_changed(msg.chat_id, msg.id);
+    Ok(())
+}
 
+async fn maybe_interrupt_inbox_after_download(context: &Context, session: &Session) -> Result<()> {
     let bcc_self = context.get_config_bool(Config::BccSelf).await?;
     if ephemeral::should_delete_all_downloaded_messages(bcc_self, session.is_chatmail()) {
         // Now that the message was downloaded, it likely needs to be deleted;
@@ -173,8 +291,7 @@ pub(crate) async fn download_msg(
         // on real devices, it would be fine to delete the message at the next iteration.
         context.scheduler.interrupt_inbox().await;
     }
-
-    Ok(Some(()))
+    Ok(())
 }
 
 impl Session {
@@ -189,7 +306,30 @@ async fn fetch_single_msg(
         uid: u32,
         rfc724_mid: String,
     ) -> Result<()> {
-        if uid == 0 {
+        let mut uid_message_ids: BTreeMap<u32, String> = BTreeMap::new();
+        uid_message_ids.insert(uid, rfc724_mid);
+        let (sender, receiver) = async_channel::unbounded();
+        Box::pin(self.fetch_msgs_fully(context, folder, &uid_message_ids, sender)).await?;
+        if receiver.recv().await.is_err() {
+            bail!("Failed to fetch UID {uid}");
+        }
+        Ok(())
+    }
+
+    /// Downloads messages from a folder fully and pipes them to receive_imf().
+    ///
+    /// All messages are requested with a single `UID FETCH` command per UID set,
+    /// so the server streams them one after another
+    /// instead of waiting for a round-trip per message.
+    /// Fetched UIDs are sent to the channel, see [`Session::fetch_many_msgs`].
+    async fn fetch_msgs_fully(
+        &mut self,
+        context: &Context,
+        folder: &str,
+        uid_message_ids: &BTreeMap<u32, String>,
+        received_msgs_channel: async_channel::Sender<(u32, Option<ReceivedMsg>)>,
+    ) -> Result<()> {
+        if uid_message_ids.contains_key(&0) {
             bail!("Attempt to fetch UID 0");
         }
 
@@ -197,20 +337,22 @@ async fn fetch_single_msg(
         ensure!(folder_exists, "No folder {folder}");
 
         // we are connected, and the folder is selected
-        info!(context, "Downloading message {}/{} fully...", folder, uid);
+        let uids: Vec<u32> = uid_message_ids.keys().copied().collect();
+        info!(
+            context,
+            "Downloading {} messages from {folder} fully...",
+            uids.len()
+        );
 
-        let mut uid_message_ids: BTreeMap<u32, String> = BTreeMap::new();
-        uid_message_ids.insert(uid, rfc724_mid);
-        let (sender, receiver) = async_channel::unbounded();
-        {
-            let _fetch_msgs_lock_guard = context.fetch_msgs_mutex.lock().await;
-            self.fetch_many_msgs(context, folder, vec![uid], &uid_message_ids, sender)
-                .await?;
-        }
-        if receiver.recv().await.is_err() {
-            bail!("Failed to fetch UID {uid}");
-        }
-        Ok(())
+        let _fetch_msgs_lock_guard = context.fetch_msgs_mutex.lock().await;
+        Box::pin(self.fetch_many_msgs(
+            context,
+            folder,
+            uids,
+            uid_message_ids,
+            received_msgs_channel,
+        ))
+        .await
     }
 }
 
@@ -277,47 +419,134 @@ pub(crate) async fn download_msgs(context: &Context, session: &mut Session) -> R
         })
         .await?;
 
+    // Messages to download fully, grouped by folder,
+    // so that they can be fetched with as few `UID FETCH` commands as possible.
+    let mut full_downloads: BTreeMap<String, BTreeMap<u32, String>> = BTreeMap::new();
+    let transport_id = session.transport_id();
+
     for rfc724_mid in &rfc724_mids {
-        match download_msg(context, rfc724_mid.clone(), session).await {
-            Ok(Some(())) => {
-                delete_from_downloads(context, rfc724_mid).await?;
-                delete_from_available_post_msgs(context, rfc724_mid).await?;
-            }
-            Ok(None) => {
-                // The message is not available on this transport.
-            }
-            Err(err) => {
-                warn!(
-                    context,
-                    "Failed to download message rfc724_mid={rfc724_mid}: {:#}.", err
-                );
-                if !msg_is_downloaded_for(context, rfc724_mid).await? {
-                    // This is probably a classical email that vanished before we could download it
-                    warn!(
-                        context,
-                        "{rfc724_mid} download failed and there is no downloaded pre-message."
-                    );
+        let msg_id = rfc724_mid_exists(context, rfc724_mid).await?;
+        if let Some(msg_id) = msg_id {
+            let res = match upload::download_attachment(context, msg_id).await {
+                Ok(false) => match Box::pin(p2p::download_attachment(context, msg_id)).await {
+                    Err(err) => {
+                        warn!(
+                            context,
+                            "Failed to download attachment of {rfc724_mid} over iroh, downloading the Post-Message instead: {err:#}."
+                        );
+                        Ok(false)
+                    }
+                    res => res,
+                },
+                res => res,
+            };
+            match res {
+                Ok(false) => {}
+                Ok(true) => {
                     delete_from_downloads(context, rfc724_mid).await?;
-                } else if available_post_msgs_contains_rfc724_mid(context, rfc724_mid).await? {
+                    continue;
+                }
+                Err(err) => {
                     warn!(
                         context,
-                        "{rfc724_mid} is in available_post_msgs table but we failed to fetch it,
-                        so set the message to DownloadState::Failure - probably it was deleted on the server in the meantime"
+                        "Failed to download attachment of {rfc724_mid}: {err:#}."
                     );
                     set_state_to_failure(context, rfc724_mid).await?;
                     delete_from_downloads(context, rfc724_mid).await?;
-                    delete_from_available_post_msgs(context, rfc724_mid).await?;
-                } else {
-                    // leave the message in DownloadState::InProgress;
-                    // it will be downloaded once it arrives.
+                    continue;
                 }
             }
         }
+        let skipped_attachment = match msg_id {
+            Some(msg_id) => has_skipped_attachment(context, msg_id).await?,
+            None => false,
+        };
+        if !skipped_attachment
+            && let Some((uid, folder)) =
+                get_server_location(context, rfc724_mid, transport_id).await?
+        {
+            full_downloads
+                .entry(folder)
+                .or_default()
+                .insert(uid, rfc724_mid.clone());
+            continue;
+        }
+        let res = download_msg(context, rfc724_mid.clone(), session).await;
+        handle_download_result(context, rfc724_mid, res).await?;
+    }
+
+    for (folder, uid_message_ids) in full_downloads {
+        let (sender, receiver) = async_channel::unbounded();
+        let fetch_res = session
+            .fetch_msgs_fully(context, &folder, &uid_message_ids, sender)
+            .await;
+        let mut fetched_uids = BTreeSet::new();
+        while let Ok((uid, _)) = receiver.try_recv() {
+            fetched_uids.insert(uid);
+        }
+        if !fetched_uids.is_empty() {
+            maybe_interrupt_inbox_after_download(context, session).await?;
+        }
+        for (uid, rfc724_mid) in &uid_message_ids {
+            let res = if fetched_uids.contains(uid) {
+                Ok(Some(()))
+            } else if let Err(err) = &fetch_res {
+                Err(anyhow!("{err:#}"))
+            } else {
+                Err(anyhow!("Failed to fetch UID {uid}"))
+            };
+            handle_download_result(context, rfc724_mid, res).await?;
+        }
     }
 
     Ok(())
 }
 
+/// Updates the `download` and `available_post_msgs` tables
+/// after trying to download a message in [`download_msgs`].
+async fn handle_download_result(
+    context: &Context,
+    rfc724_mid: &str,
+    res: Result<Option<()>>,
+) -> Result<()> {
+    match res {
+        Ok(Some(())) => {
+            delete_from_downloads(context, rfc724_mid).await?;
+            delete_from_available_post_msgs(context, rfc724_mid).await?;
+        }
+        Ok(None) => {
+            // The message is not available on this transport.
+        }
+        Err(err) => {
+            warn!(
+                context,
+                "Failed to download message rfc724_mid={rfc724_mid}: {:#}.", err
+            );
+            if !msg_is_downloaded_for(context, rfc724_mid).await? {
+                // This is probably a classical email that vanished before we could download it
+                warn!(
+                    context,
+                    "{rfc724_mid} download failed and there is no downloaded pre-message."
+                );
+                delete_from_downloads(context, rfc724_mid).await?;
+            } else if available_post_msgs_contains_rfc724_mid(context, rfc724_mid).await? {
+                warn!(
+                    context,
+                    "{rfc724_mid} is in available_post_msgs table but we failed to fetch it,
+                    so set the message to DownloadState::Failure - probably it was deleted on the server in the meantime"
+                );
+                set_state_to_failure(context, rfc724_mid).await?;
+                delete_from_downloads(context, rfc724_mid).await?;
+                delete_from_available_post_msgs(context, rfc724_mid).await?;
+            } else {
+                // leave the message in DownloadState::InProgress;
+                // it will be downloaded once it arrives.
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Downloads known post-messages without pre-messages
 /// in order to guard against lost pre-messages.
 pub(crate) async fn download_known_post_messages_without_pre_message(
@@ -359,9 +588,11 @@ pub(crate) async fn download_known_post_messages_without_pre_message(
 #[cfg(test)]
 mod tests {
     use num_traits::FromPrimitive;
+    use tokio::fs;
 
     use super::*;
     use crate::chat::send_msg;
+    use crate::message::Viewtype;
     use crate::test_utils::TestContextManager;
 
     #[test]
@@ -411,4 +642,56 @@ async fn test_update_download_state() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_download_limit_data_saver() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let t = &tcm.alice().await;
+
+        assert_eq!(download_limit(t).await?, Some(655360));
+        t.set_config_bool(Config::DataSaver, true).await?;
+        assert_eq!(download_limit(t).await?, Some(DATA_SAVER_DOWNLOAD_LIMIT));
+
+        t.set_config(Config::DownloadLimit, Some("0")).await?;
+        assert_eq!(download_limit(t).await?, Some(DATA_SAVER_DOWNLOAD_LIMIT));
+
+        // A lower limit is kept.
+        t.set_config(Config::DownloadLimit, Some("1000")).await?;
+        assert_eq!(download_limit(t).await?, Some(1000));
+
+        t.set_config_bool(Config::DataSaver, false).await?;
+        t.set_config(Config::DownloadLimit, Some("0")).await?;
+        assert_eq!(download_limit(t).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_restore_attachment() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat_id(bob).await;
+
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "notes.txt", b"hello", None)?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let mut msg = bob.recv_msg(&sent).await;
+        let path = msg.get_file(bob).unwrap();
+        fs::remove_file(&path).await?;
+
+        restore_attachment(bob, &mut msg, sent.payload().as_bytes()).await?;
+        let msg = Message::load_from_db(bob, msg.id).await?;
+        assert_eq!(msg.get_filename().unwrap(), "notes.txt");
+        assert_eq!(fs::read(msg.get_file(bob).unwrap()).await?, b"hello");
+
+        // Messages without attachment cannot be restored.
+        let sent = alice.send_text(chat_id, "no attachment").await;
+        let mut msg = bob.recv_msg(&sent).await;
+        assert!(
+            restore_attachment(bob, &mut msg, sent.payload().as_bytes())
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
 }
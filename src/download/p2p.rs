@@ -0,0 +1,473 @@
+//! # Attachments transferred directly over iroh.
+//!
+//! Attachments larger than [`Config::P2pAttachmentBytes`]
+//! sent to a contact who is online are additionally offered
+//! on the sender's iroh endpoint.
+//! Such messages are split into a Pre-Message and a Post-Message as usual,
+//! the Pre-Message contains the BLAKE3 hash of the attachment
+//! and the iroh node address of the sender
+//! in the protected `Chat-P2p-Hash` and `Chat-P2p-Node-Addr` headers.
+//!
+//! When the attachment is downloaded through the usual download queue,
+//! see [`MsgId::download_full()`],
+//! the recipient tries to get it over iroh first.
+//! The attachment can only be transferred while the sender's iroh endpoint is running,
+//! offers do not survive restarts.
+//! If the transfer fails, the Post-Message sent by email is downloaded instead.
+//! The Post-Message is always sent by email as well,
+//! because the sender may be offline by the time the recipient downloads the attachment.
+//! Offers are removed once the attachment is fetched or after [`P2P_OFFER_LIFETIME`].
+//! Attachments of contact requests and blocked chats are never fetched over iroh
+//! as this would reveal the IP address of the recipient to the sender.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result, bail, ensure};
+use futures_lite::future::Boxed as BoxedFuture;
+use iroh::NodeAddr;
+use iroh::endpoint::Connection;
+use iroh::protocol::ProtocolHandler;
+use parking_lot::{Mutex, MutexGuard};
+
+use super::PostMsgMetadata;
+use super::upload::set_downloaded_blob;
+use crate::blob::{BlobObject, file_hash};
+use crate::chat::{self, Chat};
+use crate::config::Config;
+use crate::constants::{Blocked, Chattype};
+use crate::contact::Contact;
+use crate::context::Context;
+use crate::download;
+use crate::headerdef::HeaderDef;
+use crate::log::warn;
+use crate::message::{Message, MsgId};
+use crate::mimeparser::MimeMessage;
+use crate::net::bandwidth::ConnectionType;
+use crate::param::Param;
+use crate::tools::time;
+
+/// ALPN of the attachment transfer protocol.
+///
+/// The downloading node opens a bidirectional stream,
+/// sends the hex-encoded BLAKE3 hash of the attachment and finishes its send stream.
+/// The offering node responds with the attachment and finishes the stream.
+pub(crate) const P2P_FILE_ALPN: &[u8] = b"/deltachat/p2p-file/0";
+
+/// Length of a hex-encoded BLAKE3 hash.
+const HASH_HEX_LEN: usize = 64;
+
+/// Time in seconds an attachment is offered for.
+const P2P_OFFER_LIFETIME: i64 = 24 * 60 * 60;
+
+/// Attachment offered by this node.
+#[derive(Debug)]
+struct P2pOffer {
+    path: PathBuf,
+
+    /// Timestamp after which the attachment is not served anymore.
+    expires: i64,
+}
+
+/// Attachments offered by this node, by hex-encoded BLAKE3 hash.
+///
+/// Expired offers are removed whenever the offers are accessed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct P2pOffers(Arc<Mutex<HashMap<String, P2pOffer>>>);
+
+impl P2pOffers {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, P2pOffer>> {
+        let mut offers = self.0.lock();
+        let now = time();
+        offers.retain(|_, offer| offer.expires > now);
+        offers
+    }
+
+    fn insert(&self, hash: String, path: PathBuf) {
+        let expires = time().saturating_add(P2P_OFFER_LIFETIME);
+        self.lock().insert(hash, P2pOffer { path, expires });
+    }
+
+    fn get(&self, hash: &str) -> Option<PathBuf> {
+        self.lock().get(hash).map(|offer| offer.path.clone())
+    }
+
+    fn remove(&self, hash: &str) {
+        self.lock().remove(hash);
+    }
+}
+
+/// Protocol handler serving offered attachments.
+#[derive(Debug, Clone)]
+pub(crate) struct P2pFileProvider {
+    offers: P2pOffers,
+}
+
+impl P2pFileProvider {
+    pub(crate) fn new(offers: P2pOffers) -> Self {
+        Self { offers }
+    }
+}
+
+impl ProtocolHandler for P2pFileProvider {
+    fn accept(&self, connection: Connection) -> BoxedFuture<Result<()>> {
+        let offers = self.offers.clone();
+        Box::pin(async move {
+            let (mut send_stream, mut recv_stream) = connection.accept_bi().await?;
+            let hash = recv_stream.read_to_end(HASH_HEX_LEN).await?;
+            let hash = String::from_utf8(hash)?;
+            let path = offers
+                .get(&hash)
+                .with_context(|| format!("Attachment {hash} is not offered"))?;
+            let mut file = tokio::fs::File::open(&path).await?;
+            tokio::io::copy(&mut file, &mut send_stream).await?;
+            send_stream.finish()?;
+            // The attachment is only fetched once,
+            // other devices of the recipient download the Post-Message.
+            offers.remove(&hash);
+            // Wait until the peer has read everything and closes the connection.
+            connection.closed().await;
+            Ok(())
+        })
+    }
+}
+
+/// Attachment announced by the `Chat-P2p-Hash` header of a received message.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct P2pAttachment {
+    /// Hex-encoded BLAKE3 hash of the attachment.
+    pub(crate) hash: String,
+
+    /// Iroh node address of the sender, serialized as JSON.
+    pub(crate) node_addr: String,
+
+    /// Attachment metadata.
+    pub(crate) metadata: PostMsgMetadata,
+}
+
+impl P2pAttachment {
+    /// Returns the attachment offered over iroh announced by the received message.
+    ///
+    /// The headers are only accepted from encrypted and signed messages.
+    pub(crate) fn from_mime_parser(context: &Context, mime_parser: &MimeMessage) -> Option<Self> {
+        if !mime_parser.was_encrypted() {
+            return None;
+        }
+        let hash = mime_parser.get_header(HeaderDef::ChatP2pHash)?.to_string();
+        let Some(node_addr) = mime_parser.get_header(HeaderDef::ChatP2pNodeAddr) else {
+            warn!(
+                context,
+                "Attachment {hash} offered over iroh has no node address."
+            );
+            return None;
+        };
+        let metadata = mime_parser
+            .get_header(HeaderDef::ChatPostMessageMetadata)
+            .context("No metadata header")
+            .and_then(PostMsgMetadata::try_from_header_value);
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!(
+                    context,
+                    "Failed to parse metadata of attachment {hash} offered over iroh: {err:#}."
+                );
+                return None;
+            }
+        };
+        let node_addr = node_addr.to_string();
+        Some(Self {
+            hash,
+            node_addr,
+            metadata,
+        })
+    }
+
+    /// Schedules the download of the attachment for the received message
+    /// unless it is larger than [`Config::DownloadLimit`].
+    pub(crate) async fn schedule_download(&self, context: &Context, msg_id: MsgId) -> Result<()> {
+        let download_limit = download::download_limit(context).await?;
+        if download_limit.is_none_or(|limit| self.metadata.size <= u64::from(limit)) {
+            msg_id.download_full(context).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Offers the attachment of the message over iroh
+/// if it is larger than [`Config::P2pAttachmentBytes`]
+/// and the only other chat member was seen recently.
+///
+/// On success the hash and the node address are stored in the message params
+/// and added to the Pre-Message.
+pub(crate) async fn maybe_offer_attachment(context: &Context, msg: &mut Message) -> Result<bool> {
+    if !msg.viewtype.has_file() || msg.param.exists(Param::UploadUrl) {
+        return Ok(false);
+    }
+    let min_bytes = context.get_config_u64(Config::P2pAttachmentBytes).await?;
+    if min_bytes == 0
+        || !context
+            .get_config_bool(Config::WebxdcRealtimeEnabled)
+            .await?
+    {
+        return Ok(false);
+    }
+    let file_bytes = msg
+        .get_filebytes(context)
+        .await?
+        .context("File size is not available")?;
+    if file_bytes <= min_bytes {
+        return Ok(false);
+    }
+    let chat = Chat::load_from_db(context, msg.chat_id).await?;
+    if chat.typ != Chattype::Single {
+        return Ok(false);
+    }
+    let [contact_id] = chat::get_chat_contacts(context, chat.id).await?[..] else {
+        return Ok(false);
+    };
+    if !Contact::get_by_id(context, contact_id)
+        .await?
+        .was_seen_recently()
+    {
+        return Ok(false);
+    }
+
+    let path = msg.get_file(context).context("Message has no file")?;
+    let hash = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || file_hash(&path)).await??
+    };
+    let hash = hash.to_hex().to_string();
+    let iroh = context.get_or_try_init_peer_channel().await?;
+    let node_addr = iroh.get_node_addr().await?;
+    iroh.p2p_offers.insert(hash.clone(), path);
+    info!(
+        context,
+        "Offering attachment of message {} ({file_bytes} bytes) over iroh.", msg.id
+    );
+
+    msg.param.set(Param::P2pHash, hash);
+    msg.param
+        .set(Param::P2pNodeAddr, serde_json::to_string(&node_addr)?);
+    msg.update_param(context).await?;
+    Ok(true)
+}
+
+/// Downloads the attachment of the message offered over iroh.
+///
+/// Returns `false` if the message has no attachment offered over iroh
+/// or it must not be fetched over iroh because the chat is not accepted.
+/// On failure the offer is forgotten so that the Post-Message is downloaded instead.
+pub(crate) async fn download_attachment(context: &Context, msg_id: MsgId) -> Result<bool> {
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    if msg.download_state() == download::DownloadState::Done {
+        return Ok(false);
+    }
+    let (Some(hash), Some(node_addr)) = (
+        msg.param.get(Param::P2pHash),
+        msg.param.get(Param::P2pNodeAddr),
+    ) else {
+        return Ok(false);
+    };
+    if Chat::load_from_db(context, msg.chat_id).await?.blocked != Blocked::Not {
+        return Ok(false);
+    }
+    let hash = hash.to_string();
+    let node_addr = node_addr.to_string();
+    let size = msg
+        .param
+        .get_i64(Param::PostMessageFileBytes)
+        .and_then(|size| u64::try_from(size).ok())
+        .context("Attachment size is unknown")?;
+    let filename = msg.param.get(Param::Filename).unwrap_or("file").to_string();
+    info!(context, "Downloading attachment {hash} over iroh.");
+    match fetch_attachment(context, &hash, &node_addr, size, &filename).await {
+        Ok(blob) => {
+            set_downloaded_blob(context, msg, &blob, usize::try_from(size)?).await?;
+            Ok(true)
+        }
+        Err(err) => {
+            msg.param.remove(Param::P2pHash).remove(Param::P2pNodeAddr);
+            msg.update_param(context).await?;
+            Err(err)
+        }
+    }
+}
+
+/// Receives the attachment with the given hash from `node_addr`
+/// and stores it in the blobdir.
+///
+/// The attachment is streamed to a temporary file
+/// and rejected if it is larger than `size` or has a wrong hash.
+async fn fetch_attachment<'a>(
+    context: &'a Context,
+    hash: &str,
+    node_addr: &str,
+    size: u64,
+    filename: &str,
+) -> Result<BlobObject<'a>> {
+    let node_addr: NodeAddr = serde_json::from_str(node_addr)?;
+    let endpoint = context
+        .get_or_try_init_peer_channel()
+        .await?
+        .router
+        .endpoint()
+        .clone();
+    let connection = endpoint.connect(node_addr, P2P_FILE_ALPN).await?;
+    let (mut send_stream, recv_stream) = connection.open_bi().await?;
+    send_stream.write_all(hash.as_bytes()).await?;
+    send_stream.finish()?;
+
+    let temp_path = context
+        .get_blobdir()
+        .join(format!("tmp-{}", rand::random::<u64>()));
+    let res = receive_to_file(recv_stream, &temp_path, size).await;
+    connection.close(0u32.into(), b"done");
+    let bandwidth = context.bandwidth.get(ConnectionType::Iroh);
+    bandwidth.add_sent(hash.len());
+    let received_hash = match res {
+        Ok((received, received_hash)) => {
+            bandwidth.add_received(usize::try_from(received).unwrap_or(usize::MAX));
+            received_hash
+        }
+        Err(err) => {
+            tokio::fs::remove_file(&temp_path).await.ok();
+            return Err(err);
+        }
+    };
+    if received_hash.to_hex().as_str() != hash {
+        tokio::fs::remove_file(&temp_path).await.ok();
+        bail!("Attachment {hash} received over iroh has a wrong hash");
+    }
+    tokio::task::block_in_place(|| {
+        BlobObject::rename_to_hash_name(context, &temp_path, received_hash, Path::new(filename))
+    })
+}
+
+/// Writes `stream` to a new file at `path` and hashes it.
+///
+/// Fails if the stream is longer or shorter than `size` bytes.
+/// Returns the number of bytes received and the hash.
+async fn receive_to_file(
+    stream: impl tokio::io::AsyncRead + Unpin,
+    path: &Path,
+    size: u64,
+) -> Result<(u64, blake3::Hash)> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut file = tokio::fs::File::create(path).await?;
+    let received = tokio::io::copy(&mut stream.take(size.saturating_add(1)), &mut file).await?;
+    file.flush().await?;
+    ensure!(
+        received == size,
+        "Received {received} bytes instead of the announced {size}"
+    );
+    let path = path.to_path_buf();
+    let hash = tokio::task::spawn_blocking(move || file_hash(&path)).await??;
+    Ok((received, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::download::DownloadState;
+    use crate::message::Viewtype;
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_receive_p2p_attachment() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat_id(bob).await;
+
+        let data = vec![7u8; 200_000];
+        let hash = blake3::hash(&data).to_hex().to_string();
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "video.mp4", &data, None)?;
+        msg.set_text("see attachment".to_string());
+        msg.param.set(Param::P2pHash, &hash);
+        msg.param.set(Param::P2pNodeAddr, "{}");
+        let msg_id = send_msg(alice, chat_id, &mut msg).await?;
+        let [pre_message, post_message] = &alice.get_smtp_rows_for_msg(msg_id).await[..] else {
+            panic!("Expected a Pre-Message and a Post-Message");
+        };
+
+        let rcvd = bob.recv_msg(pre_message).await;
+        assert_eq!(rcvd.param.get(Param::P2pHash), Some(hash.as_str()));
+        assert_eq!(rcvd.param.get(Param::Filename), Some("video.mp4"));
+        // Attachments of contact requests are not fetched over iroh.
+        assert_eq!(rcvd.download_state(), DownloadState::Available);
+        assert!(!download_attachment(bob, rcvd.id).await?);
+
+        rcvd.chat_id.accept(bob).await?;
+        assert!(download_attachment(bob, rcvd.id).await.is_err());
+        let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+        assert!(!rcvd.param.exists(Param::P2pHash));
+
+        // The Post-Message sent by email is the fallback.
+        bob.recv_msg_trash(post_message).await;
+        let rcvd = Message::load_from_db(bob, rcvd.id).await?;
+        assert_eq!(rcvd.download_state(), DownloadState::Done);
+        assert_eq!(rcvd.get_filename().unwrap(), "video.mp4");
+        assert_eq!(tokio::fs::read(rcvd.get_file(bob).unwrap()).await?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2p_offers() {
+        let offers = P2pOffers::default();
+        offers.insert("a".to_string(), PathBuf::from("a.mp4"));
+        offers.insert("b".to_string(), PathBuf::from("b.mp4"));
+        assert_eq!(offers.get("a"), Some(PathBuf::from("a.mp4")));
+
+        offers.remove("a");
+        assert_eq!(offers.get("a"), None);
+
+        // Expired offers are removed.
+        offers.0.lock().get_mut("b").unwrap().expires = time();
+        assert_eq!(offers.get("b"), None);
+        assert!(offers.0.lock().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_receive_to_file() -> Result<()> {
+        let t = TestContext::new().await;
+        let path = t.get_blobdir().join("tmp-test");
+        let data = b"not really a large file";
+
+        let (received, hash) = receive_to_file(&data[..], &path, data.len() as u64).await?;
+        assert_eq!(received, data.len() as u64);
+        assert_eq!(hash, blake3::hash(data));
+        assert_eq!(tokio::fs::read(&path).await?, data);
+
+        assert!(receive_to_file(&data[..], &path, 5).await.is_err());
+        assert!(receive_to_file(&data[..], &path, 100).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_offer_only_to_online_contact() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice
+            .set_config_bool(Config::WebxdcRealtimeEnabled, true)
+            .await?;
+        alice
+            .set_config(Config::P2pAttachmentBytes, Some("10"))
+            .await?;
+        let chat_id = alice.create_chat_id(bob).await;
+
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "video.mp4", b"not really a large file", None)?;
+        msg.chat_id = chat_id;
+        // Bob has never been seen.
+        assert!(!maybe_offer_attachment(alice, &mut msg).await?);
+        assert!(!msg.param.exists(Param::P2pHash));
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+//! # Partial download of messages by MIME part.
+//!
+//! Classic emails exceeding [`Config::DownloadLimit`] are not downloaded as a whole.
+//! Instead, their IMAP BODYSTRUCTURE is fetched and only the text parts are downloaded,
+//! while the attachment stays on the server until [`MsgId::download_attachment()`] is called.
+//!
+//! [`Config::DownloadLimit`]: crate::config::Config::DownloadLimit
+
+use anyhow::{Context as _, Result, bail, ensure};
+use async_imap::imap_proto::{
+    BodyParams, BodyStructure, ContentEncoding, MessageSection, SectionPath,
+};
+use async_imap::types::{Fetch, Flag};
+use futures::TryStreamExt;
+
+use super::DownloadState;
+use crate::blob::BlobObject;
+use crate::context::Context;
+use crate::imap::session::Session;
+use crate::log::warn;
+use crate::message::{self, Message, MsgId};
+use crate::param::Param;
+use crate::receive_imf::receive_imf_inner;
+use crate::tools::create_id;
+
+/// Leaf MIME part of a message as described by IMAP BODYSTRUCTURE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BodyPart {
+    /// Section path, e.g. `[1, 2]` for section `1.2`.
+    pub section: Vec<u32>,
+
+    /// MIME type in lowercase, e.g. `image/jpeg`.
+    pub mimetype: String,
+
+    /// Filename from `Content-Disposition` or `Content-Type` parameters.
+    pub filename: Option<String>,
+
+    /// `Content-Transfer-Encoding` of the part.
+    pub encoding: String,
+
+    /// Size of the encoded part in bytes.
+    pub size: u32,
+
+    /// Whether the part is an inline text part.
+    pub is_text: bool,
+}
+
+impl BodyPart {
+    /// Returns the section in the IMAP notation, e.g. `1.2`.
+    pub fn section_str(&self) -> String {
+        self.section
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+/// Flattens BODYSTRUCTURE into the list of leaf parts.
+///
+/// Returns `None` if the message cannot be downloaded partially,
+/// e.g. because it is encrypted or signed and splitting it would break decryption
+/// or signature verification.
+pub(crate) fn flatten_bodystructure(bodystructure: &BodyStructure) -> Option<Vec<BodyPart>> {
+    let mut parts = Vec::new();
+    match bodystructure {
+        BodyStructure::Multipart { .. } => walk(bodystructure, Vec::new(), &mut parts)?,
+        // Single-part message body is section 1.
+        _ => walk(bodystructure, vec![1], &mut parts)?,
+    }
+    Some(parts)
+}
+
+fn walk(bodystructure: &BodyStructure, section: Vec<u32>, parts: &mut Vec<BodyPart>) -> Option<()> {
+    let (common, other) = match bodystructure {
+        BodyStructure::Multipart { common, bodies, .. } => {
+            let subtype = common.ty.subtype.to_lowercase();
+            if subtype == "encrypted" || subtype == "signed" {
+                return None;
+            }
+            for (i, body) in (1..).zip(bodies) {
+                let mut child = section.clone();
+                child.push(i);
+                walk(body, child, parts)?;
+            }
+            return Some(());
+        }
+        BodyStructure::Basic { common, other, .. }
+        | BodyStructure::Text { common, other, .. }
+        | BodyStructure::Message { common, other, .. } => (common, other),
+    };
+
+    let ty = common.ty.ty.to_lowercase();
+    let subtype = common.ty.subtype.to_lowercase();
+    let disposition = common.disposition.as_ref();
+    let is_attachment = disposition.is_some_and(|d| d.ty.eq_ignore_ascii_case("attachment"));
+    let filename = disposition
+        .and_then(|d| find_param(&d.params, "filename"))
+        .or_else(|| find_param(&common.ty.params, "name"));
+    let encoding = match &other.transfer_encoding {
+        ContentEncoding::SevenBit => "7bit".to_string(),
+        ContentEncoding::EightBit => "8bit".to_string(),
+        ContentEncoding::Binary => "binary".to_string(),
+        ContentEncoding::Base64 => "base64".to_string(),
+        ContentEncoding::QuotedPrintable => "quoted-printable".to_string(),
+        ContentEncoding::Other(other) => other.to_lowercase(),
+    };
+    parts.push(BodyPart {
+        section,
+        is_text: ty == "text"
+            && (subtype == "plain" || subtype == "html")
+            && !is_attachment
+            && filename.is_none(),
+        mimetype: format!("{ty}/{subtype}"),
+        filename,
+        encoding,
+        size: other.octets,
+    });
+    Some(())
+}
+
+fn find_param(params: &BodyParams, name: &str) -> Option<String> {
+    params
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.to_string())
+}
+
+/// Decides whether the message can be downloaded partially.
+///
+/// Returns the text parts to download and the attachment to skip.
+/// Only messages with exactly one non-text part are downloaded partially,
+/// so that the attachment can later be attached to the received message.
+pub(crate) fn plan_partial_download(parts: Vec<BodyPart>) -> Option<(Vec<BodyPart>, BodyPart)> {
+    let (text, mut other): (Vec<_>, Vec<_>) = parts.into_iter().partition(|part| part.is_text);
+    if text.is_empty() || other.len() != 1 {
+        return None;
+    }
+    Some((text, other.pop()?))
+}
+
+/// Builds a `multipart/mixed` message out of the top-level header
+/// and the downloaded parts, each given as a pair of MIME header and body.
+fn build_partial_mime(header: &[u8], parts: &[(&[u8], &[u8])]) -> Result<Vec<u8>> {
+    let (headers, _) = mailparse::parse_headers(header)?;
+    let boundary = create_id();
+    let mut res = Vec::new();
+    for header in &headers {
+        let key = header.get_key_ref();
+        if key.eq_ignore_ascii_case("Content-Type")
+            || key.eq_ignore_ascii_case("Content-Transfer-Encoding")
+        {
+            continue;
+        }
+        res.extend_from_slice(key.as_bytes());
+        res.extend_from_slice(b": ");
+        res.extend_from_slice(header.get_value_raw());
+        res.extend_from_slice(b"\r\n");
+    }
+    res.extend_from_slice(
+        format!("Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\r\n").as_bytes(),
+    );
+    for (mime_header, body) in parts {
+        res.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        res.extend_from_slice(mime_header);
+        res.extend_from_slice(body);
+        res.extend_from_slice(b"\r\n");
+    }
+    res.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    Ok(res)
+}
+
+/// Decodes the body of a part according to its `Content-Transfer-Encoding`.
+fn decode_part(encoding: &str, body: &[u8]) -> Result<Vec<u8>> {
+    let mut raw = format!("Content-Transfer-Encoding: {encoding}\r\n\r\n").into_bytes();
+    raw.extend_from_slice(body);
+    let mail = mailparse::parse_mail(&raw)?;
+    Ok(mail.get_body_raw()?)
+}
+
+fn section_path(section: &[u32], msg_section: Option<MessageSection>) -> SectionPath {
+    SectionPath::Part(section.to_vec(), msg_section)
+}
+
+impl Session {
+    /// Fetches a single message and returns its FETCH response.
+    ///
+    /// The whole response stream is consumed,
+    /// so unsolicited FETCH responses for other UIDs are skipped.
+    async fn uid_fetch_one(&mut self, uid: u32, query: &str) -> Result<Option<Fetch>> {
+        let responses: Vec<Fetch> = self
+            .uid_fetch(uid.to_string(), query)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(responses.into_iter().find(|fetch| fetch.uid == Some(uid)))
+    }
+
+    /// Downloads text parts of a large message and stores the attachment as available.
+    ///
+    /// Returns `false` if the message cannot be downloaded partially
+    /// and must be downloaded fully instead.
+    pub(crate) async fn fetch_msg_text_parts(
+        &mut self,
+        context: &Context,
+        uid: u32,
+        rfc724_mid: &str,
+    ) -> Result<bool> {
+        let Some(fetch) = self.uid_fetch_one(uid, "(UID FLAGS BODYSTRUCTURE)").await? else {
+            return Ok(false);
+        };
+        let is_seen = fetch.flags().any(|flag| flag == Flag::Seen);
+        let Some(parts) = fetch.bodystructure().and_then(flatten_bodystructure) else {
+            return Ok(false);
+        };
+        let Some((text_parts, attachment)) = plan_partial_download(parts) else {
+            return Ok(false);
+        };
+
+        let mut query = "(UID BODY.PEEK[HEADER]".to_string();
+        for part in &text_parts {
+            let section = part.section_str();
+            query += &format!(" BODY.PEEK[{section}.MIME] BODY.PEEK[{section}]");
+        }
+        query += ")";
+        let Some(fetch) = self.uid_fetch_one(uid, &query).await? else {
+            return Ok(false);
+        };
+        let header = fetch.header().context("No header in FETCH response")?;
+        let mut mime_parts = Vec::new();
+        for part in &text_parts {
+            let mime_header = fetch
+                .section(&section_path(&part.section, Some(MessageSection::Mime)))
+                .unwrap_or_default();
+            let body = fetch
+                .section(&section_path(&part.section, None))
+                .unwrap_or_default();
+            mime_parts.push((mime_header, body));
+        }
+        let raw = build_partial_mime(header, &mime_parts)?;
+
+        info!(
+            context,
+            "Downloaded text parts of {rfc724_mid}, skipping section {}.",
+            attachment.section_str()
+        );
+        let Some(received) = receive_imf_inner(context, rfc724_mid, &raw, is_seen).await? else {
+            return Ok(true);
+        };
+        if received.chat_id.is_trash() {
+            return Ok(true);
+        }
+        let Some(&msg_id) = received.msg_ids.last() else {
+            return Ok(true);
+        };
+        context
+            .sql
+            .execute(
+                "INSERT INTO download_parts (msg_id, section, mimetype, filename, encoding, size)
+                 VALUES (?,?,?,?,?,?)",
+                (
+                    msg_id,
+                    attachment.section_str(),
+                    &attachment.mimetype,
+                    attachment.filename.as_deref().unwrap_or_default(),
+                    &attachment.encoding,
+                    attachment.size,
+                ),
+            )
+            .await?;
+        msg_id
+            .update_download_state(context, DownloadState::Available)
+            .await?;
+        Ok(true)
+    }
+
+    /// Downloads the attachment skipped by [`Session::fetch_msg_text_parts`]
+    /// and adds it to the message.
+    pub(crate) async fn fetch_msg_attachment(
+        &mut self,
+        context: &Context,
+        folder: &str,
+        uid: u32,
+        msg_id: MsgId,
+    ) -> Result<()> {
+        let (section, mimetype, filename, encoding): (String, String, String, String) = context
+            .sql
+            .query_row(
+                "SELECT section, mimetype, filename, encoding FROM download_parts WHERE msg_id=?",
+                (msg_id,),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .await?;
+
+        let folder_exists = self.select_with_uidvalidity(context, folder).await?;
+        ensure!(folder_exists, "No folder {folder}");
+        info!(
+            context,
+            "Downloading section {section} of message {folder}/{uid}."
+        );
+        let Some(fetch) = self
+            .uid_fetch_one(uid, &format!("(UID BODY.PEEK[{section}])"))
+            .await?
+        else {
+            bail!("No FETCH response for UID {uid}");
+        };
+        let section_nums = section
+            .split('.')
+            .map(|n| n.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let body = fetch
+            .section(&section_path(&section_nums, None))
+            .context("No body section in FETCH response")?;
+        let data = decode_part(&encoding, body)?;
+
+        let filename = if filename.is_empty() {
+            "file".to_string()
+        } else {
+            filename
+        };
+        let blob = BlobObject::create_and_deduplicate_from_bytes(context, &data, &filename)?;
+        let mut msg = Message::load_from_db(context, msg_id).await?;
+        msg.param
+            .set(Param::File, blob.as_name())
+            .set(Param::Filename, &filename)
+            .set(Param::MimeType, &mimetype);
+        let viewtype = message::guess_msgtype_from_suffix(&msg)
+            .map(|(viewtype, _)| viewtype)
+            .unwrap_or(message::Viewtype::File);
+        context
+            .sql
+            .transaction(|transaction| {
+                transaction.execute(
+                    "UPDATE msgs SET param=?, type=?, bytes=?, download_state=? WHERE id=?",
+                    (
+                        msg.param.to_string(),
+                        viewtype,
+                        data.len(),
+                        DownloadState::Done,
+                        msg_id,
+                    ),
+                )?;
+                transaction.execute("DELETE FROM download_parts WHERE msg_id=?", (msg_id,))?;
+                Ok(())
+            })
+            .await?;
+        context.emit_msgs_changed(msg.chat_id, msg_id);
+        Ok(())
+    }
+}
+
+impl MsgId {
+    /// Schedules download of the attachment of a partially downloaded message.
+    ///
+    /// Unlike [`MsgId::download_full()`], this fetches only the body section
+    /// containing the attachment, the text of the message is already downloaded.
+    pub async fn download_attachment(self, context: &Context) -> Result<()> {
+        if !has_skipped_attachment(context, self).await? {
+            warn!(context, "{self} has no attachment to download.");
+            bail!("Nothing to download.");
+        }
+        self.download_full(context).await
+    }
+}
+
+/// Returns true if the message has an attachment that is not downloaded yet.
+pub(crate) async fn has_skipped_attachment(context: &Context, msg_id: MsgId) -> Result<bool> {
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM download_parts WHERE msg_id=?",
+            (msg_id,),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use async_imap::imap_proto::{AttributeValue, Response};
+
+    use super::*;
+
+    fn parse_bodystructure(response: &str) -> Option<Vec<BodyPart>> {
+        let (_, response) = async_imap::imap_proto::parser::parse_response(response.as_bytes())
+            .expect("failed to parse FETCH response");
+        let Response::Fetch(_, attrs) = response else {
+            panic!("not a FETCH response");
+        };
+        let bodystructure = attrs
+            .iter()
+            .find_map(|attr| match attr {
+                AttributeValue::BodyStructure(bs) => Some(bs),
+                _ => None,
+            })
+            .expect("no BODYSTRUCTURE");
+        flatten_bodystructure(bodystructure)
+    }
+
+    #[test]
+    fn test_flatten_bodystructure() {
+        let parts = parse_bodystructure(
+            "* 1 FETCH (UID 1 BODYSTRUCTURE ((\"text\" \"plain\" (\"charset\" \"utf-8\") NIL NIL \"7bit\" 12 1 NIL NIL NIL NIL)(\"image\" \"jpeg\" (\"name\" \"cat.jpg\") NIL NIL \"base64\" 2000000 NIL (\"attachment\" (\"filename\" \"cat.jpg\")) NIL NIL) \"mixed\" (\"boundary\" \"xyz\") NIL NIL NIL))\r\n",
+        )
+        .unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].section_str(), "1");
+        assert!(parts[0].is_text);
+        assert_eq!(parts[1].section_str(), "2");
+        assert_eq!(parts[1].mimetype, "image/jpeg");
+        assert_eq!(parts[1].filename.as_deref(), Some("cat.jpg"));
+        assert_eq!(parts[1].encoding, "base64");
+        assert_eq!(parts[1].size, 2000000);
+        assert!(!parts[1].is_text);
+
+        let (text, attachment) = plan_partial_download(parts).unwrap();
+        assert_eq!(text.len(), 1);
+        assert_eq!(attachment.section_str(), "2");
+
+        // Encrypted messages cannot be split.
+        assert!(
+            parse_bodystructure(
+                "* 1 FETCH (UID 1 BODYSTRUCTURE ((\"application\" \"pgp-encrypted\" NIL NIL NIL \"7bit\" 12 NIL NIL NIL NIL)(\"application\" \"octet-stream\" NIL NIL NIL \"7bit\" 2000000 NIL NIL NIL NIL) \"encrypted\" (\"protocol\" \"application/pgp-encrypted\" \"boundary\" \"xyz\") NIL NIL NIL))\r\n",
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_build_partial_mime() -> Result<()> {
+        let header = b"From: alice@example.org\r\nTo: bob@example.net\r\nSubject: Hi\r\nContent-Type: multipart/mixed; boundary=\"xyz\"\r\n\r\n";
+        let raw = build_partial_mime(
+            header,
+            &[(b"Content-Type: text/plain\r\n\r\n", b"Hello Bob")],
+        )?;
+        let mail = mailparse::parse_mail(&raw)?;
+        assert_eq!(mail.ctype.mimetype, "multipart/mixed");
+        assert_eq!(mail.subparts.len(), 1);
+        assert_eq!(mail.subparts[0].get_body()?, "Hello Bob");
+        assert_eq!(decode_part("base64", b"SGVsbG8=")?, b"Hello");
+        Ok(())
+    }
+}
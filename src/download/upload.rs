@@ -0,0 +1,531 @@
+//! # Attachments uploaded to an HTTPS service.
+//!
+//! Attachments larger than [`Config::MaxAttachmentBytes`] are not sent by email.
+//! Instead they are encrypted with a random secret and uploaded
+//! to [`Config::AttachmentUploadUrl`] using HTTP PUT.
+//! The message contains the download URL and the secret
+//! in the protected `Chat-Upload-Url` and `Chat-Upload-Key` headers
+//! together with the attachment metadata,
+//! so uploading is only done for encrypted messages.
+//! Uploads are queued in the `uploads` table and done by the SMTP loop
+//! before sending the message, failed uploads are retried with back-off.
+//!
+//! Recipients show the message like a Pre-Message
+//! and download the attachment through the usual download queue,
+//! see [`MsgId::download_full()`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result, ensure};
+use num_traits::FromPrimitive;
+
+use super::{DownloadState, PostMsgMetadata};
+use crate::blob::BlobObject;
+use crate::chat;
+use crate::config::Config;
+use crate::context::Context;
+use crate::download;
+use crate::headerdef::HeaderDef;
+use crate::log::{LogExt, warn};
+use crate::message::{self, Message, MsgId, Viewtype};
+use crate::mimeparser::MimeMessage;
+use crate::net::http;
+use crate::param::Param;
+use crate::pgp;
+use crate::tools::{create_broadcast_secret, create_id};
+use crate::{EventType, chatlist_events};
+
+/// Attachment announced by the `Chat-Upload-Url` header of a received message.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UploadedAttachment {
+    /// URL to download the encrypted attachment from.
+    pub(crate) url: String,
+
+    /// Secret the attachment is encrypted with.
+    pub(crate) key: String,
+
+    /// Attachment metadata.
+    pub(crate) metadata: PostMsgMetadata,
+}
+
+impl UploadedAttachment {
+    /// Returns the uploaded attachment announced by the received message.
+    ///
+    /// The headers are only accepted from encrypted and signed messages.
+    pub(crate) fn from_mime_parser(context: &Context, mime_parser: &MimeMessage) -> Option<Self> {
+        if !mime_parser.was_encrypted() {
+            return None;
+        }
+        let url = mime_parser
+            .get_header(HeaderDef::ChatUploadUrl)?
+            .to_string();
+        let Some(key) = mime_parser.get_header(HeaderDef::ChatUploadKey) else {
+            warn!(context, "Uploaded attachment {url:?} has no key.");
+            return None;
+        };
+        let metadata = mime_parser
+            .get_header(HeaderDef::ChatPostMessageMetadata)
+            .context("No metadata header")
+            .and_then(PostMsgMetadata::try_from_header_value);
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!(
+                    context,
+                    "Failed to parse metadata of uploaded attachment {url:?}: {err:#}."
+                );
+                return None;
+            }
+        };
+        let key = key.to_string();
+        Some(Self { url, key, metadata })
+    }
+
+    /// Schedules the download of the attachment for the received message
+    /// unless it is larger than [`Config::DownloadLimit`].
+    pub(crate) async fn schedule_download(&self, context: &Context, msg_id: MsgId) -> Result<()> {
+        let download_limit = download::download_limit(context).await?;
+        if download_limit.is_none_or(|limit| self.metadata.size <= u64::from(limit)) {
+            msg_id.download_full(context).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of attempts to upload an attachment before the message is marked as failed.
+const MAX_UPLOAD_RETRIES: i64 = 10;
+
+/// Returns true if the attachment of the message has to be uploaded
+/// because it is larger than [`Config::MaxAttachmentBytes`].
+pub(crate) async fn needs_upload(context: &Context, msg: &Message) -> Result<bool> {
+    if !msg.viewtype.has_file() || msg.param.exists(Param::UploadUrl) {
+        return Ok(false);
+    }
+    let max_bytes = context.get_config_u64(Config::MaxAttachmentBytes).await?;
+    if max_bytes == 0
+        || context
+            .get_config(Config::AttachmentUploadUrl)
+            .await?
+            .is_none()
+    {
+        return Ok(false);
+    }
+    let file_bytes = msg
+        .get_filebytes(context)
+        .await?
+        .context("File size is not available")?;
+    Ok(file_bytes > max_bytes)
+}
+
+/// Queues the message for uploading its attachment in the SMTP loop,
+/// see [`upload_attachments`].
+pub(crate) async fn schedule_upload(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT INTO uploads (msg_id) VALUES (?) ON CONFLICT DO UPDATE SET retries=0",
+            (msg_id,),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Uploads the attachments of the queued messages
+/// and creates the SMTP jobs for sending the messages.
+///
+/// Failed uploads stay in the queue.
+/// Messages are marked as failed after [`MAX_UPLOAD_RETRIES`] attempts.
+///
+/// Returns `false` if some uploads failed and should be retried later.
+pub(crate) async fn upload_attachments(context: &Context) -> Result<bool> {
+    let msg_ids = context
+        .sql
+        .query_map_vec("SELECT msg_id FROM uploads ORDER BY msg_id", (), |row| {
+            let msg_id: MsgId = row.get(0)?;
+            Ok(msg_id)
+        })
+        .await?;
+    let mut all_uploaded = true;
+    for msg_id in msg_ids {
+        context
+            .sql
+            .execute(
+                "UPDATE uploads SET retries=retries+1 WHERE msg_id=?",
+                (msg_id,),
+            )
+            .await?;
+        let Some(retries): Option<i64> = context
+            .sql
+            .query_get_value("SELECT retries FROM uploads WHERE msg_id=?", (msg_id,))
+            .await?
+        else {
+            continue;
+        };
+        let msg = Message::load_from_db_optional(context, msg_id).await?;
+        let Some(mut msg) = msg.filter(|msg| !msg.chat_id.is_trash()) else {
+            delete_upload(context, msg_id).await?;
+            continue;
+        };
+        if retries > MAX_UPLOAD_RETRIES {
+            delete_upload(context, msg_id).await?;
+            message::set_msg_failed(
+                context,
+                &mut msg,
+                "Number of upload retries exceeded the limit.",
+            )
+            .await?;
+            continue;
+        }
+        match upload_attachment(context, &mut msg).await {
+            Ok(()) => {
+                delete_upload(context, msg_id).await?;
+                chat::create_send_msg_jobs(context, &mut msg).await?;
+            }
+            Err(err) => {
+                warn!(
+                    context,
+                    "Failed to upload attachment of message {msg_id} (try {retries}): {err:#}."
+                );
+                all_uploaded = false;
+            }
+        }
+    }
+    Ok(all_uploaded)
+}
+
+async fn delete_upload(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute("DELETE FROM uploads WHERE msg_id=?", (msg_id,))
+        .await?;
+    Ok(())
+}
+
+/// Encrypts and uploads the attachment of the message.
+///
+/// On success the download URL and the secret are stored in the message params,
+/// so the message is rendered without the attachment.
+async fn upload_attachment(context: &Context, msg: &mut Message) -> Result<()> {
+    let base_url = context
+        .get_config(Config::AttachmentUploadUrl)
+        .await?
+        .context("No upload URL configured")?;
+    let path = msg.get_file(context).context("Message has no file")?;
+    let key = create_broadcast_secret();
+    let ctext_path = temp_path(context);
+    {
+        let (ctext_path, key) = (ctext_path.clone(), key.clone());
+        tokio::task::spawn_blocking(move || pgp::symm_encrypt_file(&path, &ctext_path, key))
+            .await??;
+    }
+
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), create_id());
+    info!(
+        context,
+        "Uploading attachment of message {} to {url:?}.", msg.id
+    );
+    let token = context.get_config(Config::AttachmentUploadToken).await?;
+    let res = http::put_file(context, &url, token.as_deref(), &ctext_path).await;
+    tokio::fs::remove_file(&ctext_path)
+        .await
+        .log_err(context)
+        .ok();
+    res.context("Failed to upload attachment")?;
+
+    msg.param.set(Param::UploadUrl, url);
+    msg.param.set(Param::UploadKey, key);
+    msg.update_param(context).await?;
+    Ok(())
+}
+
+/// Downloads and decrypts the uploaded attachment of the message.
+///
+/// Returns `false` if the message has no uploaded attachment
+/// and must be downloaded from IMAP instead.
+pub(crate) async fn download_attachment(context: &Context, msg_id: MsgId) -> Result<bool> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    if msg.download_state() == DownloadState::Done {
+        return Ok(false);
+    }
+    let Some(url) = msg.param.get(Param::UploadUrl) else {
+        return Ok(false);
+    };
+    let size = msg
+        .param
+        .get_i64(Param::PostMessageFileBytes)
+        .and_then(|size| u64::try_from(size).ok())
+        .context("Attachment size is unknown")?;
+    info!(context, "Downloading uploaded attachment {url:?}.");
+    let ctext_path = temp_path(context);
+    let res = match http::get_to_file(context, url, &ctext_path, max_ctext_len(size)).await {
+        Ok(_) => store_attachment(context, msg, &ctext_path, size).await,
+        Err(err) => Err(err),
+    };
+    tokio::fs::remove_file(&ctext_path).await.ok();
+    res?;
+    Ok(true)
+}
+
+/// Returns a path for a temporary file in the blobdir.
+fn temp_path(context: &Context) -> PathBuf {
+    context
+        .get_blobdir()
+        .join(format!("tmp-{}", rand::random::<u64>()))
+}
+
+/// Returns the max. size of the encrypted attachment of `size` bytes.
+///
+/// ASCII armor adds a third and OpenPGP adds a few bytes per chunk,
+/// this leaves some room for both.
+fn max_ctext_len(size: u64) -> u64 {
+    (size.saturating_mul(3) / 2).saturating_add(4096)
+}
+
+/// Decrypts the downloaded attachment of `size` bytes
+/// and turns the message into a file message.
+async fn store_attachment(
+    context: &Context,
+    msg: Message,
+    ctext_path: &Path,
+    size: u64,
+) -> Result<()> {
+    let key = msg
+        .param
+        .get(Param::UploadKey)
+        .context("Uploaded attachment has no key")?
+        .to_string();
+    let plain_path = temp_path(context);
+    let res = {
+        let (ctext_path, plain_path) = (ctext_path.to_path_buf(), plain_path.clone());
+        tokio::task::spawn_blocking(move || pgp::symm_decrypt_file(&ctext_path, &plain_path, &key))
+            .await?
+    };
+    let blob = res.and_then(|len| {
+        ensure!(
+            len == size,
+            "Decrypted attachment has {len} bytes instead of the announced {size}"
+        );
+        let filename = msg.param.get(Param::Filename).unwrap_or("file");
+        BlobObject::create_and_deduplicate(context, &plain_path, Path::new(filename))
+    });
+    let blob = match blob {
+        Ok(blob) => blob,
+        Err(err) => {
+            tokio::fs::remove_file(&plain_path).await.ok();
+            return Err(err);
+        }
+    };
+    set_downloaded_blob(context, msg, &blob, usize::try_from(size)?).await
+}
+
+/// Turns the message announcing an attachment not sent by email into a file message
+/// with the attachment stored in `blob`.
+pub(super) async fn set_downloaded_blob(
+    context: &Context,
+    msg: Message,
+    blob: &BlobObject<'_>,
+    bytes: usize,
+) -> Result<()> {
+    let viewtype = msg
+        .param
+        .get_i64(Param::PostMessageViewtype)
+        .and_then(Viewtype::from_i64)
+        .unwrap_or(Viewtype::File);
+
+    let mut param = msg.param.clone();
+    param
+        .set(Param::File, blob.as_name())
+        .remove(Param::UploadUrl)
+        .remove(Param::UploadKey)
+        .remove(Param::P2pHash)
+        .remove(Param::P2pNodeAddr)
+        .remove(Param::PostMessageFileBytes)
+        .remove(Param::PostMessageViewtype);
+    context
+        .sql
+        .execute(
+            "UPDATE msgs SET param=?, type=?, bytes=?, download_state=? WHERE id=?",
+            (
+                param.to_string(),
+                viewtype,
+                bytes,
+                DownloadState::Done,
+                msg.id,
+            ),
+        )
+        .await?;
+    context.emit_event(EventType::MsgsChanged {
+        chat_id: msg.chat_id,
+        msg_id: msg.id,
+    });
+    chatlist_events::emit_chatlist_item_changed(context, msg.chat_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_msg;
+    use crate::message::MessageState;
+    use crate::test_utils::{TestContext, TestContextManager, start_http_server};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_receive_uploaded_attachment() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat_id(bob).await;
+
+        let data = b"not really a large file";
+        let key = create_broadcast_secret();
+        let url = "https://upload.example.org/abc";
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "report.pdf", data, None)?;
+        msg.set_text("see attachment".to_string());
+        msg.param.set(Param::UploadUrl, url);
+        msg.param.set(Param::UploadKey, &key);
+        send_msg(alice, chat_id, &mut msg).await?;
+        let sent = alice.pop_sent_msg().await;
+        assert!(!sent.payload.contains("report.pdf"));
+
+        // The attachment is not downloaded automatically for a contact request.
+        let rcvd = bob.recv_msg(&sent).await;
+        assert_eq!(rcvd.download_state(), DownloadState::Available);
+        rcvd.chat_id.accept(bob).await?;
+
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "report.pdf", data, None)?;
+        msg.set_text("see attachment".to_string());
+        msg.param.set(Param::UploadUrl, url);
+        msg.param.set(Param::UploadKey, &key);
+        send_msg(alice, chat_id, &mut msg).await?;
+        let rcvd = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        assert_eq!(rcvd.get_viewtype(), Viewtype::Text);
+        assert_eq!(rcvd.download_state(), DownloadState::InProgress);
+        assert_eq!(rcvd.param.get(Param::UploadUrl), Some(url));
+        assert_eq!(rcvd.param.get(Param::Filename), Some("report.pdf"));
+
+        let ctext_path = bob.get_blobdir().join("upload.asc");
+        pgp::symm_encrypt_file(&msg.get_file(alice).unwrap(), &ctext_path, key.clone())?;
+        let msg_id = rcvd.id;
+        store_attachment(bob, rcvd, &ctext_path, data.len() as u64).await?;
+        let rcvd = Message::load_from_db(bob, msg_id).await?;
+        assert_eq!(rcvd.download_state(), DownloadState::Done);
+        assert_eq!(rcvd.get_viewtype(), Viewtype::File);
+        assert_eq!(rcvd.get_text(), "see attachment");
+        assert_eq!(rcvd.get_filename().unwrap(), "report.pdf");
+        assert_eq!(tokio::fs::read(rcvd.get_file(bob).unwrap()).await?, data);
+        assert!(!rcvd.param.exists(Param::UploadUrl));
+
+        // Decryption with a wrong key fails.
+        let mut wrong_key = Message::load_from_db(bob, msg_id).await?;
+        wrong_key.param.set(Param::UploadKey, "wrong");
+        assert!(
+            store_attachment(bob, wrong_key, &ctext_path, data.len() as u64)
+                .await
+                .is_err()
+        );
+
+        // The decrypted attachment must have the announced size.
+        let mut wrong_size = Message::load_from_db(bob, msg_id).await?;
+        wrong_size.param.set(Param::UploadKey, &key);
+        assert!(
+            store_attachment(bob, wrong_size, &ctext_path, 1)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+    /// Sends a file larger than [`Config::MaxAttachmentBytes`] from Alice to Bob.
+    async fn send_large_file(alice: &TestContext, bob: &TestContext, url: &str) -> Result<MsgId> {
+        alice
+            .set_config(Config::AttachmentUploadUrl, Some(url))
+            .await?;
+        alice
+            .set_config(Config::MaxAttachmentBytes, Some("10"))
+            .await?;
+        let chat_id = alice.create_chat_id(bob).await;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "report.pdf", b"a large attachment", None)?;
+        let msg_id = send_msg(alice, chat_id, &mut msg).await?;
+
+        // Nothing is sent before the attachment is uploaded.
+        assert!(alice.pop_sent_msg_opt().await.is_none());
+        Ok(msg_id)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_upload_attachment() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let (url, bodies) = start_http_server(201).await?;
+        let msg_id = send_large_file(alice, bob, &url).await?;
+
+        assert!(upload_attachments(alice).await?);
+        assert!(!bodies.recv().await?.is_empty());
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert!(msg.param.get(Param::UploadUrl).unwrap().starts_with(&url));
+
+        let sent = alice.pop_sent_msg().await;
+        assert!(!sent.payload.contains("report.pdf"));
+        let rcvd = bob.recv_msg(&sent).await;
+        assert_eq!(
+            rcvd.param.get(Param::UploadUrl),
+            msg.param.get(Param::UploadUrl)
+        );
+        assert_eq!(rcvd.param.get(Param::Filename), Some("report.pdf"));
+
+        // The queue is empty now.
+        assert!(upload_attachments(alice).await?);
+        assert!(alice.pop_sent_msg_opt().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_upload_attachment_retry() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let (url, _bodies) = start_http_server(500).await?;
+        let msg_id = send_large_file(alice, bob, &url).await?;
+
+        // A failed upload stays queued and the message stays pending.
+        assert!(!upload_attachments(alice).await?);
+        assert!(!upload_attachments(alice).await?);
+        let retries: i64 = alice
+            .sql
+            .query_get_value("SELECT retries FROM uploads WHERE msg_id=?", (msg_id,))
+            .await?
+            .unwrap();
+        assert_eq!(retries, 2);
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.state, MessageState::OutPending);
+        assert!(!msg.param.exists(Param::UploadUrl));
+        assert!(alice.pop_sent_msg_opt().await.is_none());
+
+        // The upload succeeds once the server is back.
+        let (url, _bodies) = start_http_server(201).await?;
+        alice
+            .set_config(Config::AttachmentUploadUrl, Some(&url))
+            .await?;
+        assert!(upload_attachments(alice).await?);
+        alice.pop_sent_msg().await;
+
+        // The message fails after too many retries.
+        let (url, _bodies) = start_http_server(500).await?;
+        let msg_id = send_large_file(alice, bob, &url).await?;
+        alice
+            .sql
+            .execute(
+                "UPDATE uploads SET retries=? WHERE msg_id=?",
+                (MAX_UPLOAD_RETRIES, msg_id),
+            )
+            .await?;
+        assert!(upload_attachments(alice).await?);
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.state, MessageState::OutFailed);
+        assert!(!alice.sql.exists("SELECT COUNT(*) FROM uploads", ()).await?);
+        Ok(())
+    }
+}
@@ -95,6 +95,15 @@ pub async fn encrypt_symmetrically(
 
         Ok(ctext)
     }
+
+    /// Signs the passed in `raw_message` without encrypting it.
+    ///
+    /// Returns ASCII-armored detached signature.
+    pub async fn sign(self, context: &Context, raw_message: &[u8]) -> Result<String> {
+        let sign_key = load_self_secret_key(context).await?;
+        let signature = pgp::pk_calc_signature(raw_message, &sign_key)?;
+        Ok(signature)
+    }
 }
 
 #[cfg(test)]
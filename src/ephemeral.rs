@@ -80,10 +80,10 @@
 use crate::download::DownloadState;
 use crate::events::EventType;
 use crate::log::{LogExt, warn};
-use crate::message::{Message, MessageState, MsgId, Viewtype};
+use crate::message::{self, Message, MessageState, MsgId, Viewtype};
 use crate::mimeparser::SystemMessage;
 use crate::stock_str;
-use crate::tools::{SystemTime, duration_to_str, time};
+use crate::tools::{SystemTime, clock_skew, duration_to_str, time};
 use crate::{location, stats};
 
 /// Ephemeral timer value.
@@ -425,6 +425,9 @@ async fn select_expired_messages(
             .unwrap_or_default();
 
         let threshold_timestamp = now.saturating_sub(delete_device_after);
+        // `timestamp` is the sent timestamp and is compared with the server clock,
+        // `timestamp_rcvd` is set using the local clock.
+        let clock_skew = clock_skew(context).await?;
 
         let rows_expired = context
             .sql
@@ -434,12 +437,13 @@ async fn select_expired_messages(
 FROM msgs
 WHERE
   timestamp < ?1
-  AND timestamp_rcvd < ?1
+  AND timestamp_rcvd < ?2
   AND chat_id > ?
   AND chat_id != ?
   AND chat_id != ?
 "#,
                 (
+                    threshold_timestamp.saturating_sub(clock_skew),
                     threshold_timestamp,
                     DC_CHAT_ID_LAST_SPECIAL,
                     self_chat_id,
@@ -494,7 +498,12 @@ pub(crate) async fn delete_expired_messages(context: &Context, now: i64) -> Resu
                 )?;
                 let mut del_location_stmt =
                     transaction.prepare("DELETE FROM locations WHERE independent=1 AND id=?")?;
+                let mut del_draft_history_stmt = transaction.prepare(
+                    "DELETE FROM draft_history
+                     WHERE chat_id=?1 AND txt=(SELECT txt FROM msgs WHERE id=?2)",
+                )?;
                 for (msg_id, chat_id, viewtype, location_id) in rows {
+                    del_draft_history_stmt.execute((chat_id, msg_id))?;
                     del_msg_stmt.execute((msg_id, DC_CHAT_ID_TRASH))?;
                     if location_id > 0 {
                         del_location_stmt.execute((location_id,))?;
@@ -545,14 +554,19 @@ async fn next_delete_device_after_timestamp(context: &Context) -> Result<Option<
             .sql
             .query_get_value(
                 r#"
-                SELECT min(max(timestamp, timestamp_rcvd))
+                SELECT min(max(timestamp + ?, timestamp_rcvd))
                 FROM msgs
                 WHERE chat_id > ?
                   AND chat_id != ?
                   AND chat_id != ?
                 HAVING count(*) > 0
                 "#,
-                (DC_CHAT_ID_TRASH, self_chat_id, device_chat_id),
+                (
+                    clock_skew(context).await?,
+                    DC_CHAT_ID_TRASH,
+                    self_chat_id,
+                    device_chat_id,
+                ),
             )
             .await?;
 
@@ -566,6 +580,7 @@ async fn next_delete_device_after_timestamp(context: &Context) -> Result<Option<
 ///
 /// Expiration can happen either because user has set `delete_device_after` setting or because the
 /// message itself has an ephemeral timer.
+/// Reminders set with [`MsgId::set_reminder`] are handled by the same loop.
 async fn next_expiration_timestamp(context: &Context) -> Option<i64> {
     let ephemeral_timestamp: Option<i64> = match context
         .sql
@@ -600,9 +615,16 @@ async fn next_expiration_timestamp(context: &Context) -> Option<i64> {
             Ok(timestamp) => timestamp,
         };
 
+    let reminder_timestamp = message::next_reminder_timestamp(context)
+        .await
+        .log_err(context)
+        .ok()
+        .flatten();
+
     ephemeral_timestamp
         .into_iter()
         .chain(delete_device_after_timestamp)
+        .chain(reminder_timestamp)
         .min()
 }
 
@@ -660,6 +682,11 @@ pub(crate) async fn ephemeral_loop(context: &Context, interrupt_receiver: Receiv
             .await
             .log_err(context)
             .ok();
+
+        message::remind_due_msgs(context, time())
+            .await
+            .log_err(context)
+            .ok();
     }
 }
 
@@ -974,3 +974,37 @@ async fn test_delete_device_after_unknown_viewtype() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests that `delete_device_after` takes the measured clock skew into account
+/// because timestamps of outgoing messages are corrected by it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_delete_device_after_clock_skew() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    // The local clock is 1000 seconds ahead of the server clock.
+    alice
+        .set_config_internal(Config::ClockSkew, Some("1000"))
+        .await?;
+    alice
+        .set_config(Config::DeleteDeviceAfter, Some("600"))
+        .await?;
+
+    let chat = alice.create_chat(bob).await;
+    let sent = alice.send_text(chat.id, "Hi").await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert!(msg.timestamp_sort <= time() - 1000);
+
+    SystemTime::shift(Duration::from_secs(500));
+    delete_expired_messages(alice, time()).await?;
+    let msg = Message::load_from_db_optional(alice, msg.id).await?;
+    assert!(msg.is_some_and(|msg| msg.chat_id == chat.id));
+
+    SystemTime::shift(Duration::from_secs(200));
+    delete_expired_messages(alice, time()).await?;
+    let msg = Message::load_from_db_optional(alice, sent.sender_msg_id).await?;
+    assert!(msg.is_none_or(|msg| msg.chat_id.is_trash()));
+
+    Ok(())
+}
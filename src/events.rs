@@ -1,12 +1,24 @@
 //! # Events specification.
 
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
 use anyhow::Result;
 use tokio::sync::Mutex;
 
 pub(crate) mod chatlist_events;
+mod error_code;
 mod payload;
 
-pub use self::payload::EventType;
+pub use self::error_code::ErrorCode;
+pub use self::payload::{EventKind, EventType};
+
+/// Number of last events kept for [`Events::get_emitter_with_replay`].
+const REPLAY_BUFFER_SIZE: usize = 1000;
+
+/// Maximum total size of the events kept for [`Events::get_emitter_with_replay`].
+const REPLAY_BUFFER_MAX_BYTES: usize = 1024 * 1024;
 
 /// Event channel.
 #[derive(Debug, Clone)]
@@ -16,6 +28,110 @@ pub struct Events {
 
     /// Sender side of the event channel.
     sender: async_broadcast::Sender<Event>,
+
+    /// Channels of emitters created with [`Events::subscribe`].
+    subscriptions: Arc<parking_lot::Mutex<Vec<Subscription>>>,
+
+    /// Number of entries in `subscriptions`,
+    /// allows to skip locking if there are none.
+    subscription_count: Arc<AtomicUsize>,
+
+    /// Last emitted events, replayed to late emitters.
+    replay_buffer: Arc<ReplayBuffer>,
+}
+
+/// Last emitted events, replayed to emitters created with [`Events::get_emitter_with_replay`].
+///
+/// Events are only kept until the first emitter is created
+/// and afterwards while there is an emitter created with replay,
+/// so nothing is stored if replay is not used.
+#[derive(Debug)]
+struct ReplayBuffer {
+    /// True until the first emitter is created.
+    startup: AtomicBool,
+
+    /// Number of existing emitters created with replay.
+    emitters: AtomicUsize,
+
+    events: parking_lot::Mutex<ReplayEvents>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayEvents {
+    /// Events with their approximate size.
+    events: VecDeque<(Event, usize)>,
+
+    /// Total size of `events`.
+    size: usize,
+}
+
+impl ReplayBuffer {
+    fn is_active(&self) -> bool {
+        self.startup.load(Ordering::Relaxed) || self.emitters.load(Ordering::Relaxed) > 0
+    }
+
+    /// Stops keeping events if there is no emitter created with replay.
+    fn stop_startup(&self) {
+        self.startup.store(false, Ordering::Relaxed);
+        let mut events = self.events.lock();
+        if !self.is_active() {
+            *events = ReplayEvents::default();
+        }
+    }
+}
+
+/// Keeps the replay buffer active while the emitter created with replay exists.
+#[derive(Debug)]
+struct ReplayGuard(Arc<ReplayBuffer>);
+
+impl Drop for ReplayGuard {
+    fn drop(&mut self) {
+        self.0.emitters.fetch_sub(1, Ordering::Relaxed);
+        self.0.stop_startup();
+    }
+}
+
+/// Returns the approximate size of the event in memory
+/// or `None` if the event should not be replayed because of its large payload.
+fn replay_size(event: &Event) -> Option<usize> {
+    let payload_size = match &event.typ {
+        EventType::WebxdcRealtimeData { .. } => return None,
+        EventType::Info(s)
+        | EventType::SmtpConnected(s)
+        | EventType::ImapConnected(s)
+        | EventType::SmtpMessageSent(s)
+        | EventType::ImapMessageDeleted(s)
+        | EventType::ImapMessageMoved(s)
+        | EventType::NewBlobFile(s)
+        | EventType::DeletedBlobFile(s)
+        | EventType::BlobCopyProgress { path: s, .. }
+        | EventType::Warning(s)
+        | EventType::Error(s)
+        | EventType::ErrorSelfNotInGroup(s)
+        | EventType::ChatUiPropertyChanged { key: s, .. }
+        | EventType::ProxySwitched { url: s }
+        | EventType::IncomingCall {
+            place_call_info: s, ..
+        }
+        | EventType::OutgoingCallAccepted {
+            accept_call_info: s,
+            ..
+        } => s.len(),
+        EventType::IncomingWebxdcNotify { text, href, .. } => text
+            .len()
+            .saturating_add(href.as_ref().map_or(0, |href| href.len())),
+        EventType::ConfigureProgress { comment, .. } => comment.as_ref().map_or(0, |c| c.len()),
+        EventType::ImexFileWritten(path) => path.as_os_str().len(),
+        _ => 0,
+    };
+    Some(payload_size.saturating_add(size_of::<Event>()))
+}
+
+/// Event channel receiving only the selected kinds of events.
+#[derive(Debug)]
+struct Subscription {
+    filter: HashSet<EventKind>,
+    sender: async_broadcast::Sender<Event>,
 }
 
 impl Default for Events {
@@ -38,19 +154,107 @@ pub fn new() -> Self {
         // Remove oldest event on overflow.
         sender.set_overflow(true);
 
-        Self { _receiver, sender }
+        Self {
+            _receiver,
+            sender,
+            subscriptions: Default::default(),
+            subscription_count: Default::default(),
+            replay_buffer: Arc::new(ReplayBuffer {
+                startup: AtomicBool::new(true),
+                emitters: AtomicUsize::new(0),
+                events: Default::default(),
+            }),
+        }
     }
 
     /// Emits an event into event channel.
     ///
     /// If the channel is full, deletes the oldest event first.
     pub fn emit(&self, event: Event) {
+        if self.subscription_count.load(Ordering::Relaxed) > 0 {
+            let mut subscriptions = self.subscriptions.lock();
+            // Subscriptions are closed once their emitter is dropped.
+            subscriptions.retain(|subscription| !subscription.sender.is_closed());
+            self.subscription_count
+                .store(subscriptions.len(), Ordering::Relaxed);
+            let kind = EventKind::from(&event.typ);
+            for subscription in subscriptions.iter() {
+                if subscription.filter.contains(&kind) {
+                    subscription.sender.try_broadcast(event.clone()).ok();
+                }
+            }
+        }
+
+        if !self.replay_buffer.is_active() {
+            self.sender.try_broadcast(event).ok();
+            return;
+        }
+
+        // Hold the lock while broadcasting
+        // so emitters created with replay
+        // neither miss nor receive the event twice.
+        let mut replay = self.replay_buffer.events.lock();
+        if let Some(size) = replay_size(&event) {
+            replay.events.push_back((event.clone(), size));
+            replay.size = replay.size.saturating_add(size);
+            while replay.events.len() > REPLAY_BUFFER_SIZE || replay.size > REPLAY_BUFFER_MAX_BYTES
+            {
+                let Some((_, size)) = replay.events.pop_front() else {
+                    break;
+                };
+                replay.size = replay.size.saturating_sub(size);
+            }
+        }
         self.sender.try_broadcast(event).ok();
     }
 
     /// Creates an event emitter.
     pub fn get_emitter(&self) -> EventEmitter {
-        EventEmitter(Mutex::new(self.sender.new_receiver()))
+        self.replay_buffer.stop_startup();
+        EventEmitter::new(VecDeque::new(), self.sender.new_receiver(), None)
+    }
+
+    /// Creates an event emitter which first receives up to `n` last emitted events.
+    ///
+    /// This allows UIs attaching late to receive events emitted during startup,
+    /// e.g. errors.
+    /// Events are kept until the first emitter is created
+    /// and afterwards as long as an emitter created with replay exists,
+    /// so an emitter created with replay early
+    /// keeps events for emitters created later.
+    /// At most 1000 events and 1 MiB are kept,
+    /// events with large payloads such as [`EventType::WebxdcRealtimeData`] are not kept.
+    pub fn get_emitter_with_replay(&self, n: usize) -> EventEmitter {
+        self.replay_buffer.emitters.fetch_add(1, Ordering::Relaxed);
+        let guard = ReplayGuard(self.replay_buffer.clone());
+        self.replay_buffer.startup.store(false, Ordering::Relaxed);
+
+        let replay = self.replay_buffer.events.lock();
+        let events = replay
+            .events
+            .iter()
+            .skip(replay.events.len().saturating_sub(n))
+            .map(|(event, _size)| event.clone())
+            .collect();
+        EventEmitter::new(events, self.sender.new_receiver(), Some(guard))
+    }
+
+    /// Creates an event emitter receiving only the events of the given kinds.
+    ///
+    /// Unlike emitters created with [`Events::get_emitter`],
+    /// each subscribed emitter receives all matching events
+    /// and other events are not queued for it at all.
+    pub fn subscribe(&self, filter: impl IntoIterator<Item = EventKind>) -> EventEmitter {
+        let (mut sender, receiver) = async_broadcast::broadcast(10_000);
+        sender.set_overflow(true);
+        let mut subscriptions = self.subscriptions.lock();
+        subscriptions.push(Subscription {
+            filter: filter.into_iter().collect(),
+            sender,
+        });
+        self.subscription_count
+            .store(subscriptions.len(), Ordering::Relaxed);
+        EventEmitter::new(VecDeque::new(), receiver, None)
     }
 }
 
@@ -63,15 +267,41 @@ pub fn get_emitter(&self) -> EventEmitter {
 /// [`Context`]: crate::context::Context
 /// [`Context::get_event_emitter`]: crate::context::Context::get_event_emitter
 #[derive(Debug)]
-pub struct EventEmitter(Mutex<async_broadcast::Receiver<Event>>);
+pub struct EventEmitter(Mutex<EventReceiver>);
+
+#[derive(Debug)]
+struct EventReceiver {
+    /// Events to return before receiving from the channel.
+    replay: VecDeque<Event>,
+
+    receiver: async_broadcast::Receiver<Event>,
+
+    /// Keeps the replay buffer active for emitters created with replay.
+    _replay_guard: Option<ReplayGuard>,
+}
 
 impl EventEmitter {
+    fn new(
+        replay: VecDeque<Event>,
+        receiver: async_broadcast::Receiver<Event>,
+        replay_guard: Option<ReplayGuard>,
+    ) -> Self {
+        Self(Mutex::new(EventReceiver {
+            replay,
+            receiver,
+            _replay_guard: replay_guard,
+        }))
+    }
+
     /// Async recv of an event. Return `None` if the `Sender` has been dropped.
     ///
     /// [`try_recv`]: Self::try_recv
     pub async fn recv(&self) -> Option<Event> {
         let mut lock = self.0.lock().await;
-        match lock.recv_direct().await {
+        if let Some(event) = lock.replay.pop_front() {
+            return Some(event);
+        }
+        match lock.receiver.recv_direct().await {
             Err(async_broadcast::RecvError::Overflowed(n)) => Some(Event {
                 id: 0,
                 typ: EventType::EventChannelOverflow { n },
@@ -93,7 +323,10 @@ pub fn try_recv(&self) -> Result<Event> {
         // to avoid blocking
         // in case there is a concurrent call to `recv`.
         let mut lock = self.0.try_lock()?;
-        match lock.try_recv() {
+        if let Some(event) = lock.replay.pop_front() {
+            return Ok(event);
+        }
+        match lock.receiver.try_recv() {
             Err(async_broadcast::TryRecvError::Overflowed(n)) => {
                 // Some events have been lost,
                 // but the channel is not closed.
@@ -114,19 +347,27 @@ pub fn try_recv(&self) -> Result<Event> {
     /// Returns empty vector if the sender has been dropped.
     pub async fn recv_batch(&self) -> Vec<Event> {
         let mut lock = self.0.lock().await;
-        let mut res = match lock.recv_direct().await {
-            Err(async_broadcast::RecvError::Overflowed(n)) => vec![Event {
-                id: 0,
-                typ: EventType::EventChannelOverflow { n },
-            }],
-            Err(async_broadcast::RecvError::Closed) => return Vec::new(),
-            Ok(event) => vec![event],
+        let mut res = if let Some(event) = lock.replay.pop_front() {
+            vec![event]
+        } else {
+            match lock.receiver.recv_direct().await {
+                Err(async_broadcast::RecvError::Overflowed(n)) => vec![Event {
+                    id: 0,
+                    typ: EventType::EventChannelOverflow { n },
+                }],
+                Err(async_broadcast::RecvError::Closed) => return Vec::new(),
+                Ok(event) => vec![event],
+            }
         };
 
         // Return up to 100 events in a single batch
         // to have a limit on used memory if events arrive too fast.
         for _ in 0..100 {
-            match lock.try_recv() {
+            if let Some(event) = lock.replay.pop_front() {
+                res.push(event);
+                continue;
+            }
+            match lock.receiver.try_recv() {
                 Err(async_broadcast::TryRecvError::Overflowed(n)) => res.push(Event {
                     id: 0,
                     typ: EventType::EventChannelOverflow { n },
@@ -163,3 +404,133 @@ pub struct Event {
     /// These are documented in `deltachat.h` as the `DC_EVENT_*` constants.
     pub typ: EventType,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MsgId;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_subscribe() -> Result<()> {
+        let events = Events::new();
+        let emitter = events.get_emitter();
+        let subscription = events.subscribe([EventKind::IncomingMsgBunch, EventKind::Test]);
+        let other_subscription = events.subscribe([EventKind::Test]);
+
+        for typ in [
+            EventType::Info("foo".to_string()),
+            EventType::IncomingMsgBunch,
+            EventType::Test,
+        ] {
+            events.emit(Event { id: 1, typ });
+        }
+
+        assert_eq!(emitter.recv_batch().await.len(), 3);
+        let received: Vec<EventType> = subscription
+            .recv_batch()
+            .await
+            .into_iter()
+            .map(|event| event.typ)
+            .collect();
+        assert_eq!(received, [EventType::IncomingMsgBunch, EventType::Test]);
+        assert_eq!(
+            other_subscription.recv().await.unwrap().typ,
+            EventType::Test
+        );
+        assert!(other_subscription.try_recv().is_err());
+
+        // Dropped subscriptions are removed.
+        drop(other_subscription);
+        events.emit(Event {
+            id: 1,
+            typ: EventType::Test,
+        });
+        assert_eq!(events.subscriptions.lock().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_emitter_with_replay() -> Result<()> {
+        let events = Events::new();
+        for i in 0..REPLAY_BUFFER_SIZE + 10 {
+            events.emit(Event {
+                id: 1,
+                typ: EventType::Info(i.to_string()),
+            });
+        }
+        let emitter = events.get_emitter_with_replay(2);
+        let all_emitter = events.get_emitter_with_replay(usize::MAX);
+        events.emit(Event {
+            id: 1,
+            typ: EventType::Test,
+        });
+
+        let received: Vec<EventType> = emitter
+            .recv_batch()
+            .await
+            .into_iter()
+            .map(|event| event.typ)
+            .collect();
+        assert_eq!(
+            received,
+            [
+                EventType::Info((REPLAY_BUFFER_SIZE + 8).to_string()),
+                EventType::Info((REPLAY_BUFFER_SIZE + 9).to_string()),
+                EventType::Test
+            ]
+        );
+
+        assert_eq!(
+            all_emitter.recv().await.unwrap().typ,
+            EventType::Info(10.to_string())
+        );
+        let mut n = 1;
+        while all_emitter.try_recv().is_ok() {
+            n += 1;
+        }
+        assert_eq!(n, REPLAY_BUFFER_SIZE + 1);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_replay_buffer_limits() -> Result<()> {
+        let events = Events::new();
+        let _emitter = events.get_emitter();
+
+        // Nothing is kept without an emitter created with replay.
+        events.emit(Event {
+            id: 1,
+            typ: EventType::Test,
+        });
+        assert!(events.replay_buffer.events.lock().events.is_empty());
+
+        let replay_emitter = events.get_emitter_with_replay(0);
+        events.emit(Event {
+            id: 1,
+            typ: EventType::WebxdcRealtimeData {
+                msg_id: MsgId::new(10),
+                data: vec![0; 100_000],
+            },
+        });
+        assert!(events.replay_buffer.events.lock().events.is_empty());
+
+        let large_info = "a".repeat(100_000);
+        for _ in 0..20 {
+            events.emit(Event {
+                id: 1,
+                typ: EventType::Info(large_info.clone()),
+            });
+        }
+        let n = events
+            .get_emitter_with_replay(usize::MAX)
+            .recv_batch()
+            .await
+            .len();
+        assert_eq!(n, REPLAY_BUFFER_MAX_BYTES / 100_000);
+
+        // The events are dropped together with the last emitter created with replay.
+        drop(replay_emitter);
+        assert!(events.replay_buffer.events.lock().events.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! # Error codes.
+
+use async_smtp::response::{Category, Detail, Response, Severity};
+use serde::{Deserialize, Serialize};
+use tokio_rustls::rustls;
+
+/// Machine-readable category of an error,
+/// returned by [`Context::get_last_error_code`](crate::context::Context::get_last_error_code)
+/// so UIs do not have to match on error strings.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::FromRepr,
+)]
+#[repr(u32)]
+pub enum ErrorCode {
+    /// The error does not belong to any of the other categories.
+    #[default]
+    Other = 0,
+
+    /// The server rejected the login credentials.
+    AuthFailed = 1,
+
+    /// The storage quota on the server is exceeded.
+    QuotaExceeded = 2,
+
+    /// The TLS certificate of the server is invalid.
+    TlsInvalid = 3,
+
+    /// The server refused the request because of rate limiting.
+    RateLimited = 4,
+
+    /// The server is unreachable.
+    Network = 5,
+
+    /// The scanned single-use invite code was already used.
+    SecurejoinCodeUsed = 6,
+}
+
+impl ErrorCode {
+    /// Determines the error code of an error
+    /// by looking at the errors in its chain.
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<async_smtp::error::Error>() {
+                let code = match err {
+                    async_smtp::error::Error::Permanent(response)
+                    | async_smtp::error::Error::Transient(response) => {
+                        Self::from_smtp_response(response)
+                    }
+                    _ => Self::Other,
+                };
+                if code != Self::Other {
+                    return code;
+                }
+            }
+            if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+                if err
+                    .get_ref()
+                    .and_then(|err| err.downcast_ref::<rustls::Error>())
+                    .is_some_and(|err| matches!(err, rustls::Error::InvalidCertificate(_)))
+                {
+                    return Self::TlsInvalid;
+                }
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::ConnectionRefused
+                        | std::io::ErrorKind::ConnectionReset
+                        | std::io::ErrorKind::HostUnreachable
+                        | std::io::ErrorKind::NetworkUnreachable
+                        | std::io::ErrorKind::TimedOut
+                ) {
+                    return Self::Network;
+                }
+            }
+            if let Some(rustls::Error::InvalidCertificate(_)) =
+                cause.downcast_ref::<rustls::Error>()
+            {
+                return Self::TlsInvalid;
+            }
+        }
+        from_text(&format!("{err:#}"))
+    }
+
+    /// Determines the error code from an SMTP reply code,
+    /// the enhanced status code (RFC 3463) and the reply text.
+    pub(crate) fn from_smtp_response(response: &Response) -> Self {
+        let code = response.code;
+        match (code.severity, code.category, code.detail) {
+            (Severity::PermanentNegativeCompletion, Category::Unspecified3, Detail::Five)
+            | (Severity::PermanentNegativeCompletion, Category::Unspecified3, Detail::Four) => {
+                return Self::AuthFailed;
+            }
+            (Severity::PermanentNegativeCompletion, Category::MailSystem, Detail::Two) => {
+                return Self::QuotaExceeded;
+            }
+            _ => {}
+        }
+        match response
+            .first_word()
+            .and_then(|enhanced_code| enhanced_code.get(1..))
+        {
+            Some(".7.8") => Self::AuthFailed,
+            Some(".2.2") => Self::QuotaExceeded,
+            _ => from_text(&response.message.join(" ")),
+        }
+    }
+}
+
+/// Determines the error code from the error message
+/// for servers which do not return structured errors.
+fn from_text(text: &str) -> ErrorCode {
+    let text = text.to_lowercase();
+    if text.contains("authentication failed") || text.contains("invalid credentials") {
+        ErrorCode::AuthFailed
+    } else if text.contains("quota") || text.contains("mailbox full") {
+        ErrorCode::QuotaExceeded
+    } else if text.contains("rate limit") || text.contains("ratelimit") {
+        ErrorCode::RateLimited
+    } else {
+        ErrorCode::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::format_err;
+    use async_smtp::response::Code;
+
+    use super::*;
+
+    #[test]
+    fn test_error_code_from_error() {
+        let smtp_error = |code, message: &str| {
+            anyhow::Error::new(async_smtp::error::Error::Permanent(Response::new(
+                code,
+                vec![message.to_string()],
+            )))
+            .context("Failed to send message")
+        };
+        assert_eq!(
+            ErrorCode::from_error(&smtp_error(
+                Code::new(
+                    Severity::PermanentNegativeCompletion,
+                    Category::Unspecified3,
+                    Detail::Five
+                ),
+                "5.7.8 Error: authentication failed"
+            )),
+            ErrorCode::AuthFailed
+        );
+        assert_eq!(
+            ErrorCode::from_error(&smtp_error(
+                Code::new(
+                    Severity::PermanentNegativeCompletion,
+                    Category::MailSystem,
+                    Detail::Two
+                ),
+                "5.2.2 Mailbox full"
+            )),
+            ErrorCode::QuotaExceeded
+        );
+        assert_eq!(
+            ErrorCode::from_error(&smtp_error(
+                Code::new(
+                    Severity::PermanentNegativeCompletion,
+                    Category::MailSystem,
+                    Detail::Zero
+                ),
+                "5.7.1 Rate limit exceeded"
+            )),
+            ErrorCode::RateLimited
+        );
+
+        let io_error = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            ErrorCode::from_error(&anyhow::Error::new(io_error).context("Failed to connect")),
+            ErrorCode::Network
+        );
+        let tls_error = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::Expired),
+        );
+        assert_eq!(
+            ErrorCode::from_error(&anyhow::Error::new(tls_error)),
+            ErrorCode::TlsInvalid
+        );
+
+        assert_eq!(
+            ErrorCode::from_error(&format_err!("Something went wrong")),
+            ErrorCode::Other
+        );
+    }
+}
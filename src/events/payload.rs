@@ -13,7 +13,16 @@
 use crate::webxdc::StatusUpdateSerial;
 
 /// Event payload.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// [`EventKind`] is the kind of the event without the payload,
+/// used to subscribe to selected events with [`Events::subscribe`](crate::events::Events::subscribe).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumDiscriminants)]
+#[strum_discriminants(
+    name(EventKind),
+    vis(pub),
+    derive(Hash, PartialOrd, Ord, Serialize, Deserialize),
+    doc = "Kind of an [`EventType`] without the payload."
+)]
 pub enum EventType {
     /// The library-user may write an informational string to the log.
     ///
@@ -45,6 +54,16 @@ pub enum EventType {
     /// Emitted when an file in the $BLOBDIR was deleted
     DeletedBlobFile(String),
 
+    /// Inform about the progress of copying a large file into the $BLOBDIR,
+    /// e.g. when a big video is attached to a message.
+    BlobCopyProgress {
+        /// Path of the file being copied.
+        path: String,
+
+        /// Progress in permille, 1000=done.
+        progress: u16,
+    },
+
     /// The library-user should write a warning string to the log.
     ///
     /// This event should *not* be reported to the end-user using a popup or something like
@@ -61,6 +80,9 @@ pub enum EventType {
     /// it might be better to delay showing these events until the function has really
     /// failed (returned false). It should be sufficient to report only the *last* error
     /// in a message box then.
+    ///
+    /// The machine-readable category of the error
+    /// is returned by `Context::get_last_error_code()`.
     Error(String),
 
     /// An action cannot be performed because the user is not in the group.
@@ -114,6 +136,19 @@ pub enum EventType {
         reaction: Reaction,
     },
 
+    /// A subscriber reported a message of an outgoing broadcast channel,
+    /// see [`Context::get_abuse_reports()`](crate::context::Context::get_abuse_reports).
+    IncomingAbuseReport {
+        /// ID of the broadcast channel.
+        chat_id: ChatId,
+
+        /// ID of the reported message.
+        msg_id: MsgId,
+
+        /// ID of the contact who sent the report.
+        contact_id: ContactId,
+    },
+
     /// A webxdc wants an info message or a changed summary to be notified.
     IncomingWebxdcNotify {
         /// ID of the chat.
@@ -136,6 +171,9 @@ pub enum EventType {
     /// when receiving this message.
     ///
     /// There is no extra #DC_EVENT_MSGS_CHANGED event send together with this event.
+    ///
+    /// During quiet hours, see [`Context::is_quiet_hours()`](crate::context::Context::is_quiet_hours),
+    /// the event is still emitted, but no notification should be shown.
     IncomingMsg {
         /// ID of the chat where the message is assigned.
         chat_id: ChatId,
@@ -147,6 +185,18 @@ pub enum EventType {
     /// Downloading a bunch of messages just finished.
     IncomingMsgBunch,
 
+    /// A received message has an attachment that looks harmful,
+    /// see [`crate::message::Message::get_attachment_warning`].
+    ///
+    /// Emitted in addition to the usual events for the message.
+    AttachmentWarning {
+        /// ID of the chat where the message is assigned.
+        chat_id: ChatId,
+
+        /// ID of the message.
+        msg_id: MsgId,
+    },
+
     /// Messages were seen or noticed.
     /// chat id is always set.
     MsgsNoticed(ChatId),
@@ -192,6 +242,26 @@ pub enum EventType {
         msg_id: MsgId,
     },
 
+    /// Aggregated delivery state of a message sent to a broadcast channel changed.
+    ///
+    /// Emitted instead of tracking [`EventType::MsgDelivered`]
+    /// and [`EventType::MsgReadCountChanged`] for each message,
+    /// e.g. by bots sending to many subscribers.
+    #[serde(rename_all = "camelCase")]
+    BroadcastDeliveryUpdate {
+        /// ID of the message sent to the broadcast channel.
+        msg_id: MsgId,
+
+        /// Number of subscribers the message was delivered to.
+        delivered: u32,
+
+        /// Number of subscribers who sent a read receipt.
+        read: u32,
+
+        /// Number of subscribers of the channel.
+        total: u32,
+    },
+
     /// A single message was deleted.
     ///
     /// This event means that the message will no longer appear in the messagelist.
@@ -213,6 +283,19 @@ pub enum EventType {
         msg_id: MsgId,
     },
 
+    /// A reminder set with [`MsgId::set_reminder`] is due.
+    ///
+    /// If the message is incoming, it is marked as fresh again.
+    /// UI may show a notification for the message.
+    #[serde(rename_all = "camelCase")]
+    MsgReminder {
+        /// ID of the chat which the message belongs to.
+        chat_id: ChatId,
+
+        /// ID of the message to remind about.
+        msg_id: MsgId,
+    },
+
     /// Chat changed.  The name or the image of a chat group was changed or members were added or removed.
     /// Or the verify state of a chat has changed.
     /// See dc_set_chat_name(), dc_set_chat_profile_image(), dc_add_contact_to_chat()
@@ -231,6 +314,15 @@ pub enum EventType {
         timer: EphemeralTimer,
     },
 
+    /// A UI-specific property of the chat was changed,
+    /// see [`ChatId::set_ui_property`](crate::chat::ChatId::set_ui_property).
+    ChatUiPropertyChanged {
+        /// ID of the chat.
+        chat_id: ChatId,
+        /// Key of the changed property.
+        key: String,
+    },
+
     /// Chat was deleted.
     ChatDeleted {
         /// Chat ID.
@@ -274,6 +366,20 @@ pub enum EventType {
     /// @param data2 0
     ImexFileWritten(PathBuf),
 
+    /// Inform about the progress of searching older messages
+    /// started by [`Context::fetch_older_messages`](crate::context::Context::fetch_older_messages).
+    ///
+    /// @param data1 (usize) 0=error, 1-999=progress in permille, 1000=success and done
+    /// @param data2 0
+    FetchOlderMsgsProgress(u16),
+
+    /// Inform about the progress of housekeeping,
+    /// either scheduled or started by [`Context::run_housekeeping_now`](crate::context::Context::run_housekeeping_now).
+    ///
+    /// @param data1 (usize) 1-999=progress in permille, 1000=done
+    /// @param data2 0
+    HousekeepingProgress(u16),
+
     /// Progress information of a secure-join handshake from the view of the inviter
     /// (Alice, the person who shows the QR code).
     ///
@@ -302,6 +408,7 @@ pub enum EventType {
         contact_id: ContactId,
 
         /// Progress as:
+        /// 0=inviter rejected the handshake, the reason is returned by `Context::get_last_error_code()`
         /// 400=vg-/vc-request-with-auth sent, typically shown as "alice@addr verified, introducing myself."
         /// (Bob has verified alice and waits until Alice does the same for him)
         /// 1000=vg-member-added/vc-contact-confirm received
@@ -314,6 +421,25 @@ pub enum EventType {
     /// dc_get_connectivity_html() for details.
     ConnectivityChanged,
 
+    /// Connections through the active proxy failed repeatedly
+    /// and the next proxy configured in `proxy_url` is used now.
+    /// See `Context::get_proxy_health()` for details.
+    ProxySwitched {
+        /// URL of the proxy used now.
+        url: String,
+    },
+
+    /// The local clock differs from the server clock by more than a few minutes.
+    /// The UI may ask the user to check the date, time and time zone of the device.
+    ///
+    /// @param data1 (int) Seconds the local clock is ahead of the server clock,
+    ///     negative if it is behind.
+    /// @param data2 0
+    ClockSkewDetected {
+        /// Seconds the local clock is ahead of the server clock, negative if it is behind.
+        skew: i64,
+    },
+
     /// The user's avatar changed.
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
@@ -326,6 +452,23 @@ pub enum EventType {
         key: Config,
     },
 
+    /// A config value was set, either locally or by a sync message from another device.
+    /// Like for `ConfigSynced`, the value isn't here.
+    ConfigChanged {
+        /// Configuration key.
+        key: Config,
+    },
+
+    /// The encrypted key backup on the server was stored or deleted,
+    /// see `Context::backup_key_to_server()`.
+    ///
+    /// @param data1 (int) 1 if the backup was stored, 0 if it was deleted.
+    /// @param data2 0
+    KeyBackupChanged {
+        /// True if the backup was stored, false if it was deleted.
+        stored: bool,
+    },
+
     /// Webxdc status update received.
     WebxdcStatusUpdate {
         /// Message ID.
@@ -344,6 +487,25 @@ pub enum EventType {
         data: Vec<u8>,
     },
 
+    /// Realtime data stored while there were no peers has been sent to peers.
+    WebxdcRealtimeDataForwarded {
+        /// Message ID of the webxdc instance.
+        msg_id: MsgId,
+
+        /// ID returned by [`crate::peer_channels::send_webxdc_realtime_data_stored`].
+        data_id: u32,
+    },
+
+    /// Realtime data stored while there were no peers has been dropped
+    /// because no peer appeared in time or the storage limit was reached.
+    WebxdcRealtimeDataExpired {
+        /// Message ID of the webxdc instance.
+        msg_id: MsgId,
+
+        /// ID returned by [`crate::peer_channels::send_webxdc_realtime_data_stored`].
+        data_id: u32,
+    },
+
     /// Advertisement received over an ephemeral peer channel.
     /// This can be used by bots to initiate peer-to-peer communication from their side.
     WebxdcRealtimeAdvertisementReceived {
@@ -0,0 +1,218 @@
+//! # Incoming message filters.
+//!
+//! Filters are user-configured rules evaluated for every incoming message.
+//! A rule matches a regular expression against the sender address,
+//! the subject, the text or the `Auto-Submitted` header of the message.
+//! The action of the first matching rule is applied to the message,
+//! see [`FilterAction`].
+
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result, ensure};
+use deltachat_derive::{FromSql, ToSql};
+use regex::Regex;
+
+use crate::context::Context;
+use crate::headerdef::HeaderDef;
+use crate::log::warn;
+use crate::mimeparser::MimeMessage;
+
+/// Part of the message a [`MsgFilter`] is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql)]
+#[repr(u32)]
+pub enum FilterField {
+    /// Address of the sender.
+    Sender = 1,
+
+    /// Subject of the message.
+    Subject = 2,
+
+    /// Text of the message.
+    Text = 3,
+
+    /// Value of the `Auto-Submitted` header, empty if the header is missing.
+    ///
+    /// Automated messages such as bot messages and vacation notices
+    /// have values like `auto-generated` or `auto-replied`,
+    /// so matching `^auto-` catches them, see [RFC 3834](https://www.rfc-editor.org/rfc/rfc3834).
+    AutoSubmitted = 4,
+}
+
+/// Action applied to a message matching a [`MsgFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql)]
+#[repr(u32)]
+pub enum FilterAction {
+    /// Add the message as noticed, so that no notification is shown.
+    MuteNotification = 1,
+
+    /// Archive the chat the message is added to.
+    Archive = 2,
+
+    /// Hide the message and move it to the Spam folder on the server.
+    MoveToSpam = 3,
+
+    /// Delete the message locally and on the server.
+    Delete = 4,
+}
+
+/// Filter with the compiled pattern,
+/// cached in [`Context`] to not compile the patterns for each incoming message.
+#[derive(Debug)]
+pub(crate) struct CompiledFilter {
+    field: FilterField,
+    re: Regex,
+    action: FilterAction,
+}
+
+/// Incoming message filter, see [`Context::add_msg_filter()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsgFilter {
+    /// ID of the filter, to be passed to [`Context::delete_msg_filter()`].
+    pub id: u32,
+
+    /// Part of the message the pattern is matched against.
+    pub field: FilterField,
+
+    /// Regular expression.
+    pub pattern: String,
+
+    /// Action applied to matching messages.
+    pub action: FilterAction,
+}
+
+impl Context {
+    /// Adds a filter for incoming messages.
+    ///
+    /// `pattern` is a regular expression matched against the `field` of the message,
+    /// use `(?i)` prefix for case-insensitive matching.
+    /// Filters are evaluated in the order they were added,
+    /// only the action of the first matching filter is applied.
+    ///
+    /// Returns the ID of the new filter.
+    pub async fn add_msg_filter(
+        &self,
+        field: FilterField,
+        pattern: &str,
+        action: FilterAction,
+    ) -> Result<u32> {
+        ensure!(!pattern.is_empty(), "Empty filter pattern");
+        Regex::new(pattern).with_context(|| format!("Invalid filter pattern {pattern:?}"))?;
+        let id = self
+            .sql
+            .insert(
+                "INSERT INTO msg_filters (field, pattern, action) VALUES (?, ?, ?)",
+                (field, pattern, action),
+            )
+            .await?;
+        *self.msg_filters.write().await = None;
+        Ok(u32::try_from(id)?)
+    }
+
+    /// Returns all filters for incoming messages in the order they are evaluated.
+    pub async fn get_msg_filters(&self) -> Result<Vec<MsgFilter>> {
+        self.sql
+            .query_map_vec(
+                "SELECT id, field, pattern, action FROM msg_filters ORDER BY id",
+                (),
+                |row| {
+                    Ok(MsgFilter {
+                        id: row.get(0)?,
+                        field: row.get(1)?,
+                        pattern: row.get(2)?,
+                        action: row.get(3)?,
+                    })
+                },
+            )
+            .await
+    }
+
+    /// Deletes the filter with the given ID.
+    pub async fn delete_msg_filter(&self, id: u32) -> Result<()> {
+        self.sql
+            .execute("DELETE FROM msg_filters WHERE id=?", (id,))
+            .await?;
+        *self.msg_filters.write().await = None;
+        Ok(())
+    }
+}
+
+/// Returns the action of the first filter matching the message, if any.
+pub(crate) async fn get_filter_action(
+    context: &Context,
+    mime_parser: &MimeMessage,
+) -> Result<Option<FilterAction>> {
+    let filters = get_compiled_filters(context).await?;
+    if filters.is_empty() {
+        return Ok(None);
+    }
+    let subject = mime_parser.get_subject().unwrap_or_default();
+    let text = mime_parser
+        .parts
+        .iter()
+        .map(|part| part.msg.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let auto_submitted = mime_parser
+        .get_header(HeaderDef::AutoSubmitted)
+        .unwrap_or_default();
+    for filter in filters.iter() {
+        let haystack = match filter.field {
+            FilterField::Sender => &mime_parser.from.addr,
+            FilterField::Subject => &subject,
+            FilterField::Text => &text,
+            FilterField::AutoSubmitted => auto_submitted,
+        };
+        if filter.re.is_match(haystack) {
+            return Ok(Some(filter.action));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the filters with compiled patterns,
+/// loading them from the database if they are not cached yet.
+async fn get_compiled_filters(context: &Context) -> Result<Arc<Vec<CompiledFilter>>> {
+    if let Some(filters) = &*context.msg_filters.read().await {
+        return Ok(Arc::clone(filters));
+    }
+    let mut cache = context.msg_filters.write().await;
+    if let Some(filters) = &*cache {
+        return Ok(Arc::clone(filters));
+    }
+    let mut filters = Vec::new();
+    for filter in context.get_msg_filters().await? {
+        let Ok(re) = Regex::new(&filter.pattern) else {
+            warn!(
+                context,
+                "Skipping filter {} with invalid pattern.", filter.id
+            );
+            continue;
+        };
+        filters.push(CompiledFilter {
+            field: filter.field,
+            re,
+            action: filter.action,
+        });
+    }
+    let filters = Arc::new(filters);
+    *cache = Some(Arc::clone(&filters));
+    Ok(filters)
+}
+
+/// Schedules moving all known IMAP messages with the given Message-ID
+/// to the Spam folder by adding them to the `imap_spam` table.
+pub(crate) async fn move_to_spam_on_imap_table(context: &Context, message_id: &str) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT OR IGNORE INTO imap_spam (id)
+             SELECT id FROM imap WHERE rfc724_mid=?",
+            (message_id,),
+        )
+        .await?;
+    context.scheduler.interrupt_inbox().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod filters_tests;
@@ -0,0 +1,151 @@
+use super::*;
+use crate::chat::{Chat, ChatVisibility};
+use crate::message::{Message, MessageState};
+use crate::test_utils::TestContextManager;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_msg_filters() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+
+    assert!(alice.get_msg_filters().await?.is_empty());
+    assert!(
+        alice
+            .add_msg_filter(FilterField::Text, "(unclosed", FilterAction::Delete)
+            .await
+            .is_err()
+    );
+    assert!(
+        alice
+            .add_msg_filter(FilterField::Text, "", FilterAction::Delete)
+            .await
+            .is_err()
+    );
+
+    let id1 = alice
+        .add_msg_filter(
+            FilterField::Sender,
+            "@spam\\.example$",
+            FilterAction::Delete,
+        )
+        .await?;
+    let id2 = alice
+        .add_msg_filter(
+            FilterField::Subject,
+            "(?i)newsletter",
+            FilterAction::Archive,
+        )
+        .await?;
+    let filters = alice.get_msg_filters().await?;
+    assert_eq!(filters.len(), 2);
+    assert_eq!(filters[0].id, id1);
+    assert_eq!(filters[0].field, FilterField::Sender);
+    assert_eq!(filters[0].pattern, "@spam\\.example$");
+    assert_eq!(filters[0].action, FilterAction::Delete);
+    assert_eq!(filters[1].id, id2);
+
+    alice.delete_msg_filter(id1).await?;
+    let filters = alice.get_msg_filters().await?;
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0].id, id2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_msg_filter_actions() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    alice
+        .add_msg_filter(FilterField::Text, "(?i)^buy now", FilterAction::Delete)
+        .await?;
+    alice
+        .add_msg_filter(FilterField::Text, "cheap", FilterAction::MoveToSpam)
+        .await?;
+    alice
+        .add_msg_filter(FilterField::Text, "quiet", FilterAction::MuteNotification)
+        .await?;
+    alice
+        .add_msg_filter(FilterField::Sender, "^bob@", FilterAction::Archive)
+        .await?;
+
+    let bob_chat_id = bob.create_chat(alice).await.id;
+
+    // The first matching filter wins.
+    let sent = bob.send_text(bob_chat_id, "Buy now, it's cheap!").await;
+    alice.recv_msg_trash(&sent).await;
+    let sent = bob.send_text(bob_chat_id, "cheap pills").await;
+    alice.recv_msg_trash(&sent).await;
+
+    let sent = bob.send_text(bob_chat_id, "quiet please").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InNoticed);
+    let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+    assert_eq!(chat.get_visibility(), ChatVisibility::Normal);
+
+    let sent = bob.send_text(bob_chat_id, "Hello").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InNoticed);
+    let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+    assert_eq!(chat.get_visibility(), ChatVisibility::Archived);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_msg_filter_auto_submitted() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    alice
+        .add_msg_filter(
+            FilterField::AutoSubmitted,
+            "^auto-",
+            FilterAction::MuteNotification,
+        )
+        .await?;
+    let bob_chat_id = bob.create_chat(alice).await.id;
+
+    let sent = bob.send_text(bob_chat_id, "Hello").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InFresh);
+
+    let mut msg = Message::new_text("Out of office".to_string());
+    msg.set_bot(true);
+    let sent = bob.send_msg(bob_chat_id, &mut msg).await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InNoticed);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_msg_filter_cache() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let bob_chat_id = bob.create_chat(alice).await.id;
+
+    let sent = bob.send_text(bob_chat_id, "quiet please").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InFresh);
+
+    // Adding a filter invalidates the cache.
+    let id = alice
+        .add_msg_filter(FilterField::Text, "quiet", FilterAction::MuteNotification)
+        .await?;
+    let sent = bob.send_text(bob_chat_id, "quiet please").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InNoticed);
+
+    // So does deleting it.
+    alice.delete_msg_filter(id).await?;
+    let sent = bob.send_text(bob_chat_id, "quiet please").await;
+    let msg = alice.recv_msg(&sent).await;
+    assert_eq!(msg.state, MessageState::InFresh);
+
+    Ok(())
+}
@@ -0,0 +1,293 @@
+//! # Sharing group history with new members.
+//!
+//! If enabled for a group with [`chat::set_share_history()`],
+//! the device handling a QR code invitation to the group
+//! sends recent messages of the group to the new member,
+//! encrypted to the new member only.
+//! The new member adds them to the group as historical messages
+//! with their original timestamps.
+//!
+//! Only the text of messages is shared, attachments are not.
+//! Original signatures cannot be verified by the new member,
+//! so shared messages are marked, see [`Message::is_from_shared_history()`].
+//! The new member accepts the history only once per join,
+//! only from the member who added it and only shortly after joining.
+//! Messages are only attributed to existing members of the group,
+//! no contacts are created for their senders.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+
+use anyhow::{Context as _, Result, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, Chat, ChatId, ChatIdBlocked, get_chat_id_by_grpid, is_contact_in_chat};
+use crate::chatlist_events;
+use crate::constants::{Blocked, Chattype};
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::download::DownloadState;
+use crate::message::{Message, MessageState, Viewtype, rfc724_mid_exists};
+use crate::mimeparser::SystemMessage;
+use crate::param::{Param, Params};
+use crate::tools::{normalize_text, time};
+
+/// Maximum number of messages shared with a new member.
+pub(crate) const SHARED_HISTORY_LEN: usize = 50;
+
+/// Maximum time in seconds between joining the group and the shared history being sent.
+///
+/// The history is sent right after the member-added message.
+const MAX_HISTORY_DELAY: i64 = 10 * 60;
+
+/// Recent messages of a group, serialized into `group-history.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct GroupHistory {
+    /// Group ID of the group the messages belong to.
+    grpid: String,
+
+    /// Messages sorted from the oldest to the newest.
+    messages: Vec<HistoryItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryItem {
+    rfc724_mid: String,
+    from_addr: String,
+    timestamp: i64,
+    text: String,
+}
+
+/// Sends recent messages of the group `chat` to the new member `contact_id`
+/// if sharing history is enabled for the group.
+pub(crate) async fn share_history(
+    context: &Context,
+    chat: &Chat,
+    contact_id: ContactId,
+) -> Result<()> {
+    if chat.typ != Chattype::Group
+        || !chat.is_history_shared()
+        || !chat.is_encrypted(context).await?
+    {
+        return Ok(());
+    }
+
+    let rows = context
+        .sql
+        .query_map_vec(
+            "SELECT rfc724_mid, from_id, timestamp_sent, txt, param FROM msgs
+             WHERE chat_id=? AND hidden=0 AND type=? AND txt!='' AND download_state=?
+             AND (from_id=? OR from_id>?)
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?",
+            (
+                chat.id,
+                Viewtype::Text,
+                DownloadState::Done,
+                ContactId::SELF,
+                ContactId::LAST_SPECIAL,
+                SHARED_HISTORY_LEN,
+            ),
+            |row| {
+                let rfc724_mid: String = row.get(0)?;
+                let from_id: ContactId = row.get(1)?;
+                let timestamp: i64 = row.get(2)?;
+                let text: String = row.get(3)?;
+                let param: String = row.get(4)?;
+                Ok((rfc724_mid, from_id, timestamp, text, param))
+            },
+        )
+        .await?;
+
+    let mut senders = BTreeMap::new();
+    let mut messages = Vec::new();
+    for (rfc724_mid, from_id, timestamp, text, param) in rows.into_iter().rev() {
+        let param: Params = param.parse().unwrap_or_default();
+        if param.get_cmd() != SystemMessage::Unknown {
+            continue;
+        }
+        let sender = match senders.entry(from_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Contact::get_by_id(context, from_id).await?),
+        };
+        messages.push(HistoryItem {
+            rfc724_mid,
+            from_addr: sender.get_addr().to_string(),
+            timestamp,
+            text,
+        });
+    }
+    if messages.is_empty() {
+        return Ok(());
+    }
+    info!(
+        context,
+        "Sharing {} messages of {} with {contact_id}.",
+        messages.len(),
+        chat.id
+    );
+    let history = GroupHistory {
+        grpid: chat.grpid.clone(),
+        messages,
+    };
+
+    let mut msg = Message {
+        viewtype: Viewtype::Text,
+        text: "Recent messages of the group you joined.".to_string(),
+        hidden: true,
+        ..Default::default()
+    };
+    msg.param.set_cmd(SystemMessage::GroupHistory);
+    msg.param.set(Param::Arg, serde_json::to_string(&history)?);
+    msg.param.set_int(Param::GuaranteeE2ee, 1);
+    let chat_id = ChatIdBlocked::get_for_contact(context, contact_id, Blocked::Yes)
+        .await?
+        .id;
+    chat::send_msg(context, chat_id, &mut msg).await?;
+    Ok(())
+}
+
+/// Adds the messages of a group history shared by `from_id` to the group.
+///
+/// The history is only accepted from the member who added us,
+/// if it was sent shortly after we joined
+/// and no history was received for this join yet.
+/// Only messages sent before we joined the group by its members are added,
+/// messages which exist already are skipped.
+pub(crate) async fn receive_history(
+    context: &Context,
+    from_id: ContactId,
+    timestamp_sent: i64,
+    json: &str,
+) -> Result<()> {
+    let history: GroupHistory =
+        serde_json::from_str(json).context("Failed to parse group history")?;
+    let (chat_id, _blocked) = get_chat_id_by_grpid(context, &history.grpid)
+        .await?
+        .with_context(|| format!("No group for grpid {:?}", history.grpid))?;
+    let mut chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(chat.typ == Chattype::Group, "{chat_id} is not a group");
+    ensure!(
+        is_contact_in_chat(context, chat_id, from_id).await?,
+        "{from_id} is not a member of {chat_id}"
+    );
+    let join_timestamp = chat_id
+        .join_timestamp(context)
+        .await?
+        .with_context(|| format!("We are not a member of {chat_id}"))?;
+    ensure!(
+        timestamp_sent.abs_diff(join_timestamp) <= MAX_HISTORY_DELAY.unsigned_abs(),
+        "History of {chat_id} was not sent when we joined"
+    );
+    ensure!(
+        get_added_by(context, chat_id).await? == Some(from_id),
+        "{from_id} did not add us to {chat_id}"
+    );
+    ensure!(
+        chat.param.get_i64(Param::HistoryReceived) != Some(join_timestamp),
+        "History of {chat_id} was received already"
+    );
+    chat.param.set_i64(Param::HistoryReceived, join_timestamp);
+    chat.update_param(context).await?;
+
+    let mut added: usize = 0;
+    for item in history.messages.iter().take(SHARED_HISTORY_LEN) {
+        if item.timestamp > join_timestamp
+            || rfc724_mid_exists(context, &item.rfc724_mid)
+                .await?
+                .is_some()
+        {
+            continue;
+        }
+        let Some(sender_id) = lookup_member(context, chat_id, &item.from_addr).await? else {
+            info!(
+                context,
+                "Skipping shared message {:?}, sender is not a member.", item.rfc724_mid
+            );
+            continue;
+        };
+        let mut param = Params::new();
+        param.set_int(Param::FromSharedHistory, 1);
+        context
+            .sql
+            .insert(
+                "INSERT INTO msgs (chat_id, from_id, to_id, timestamp, timestamp_sent, timestamp_rcvd,
+                                   type, state, txt, txt_normalized, rfc724_mid, param)
+                 VALUES (?,?,?,?,?,?,?,?,?,?,?,?)",
+                (
+                    chat_id,
+                    sender_id,
+                    ContactId::SELF,
+                    item.timestamp,
+                    item.timestamp,
+                    time(),
+                    Viewtype::Text,
+                    MessageState::InSeen,
+                    &item.text,
+                    normalize_text(&item.text),
+                    &item.rfc724_mid,
+                    param.to_string(),
+                ),
+            )
+            .await?;
+        added = added.saturating_add(1);
+    }
+    info!(
+        context,
+        "Added {added} messages shared by {from_id} to {chat_id}."
+    );
+    if added > 0 {
+        context.emit_msgs_changed_without_msg_id(chat_id);
+        chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    }
+    Ok(())
+}
+
+/// Returns the member who added us to the group most recently.
+async fn get_added_by(context: &Context, chat_id: ChatId) -> Result<Option<ContactId>> {
+    let rows = context
+        .sql
+        .query_map_vec(
+            "SELECT from_id, param FROM msgs
+             WHERE chat_id=? AND from_id>?
+             ORDER BY timestamp DESC, id DESC",
+            (chat_id, ContactId::LAST_SPECIAL),
+            |row| {
+                let from_id: ContactId = row.get(0)?;
+                let param: String = row.get(1)?;
+                Ok((from_id, param))
+            },
+        )
+        .await?;
+    for (from_id, param) in rows {
+        let param: Params = param.parse().unwrap_or_default();
+        if param.get_cmd() == SystemMessage::MemberAddedToGroup
+            && param.get_int(Param::ContactAddedRemoved) == Some(ContactId::SELF.to_u32() as i32)
+        {
+            return Ok(Some(from_id));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the member of the group with the address `addr`, excluding ourselves.
+async fn lookup_member(
+    context: &Context,
+    chat_id: ChatId,
+    addr: &str,
+) -> Result<Option<ContactId>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT c.id FROM contacts c
+             INNER JOIN chats_contacts cc ON cc.contact_id=c.id
+             WHERE cc.chat_id=? AND c.id>? AND c.addr=? COLLATE NOCASE
+             ORDER BY cc.add_timestamp DESC
+             LIMIT 1",
+            (chat_id, ContactId::LAST_SPECIAL, addr),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod group_history_tests;
@@ -0,0 +1,136 @@
+use super::*;
+use crate::chat::{ChatItem, get_chat_msgs, set_share_history};
+use crate::contact::Origin;
+use crate::securejoin::get_securejoin_qr;
+use crate::test_utils::TestContextManager;
+
+async fn shared_texts(t: &Context, chat_id: chat::ChatId) -> Result<Vec<(String, i64)>> {
+    let mut texts = Vec::new();
+    for item in get_chat_msgs(t, chat_id).await? {
+        if let ChatItem::Message { msg_id } = item {
+            let msg = Message::load_from_db(t, msg_id).await?;
+            if msg.is_from_shared_history() {
+                texts.push((msg.get_text(), msg.get_timestamp()));
+            }
+        }
+    }
+    Ok(texts)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_share_history() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let alice_chat_id = alice.create_group_with_members("Group", &[bob]).await;
+    let sent = alice.send_text(alice_chat_id, "Hello Bob").await;
+    let bob_chat_id = bob.recv_msg(&sent).await.chat_id;
+    bob_chat_id.accept(bob).await?;
+    let hello_timestamp = alice.get_last_msg_in(alice_chat_id).await.get_timestamp();
+    let sent = bob.send_text(bob_chat_id, "Hi Alice").await;
+    alice.recv_msg(&sent).await;
+    set_share_history(alice, alice_chat_id, true).await?;
+
+    let qr = get_securejoin_qr(alice, Some(alice_chat_id)).await?;
+    let fiona_chat_id = tcm.exec_securejoin_qr(fiona, alice, &qr).await;
+    while let Some(sent) = alice.pop_sent_msg_opt().await {
+        fiona.recv_msg_opt(&sent).await;
+    }
+
+    let texts = shared_texts(fiona, fiona_chat_id).await?;
+    assert_eq!(texts.len(), 2);
+    assert_eq!(texts[0], ("Hello Bob".to_string(), hello_timestamp));
+    assert_eq!(texts[1].0, "Hi Alice");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_share_history_disabled() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let alice_chat_id = alice.create_group_with_members("Group", &[bob]).await;
+    alice.send_text(alice_chat_id, "Hello Bob").await;
+
+    let qr = get_securejoin_qr(alice, Some(alice_chat_id)).await?;
+    let fiona_chat_id = tcm.exec_securejoin_qr(fiona, alice, &qr).await;
+    while let Some(sent) = alice.pop_sent_msg_opt().await {
+        fiona.recv_msg_opt(&sent).await;
+    }
+    assert!(shared_texts(fiona, fiona_chat_id).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_receive_history_checks() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let alice_chat_id = alice.create_group_with_members("Group", &[bob]).await;
+    let qr = get_securejoin_qr(alice, Some(alice_chat_id)).await?;
+    let fiona_chat_id = tcm.exec_securejoin_qr(fiona, alice, &qr).await;
+    while let Some(sent) = alice.pop_sent_msg_opt().await {
+        fiona.recv_msg_opt(&sent).await;
+    }
+    let grpid = Chat::load_from_db(fiona, fiona_chat_id).await?.grpid;
+    let join_timestamp = fiona_chat_id.join_timestamp(fiona).await?.unwrap();
+    let fiona_alice_id = fiona.add_or_lookup_contact_id(alice).await;
+    let fiona_bob_id = fiona.add_or_lookup_contact_id(bob).await;
+    let json = serde_json::to_string(&GroupHistory {
+        grpid,
+        messages: vec![
+            HistoryItem {
+                rfc724_mid: "1@example.org".to_string(),
+                from_addr: "alice@example.org".to_string(),
+                timestamp: join_timestamp - 100,
+                text: "Hello".to_string(),
+            },
+            HistoryItem {
+                rfc724_mid: "2@example.net".to_string(),
+                from_addr: "mallory@example.net".to_string(),
+                timestamp: join_timestamp - 50,
+                text: "Not a member".to_string(),
+            },
+        ],
+    })?;
+
+    // Only the member who added us can share the history.
+    assert!(
+        receive_history(fiona, fiona_bob_id, join_timestamp, &json)
+            .await
+            .is_err()
+    );
+    // The history must be sent when we joined.
+    assert!(
+        receive_history(fiona, fiona_alice_id, join_timestamp + 3600, &json)
+            .await
+            .is_err()
+    );
+
+    receive_history(fiona, fiona_alice_id, join_timestamp, &json).await?;
+    let texts = shared_texts(fiona, fiona_chat_id).await?;
+    assert_eq!(texts, vec![("Hello".to_string(), join_timestamp - 100)]);
+    let msg_id = rfc724_mid_exists(fiona, "1@example.org").await?.unwrap();
+    let msg = Message::load_from_db(fiona, msg_id).await?;
+    assert_eq!(msg.from_id, fiona_alice_id);
+    assert!(!msg.get_showpadlock());
+    assert!(
+        Contact::lookup_id_by_addr(fiona, "mallory@example.net", Origin::Unknown)
+            .await?
+            .is_none()
+    );
+
+    // The history is only accepted once.
+    assert!(
+        receive_history(fiona, fiona_alice_id, join_timestamp, &json)
+            .await
+            .is_err()
+    );
+    Ok(())
+}
@@ -44,6 +44,9 @@ pub enum HeaderDef {
 
     /// List-Help header defined in [RFC 2369](https://datatracker.ietf.org/doc/html/rfc2369).
     ListHelp,
+
+    /// List-Owner header defined in [RFC 2369](https://datatracker.ietf.org/doc/html/rfc2369).
+    ListOwner,
     References,
 
     /// In-Reply-To header containing Message-ID of the parent message.
@@ -55,6 +58,10 @@ pub enum HeaderDef {
 
     ContentType,
     ContentId,
+
+    /// Description of a MIME part as of [RFC 2045](https://tools.ietf.org/html/rfc2045),
+    /// used as alternative text of attachments.
+    ContentDescription,
     ChatVersion,
     ChatGroupId,
     ChatGroupName,
@@ -113,10 +120,35 @@ pub enum HeaderDef {
     /// referencing the Post-Message's rfc724_mid.
     ChatPostMessageId,
 
-    /// Announces Post-Message metadata in a Pre-Message.
+    /// Announces Post-Message metadata in a Pre-Message
+    /// or metadata of an uploaded attachment.
     /// Contains a serialized `PostMsgMetadata` struct.
     ChatPostMessageMetadata,
 
+    /// URL of an attachment uploaded instead of being attached,
+    /// see `Config::MaxAttachmentBytes`.
+    ChatUploadUrl,
+
+    /// Secret the attachment at `Chat-Upload-Url` is encrypted with.
+    /// Only sent in encrypted messages.
+    ChatUploadKey,
+
+    /// BLAKE3 hash of an attachment offered over iroh instead of being attached,
+    /// see `Config::P2pAttachmentBytes`.
+    ChatP2pHash,
+
+    /// Iroh node address the attachment with `Chat-P2p-Hash` is offered at.
+    ChatP2pNodeAddr,
+
+    /// Index and count of a text part, `<index>/<count>`,
+    /// for texts split into several messages because of their size.
+    /// Followed by `; newline` if the part ends at a line break.
+    ChatSplitPart,
+
+    /// Message-ID of the first part of a split text.
+    /// Sent in all other parts.
+    ChatSplitOf,
+
     /// This message is preceded by a Pre-Message
     /// and thus this message can be skipped while fetching messages.
     /// This is an unprotected header.
@@ -6,9 +6,10 @@
 use std::{
     cmp::max,
     cmp::min,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     iter::Peekable,
     mem::take,
+    ops::RangeInclusive,
     sync::atomic::Ordering,
     time::{Duration, UNIX_EPOCH},
 };
@@ -26,21 +27,24 @@
 use crate::constants::{Blocked, DC_VERSION_STR};
 use crate::contact::ContactId;
 use crate::context::Context;
+use crate::download;
 use crate::ensure_and_debug_assert;
-use crate::events::EventType;
+use crate::events::{ErrorCode, EventType};
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::log::{LogExt, warn};
-use crate::message::{self, Message};
+use crate::message::{self, Message, rfc724_mid_exists};
 use crate::mimeparser;
+use crate::net::NetworkProfile;
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
+use crate::param::Param;
 use crate::push::encrypt_device_token;
 use crate::receive_imf::{
     ReceivedMsg, from_field_to_contact_id, get_prefetch_parent_message, receive_imf_inner,
 };
 use crate::scheduler::connectivity::ConnectivityStore;
 use crate::stock_str;
-use crate::tools::{self, create_id, duration_to_str, time};
+use crate::tools::{self, create_id, duration_to_str, time, time_elapsed};
 use crate::transport::{
     ConfiguredLoginParam, ConfiguredServerLoginParam, prioritize_server_login_params,
 };
@@ -51,11 +55,15 @@
 
 pub(crate) mod capabilities;
 mod client;
+mod gmail;
 mod idle;
+mod namespace;
 pub mod select_folder;
 pub(crate) mod session;
+pub(crate) mod utf7;
 
 use client::{Client, determine_capabilities};
+use namespace::Namespace;
 use session::Session;
 
 pub(crate) const GENERATED_PREFIX: &str = "GEN_";
@@ -66,6 +74,9 @@
                              )])";
 const BODY_FULL: &str = "(FLAGS BODY.PEEK[])";
 
+/// How often the watched folder is checked for messages expunged by the server.
+const EXPUNGE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Debug)]
 pub(crate) struct Imap {
     /// ID of the transport configuration in the `transports` table.
@@ -110,6 +121,9 @@ pub(crate) struct Imap {
 
     /// IMAP UID resync request receiver.
     pub(crate) resync_request_receiver: async_channel::Receiver<()>,
+
+    /// Last time the watched folder was checked for messages expunged by the server.
+    expunge_check_last: tools::Time,
 }
 
 #[derive(Debug, Default)]
@@ -143,6 +157,7 @@ pub enum FolderMeaning {
     /// Spam folder.
     Spam,
     Inbox,
+    Sent,
     Trash,
 
     /// Virtual folders.
@@ -250,6 +265,7 @@ pub async fn new(
             ratelimit: Ratelimit::new(Duration::new(120, 0), 2.0),
             resync_request_sender,
             resync_request_receiver,
+            expunge_check_last: UNIX_EPOCH,
         })
     }
 
@@ -321,6 +337,10 @@ pub(crate) async fn connect(
         ));
         self.conn_backoff_ms = max(BACKOFF_MIN_MS, self.conn_backoff_ms);
 
+        if context.get_network_profile().await == NetworkProfile::Offline {
+            bail!("Not connecting to IMAP server, network profile is offline.");
+        }
+
         let login_params = prioritize_server_login_params(&context.sql, &self.lp, "imap").await?;
         let mut first_error = None;
         'candidate: for lp in login_params {
@@ -427,6 +447,7 @@ pub(crate) async fn connect(
                             && !configuring
                             && context.get_config_bool(Config::NotifyAboutWrongPw).await?
                         {
+                            error!(context, code = ErrorCode::AuthFailed, "{message}");
                             let mut msg = Message::new_text(message);
                             if let Err(e) = chat::add_device_msg_with_importance(
                                 context,
@@ -502,12 +523,29 @@ pub async fn fetch_move_delete(
             context.scheduler.interrupt_ephemeral_task().await;
         }
 
+        if time_elapsed(&self.expunge_check_last) >= EXPUNGE_CHECK_INTERVAL
+            && session.selected_folder.as_deref() == Some(watch_folder)
+        {
+            self.expunge_check_last = tools::Time::now();
+            if let Err(err) = session.detect_expunged(context, watch_folder).await {
+                warn!(context, "Failed to detect expunged messages: {err:#}.");
+            }
+        }
+
         // Mark expired messages for deletion. Note that `delete_expired_imap_messages` is
         // not well optimized and should not be called before fetching.
         delete_expired_imap_messages(context, session.transport_id(), session.is_chatmail())
             .await
             .context("delete_expired_imap_messages")?;
 
+        session
+            .plan_spam_moves(context)
+            .await
+            .context("plan_spam_moves")?;
+        session
+            .append_sent_messages(context)
+            .await
+            .context("append_sent_messages")?;
         session
             .move_delete_messages(context, watch_folder)
             .await
@@ -575,7 +613,10 @@ async fn fetch_new_msg_batch(
             "fetch_new_msg_batch({folder}): UIDVALIDITY={uid_validity}, UIDNEXT={old_uid_next}."
         );
 
-        let uids_to_prefetch = 500;
+        let uids_to_prefetch = match context.get_network_profile().await {
+            NetworkProfile::Metered => 100,
+            NetworkProfile::Wifi | NetworkProfile::Offline => 500,
+        };
         let msgs = session
             .prefetch(old_uid_next, uids_to_prefetch)
             .await
@@ -586,13 +627,11 @@ async fn fetch_new_msg_batch(
         let mut uids_fetch: Vec<u32> = Vec::new();
         let mut available_post_msgs: Vec<String> = Vec::new();
         let mut download_later: Vec<String> = Vec::new();
+        let mut download_partially: Vec<(u32, String)> = Vec::new();
         let mut uid_message_ids = BTreeMap::new();
         let mut largest_uid_skipped = None;
 
-        let download_limit: Option<u32> = context
-            .get_config_parsed(Config::DownloadLimit)
-            .await?
-            .filter(|&l| 0 < l);
+        let download_limit = download::download_limit(context).await?;
 
         // Store the info about IMAP messages in the database.
         for (uid, ref fetch_response) in msgs {
@@ -604,7 +643,13 @@ async fn fetch_new_msg_batch(
                 }
             };
 
-            let message_id = prefetch_get_message_id(&headers);
+            // Gmail messages without Message-ID get an ID derived from X-GM-MSGID
+            // so that the same message in several label folders is recognized.
+            let message_id = prefetch_get_message_id(&headers).or_else(|| {
+                fetch_response
+                    .gmail_msg_id()
+                    .map(|gm_msgid| gmail::generated_message_id(*gm_msgid))
+            });
             let size = fetch_response
                 .size
                 .context("imap fetch response does not contain size")?;
@@ -656,6 +701,11 @@ async fn fetch_new_msg_batch(
                 )
                 .await?;
 
+            if let Some(gm_msgid) = fetch_response.gmail_msg_id() {
+                let labels = fetch_response.gmail_labels().cloned().unwrap_or_default();
+                gmail::store_labels(context, transport_id, &message_id, *gm_msgid, &labels).await?;
+            }
+
             // Download only the messages which have reached their target folder if there are
             // multiple devices. This prevents race conditions in multidevice case, where one
             // device tries to download the message while another device moves the message at the
@@ -693,7 +743,8 @@ async fn fetch_new_msg_batch(
                         uids_fetch.push(uid);
                         uid_message_ids.insert(uid, message_id);
                     } else {
-                        download_later.push(message_id.clone());
+                        // Try to download only the text parts after all the small messages.
+                        download_partially.push((uid, message_id));
                         largest_uid_skipped = Some(uid);
                     }
                 };
@@ -730,8 +781,7 @@ async fn fetch_new_msg_batch(
         };
 
         let actually_download_messages_future = async {
-            session
-                .fetch_many_msgs(context, folder, uids_fetch, &uid_message_ids, sender)
+            Box::pin(session.fetch_many_msgs(context, folder, uids_fetch, &uid_message_ids, sender))
                 .await
                 .context("fetch_many_msgs")
         };
@@ -769,6 +819,22 @@ async fn fetch_new_msg_batch(
         chat::mark_old_messages_as_noticed(context, received_msgs).await?;
 
         if fetch_res.is_ok() {
+            for (uid, rfc724_mid) in download_partially {
+                match session
+                    .fetch_msg_text_parts(context, uid, &rfc724_mid)
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => download_later.push(rfc724_mid),
+                    Err(err) => {
+                        warn!(
+                            context,
+                            "Failed to download text parts of {rfc724_mid}: {err:#}."
+                        );
+                        download_later.push(rfc724_mid);
+                    }
+                }
+            }
             info!(
                 context,
                 "available_post_msgs: {}, download_later: {}.",
@@ -807,13 +873,19 @@ pub(crate) async fn resync_folders(&mut self, context: &Context) -> Result<()> {
             .list_folders()
             .await
             .context("listing folders for resync")?;
+        let namespace = Namespace::from_folders(
+            all_folders
+                .iter()
+                .map(|folder| (folder.name(), folder.delimiter())),
+        );
         for folder in all_folders {
-            let folder_meaning = get_folder_meaning(&folder);
+            let folder_meaning = get_folder_meaning(&folder, &namespace);
             if !matches!(
                 folder_meaning,
-                FolderMeaning::Virtual | FolderMeaning::Unknown
+                FolderMeaning::Virtual | FolderMeaning::Unknown | FolderMeaning::Sent
             ) {
-                self.resync_folder_uids(context, folder.name(), folder_meaning)
+                let folder_name = utf7::from_server_name(folder.name());
+                self.resync_folder_uids(context, &folder_name, folder_meaning)
                     .await?;
             }
         }
@@ -899,6 +971,46 @@ pub(crate) async fn resync_folder_uids(
         Ok(())
     }
 
+    /// Detects messages expunged from the selected folder without our involvement,
+    /// e.g. by retention policy of the server, by the administrator or by another client.
+    ///
+    /// The server is not required to support QRESYNC,
+    /// a single `UID SEARCH` over the range of UIDs
+    /// stored in the `imap` table is used instead.
+    pub(crate) async fn detect_expunged(&mut self, context: &Context, folder: &str) -> Result<()> {
+        let transport_id = self.transport_id();
+        let uid_validity = get_uidvalidity(context, transport_id, folder).await?;
+        let (min_uid, max_uid) = context
+            .sql
+            .query_row(
+                "SELECT MIN(uid), MAX(uid) FROM imap
+                 WHERE transport_id=? AND folder=? AND uidvalidity=?",
+                (transport_id, folder, uid_validity),
+                |row| {
+                    let min_uid: Option<u32> = row.get(0)?;
+                    let max_uid: Option<u32> = row.get(1)?;
+                    Ok((min_uid, max_uid))
+                },
+            )
+            .await?;
+        let (Some(min_uid), Some(max_uid)) = (min_uid, max_uid) else {
+            return Ok(());
+        };
+        let server_uids = self
+            .uid_search(format!("UID {min_uid}:{max_uid}"))
+            .await
+            .with_context(|| format!("UID SEARCH in folder {folder:?} failed"))?;
+        remove_expunged(
+            context,
+            transport_id,
+            folder,
+            uid_validity,
+            min_uid..=max_uid,
+            &server_uids,
+        )
+        .await
+    }
+
     /// Deletes batch of messages identified by their UID from the currently
     /// selected folder.
     async fn delete_message_batch(
@@ -938,7 +1050,7 @@ async fn move_message_batch(
         target: &str,
     ) -> Result<()> {
         if self.can_move() {
-            match self.uid_mv(set, &target).await {
+            match self.uid_mv(set, utf7::to_server_name(target)).await {
                 Ok(()) => {
                     // Messages are moved or don't exist, IMAP returns OK response in both cases.
                     context
@@ -975,7 +1087,7 @@ async fn move_message_batch(
             context,
             "Server does not support MOVE, fallback to COPY/DELETE {} to {}", set, target
         );
-        self.uid_copy(&set, &target).await?;
+        self.uid_copy(&set, utf7::to_server_name(target)).await?;
         context
             .sql
             .transaction(|transaction| {
@@ -996,7 +1108,11 @@ async fn move_message_batch(
     /// Moves and deletes messages as planned in the `imap` table.
     ///
     /// This is the only place where messages are moved or deleted on the IMAP server.
-    async fn move_delete_messages(&mut self, context: &Context, folder: &str) -> Result<()> {
+    pub(crate) async fn move_delete_messages(
+        &mut self,
+        context: &Context,
+        folder: &str,
+    ) -> Result<()> {
         let transport_id = self.transport_id();
         let rows = context
             .sql
@@ -1047,6 +1163,135 @@ async fn move_delete_messages(&mut self, context: &Context, folder: &str) -> Res
         Ok(())
     }
 
+    /// Sets the Spam folder as the target of messages in `imap_spam` table.
+    ///
+    /// The messages are then moved by [`Session::move_delete_messages`].
+    /// If the server has no Spam folder, the messages are left in place.
+    async fn plan_spam_moves(&mut self, context: &Context) -> Result<()> {
+        let transport_id = self.transport_id();
+        if !context
+            .sql
+            .exists(
+                "SELECT COUNT(*) FROM imap, imap_spam
+                 WHERE imap.id=imap_spam.id AND imap.transport_id=?",
+                (transport_id,),
+            )
+            .await?
+        {
+            return Ok(());
+        }
+
+        let all_folders = self
+            .list_folders()
+            .await
+            .context("listing folders to find Spam folder")?;
+        let namespace = Namespace::from_folders(
+            all_folders
+                .iter()
+                .map(|folder| (folder.name(), folder.delimiter())),
+        );
+        let spam_folder = all_folders
+            .iter()
+            .find(|folder| get_folder_meaning(folder, &namespace) == FolderMeaning::Spam)
+            .map(|folder| utf7::from_server_name(folder.name()));
+        if let Some(spam_folder) = &spam_folder {
+            info!(
+                context,
+                "Transport {transport_id}: Moving filtered messages to {spam_folder}."
+            );
+        } else {
+            warn!(
+                context,
+                "Transport {transport_id}: No Spam folder, leaving filtered messages in place."
+            );
+        }
+
+        context
+            .sql
+            .transaction(move |transaction| {
+                if let Some(spam_folder) = spam_folder {
+                    transaction.execute(
+                        "UPDATE imap SET target=?
+                         WHERE transport_id=? AND target=folder
+                         AND id IN (SELECT id FROM imap_spam)",
+                        (spam_folder, transport_id),
+                    )?;
+                }
+                transaction.execute(
+                    "DELETE FROM imap_spam
+                     WHERE id IN (SELECT id FROM imap WHERE transport_id=?)
+                     OR id NOT IN (SELECT id FROM imap)",
+                    (transport_id,),
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Appends copies of sent messages from the `imap_append` table to the Sent folder,
+    /// see [`Config::SentFolderStrategy`].
+    ///
+    /// If the server has no Sent folder, the copies are dropped.
+    async fn append_sent_messages(&mut self, context: &Context) -> Result<()> {
+        let transport_id = self.transport_id();
+        let rows = context
+            .sql
+            .query_map_vec(
+                "SELECT a.id, a.mime FROM imap_append a
+                 INNER JOIN transports t ON t.addr=a.addr
+                 WHERE t.id=?
+                 ORDER BY a.id",
+                (transport_id,),
+                |row| {
+                    let id: i64 = row.get(0)?;
+                    let mime: String = row.get(1)?;
+                    Ok((id, mime))
+                },
+            )
+            .await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let all_folders = self
+            .list_folders()
+            .await
+            .context("listing folders to find Sent folder")?;
+        let namespace = Namespace::from_folders(
+            all_folders
+                .iter()
+                .map(|folder| (folder.name(), folder.delimiter())),
+        );
+        let sent_folder = all_folders
+            .iter()
+            .find(|folder| get_folder_meaning(folder, &namespace) == FolderMeaning::Sent)
+            .map(|folder| utf7::from_server_name(folder.name()));
+
+        for (id, mime) in rows {
+            if let Some(sent_folder) = &sent_folder {
+                self.append(
+                    utf7::to_server_name(sent_folder),
+                    Some(r"(\Seen)"),
+                    None,
+                    &mime,
+                )
+                .await
+                .with_context(|| format!("Failed to append sent message to {sent_folder}"))?;
+            } else {
+                warn!(
+                    context,
+                    "Transport {transport_id}: No Sent folder, dropping copy of sent message."
+                );
+            }
+            context
+                .sql
+                .execute("DELETE FROM imap_append WHERE id=?", (id,))
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Stores pending `\Seen` flags for messages in `imap_markseen` table.
     pub(crate) async fn store_seen_flags_on_imap(&mut self, context: &Context) -> Result<()> {
         if context.get_config_bool(Config::TeamProfile).await? {
@@ -1254,6 +1499,7 @@ pub(crate) async fn fetch_many_msgs(
                     context,
                     "Passing message UID {} to receive_imf().", request_uid
                 );
+                context.set_last_activity();
                 let res = receive_imf_inner(context, rfc724_mid, body, is_seen).await;
                 crate::sql::update_transport_last_rcvd_timestamp(context, transport_id)
                     .await
@@ -1452,7 +1698,7 @@ pub(crate) async fn register_token(&mut self, context: &Context) -> Result<()> {
 
         let transport_id = self.transport_id();
 
-        let Some(device_token) = context.push_subscriber.device_token().await else {
+        let Some(device_token) = context.push_token().await? else {
             return Ok(());
         };
 
@@ -1525,7 +1771,11 @@ pub(crate) async fn register_token(&mut self, context: &Context) -> Result<()> {
 
                 context.push_subscribed.store(true, Ordering::Relaxed);
             }
-        } else if !context.push_subscriber.heartbeat_subscribed().await {
+        } else if !context
+            .push_subscriber
+            .heartbeat_subscribed(context.id)
+            .await
+        {
             let context = context.clone();
             // Subscribe for heartbeat notifications.
             tokio::spawn(async move { context.push_subscriber.subscribe(&context).await });
@@ -1535,7 +1785,148 @@ pub(crate) async fn register_token(&mut self, context: &Context) -> Result<()> {
     }
 }
 
+impl Session {
+    /// Fetches a single message without storing it in the database.
+    ///
+    /// The folder is opened read-only with EXAMINE,
+    /// so the message is not marked as seen
+    /// and is still fetched normally later.
+    ///
+    /// Returns `None` if there is no such message
+    /// or it is marked for deletion.
+    pub(crate) async fn peek_single_msg(
+        &mut self,
+        context: &Context,
+        folder: &str,
+        uid: u32,
+    ) -> Result<Option<Vec<u8>>> {
+        // EXAMINE changes the selected folder,
+        // forget the previous one even if the command fails.
+        self.selected_folder = None;
+        self.selected_mailbox = None;
+        self.examine(utf7::to_server_name(folder))
+            .await
+            .with_context(|| format!("Failed to examine folder {folder:?}"))?;
+
+        info!(context, "Fetching single message {folder:?}/{uid}.");
+        let mut fetch_responses = self
+            .uid_fetch(uid.to_string(), BODY_FULL)
+            .await
+            .with_context(|| format!("Failed to fetch message {uid} from folder {folder:?}"))?;
+        let mut body = None;
+        while let Some(fetch_response) = fetch_responses
+            .try_next()
+            .await
+            .context("Failed to process IMAP FETCH result")?
+        {
+            if fetch_response.uid == Some(uid)
+                && !fetch_response.flags().any(|flag| flag == Flag::Deleted)
+            {
+                body = fetch_response.body().map(|body| body.to_vec());
+            }
+        }
+        Ok(body)
+    }
+}
+
+/// Removes messages expunged by the server from the `imap` table.
+///
+/// `server_uids` are the UIDs which are still on the server in the `uid_range`.
+/// If [`Config::MarkExpungedMsgs`] is enabled,
+/// local messages not available in any server folder anymore
+/// are marked as removed from server.
+pub(crate) async fn remove_expunged(
+    context: &Context,
+    transport_id: u32,
+    folder: &str,
+    uid_validity: u32,
+    uid_range: RangeInclusive<u32>,
+    server_uids: &HashSet<u32>,
+) -> Result<()> {
+    let expunged: Vec<(i64, String)> = context
+        .sql
+        .query_map_vec(
+            "SELECT id, uid, rfc724_mid FROM imap
+             WHERE transport_id=? AND folder=? AND uidvalidity=? AND uid>=? AND uid<=?",
+            (
+                transport_id,
+                folder,
+                uid_validity,
+                uid_range.start(),
+                uid_range.end(),
+            ),
+            |row| {
+                let id: i64 = row.get(0)?;
+                let uid: u32 = row.get(1)?;
+                let rfc724_mid: String = row.get(2)?;
+                Ok((id, uid, rfc724_mid))
+            },
+        )
+        .await?
+        .into_iter()
+        .filter(|(_id, uid, _rfc724_mid)| !server_uids.contains(uid))
+        .map(|(id, _uid, rfc724_mid)| (id, rfc724_mid))
+        .collect();
+    if expunged.is_empty() {
+        return Ok(());
+    }
+    info!(
+        context,
+        "Transport {transport_id}: {} messages were expunged from {folder:?} by the server.",
+        expunged.len()
+    );
+
+    let rfc724_mids = context
+        .sql
+        .transaction(move |transaction| {
+            let mut stmt = transaction.prepare("DELETE FROM imap WHERE id=?")?;
+            let mut rfc724_mids = Vec::with_capacity(expunged.len());
+            for (id, rfc724_mid) in expunged {
+                stmt.execute((id,))?;
+                rfc724_mids.push(rfc724_mid);
+            }
+            Ok(rfc724_mids)
+        })
+        .await?;
+
+    if context.get_config_bool(Config::MarkExpungedMsgs).await? {
+        for rfc724_mid in rfc724_mids {
+            mark_removed_from_server(context, &rfc724_mid).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Marks the message as removed from server
+/// unless it is still available in another server folder.
+async fn mark_removed_from_server(context: &Context, rfc724_mid: &str) -> Result<()> {
+    if context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM imap WHERE rfc724_mid=?",
+            (rfc724_mid,),
+        )
+        .await?
+    {
+        return Ok(());
+    }
+    let Some(msg_id) = rfc724_mid_exists(context, rfc724_mid).await? else {
+        return Ok(());
+    };
+    let Some(mut msg) = Message::load_from_db_optional(context, msg_id).await? else {
+        return Ok(());
+    };
+    if msg.chat_id.is_trash() || msg.is_removed_from_server() {
+        return Ok(());
+    }
+    msg.param.set_int(Param::RemovedFromServer, 1);
+    msg.update_param(context).await?;
+    context.emit_msgs_changed(msg.chat_id, msg_id);
+    Ok(())
+}
+
 fn format_setmetadata(folder: &str, device_token: &str) -> String {
+    let folder = utf7::to_server_name(folder);
     let device_token_len = device_token.len();
     format!(
         "SETMETADATA \"{folder}\" (/private/devicetoken {{{device_token_len}+}}\r\n{device_token})"
@@ -1761,6 +2152,30 @@ fn get_folder_meaning_by_name(folder_name: &str) -> FolderMeaning {
         "迷惑メール",
         "스팸",
     ];
+    const SENT_NAMES: &[&str] = &[
+        "Sent",
+        "Sent Items",
+        "Sent Mail",
+        "Sent Messages",
+        "Enviados",
+        "Envoyés",
+        "Gesendet",
+        "Gesendete Elemente",
+        "Inviati",
+        "Itens Enviados",
+        "Verzonden",
+        "Wysłane",
+        "Odeslané",
+        "Skickat",
+        "Sendt",
+        "Отправленные",
+        "Надіслані",
+        "送信済み",
+        "送信済みメール",
+        "已发送",
+        "已發送",
+        "보낸편지함",
+    ];
     const TRASH_NAMES: &[&str] = &[
         "Trash",
         "Bin",
@@ -1781,12 +2196,17 @@ fn get_folder_meaning_by_name(folder_name: &str) -> FolderMeaning {
         "已删除邮件",
         "휴지통",
     ];
-    let lower = folder_name.to_lowercase();
+    // Localized names are usually encoded in modified UTF-7.
+    let lower = utf7::decode(folder_name)
+        .unwrap_or_else(|| folder_name.to_string())
+        .to_lowercase();
 
     if lower == "inbox" {
         FolderMeaning::Inbox
     } else if SPAM_NAMES.iter().any(|s| s.to_lowercase() == lower) {
         FolderMeaning::Spam
+    } else if SENT_NAMES.iter().any(|s| s.to_lowercase() == lower) {
+        FolderMeaning::Sent
     } else if TRASH_NAMES.iter().any(|s| s.to_lowercase() == lower) {
         FolderMeaning::Trash
     } else {
@@ -1798,6 +2218,7 @@ fn get_folder_meaning_by_attrs(folder_attrs: &[NameAttribute]) -> FolderMeaning
     for attr in folder_attrs {
         match attr {
             NameAttribute::Trash => return FolderMeaning::Trash,
+            NameAttribute::Sent => return FolderMeaning::Sent,
             NameAttribute::Junk => return FolderMeaning::Spam,
             NameAttribute::All | NameAttribute::Flagged => return FolderMeaning::Virtual,
             NameAttribute::Extension(label) => {
@@ -1813,9 +2234,11 @@ fn get_folder_meaning_by_attrs(folder_attrs: &[NameAttribute]) -> FolderMeaning
     FolderMeaning::Unknown
 }
 
-pub(crate) fn get_folder_meaning(folder: &Name) -> FolderMeaning {
+/// Returns the meaning of the folder,
+/// guessing from the name relative to the personal `namespace` if there are no attributes.
+pub(crate) fn get_folder_meaning(folder: &Name, namespace: &Namespace) -> FolderMeaning {
     match get_folder_meaning_by_attrs(folder.attributes()) {
-        FolderMeaning::Unknown => get_folder_meaning_by_name(folder.name()),
+        FolderMeaning::Unknown => get_folder_meaning_by_name(namespace.strip_prefix(folder.name())),
         meaning => meaning,
     }
 }
@@ -1906,13 +2329,33 @@ pub(crate) async fn prefetch_should_download(
 /// Schedule marking the message as Seen on IMAP by adding all known IMAP messages corresponding to
 /// the given Message-ID to `imap_markseen` table.
 pub(crate) async fn markseen_on_imap_table(context: &Context, message_id: &str) -> Result<()> {
+    markseen_many_on_imap_table(context, &[message_id]).await
+}
+
+/// Same as [`markseen_on_imap_table`], but for multiple Message-IDs.
+///
+/// The IMAP loop is interrupted only once after all messages are scheduled,
+/// so `\Seen` flags are stored with one `UID STORE` command per folder and UID ranges
+/// instead of one command per message.
+pub(crate) async fn markseen_many_on_imap_table(
+    context: &Context,
+    message_ids: &[&str],
+) -> Result<()> {
+    if message_ids.is_empty() {
+        return Ok(());
+    }
     context
         .sql
-        .execute(
-            "INSERT OR IGNORE INTO imap_markseen (id)
-             SELECT id FROM imap WHERE rfc724_mid=?",
-            (message_id,),
-        )
+        .transaction(|transaction| {
+            let mut stmt = transaction.prepare(
+                "INSERT OR IGNORE INTO imap_markseen (id)
+                 SELECT id FROM imap WHERE rfc724_mid=?",
+            )?;
+            for message_id in message_ids {
+                stmt.execute((message_id,))?;
+            }
+            Ok(())
+        })
         .await?;
     context.scheduler.interrupt_inbox().await;
 
@@ -1944,7 +2387,11 @@ pub(crate) async fn set_uid_next(
 /// This method returns the uid_next from the last time we fetched messages.
 /// We can compare this to the current uid_next to find out whether there are new messages
 /// and fetch from this value on to get all new messages.
-async fn get_uid_next(context: &Context, transport_id: u32, folder: &str) -> Result<u32> {
+pub(crate) async fn get_uid_next(
+    context: &Context,
+    transport_id: u32,
+    folder: &str,
+) -> Result<u32> {
     Ok(context
         .sql
         .query_get_value(
@@ -1972,7 +2419,7 @@ pub(crate) async fn set_uidvalidity(
     Ok(())
 }
 
-async fn get_uidvalidity(context: &Context, transport_id: u32, folder: &str) -> Result<u32> {
+pub(crate) async fn get_uidvalidity(context: &Context, transport_id: u32, folder: &str) -> Result<u32> {
     Ok(context
         .sql
         .query_get_value(
@@ -1983,11 +2430,19 @@ async fn get_uidvalidity(context: &Context, transport_id: u32, folder: &str) ->
         .unwrap_or(0))
 }
 
+/// Builds the `UID SEARCH` criteria matching messages
+/// with an internal date within the last `days` days.
+pub(crate) fn build_since_criteria(days: u32, now: i64) -> String {
+    let since = now.saturating_sub(i64::from(days).saturating_mul(24 * 60 * 60));
+    let date = chrono::DateTime::<chrono::Utc>::from_timestamp(since, 0).unwrap_or_default();
+    format!("SINCE {}", date.format("%d-%b-%Y"))
+}
+
 /// Builds a list of sequence/uid sets. The returned sets have each no more than around 1000
 /// characters because according to <https://tools.ietf.org/html/rfc2683#section-3.2.1.5>
 /// command lines should not be much more than 1000 chars (servers should allow at least 8000 chars)
 #[expect(clippy::arithmetic_side_effects)]
-fn build_sequence_sets(uids: &[u32]) -> Result<Vec<(Vec<u32>, String)>> {
+pub(crate) fn build_sequence_sets(uids: &[u32]) -> Result<Vec<(Vec<u32>, String)>> {
     // first, try to find consecutive ranges:
     let mut ranges: Vec<UidRange> = vec![];
 
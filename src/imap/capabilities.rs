@@ -25,6 +25,15 @@ pub(crate) struct Capabilities {
     /// <https://tools.ietf.org/html/rfc4978>
     pub can_compress: bool,
 
+    /// True if the server has LITERAL+ capability as defined in
+    /// <https://tools.ietf.org/html/rfc7888>
+    pub can_literal_plus: bool,
+
+    /// True if the server has LITERAL- capability as defined in
+    /// <https://tools.ietf.org/html/rfc7888>,
+    /// allowing non-synchronizing literals of up to 4096 bytes.
+    pub can_literal_minus: bool,
+
     /// True if the server supports XDELTAPUSH capability.
     /// This capability means setting /private/devicetoken IMAP METADATA
     /// on the INBOX results in new mail notifications
@@ -40,6 +49,11 @@ pub(crate) struct Capabilities {
     /// e.g. the ability to move messages to Delta Chat folder.
     pub is_chatmail: bool,
 
+    /// True if the server has X-GM-EXT-1 capability
+    /// indicating that it is a Gmail server supporting
+    /// <https://developers.google.com/workspace/gmail/imap/imap-extensions>
+    pub is_gmail: bool,
+
     /// Server ID if the server supports ID capability.
     pub server_id: Option<HashMap<String, String>>,
 }
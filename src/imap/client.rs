@@ -9,6 +9,7 @@
 use super::capabilities::Capabilities;
 use crate::context::Context;
 use crate::log::{LoggingStream, warn};
+use crate::net::bandwidth::{ConnectionType, MeteredStream};
 use crate::net::dns::{lookup_host_with_cache, update_connect_timestamp};
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
@@ -82,8 +83,11 @@ pub(crate) async fn identify_server(
         can_check_quota: caps.has_str("QUOTA"),
         can_metadata: caps.has_str("METADATA"),
         can_compress: caps.has_str("COMPRESS=DEFLATE"),
+        can_literal_plus: caps.has_str("LITERAL+"),
+        can_literal_minus: caps.has_str("LITERAL-"),
         can_push: caps.has_str("XDELTAPUSH"),
         is_chatmail: caps.has_str("XCHATMAIL"),
+        is_gmail: caps.has_str("X-GM-EXT-1"),
         server_id,
     };
     Ok(capabilities)
@@ -237,7 +241,11 @@ async fn connect_secure(
         )
         .await?;
         let buffered_stream = BufWriter::new(tls_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let mut client = Client::new(session_stream);
         let _greeting = client
             .read_response()
@@ -252,7 +260,11 @@ async fn connect_insecure(context: &Context, addr: SocketAddr) -> Result<Self> {
         let events = context.events.clone();
         let logging_stream = LoggingStream::new(tcp_stream, account_id, events)?;
         let buffered_stream = BufWriter::new(logging_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let mut client = Client::new(session_stream);
         let _greeting = client
             .read_response()
@@ -302,7 +314,11 @@ async fn connect_starttls(
         .await
         .context("STARTTLS upgrade failed")?;
         let buffered_stream = BufWriter::new(tls_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let client = Client::new(session_stream);
         Ok(client)
     }
@@ -331,7 +347,11 @@ async fn connect_secure_proxy(
         )
         .await?;
         let buffered_stream = BufWriter::new(tls_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let mut client = Client::new(session_stream);
         let _greeting = client
             .read_response()
@@ -348,7 +368,11 @@ async fn connect_insecure_proxy(
     ) -> Result<Self> {
         let proxy_stream = proxy_config.connect(context, domain, port, false).await?;
         let buffered_stream = BufWriter::new(proxy_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let mut client = Client::new(session_stream);
         let _greeting = client
             .read_response()
@@ -397,7 +421,11 @@ async fn connect_starttls_proxy(
         .await
         .context("STARTTLS upgrade failed")?;
         let buffered_stream = BufWriter::new(tls_stream);
-        let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
+        let session_stream: Box<dyn SessionStream> = Box::new(MeteredStream::new(
+            context,
+            ConnectionType::Imap,
+            buffered_stream,
+        ));
         let client = Client::new(session_stream);
         Ok(client)
     }
@@ -0,0 +1,103 @@
+//! # Gmail IMAP extensions.
+//!
+//! Gmail exposes labels as IMAP folders,
+//! so the same message may appear in several folders with different UIDs,
+//! and moving a message between folders only changes its labels.
+//! With the [X-GM-EXT-1] extension each message has a stable `X-GM-MSGID`
+//! and its labels can be fetched with `X-GM-LABELS`.
+//!
+//! [X-GM-EXT-1]: https://developers.google.com/workspace/gmail/imap/imap-extensions
+
+use std::borrow::Cow;
+
+use anyhow::Result;
+
+use super::GENERATED_PREFIX;
+use crate::context::Context;
+use crate::message::MsgId;
+
+/// Returns a Message-ID for a Gmail message which has no `Message-ID` header.
+///
+/// Unlike randomly generated IDs, it is the same in all label folders,
+/// so the message is not processed once per label.
+pub(crate) fn generated_message_id(gm_msgid: u64) -> String {
+    format!("{GENERATED_PREFIX}gm{gm_msgid}")
+}
+
+/// Stores Gmail message ID and labels of a prefetched message.
+pub(crate) async fn store_labels(
+    context: &Context,
+    transport_id: u32,
+    rfc724_mid: &str,
+    gm_msgid: u64,
+    labels: &[Cow<'_, str>],
+) -> Result<()> {
+    let labels = labels.join("\n");
+    context
+        .sql
+        .execute(
+            "INSERT INTO gmail_labels (transport_id, rfc724_mid, gm_msgid, labels)
+             VALUES (?,?,?,?)
+             ON CONFLICT(transport_id, rfc724_mid)
+             DO UPDATE SET gm_msgid=excluded.gm_msgid, labels=excluded.labels",
+            (transport_id, rfc724_mid, gm_msgid.to_string(), labels),
+        )
+        .await?;
+    Ok(())
+}
+
+impl MsgId {
+    /// Returns Gmail labels of the message, e.g. `\Inbox`, `\Important` or user labels.
+    ///
+    /// Labels are known only for messages received from Gmail servers
+    /// supporting the X-GM-EXT-1 extension, otherwise the list is empty.
+    pub async fn get_gmail_labels(self, context: &Context) -> Result<Vec<String>> {
+        let labels: Option<String> = context
+            .sql
+            .query_get_value(
+                "SELECT g.labels FROM gmail_labels g, msgs m
+                 WHERE m.id=? AND g.rfc724_mid=m.rfc724_mid",
+                (self,),
+            )
+            .await?;
+        Ok(labels
+            .unwrap_or_default()
+            .split('\n')
+            .filter(|label| !label.is_empty())
+            .map(|label| label.to_string())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_gmail_labels() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.get_self_chat().await;
+        let msg_id = t.send_text(chat.id, "Hi").await.sender_msg_id;
+        assert!(msg_id.get_gmail_labels(&t).await?.is_empty());
+
+        let rfc724_mid = crate::message::Message::load_from_db(&t, msg_id)
+            .await?
+            .rfc724_mid;
+        store_labels(
+            &t,
+            1,
+            &rfc724_mid,
+            1278455344230334865,
+            &[Cow::Borrowed("\\Inbox"), Cow::Borrowed("Muy Importante")],
+        )
+        .await?;
+        assert_eq!(
+            msg_id.get_gmail_labels(&t).await?,
+            vec!["\\Inbox".to_string(), "Muy Importante".to_string()]
+        );
+
+        assert_eq!(generated_message_id(42), generated_message_id(42));
+        Ok(())
+    }
+}
@@ -20,6 +20,10 @@
 /// For example, Dovecot sends keepalives every 2 minutes by default.
 const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
+/// Polling interval if IDLE is not available and network usage should be reduced,
+/// see [`Context::is_data_saving`].
+const DATA_SAVER_FAKE_IDLE_DURATION: Duration = Duration::from_secs(5 * 60);
+
 impl Session {
     pub async fn idle(
         mut self,
@@ -125,8 +129,13 @@ pub(crate) async fn fake_idle(&mut self, context: &Context, watch_folder: &str)
 
         info!(context, "IMAP-fake-IDLEing folder={:?}", watch_folder);
 
-        // Wait for 60 seconds or until we are interrupted.
-        match timeout(Duration::from_secs(60), self.idle_interrupt_receiver.recv()).await {
+        // Wait for 60 seconds, or 5 minutes when saving data, or until we are interrupted.
+        let fake_idle_duration = if context.is_data_saving().await? {
+            DATA_SAVER_FAKE_IDLE_DURATION
+        } else {
+            Duration::from_secs(60)
+        };
+        match timeout(fake_idle_duration, self.idle_interrupt_receiver.recv()).await {
             Err(_) => info!(context, "Fake IDLE finished."),
             Ok(_) => info!(context, "Fake IDLE interrupted."),
         }
@@ -8,6 +8,14 @@ fn test_get_folder_meaning_by_name() {
     assert_eq!(get_folder_meaning_by_name("xxx"), FolderMeaning::Unknown);
     assert_eq!(get_folder_meaning_by_name("SPAM"), FolderMeaning::Spam);
     assert_eq!(get_folder_meaning_by_name("Trash"), FolderMeaning::Trash);
+    assert_eq!(
+        get_folder_meaning_by_name("&BBoEPgRABDcEOAQ9BDA-"),
+        FolderMeaning::Trash
+    );
+    assert_eq!(
+        get_folder_meaning_by_name("&kAFP4W4IMH8w4TD8MOs-"),
+        FolderMeaning::Sent
+    );
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -32,6 +40,14 @@ async fn test_set_uid_next_validity() {
     assert_eq!(get_uidvalidity(&t.ctx, 2, "Inbox").await.unwrap(), 0);
 }
 
+#[test]
+fn test_build_since_criteria() {
+    // 2021-11-14 00:10:00 UTC
+    let now = 1636848600;
+    assert_eq!(build_since_criteria(1, now), "SINCE 13-Nov-2021");
+    assert_eq!(build_since_criteria(30, now), "SINCE 15-Oct-2021");
+}
+
 #[test]
 fn test_build_sequence_sets() {
     assert_eq!(build_sequence_sets(&[]).unwrap(), vec![]);
@@ -243,3 +259,68 @@ fn test_setmetadata_device_token() {
         "SETMETADATA \"INBOX\" (/private/devicetoken {15+}\r\nfoo\r\nbar\r\nbaz\r\n)"
     );
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_remove_expunged() -> Result<()> {
+    let mut tcm = crate::test_utils::TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice
+        .set_config_bool(Config::MarkExpungedMsgs, true)
+        .await?;
+
+    let bob_chat_id = bob.create_chat(alice).await.id;
+    let sent1 = bob.send_text(bob_chat_id, "first").await;
+    let sent2 = bob.send_text(bob_chat_id, "second").await;
+    let sent3 = bob.send_text(bob_chat_id, "third").await;
+    let mut msg_ids = Vec::new();
+    for (uid, sent) in [(1, &sent1), (2, &sent2), (3, &sent3)] {
+        let msg = alice.recv_msg(sent).await;
+        alice
+            .sql
+            .execute(
+                "INSERT INTO imap (transport_id, rfc724_mid, folder, target, uid, uidvalidity)
+                 VALUES (1, ?, 'INBOX', 'INBOX', ?, 1)",
+                (&msg.rfc724_mid, uid),
+            )
+            .await?;
+        msg_ids.push(msg.id);
+    }
+    // The second message is still available in another folder.
+    alice
+        .sql
+        .execute(
+            "INSERT INTO imap (transport_id, rfc724_mid, folder, target, uid, uidvalidity)
+             VALUES (1, (SELECT rfc724_mid FROM msgs WHERE id=?), 'Archive', 'Archive', 1, 1)",
+            (msg_ids[1],),
+        )
+        .await?;
+
+    // Only the third message is still in the INBOX,
+    // but UIDs outside of the range are not checked.
+    remove_expunged(alice, 1, "INBOX", 1, 2..=3, &HashSet::from([3])).await?;
+
+    let is_removed = async |msg_id| -> Result<bool> {
+        Ok(Message::load_from_db(alice, msg_id)
+            .await?
+            .is_removed_from_server())
+    };
+    assert!(!is_removed(msg_ids[0]).await?);
+    assert!(!is_removed(msg_ids[1]).await?);
+    assert!(!is_removed(msg_ids[2]).await?);
+    assert_eq!(
+        alice
+            .sql
+            .count("SELECT COUNT(*) FROM imap WHERE folder='INBOX'", ())
+            .await?,
+        2
+    );
+
+    remove_expunged(alice, 1, "INBOX", 1, 1..=3, &HashSet::new()).await?;
+    assert!(is_removed(msg_ids[0]).await?);
+    assert!(!is_removed(msg_ids[1]).await?);
+    assert!(is_removed(msg_ids[2]).await?);
+    assert_eq!(alice.sql.count("SELECT COUNT(*) FROM imap", ()).await?, 1);
+
+    Ok(())
+}
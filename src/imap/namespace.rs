@@ -0,0 +1,105 @@
+//! # Personal IMAP namespace.
+//!
+//! Some servers, e.g. Courier and older Cyrus setups,
+//! keep all folders of the user below INBOX,
+//! so the trash folder is called `INBOX.Trash`
+//! and the folder meaning cannot be guessed from the full name.
+//!
+//! [RFC 2342] NAMESPACE responses are not understood by the IMAP parser we use
+//! and would break the connection, so the personal namespace
+//! is derived from the `LIST` response instead.
+//!
+//! [RFC 2342]: https://datatracker.ietf.org/doc/html/rfc2342
+
+/// Personal namespace of the IMAP account.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Namespace {
+    /// Prefix of all personal folders except INBOX, e.g. `INBOX.`, or empty.
+    pub prefix: String,
+}
+
+impl Namespace {
+    /// Determines the personal namespace from the folder names and delimiters
+    /// returned by `LIST "" "*"`.
+    ///
+    /// The namespace is INBOX-prefixed if there are folders other than INBOX
+    /// and all of them are below INBOX.
+    pub(crate) fn from_folders<'a>(
+        folders: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    ) -> Self {
+        let mut delimiter = None;
+        let mut has_other_folders = false;
+        let mut all_below_inbox = true;
+        for (name, folder_delimiter) in folders {
+            if delimiter.is_none() {
+                delimiter = folder_delimiter;
+            }
+            if name.eq_ignore_ascii_case("INBOX") {
+                continue;
+            }
+            has_other_folders = true;
+            let below_inbox = folder_delimiter.is_some_and(|d| {
+                name.get(..5)
+                    .is_some_and(|inbox| inbox.eq_ignore_ascii_case("INBOX"))
+                    && name.get(5..).is_some_and(|rest| rest.starts_with(d))
+            });
+            if !below_inbox {
+                all_below_inbox = false;
+            }
+        }
+
+        let prefix = match &delimiter {
+            Some(delimiter) if has_other_folders && all_below_inbox => {
+                format!("INBOX{delimiter}")
+            }
+            _ => String::new(),
+        };
+        Self { prefix }
+    }
+
+    /// Returns the folder name relative to the personal namespace,
+    /// e.g. `Trash` for `INBOX.Trash`.
+    pub(crate) fn strip_prefix<'a>(&self, folder: &'a str) -> &'a str {
+        if self.prefix.is_empty() {
+            return folder;
+        }
+        folder
+            .get(..self.prefix.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(&self.prefix))
+            .and_then(|_| folder.get(self.prefix.len()..))
+            .unwrap_or(folder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inbox_namespace() {
+        let namespace = Namespace::from_folders([
+            ("INBOX", Some(".")),
+            ("INBOX.Trash", Some(".")),
+            ("INBOX.Sent", Some(".")),
+        ]);
+        assert_eq!(namespace.prefix, "INBOX.");
+        assert_eq!(namespace.strip_prefix("INBOX.Trash"), "Trash");
+        assert_eq!(namespace.strip_prefix("Inbox.Trash"), "Trash");
+        assert_eq!(namespace.strip_prefix("INBOX"), "INBOX");
+    }
+
+    #[test]
+    fn test_flat_namespace() {
+        let namespace = Namespace::from_folders([
+            ("INBOX", Some("/")),
+            ("INBOX/Receipts", Some("/")),
+            ("Trash", Some("/")),
+        ]);
+        assert_eq!(namespace.prefix, "");
+        assert_eq!(namespace.strip_prefix("INBOX/Receipts"), "INBOX/Receipts");
+
+        // Only INBOX, nothing to derive the namespace from.
+        let namespace = Namespace::from_folders([("INBOX", Some("/"))]);
+        assert_eq!(namespace.prefix, "");
+    }
+}
@@ -3,10 +3,13 @@
 use anyhow::Context as _;
 
 use super::session::Session as ImapSession;
-use super::{get_uid_next, get_uidvalidity, set_uid_next, set_uidvalidity};
+use super::utf7;
+use super::{build_since_criteria, get_uid_next, get_uidvalidity, set_uid_next, set_uidvalidity};
+use crate::config::Config;
 use crate::context::Context;
 use crate::ensure_and_debug_assert;
 use crate::log::warn;
+use crate::tools::time;
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -65,7 +68,7 @@ async fn select_folder(&mut self, context: &Context, folder: &str) -> Result<New
         self.maybe_close_folder(context).await?;
 
         // select new folder
-        let res = self.select(folder).await;
+        let res = self.select(utf7::to_server_name(folder)).await;
 
         let transport_id = self.transport_id();
 
@@ -160,7 +163,7 @@ pub(crate) async fn select_with_uidvalidity(
             // but responds to "STATUS INBOX (UIDNEXT)" command.
             let status = self
                 .inner
-                .status(folder, "(UIDNEXT)")
+                .status(utf7::to_server_name(folder), "(UIDNEXT)")
                 .await
                 .with_context(|| format!("STATUS (UIDNEXT) error for {folder:?}"))?;
 
@@ -202,7 +205,16 @@ pub(crate) async fn select_with_uidvalidity(
 
         // ==============  uid_validity has changed or is being set the first time.  ==============
 
-        let new_uid_next = new_uid_next.unwrap_or_default();
+        let mut new_uid_next = new_uid_next.unwrap_or_default();
+        if old_uid_validity == 0 && old_uid_next == 0 {
+            match self.initial_uid_next(context, new_uid_next).await {
+                Ok(uid_next) => new_uid_next = uid_next,
+                Err(err) => warn!(
+                    context,
+                    "Failed to determine existing messages to fetch from {folder:?}: {err:#}."
+                ),
+            }
+        }
         set_uid_next(context, transport_id, folder, new_uid_next).await?;
         set_uidvalidity(context, transport_id, folder, new_uid_validity).await?;
         self.new_mail = true;
@@ -225,6 +237,46 @@ pub(crate) async fn select_with_uidvalidity(
         );
         Ok(true)
     }
+
+    /// Returns the UID to start fetching from
+    /// when the selected folder is seen for the first time.
+    ///
+    /// Existing messages are fetched according to [`Config::InitialFetchCount`]
+    /// and [`Config::InitialFetchSinceDays`], by default only new messages are fetched.
+    async fn initial_uid_next(&mut self, context: &Context, uid_next: u32) -> anyhow::Result<u32> {
+        let count = context.get_config_u32(Config::InitialFetchCount).await?;
+        let days = context
+            .get_config_u32(Config::InitialFetchSinceDays)
+            .await?;
+        if count == 0 && days == 0 {
+            return Ok(uid_next);
+        }
+
+        let criteria = if days > 0 {
+            build_since_criteria(days, time())
+        } else {
+            "ALL".to_string()
+        };
+        let mut uids: Vec<u32> = self
+            .uid_search(&criteria)
+            .await
+            .context("UID SEARCH failed")?
+            .into_iter()
+            .filter(|uid| *uid < uid_next)
+            .collect();
+        uids.sort_unstable();
+        if count > 0 {
+            let skip = uids.len().saturating_sub(usize::try_from(count)?);
+            uids.drain(..skip);
+        }
+        let first_uid = uids.first().copied().unwrap_or(uid_next);
+        info!(
+            context,
+            "Fetching {} existing messages starting from UID {first_uid}.",
+            uids.len()
+        );
+        Ok(first_uid)
+    }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone, Eq)]
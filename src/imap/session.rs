@@ -106,6 +106,12 @@ pub fn can_metadata(&self) -> bool {
         self.capabilities.can_metadata
     }
 
+    /// Returns true if a literal of `len` bytes
+    /// can be sent without waiting for a continuation request.
+    pub(crate) fn can_non_sync_literal(&self, len: usize) -> bool {
+        self.capabilities.can_literal_plus || (self.capabilities.can_literal_minus && len <= 4096)
+    }
+
     pub fn can_push(&self) -> bool {
         self.capabilities.can_push
     }
@@ -115,6 +121,11 @@ pub(crate) fn is_chatmail(&self) -> bool {
         self.capabilities.is_chatmail
     }
 
+    /// Returns true if IMAP server has `X-GM-EXT-1` capability.
+    pub(crate) fn is_gmail(&self) -> bool {
+        self.capabilities.is_gmail
+    }
+
     /// Returns the names of all folders on the IMAP server.
     pub async fn list_folders(&mut self) -> Result<Vec<async_imap::types::Name>> {
         let list = self.list(Some(""), Some("*")).await?.try_collect().await?;
@@ -132,8 +143,15 @@ pub(crate) async fn prefetch(
         let uid_last = uid_next.saturating_add(n_uids - 1);
         // fetch messages with larger UID than the last one seen
         let set = format!("{uid_next}:{uid_last}");
+        let flags = if self.is_gmail() {
+            // Gmail message ID is the same in all label folders,
+            // labels tell which folders contain the message.
+            PREFETCH_FLAGS.replacen("(UID ", "(UID X-GM-MSGID X-GM-LABELS ", 1)
+        } else {
+            PREFETCH_FLAGS.to_string()
+        };
         let mut list = self
-            .uid_fetch(set, PREFETCH_FLAGS)
+            .uid_fetch(set, flags)
             .await
             .context("IMAP could not fetch")?;
 
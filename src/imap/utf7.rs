@@ -0,0 +1,116 @@
+//! # Modified UTF-7 folder names.
+//!
+//! IMAP servers not supporting `UTF8=ACCEPT` return non-ASCII folder names
+//! in modified UTF-7 as defined in [RFC 3501 section 5.1.3],
+//! e.g. `&BBoEPgRABDcEOAQ9BDA-` for `Корзина`.
+//!
+//! Folder names are kept decoded everywhere in the core:
+//! names received from the server are decoded with [`from_server_name`]
+//! and all names sent to the server are encoded with [`to_server_name`].
+//!
+//! [RFC 3501 section 5.1.3]: https://datatracker.ietf.org/doc/html/rfc3501#section-5.1.3
+
+use base64::Engine as _;
+use base64::alphabet::IMAP_MUTF7;
+use base64::engine::general_purpose::{GeneralPurpose, NO_PAD};
+
+/// Base64 engine with `,` instead of `/` and without padding.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(&IMAP_MUTF7, NO_PAD);
+
+/// Encodes the folder name in modified UTF-7.
+pub(crate) fn encode(name: &str) -> String {
+    let mut res = String::new();
+    let mut utf16: Vec<u8> = Vec::new();
+    for c in name.chars() {
+        if (' '..='~').contains(&c) {
+            if !utf16.is_empty() {
+                res.push('&');
+                res.push_str(&ENGINE.encode(&utf16));
+                res.push('-');
+                utf16.clear();
+            }
+            if c == '&' {
+                res.push_str("&-");
+            } else {
+                res.push(c);
+            }
+        } else {
+            let mut buf = [0u16; 2];
+            for unit in c.encode_utf16(&mut buf) {
+                utf16.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+    if !utf16.is_empty() {
+        res.push('&');
+        res.push_str(&ENGINE.encode(&utf16));
+        res.push('-');
+    }
+    res
+}
+
+/// Decodes the folder name from modified UTF-7.
+///
+/// Returns `None` if the name is not valid modified UTF-7.
+pub(crate) fn decode(name: &str) -> Option<String> {
+    let mut res = String::new();
+    let mut rest = name;
+    while let Some((plain, encoded)) = rest.split_once('&') {
+        res.push_str(plain);
+        let (encoded, tail) = encoded.split_once('-')?;
+        if encoded.is_empty() {
+            res.push('&');
+        } else {
+            let bytes = ENGINE.decode(encoded).ok()?;
+            let units: Vec<u16> = bytes
+                .chunks(2)
+                .map(|pair| match pair {
+                    [hi, lo] => Some(u16::from_be_bytes([*hi, *lo])),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?;
+            res.push_str(&String::from_utf16(&units).ok()?);
+        }
+        rest = tail;
+    }
+    res.push_str(rest);
+    Some(res)
+}
+
+/// Returns the folder name to send to the server.
+pub(crate) fn to_server_name(name: &str) -> String {
+    encode(name)
+}
+
+/// Returns the folder name received from the server.
+///
+/// Names that are not valid modified UTF-7 are returned as is.
+pub(crate) fn from_server_name(name: &str) -> String {
+    decode(name).unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf7() {
+        for (decoded, encoded) in [
+            ("INBOX", "INBOX"),
+            ("Корзина", "&BBoEPgRABDcEOAQ9BDA-"),
+            ("ゴミ箱", "&MLQw33ux-"),
+            ("Tom & Jerry", "Tom &- Jerry"),
+            ("~peter/mail/台北/日本語", "~peter/mail/&U,BTFw-/&ZeVnLIqe-"),
+        ] {
+            assert_eq!(encode(decoded), encoded);
+            assert_eq!(decode(encoded).unwrap(), decoded);
+        }
+        assert_eq!(decode("&BBoEPg"), None);
+        assert_eq!(to_server_name("INBOX.Spam"), "INBOX.Spam");
+        assert_eq!(to_server_name("Спам"), "&BCEEPwQwBDw-");
+        assert_eq!(to_server_name("Tom & Jerry"), "Tom &- Jerry");
+        assert_eq!(from_server_name("&BCEEPwQwBDw-"), "Спам");
+        assert_eq!(from_server_name("Tom &- Jerry"), "Tom & Jerry");
+        assert_eq!(from_server_name("A&B"), "A&B");
+    }
+}
@@ -17,7 +17,7 @@
 use crate::chat::delete_and_reset_all_device_msgs;
 use crate::config::Config;
 use crate::context::Context;
-use crate::events::EventType;
+use crate::events::{ErrorCode, EventType};
 use crate::key::{self, DcKey, SignedSecretKey};
 use crate::log::{LogExt, warn};
 use crate::qr::DCBACKUP_VERSION;
@@ -27,9 +27,12 @@
     write_file,
 };
 
+mod foreign_chat;
+mod key_backup;
 mod transfer;
 
 use ::pgp::types::KeyDetails;
+pub use foreign_chat::{ForeignChatFormat, import_foreign_chat};
 pub use transfer::{BackupProvider, get_backup};
 
 // Name of the database file in the backup.
@@ -99,7 +102,7 @@ pub async fn imex(
 
     if let Err(err) = res.as_ref() {
         // We are using Anyhow's .context() and to show the inner error, too, we need the {:#}:
-        error!(context, "{:#}", err);
+        error!(context, code = ErrorCode::from_error(err), "{:#}", err);
         warn!(context, "IMEX failed to complete: {:#}", err);
         context.emit_event(EventType::ImexProgress(0));
     } else {
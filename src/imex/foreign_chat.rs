@@ -0,0 +1,789 @@
+//! # Import of chats exported from other messengers.
+//!
+//! Supported are WhatsApp chat exports,
+//! either the zip archive created by "Export chat" or the extracted text file,
+//! and Signal chats exported to Markdown by `signal-export`,
+//! either as a zip archive or as a directory with `chat.md`.
+//!
+//! Messages are added to an existing chat with their original timestamps.
+//! Senders are mapped to the chat members with the same name,
+//! messages of other senders are attributed to the device
+//! and show the original sender name, see [`Message::get_override_sender_name()`].
+//!
+//! [`Message::get_override_sender_name()`]: crate::message::Message::get_override_sender_name
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{Context as _, Result, ensure};
+use async_zip::tokio::read::seek::ZipFileReader;
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use regex::Regex;
+use tokio::fs::{self, File};
+use tokio::io::BufReader;
+
+use crate::blob::BlobObject;
+use crate::chat::{Chat, ChatId, get_chat_contacts};
+use crate::chatlist_events;
+use crate::config::Config;
+use crate::constants::Chattype;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::log::warn;
+use crate::message::{MessageState, Viewtype, guess_msgtype_from_path_suffix};
+use crate::param::{Param, Params};
+use crate::tools::{create_outgoing_rfc724_mid, normalize_text, time};
+
+/// Format of a chat export, see [`import_foreign_chat()`].
+#[derive(Debug, Display, EnumString, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[strum(serialize_all = "lowercase")]
+#[repr(u32)]
+pub enum ForeignChatFormat {
+    /// WhatsApp chat export created by "Export chat" on Android or iOS.
+    WhatsApp = 1,
+
+    /// Signal chat exported to Markdown by `signal-export`.
+    Signal = 2,
+}
+
+/// Message line of a WhatsApp export,
+/// `[31.12.20, 23:59:59] Name: text` on iOS and `31/12/2020, 23:59 - Name: text` on Android.
+static WHATSAPP_LINE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\[?(\d{1,4}[./-]\d{1,2}[./-]\d{1,4}),? (\d{1,2}:\d{2}(?::\d{2})?(?: ?[AaPp]\.? ?[Mm]\.?)?)(?:\] | - )([^:]+): (.*)$",
+    )
+    .unwrap()
+});
+
+/// Attachment of a WhatsApp export, `<attached: name>` on iOS and `name (file attached)` on Android.
+static WHATSAPP_ATTACHMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:<attached: ([^>]+)>|(\S.*) \(file attached\))$").unwrap());
+
+/// Message line of a `signal-export` Markdown file, `[2020-12-31 23:59] Name: text`.
+static SIGNAL_LINE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[(\d{4}-\d{2}-\d{2}) (\d{2}:\d{2}(?::\d{2})?)\] ([^:]+): (.*)$").unwrap()
+});
+
+/// Attachment of a `signal-export` Markdown file, `![name](./media/name)` or `[name](./media/name)`.
+static SIGNAL_ATTACHMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^!?\[[^\]]*\]\(([^)]+)\)$").unwrap());
+
+/// Message parsed from a chat export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ForeignMessage {
+    timestamp: i64,
+    sender: String,
+    text: String,
+
+    /// Names of attached files in the export.
+    attachments: Vec<String>,
+}
+
+/// Order of day, month and year in the dates of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    DayFirst,
+    MonthFirst,
+}
+
+/// Imports the messages of a chat exported from another messenger into `chat_id`.
+///
+/// `path` is the exported zip archive, a directory containing the export
+/// or the exported text file, in which case attachments are looked up next to it.
+/// Timestamps are interpreted in the local time zone.
+///
+/// Returns the number of imported messages.
+pub async fn import_foreign_chat(
+    context: &Context,
+    path: &Path,
+    format: ForeignChatFormat,
+    chat_id: ChatId,
+) -> Result<usize> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    let mut export = Export::open(path, format).await?;
+    let messages = match format {
+        ForeignChatFormat::WhatsApp => parse_whatsapp(&export.text),
+        ForeignChatFormat::Signal => parse_signal(&export.text),
+    };
+    let senders = map_senders(context, &chat, &messages).await?;
+
+    let mut imported: usize = 0;
+    for msg in messages {
+        let from_id = senders
+            .get(&msg.sender)
+            .copied()
+            .unwrap_or(ContactId::DEVICE);
+        let mut param = Params::new();
+        if from_id == ContactId::DEVICE {
+            param.set(Param::OverrideSenderDisplayname, &msg.sender);
+        }
+
+        let mut parts = Vec::new();
+        for name in &msg.attachments {
+            let data = export.read(name).await.unwrap_or_else(|err| {
+                warn!(
+                    context,
+                    "Failed to read {name:?} from chat export: {err:#}."
+                );
+                None
+            });
+            parts.push((basename(name), data));
+        }
+        if parts.is_empty() {
+            insert_msg(context, chat_id, from_id, &msg, &msg.text, param, None).await?;
+            imported = imported.saturating_add(1);
+            continue;
+        }
+        let last = parts.len().saturating_sub(1);
+        for (i, (name, data)) in parts.into_iter().enumerate() {
+            // The text is the caption of the last attachment.
+            let text = if i == last { msg.text.as_str() } else { "" };
+            match data {
+                Some(data) => {
+                    let blob = BlobObject::create_and_deduplicate_from_bytes(context, &data, name)?;
+                    let file = Some((name, &blob));
+                    insert_msg(context, chat_id, from_id, &msg, text, param.clone(), file).await?;
+                }
+                None => {
+                    // The export does not contain the file, keep its name in the text.
+                    let text = format!("{name}\n{text}");
+                    let text = text.trim();
+                    insert_msg(context, chat_id, from_id, &msg, text, param.clone(), None).await?;
+                }
+            }
+        }
+        imported = imported.saturating_add(1);
+    }
+
+    info!(context, "Imported {imported} messages into {chat_id}.");
+    if imported > 0 {
+        context.emit_msgs_changed_without_msg_id(chat_id);
+        chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    }
+    Ok(imported)
+}
+
+async fn insert_msg(
+    context: &Context,
+    chat_id: ChatId,
+    from_id: ContactId,
+    msg: &ForeignMessage,
+    text: &str,
+    mut param: Params,
+    file: Option<(&str, &BlobObject<'_>)>,
+) -> Result<()> {
+    let mut viewtype = Viewtype::Text;
+    if let Some((name, blob)) = file {
+        let (guessed_viewtype, mime) = guess_msgtype_from_path_suffix(Path::new(name))
+            .unwrap_or((Viewtype::File, "application/octet-stream"));
+        viewtype = guessed_viewtype;
+        param.set(Param::File, blob.as_name());
+        param.set(Param::Filename, name);
+        param.set(Param::MimeType, mime);
+    }
+    let state = if from_id == ContactId::SELF {
+        MessageState::OutDelivered
+    } else {
+        MessageState::InSeen
+    };
+    context
+        .sql
+        .insert(
+            "INSERT INTO msgs (chat_id, from_id, to_id, timestamp, timestamp_sent, timestamp_rcvd,
+                               type, state, txt, txt_normalized, rfc724_mid, param)
+             VALUES (?,?,?,?,?,?,?,?,?,?,?,?)",
+            (
+                chat_id,
+                from_id,
+                ContactId::SELF,
+                msg.timestamp,
+                msg.timestamp,
+                time(),
+                viewtype,
+                state,
+                text,
+                normalize_text(text),
+                create_outgoing_rfc724_mid(),
+                param.to_string(),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Maps the sender names of the export to the chat members with the same name.
+///
+/// In chats with a single contact, a single unknown sender name
+/// is mapped to the member not found by name.
+async fn map_senders(
+    context: &Context,
+    chat: &Chat,
+    messages: &[ForeignMessage],
+) -> Result<BTreeMap<String, ContactId>> {
+    let mut members = Vec::new();
+    if let Some(displayname) = context.get_config(Config::Displayname).await? {
+        members.push((displayname.to_lowercase(), ContactId::SELF));
+    }
+    for contact_id in get_chat_contacts(context, chat.id).await? {
+        if contact_id == ContactId::SELF {
+            continue;
+        }
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        for name in [
+            contact.get_name(),
+            contact.get_authname(),
+            contact.get_display_name(),
+        ] {
+            if !name.is_empty() {
+                members.push((name.to_lowercase(), contact_id));
+            }
+        }
+    }
+
+    let mut senders = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for msg in messages {
+        if senders.contains_key(&msg.sender) || unknown.contains(&msg.sender) {
+            continue;
+        }
+        let name = msg.sender.to_lowercase();
+        match members.iter().find(|(member, _)| *member == name) {
+            Some((_, contact_id)) => {
+                senders.insert(msg.sender.clone(), *contact_id);
+            }
+            None => unknown.push(msg.sender.clone()),
+        }
+    }
+
+    if chat.typ == Chattype::Single
+        && let [sender] = unknown.as_slice()
+    {
+        let contact_id = get_chat_contacts(context, chat.id)
+            .await?
+            .into_iter()
+            .find(|contact_id| *contact_id != ContactId::SELF);
+        let remaining = [ContactId::SELF]
+            .into_iter()
+            .chain(contact_id)
+            .find(|contact_id| !senders.values().any(|id| id == contact_id));
+        if let Some(contact_id) = remaining {
+            senders.insert(sender.clone(), contact_id);
+        }
+    }
+    Ok(senders)
+}
+
+/// Text of a chat export and the source of its attachments.
+struct Export {
+    text: String,
+    files: ExportFiles,
+}
+
+enum ExportFiles {
+    Zip(Box<ZipFileReader<BufReader<File>>>),
+    Dir(PathBuf),
+}
+
+impl Export {
+    async fn open(path: &Path, format: ForeignChatFormat) -> Result<Self> {
+        let (text_name, text_suffix) = match format {
+            ForeignChatFormat::WhatsApp => ("_chat.txt", ".txt"),
+            ForeignChatFormat::Signal => ("chat.md", ".md"),
+        };
+        let is_text_file = |name: &str| name == text_name || name.ends_with(text_suffix);
+
+        if fs::metadata(path).await?.is_dir() {
+            let mut dir = fs::read_dir(path).await?;
+            let mut text_path = None;
+            while let Some(entry) = dir.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name == text_name || (text_path.is_none() && is_text_file(&name)) {
+                    text_path = Some(entry.path());
+                }
+            }
+            let text_path = text_path.context("No chat found in the export directory")?;
+            return Ok(Self {
+                text: fs::read_to_string(text_path).await?,
+                files: ExportFiles::Dir(path.to_path_buf()),
+            });
+        }
+
+        let file = File::open(path).await?;
+        match ZipFileReader::with_tokio(BufReader::new(file)).await {
+            Ok(archive) => {
+                let entries = archive.file().entries();
+                let text_name = entries
+                    .iter()
+                    .filter_map(|entry| entry.filename().as_str().ok())
+                    .filter(|name| is_text_file(basename(name)))
+                    .min_by_key(|name| basename(name) != text_name)
+                    .context("No chat found in the export archive")?
+                    .to_string();
+                let mut files = ExportFiles::Zip(Box::new(archive));
+                let text = files
+                    .read(&text_name)
+                    .await?
+                    .context("Failed to read chat from the export archive")?;
+                Ok(Self {
+                    text: String::from_utf8_lossy(&text).to_string(),
+                    files,
+                })
+            }
+            Err(_) => Ok(Self {
+                text: fs::read_to_string(path).await?,
+                files: ExportFiles::Dir(path.parent().unwrap_or(Path::new(".")).to_path_buf()),
+            }),
+        }
+    }
+
+    /// Reads the attachment `name`, returns `None` if the export does not contain it.
+    async fn read(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.files.read(name).await
+    }
+}
+
+impl ExportFiles {
+    async fn read(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        let name = basename(name);
+        match self {
+            Self::Zip(archive) => {
+                let index = archive.file().entries().iter().position(|entry| {
+                    entry
+                        .filename()
+                        .as_str()
+                        .is_ok_and(|entry_name| basename(entry_name) == name)
+                });
+                let Some(index) = index else {
+                    return Ok(None);
+                };
+                let mut reader = archive.reader_with_entry(index).await?;
+                let mut data = Vec::new();
+                reader.read_to_end_checked(&mut data).await?;
+                Ok(Some(data))
+            }
+            Self::Dir(dir) => {
+                for path in [dir.join(name), dir.join("media").join(name)] {
+                    if fs::try_exists(&path).await? {
+                        return Ok(Some(fs::read(path).await?));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Returns the last component of a path inside an export,
+/// so that attachments cannot refer to files outside of it.
+fn basename(name: &str) -> &str {
+    name.rsplit(['/', '\\']).next().unwrap_or(name)
+}
+
+fn parse_whatsapp(text: &str) -> Vec<ForeignMessage> {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            line.replace(['\u{200e}', '\u{200f}', '\u{feff}'], "")
+                .replace(['\u{202f}', '\u{a0}'], " ")
+        })
+        .collect();
+    let order = date_order(
+        lines
+            .iter()
+            .filter_map(|line| WHATSAPP_LINE_RE.captures(line))
+            .filter_map(|captures| captures.get(1))
+            .map(|date| date.as_str()),
+    );
+
+    let mut messages = parse_lines(&lines, &WHATSAPP_LINE_RE, order);
+    for msg in &mut messages {
+        let first_line = msg.text.lines().next().unwrap_or_default();
+        if let Some(captures) = WHATSAPP_ATTACHMENT_RE.captures(first_line)
+            && let Some(name) = captures.get(1).or_else(|| captures.get(2))
+        {
+            msg.attachments.push(name.as_str().to_string());
+            msg.text = msg
+                .text
+                .split_once('\n')
+                .map(|(_, caption)| caption.to_string())
+                .unwrap_or_default();
+        }
+    }
+    messages
+}
+
+fn parse_signal(text: &str) -> Vec<ForeignMessage> {
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut messages = parse_lines(&lines, &SIGNAL_LINE_RE, DateOrder::DayFirst);
+    for msg in &mut messages {
+        let mut text = Vec::new();
+        for line in msg.text.lines() {
+            match SIGNAL_ATTACHMENT_RE.captures(line.trim()) {
+                Some(captures) => {
+                    if let Some(name) = captures.get(1) {
+                        msg.attachments.push(name.as_str().to_string());
+                    }
+                }
+                None => text.push(line),
+            }
+        }
+        msg.text = text.join("\n").trim().to_string();
+    }
+    messages
+}
+
+/// Splits the export into messages starting at lines matching `line_re`,
+/// other lines are continuations of the previous message.
+///
+/// `line_re` captures the date, time, sender and the first line of the text.
+fn parse_lines(lines: &[String], line_re: &Regex, order: DateOrder) -> Vec<ForeignMessage> {
+    let mut messages: Vec<ForeignMessage> = Vec::new();
+    let mut skipping = true;
+    for line in lines {
+        if let Some(captures) = line_re.captures(line) {
+            let (Some(date), Some(time), Some(sender), Some(text)) = (
+                captures.get(1),
+                captures.get(2),
+                captures.get(3),
+                captures.get(4),
+            ) else {
+                continue;
+            };
+            let Some(timestamp) = parse_datetime(date.as_str(), time.as_str(), order)
+                .and_then(|datetime| Local.from_local_datetime(&datetime).earliest())
+                .map(|datetime| datetime.timestamp())
+            else {
+                skipping = true;
+                continue;
+            };
+            skipping = false;
+            messages.push(ForeignMessage {
+                timestamp,
+                sender: sender.as_str().trim().to_string(),
+                text: text.as_str().to_string(),
+                attachments: Vec::new(),
+            });
+        } else if !skipping && let Some(msg) = messages.last_mut() {
+            msg.text.push('\n');
+            msg.text.push_str(line);
+        }
+    }
+    messages
+}
+
+/// Guesses whether the dates of an export start with the day or the month.
+///
+/// Dates separated by dots start with the day unless a month would be larger than 12.
+fn date_order<'a>(dates: impl Iterator<Item = &'a str>) -> DateOrder {
+    let mut dotted = false;
+    for date in dates {
+        dotted |= date.contains('.');
+        let parts = date_parts(date);
+        match parts {
+            Some((first, _, _)) if first > 12 && first < 100 => return DateOrder::DayFirst,
+            Some((first, second, _)) if second > 12 && first < 100 => {
+                return DateOrder::MonthFirst;
+            }
+            _ => {}
+        }
+    }
+    if dotted {
+        DateOrder::DayFirst
+    } else {
+        DateOrder::MonthFirst
+    }
+}
+
+fn date_parts(date: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = date.split(['.', '/', '-']).map(|part| part.parse().ok());
+    let first = parts.next()??;
+    let second = parts.next()??;
+    let third = parts.next()??;
+    Some((first, second, third))
+}
+
+fn parse_datetime(date: &str, time: &str, order: DateOrder) -> Option<NaiveDateTime> {
+    let (first, second, third) = date_parts(date)?;
+    let (year, month, day) = if first > 31 {
+        (first, second, third)
+    } else {
+        match order {
+            DateOrder::DayFirst => (third, second, first),
+            DateOrder::MonthFirst => (third, first, second),
+        }
+    };
+    let year = if year < 100 {
+        year.saturating_add(2000)
+    } else {
+        year
+    };
+
+    let time = time.to_lowercase().replace(['.', ' '], "");
+    let (time, pm) = match time.strip_suffix("pm") {
+        Some(time) => (time, Some(true)),
+        None => match time.strip_suffix("am") {
+            Some(time) => (time, Some(false)),
+            None => (time.as_str(), None),
+        },
+    };
+    let mut parts = time.split(':').map(|part| part.parse::<u32>().ok());
+    let mut hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = parts.next().flatten().unwrap_or_default();
+    match pm {
+        Some(true) if hour < 12 => hour = hour.saturating_add(12),
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+    NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, day)?
+        .and_hms_opt(hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_zip::tokio::write::ZipFileWriter;
+    use async_zip::{Compression, ZipEntryBuilder};
+
+    use super::*;
+    use crate::chat::{self, ChatItem};
+    use crate::message::Message;
+    use crate::test_utils::TestContextManager;
+
+    fn local_timestamp(date: &str) -> i64 {
+        let datetime = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local
+            .from_local_datetime(&datetime)
+            .earliest()
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn test_parse_whatsapp_android() {
+        let text = "12/31/20, 11:58 PM - Messages and calls are end-to-end encrypted.
+12/31/20, 11:59 PM - Bob: Happy new year!
+See you soon.
+1/1/21, 12:00 AM - Alice: IMG-20210101-WA0001.jpg (file attached)
+Fireworks
+1/1/21, 12:01 AM - Alice: <Media omitted>";
+        let messages = parse_whatsapp(text);
+        assert_eq!(
+            messages,
+            vec![
+                ForeignMessage {
+                    timestamp: local_timestamp("2020-12-31 23:59:00"),
+                    sender: "Bob".to_string(),
+                    text: "Happy new year!\nSee you soon.".to_string(),
+                    attachments: Vec::new(),
+                },
+                ForeignMessage {
+                    timestamp: local_timestamp("2021-01-01 00:00:00"),
+                    sender: "Alice".to_string(),
+                    text: "Fireworks".to_string(),
+                    attachments: vec!["IMG-20210101-WA0001.jpg".to_string()],
+                },
+                ForeignMessage {
+                    timestamp: local_timestamp("2021-01-01 00:01:00"),
+                    sender: "Alice".to_string(),
+                    text: "<Media omitted>".to_string(),
+                    attachments: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_whatsapp_ios() {
+        let text = "[03.02.21, 14:15:16] Bob: Hallo: wie geht's?
+[03.02.21, 14:16:00] Alice Example: \u{200e}<attached: 00000003-PHOTO-2021-02-03-14-16-00.jpg>";
+        let messages = parse_whatsapp(text);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0].timestamp,
+            local_timestamp("2021-02-03 14:15:16")
+        );
+        assert_eq!(messages[0].text, "Hallo: wie geht's?");
+        assert_eq!(messages[1].sender, "Alice Example");
+        assert_eq!(messages[1].text, "");
+        assert_eq!(
+            messages[1].attachments,
+            vec!["00000003-PHOTO-2021-02-03-14-16-00.jpg".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_date_order() {
+        assert_eq!(
+            date_order(["01/02/21", "13/02/21"].into_iter()),
+            DateOrder::DayFirst
+        );
+        assert_eq!(
+            date_order(["01/02/21", "02/13/21"].into_iter()),
+            DateOrder::MonthFirst
+        );
+        assert_eq!(date_order(["01.02.21"].into_iter()), DateOrder::DayFirst);
+        assert_eq!(date_order(["01/02/21"].into_iter()), DateOrder::MonthFirst);
+        assert_eq!(
+            parse_datetime("2021-02-03", "12:30 p. m.", DateOrder::MonthFirst),
+            NaiveDate::from_ymd_opt(2021, 2, 3)
+                .unwrap()
+                .and_hms_opt(12, 30, 0)
+        );
+        assert_eq!(
+            parse_datetime("31/12/2020", "12:05 AM", DateOrder::DayFirst),
+            NaiveDate::from_ymd_opt(2020, 12, 31)
+                .unwrap()
+                .and_hms_opt(0, 5, 0)
+        );
+        assert_eq!(
+            parse_datetime("31/31/20", "12:00", DateOrder::DayFirst),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        let text = "[2021-02-03 14:15] Bob: Look at this
+![photo.jpg](./media/photo.jpg)
+[2021-02-03 14:16] Alice: Nice!
+Really.";
+        let messages = parse_signal(text);
+        assert_eq!(
+            messages,
+            vec![
+                ForeignMessage {
+                    timestamp: local_timestamp("2021-02-03 14:15:00"),
+                    sender: "Bob".to_string(),
+                    text: "Look at this".to_string(),
+                    attachments: vec!["./media/photo.jpg".to_string()],
+                },
+                ForeignMessage {
+                    timestamp: local_timestamp("2021-02-03 14:16:00"),
+                    sender: "Alice".to_string(),
+                    text: "Nice!\nReally.".to_string(),
+                    attachments: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    /// Returns the messages of the chat sent before the test started.
+    async fn imported_msgs(context: &Context, chat_id: ChatId) -> Result<Vec<Message>> {
+        let mut msgs = Vec::new();
+        for item in chat::get_chat_msgs(context, chat_id).await? {
+            if let ChatItem::Message { msg_id } = item {
+                let msg = Message::load_from_db(context, msg_id).await?;
+                if msg.get_timestamp() < local_timestamp("2022-01-01 00:00:00") {
+                    msgs.push(msg);
+                }
+            }
+        }
+        Ok(msgs)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_whatsapp_zip() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice.set_config(Config::Displayname, Some("Alice")).await?;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let text = "[03.02.21, 14:15:16] Bob Example: Hi!
+[03.02.21, 14:16:00] alice: <attached: 00000003-PHOTO-2021-02-03-14-16-00.jpg>
+[03.02.21, 14:17:00] alice: <attached: missing.pdf>";
+        let image = include_bytes!("../../test-data/image/avatar64x64.png");
+        let path = alice.get_blobdir().join("WhatsApp Chat - Bob.zip");
+        let mut writer = ZipFileWriter::with_tokio(File::create(&path).await?);
+        writer
+            .write_entry_whole(
+                ZipEntryBuilder::new("_chat.txt".into(), Compression::Deflate),
+                text.as_bytes(),
+            )
+            .await?;
+        writer
+            .write_entry_whole(
+                ZipEntryBuilder::new(
+                    "00000003-PHOTO-2021-02-03-14-16-00.jpg".into(),
+                    Compression::Stored,
+                ),
+                image,
+            )
+            .await?;
+        writer.close().await?;
+
+        let imported =
+            import_foreign_chat(alice, &path, ForeignChatFormat::WhatsApp, chat_id).await?;
+        assert_eq!(imported, 3);
+        let msgs = imported_msgs(alice, chat_id).await?;
+        assert_eq!(msgs.len(), 3);
+
+        // "Bob Example" is not a known name of Bob,
+        // but the only unknown sender in the chat with Bob.
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        assert_eq!(msgs[0].get_from_id(), bob_id);
+        assert_eq!(msgs[0].get_state(), MessageState::InSeen);
+        assert_eq!(msgs[0].get_text(), "Hi!");
+        assert_eq!(
+            msgs[0].get_timestamp(),
+            local_timestamp("2021-02-03 14:15:16")
+        );
+
+        assert_eq!(msgs[1].get_from_id(), ContactId::SELF);
+        assert_eq!(msgs[1].get_viewtype(), Viewtype::Image);
+        assert_eq!(
+            msgs[1].get_filename().unwrap(),
+            "00000003-PHOTO-2021-02-03-14-16-00.jpg"
+        );
+        assert_eq!(fs::read(msgs[1].get_file(alice).unwrap()).await?, image);
+
+        assert_eq!(msgs[2].get_viewtype(), Viewtype::Text);
+        assert_eq!(msgs[2].get_text(), "missing.pdf");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_import_signal_dir() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        alice.set_config(Config::Displayname, Some("Alice")).await?;
+        let chat_id = alice
+            .create_group_with_members("Friends", &[bob, fiona])
+            .await;
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        bob_id.set_name(alice, "Bob").await?;
+
+        let dir = alice.get_blobdir().join("signal-export");
+        fs::create_dir_all(dir.join("media")).await?;
+        fs::write(
+            dir.join("chat.md"),
+            "[2021-02-03 14:15] Bob: Look at this
+![report.pdf](./media/report.pdf)
+[2021-02-03 14:16] Charlie: Hi all!
+[2021-02-03 14:17] Alice: Thanks!",
+        )
+        .await?;
+        fs::write(dir.join("media").join("report.pdf"), b"%PDF-1.4").await?;
+
+        let imported = import_foreign_chat(alice, &dir, ForeignChatFormat::Signal, chat_id).await?;
+        assert_eq!(imported, 3);
+        let msgs = imported_msgs(alice, chat_id).await?;
+        assert_eq!(msgs.len(), 3);
+
+        assert_eq!(msgs[0].get_from_id(), bob_id);
+        assert_eq!(msgs[0].get_viewtype(), Viewtype::File);
+        assert_eq!(msgs[0].get_text(), "Look at this");
+
+        // Unknown senders are attributed to the device.
+        assert_eq!(msgs[1].get_from_id(), ContactId::DEVICE);
+        assert_eq!(msgs[1].get_override_sender_name().unwrap(), "Charlie");
+
+        assert_eq!(msgs[2].get_from_id(), ContactId::SELF);
+        assert_eq!(msgs[2].get_state(), MessageState::OutDelivered);
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! # Encrypted key backup on the server.
+//!
+//! The user may opt in to store the self key on the server
+//! so it can be restored if all devices are lost.
+//! The key is symmetrically encrypted with a passphrase chosen by the user
+//! and stored in the private IMAP METADATA entry
+//! `/private/vendor/deltachat/keybackup` of the primary transport.
+//! The server never sees the passphrase or the unencrypted key.
+//!
+//! The backup can be removed from the server at any time.
+
+use anyhow::{Context as _, Result, bail, ensure};
+use async_channel as channel;
+use async_imap::imap_proto::{Response, Status};
+
+use crate::EventType;
+use crate::context::Context;
+use crate::imap::Imap;
+use crate::imap::session::Session;
+use crate::key::{self, DcKey, SignedSecretKey};
+use crate::pgp;
+
+/// IMAP METADATA entry storing the encrypted key backup.
+const KEY_BACKUP_ENTRY: &str = "/private/vendor/deltachat/keybackup";
+
+impl Context {
+    /// Encrypts the self key with `passphrase` and stores it on the server.
+    ///
+    /// Replaces a previously stored key backup.
+    /// Use [`Context::delete_key_backup_from_server`] to remove the backup.
+    pub async fn backup_key_to_server(&self, passphrase: &str) -> Result<()> {
+        ensure!(!passphrase.is_empty(), "Passphrase must not be empty.");
+        let secret_key = key::load_self_secret_key(self).await?;
+        let encrypted = encrypt_key_backup(&secret_key, passphrase)?;
+        let mut session = connect(self).await?;
+        set_key_backup(&mut session, Some(&encrypted))
+            .await
+            .context("SETMETADATA command failed")?;
+        info!(
+            self,
+            "Stored encrypted backup of key {:?} on the server.",
+            secret_key.dc_fingerprint()
+        );
+        self.emit_event(EventType::KeyBackupChanged { stored: true });
+        Ok(())
+    }
+
+    /// Restores the self key from the backup stored on the server
+    /// and sets it as the default key.
+    pub async fn restore_key_from_server(&self, passphrase: &str) -> Result<()> {
+        let mut session = connect(self).await?;
+        let metadata = session
+            .get_metadata("", "", &format!("({KEY_BACKUP_ENTRY})"))
+            .await
+            .context("GETMETADATA command failed")?;
+        let Some(encrypted) = metadata
+            .into_iter()
+            .find(|m| m.entry == KEY_BACKUP_ENTRY)
+            .and_then(|m| m.value)
+        else {
+            bail!("No key backup found on the server.");
+        };
+        let secret_key = decrypt_key_backup(&encrypted, passphrase)?;
+        key::store_self_keypair(self, &secret_key).await?;
+        info!(
+            self,
+            "Restored key {:?} from the backup on the server.",
+            secret_key.dc_fingerprint()
+        );
+        Ok(())
+    }
+
+    /// Removes the key backup stored with [`Context::backup_key_to_server`]
+    /// from the server.
+    pub async fn delete_key_backup_from_server(&self) -> Result<()> {
+        let mut session = connect(self).await?;
+        set_key_backup(&mut session, None)
+            .await
+            .context("SETMETADATA command failed")?;
+        info!(self, "Deleted key backup from the server.");
+        self.emit_event(EventType::KeyBackupChanged { stored: false });
+        Ok(())
+    }
+}
+
+/// Opens a dedicated IMAP session on the primary transport
+/// that supports METADATA.
+async fn connect(context: &Context) -> Result<Session> {
+    let mut imap = Imap::new_configured(context, channel::bounded(1).1).await?;
+    let session = imap.prepare(context).await?;
+    ensure!(
+        session.can_metadata(),
+        "The server does not support storing a key backup."
+    );
+    Ok(session)
+}
+
+fn encrypt_key_backup(secret_key: &SignedSecretKey, passphrase: &str) -> Result<String> {
+    let armored = secret_key.to_asc(None);
+    pgp::passphrase_encrypt_message(armored.into_bytes(), passphrase)
+}
+
+fn decrypt_key_backup(encrypted: &str, passphrase: &str) -> Result<SignedSecretKey> {
+    let armored = pgp::symm_decrypt_message(encrypted.as_bytes().to_vec(), passphrase)
+        .context("Failed to decrypt key backup, wrong passphrase?")?;
+    SignedSecretKey::from_asc(std::str::from_utf8(&armored)?)
+}
+
+/// Stores `value` in the key backup entry or deletes the entry if `value` is `None`.
+///
+/// The value is sent as a non-synchronizing literal if the server supports it,
+/// otherwise waits for the continuation request before sending the literal.
+async fn set_key_backup(session: &mut Session, value: Option<&str>) -> Result<()> {
+    let Some(value) = value.filter(|value| !session.can_non_sync_literal(value.len())) else {
+        session
+            .run_command_and_check_ok(format_setmetadata_keybackup(value, true))
+            .await?;
+        return Ok(());
+    };
+
+    let id = session
+        .run_command(format_setmetadata_keybackup(Some(value), false))
+        .await?;
+    let response = session
+        .read_response()
+        .await?
+        .context("Connection closed before continuation request")?;
+    if !matches!(response.parsed(), Response::Continue { .. }) {
+        bail!("Server did not accept the literal: {response:?}");
+    }
+    session.run_command_untagged(format!("{value})")).await?;
+    loop {
+        let response = session
+            .read_response()
+            .await?
+            .context("Connection closed before SETMETADATA completed")?;
+        if let Response::Done {
+            tag,
+            status,
+            information,
+            ..
+        } = response.parsed()
+            && *tag == id
+        {
+            ensure!(*status == Status::Ok, "SETMETADATA failed: {information:?}");
+            return Ok(());
+        }
+    }
+}
+
+/// Formats the SETMETADATA command for the key backup entry.
+///
+/// If `non_sync` is false, only the command up to the synchronizing literal
+/// is returned and the value has to be sent after the continuation request.
+fn format_setmetadata_keybackup(value: Option<&str>, non_sync: bool) -> String {
+    match value {
+        Some(value) if non_sync => {
+            let len = value.len();
+            format!("SETMETADATA \"\" ({KEY_BACKUP_ENTRY} {{{len}+}}\r\n{value})")
+        }
+        Some(value) => {
+            let len = value.len();
+            format!("SETMETADATA \"\" ({KEY_BACKUP_ENTRY} {{{len}}}")
+        }
+        None => format!("SETMETADATA \"\" ({KEY_BACKUP_ENTRY} NIL)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ::pgp::composed::{Esk, Message};
+    use ::pgp::types::StringToKey;
+
+    use super::*;
+    use crate::test_utils::alice_keypair;
+
+    #[test]
+    fn test_key_backup_roundtrip() -> Result<()> {
+        let secret_key = alice_keypair();
+        let encrypted = encrypt_key_backup(&secret_key, "correct horse")?;
+        assert!(!encrypted.contains("PRIVATE KEY"));
+        assert!(decrypt_key_backup(&encrypted, "battery staple").is_err());
+        let decrypted = decrypt_key_backup(&encrypted, "correct horse")?;
+        assert_eq!(decrypted.dc_fingerprint(), secret_key.dc_fingerprint());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_backup_s2k() -> Result<()> {
+        let encrypted = encrypt_key_backup(&alice_keypair(), "correct horse")?;
+        let (msg, _headers) = Message::from_armor(Cursor::new(encrypted.as_bytes()))?;
+        let Message::Encrypted { esk, .. } = msg else {
+            bail!("Key backup is not encrypted");
+        };
+        let [Esk::SymKeyEncryptedSessionKey(esk)] = &esk[..] else {
+            bail!("Key backup is not encrypted with a passphrase");
+        };
+        assert!(matches!(esk.s2k(), Some(StringToKey::Argon2 { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_setmetadata_keybackup() {
+        assert_eq!(
+            format_setmetadata_keybackup(Some("abc"), true),
+            "SETMETADATA \"\" (/private/vendor/deltachat/keybackup {3+}\r\nabc)"
+        );
+        assert_eq!(
+            format_setmetadata_keybackup(Some("abc"), false),
+            "SETMETADATA \"\" (/private/vendor/deltachat/keybackup {3}"
+        );
+        assert_eq!(
+            format_setmetadata_keybackup(None, true),
+            "SETMETADATA \"\" (/private/vendor/deltachat/keybackup NIL)"
+        );
+    }
+}
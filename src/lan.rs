@@ -0,0 +1,466 @@
+//! # Messaging on the local network.
+//!
+//! If [`Config::LanMessaging`] is enabled, the device periodically announces
+//! its iroh node address with a UDP broadcast to the local network.
+//! The announcement is signed with the device's key
+//! over the node ID and the current time.
+//! Instead of the fingerprint of the key, the announcement contains
+//! a hash of the fingerprint and the time,
+//! so observers who do not know the key cannot recognize the device
+//! across announcements.
+//! When an announcement of a known contact is received,
+//! messages to this contact waiting in the SMTP queue
+//! are delivered directly to the announced iroh node.
+//! The messages stay in the SMTP queue and are sent by email as usual
+//! once the device is online again,
+//! the recipient deduplicates them by their Message-ID.
+//!
+//! Messages delivered directly are processed
+//! like messages fetched over IMAP, so signatures are checked as usual.
+//! Only nodes announced with a valid signature of a known contact
+//! may deliver messages.
+//!
+//! This works without internet connection,
+//! e.g. at events or during outages,
+//! as long as both devices are in the same local network.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket as StdUdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result, ensure};
+use deltachat_contact_tools::addr_cmp;
+use futures_lite::future::Boxed as BoxedFuture;
+use iroh::endpoint::Connection;
+use iroh::protocol::ProtocolHandler;
+use iroh::{NodeAddr, NodeId};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+
+use crate::config::Config;
+use crate::contact::ContactId;
+use crate::context::{Context, WeakContext};
+use crate::imap::prefetch_get_message_id;
+use crate::key::{DcKey, SignedPublicKey, load_self_secret_key, self_fingerprint};
+use crate::log::{LogExt, warn};
+use crate::net::bandwidth::ConnectionType;
+use crate::pgp::{self, RawSignature};
+use crate::receive_imf::receive_imf_inner;
+use crate::tools::time;
+
+/// ALPN of the protocol delivering messages on the local network.
+///
+/// The sending node opens a bidirectional stream,
+/// sends the raw message and finishes its send stream.
+/// The receiving node finishes its send stream once the message is processed.
+pub(crate) const LAN_MSG_ALPN: &[u8] = b"/deltachat/lan-msg/0";
+
+/// UDP port used for announcements.
+///
+/// The socket is bound with `SO_REUSEADDR` and `SO_REUSEPORT`,
+/// so all accounts and processes on the device receive the announcements.
+const LAN_DISCOVERY_PORT: u16 = 48627;
+
+/// Interval between announcements.
+const LAN_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum difference between the announcement time and the local time in seconds.
+const MAX_ANNOUNCEMENT_AGE: i64 = 5 * 60;
+
+/// Maximum size of a message delivered on the local network.
+const MAX_LAN_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Nodes announced with a valid signature of a known contact.
+pub(crate) type LanPeers = Arc<Mutex<HashMap<NodeId, ContactId>>>;
+
+/// Announcement broadcasted to the local network.
+#[derive(Debug, Serialize, Deserialize)]
+struct LanAnnouncement {
+    /// Identifier of the announcing device, see [`blinded_id`].
+    id: String,
+
+    /// Unix timestamp of the announcement.
+    timestamp: i64,
+
+    /// Iroh node address including direct addresses.
+    node_addr: NodeAddr,
+
+    /// Signature over the node ID and the timestamp, see [`signed_data`].
+    signature: RawSignature,
+}
+
+/// Returns the identifier of the device with the key `fingerprint`
+/// announced at `timestamp`.
+///
+/// The identifier changes with every announcement,
+/// only those knowing the fingerprint can recognize it.
+fn blinded_id(fingerprint: &str, timestamp: i64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"deltachat-lan-id");
+    hasher.update(fingerprint.as_bytes());
+    hasher.update(timestamp.to_be_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Returns the data signed by the announcing device.
+fn signed_data(id: &str, timestamp: i64, node_id: &NodeId) -> Vec<u8> {
+    let mut data = b"deltachat-lan-announcement".to_vec();
+    data.extend_from_slice(node_id.as_bytes());
+    data.extend_from_slice(&timestamp.to_be_bytes());
+    data.extend_from_slice(id.as_bytes());
+    data
+}
+
+/// Protocol handler receiving messages on the local network.
+#[derive(Debug, Clone)]
+pub(crate) struct LanMessageReceiver {
+    context: WeakContext,
+    peers: LanPeers,
+}
+
+impl LanMessageReceiver {
+    pub(crate) fn new(context: WeakContext, peers: LanPeers) -> Self {
+        Self { context, peers }
+    }
+}
+
+impl ProtocolHandler for LanMessageReceiver {
+    fn accept(&self, connection: Connection) -> BoxedFuture<Result<()>> {
+        let context = self.context.clone();
+        let peers = Arc::clone(&self.peers);
+        Box::pin(async move {
+            let context = context.upgrade()?;
+            let node_id = connection.remote_node_id()?;
+            let contact_id = peers
+                .lock()
+                .get(&node_id)
+                .copied()
+                .with_context(|| format!("Node {node_id} is not a known contact"))?;
+            let (mut send_stream, mut recv_stream) = connection.accept_bi().await?;
+            let imf_raw = recv_stream.read_to_end(MAX_LAN_MESSAGE_BYTES).await?;
+            context
+                .bandwidth
+                .get(ConnectionType::Iroh)
+                .add_received(imf_raw.len());
+            info!(
+                context,
+                "Received message from {contact_id} on the local network."
+            );
+            let mail = mailparse::parse_mail(&imf_raw)?;
+            let rfc724_mid =
+                prefetch_get_message_id(&mail.headers).context("Message has no Message-ID")?;
+            receive_imf_inner(&context, &rfc724_mid, &imf_raw, false).await?;
+            send_stream.finish()?;
+            connection.closed().await;
+            Ok(())
+        })
+    }
+}
+
+/// Announces this device and delivers queued messages to discovered contacts
+/// while [`Config::LanMessaging`] is enabled.
+pub(crate) async fn lan_loop(context: &Context) {
+    // Messages already delivered to a node, by node and Message-ID.
+    let mut delivered = HashSet::new();
+    loop {
+        if let Err(err) = lan_discovery(context, &mut delivered).await {
+            warn!(context, "Local network discovery failed: {err:#}.");
+        }
+        tokio::time::sleep(LAN_ANNOUNCE_INTERVAL).await;
+    }
+}
+
+async fn is_enabled(context: &Context) -> Result<bool> {
+    Ok(context.get_config_bool(Config::LanMessaging).await?
+        && context
+            .get_config_bool(Config::WebxdcRealtimeEnabled)
+            .await?
+        && context.is_configured().await?)
+}
+
+async fn lan_discovery(context: &Context, delivered: &mut HashSet<(NodeId, String)>) -> Result<()> {
+    if !is_enabled(context).await? {
+        return Ok(());
+    }
+    let socket = bind_discovery_socket()?;
+    info!(context, "Local network discovery started.");
+    let mut interval = tokio::time::interval(LAN_ANNOUNCE_INTERVAL);
+    let mut buf = vec![0; 4096];
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if !is_enabled(context).await? {
+                    info!(context, "Local network discovery stopped.");
+                    return Ok(());
+                }
+                announce(context, &socket).await?;
+            }
+            res = socket.recv_from(&mut buf) => {
+                let (len, _) = res?;
+                if let Some(data) = buf.get(..len) {
+                    handle_announcement(context, data, delivered)
+                        .await
+                        .log_err(context)
+                        .ok();
+                }
+            }
+        }
+    }
+}
+
+/// Binds the UDP socket receiving announcements.
+///
+/// Several accounts of this process and other processes
+/// may bind the port at the same time,
+/// each of them receives all broadcasts.
+fn bind_discovery_socket() -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(
+        unix,
+        not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+    ))]
+    socket.set_reuse_port(true)?;
+    socket.set_broadcast(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, LAN_DISCOVERY_PORT)).into())?;
+    Ok(UdpSocket::from_std(StdUdpSocket::from(socket))?)
+}
+
+async fn announce(context: &Context, socket: &UdpSocket) -> Result<()> {
+    let node_addr = context
+        .get_or_try_init_peer_channel()
+        .await?
+        .router
+        .endpoint()
+        .node_addr()
+        .await?;
+    let timestamp = time();
+    let id = blinded_id(self_fingerprint(context).await?, timestamp);
+    let signature = pgp::pk_sign_raw(
+        &load_self_secret_key(context).await?,
+        &signed_data(&id, timestamp, &node_addr.node_id),
+    )?;
+    let announcement = LanAnnouncement {
+        id,
+        timestamp,
+        node_addr,
+        signature,
+    };
+    let data = serde_json::to_vec(&announcement)?;
+    socket
+        .send_to(
+            &data,
+            SocketAddr::from((Ipv4Addr::BROADCAST, LAN_DISCOVERY_PORT)),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn handle_announcement(
+    context: &Context,
+    data: &[u8],
+    delivered: &mut HashSet<(NodeId, String)>,
+) -> Result<()> {
+    let announcement: LanAnnouncement = serde_json::from_slice(data)?;
+    let Some((contact_id, addr)) = verify_announcement(context, &announcement).await? else {
+        return Ok(());
+    };
+    let node_id = announcement.node_addr.node_id;
+    let iroh = context.get_or_try_init_peer_channel().await?;
+    if iroh.lan_peers.lock().insert(node_id, contact_id).is_none() {
+        info!(
+            context,
+            "Discovered {contact_id} on the local network as node {node_id}."
+        );
+    }
+
+    let queued = context
+        .sql
+        .query_map_vec(
+            "SELECT rfc724_mid, recipients, mime FROM smtp ORDER BY id",
+            (),
+            |row| {
+                let rfc724_mid: String = row.get(0)?;
+                let recipients: String = row.get(1)?;
+                let mime: String = row.get(2)?;
+                Ok((rfc724_mid, recipients, mime))
+            },
+        )
+        .await?;
+    delivered.retain(|(_, mid)| queued.iter().any(|(rfc724_mid, ..)| rfc724_mid == mid));
+    for (rfc724_mid, recipients, mime) in queued {
+        if !recipients.split(' ').any(|r| addr_cmp(r, &addr))
+            || delivered.contains(&(node_id, rfc724_mid.clone()))
+        {
+            continue;
+        }
+        let endpoint = iroh.router.endpoint();
+        let connection = endpoint
+            .connect(announcement.node_addr.clone(), LAN_MSG_ALPN)
+            .await?;
+        let (mut send_stream, mut recv_stream) = connection.open_bi().await?;
+        send_stream.write_all(mime.as_bytes()).await?;
+        send_stream.finish()?;
+        let response = recv_stream.read_to_end(0).await?;
+        connection.close(0u32.into(), b"done");
+        ensure!(response.is_empty(), "Unexpected response");
+        context
+            .bandwidth
+            .get(ConnectionType::Iroh)
+            .add_sent(mime.len());
+        info!(
+            context,
+            "Delivered message {rfc724_mid} to {contact_id} on the local network."
+        );
+        delivered.insert((node_id, rfc724_mid));
+    }
+    Ok(())
+}
+
+/// Returns the contact ID and address of the known contact
+/// that sent the announcement.
+///
+/// Returns `None` for own announcements and announcements of unknown devices,
+/// fails if the announcement is outdated or the signature is invalid.
+async fn verify_announcement(
+    context: &Context,
+    announcement: &LanAnnouncement,
+) -> Result<Option<(ContactId, String)>> {
+    let timestamp = announcement.timestamp;
+    ensure!(
+        time().abs_diff(timestamp) <= MAX_ANNOUNCEMENT_AGE.unsigned_abs(),
+        "Announcement timestamp {timestamp} is too far from the local time"
+    );
+    if announcement.id == blinded_id(self_fingerprint(context).await?, timestamp) {
+        return Ok(None);
+    }
+    let contacts = context
+        .sql
+        .query_map_vec(
+            "SELECT id, addr, fingerprint FROM contacts
+             WHERE fingerprint<>'' AND id>? AND blocked=0",
+            (ContactId::LAST_SPECIAL,),
+            |row| {
+                let contact_id: ContactId = row.get(0)?;
+                let addr: String = row.get(1)?;
+                let fingerprint: String = row.get(2)?;
+                Ok((contact_id, addr, fingerprint))
+            },
+        )
+        .await?;
+    let Some((contact_id, addr, fingerprint)) = contacts
+        .into_iter()
+        .find(|(_, _, fingerprint)| blinded_id(fingerprint, timestamp) == announcement.id)
+    else {
+        return Ok(None);
+    };
+    let public_key: Vec<u8> = context
+        .sql
+        .query_get_value(
+            "SELECT public_key FROM public_keys WHERE fingerprint=?",
+            (&fingerprint,),
+        )
+        .await?
+        .with_context(|| format!("No key of {contact_id}"))?;
+    let public_key = SignedPublicKey::from_slice(&public_key)?;
+    pgp::pk_verify_raw(
+        &public_key,
+        &signed_data(&announcement.id, timestamp, &announcement.node_addr.node_id),
+        &announcement.signature,
+    )
+    .with_context(|| format!("Invalid announcement signature of {contact_id}"))?;
+    Ok(Some((contact_id, addr)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    /// Returns an announcement of `node_addr` signed by `context`.
+    async fn signed_announcement(
+        context: &Context,
+        node_addr: NodeAddr,
+        timestamp: i64,
+    ) -> Result<LanAnnouncement> {
+        let id = blinded_id(self_fingerprint(context).await?, timestamp);
+        let signature = pgp::pk_sign_raw(
+            &load_self_secret_key(context).await?,
+            &signed_data(&id, timestamp, &node_addr.node_id),
+        )?;
+        Ok(LanAnnouncement {
+            id,
+            timestamp,
+            node_addr,
+            signature,
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ignore_unknown_announcement() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let node_addr = NodeAddr::new(iroh::SecretKey::from_bytes(&[1; 32]).public());
+        let mut delivered = HashSet::new();
+
+        for context in [alice, bob] {
+            let announcement = signed_announcement(context, node_addr.clone(), time()).await?;
+            let data = serde_json::to_vec(&announcement)?;
+            handle_announcement(alice, &data, &mut delivered).await?;
+        }
+        // Neither own nor unknown keys start peer channels.
+        assert!(alice.get_peer_channels().await.is_none());
+        assert!(
+            handle_announcement(alice, b"garbage", &mut delivered)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_verify_announcement() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        alice.add_or_lookup_contact_id(fiona).await;
+        let node_addr = NodeAddr::new(iroh::SecretKey::from_bytes(&[1; 32]).public());
+        let other_node_addr = NodeAddr::new(iroh::SecretKey::from_bytes(&[2; 32]).public());
+
+        let announcement = signed_announcement(bob, node_addr.clone(), time()).await?;
+        let (contact_id, addr) = verify_announcement(alice, &announcement)
+            .await?
+            .context("Bob is not recognized")?;
+        assert_eq!(contact_id, bob_id);
+        assert_eq!(addr, "bob@example.net");
+
+        // The identifier does not reveal the fingerprint and changes over time.
+        let later = signed_announcement(bob, node_addr.clone(), time() + 15).await?;
+        assert_ne!(later.id, announcement.id);
+        assert!(!announcement.id.contains(self_fingerprint(bob).await?));
+
+        // Another node cannot be announced with Bob's signature.
+        let mut forged = signed_announcement(bob, node_addr.clone(), time()).await?;
+        forged.node_addr = other_node_addr.clone();
+        assert!(verify_announcement(alice, &forged).await.is_err());
+
+        // Fiona cannot announce a node as Bob.
+        let mut forged = signed_announcement(fiona, other_node_addr, time()).await?;
+        forged.id = blinded_id(self_fingerprint(bob).await?, forged.timestamp);
+        assert!(verify_announcement(alice, &forged).await.is_err());
+
+        // Outdated announcements are rejected.
+        let outdated =
+            signed_announcement(bob, node_addr, time() - MAX_ANNOUNCEMENT_AGE - 1).await?;
+        assert!(verify_announcement(alice, &outdated).await.is_err());
+        Ok(())
+    }
+}
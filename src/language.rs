@@ -0,0 +1,336 @@
+//! # Language detection.
+//!
+//! Lightweight detection of the language of received message texts,
+//! see [`crate::message::Message::get_language()`].
+//!
+//! The language is guessed from the script of the text
+//! and, for Latin and Cyrillic scripts, from frequent words and letters.
+//! The detection is meant to decide whether to offer a translation,
+//! it is not reliable for short texts and returns `None` for them.
+
+use std::collections::HashMap;
+
+/// Minimum number of letters needed to detect the language.
+const MIN_LETTERS: usize = 12;
+
+/// Minimum number of frequent words or letters
+/// needed to tell apart languages using the same script.
+const MIN_SCORE: usize = 2;
+
+/// Frequent words of languages using the Latin script.
+/// Words frequent in several languages count for each of them.
+const LATIN_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "you", "that", "this", "with", "have", "was", "for", "not",
+            "what", "it", "of", "to", "be", "my", "your", "will",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "und", "ist", "ich", "nicht", "das", "der", "die", "du", "wir", "mit", "ein", "eine",
+            "auf", "auch", "sind", "es", "zu", "hast", "habe", "wie",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "les", "et", "est", "je", "vous", "pas", "une", "des", "du", "que", "qui",
+            "pour", "avec", "sur", "ce", "nous", "mais", "tu", "au",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "es", "que", "una", "por", "para", "con", "pero", "yo", "muy",
+            "como", "del", "esta", "está", "tengo", "hola", "gracias",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "gli", "e", "è", "che", "sono", "una", "per", "con", "non", "mi", "ho", "ma",
+            "della", "anche", "ciao", "grazie", "come", "questo", "sei",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "e", "é", "que", "não", "uma", "com", "para", "eu", "você", "mas", "muito",
+            "isso", "obrigado", "obrigada", "tudo", "bem", "do", "da",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "ik", "niet", "je", "dat", "van", "wat", "zijn", "met",
+            "ook", "maar", "bent", "heb", "hoe", "jij", "wij",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "är", "jag", "inte", "det", "att", "som", "en", "du", "med", "har", "på", "för",
+            "vi", "men", "hur", "tack", "hej", "mig", "kan",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "i", "jest", "nie", "się", "że", "na", "to", "ja", "ty", "jak", "co", "ale", "czy",
+            "mam", "dla", "tak", "cześć", "dzięki", "już", "być",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "ve",
+            "bir",
+            "bu",
+            "ben",
+            "sen",
+            "ne",
+            "için",
+            "çok",
+            "mi",
+            "da",
+            "de",
+            "var",
+            "yok",
+            "ama",
+            "gibi",
+            "merhaba",
+            "teşekkürler",
+            "nasılsın",
+            "evet",
+            "hayır",
+        ],
+    ),
+];
+
+/// Letters used by only one of the languages using the Latin script.
+const LATIN_LETTERS: &[(char, &str)] = &[
+    ('ß', "de"),
+    ('ñ', "es"),
+    ('¿', "es"),
+    ('¡', "es"),
+    ('ã', "pt"),
+    ('õ', "pt"),
+    ('ç', "pt"),
+    ('å', "sv"),
+    ('ł', "pl"),
+    ('ą', "pl"),
+    ('ę', "pl"),
+    ('ś', "pl"),
+    ('ż', "pl"),
+    ('ğ', "tr"),
+    ('ş', "tr"),
+    ('ı', "tr"),
+];
+
+/// Frequent words of languages using the Cyrillic script.
+const CYRILLIC_WORDS: &[(&str, &[&str])] = &[
+    (
+        "ru",
+        &[
+            "и",
+            "не",
+            "что",
+            "я",
+            "ты",
+            "это",
+            "как",
+            "он",
+            "мы",
+            "вы",
+            "есть",
+            "привет",
+            "спасибо",
+            "да",
+            "нет",
+            "все",
+            "уже",
+            "так",
+            "был",
+            "меня",
+        ],
+    ),
+    (
+        "uk",
+        &[
+            "і",
+            "й",
+            "що",
+            "не",
+            "я",
+            "ти",
+            "це",
+            "як",
+            "ми",
+            "ви",
+            "є",
+            "привіт",
+            "дякую",
+            "так",
+            "ні",
+            "вже",
+            "був",
+            "мене",
+            "та",
+            "але",
+        ],
+    ),
+    (
+        "bg",
+        &[
+            "и",
+            "не",
+            "че",
+            "аз",
+            "ти",
+            "това",
+            "как",
+            "той",
+            "ние",
+            "вие",
+            "е",
+            "здравей",
+            "благодаря",
+            "да",
+            "са",
+            "вече",
+            "така",
+            "беше",
+            "мен",
+            "ще",
+        ],
+    ),
+];
+
+/// Letters used by only one of the languages using the Cyrillic script.
+const CYRILLIC_LETTERS: &[(char, &str)] = &[
+    ('ы', "ru"),
+    ('э', "ru"),
+    ('ё', "ru"),
+    ('і', "uk"),
+    ('ї', "uk"),
+    ('є', "uk"),
+    ('ґ', "uk"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Han,
+    Kana,
+    Hangul,
+    Thai,
+    Devanagari,
+    Armenian,
+    Georgian,
+}
+
+fn script(c: char) -> Option<Script> {
+    let script = match c {
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' => Script::Greek,
+        '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+        '\u{0530}'..='\u{058F}' => Script::Armenian,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => Script::Arabic,
+        '\u{0900}'..='\u{097F}' => Script::Devanagari,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{10A0}'..='\u{10FF}' => Script::Georgian,
+        '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => Script::Hangul,
+        '\u{3040}'..='\u{30FF}' => Script::Kana,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => Script::Han,
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// Returns the ISO 639-1 code of the language of `text`
+/// or `None` if the language cannot be detected.
+pub(crate) fn detect(text: &str) -> Option<&'static str> {
+    let mut scripts: HashMap<Script, usize> = HashMap::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        if let Some(script) = script(c) {
+            let count = scripts.entry(script).or_default();
+            *count = count.saturating_add(1);
+        }
+    }
+    if scripts.values().sum::<usize>() < MIN_LETTERS {
+        return None;
+    }
+    // Japanese texts usually contain Han characters, but Chinese texts never contain kana.
+    if scripts.contains_key(&Script::Kana) {
+        return Some("ja");
+    }
+    let (script, _) = scripts.into_iter().max_by_key(|(_, count)| *count)?;
+    match script {
+        Script::Latin => detect_by_words(text, LATIN_WORDS, LATIN_LETTERS),
+        Script::Cyrillic => detect_by_words(text, CYRILLIC_WORDS, CYRILLIC_LETTERS),
+        Script::Greek => Some("el"),
+        Script::Arabic => {
+            if text.chars().any(|c| matches!(c, 'پ' | 'چ' | 'ژ' | 'گ')) {
+                Some("fa")
+            } else {
+                Some("ar")
+            }
+        }
+        Script::Hebrew => Some("he"),
+        Script::Han => Some("zh"),
+        Script::Kana => Some("ja"),
+        Script::Hangul => Some("ko"),
+        Script::Thai => Some("th"),
+        Script::Devanagari => Some("hi"),
+        Script::Armenian => Some("hy"),
+        Script::Georgian => Some("ka"),
+    }
+}
+
+/// Scores the languages by the frequent `words` and distinctive `letters` found in `text`
+/// and returns the language with the highest score if it is unambiguous.
+fn detect_by_words(
+    text: &str,
+    words: &[(&'static str, &[&str])],
+    letters: &[(char, &'static str)],
+) -> Option<&'static str> {
+    let mut scores: HashMap<&'static str, usize> = HashMap::new();
+    let text = text.to_lowercase();
+    for word in text.split(|c: char| !c.is_alphabetic()) {
+        for (language, frequent_words) in words {
+            if frequent_words.contains(&word) {
+                let score = scores.entry(language).or_default();
+                *score = score.saturating_add(1);
+            }
+        }
+    }
+    for c in text.chars() {
+        for (letter, language) in letters {
+            if c == *letter {
+                let score = scores.entry(language).or_default();
+                *score = score.saturating_add(1);
+            }
+        }
+    }
+
+    let mut scores: Vec<_> = scores.into_iter().collect();
+    scores.sort_by(|(_, score1), (_, score2)| score2.cmp(score1));
+    match scores.as_slice() {
+        [(language, score)] if *score >= MIN_SCORE => Some(language),
+        [(language, score), (_, second), ..] if *score >= MIN_SCORE && score > second => {
+            Some(language)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod language_tests;
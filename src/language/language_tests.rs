@@ -0,0 +1,36 @@
+use super::*;
+
+#[test]
+fn test_detect() {
+    for (text, language) in [
+        ("Hello, how are you? Have you seen my keys?", "en"),
+        ("Hallo, wie geht es dir? Ich habe dich nicht gesehen.", "de"),
+        ("Bonjour, je ne sais pas si vous avez le temps.", "fr"),
+        ("Hola, ¿cómo estás? Tengo una pregunta para ti.", "es"),
+        ("Ciao, come stai? Non ho visto il messaggio.", "it"),
+        ("Olá, tudo bem? Eu não sei se você vem.", "pt"),
+        ("Hallo, hoe gaat het? Ik heb het niet gezien.", "nl"),
+        ("Hej, hur mår du? Jag har inte sett det.", "sv"),
+        ("Cześć, jak się masz? Nie wiem, czy już jest.", "pl"),
+        ("Merhaba, nasılsın? Bu çok güzel bir gün.", "tr"),
+        ("Привет, как дела? Я уже все сделал.", "ru"),
+        ("Привіт, як справи? Я вже все зробив, дякую.", "uk"),
+        ("Γεια σου, τι κάνεις σήμερα;", "el"),
+        ("مرحبا، كيف حالك اليوم؟", "ar"),
+        ("سلام، حال شما چطور است؟", "fa"),
+        ("שלום, מה שלומך היום?", "he"),
+        ("你好，你今天怎么样？我很好，谢谢。", "zh"),
+        ("こんにちは、今日はいい天気ですね。", "ja"),
+        ("안녕하세요, 오늘 어떻게 지내세요?", "ko"),
+    ] {
+        assert_eq!(detect(text), Some(language), "{text}");
+    }
+}
+
+#[test]
+fn test_detect_unknown() {
+    assert_eq!(detect(""), None);
+    assert_eq!(detect("ok"), None);
+    assert_eq!(detect("👍 😀 🎉"), None);
+    assert_eq!(detect("https://delta.chat 1234567890"), None);
+}
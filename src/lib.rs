@@ -55,9 +55,11 @@
 pub use events::*;
 
 mod aheader;
+pub mod archive;
 pub mod blob;
 pub mod calls;
 pub mod chat;
+pub mod chat_statistics;
 pub mod chatlist;
 pub mod config;
 mod configure;
@@ -65,17 +67,23 @@
 pub mod contact;
 pub mod context;
 mod decrypt;
+pub mod diagnostics;
 pub mod download;
 mod e2ee;
 pub mod ephemeral;
+pub mod filters;
+mod group_history;
 mod imap;
 pub mod imex;
 pub mod key;
+pub mod lan;
+mod language;
 pub mod location;
 pub mod login_param;
 pub mod message;
 mod mimefactory;
 pub mod mimeparser;
+pub mod moderation;
 mod param;
 #[cfg(not(feature = "internals"))]
 mod pgp;
@@ -88,12 +96,17 @@
 pub mod release;
 mod scheduler;
 pub mod securejoin;
+pub mod security_report;
+pub mod server_search;
+pub mod sieve;
 mod simplify;
 mod smtp;
+mod split_msg;
 pub mod stock_str;
 pub mod storage_usage;
 mod sync;
 mod token;
+pub mod transcript;
 mod transport;
 mod update_helper;
 pub mod webxdc;
@@ -102,8 +115,11 @@
 pub mod color;
 pub mod html;
 pub mod net;
+pub mod notifications;
 pub mod plaintext;
+pub mod proof;
 pub mod push;
+mod quiet_hours;
 mod stats;
 pub use stats::SecurejoinSource;
 pub use stats::SecurejoinUiPath;
@@ -114,6 +130,7 @@
 pub mod tools;
 
 pub mod accounts;
+mod applock;
 pub mod peer_channels;
 pub mod reaction;
 
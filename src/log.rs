@@ -3,6 +3,7 @@
 #![allow(missing_docs)]
 
 use crate::context::Context;
+use crate::events::ErrorCode;
 
 mod stream;
 
@@ -47,31 +48,49 @@ macro_rules! warn_macro {
 pub(crate) use warn_macro_mod::warn_macro as warn;
 
 macro_rules! error {
-    ($ctx:expr, $msg:expr) => {
-        error!($ctx, $msg,)
-    };
-    ($ctx:expr, $msg:expr, $($args:expr),* $(,)?) => {{
+    ($ctx:expr, code = $code:expr, $msg:expr $(, $args:expr)* $(,)?) => {{
         let formatted = format!($msg, $($args),*);
+        let code: $crate::ErrorCode = $code;
         ::tracing::event!(::tracing::Level::ERROR, account_id = $ctx.get_id(), "{}", &formatted);
-        $ctx.set_last_error(&formatted);
+        $ctx.set_last_error_with_code(code, &formatted);
         $ctx.emit_event($crate::EventType::Error(formatted));
     }};
+    ($ctx:expr, $msg:expr) => {
+        error!($ctx, $msg,)
+    };
+    ($ctx:expr, $msg:expr, $($args:expr),* $(,)?) => {
+        error!($ctx, code = $crate::ErrorCode::Other, $msg, $($args),*)
+    };
 }
 
 impl Context {
     /// Set last error string.
     /// Implemented as blocking as used from macros in different, not always async blocks.
     pub fn set_last_error(&self, error: &str) {
+        self.set_last_error_with_code(ErrorCode::Other, error);
+    }
+
+    /// Set last error string and its code.
+    pub(crate) fn set_last_error_with_code(&self, code: ErrorCode, error: &str) {
         let mut last_error = self.last_error.write();
-        *last_error = error.to_string();
+        *last_error = (code, error.to_string());
     }
 
     /// Get last error string.
     pub fn get_last_error(&self) -> String {
-        let last_error = &*self.last_error.read();
+        let (_code, last_error) = &*self.last_error.read();
         last_error.clone()
     }
 
+    /// Get code of the last error.
+    ///
+    /// The code belongs to the error returned by [`Context::get_last_error`]
+    /// and to the last [`EventType::Error`](crate::EventType::Error) emitted.
+    pub fn get_last_error_code(&self) -> ErrorCode {
+        let (code, _last_error) = &*self.last_error.read();
+        *code
+    }
+
     pub fn set_migration_error(&self, error: &str) {
         let mut migration_error = self.migration_error.write();
         *migration_error = Some(error.to_string());
@@ -154,6 +173,20 @@ async fn test_get_last_error() -> Result<()> {
         error!(t, "bar-error");
         error!(t, "baz-error");
         assert_eq!(t.get_last_error(), "baz-error");
+        assert_eq!(t.get_last_error_code(), ErrorCode::Other);
+
+        error!(
+            t,
+            code = ErrorCode::AuthFailed,
+            "Login for {} failed.",
+            "alice"
+        );
+        assert_eq!(t.get_last_error(), "Login for alice failed.");
+        assert_eq!(t.get_last_error_code(), ErrorCode::AuthFailed);
+
+        t.set_last_error("qux-error");
+        assert_eq!(t.get_last_error(), "qux-error");
+        assert_eq!(t.get_last_error_code(), ErrorCode::Other);
 
         Ok(())
     }
@@ -1,10 +1,11 @@
 //! # Messages and their identifiers.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 use std::str;
 
 use anyhow::{Context as _, Result, ensure, format_err};
+use base64::Engine as _;
 use deltachat_contact_tools::{VcardContact, parse_vcard};
 use deltachat_derive::{FromSql, ToSql};
 use humansize::BINARY;
@@ -13,8 +14,8 @@
 use serde::{Deserialize, Serialize};
 use tokio::{fs, io};
 
-use crate::blob::BlobObject;
-use crate::chat::{Chat, ChatId, ChatIdBlocked, ChatVisibility, send_msg};
+use crate::blob::{self, BlobObject};
+use crate::chat::{self, Chat, ChatId, ChatIdBlocked, ChatVisibility, send_msg};
 use crate::chatlist_events;
 use crate::config::Config;
 use crate::constants::{Blocked, Chattype, DC_CHAT_ID_TRASH, DC_MSG_ID_LAST_SPECIAL};
@@ -24,19 +25,21 @@
 use crate::download::DownloadState;
 use crate::ephemeral::{Timer as EphemeralTimer, start_ephemeral_timers_msgids};
 use crate::events::EventType;
-use crate::imap::markseen_on_imap_table;
+use crate::headerdef::HeaderDef;
+use crate::imap::markseen_many_on_imap_table;
 use crate::location;
 use crate::location::get_poi_location;
 use crate::log::warn;
 use crate::mimeparser::{SystemMessage, parse_message_id};
 use crate::param::{Param, Params};
 use crate::reaction::get_msg_reactions;
+use crate::sql;
 use crate::summary::Summary;
-use crate::sync::SyncData;
+use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::create_outgoing_rfc724_mid;
 use crate::tools::{
-    get_filebytes, get_filemeta, gm2local_offset, read_file, sanitize_filename, time,
-    timestamp_to_str,
+    buf_decompress, get_filebytes, get_filemeta, gm2local_offset, read_file, sanitize_filename,
+    time, timestamp_to_str,
 };
 
 /// Message ID, including reserved IDs.
@@ -101,6 +104,28 @@ pub async fn get_state(self, context: &Context) -> Result<MessageState> {
         Ok(result)
     }
 
+    /// Returns the hidden group member change messages
+    /// merged into this summarizing info message, oldest first.
+    ///
+    /// Returns an empty list if this is not a summary,
+    /// see [`Config::CoalesceMemberChanges`].
+    pub async fn get_coalesced_msgs(self, context: &Context) -> Result<Vec<MsgId>> {
+        context
+            .sql
+            .query_map_vec(
+                "SELECT c.msg_id FROM msgs_coalesced c
+                 INNER JOIN msgs m ON m.id=c.msg_id
+                 WHERE c.summary_msg_id=?
+                 ORDER BY m.timestamp, m.id",
+                (self,),
+                |row| {
+                    let msg_id: MsgId = row.get(0)?;
+                    Ok(msg_id)
+                },
+            )
+            .await
+    }
+
     pub(crate) async fn get_param(self, context: &Context) -> Result<Params> {
         let res: Option<String> = context
             .sql
@@ -165,9 +190,54 @@ pub(crate) async fn set_delivered(self, context: &Context) -> Result<bool> {
         if let Some(chat_id) = chat_id {
             chatlist_events::emit_chatlist_item_changed(context, chat_id);
         }
+        self.emit_broadcast_delivery_update(context).await?;
         Ok(true)
     }
 
+    /// Emits [`EventType::BroadcastDeliveryUpdate`]
+    /// if the message is sent to a broadcast channel.
+    pub(crate) async fn emit_broadcast_delivery_update(self, context: &Context) -> Result<()> {
+        let Some((chattype, state, read, total)) = context
+            .sql
+            .query_row_optional(
+                "SELECT c.type, m.state,
+                        (SELECT COUNT(*) FROM msgs_mdns WHERE msg_id=m.id),
+                        (SELECT COUNT(*) FROM chats_contacts
+                          WHERE chat_id=c.id AND contact_id!=?
+                            AND add_timestamp >= remove_timestamp)
+                 FROM msgs m
+                 INNER JOIN chats c ON c.id=m.chat_id
+                 WHERE m.id=?",
+                (ContactId::SELF, self),
+                |row| {
+                    let chattype: Chattype = row.get(0)?;
+                    let state: MessageState = row.get(1)?;
+                    let read: u32 = row.get(2)?;
+                    let total: u32 = row.get(3)?;
+                    Ok((chattype, state, read, total))
+                },
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+        if chattype != Chattype::OutBroadcast {
+            return Ok(());
+        }
+        let delivered = if state >= MessageState::OutDelivered {
+            total.max(read)
+        } else {
+            read
+        };
+        context.emit_event(EventType::BroadcastDeliveryUpdate {
+            msg_id: self,
+            delivered,
+            read,
+            total,
+        });
+        Ok(())
+    }
+
     /// Bad evil escape hatch.
     ///
     /// Avoid using this, eventually types should be cleaned up enough
@@ -210,6 +280,163 @@ pub async fn hop_info(self, context: &Context) -> Result<String> {
         Ok(hop_info)
     }
 
+    /// Returns the headers of a received message in their original order.
+    ///
+    /// For encrypted messages, the headers of the decrypted message are returned.
+    /// Like [`MsgId::get_raw_mime()`], this requires the full MIME to be retained
+    /// and returns `None` otherwise.
+    pub async fn get_headers(self, context: &Context) -> Result<Option<Vec<(String, String)>>> {
+        let Some(mime) = self.get_raw_mime(context).await? else {
+            return Ok(None);
+        };
+        let (headers, _) = mailparse::parse_headers(&mime)?;
+        let headers = headers
+            .iter()
+            .map(|header| (header.get_key(), header.get_value()))
+            .collect();
+        Ok(Some(headers))
+    }
+
+    /// Returns the full MIME of a received message as it was decrypted.
+    ///
+    /// The full MIME is retained for all messages received
+    /// while [`Config::SaveMimeHeaders`] is enabled,
+    /// otherwise only for messages having [`Message::has_html()`] set.
+    /// Returns `None` if the full MIME is not retained.
+    pub async fn get_raw_mime(self, context: &Context) -> Result<Option<Vec<u8>>> {
+        let (mime_headers, compressed) = context
+            .sql
+            .query_row_optional(
+                "SELECT mime_headers, mime_compressed FROM msgs WHERE id=?",
+                (self,),
+                |row| {
+                    let mime_headers = sql::row_get_vec(row, 0)?;
+                    let compressed: bool = row.get(1)?;
+                    Ok((mime_headers, compressed))
+                },
+            )
+            .await?
+            .with_context(|| format!("Message {self} not found"))?;
+        if mime_headers.is_empty() {
+            return Ok(None);
+        }
+        if compressed {
+            let mime = tokio::task::block_in_place(move || buf_decompress(&mime_headers))?;
+            return Ok(Some(mime));
+        }
+        Ok(Some(mime_headers))
+    }
+
+    /// Sets a local annotation of the message,
+    /// e.g. a ticket ID or a moderation verdict attached by a bot.
+    ///
+    /// Annotations are stored only locally and are never sent or synchronized.
+    /// Setting `value` to `None` removes the annotation.
+    pub async fn set_annotation(
+        self,
+        context: &Context,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Cannot annotate special message {self}");
+        ensure!(!key.is_empty(), "Annotation key must not be empty");
+        if let Some(value) = value {
+            context
+                .sql
+                .execute(
+                    "INSERT INTO msgs_annotations (msg_id, key, value) VALUES (?, ?, ?)
+                     ON CONFLICT (msg_id, key) DO UPDATE SET value=excluded.value",
+                    (self, key, value),
+                )
+                .await?;
+        } else {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM msgs_annotations WHERE msg_id=? AND key=?",
+                    (self, key),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns local annotations of the message set with [`MsgId::set_annotation`].
+    pub async fn get_annotations(self, context: &Context) -> Result<BTreeMap<String, String>> {
+        context
+            .sql
+            .query_map_collect(
+                "SELECT key, value FROM msgs_annotations WHERE msg_id=?",
+                (self,),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .await
+    }
+
+    /// Sets a reminder for the message, e.g. for a "Remind me later" option.
+    ///
+    /// At `timestamp`, [`EventType::MsgReminder`] is emitted
+    /// and an incoming message is marked as fresh again,
+    /// so that it is returned by [`Context::get_fresh_msgs`] and counted as unread.
+    /// The reminder is synchronized to other devices.
+    ///
+    /// Setting `timestamp` to 0 removes the reminder.
+    pub async fn set_reminder(self, context: &Context, timestamp: i64) -> Result<()> {
+        self.set_reminder_ex(context, Sync, timestamp).await
+    }
+
+    pub(crate) async fn set_reminder_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        timestamp: i64,
+    ) -> Result<()> {
+        ensure!(
+            !self.is_special(),
+            "Cannot set reminder for special message {self}"
+        );
+        if timestamp > 0 {
+            context
+                .sql
+                .execute(
+                    "INSERT INTO msgs_reminders (msg_id, timestamp) VALUES (?, ?)
+                     ON CONFLICT (msg_id) DO UPDATE SET timestamp=excluded.timestamp",
+                    (self, timestamp),
+                )
+                .await?;
+        } else {
+            context
+                .sql
+                .execute("DELETE FROM msgs_reminders WHERE msg_id=?", (self,))
+                .await?;
+        }
+        context.scheduler.interrupt_ephemeral_task().await;
+
+        if sync.into() {
+            let rfc724_mid = Message::load_from_db(context, self).await?.rfc724_mid;
+            context
+                .add_sync_item(SyncData::SetMsgReminder {
+                    msg: rfc724_mid,
+                    timestamp,
+                })
+                .await?;
+            context.scheduler.interrupt_smtp().await;
+        }
+        Ok(())
+    }
+
+    /// Returns the timestamp of the reminder set with [`MsgId::set_reminder`],
+    /// `None` if no reminder is set.
+    pub async fn get_reminder(self, context: &Context) -> Result<Option<i64>> {
+        context
+            .sql
+            .query_get_value(
+                "SELECT timestamp FROM msgs_reminders WHERE msg_id=?",
+                (self,),
+            )
+            .await
+    }
+
     /// Returns detailed message information in a multi-line text form.
     pub async fn get_info(self, context: &Context) -> Result<String> {
         let msg = Message::load_from_db(context, self).await?;
@@ -699,6 +926,21 @@ pub fn get_file(&self, context: &Context) -> Option<PathBuf> {
         self.param.get_file_path(context).unwrap_or(None)
     }
 
+    /// Returns the alternative text describing the attachment,
+    /// see [`Message::set_alt_text()`].
+    pub fn get_alt_text(&self) -> Option<&str> {
+        self.param.get(Param::AltText)
+    }
+
+    /// Returns why the attachment may be harmful to open, if it looks suspicious.
+    ///
+    /// Only set for received messages, UIs may show a warning before saving or opening the file.
+    pub fn get_attachment_warning(&self) -> Option<AttachmentWarning> {
+        self.param
+            .get_int(Param::AttachmentWarning)
+            .and_then(AttachmentWarning::from_i32)
+    }
+
     /// Returns vector of vcards if the file has a vCard attachment.
     pub async fn vcard_contacts(&self, context: &Context) -> Result<Vec<VcardContact>> {
         if self.viewtype != Viewtype::Vcard {
@@ -863,6 +1105,17 @@ pub fn get_sort_timestamp(&self) -> i64 {
         }
     }
 
+    /// Returns a key for ordering the message among other messages of the chat.
+    ///
+    /// Comparing the keys of two messages as strings gives the same order
+    /// as the list returned by [`crate::chat::get_chat_msgs()`],
+    /// even if the messages have the same sort timestamp.
+    /// UIs should use it instead of comparing timestamps and IDs themselves.
+    pub fn get_sort_key(&self) -> String {
+        let timestamp = u64::try_from(self.get_sort_timestamp()).unwrap_or_default();
+        format!("{timestamp:016x}{:08x}", self.id.to_u32())
+    }
+
     /// Returns the text of the message.
     ///
     /// Currently this includes `additional_text`, but this may change in future, when the UIs show
@@ -948,6 +1201,15 @@ pub fn is_bot(&self) -> bool {
         self.param.get_bool(Param::Bot).unwrap_or_default()
     }
 
+    /// Marks an outgoing message as auto-generated.
+    ///
+    /// The message is then sent with `Auto-Submitted: auto-generated` header
+    /// even if [`Config::Bot`] is not set,
+    /// so that receiving bots and auto-responders do not reply to it.
+    pub fn set_bot(&mut self, bot: bool) {
+        self.param.set_optional(Param::Bot, bot.then_some("1"));
+    }
+
     /// Return the ephemeral timer duration for a message.
     pub fn get_ephemeral_timer(&self) -> EphemeralTimer {
         self.ephemeral_timer
@@ -1034,6 +1296,35 @@ pub fn is_edited(&self) -> bool {
         self.param.get_bool(Param::IsEdited).unwrap_or_default()
     }
 
+    /// Returns true if the message was removed from the server
+    /// without our involvement and cannot be downloaded again.
+    ///
+    /// Messages are only marked so if [`Config::MarkExpungedMsgs`] is enabled.
+    pub fn is_removed_from_server(&self) -> bool {
+        self.param
+            .get_bool(Param::RemovedFromServer)
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the message was shared by another member
+    /// when we joined the group, see [`crate::chat::set_share_history()`].
+    ///
+    /// The original sender of such messages is not verified,
+    /// they are only as trustworthy as the member who shared them.
+    pub fn is_from_shared_history(&self) -> bool {
+        self.param
+            .get_bool(Param::FromSharedHistory)
+            .unwrap_or_default()
+    }
+
+    /// Returns the ISO 639-1 code of the language of a received message text, e.g. `"de"`,
+    /// or `None` if the language could not be detected or the message was sent by us.
+    ///
+    /// UIs may offer a translation if the language differs from the UI language.
+    pub fn get_language(&self) -> Option<&str> {
+        self.param.get(Param::Language)
+    }
+
     /// Returns true if the message is an informational message.
     pub fn is_info(&self) -> bool {
         let cmd = self.param.get_cmd();
@@ -1092,6 +1383,8 @@ pub async fn get_info_contact_id(&self, context: &Context) -> Result<Option<Cont
             | SystemMessage::IrohNodeAddr
             | SystemMessage::CallAccepted
             | SystemMessage::CallEnded
+            | SystemMessage::GroupHistory
+            | SystemMessage::AbuseReport
             | SystemMessage::Unknown => Ok(None),
         }
     }
@@ -1123,6 +1416,8 @@ pub fn set_subject(&mut self, subject: String) {
     ///
     /// In order to deduplicate files that contain the same data,
     /// the file will be named `<hash>.<extension>`, e.g. `ce940175885d7b78f7b7e9f1396611f.jpg`.
+    /// If the file is a blob that already has such a name, e.g. from a message being forwarded,
+    /// it is used as is without hashing it again, see [`BlobObject::from_trusted_name`].
     ///
     /// NOTE:
     /// - This function will rename the file. To get the new file path, call `get_file()`.
@@ -1142,8 +1437,20 @@ pub fn set_file_and_deduplicate(
                 .unwrap_or_else(|| "unknown_file".to_string())
         };
 
-        let blob = BlobObject::create_and_deduplicate(context, file, Path::new(&name))?;
+        // Blobs created by core before, e.g. when resending or forwarding,
+        // already have the right name and do not need to be hashed again.
+        let trusted_blob = file
+            .strip_prefix(context.get_blobdir())
+            .ok()
+            .and_then(|rel_path| rel_path.to_str())
+            .and_then(|blob_name| BlobObject::from_trusted_name(context, blob_name).ok())
+            .filter(|blob| blob.is_hash_named_for(Path::new(&name)));
+        let blob = match trusted_blob {
+            Some(blob) => blob,
+            None => BlobObject::create_and_deduplicate(context, file, Path::new(&name))?,
+        };
         self.param.set(Param::File, blob.as_name());
+        self.param.remove(Param::ImageRecodedWh);
 
         self.param.set(Param::Filename, name);
         self.param.set_optional(Param::MimeType, filemime);
@@ -1167,6 +1474,7 @@ pub fn set_file_from_bytes(
         let blob = BlobObject::create_and_deduplicate_from_bytes(context, data, name)?;
         self.param.set(Param::Filename, name);
         self.param.set(Param::File, blob.as_name());
+        self.param.remove(Param::ImageRecodedWh);
         self.param.set_optional(Param::MimeType, filemime);
 
         Ok(())
@@ -1204,6 +1512,50 @@ pub fn set_override_sender_name(&mut self, name: Option<String>) {
             .set_optional(Param::OverrideSenderDisplayname, name);
     }
 
+    /// Sets the alternative text describing the attachment, e.g. the content of an image,
+    /// for users of screen readers.
+    ///
+    /// The text is sent along with the attachment and shown by other clients as well.
+    pub fn set_alt_text(&mut self, text: Option<String>) {
+        self.param.set_optional(Param::AltText, text);
+    }
+
+    /// Adds a custom header to the outgoing message, e.g. for gateway bots
+    /// bridging messages from other networks.
+    ///
+    /// Only `X-` headers not interpreted by the core are allowed.
+    /// Setting a header again replaces the previous value.
+    /// In encrypted messages, the headers are encrypted as well.
+    /// Received headers can be read with [`MsgId::get_headers()`].
+    pub fn set_extra_header(&mut self, name: &str, value: &str) -> Result<()> {
+        ensure!(
+            is_allowed_extra_header(name),
+            "Header {name:?} is not allowed"
+        );
+        ensure!(
+            !value.contains(['\r', '\n']),
+            "Header value must be a single line"
+        );
+        ensure!(
+            value.len() <= MAX_EXTRA_HEADER_LEN,
+            "Header value is too long"
+        );
+        let mut headers = self.get_extra_headers();
+        headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        headers.push((name.to_string(), value.to_string()));
+        self.param
+            .set(Param::ExtraHeaders, serde_json::to_string(&headers)?);
+        Ok(())
+    }
+
+    /// Returns the custom headers set with [`Message::set_extra_header()`].
+    pub fn get_extra_headers(&self) -> Vec<(String, String)> {
+        self.param
+            .get(Param::ExtraHeaders)
+            .and_then(|headers| serde_json::from_str(headers).ok())
+            .unwrap_or_default()
+    }
+
     /// Sets the dimensions of associated image or video file.
     pub fn set_dimension(&mut self, width: i32, height: i32) {
         self.param.set_int(Param::Width, width);
@@ -1246,6 +1598,10 @@ pub async fn latefiling_mediasize(
     /// be sent encrypted. If it should, but the message is unencrypted, `text_str` is replaced with
     /// "...".
     pub fn set_quote_text(&mut self, text: Option<(String, bool)>) {
+        self.param.remove(Param::QuoteAuthor);
+        self.param.remove(Param::QuoteTimestamp);
+        self.param.remove(Param::QuoteSnippet);
+        self.param.remove(Param::QuoteThumbnail);
         let Some((text, protect)) = text else {
             self.param.remove(Param::Quote);
             self.param.remove(Param::ProtectQuote);
@@ -1295,6 +1651,7 @@ pub async fn set_quote(&mut self, context: &Context, quote: Option<&Message>) ->
                     .get_bool(Param::GuaranteeE2ee)
                     .unwrap_or_default(),
             )));
+            set_quote_metadata(context, &mut self.param, quote).await?;
         } else {
             self.in_reply_to = None;
             self.set_quote_text(None);
@@ -1308,6 +1665,41 @@ pub fn quoted_text(&self) -> Option<String> {
         self.param.get(Param::Quote).map(|s| s.to_string())
     }
 
+    /// Returns the display name of the author of the quoted message.
+    ///
+    /// This is stored when quoting
+    /// and is available even if the quoted message is deleted.
+    pub fn get_quote_author(&self) -> Option<String> {
+        self.param.get(Param::QuoteAuthor).map(|s| s.to_string())
+    }
+
+    /// Returns the timestamp of the quoted message.
+    ///
+    /// This is stored when quoting
+    /// and is available even if the quoted message is deleted.
+    pub fn get_quote_timestamp(&self) -> Option<i64> {
+        self.param.get_i64(Param::QuoteTimestamp)
+    }
+
+    /// Returns the summary of the quoted message, such as "📷 Image – caption".
+    ///
+    /// This is stored when quoting
+    /// and is available even if the quoted message is deleted.
+    pub fn get_quote_snippet(&self) -> Option<String> {
+        self.param.get(Param::QuoteSnippet).map(|s| s.to_string())
+    }
+
+    /// Returns a tiny JPEG preview of the quoted image.
+    ///
+    /// This is stored when quoting
+    /// and is available even if the quoted message is deleted.
+    pub fn get_quote_thumbnail(&self) -> Option<Vec<u8>> {
+        let thumbnail = self.param.get(Param::QuoteThumbnail)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(thumbnail)
+            .ok()
+    }
+
     /// Returns quoted message, if any.
     pub async fn quoted_message(&self, context: &Context) -> Result<Option<Message>> {
         if self.param.get(Param::Quote).is_some() && !self.is_forwarded() {
@@ -1452,6 +1844,12 @@ pub enum MessageState {
     /// the OutFailed state if we get such a hint from the server.
     OutDelivered = 26,
 
+    /// Outgoing message reached the server of at least one recipient,
+    /// confirmed by a delivery status notification from that server.
+    /// Servers send these only if they support it,
+    /// so most messages stay in the OutDelivered state until they are read.
+    OutServerRcvd = 27,
+
     /// Outgoing message read by the recipient (two checkmarks; this
     /// requires goodwill on the receiver's side). Not used in the db for new messages.
     OutMdnRcvd = 28,
@@ -1471,6 +1869,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
                 Self::OutPending => "Pending",
                 Self::OutFailed => "Failed",
                 Self::OutDelivered => "Delivered",
+                Self::OutServerRcvd => "Received by server",
                 Self::OutMdnRcvd => "Read",
             }
         )
@@ -1483,7 +1882,7 @@ pub fn can_fail(self) -> bool {
         use MessageState::*;
         matches!(
             self,
-            OutPending | OutDelivered | OutMdnRcvd // OutMdnRcvd can still fail because it could be a group message and only some recipients failed.
+            OutPending | OutDelivered | OutServerRcvd | OutMdnRcvd // OutMdnRcvd can still fail because it could be a group message and only some recipients failed.
         )
     }
 
@@ -1492,13 +1891,17 @@ pub fn is_outgoing(self) -> bool {
         use MessageState::*;
         matches!(
             self,
-            OutDraft | OutPending | OutFailed | OutDelivered | OutMdnRcvd
+            OutDraft | OutPending | OutFailed | OutDelivered | OutServerRcvd | OutMdnRcvd
         )
     }
 
     /// Returns adjusted message state if the message has MDNs.
     pub(crate) fn with_mdns(self, has_mdns: bool) -> Self {
-        if self == MessageState::OutDelivered && has_mdns {
+        if matches!(
+            self,
+            MessageState::OutDelivered | MessageState::OutServerRcvd
+        ) && has_mdns
+        {
             return MessageState::OutMdnRcvd;
         }
         self
@@ -1534,6 +1937,26 @@ pub async fn get_msg_read_receipt_count(context: &Context, msg_id: MsgId) -> Res
         .await
 }
 
+/// Maximum length of a value set with [`Message::set_extra_header()`] in bytes.
+const MAX_EXTRA_HEADER_LEN: usize = 900;
+
+/// Returns true if `name` may be set with [`Message::set_extra_header()`].
+fn is_allowed_extra_header(name: &str) -> bool {
+    let reserved = [
+        HeaderDef::XMicrosoftOriginalMessageId,
+        HeaderDef::XMozillaDraftInfo,
+    ];
+    name.len() > 2
+        && name.len() <= 64
+        && name
+            .get(..2)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("x-"))
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !reserved
+            .iter()
+            .any(|header| header.get_headername().eq_ignore_ascii_case(name))
+}
+
 pub(crate) fn guess_msgtype_from_suffix(msg: &Message) -> Option<(Viewtype, &'static str)> {
     msg.param
         .get(Param::Filename)
@@ -1635,6 +2058,69 @@ pub(crate) fn guess_msgtype_from_path_suffix(path: &Path) -> Option<(Viewtype, &
     Some(info)
 }
 
+/// Guesses the viewtype, mimetype and file extension of an image from its magic bytes.
+///
+/// This is used to correct attachments sent with a wrong or generic `Content-Type`,
+/// so only formats shown inline by all UIs are recognized.
+pub(crate) fn guess_msgtype_from_content(
+    data: &[u8],
+) -> Option<(Viewtype, &'static str, &'static str)> {
+    if data.starts_with(b"\xFF\xD8\xFF") {
+        Some((Viewtype::Image, "image/jpeg", "jpg"))
+    } else if data.starts_with(b"\x89PNG\r\n\x1A\n") {
+        Some((Viewtype::Image, "image/png", "png"))
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some((Viewtype::Gif, "image/gif", "gif"))
+    } else {
+        None
+    }
+}
+
+/// Reason why a received attachment may be harmful to open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum AttachmentWarning {
+    /// The file extension denotes an executable or a script, e.g. `.exe` or `.js`.
+    DangerousExtension = 1,
+
+    /// A dangerous extension is hidden behind a harmless one, e.g. `invoice.pdf.exe`.
+    DoubleExtension = 2,
+
+    /// The `Content-Type` of the attachment does not match its file extension.
+    ContentTypeMismatch = 3,
+}
+
+/// File extensions of executables and scripts run by common operating systems.
+const DANGEROUS_EXTENSIONS: &[&str] = &[
+    "apk", "app", "bat", "cmd", "com", "cpl", "dll", "exe", "hta", "jar", "js", "jse", "lnk",
+    "msi", "pif", "ps1", "reg", "scr", "sh", "vbe", "vbs", "wsf",
+];
+
+/// Checks the name and the `Content-Type` of a received attachment for signs of malware.
+pub(crate) fn check_attachment(
+    filename: &str,
+    mimetype: Option<&str>,
+) -> Option<AttachmentWarning> {
+    let path = Path::new(filename.trim_end_matches(['.', ' ']));
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if DANGEROUS_EXTENSIONS.contains(&extension.as_str()) {
+        let inner = Path::new(path.file_stem()?);
+        if guess_msgtype_from_path_suffix(inner).is_some() {
+            return Some(AttachmentWarning::DoubleExtension);
+        }
+        return Some(AttachmentWarning::DangerousExtension);
+    }
+
+    // Only compare the top-level types, subtypes differ a lot between mail clients.
+    let declared = mimetype?.split('/').next()?.trim().to_lowercase();
+    let (_, expected) = guess_msgtype_from_path_suffix(path)?;
+    let expected = expected.split('/').next()?;
+    if declared != expected && declared != "application" && declared != "multipart" {
+        return Some(AttachmentWarning::ContentTypeMismatch);
+    }
+    None
+}
+
 /// Delete a single message from the database, including references in other tables.
 /// This may be called in batches; the final events are emitted in delete_msgs_locally_done() then.
 pub(crate) async fn delete_msg_locally(context: &Context, msg: &Message) -> Result<()> {
@@ -1741,6 +2227,14 @@ pub async fn delete_msgs_ex(
                 "DELETE FROM available_post_msgs WHERE rfc724_mid=?",
                 (&msg.rfc724_mid,),
             )?;
+            if msg.state == MessageState::OutDraft {
+                trans.execute("DELETE FROM draft_history WHERE chat_id=?", (msg.chat_id,))?;
+            } else {
+                trans.execute(
+                    "DELETE FROM draft_history WHERE chat_id=? AND txt=?",
+                    (msg.chat_id, &msg.text),
+                )?;
+            }
             Ok(())
         };
         if let Err(e) = context.sql.transaction(update_db).await {
@@ -1861,7 +2355,12 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
     }
 
     let mut updated_chat_ids = BTreeSet::new();
+    let mut last_read_msg_ids = BTreeMap::new();
     let mut archived_chats_maybe_noticed = false;
+    // Interrupting the IMAP and SMTP loops is delayed until all messages are processed,
+    // so that `\Seen` flags are stored in batches and MDNs to the same contact are aggregated.
+    let mut seen_rfc724_mids = Vec::new();
+    let mut mdns_queued = false;
     for (
         (
             id,
@@ -1881,8 +2380,6 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
             update_msg_state(context, id, MessageState::InSeen).await?;
             info!(context, "Seen message {}.", id);
 
-            markseen_on_imap_table(context, &curr_rfc724_mid).await?;
-
             // Read receipts for system messages are never sent to contacts.
             // These messages have no place to display received read receipt
             // anyway. And since their text is locally generated,
@@ -1927,22 +2424,36 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
                     .sql
                     .execute(
                         "INSERT INTO smtp_mdns (msg_id, from_id, rfc724_mid) VALUES(?, ?, ?)",
-                        (id, to_id, curr_rfc724_mid),
+                        (id, to_id, &curr_rfc724_mid),
                     )
                     .await
                     .context("failed to insert into smtp_mdns")?;
-                context.scheduler.interrupt_smtp().await;
+                mdns_queued = true;
             }
 
             if !curr_hidden {
                 updated_chat_ids.insert(curr_chat_id);
             }
+            seen_rfc724_mids.push(curr_rfc724_mid);
+        }
+        if !curr_hidden {
+            let last_read_msg_id = last_read_msg_ids.entry(curr_chat_id).or_insert(id);
+            *last_read_msg_id = std::cmp::max(*last_read_msg_id, id);
         }
         archived_chats_maybe_noticed |= curr_state == MessageState::InFresh
             && !curr_hidden
             && curr_visibility == ChatVisibility::Archived;
     }
 
+    let seen_rfc724_mids: Vec<&str> = seen_rfc724_mids.iter().map(String::as_str).collect();
+    markseen_many_on_imap_table(context, &seen_rfc724_mids).await?;
+    if mdns_queued {
+        context.scheduler.interrupt_smtp().await;
+    }
+
+    for (chat_id, msg_id) in last_read_msg_ids {
+        chat::set_last_read_msg_ex(context, Sync, chat_id, msg_id).await?;
+    }
     for updated_chat_id in updated_chat_ids {
         context.emit_event(EventType::MsgsNoticed(updated_chat_id));
         chatlist_events::emit_chatlist_item_changed(context, updated_chat_id);
@@ -1981,6 +2492,59 @@ pub async fn get_existing_msg_ids(context: &Context, ids: &[MsgId]) -> Result<Ve
     Ok(res)
 }
 
+/// Returns the timestamp of the next reminder set with [`MsgId::set_reminder`].
+pub(crate) async fn next_reminder_timestamp(context: &Context) -> Result<Option<i64>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT min(timestamp) FROM msgs_reminders HAVING count(*) > 0",
+            (),
+        )
+        .await
+}
+
+/// Emits [`EventType::MsgReminder`] for messages with due reminders
+/// and marks incoming ones as fresh again.
+pub(crate) async fn remind_due_msgs(context: &Context, now: i64) -> Result<()> {
+    let msg_ids = context
+        .sql
+        .query_map_vec(
+            "SELECT msg_id FROM msgs_reminders WHERE timestamp<=?",
+            (now,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                Ok(msg_id)
+            },
+        )
+        .await?;
+    if msg_ids.is_empty() {
+        return Ok(());
+    }
+    context
+        .sql
+        .execute("DELETE FROM msgs_reminders WHERE timestamp<=?", (now,))
+        .await?;
+
+    for msg_id in msg_ids {
+        let Some(msg) = Message::load_from_db_optional(context, msg_id).await? else {
+            continue;
+        };
+        if msg.chat_id.is_trash() {
+            continue;
+        }
+        if matches!(msg.state, MessageState::InNoticed | MessageState::InSeen) {
+            update_msg_state(context, msg_id, MessageState::InFresh).await?;
+        }
+        context.emit_event(EventType::MsgReminder {
+            chat_id: msg.chat_id,
+            msg_id,
+        });
+        context.emit_msgs_changed(msg.chat_id, msg_id);
+        chatlist_events::emit_chatlist_item_changed(context, msg.chat_id);
+    }
+    Ok(())
+}
+
 pub(crate) async fn update_msg_state(
     context: &Context,
     msg_id: MsgId,
@@ -2145,6 +2709,40 @@ pub async fn estimate_deletion_cnt(
     Ok(cnt)
 }
 
+/// Stores metadata of the quoted message `quote` in `param`,
+/// so the quote can be rendered even after `quote` is deleted.
+pub(crate) async fn set_quote_metadata(
+    context: &Context,
+    param: &mut Params,
+    quote: &Message,
+) -> Result<()> {
+    let author = match quote.get_override_sender_name() {
+        Some(name) => name,
+        None => Contact::get_by_id(context, quote.from_id)
+            .await?
+            .get_display_name()
+            .to_string(),
+    };
+    param.set(Param::QuoteAuthor, author);
+    param.set_i64(Param::QuoteTimestamp, quote.get_timestamp());
+    let snippet = quote.get_summary_text(context).await;
+    param.set(Param::QuoteSnippet, crate::tools::truncate(&snippet, 160));
+    param.remove(Param::QuoteThumbnail);
+    if matches!(
+        quote.viewtype,
+        Viewtype::Image | Viewtype::Gif | Viewtype::Sticker
+    ) && let Some(path) = quote.get_file(context)
+    {
+        match tokio::task::block_in_place(|| blob::create_micro_thumbnail(&path)) {
+            Ok(thumbnail) => {
+                param.set(Param::QuoteThumbnail, thumbnail);
+            }
+            Err(err) => warn!(context, "Failed to create quote thumbnail: {err:#}."),
+        }
+    }
+    Ok(())
+}
+
 /// See [`rfc724_mid_exists_ex()`].
 pub(crate) async fn rfc724_mid_exists(
     context: &Context,
@@ -2232,8 +2830,19 @@ pub(crate) async fn get_by_rfc724_mids(
         let Some(msg_id) = rfc724_mid_exists(context, id).await? else {
             continue;
         };
-        let Some(msg) = Message::load_from_db_optional(context, msg_id).await? else {
-            continue;
+        let msg = match Message::load_from_db_optional(context, msg_id).await? {
+            Some(msg) => msg,
+            None => {
+                // The message may have been trashed as a duplicate of another message
+                // received with a different Message-ID.
+                let Some(msg_id) = get_duplicate_of(context, id).await? else {
+                    continue;
+                };
+                let Some(msg) = Message::load_from_db_optional(context, msg_id).await? else {
+                    continue;
+                };
+                msg
+            }
         };
         if msg.download_state == DownloadState::Done {
             return Ok(Some(msg));
@@ -2243,6 +2852,19 @@ pub(crate) async fn get_by_rfc724_mids(
     Ok(latest)
 }
 
+/// Returns the ID of the message which the message with the given `rfc724_mid`
+/// was detected to be a duplicate of on receipt.
+pub(crate) async fn get_duplicate_of(context: &Context, rfc724_mid: &str) -> Result<Option<MsgId>> {
+    let rfc724_mid = rfc724_mid.trim_start_matches('<').trim_end_matches('>');
+    context
+        .sql
+        .query_get_value(
+            "SELECT msg_id FROM msgs_duplicates WHERE rfc724_mid=?",
+            (rfc724_mid,),
+        )
+        .await
+}
+
 /// Returns the 1st part of summary text (i.e. before the dash if any) for a valid DeltaChat vCard.
 pub(crate) fn get_vcard_summary(vcard: &[u8]) -> Option<String> {
     let vcard = str::from_utf8(vcard).ok()?;
@@ -6,6 +6,7 @@
 use crate::config::Config;
 use crate::reaction::send_reaction;
 use crate::receive_imf::receive_imf;
+use crate::sql::housekeeping;
 use crate::test_utils;
 use crate::test_utils::{E2EE_INFO_MSGS, TestContext, TestContextManager};
 
@@ -401,6 +402,60 @@ async fn test_pre_and_post_msgs_deleted_ex(reorder: bool) -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_markseen_msgs_many() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let alice_chat_id = alice.create_chat(bob).await.id;
+
+    let mut msg_ids = Vec::new();
+    for uid in 1..=3 {
+        let sent = alice
+            .send_text(alice_chat_id, &format!("Message {uid}"))
+            .await;
+        let msg = bob.recv_msg(&sent).await;
+        bob.sql
+            .execute(
+                "INSERT INTO imap (transport_id, rfc724_mid, folder, uid, target, uidvalidity) VALUES (1, ?, 'INBOX', ?, 'INBOX', 12345)",
+                (&msg.rfc724_mid, uid),
+            )
+            .await?;
+        msg_ids.push(msg.id);
+    }
+    bob.get_chat(alice).await.id.accept(bob).await?;
+
+    markseen_msgs(bob, msg_ids.clone()).await?;
+    for msg_id in &msg_ids {
+        assert_eq!(msg_id.get_state(bob).await?, MessageState::InSeen);
+    }
+    assert_eq!(
+        bob.sql
+            .count("SELECT COUNT(*) FROM imap_markseen", ())
+            .await?,
+        3
+    );
+    assert_eq!(
+        bob.sql.count("SELECT COUNT(*) FROM smtp_mdns", ()).await?,
+        3
+    );
+
+    // Marking messages as seen twice does not schedule anything new.
+    markseen_msgs(bob, msg_ids).await?;
+    assert_eq!(
+        bob.sql
+            .count("SELECT COUNT(*) FROM imap_markseen", ())
+            .await?,
+        3
+    );
+    assert_eq!(
+        bob.sql.count("SELECT COUNT(*) FROM smtp_mdns", ()).await?,
+        3
+    );
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_get_state() -> Result<()> {
     let alice = TestContext::new_alice().await;
@@ -524,6 +579,31 @@ async fn test_is_bot() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_set_bot() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat_id(bob).await;
+
+    let mut msg = Message::new_text("Reminder: meeting at 10:00".to_string());
+    msg.set_bot(true);
+    assert!(msg.is_bot());
+    let sent = alice.send_msg(chat_id, &mut msg).await;
+    let msg = bob.recv_msg(&sent).await;
+    assert!(msg.is_bot());
+
+    let sent = alice.send_text(chat_id, "I will be late").await;
+    let msg = bob.recv_msg(&sent).await;
+    assert!(!msg.is_bot());
+
+    let mut msg = Message::new_text("Not automated".to_string());
+    msg.set_bot(true);
+    msg.set_bot(false);
+    assert!(!msg.is_bot());
+    Ok(())
+}
+
 #[test]
 fn test_viewtype_derive_display_works_as_expected() {
     assert_eq!(format!("{}", Viewtype::Audio), "Audio");
@@ -567,6 +647,59 @@ async fn test_send_quotes() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_quote_metadata() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let alice_chat_id = alice.create_chat(bob).await.id;
+
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_text("Look!".to_string());
+    msg.set_file_from_bytes(alice, "a.png", test_utils::AVATAR_64x64_BYTES, None)?;
+    let sent = alice.send_msg(alice_chat_id, &mut msg).await;
+    let bob_img = bob.recv_msg(&sent).await;
+    bob_img.chat_id.accept(bob).await?;
+
+    let mut reply = Message::new_text("Nice".to_string());
+    reply.set_quote(bob, Some(&bob_img)).await?;
+    assert_eq!(
+        reply.get_quote_author().as_deref(),
+        Some("alice@example.org")
+    );
+    assert_eq!(reply.get_quote_timestamp(), Some(bob_img.get_timestamp()));
+    assert_eq!(reply.get_quote_snippet().as_deref(), Some("📷 Look!"));
+    let thumbnail = reply.get_quote_thumbnail().unwrap();
+    let img = image::load_from_memory(&thumbnail)?;
+    assert!(img.width() <= 32 && img.height() <= 32);
+    let sent = bob.send_msg(bob_img.chat_id, &mut reply).await;
+
+    // The receiver stores the metadata of its own copy of the quoted message.
+    let alice_reply = alice.recv_msg(&sent).await;
+    assert_eq!(alice_reply.get_quote_author().as_deref(), Some("Me"));
+    assert_eq!(alice_reply.get_quote_snippet().as_deref(), Some("📷 Look!"));
+    assert!(alice_reply.get_quote_thumbnail().is_some());
+
+    // Metadata is still available after the quoted message is deleted.
+    delete_msgs(bob, &[bob_img.id]).await?;
+    let bob_reply = Message::load_from_db(bob, sent.sender_msg_id).await?;
+    assert!(bob_reply.quoted_message(bob).await?.is_none());
+    assert_eq!(bob_reply.quoted_text().as_deref(), Some("Look!"));
+    assert_eq!(
+        bob_reply.get_quote_author().as_deref(),
+        Some("alice@example.org")
+    );
+    assert_eq!(bob_reply.get_quote_snippet().as_deref(), Some("📷 Look!"));
+    assert_eq!(bob_reply.get_quote_thumbnail(), Some(thumbnail));
+
+    // Removing the quote removes the metadata.
+    reply.set_quote(bob, None).await?;
+    assert!(reply.get_quote_author().is_none());
+    assert!(reply.get_quote_thumbnail().is_none());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_get_message_summary_text() -> Result<()> {
     let t = TestContext::new_alice().await;
@@ -667,6 +800,174 @@ async fn test_delete_msgs_sync() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_annotations() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat_id(bob).await;
+    let sent = alice.send_text(chat_id, "hi").await;
+    let msg_id = sent.sender_msg_id;
+    assert!(msg_id.get_annotations(alice).await?.is_empty());
+
+    msg_id.set_annotation(alice, "ticket", Some("42")).await?;
+    msg_id
+        .set_annotation(alice, "verdict", Some("spam"))
+        .await?;
+    msg_id.set_annotation(alice, "verdict", Some("ham")).await?;
+    assert_eq!(
+        msg_id.get_annotations(alice).await?,
+        BTreeMap::from([
+            ("ticket".to_string(), "42".to_string()),
+            ("verdict".to_string(), "ham".to_string())
+        ])
+    );
+    assert!(msg_id.set_annotation(alice, "", Some("x")).await.is_err());
+
+    msg_id.set_annotation(alice, "ticket", None).await?;
+    assert_eq!(msg_id.get_annotations(alice).await?.len(), 1);
+
+    // Annotations are never sent.
+    let msg = bob.recv_msg(&sent).await;
+    assert!(msg.id.get_annotations(bob).await?.is_empty());
+
+    delete_msgs(alice, &[msg_id]).await?;
+    housekeeping(alice).await?;
+    assert!(msg_id.get_annotations(alice).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_raw_mime() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat_id(bob).await;
+
+    let msg = bob.recv_msg(&alice.send_text(chat_id, "hi").await).await;
+    assert_eq!(msg.id.get_raw_mime(bob).await?, None);
+
+    bob.set_config_bool(Config::SaveMimeHeaders, true).await?;
+    let msg = bob
+        .recv_msg(&alice.send_text(chat_id, "bridge me").await)
+        .await;
+    assert!(!msg.has_html());
+    let mime = msg.id.get_raw_mime(bob).await?.unwrap();
+    // The decrypted MIME is retained.
+    let mime = String::from_utf8_lossy(&mime);
+    assert!(mime.contains("bridge me"));
+    assert!(mime.contains("Chat-Version: 1.0"));
+
+    assert!(MsgId::new(12345).get_raw_mime(bob).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_extra_headers() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    bob.set_config_bool(Config::SaveMimeHeaders, true).await?;
+    let chat_id = alice.create_chat_id(bob).await;
+
+    let mut msg = Message::new_text("Bridged from Matrix".to_string());
+    for (name, value) in [
+        ("Chat-Version", "1.0"),
+        ("X-Microsoft-Original-Message-ID", "<foo@example.org>"),
+        ("X-", "foo"),
+        ("X-Foo Bar", "foo"),
+        ("X-Bridge-Room", "foo\r\nBcc: mallory@example.net"),
+    ] {
+        assert!(msg.set_extra_header(name, value).is_err(), "{name}");
+    }
+    msg.set_extra_header("X-Bridge-Room", "!old:example.org")?;
+    msg.set_extra_header("x-bridge-room", "!room:example.org")?;
+    msg.set_extra_header("X-Bridge-Sender", "@alice:example.org")?;
+    assert_eq!(
+        msg.get_extra_headers(),
+        vec![
+            ("x-bridge-room".to_string(), "!room:example.org".to_string()),
+            (
+                "X-Bridge-Sender".to_string(),
+                "@alice:example.org".to_string()
+            ),
+        ]
+    );
+
+    let sent = alice.send_msg(chat_id, &mut msg).await;
+    // The headers are encrypted.
+    assert!(!sent.payload.contains("X-Bridge-Sender"));
+    let msg = bob.recv_msg(&sent).await;
+    let headers = msg.id.get_headers(bob).await?.unwrap();
+    assert!(headers.contains(&("x-bridge-room".to_string(), "!room:example.org".to_string())));
+    assert!(headers.contains(&(
+        "X-Bridge-Sender".to_string(),
+        "@alice:example.org".to_string()
+    )));
+
+    bob.set_config_bool(Config::SaveMimeHeaders, false).await?;
+    let msg = bob.recv_msg(&alice.send_text(chat_id, "hi").await).await;
+    assert_eq!(msg.id.get_headers(bob).await?, None);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reminder() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice0 = &tcm.alice().await;
+    let alice1 = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    for a in [alice0, alice1] {
+        a.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+
+    let chat_id = bob.create_chat_id(alice0).await;
+    let sent = bob.send_text(chat_id, "Talk tomorrow?").await;
+    let msg0 = alice0.recv_msg(&sent).await;
+    let msg1 = alice1.recv_msg(&sent).await;
+    msg0.chat_id.accept(alice0).await?;
+    markseen_msgs(alice0, vec![msg0.id]).await?;
+    assert!(msg0.id.get_reminder(alice0).await?.is_none());
+
+    let timestamp = time() + 3600;
+    msg0.id.set_reminder(alice0, timestamp).await?;
+    assert_eq!(msg0.id.get_reminder(alice0).await?, Some(timestamp));
+    assert_eq!(next_reminder_timestamp(alice0).await?, Some(timestamp));
+    test_utils::sync(alice0, alice1).await;
+    assert_eq!(msg1.id.get_reminder(alice1).await?, Some(timestamp));
+
+    remind_due_msgs(alice0, timestamp - 1).await?;
+    let msg = Message::load_from_db(alice0, msg0.id).await?;
+    assert_eq!(msg.state, MessageState::InSeen);
+
+    alice0.evtracker.clear_events();
+    remind_due_msgs(alice0, timestamp).await?;
+    alice0
+        .evtracker
+        .get_matching(|e| {
+            *e == EventType::MsgReminder {
+                chat_id: msg0.chat_id,
+                msg_id: msg0.id,
+            }
+        })
+        .await;
+    let msg = Message::load_from_db(alice0, msg0.id).await?;
+    assert_eq!(msg.state, MessageState::InFresh);
+    assert_eq!(alice0.get_fresh_msgs().await?, vec![msg0.id]);
+    assert!(msg0.id.get_reminder(alice0).await?.is_none());
+    assert!(next_reminder_timestamp(alice0).await?.is_none());
+
+    // Removing the reminder is synchronized as well.
+    msg1.id.set_reminder(alice1, 0).await?;
+    assert!(msg1.id.get_reminder(alice1).await?.is_none());
+    msg0.id.set_reminder(alice0, timestamp).await?;
+    test_utils::sync(alice1, alice0).await;
+    assert!(msg0.id.get_reminder(alice0).await?.is_none());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_sanitize_filename_message() -> Result<()> {
     let t = &TestContext::new().await;
@@ -755,3 +1056,98 @@ async fn test_get_existing_msg_ids() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_guess_msgtype_from_content() {
+    assert_eq!(
+        guess_msgtype_from_content(include_bytes!("../../test-data/image/logo.png")),
+        Some((Viewtype::Image, "image/png", "png"))
+    );
+    assert_eq!(
+        guess_msgtype_from_content(include_bytes!("../../test-data/image/avatar1000x1000.jpg")),
+        Some((Viewtype::Image, "image/jpeg", "jpg"))
+    );
+    assert_eq!(
+        guess_msgtype_from_content(include_bytes!("../../test-data/image/logo.gif")),
+        Some((Viewtype::Gif, "image/gif", "gif"))
+    );
+    assert_eq!(guess_msgtype_from_content(b"%PDF-1.4"), None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_sort_key() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    let chat_id = t.get_self_chat().await.id;
+    let mut msg_ids = Vec::new();
+    for text in ["first", "second", "third"] {
+        msg_ids.push(send_text_msg(t, chat_id, text.to_string()).await?);
+    }
+    // Give all messages the same timestamp, the third message is sorted first.
+    t.sql
+        .execute(
+            "UPDATE msgs SET timestamp=? WHERE id IN (?, ?)",
+            (1_700_000_000, msg_ids[0], msg_ids[1]),
+        )
+        .await?;
+    t.sql
+        .execute(
+            "UPDATE msgs SET timestamp=? WHERE id=?",
+            (1_600_000_000, msg_ids[2]),
+        )
+        .await?;
+
+    let mut keys = Vec::new();
+    for msg_id in &msg_ids {
+        keys.push(Message::load_from_db(t, *msg_id).await?.get_sort_key());
+    }
+    assert!(keys[0] < keys[1]);
+    assert!(keys[2] < keys[0]);
+
+    let chat_msgs: Vec<MsgId> = chat::get_chat_msgs(t, chat_id)
+        .await?
+        .into_iter()
+        .filter_map(|item| match item {
+            ChatItem::Message { msg_id } => msg_ids.contains(&msg_id).then_some(msg_id),
+            ChatItem::DayMarker { .. } => None,
+        })
+        .collect();
+    assert_eq!(chat_msgs, vec![msg_ids[2], msg_ids[0], msg_ids[1]]);
+
+    Ok(())
+}
+
+#[test]
+fn test_check_attachment() {
+    assert_eq!(
+        check_attachment("report.pdf", Some("application/pdf")),
+        None
+    );
+    assert_eq!(check_attachment("photo.jpg", Some("image/jpg")), None);
+    assert_eq!(
+        check_attachment("photo.jpg", Some("application/octet-stream")),
+        None
+    );
+    assert_eq!(check_attachment("notes", Some("text/plain")), None);
+    assert_eq!(check_attachment("archive.tar.gz", None), None);
+
+    assert_eq!(
+        check_attachment("setup.EXE", None),
+        Some(AttachmentWarning::DangerousExtension)
+    );
+    assert_eq!(
+        check_attachment("script.js", Some("text/plain")),
+        Some(AttachmentWarning::DangerousExtension)
+    );
+    assert_eq!(
+        check_attachment("screensaver.scr. ", None),
+        Some(AttachmentWarning::DangerousExtension)
+    );
+    assert_eq!(
+        check_attachment("invoice.pdf.exe", Some("application/pdf")),
+        Some(AttachmentWarning::DoubleExtension)
+    );
+    assert_eq!(
+        check_attachment("report.pdf", Some("image/jpeg")),
+        Some(AttachmentWarning::ContentTypeMismatch)
+    );
+}
@@ -11,13 +11,18 @@
 use mail_builder::headers::HeaderType;
 use mail_builder::headers::address::Address;
 use mail_builder::mime::MimePart;
+use num_traits::FromPrimitive;
 use tokio::fs;
 
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::blob::BlobObject;
-use crate::chat::{self, Chat, PARAM_BROADCAST_SECRET, load_broadcast_secret};
+use crate::chat::{
+    self, Chat, EncryptionPreference, PARAM_BROADCAST_SECRET, load_broadcast_secret,
+};
 use crate::config::Config;
-use crate::constants::{BROADCAST_INCOMPATIBILITY_MSG, Chattype, DC_FROM_HANDSHAKE};
+use crate::constants::{
+    BROADCAST_INCOMPATIBILITY_MSG, Chattype, DC_FROM_HANDSHAKE, SentFolderStrategy, SubjectMode,
+};
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::download::PostMsgMetadata;
@@ -35,7 +40,9 @@
 use crate::pgp::{SeipdVersion, addresses_from_public_key, pubkey_supports_seipdv2};
 use crate::simplify::escape_message_footer_marks;
 use crate::stock_str;
-use crate::tools::{IsNoneOrEmpty, create_outgoing_rfc724_mid, remove_subject_prefix, time};
+use crate::tools::{
+    IsNoneOrEmpty, create_outgoing_rfc724_mid, remove_subject_prefix, time, truncate,
+};
 use crate::webxdc::StatusUpdateSerial;
 
 // attachments of 25 mb brutto should work on the majority of providers
@@ -43,6 +50,12 @@
 // to get the netto sizes, we subtract 1 mb header-overhead and the base64-overhead.
 pub const RECOMMENDED_FILE_SIZE: u64 = 24 * 1024 * 1024 / 4 * 3;
 
+/// Max. number of words in the subject with [`SubjectMode::FirstWords`].
+const SUBJECT_WORDS: usize = 8;
+
+/// Approximate max. length of the subject with [`SubjectMode::FirstWords`] in graphemes.
+const SUBJECT_LEN: usize = 60;
+
 #[derive(Debug, Clone)]
 #[expect(clippy::large_enum_variant)]
 pub enum Loaded {
@@ -67,6 +80,17 @@ pub enum PreMessageMode {
     None,
 }
 
+/// Part of a text split into several messages, see [`crate::split_msg`].
+#[derive(Debug, Clone, PartialEq)]
+struct SplitPart {
+    /// 1-based index of the part.
+    index: usize,
+    /// Number of parts.
+    count: usize,
+    /// Whether the part ends at a line break that is not included into the text.
+    newline: bool,
+}
+
 /// Helper to construct mime messages.
 #[derive(Debug, Clone)]
 pub struct MimeFactory {
@@ -81,6 +105,8 @@ pub struct MimeFactory {
     /// names are alsways read from the `From:`-header.
     sender_displayname: Option<String>,
 
+    /// Footer below the message text:
+    /// own status or, for classic email recipients, [`Config::EmailSignature`].
     selfstatus: String,
 
     /// Vector of actual recipient addresses.
@@ -159,6 +185,9 @@ pub struct MimeFactory {
 
     /// Pre-message / post-message / atomic message.
     pre_message_mode: PreMessageMode,
+
+    /// Set if only a part of the message text is rendered.
+    split_part: Option<SplitPart>,
 }
 
 /// Result of rendering a message, ready to be submitted to a send job.
@@ -287,15 +316,24 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
                 None
             };
 
+            let encryption_preference = chat.get_encryption_preference();
             let is_encrypted = if msg
                 .param
                 .get_bool(Param::ForcePlaintext)
                 .unwrap_or_default()
             {
                 false
+            } else if msg.param.get_bool(Param::GuaranteeE2ee).unwrap_or_default() {
+                true
+            } else if encryption_preference == EncryptionPreference::ForcePlain {
+                warn!(
+                    context,
+                    "Sending unencrypted message to {} because of the encryption preference.",
+                    chat.id
+                );
+                false
             } else {
-                msg.param.get_bool(Param::GuaranteeE2ee).unwrap_or_default()
-                    || chat.is_encrypted(context).await?
+                chat.is_encrypted(context).await?
             };
 
             let mut keys = Vec::new();
@@ -487,6 +525,15 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
                 None
             } else if should_encrypt_symmetrically(&msg, &chat) {
                 Some(Vec::new())
+            } else if encryption_preference == EncryptionPreference::Opportunistic
+                && !missing_key_addresses.is_empty()
+            {
+                warn!(
+                    context,
+                    "Sending unencrypted message to {} because of missing keys.", chat.id
+                );
+                member_fingerprints.clear();
+                None
             } else {
                 if keys.is_empty() && !recipients.is_empty() {
                     bail!("No recipient keys are available, cannot encrypt to {recipients:?}.");
@@ -520,18 +567,33 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
             .split_ascii_whitespace()
             .map(|s| s.trim_start_matches('<').trim_end_matches('>').to_string())
             .collect();
-        let selfstatus = match attach_profile_data {
-            true => context
+        // Classic email recipients get `Config::EmailSignature` instead of the status, if set.
+        // Chat messages are always encrypted, so they never carry the signature.
+        let email_signature = if encryption_pubkeys.is_none() && !msg.is_system_message() {
+            context
+                .get_config(Config::EmailSignature)
+                .await?
+                .filter(|s| !s.trim().is_empty())
+        } else {
+            None
+        };
+        let selfstatus = match (email_signature, attach_profile_data) {
+            (Some(email_signature), _) => email_signature,
+            (None, true) => context
                 .get_config(Config::Selfstatus)
                 .await?
                 .unwrap_or_default(),
-            false => "".to_string(),
+            (None, false) => "".to_string(),
         };
         // We don't display avatars for address-contacts, so sending avatars w/o encryption is not
         // useful and causes e.g. Outlook to reject a message with a big header, see
         // https://support.delta.chat/t/invalid-mime-content-single-text-value-size-32822-exceeded-allowed-maximum-32768-for-the-chat-user-avatar-header/4067.
-        let attach_selfavatar =
-            Self::should_attach_selfavatar(context, &msg).await && encryption_pubkeys.is_some();
+        //
+        // With `Config::MinimalHeaders`, Chat-User-Avatar is not sent at all.
+        // Receivers keep the avatar they already have.
+        let attach_selfavatar = Self::should_attach_selfavatar(context, &msg).await
+            && encryption_pubkeys.is_some()
+            && !context.get_config_bool(Config::MinimalHeaders).await?;
 
         ensure_and_debug_assert!(
             member_timestamps.is_empty()
@@ -563,6 +625,7 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
             attach_selfavatar,
             webxdc_topic,
             pre_message_mode: PreMessageMode::None,
+            split_part: None,
         };
         Ok(factory)
     }
@@ -614,6 +677,7 @@ pub async fn from_mdn(
             attach_selfavatar: false,
             webxdc_topic: None,
             pre_message_mode: PreMessageMode::None,
+            split_part: None,
         };
 
         Ok(res)
@@ -697,6 +761,35 @@ async fn subject_str(&self, context: &Context) -> Result<String> {
                     return Ok(msg.subject.clone());
                 }
 
+                let subject_mode =
+                    SubjectMode::from_i32(context.get_config_int(Config::SubjectMode).await?)
+                        .unwrap_or_default();
+                match subject_mode {
+                    SubjectMode::ThreadStable => {}
+                    SubjectMode::FirstWords => {
+                        let words = msg
+                            .text
+                            .split_whitespace()
+                            .take(SUBJECT_WORDS)
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        if !words.is_empty() {
+                            return Ok(truncate(&words, SUBJECT_LEN).into_owned());
+                        }
+                    }
+                    SubjectMode::Fixed => {
+                        return Ok(context
+                            .get_config(Config::SubjectText)
+                            .await?
+                            .unwrap_or_default());
+                    }
+                    SubjectMode::EmptyWhenEncrypted => {
+                        if self.will_be_encrypted() {
+                            return Ok("".to_string());
+                        }
+                    }
+                }
+
                 if (chat.typ == Chattype::Group || chat.typ == Chattype::OutBroadcast)
                     && quoted_msg_subject.is_none_or_empty()
                 {
@@ -742,6 +835,7 @@ pub fn recipients(&self) -> Vec<String> {
     #[expect(clippy::arithmetic_side_effects)]
     pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
         let mut headers = Vec::<(&'static str, HeaderType<'static>)>::new();
+        let minimal_headers = context.get_config_bool(Config::MinimalHeaders).await?;
 
         let from = new_address_with_name(&self.from_displayname, self.from_addr.clone());
 
@@ -838,7 +932,16 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             mail_builder::headers::text::Text::new(subject_str.to_string()).into(),
         ));
 
-        let date = chrono::DateTime::<chrono::Utc>::from_timestamp(self.timestamp, 0)
+        // With `Config::MinimalHeaders`, Date of unencrypted messages
+        // is shifted back by up to a minute to not reveal the exact sending time.
+        // Encrypted messages only have randomized Date in the unprotected header anyway,
+        // see `group_headers_by_confidentiality()`.
+        let date_timestamp = if minimal_headers && !self.will_be_encrypted() {
+            self.timestamp.saturating_sub(rand::random_range(0..60))
+        } else {
+            self.timestamp
+        };
+        let date = chrono::DateTime::<chrono::Utc>::from_timestamp(date_timestamp, 0)
             .unwrap()
             .to_rfc2822();
         headers.push(("Date", mail_builder::headers::raw::Raw::new(date).into()));
@@ -852,6 +955,9 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                         msg.pre_rfc724_mid.clone()
                     }
                 }
+                _ if self.split_part.as_ref().is_some_and(|part| part.index > 1) => {
+                    create_outgoing_rfc724_mid()
+                }
                 _ => msg.rfc724_mid.clone(),
             },
             Loaded::Mdn { .. } => create_outgoing_rfc724_mid(),
@@ -884,7 +990,9 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                 "Auto-Submitted",
                 mail_builder::headers::raw::Raw::new("auto-replied".to_string()).into(),
             ));
-        } else if context.get_config_bool(Config::Bot).await? {
+        } else if matches!(&self.loaded, Loaded::Message { msg, .. } if msg.is_bot())
+            || context.get_config_bool(Config::Bot).await?
+        {
             headers.push((
                 "Auto-Submitted",
                 mail_builder::headers::raw::Raw::new("auto-generated".to_string()).into(),
@@ -973,12 +1081,43 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             ));
         }
 
+        if let Some(SplitPart {
+            index,
+            count,
+            newline,
+        }) = self.split_part
+            && let Loaded::Message { msg, .. } = &self.loaded
+        {
+            let value = if newline {
+                format!("{index}/{count}; newline")
+            } else {
+                format!("{index}/{count}")
+            };
+            headers.push((
+                "Chat-Split-Part",
+                mail_builder::headers::raw::Raw::new(value).into(),
+            ));
+            if index > 1 {
+                headers.push((
+                    "Chat-Split-Of",
+                    mail_builder::headers::message_id::MessageId::new(msg.rfc724_mid.clone())
+                        .into(),
+                ));
+            }
+        }
+
         let is_encrypted = self.will_be_encrypted();
 
         // Add ephemeral timer for non-MDN messages.
         // For MDNs it does not matter because they are not visible
         // and ignored by the receiver.
-        if let Loaded::Message { msg, .. } = &self.loaded {
+        //
+        // With `Config::MinimalHeaders`, the timer is only sent in encrypted messages
+        // where it is protected. Unencrypted messages usually go to classic email clients
+        // which ignore the timer anyway.
+        if let Loaded::Message { msg, .. } = &self.loaded
+            && (is_encrypted || !minimal_headers)
+        {
             let ephemeral_timer = msg.chat_id.get_ephemeral_timer(context).await?;
             if let EphemeralTimer::Enabled { duration } = ephemeral_timer {
                 headers.push((
@@ -1000,7 +1139,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                 let (main_part, mut parts) = self
                     .render_message(context, &mut headers, &grpimage, is_encrypted)
                     .await?;
-                if parts.is_empty() {
+                let message = if parts.is_empty() {
                     // Single part, render as regular message.
                     main_part
                 } else {
@@ -1014,7 +1153,15 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                     } else {
                         MimePart::new("multipart/mixed", parts)
                     }
-                }
+                };
+
+                // Custom headers are added to the inner message
+                // so that they are encrypted.
+                msg.get_extra_headers()
+                    .into_iter()
+                    .fold(message, |message, (name, value)| {
+                        message.header(name, mail_builder::headers::text::Text::new(value))
+                    })
             }
             Loaded::Mdn { .. } => self.render_mdn()?,
         };
@@ -1040,14 +1187,18 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             );
 
             // Add gossip headers in chats with multiple recipients
-            let multiple_recipients =
-                encryption_pubkeys.len() > 1 || context.get_config_bool(Config::BccSelf).await?;
+            let multiple_recipients = encryption_pubkeys.len() > 1
+                || (context.get_config_bool(Config::BccSelf).await?
+                    && context.get_sent_folder_strategy().await? == SentFolderStrategy::BccSelf);
 
             let gossip_period = context.get_config_i64(Config::GossipPeriod).await?;
             let now = time();
+            let mut gossip_headers_cnt: usize = 0;
+            let mut gossip_headers_size: usize = 0;
 
             match &self.loaded {
                 Loaded::Message { chat, msg } => {
+                    let suppress_gossip = chat.is_gossip_suppressed();
                     if !should_hide_recipients(msg, chat) {
                         for (addr, key) in &encryption_pubkeys {
                             let fingerprint = key.dc_fingerprint().hex();
@@ -1069,15 +1220,20 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                                         )
                                         .await?;
 
+                                    // Zero timestamp is set by `chat::force_gossip()`.
+                                    //
                                     // `gossip_period == 0` is a special case for testing,
                                     // enabling gossip in every message.
                                     //
                                     // If current time is in the past compared to
                                     // `gossiped_timestamp`, we also gossip because
                                     // either the `gossiped_timestamp` or clock is wrong.
-                                    gossip_period == 0
-                                        || gossiped_timestamp
-                                            .is_none_or(|ts| now >= ts + gossip_period || now < ts)
+                                    gossiped_timestamp == Some(0)
+                                        || !suppress_gossip
+                                            && (gossip_period == 0
+                                                || gossiped_timestamp.is_none_or(|ts| {
+                                                    now >= ts + gossip_period || now < ts
+                                                }))
                                 };
 
                             let verifier_id: Option<u32> = context
@@ -1105,6 +1261,8 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                             }
                             .to_string();
 
+                            gossip_headers_cnt = gossip_headers_cnt.saturating_add(1);
+                            gossip_headers_size = gossip_headers_size.saturating_add(header.len());
                             message = message.header(
                                 "Autocrypt-Gossip",
                                 mail_builder::headers::raw::Raw::new(header),
@@ -1127,6 +1285,12 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                     // Never gossip in MDNs.
                 }
             }
+            if gossip_headers_cnt > 0 {
+                info!(
+                    context,
+                    "Gossiping {gossip_headers_cnt} keys in {gossip_headers_size} bytes of headers."
+                );
+            }
 
             // Disable compression for SecureJoin to ensure
             // there are no compression side channels
@@ -1210,18 +1374,57 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                     message.header(header, value)
                 });
             let message = MimePart::new("multipart/mixed", vec![message]);
-            let message = protected_headers
+            let mut message = protected_headers
                 .iter()
                 .fold(message, |message, (header, value)| {
                     message.header(*header, value.clone())
                 });
 
-            // Deduplicate unprotected headers that also are in the protected headers:
-            let protected: HashSet<&str> =
-                HashSet::from_iter(protected_headers.iter().map(|(header, _value)| *header));
-            unprotected_headers.retain(|(header, _value)| !protected.contains(header));
+            let sign = match &self.loaded {
+                Loaded::Message { chat, .. } => chat.is_sign_unencrypted(),
+                Loaded::Mdn { .. } => false,
+            };
+            if sign {
+                // Keep unprotected headers intact as the receiver
+                // does not take headers from the signed part of unencrypted messages.
+                for (header, value) in &mut message.headers {
+                    if header == "Content-Type"
+                        && let HeaderType::ContentType(content_type) = value
+                    {
+                        *content_type = content_type.clone().attribute("protected-headers", "v1");
+                    }
+                }
+
+                // Serialize the signed part only once,
+                // MIME boundaries are random and differ between serializations.
+                let mut raw_message = Vec::new();
+                message.write_part(Cursor::new(&mut raw_message)).ok();
+                let signature = encrypt_helper.sign(context, &raw_message).await?;
+                MimePart::new(
+                    "multipart/signed; protocol=\"application/pgp-signature\"; protected",
+                    vec![
+                        MimePart::raw(raw_message),
+                        MimePart::new(
+                            "application/pgp-signature; name=\"signature.asc\"",
+                            signature,
+                        )
+                        .header(
+                            "Content-Description",
+                            mail_builder::headers::raw::Raw::<'static>::new(
+                                "OpenPGP digital signature",
+                            ),
+                        )
+                        .attachment("signature"),
+                    ],
+                )
+            } else {
+                // Deduplicate unprotected headers that also are in the protected headers:
+                let protected: HashSet<&str> =
+                    HashSet::from_iter(protected_headers.iter().map(|(header, _value)| *header));
+                unprotected_headers.retain(|(header, _value)| !protected.contains(header));
 
-            message
+                message
+            }
         };
 
         let MimeFactory {
@@ -1463,6 +1666,8 @@ async fn render_message(
                 SystemMessage::ChatE2ee => {}
                 SystemMessage::CallAccepted => {}
                 SystemMessage::CallEnded => {}
+                SystemMessage::GroupHistory => {}
+                SystemMessage::AbuseReport => {}
             }
 
             if command == SystemMessage::GroupDescriptionChanged
@@ -1501,7 +1706,9 @@ async fn render_message(
             }
             SystemMessage::LocationOnly
             | SystemMessage::MultiDeviceSync
-            | SystemMessage::WebxdcStatusUpdate => {
+            | SystemMessage::WebxdcStatusUpdate
+            | SystemMessage::GroupHistory
+            | SystemMessage::AbuseReport => {
                 // This should prevent automatic replies,
                 // such as non-delivery reports,
                 // if the message is unencrypted.
@@ -1763,6 +1970,37 @@ async fn render_message(
                     HeaderDef::ChatPostMessageMetadata.into(),
                     mail_builder::headers::raw::Raw::new(metadata.to_header_value()?).into(),
                 ));
+                if let Some(p2p_hash) = msg.param.get(Param::P2pHash)
+                    && let Some(p2p_node_addr) = msg.param.get(Param::P2pNodeAddr)
+                {
+                    headers.push((
+                        HeaderDef::ChatP2pHash.into(),
+                        mail_builder::headers::raw::Raw::new(p2p_hash.to_string()).into(),
+                    ));
+                    headers.push((
+                        HeaderDef::ChatP2pNodeAddr.into(),
+                        mail_builder::headers::text::Text::new(p2p_node_addr.to_string()).into(),
+                    ));
+                }
+            } else if is_encrypted
+                && let Some(upload_url) = msg.param.get(Param::UploadUrl)
+                && let Some(upload_key) = msg.param.get(Param::UploadKey)
+            {
+                let Some(metadata) = PostMsgMetadata::from_msg(context, &msg).await? else {
+                    bail!("Failed to generate metadata for uploaded attachment")
+                };
+                headers.push((
+                    HeaderDef::ChatUploadUrl.into(),
+                    mail_builder::headers::raw::Raw::new(upload_url.to_string()).into(),
+                ));
+                headers.push((
+                    HeaderDef::ChatUploadKey.into(),
+                    mail_builder::headers::raw::Raw::new(upload_key.to_string()).into(),
+                ));
+                headers.push((
+                    HeaderDef::ChatPostMessageMetadata.into(),
+                    mail_builder::headers::raw::Raw::new(metadata.to_header_value()?).into(),
+                ));
             } else {
                 let file_part = build_body_file(context, &msg).await?;
                 parts.push(file_part);
@@ -1793,6 +2031,18 @@ async fn render_message(
         } else if command == SystemMessage::WebxdcStatusUpdate {
             let json = msg.param.get(Param::Arg).unwrap_or_default();
             parts.push(context.build_status_update_part(json));
+        } else if command == SystemMessage::GroupHistory {
+            let json = msg.param.get(Param::Arg).unwrap_or_default();
+            parts.push(
+                MimePart::new("application/json", json.as_bytes().to_vec())
+                    .attachment("group-history.json"),
+            );
+        } else if command == SystemMessage::AbuseReport {
+            let json = msg.param.get(Param::Arg).unwrap_or_default();
+            parts.push(
+                MimePart::new("application/json", json.as_bytes().to_vec())
+                    .attachment("abuse-report.json"),
+            );
         } else if msg.viewtype == Viewtype::Webxdc {
             let topic = self
                 .webxdc_topic
@@ -1892,6 +2142,41 @@ pub fn will_be_encrypted(&self) -> bool {
         self.encryption_pubkeys.is_some()
     }
 
+    /// Updates the message params after its attachment was uploaded,
+    /// so the attachment is replaced with the download URL.
+    pub(crate) fn set_uploaded_attachment(&mut self, uploaded_msg: &Message) {
+        if let Loaded::Message { msg, .. } = &mut self.loaded {
+            msg.param = uploaded_msg.param.clone();
+        }
+    }
+
+    /// Renders only the given part of the message text.
+    ///
+    /// Parts after the first one get own Message-IDs
+    /// and refer to the first part, see [`crate::split_msg`].
+    /// `newline` is true if the part ends at a line break not included into `text`.
+    pub(crate) fn set_as_split_part(
+        &mut self,
+        index: usize,
+        count: usize,
+        newline: bool,
+        text: String,
+    ) {
+        if let Loaded::Message { msg, .. } = &mut self.loaded {
+            msg.text = text;
+            if index > 1 {
+                msg.param.remove(Param::Quote);
+                self.attach_selfavatar = false;
+                self.req_mdn = false;
+            }
+        }
+        self.split_part = Some(SplitPart {
+            index,
+            count,
+            newline,
+        });
+    }
+
     pub fn set_as_post_message(&mut self) {
         self.pre_message_mode = PreMessageMode::Post;
     }
@@ -2061,7 +2346,7 @@ fn group_headers_by_confidentiality(
             } else {
                 unprotected_headers.push(header.clone());
             }
-        } else if header_name == "chat-broadcast-secret" {
+        } else if header_name == "chat-broadcast-secret" || header_name == "chat-upload-key" {
             if is_encrypted {
                 protected_headers.push(header.clone());
             }
@@ -2180,7 +2465,13 @@ async fn build_body_file(context: &Context, msg: &Message) -> Result<MimePart<'s
     // at least on tested Thunderbird and Gma'l in 2017.
     // But I've heard about problems with inline and outl'k, so we just use the attachment-type until we
     // run into other problems ...
-    let mail = MimePart::new(mimetype, body).attachment(sanitize_bidi_characters(&file_name));
+    let mut mail = MimePart::new(mimetype, body).attachment(sanitize_bidi_characters(&file_name));
+    if let Some(alt_text) = msg.get_alt_text() {
+        mail = mail.header(
+            HeaderDef::ContentDescription.get_headername(),
+            mail_builder::headers::text::Text::new(alt_text.to_string()),
+        );
+    }
 
     Ok(mail)
 }
@@ -907,3 +907,115 @@ async fn test_fallback_to_seipdv1() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sign_unencrypted() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice.allow_unencrypted().await?;
+    bob.allow_unencrypted().await?;
+    let chat_id = alice.create_email_chat(bob).await.id;
+
+    let sent = alice.send_text(chat_id, "unsigned").await;
+    assert!(!sent.payload().contains("multipart/signed"));
+
+    chat::set_sign_unencrypted(alice, chat_id, true).await?;
+    assert!(
+        Chat::load_from_db(alice, chat_id)
+            .await?
+            .is_sign_unencrypted()
+    );
+    let sent = alice.send_text(chat_id, "signed").await;
+    let payload = sent.payload();
+    assert!(payload.contains("multipart/signed"));
+    assert!(!payload.contains("-----BEGIN PGP MESSAGE-----"));
+
+    let mail = mailparse::parse_mail(payload.as_bytes())?;
+    let (_, signatures) = crate::decrypt::validate_detached_signature(
+        &mail,
+        &[crate::key::load_self_public_key(alice).await?],
+    )
+    .unwrap();
+    assert_eq!(signatures.len(), 1);
+
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.text, "signed");
+    assert!(!msg.get_showpadlock());
+
+    chat::set_sign_unencrypted(alice, chat_id, false).await?;
+    let sent = alice.send_text(chat_id, "unsigned again").await;
+    assert!(!sent.payload().contains("multipart/signed"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_minimal_headers() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let chat_id = alice.create_chat(bob).await.id;
+
+    let file = alice.dir.path().join("avatar.png");
+    let bytes = include_bytes!("../../test-data/image/avatar64x64.png");
+    tokio::fs::write(&file, bytes).await?;
+    alice
+        .set_config(Config::Selfavatar, Some(file.to_str().unwrap()))
+        .await?;
+
+    alice.set_config_bool(Config::MinimalHeaders, true).await?;
+    let sent = alice.send_text(chat_id, "Without avatar").await;
+    assert!(bob.parse_msg(&sent).await.user_avatar.is_none());
+
+    alice.set_config_bool(Config::MinimalHeaders, false).await?;
+    let sent = alice.send_text(chat_id, "With avatar").await;
+    assert!(bob.parse_msg(&sent).await.user_avatar.is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_subject_mode() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    t.allow_unencrypted().await?;
+    t.set_config(Config::SubjectMode, Some("1")).await?;
+    assert_eq!(first_subject_str(t).await, "Hi");
+
+    let t = TestContext::new_alice().await;
+    t.allow_unencrypted().await?;
+    t.set_config(Config::SubjectMode, Some("2")).await?;
+    t.set_config(Config::SubjectText, Some("Fixed subject"))
+        .await?;
+    assert_eq!(first_subject_str(t).await, "Fixed subject");
+
+    // Unencrypted messages keep the default subject.
+    let t = TestContext::new_alice().await;
+    t.allow_unencrypted().await?;
+    t.set_config(Config::SubjectMode, Some("3")).await?;
+    assert_eq!(first_subject_str(t).await, "Message from alice@example.org");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_email_signature() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice.allow_unencrypted().await?;
+    alice
+        .set_config(Config::EmailSignature, Some("Alice, Example Org"))
+        .await?;
+
+    let email_chat_id = alice.create_email_chat(bob).await.id;
+    let sent = alice.send_text(email_chat_id, "Hi Bob").await;
+    let mime = bob.parse_msg(&sent).await;
+    assert_eq!(mime.footer.as_deref(), Some("Alice, Example Org"));
+
+    let chat_id = alice.create_chat(bob).await.id;
+    let sent = alice.send_text(chat_id, "Hi Bob").await;
+    let mime = bob.parse_msg(&sent).await;
+    assert_ne!(mime.footer.as_deref(), Some("Alice, Example Org"));
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 //! # MIME message parsing module.
 
+use std::borrow::Cow;
 use std::cmp::min;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
@@ -10,6 +11,7 @@
 use deltachat_contact_tools::{addr_cmp, addr_normalize, sanitize_bidi_characters};
 use deltachat_derive::{FromSql, ToSql};
 use format_flowed::unformat_flowed;
+use mailparse::body::Body;
 use mailparse::{DispositionType, MailHeader, MailHeaderMap, SingleInfo, addrparse_header};
 use mime::Mime;
 
@@ -27,13 +29,37 @@
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::key::{self, DcKey, Fingerprint, SignedPublicKey};
 use crate::log::warn;
-use crate::message::{self, Message, MsgId, Viewtype, get_vcard_summary, set_msg_failed};
+use crate::message::{
+    self, Message, MessageState, MsgId, Viewtype, get_vcard_summary, set_msg_failed,
+};
 use crate::param::{Param, Params};
 use crate::simplify::{SimplifiedText, simplify};
 use crate::sync::SyncItems;
-use crate::tools::{get_filemeta, parse_receive_headers, time, truncate_msg_text, validate_group_id};
+use crate::tools::{
+    get_filemeta, parse_receive_headers, time, truncate_msg_text, validate_group_id,
+};
+use crate::webxdc::WEBXDC_SUFFIX;
 use crate::{chatlist_events, location, tools};
 
+/// Maximum number of `Content-Type` headers in a message.
+///
+/// Every nested multipart has its own `Content-Type` header,
+/// so this bounds the recursion depth of `mailparse::parse_mail()`
+/// which has no limit itself.
+const MAX_CONTENT_TYPE_HEADERS: usize = 1000;
+
+/// Maximum nesting depth of MIME parts processed by `parse_mime_recursive()`.
+/// Deeper parts are ignored.
+const MAX_MIME_NESTING_DEPTH: usize = 32;
+
+/// Maximum encoded size of a text part.
+/// Larger text parts are ignored to avoid decoding and simplifying them in memory.
+const MAX_TEXT_PART_BYTES: usize = 16 * 1024 * 1024;
+
+/// Encoded size above which attachments are decoded into the blob file
+/// using a bounded buffer instead of decoding them into memory first.
+const STREAMING_PART_BYTES: usize = 1024 * 1024;
+
 /// Public key extracted from `Autocrypt-Gossip`
 /// header with associated information.
 #[derive(Debug)]
@@ -111,6 +137,8 @@ pub(crate) struct MimeMessage {
     pub message_kml: Option<location::Kml>,
     pub(crate) sync_items: Option<SyncItems>,
     pub(crate) webxdc_status_update: Option<String>,
+    pub(crate) group_history: Option<String>,
+    pub(crate) abuse_report: Option<String>,
     pub(crate) user_avatar: Option<AvatarAction>,
     pub(crate) group_avatar: Option<AvatarAction>,
     pub(crate) mdn_reports: Vec<Report>,
@@ -259,6 +287,16 @@ pub enum SystemMessage {
 
     /// Group or broadcast channel description changed.
     GroupDescriptionChanged = 70,
+
+    /// Recent messages of a group sent to a new member,
+    /// see [`crate::chat::set_share_history()`].
+    /// These messages are not shown in the chat.
+    GroupHistory = 71,
+
+    /// Report about a broadcast channel message sent to the channel owner,
+    /// see [`MsgId::report_to_owner()`](crate::message::MsgId::report_to_owner).
+    /// These messages are not shown in the chat.
+    AbuseReport = 72,
 }
 
 impl MimeMessage {
@@ -267,7 +305,7 @@ impl MimeMessage {
     /// This method has some side-effects,
     /// such as saving blobs and saving found public keys to the database.
     pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
-        let mail = mailparse::parse_mail(body)?;
+        let mail = parse_mail(body)?;
 
         let timestamp_rcvd = time();
         let mut timestamp_sent =
@@ -358,7 +396,7 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             Ok(Some((mut msg, expected_sender_fp))) => {
                 mail_raw = msg.as_data_vec().unwrap_or_default();
 
-                let decrypted_mail = mailparse::parse_mail(&mail_raw)?;
+                let decrypted_mail = parse_mail(&mail_raw)?;
                 if std::env::var(crate::DCC_MIME_DEBUG).is_ok() {
                     info!(
                         context,
@@ -653,6 +691,8 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             message_kml: None,
             sync_items: None,
             webxdc_status_update: None,
+            group_history: None,
+            abuse_report: None,
             user_avatar: None,
             group_avatar: None,
             delivery_report: None,
@@ -668,7 +708,7 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
 
         match mail {
             Ok(mail) => {
-                parser.parse_mime_recursive(context, mail, false).await?;
+                parser.parse_mime_recursive(context, mail, false, 0).await?;
             }
             Err(err) => {
                 let txt = "[This message cannot be decrypted.\n\n• It might already help to simply reply to this message and ask the sender to send the message again.\n\n• If you just re-installed Delta Chat then it is best if you re-setup Delta Chat now and choose \"Add as second device\" or import a backup.]";
@@ -691,6 +731,8 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             && !is_location_only
             && parser.sync_items.is_none()
             && parser.webxdc_status_update.is_none()
+            && parser.group_history.is_none()
+            && parser.abuse_report.is_none()
         {
             let is_bot =
                 parser.headers.get("auto-submitted") == Some(&"auto-generated".to_string());
@@ -1073,6 +1115,7 @@ async fn parse_mime_recursive<'a>(
         context: &'a Context,
         mail: &'a mailparse::ParsedMail<'a>,
         is_related: bool,
+        depth: usize,
     ) -> Result<bool> {
         enum MimeS {
             Multiple,
@@ -1080,6 +1123,11 @@ enum MimeS {
             Message,
         }
 
+        if depth > MAX_MIME_NESTING_DEPTH {
+            warn!(context, "MIME parts nested too deeply, ignoring them.");
+            return Ok(false);
+        }
+
         let mimetype = mail.ctype.mimetype.to_lowercase();
 
         let m = if mimetype.starts_with("multipart") {
@@ -1100,15 +1148,23 @@ enum MimeS {
 
         let is_related = is_related || mimetype == "multipart/related";
         match m {
-            MimeS::Multiple => Box::pin(self.handle_multiple(context, mail, is_related)).await,
+            MimeS::Multiple => {
+                Box::pin(self.handle_multiple(context, mail, is_related, depth)).await
+            }
             MimeS::Message => {
                 let raw = mail.get_body_raw()?;
                 if raw.is_empty() {
                     return Ok(false);
                 }
-                let mail = mailparse::parse_mail(&raw).context("failed to parse mail")?;
+                let mail = parse_mail(&raw).context("failed to parse mail")?;
 
-                Box::pin(self.parse_mime_recursive(context, &mail, is_related)).await
+                Box::pin(self.parse_mime_recursive(
+                    context,
+                    &mail,
+                    is_related,
+                    depth.saturating_add(1),
+                ))
+                .await
             }
             MimeS::Single => {
                 self.add_single_part_if_known(context, mail, is_related)
@@ -1122,7 +1178,9 @@ async fn handle_multiple(
         context: &Context,
         mail: &mailparse::ParsedMail<'_>,
         is_related: bool,
+        depth: usize,
     ) -> Result<bool> {
+        let depth = depth.saturating_add(1);
         let mut any_part_added = false;
         let mimetype = get_mime_type(
             mail,
@@ -1152,7 +1210,7 @@ async fn handle_multiple(
 
                     if mime_type == mime::TEXT_PLAIN || mime_type.type_() == mime::MULTIPART {
                         any_part_added = self
-                            .parse_mime_recursive(context, cur_data, is_related)
+                            .parse_mime_recursive(context, cur_data, is_related, depth)
                             .await?;
                         break;
                     }
@@ -1187,7 +1245,7 @@ async fn handle_multiple(
                 if !any_part_added {
                     for cur_part in mail.subparts.iter().rev() {
                         if self
-                            .parse_mime_recursive(context, cur_part, is_related)
+                            .parse_mime_recursive(context, cur_part, is_related, depth)
                             .await?
                         {
                             any_part_added = true;
@@ -1213,7 +1271,7 @@ async fn handle_multiple(
                 for background information why we use encrypted+signed) */
                 if let Some(first) = mail.subparts.first() {
                     any_part_added = self
-                        .parse_mime_recursive(context, first, is_related)
+                        .parse_mime_recursive(context, first, is_related, depth)
                         .await?;
                 }
             }
@@ -1247,7 +1305,7 @@ async fn handle_multiple(
                             // Add all parts (we need another part, preferably text/plain, to show as an error message)
                             for cur_data in &mail.subparts {
                                 if self
-                                    .parse_mime_recursive(context, cur_data, is_related)
+                                    .parse_mime_recursive(context, cur_data, is_related, depth)
                                     .await?
                                 {
                                     any_part_added = true;
@@ -1269,7 +1327,7 @@ async fn handle_multiple(
                         Some(_) => {
                             for cur_data in &mail.subparts {
                                 if self
-                                    .parse_mime_recursive(context, cur_data, is_related)
+                                    .parse_mime_recursive(context, cur_data, is_related, depth)
                                     .await?
                                 {
                                     any_part_added = true;
@@ -1284,7 +1342,7 @@ async fn handle_multiple(
                 // the parts are really supported)
                 for cur_data in &mail.subparts {
                     if self
-                        .parse_mime_recursive(context, cur_data, is_related)
+                        .parse_mime_recursive(context, cur_data, is_related, depth)
                         .await?
                     {
                         any_part_added = true;
@@ -1312,16 +1370,43 @@ async fn add_single_part_if_known(
 
         match filename {
             Some(filename) => {
-                self.do_add_single_file_part(
+                if let Some((blob, bytes)) = stream_file_part(
                     context,
+                    mail,
                     msg_type,
-                    mime_type,
-                    &raw_mime,
-                    &mail.get_body_raw()?,
+                    &mime_type,
                     &filename,
-                    is_related,
-                )
-                .await?;
+                    self.has_chat_version(),
+                ) {
+                    self.do_add_blob_part(
+                        context,
+                        Part::default(),
+                        &blob,
+                        msg_type,
+                        mime_type,
+                        &raw_mime,
+                        bytes,
+                        &filename,
+                        is_related,
+                    );
+                } else {
+                    self.do_add_single_file_part(
+                        context,
+                        msg_type,
+                        mime_type,
+                        &raw_mime,
+                        &mail.get_body_raw()?,
+                        &filename,
+                        is_related,
+                    )
+                    .await?;
+                }
+                if let Some(alt_text) = mail.headers.get_header_value(HeaderDef::ContentDescription)
+                {
+                    for part in self.parts.iter_mut().skip(old_part_count) {
+                        part.param.set(Param::AltText, &alt_text);
+                    }
+                }
             }
             None => {
                 match mime_type.type_() {
@@ -1354,6 +1439,10 @@ async fn add_single_part_if_known(
                         return Ok(true);
                     }
                     mime::TEXT | mime::HTML => {
+                        if encoded_body_len(mail) > MAX_TEXT_PART_BYTES {
+                            warn!(context, "Text part is too large, ignoring it.");
+                            return Ok(false);
+                        }
                         let decoded_data = match mail.get_body() {
                             Ok(decoded_data) => decoded_data,
                             Err(err) => {
@@ -1437,8 +1526,14 @@ async fn add_single_part_if_known(
                             (simplified_txt, top_quote)
                         };
 
+                        // Parts of split texts are joined before display
+                        // and must not be truncated.
                         let (simplified_txt, was_truncated) =
-                            truncate_msg_text(context, simplified_txt).await?;
+                            if self.get_header(HeaderDef::ChatSplitPart).is_some() {
+                                (simplified_txt, false)
+                            } else {
+                                truncate_msg_text(context, simplified_txt).await?
+                            };
                         if was_truncated {
                             self.is_mime_modified = was_truncated;
                         }
@@ -1494,6 +1589,35 @@ async fn do_add_single_file_part(
         {
             return Ok(());
         }
+        // Correct the type of images sent with a wrong `Content-Type`.
+        // Chat messages are not corrected
+        // as images may be sent as files on purpose to avoid recoding.
+        let mut blob_name = Cow::Borrowed(filename);
+        let (msg_type, mime_type, raw_mime) =
+            match message::guess_msgtype_from_content(decoded_data) {
+                Some((viewtype, mimetype, extension))
+                    if msg_type == Viewtype::File && !self.has_chat_version() =>
+                {
+                    info!(
+                        context,
+                        "Attachment {filename:?} is {mimetype} according to its content."
+                    );
+                    if message::guess_msgtype_from_path_suffix(Path::new(filename))
+                        .map(|(_, suffix_mimetype)| suffix_mimetype)
+                        != Some(mimetype)
+                    {
+                        blob_name = Cow::Owned(
+                            Path::new(filename)
+                                .with_extension(extension)
+                                .to_string_lossy()
+                                .into_owned(),
+                        );
+                    }
+                    (viewtype, mimetype.parse()?, mimetype)
+                }
+                _ => (msg_type, mime_type, raw_mime),
+            };
+
         let mut part = Part::default();
         let msg_type = if context
             .is_webxdc_file(filename, decoded_data)
@@ -1538,6 +1662,12 @@ async fn do_add_single_file_part(
                 .unwrap_or_default();
             self.webxdc_status_update = Some(serialized);
             return Ok(());
+        } else if filename == "group-history.json" {
+            self.group_history = Some(String::from_utf8_lossy(decoded_data).to_string());
+            return Ok(());
+        } else if filename == "abuse-report.json" {
+            self.abuse_report = Some(String::from_utf8_lossy(decoded_data).to_string());
+            return Ok(());
         } else if msg_type == Viewtype::Vcard {
             if let Some(summary) = get_vcard_summary(decoded_data) {
                 part.param.set(Param::Summary1, summary);
@@ -1566,30 +1696,59 @@ async fn do_add_single_file_part(
         /* we have a regular file attachment,
         write decoded data to new blob object */
 
-        let blob =
-            match BlobObject::create_and_deduplicate_from_bytes(context, decoded_data, filename) {
-                Ok(blob) => blob,
-                Err(err) => {
-                    error!(
-                        context,
-                        "Could not add blob for mime part {}, error {:#}", filename, err
-                    );
-                    return Ok(());
-                }
-            };
+        let blob = match BlobObject::create_and_deduplicate_from_bytes(
+            context,
+            decoded_data,
+            &blob_name,
+        ) {
+            Ok(blob) => blob,
+            Err(err) => {
+                error!(
+                    context,
+                    "Could not add blob for mime part {}, error {:#}", filename, err
+                );
+                return Ok(());
+            }
+        };
+        self.do_add_blob_part(
+            context,
+            part,
+            &blob,
+            msg_type,
+            mime_type,
+            raw_mime,
+            decoded_data.len(),
+            filename,
+            is_related,
+        );
+        Ok(())
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    fn do_add_blob_part(
+        &mut self,
+        context: &Context,
+        mut part: Part,
+        blob: &BlobObject<'_>,
+        msg_type: Viewtype,
+        mime_type: Mime,
+        raw_mime: &str,
+        bytes: usize,
+        filename: &str,
+        is_related: bool,
+    ) {
         info!(context, "added blobfile: {:?}", blob.as_name());
 
         part.typ = msg_type;
         part.org_filename = Some(filename.to_string());
         part.mimetype = Some(mime_type);
-        part.bytes = decoded_data.len();
+        part.bytes = bytes;
         part.param.set(Param::File, blob.as_name());
         part.param.set(Param::Filename, filename);
         part.param.set(Param::MimeType, raw_mime);
         part.is_related = is_related;
 
         self.do_add_single_part(part);
-        Ok(())
     }
 
     /// Returns whether a key from the attachment was saved.
@@ -1656,6 +1815,19 @@ pub(crate) fn is_mailinglist_message(&self) -> bool {
         self.get_mailinglist_header().is_some()
     }
 
+    /// Returns the first `mailto:` address of the List-Owner header.
+    pub(crate) fn get_list_owner(&self) -> Option<String> {
+        let list_owner = self.get_header(HeaderDef::ListOwner)?;
+        list_owner.split(',').find_map(|uri| {
+            let uri = uri.trim().strip_prefix('<')?.strip_suffix('>')?;
+            let addr = uri
+                .get(..7)
+                .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+                .and(uri.get(7..))?;
+            addr.split('?').next().map(|addr| addr.to_string())
+        })
+    }
+
     /// Detects Schleuder mailing list by List-Help header.
     pub(crate) fn is_schleuder_message(&self) -> bool {
         if let Some(list_help) = self.get_header(HeaderDef::ListHelp) {
@@ -1824,6 +1996,7 @@ fn process_delivery_status(
     ) -> Result<Option<DeliveryReport>> {
         // Assume failure.
         let mut failure = true;
+        let mut delivered = false;
 
         if let Some(status_part) = report.subparts.get(1) {
             // RFC 3464 defines `message/delivery-status`
@@ -1850,6 +2023,7 @@ fn process_delivery_status(
                     if action != "failed" {
                         info!(context, "DSN with {:?} action", action);
                         failure = false;
+                        delivered = action == "delivered";
                     }
                 } else {
                     warn!(context, "DSN without action");
@@ -1878,6 +2052,7 @@ fn process_delivery_status(
                 return Ok(Some(DeliveryReport {
                     rfc724_mid: original_message_id,
                     failure,
+                    delivered,
                 }));
             }
 
@@ -1974,6 +2149,7 @@ async fn heuristically_parse_ndn(&mut self, context: &Context) {
                     self.delivery_report = Some(DeliveryReport {
                         rfc724_mid: original_message_id,
                         failure: true,
+                        delivered: false,
                     })
                 }
             }
@@ -2008,6 +2184,11 @@ pub async fn handle_reports(&self, context: &Context, from_id: ContactId, parts:
             if let Err(err) = handle_ndn(context, delivery_report, error).await {
                 warn!(context, "Could not handle NDN: {err:#}.");
             }
+        } else if let Some(delivery_report) = &self.delivery_report
+            && delivery_report.delivered
+            && let Err(err) = handle_dsn_delivered(context, delivery_report).await
+        {
+            warn!(context, "Could not handle DSN: {err:#}.");
         }
     }
 
@@ -2160,6 +2341,9 @@ pub(crate) struct Report {
 pub(crate) struct DeliveryReport {
     pub rfc724_mid: String,
     pub failure: bool,
+
+    /// Whether the message reached the server of the recipient (`Action: delivered`).
+    pub delivered: bool,
 }
 
 pub(crate) fn parse_message_ids(ids: &str) -> Vec<String> {
@@ -2262,6 +2446,153 @@ pub struct Part {
     pub(crate) is_reaction: bool,
 }
 
+/// Parses a mail with `mailparse::parse_mail()`
+/// after checking that it is not nested too deeply.
+fn parse_mail(body: &[u8]) -> Result<mailparse::ParsedMail<'_>> {
+    let content_type_headers = body
+        .split(|&b| b == b'\n')
+        .filter(|line| {
+            line.get(..13)
+                .is_some_and(|name| name.eq_ignore_ascii_case(b"content-type:"))
+        })
+        .count();
+    ensure!(
+        content_type_headers <= MAX_CONTENT_TYPE_HEADERS,
+        "Too many MIME parts ({content_type_headers})"
+    );
+    Ok(mailparse::parse_mail(body)?)
+}
+
+/// Returns the size of the part's body before decoding.
+fn encoded_body_len(mail: &mailparse::ParsedMail<'_>) -> usize {
+    match mail.get_body_encoded() {
+        Body::Base64(body) | Body::QuotedPrintable(body) => body.get_raw().len(),
+        Body::SevenBit(body) | Body::EightBit(body) => body.get_raw().len(),
+        Body::Binary(body) => body.get_raw().len(),
+    }
+}
+
+/// Decodes a large attachment directly into a blob file
+/// if the attachment doesn't need to be inspected by `do_add_single_file_part()`.
+///
+/// Returns `None` if the attachment should be decoded into memory instead.
+fn stream_file_part<'a>(
+    context: &'a Context,
+    mail: &mailparse::ParsedMail<'_>,
+    msg_type: Viewtype,
+    mime_type: &Mime,
+    filename: &str,
+    is_chat_msg: bool,
+) -> Option<(BlobObject<'a>, usize)> {
+    use std::io::Read as _;
+
+    if !matches!(
+        msg_type,
+        Viewtype::File | Viewtype::Audio | Viewtype::Voice | Viewtype::Video
+    ) || filename.ends_with(WEBXDC_SUFFIX)
+        || filename.ends_with(".kml")
+        || filename == "multi-device-sync.json"
+        || filename == "status-update.json"
+        || filename == "group-history.json"
+        || (mime_type.type_() == mime::APPLICATION && mime_type.subtype().as_str() == "pgp-keys")
+    {
+        return None;
+    }
+    if encoded_body_len(mail) <= STREAMING_PART_BYTES {
+        return None;
+    }
+    let body = mail.get_body_encoded();
+    if msg_type == Viewtype::File && !is_chat_msg {
+        // The type may need to be corrected by `do_add_single_file_part()`.
+        let mut prefix = Vec::new();
+        body_reader(&body)?.take(16).read_to_end(&mut prefix).ok()?;
+        if message::guess_msgtype_from_content(&prefix).is_some() {
+            return None;
+        }
+    }
+    match write_blob(context, body_reader(&body)?, filename) {
+        Ok(res) => Some(res),
+        Err(err) => {
+            warn!(context, "Cannot decode {filename:?} into a blob: {err:#}.");
+            None
+        }
+    }
+}
+
+/// Base64 engine as lenient as `mailparse` regarding padding.
+const LENIENT_BASE64: base64::engine::GeneralPurpose = base64::engine::GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    base64::engine::GeneralPurposeConfig::new()
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+);
+
+/// Returns a reader decoding the body,
+/// `None` for quoted-printable bodies which are not decoded incrementally.
+fn body_reader<'a>(body: &Body<'a>) -> Option<Box<dyn std::io::Read + 'a>> {
+    match body {
+        Body::Base64(body) => Some(Box::new(base64::read::DecoderReader::new(
+            SkipWhitespace(body.get_raw()),
+            &LENIENT_BASE64,
+        ))),
+        Body::SevenBit(body) | Body::EightBit(body) => Some(Box::new(body.get_raw())),
+        Body::Binary(body) => Some(Box::new(body.get_raw())),
+        Body::QuotedPrintable(_) => None,
+    }
+}
+
+/// Copies the data from `reader` into a new blob using a bounded buffer.
+///
+/// Returns the blob and the number of bytes written.
+fn write_blob<'a>(
+    context: &'a Context,
+    mut reader: impl std::io::Read,
+    filename: &str,
+) -> Result<(BlobObject<'a>, usize)> {
+    tokio::task::block_in_place(|| {
+        let temp_path = context
+            .get_blobdir()
+            .join(format!("tmp-{}", rand::random::<u64>()));
+        let bytes = match std::fs::File::create(&temp_path)
+            .and_then(|mut file| std::io::copy(&mut reader, &mut file))
+        {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                std::fs::remove_file(&temp_path).ok();
+                return Err(err).context("Cannot write blob");
+            }
+        };
+        let blob = BlobObject::create_and_deduplicate(context, &temp_path, Path::new(filename))?;
+        Ok((blob, usize::try_from(bytes)?))
+    })
+}
+
+/// Reader skipping ASCII whitespace, used to decode line-wrapped base64.
+struct SkipWhitespace<'a>(&'a [u8]);
+
+impl std::io::Read for SkipWhitespace<'_> {
+    #[expect(clippy::arithmetic_side_effects)]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+        for dst in buf.iter_mut() {
+            let Some((&byte, rest)) = self
+                .0
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .and_then(|pos| self.0.get(pos..))
+                .and_then(|data| data.split_first())
+            else {
+                self.0 = &[];
+                break;
+            };
+            *dst = byte;
+            self.0 = rest;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
 /// Returns the mimetype and viewtype for a parsed mail.
 ///
 /// This only looks at the metadata, not at the content;
@@ -2513,6 +2844,7 @@ async fn handle_mdn(
         chatlist_events::emit_chatlist_item_changed(context, chat_id);
     }
     context.emit_event(EventType::MsgReadCountChanged { chat_id, msg_id });
+    msg_id.emit_broadcast_delivery_update(context).await?;
     Ok(())
 }
 
@@ -2570,6 +2902,46 @@ async fn handle_ndn(
     Ok(())
 }
 
+/// Marks a message as received by the server of the recipient
+/// after a successful delivery status notification arrived.
+async fn handle_dsn_delivered(context: &Context, delivered: &DeliveryReport) -> Result<()> {
+    if delivered.rfc724_mid.is_empty() {
+        return Ok(());
+    }
+
+    let msgs = context
+        .sql
+        .query_map_vec(
+            "SELECT id, chat_id FROM msgs
+             WHERE rfc724_mid=? AND from_id=1 AND state=?",
+            (&delivered.rfc724_mid, MessageState::OutDelivered),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                Ok((msg_id, chat_id))
+            },
+        )
+        .await?;
+
+    for (msg_id, chat_id) in msgs {
+        context
+            .sql
+            .execute(
+                "UPDATE msgs SET state=? WHERE id=? AND state=?",
+                (
+                    MessageState::OutServerRcvd,
+                    msg_id,
+                    MessageState::OutDelivered,
+                ),
+            )
+            .await?;
+        context.emit_msgs_changed(chat_id, msg_id);
+        chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod mimeparser_tests;
 #[cfg(test)]
@@ -2176,3 +2176,131 @@ async fn test_load_shared_secrets_with_legacy_state() -> Result<()> {
 
     Ok(())
 }
+
+fn nested_mime(depth: usize) -> Vec<u8> {
+    let mut body = "Content-Type: text/plain\n\nHello\n".to_string();
+    for i in 0..depth {
+        body = format!(
+            "Content-Type: multipart/mixed; boundary=\"b{i}\"\n\n--b{i}\n{body}\n--b{i}--\n"
+        );
+    }
+    format!(
+        "From: sender@example.com\n\
+         To: receiver@example.com\n\
+         Subject: Nested\n\
+         Date: Thu, 13 Feb 2020 22:41:20 +0000\n\
+         {body}"
+    )
+    .into_bytes()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_parse_deeply_nested_mime() -> Result<()> {
+    let t = TestContext::new_alice().await;
+
+    let mimeparser = MimeMessage::from_bytes(&t, &nested_mime(5)).await?;
+    assert_eq!(mimeparser.parts.len(), 1);
+    assert_eq!(mimeparser.parts[0].msg, "Nested – Hello");
+
+    // Parts nested too deeply are ignored.
+    let mimeparser = MimeMessage::from_bytes(&t, &nested_mime(MAX_MIME_NESTING_DEPTH + 5)).await?;
+    assert!(
+        !mimeparser
+            .parts
+            .iter()
+            .any(|part| part.msg.contains("Hello"))
+    );
+
+    // Pathological nesting is rejected before `mailparse` recurses into it.
+    assert!(
+        MimeMessage::from_bytes(&t, &nested_mime(MAX_CONTENT_TYPE_HEADERS + 1))
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stream_large_attachment() -> Result<()> {
+    use base64::Engine as _;
+
+    let t = TestContext::new_alice().await;
+    let data: Vec<u8> = (0..STREAMING_PART_BYTES * 2)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+    let wrapped = encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    let raw = format!(
+        "From: sender@example.com\n\
+         To: receiver@example.com\n\
+         Subject: Large\n\
+         Date: Thu, 13 Feb 2020 22:41:20 +0000\n\
+         Content-Type: multipart/mixed; boundary=\"b\"\n\
+         \n\
+         --b\n\
+         Content-Type: application/octet-stream; name=\"large.bin\"\n\
+         Content-Transfer-Encoding: base64\n\
+         Content-Disposition: attachment; filename=\"large.bin\"\n\
+         \n\
+         {wrapped}\n\
+         --b--\n"
+    );
+
+    let mimeparser = MimeMessage::from_bytes(&t, raw.as_bytes()).await?;
+    assert_eq!(mimeparser.parts.len(), 1);
+    let part = &mimeparser.parts[0];
+    assert_eq!(part.typ, Viewtype::File);
+    assert_eq!(part.bytes, data.len());
+    let blob = part.param.get_file_path(&t)?.unwrap();
+    assert_eq!(tokio::fs::read(blob).await?, data);
+    Ok(())
+}
+
+#[test]
+fn test_skip_whitespace() {
+    use std::io::Read as _;
+
+    let mut decoded = String::new();
+    SkipWhitespace(b" a b\r\n\tc \n")
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, "abc");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sniff_attachment_content_type() -> Result<()> {
+    use base64::Engine as _;
+
+    let t = TestContext::new_alice().await;
+    let data = include_bytes!("../../test-data/image/logo.png");
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let raw = format!(
+        "From: sender@example.com\n\
+         To: receiver@example.com\n\
+         Subject: Photo\n\
+         Date: Thu, 13 Feb 2020 22:41:20 +0000\n\
+         Content-Type: multipart/mixed; boundary=\"b\"\n\
+         \n\
+         --b\n\
+         Content-Type: application/octet-stream; name=\"photo.bin\"\n\
+         Content-Transfer-Encoding: base64\n\
+         Content-Disposition: attachment; filename=\"photo.bin\"\n\
+         \n\
+         {encoded}\n\
+         --b--\n"
+    );
+
+    let mimeparser = MimeMessage::from_bytes(&t, raw.as_bytes()).await?;
+    assert_eq!(mimeparser.parts.len(), 1);
+    let part = &mimeparser.parts[0];
+    assert_eq!(part.typ, Viewtype::Image);
+    assert_eq!(part.mimetype.as_ref().unwrap().essence_str(), "image/png");
+    assert_eq!(part.param.get(Param::Filename), Some("photo.bin"));
+    assert!(part.param.get(Param::File).unwrap().ends_with(".png"));
+    Ok(())
+}
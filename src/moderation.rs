@@ -0,0 +1,214 @@
+//! # Reporting abuse to channel owners.
+//!
+//! Subscribers of a broadcast channel or a mailing list
+//! can report a message to the owner with [`MsgId::report_to_owner()`].
+//!
+//! Reports about broadcast channel messages are sent encrypted to the channel owner.
+//! The owner's device adds them to the list returned by [`Context::get_abuse_reports()`]
+//! and emits [`EventType::IncomingAbuseReport`].
+//!
+//! Reports about mailing list messages are sent to the list owner address
+//! announced in the `List-Owner` header defined in RFC 2369,
+//! the text of the report is readable by humans.
+//! If the mailing list has no `List-Owner` header, messages cannot be reported.
+//!
+//! [`EventType::IncomingAbuseReport`]: crate::EventType::IncomingAbuseReport
+
+use anyhow::{Context as _, Result, bail, ensure};
+use deltachat_contact_tools::ContactAddress;
+use serde::{Deserialize, Serialize};
+
+use crate::EventType;
+use crate::chat::{self, Chat, ChatId, ChatIdBlocked, get_chat_id_by_grpid, is_contact_in_chat};
+use crate::constants::{Blocked, Chattype};
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::message::{Message, MsgId, Viewtype};
+use crate::mimeparser::SystemMessage;
+use crate::param::Param;
+use crate::tools::time;
+
+/// Maximum length of the reason of a report in characters.
+const MAX_REASON_LEN: usize = 1000;
+
+/// Report about a message, serialized into `abuse-report.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Report {
+    /// Group ID of the channel or List-ID of the mailing list.
+    grpid: String,
+
+    /// Message-ID of the reported message.
+    rfc724_mid: String,
+
+    /// Reason given by the reporter.
+    reason: String,
+}
+
+/// Report about a message of an outgoing broadcast channel,
+/// see [`Context::get_abuse_reports()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbuseReport {
+    /// ID of the report, to be passed to [`Context::delete_abuse_report()`].
+    pub id: u32,
+
+    /// ID of the broadcast channel.
+    pub chat_id: ChatId,
+
+    /// ID of the reported message.
+    pub msg_id: MsgId,
+
+    /// ID of the subscriber who sent the report.
+    pub contact_id: ContactId,
+
+    /// Reason given by the subscriber.
+    pub reason: String,
+
+    /// Time the report was received.
+    pub timestamp: i64,
+}
+
+impl MsgId {
+    /// Reports a message of a broadcast channel or mailing list to its owner,
+    /// e.g. because it is spam or abusive.
+    ///
+    /// `reason` is a free-form text shown to the owner.
+    pub async fn report_to_owner(self, context: &Context, reason: &str) -> Result<()> {
+        let msg = Message::load_from_db(context, self).await?;
+        ensure!(
+            !msg.from_id.is_special(),
+            "Cannot report message {self} not sent by a contact"
+        );
+        let chat = Chat::load_from_db(context, msg.chat_id).await?;
+        let report = Report {
+            grpid: chat.grpid.clone(),
+            rfc724_mid: msg.rfc724_mid.clone(),
+            reason: reason.trim().chars().take(MAX_REASON_LEN).collect(),
+        };
+
+        let mut report_msg = Message {
+            viewtype: Viewtype::Text,
+            hidden: true,
+            ..Default::default()
+        };
+        let owner_id = match chat.typ {
+            Chattype::InBroadcast => {
+                report_msg.param.set_int(Param::GuaranteeE2ee, 1);
+                msg.from_id
+            }
+            Chattype::Mailinglist => {
+                let owner_addr = chat
+                    .param
+                    .get(Param::ListOwner)
+                    .context("Mailing list has no List-Owner to send reports to")?;
+                let owner_addr = ContactAddress::new(owner_addr)?;
+                let (owner_id, _) =
+                    Contact::add_or_lookup(context, "", &owner_addr, Origin::Hidden).await?;
+                owner_id
+            }
+            _ => bail!("Can only report messages of broadcast channels and mailing lists"),
+        };
+        report_msg.text = format!(
+            "Report about message {} in {}:\n\n{}",
+            report.rfc724_mid,
+            chat.get_name(),
+            report.reason
+        );
+        report_msg.param.set_cmd(SystemMessage::AbuseReport);
+        report_msg
+            .param
+            .set(Param::Arg, serde_json::to_string(&report)?);
+        let chat_id = ChatIdBlocked::get_for_contact(context, owner_id, Blocked::Yes)
+            .await?
+            .id;
+        info!(context, "Reporting {self} to {owner_id}.");
+        chat::send_msg(context, chat_id, &mut report_msg).await?;
+        Ok(())
+    }
+}
+
+/// Adds a report sent by `from_id` to the list of reports.
+pub(crate) async fn receive_report(
+    context: &Context,
+    from_id: ContactId,
+    json: &str,
+) -> Result<()> {
+    let report: Report = serde_json::from_str(json).context("Failed to parse abuse report")?;
+    let (chat_id, _blocked) = get_chat_id_by_grpid(context, &report.grpid)
+        .await?
+        .with_context(|| format!("No channel for grpid {:?}", report.grpid))?;
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(
+        chat.typ == Chattype::OutBroadcast,
+        "{chat_id} is not an outgoing broadcast channel"
+    );
+    ensure!(
+        is_contact_in_chat(context, chat_id, from_id).await?,
+        "{from_id} is not a subscriber of {chat_id}"
+    );
+    let msg_id: MsgId = context
+        .sql
+        .query_get_value(
+            "SELECT id FROM msgs WHERE rfc724_mid=? AND chat_id=?",
+            (&report.rfc724_mid, chat_id),
+        )
+        .await?
+        .with_context(|| format!("No message {:?} in {chat_id}", report.rfc724_mid))?;
+    let reason: String = report.reason.chars().take(MAX_REASON_LEN).collect();
+    context
+        .sql
+        .execute(
+            "INSERT INTO abuse_reports (chat_id, msg_id, contact_id, reason, timestamp)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (msg_id, contact_id) DO UPDATE SET
+             reason=excluded.reason, timestamp=excluded.timestamp",
+            (chat_id, msg_id, from_id, reason, time()),
+        )
+        .await?;
+    info!(context, "{from_id} reported {msg_id} in {chat_id}.");
+    context.emit_event(EventType::IncomingAbuseReport {
+        chat_id,
+        msg_id,
+        contact_id: from_id,
+    });
+    Ok(())
+}
+
+impl Context {
+    /// Returns reports about messages of outgoing broadcast channels,
+    /// newest first.
+    ///
+    /// If `chat_id` is set, only reports about messages of this channel are returned.
+    pub async fn get_abuse_reports(&self, chat_id: Option<ChatId>) -> Result<Vec<AbuseReport>> {
+        self.sql
+            .query_map_vec(
+                "SELECT r.id, r.chat_id, r.msg_id, r.contact_id, r.reason, r.timestamp
+                 FROM abuse_reports r
+                 INNER JOIN msgs m ON m.id=r.msg_id AND m.chat_id=r.chat_id
+                 WHERE ?1 IS NULL OR r.chat_id=?1
+                 ORDER BY r.timestamp DESC, r.id DESC",
+                (chat_id,),
+                |row| {
+                    Ok(AbuseReport {
+                        id: row.get(0)?,
+                        chat_id: row.get(1)?,
+                        msg_id: row.get(2)?,
+                        contact_id: row.get(3)?,
+                        reason: row.get(4)?,
+                        timestamp: row.get(5)?,
+                    })
+                },
+            )
+            .await
+    }
+
+    /// Deletes the report with the given ID, e.g. after it was handled.
+    pub async fn delete_abuse_report(&self, id: u32) -> Result<()> {
+        self.sql
+            .execute("DELETE FROM abuse_reports WHERE id=?", (id,))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod moderation_tests;
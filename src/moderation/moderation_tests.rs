@@ -0,0 +1,148 @@
+use super::*;
+use crate::chat::create_broadcast;
+use crate::receive_imf::receive_imf;
+use crate::securejoin::get_securejoin_qr;
+use crate::test_utils::TestContextManager;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_report_to_owner() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_bc_id = create_broadcast(alice, "Channel".to_string()).await?;
+    let qr = get_securejoin_qr(alice, Some(alice_bc_id)).await?;
+    tcm.exec_securejoin_qr(bob, alice, &qr).await;
+
+    let sent = alice.send_text(alice_bc_id, "Buy cheap pills").await;
+    let bob_msg = bob.recv_msg(&sent).await;
+
+    bob_msg
+        .id
+        .report_to_owner(bob, "  Spam, not what I subscribed to  ")
+        .await?;
+    let report = bob.pop_sent_msg().await;
+    assert!(!report.payload.contains("Spam, not what I subscribed to"));
+    alice.recv_msg_trash(&report).await;
+
+    let alice_bob_id = alice.add_or_lookup_contact_id(bob).await;
+    let event = alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::IncomingAbuseReport { .. }))
+        .await;
+    assert_eq!(
+        event,
+        EventType::IncomingAbuseReport {
+            chat_id: alice_bc_id,
+            msg_id: sent.sender_msg_id,
+            contact_id: alice_bob_id,
+        }
+    );
+
+    let reports = alice.get_abuse_reports(None).await?;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].chat_id, alice_bc_id);
+    assert_eq!(reports[0].msg_id, sent.sender_msg_id);
+    assert_eq!(reports[0].contact_id, alice_bob_id);
+    assert_eq!(reports[0].reason, "Spam, not what I subscribed to");
+    assert_eq!(alice.get_abuse_reports(Some(alice_bc_id)).await?, reports);
+
+    // Reporting the same message again replaces the report.
+    bob_msg.id.report_to_owner(bob, "Scam").await?;
+    alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+    let reports = alice.get_abuse_reports(None).await?;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].reason, "Scam");
+
+    let alice_chat_id = alice.create_chat_id(bob).await;
+    assert!(
+        alice
+            .get_abuse_reports(Some(alice_chat_id))
+            .await?
+            .is_empty()
+    );
+
+    alice.delete_abuse_report(reports[0].id).await?;
+    assert!(alice.get_abuse_reports(None).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_report_only_channels() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat_id = alice.create_chat_id(bob).await;
+    let msg = bob
+        .recv_msg(&alice.send_text(alice_chat_id, "Hi").await)
+        .await;
+    assert!(msg.id.report_to_owner(bob, "Spam").await.is_err());
+    assert!(bob.pop_sent_msg_opt().await.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_report_mailinglist() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    alice.allow_unencrypted().await?;
+
+    receive_imf(
+        alice,
+        b"From: Bob <bob@posteo.org>\n\
+          To: delta@codespeak.net\n\
+          Subject: [delta-dev] Cheap pills\n\
+          Message-ID: <38942@posteo.org>\n\
+          List-ID: \"discussions about and around https://delta.chat developments\" <delta.codespeak.net>\n\
+          List-Post: <mailto:delta@codespeak.net>\n\
+          List-Owner: <https://codespeak.net/owner>, <mailto:list-admin@codespeak.net?subject=help>\n\
+          Precedence: list\n\
+          Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+          \n\
+          Buy now!\n",
+        false,
+    )
+    .await?;
+    let msg = alice.get_last_msg().await;
+
+    msg.id.report_to_owner(alice, "Spam").await?;
+    let report = alice.pop_sent_msg().await;
+    assert!(report.recipients.contains("list-admin@codespeak.net"));
+    assert!(
+        report
+            .payload
+            .contains("Report about message 38942@posteo.org")
+    );
+    assert!(report.payload.contains("abuse-report.json"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_report_mailinglist_without_owner() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    alice.allow_unencrypted().await?;
+
+    receive_imf(
+        alice,
+        b"From: Bob <bob@posteo.org>\n\
+          To: delta@codespeak.net\n\
+          Subject: [delta-dev] Cheap pills\n\
+          Message-ID: <38943@posteo.org>\n\
+          List-ID: \"discussions about and around https://delta.chat developments\" <delta.codespeak.net>\n\
+          List-Post: <mailto:delta@codespeak.net>\n\
+          Precedence: list\n\
+          Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+          \n\
+          Buy now!\n",
+        false,
+    )
+    .await?;
+    let msg = alice.get_last_msg().await;
+
+    // The owner address is not guessed.
+    assert!(msg.id.report_to_owner(alice, "Spam").await.is_err());
+    assert!(alice.pop_sent_msg_opt().await.is_none());
+    Ok(())
+}
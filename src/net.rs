@@ -16,15 +16,20 @@
 use crate::sql::Sql;
 use crate::tools::time;
 
+pub(crate) mod bandwidth;
 pub(crate) mod dns;
 pub(crate) mod http;
+pub(crate) mod profile;
 pub(crate) mod proxy;
 pub(crate) mod session;
 pub(crate) mod tls;
 
+pub use bandwidth::{BandwidthStats, ConnectionType};
 use dns::lookup_host_with_cache;
 pub(crate) use http::read_url_with_tls;
 pub use http::{Response as HttpResponse, read_url, read_url_blob};
+pub use profile::NetworkProfile;
+pub use proxy::ProxyHealth;
 use tls::wrap_tls;
 
 /// Connection, write and read timeout.
@@ -0,0 +1,408 @@
+//! # Bandwidth accounting.
+//!
+//! Bytes sent and received are counted in memory per connection type and UTC day
+//! and stored in the database every [`BANDWIDTH_FLUSH_INTERVAL`]
+//! while IO is running, when IO is stopped and during housekeeping.
+//!
+//! The counted bytes are the application data read from and written to
+//! the connection streams, above TLS,
+//! so TLS, TCP and IP overhead is not included.
+//! Backup transfer between devices over iroh is not counted.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use anyhow::Result;
+use deltachat_derive::{FromSql, ToSql};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::context::Context;
+use crate::log::warn;
+use crate::net::session::SessionStream;
+use crate::tools::{time, usize_to_u64};
+
+/// Interval of storing the counted traffic in the database while IO is running.
+const BANDWIDTH_FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Type of the network connection traffic is accounted for.
+#[derive(
+    Debug,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromPrimitive,
+    ToPrimitive,
+    FromSql,
+    ToSql,
+    Serialize,
+    Deserialize,
+)]
+#[repr(u32)]
+pub enum ConnectionType {
+    /// IMAP connections.
+    Imap = 1,
+
+    /// SMTP connections.
+    Smtp = 2,
+
+    /// HTTP(S) requests.
+    Http = 3,
+
+    /// Iroh peer-to-peer connections.
+    Iroh = 4,
+}
+
+/// Number of bytes sent and received per UTC day, not yet stored in the database.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounter {
+    /// Bytes sent and received by the start of the day.
+    days: Mutex<BTreeMap<i64, (u64, u64)>>,
+}
+
+impl TrafficCounter {
+    pub(crate) fn add_sent(&self, n: usize) {
+        self.add(usize_to_u64(n), 0);
+    }
+
+    pub(crate) fn add_received(&self, n: usize) {
+        self.add(0, usize_to_u64(n));
+    }
+
+    fn add(&self, sent: u64, received: u64) {
+        if sent == 0 && received == 0 {
+            return;
+        }
+        let day = day_start(time());
+        let mut days = self.days.lock();
+        let (day_sent, day_received) = days.entry(day).or_default();
+        *day_sent = day_sent.saturating_add(sent);
+        *day_received = day_received.saturating_add(received);
+    }
+
+    /// Returns the number of bytes sent and received per day and resets the counter.
+    fn take(&self) -> BTreeMap<i64, (u64, u64)> {
+        std::mem::take(&mut *self.days.lock())
+    }
+
+    /// Adds back numbers returned by [`Self::take`] if storing them failed.
+    fn restore(&self, days: BTreeMap<i64, (u64, u64)>) {
+        let mut counted_days = self.days.lock();
+        for (day, (sent, received)) in days {
+            let (day_sent, day_received) = counted_days.entry(day).or_default();
+            *day_sent = day_sent.saturating_add(sent);
+            *day_received = day_received.saturating_add(received);
+        }
+    }
+}
+
+/// Traffic counters of the context, one per connection type.
+#[derive(Debug, Default)]
+pub(crate) struct BandwidthCounters {
+    imap: Arc<TrafficCounter>,
+    smtp: Arc<TrafficCounter>,
+    http: Arc<TrafficCounter>,
+    iroh: Arc<TrafficCounter>,
+}
+
+impl BandwidthCounters {
+    pub(crate) fn get(&self, connection_type: ConnectionType) -> &Arc<TrafficCounter> {
+        match connection_type {
+            ConnectionType::Imap => &self.imap,
+            ConnectionType::Smtp => &self.smtp,
+            ConnectionType::Http => &self.http,
+            ConnectionType::Iroh => &self.iroh,
+        }
+    }
+}
+
+/// Traffic of a single connection type during a single day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandwidthStats {
+    /// Timestamp of the day start, UTC.
+    pub day: i64,
+
+    /// Connection type.
+    pub connection_type: ConnectionType,
+
+    /// Number of bytes sent.
+    pub bytes_sent: u64,
+
+    /// Number of bytes received.
+    pub bytes_received: u64,
+}
+
+/// Stream counting bytes sent and received.
+#[derive(Debug)]
+#[pin_project]
+pub(crate) struct MeteredStream<S> {
+    #[pin]
+    inner: S,
+
+    counter: Arc<TrafficCounter>,
+}
+
+impl<S> MeteredStream<S> {
+    pub(crate) fn new(context: &Context, connection_type: ConnectionType, inner: S) -> Self {
+        Self {
+            inner,
+            counter: Arc::clone(context.bandwidth.get(connection_type)),
+        }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for MeteredStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let old_filled = buf.filled().len();
+        let res = this.inner.poll_read(cx, buf);
+        this.counter
+            .add_received(buf.filled().len().saturating_sub(old_filled));
+        res
+    }
+}
+
+impl<S: AsyncBufRead> AsyncBufRead for MeteredStream<S> {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        self.project().inner.poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+        this.counter.add_received(amt);
+        this.inner.consume(amt)
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for MeteredStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            this.counter.add_sent(n);
+        }
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = res {
+            this.counter.add_sent(n);
+        }
+        res
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+impl<S: SessionStream> SessionStream for MeteredStream<S> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+}
+
+/// Returns the timestamp of the start of the UTC day containing `timestamp`.
+fn day_start(timestamp: i64) -> i64 {
+    timestamp.saturating_sub(timestamp.rem_euclid(24 * 60 * 60))
+}
+
+/// Adds the traffic counted since the last call to the daily totals in the database.
+pub(crate) async fn flush_bandwidth_stats(context: &Context) -> Result<()> {
+    for connection_type in [
+        ConnectionType::Imap,
+        ConnectionType::Smtp,
+        ConnectionType::Http,
+        ConnectionType::Iroh,
+    ] {
+        let counter = context.bandwidth.get(connection_type);
+        let days = counter.take();
+        if days.is_empty() {
+            continue;
+        }
+        let res = context
+            .sql
+            .transaction(|transaction| {
+                let mut stmt = transaction.prepare(
+                    "INSERT INTO bandwidth_stats (day, connection_type, bytes_sent, bytes_received)
+                     VALUES (?, ?, ?, ?)
+                     ON CONFLICT (day, connection_type) DO UPDATE SET
+                     bytes_sent=bytes_sent+excluded.bytes_sent,
+                     bytes_received=bytes_received+excluded.bytes_received",
+                )?;
+                for (&day, &(sent, received)) in &days {
+                    stmt.execute((
+                        day,
+                        connection_type,
+                        i64::try_from(sent).unwrap_or(i64::MAX),
+                        i64::try_from(received).unwrap_or(i64::MAX),
+                    ))?;
+                }
+                Ok(())
+            })
+            .await;
+        if let Err(err) = res {
+            counter.restore(days);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Stores the counted traffic in the database every [`BANDWIDTH_FLUSH_INTERVAL`].
+pub(crate) async fn bandwidth_loop(context: &Context) {
+    loop {
+        tokio::time::sleep(BANDWIDTH_FLUSH_INTERVAL).await;
+        if let Err(err) = flush_bandwidth_stats(context).await {
+            warn!(context, "Failed to store bandwidth stats: {err:#}.");
+        }
+    }
+}
+
+impl Context {
+    /// Returns daily network traffic per connection type
+    /// for the days starting within `range`, a range of unix timestamps.
+    ///
+    /// Days are UTC days, days without traffic are omitted.
+    /// Results are ordered by day and connection type.
+    /// See the [module documentation](crate::net::bandwidth) for what is counted.
+    pub async fn get_bandwidth_stats(&self, range: Range<i64>) -> Result<Vec<BandwidthStats>> {
+        flush_bandwidth_stats(self).await?;
+        self.sql
+            .query_map_vec(
+                "SELECT day, connection_type, bytes_sent, bytes_received
+                 FROM bandwidth_stats
+                 WHERE day>=? AND day<?
+                 ORDER BY day, connection_type",
+                (range.start, range.end),
+                |row| {
+                    let bytes_sent: i64 = row.get(2)?;
+                    let bytes_received: i64 = row.get(3)?;
+                    Ok(BandwidthStats {
+                        day: row.get(0)?,
+                        connection_type: row.get(1)?,
+                        bytes_sent: u64::try_from(bytes_sent).unwrap_or_default(),
+                        bytes_received: u64::try_from(bytes_received).unwrap_or_default(),
+                    })
+                },
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_day_start() {
+        assert_eq!(day_start(0), 0);
+        assert_eq!(day_start(86399), 0);
+        assert_eq!(day_start(86400), 86400);
+        assert_eq!(day_start(1_700_000_000), 1_699_920_000);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bandwidth_stats() -> Result<()> {
+        let t = TestContext::new().await;
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut stream = MeteredStream::new(&t, ConnectionType::Smtp, client);
+        stream.write_all(b"EHLO localhost\r\n").await?;
+        server.write_all(b"250 OK\r\n").await?;
+        let mut buf = [0u8; 8];
+        stream.read_exact(&mut buf).await?;
+
+        t.bandwidth.get(ConnectionType::Http).add_received(1000);
+
+        let today = day_start(time());
+        let stats = t.get_bandwidth_stats(today..today + 86400).await?;
+        assert_eq!(
+            stats,
+            vec![
+                BandwidthStats {
+                    day: today,
+                    connection_type: ConnectionType::Smtp,
+                    bytes_sent: 16,
+                    bytes_received: 8,
+                },
+                BandwidthStats {
+                    day: today,
+                    connection_type: ConnectionType::Http,
+                    bytes_sent: 0,
+                    bytes_received: 1000,
+                },
+            ]
+        );
+
+        // Traffic is added to the daily totals.
+        t.bandwidth.get(ConnectionType::Http).add_sent(5);
+        let stats = t.get_bandwidth_stats(today..today + 86400).await?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].bytes_sent, 5);
+        assert_eq!(stats[1].bytes_received, 1000);
+
+        assert!(t.get_bandwidth_stats(0..today).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bandwidth_stats_per_day() -> Result<()> {
+        let t = TestContext::new().await;
+        let today = day_start(time());
+        let yesterday = today - 86400;
+
+        // Traffic counted before midnight is stored for the previous day.
+        let counter = t.bandwidth.get(ConnectionType::Imap);
+        counter.days.lock().insert(yesterday, (1, 2));
+        counter.add_sent(3);
+        flush_bandwidth_stats(&t).await?;
+        let stats = t.get_bandwidth_stats(yesterday..today + 86400).await?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].day, stats[0].bytes_sent), (yesterday, 1));
+        assert_eq!((stats[1].day, stats[1].bytes_sent), (today, 3));
+        assert!(counter.take().is_empty());
+        Ok(())
+    }
+}
@@ -1,15 +1,20 @@
 //! # HTTP module.
 
-use anyhow::{Context as _, Result, anyhow, bail};
+use std::path::Path;
+
+use anyhow::{Context as _, Result, anyhow, bail, ensure};
 use bytes::Bytes;
-use http_body_util::BodyExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Frame;
 use hyper_util::rt::TokioIo;
 use mime::Mime;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::blob::BlobObject;
 use crate::context::Context;
 use crate::log::warn;
+use crate::net::bandwidth::{ConnectionType, MeteredStream};
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_tls;
@@ -112,7 +117,7 @@ async fn get_http_sender<B>(
         _ => bail!("Unknown URL scheme"),
     };
 
-    let io = TokioIo::new(stream);
+    let io = TokioIo::new(MeteredStream::new(context, ConnectionType::Http, stream));
     let (sender, conn) = hyper::client::conn::http1::handshake(io).await?;
     tokio::task::spawn(conn);
 
@@ -423,6 +428,34 @@ pub(crate) async fn post_empty(context: &Context, url: &str) -> Result<(String,
     Ok((response_text, response_status.is_success()))
 }
 
+/// Returns the current time of the server at `url`
+/// taken from the `Date` header of the response to an HTTPS HEAD request.
+///
+/// Does not follow redirects.
+pub(crate) async fn get_server_time(context: &Context, url: &str) -> Result<i64> {
+    let parsed_url = url
+        .parse::<hyper::Uri>()
+        .with_context(|| format!("Failed to parse URL {url:?}"))?;
+    let mut sender = get_http_sender(context, parsed_url.clone(), true).await?;
+    let authority = parsed_url
+        .authority()
+        .context("URL has no authority")?
+        .clone();
+    let req = hyper::Request::head(parsed_url)
+        .header(hyper::header::HOST, authority.as_str())
+        .body(http_body_util::Empty::<Bytes>::new())?;
+    let response = sender.send_request(req).await?;
+
+    let date = response
+        .headers()
+        .get(hyper::header::DATE)
+        .context("Response has no Date header")?
+        .to_str()?;
+    let date = chrono::DateTime::parse_from_rfc2822(date)
+        .with_context(|| format!("Failed to parse Date header {date:?}"))?;
+    Ok(date.timestamp())
+}
+
 /// Posts string to the given URL.
 ///
 /// Returns true if successful HTTP response code was returned.
@@ -452,15 +485,168 @@ pub(crate) async fn post_string(context: &Context, url: &str, body: String) -> R
     Ok(response.status().is_success())
 }
 
+/// Uploads the file at `path` to the given URL using HTTP PUT request.
+///
+/// The file is streamed, so it is never loaded into memory completely.
+/// If `token` is set, it is sent as a bearer token in the `Authorization` header.
+///
+/// Does not follow redirects.
+pub(crate) async fn put_file(
+    context: &Context,
+    url: &str,
+    token: Option<&str>,
+    path: &Path,
+) -> Result<()> {
+    let parsed_url = url
+        .parse::<hyper::Uri>()
+        .with_context(|| format!("Failed to parse URL {url:?}"))?;
+    let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
+    // Tests upload to a local plain HTTP server.
+    if scheme != "https" && !(cfg!(test) && scheme == "http") {
+        bail!("PUT requests to non-HTTPS URLs are not allowed");
+    }
+
+    let file = fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file.metadata().await?.len();
+    let stream = futures::stream::unfold(file, |mut file| async move {
+        let mut buf = vec![0u8; 64 * 1024];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Frame::data(Bytes::from(buf))), file))
+            }
+            Err(err) => Some((Err(err), file)),
+        }
+    });
+
+    let mut sender = get_http_sender(context, parsed_url.clone(), true).await?;
+    let authority = parsed_url
+        .authority()
+        .context("URL has no authority")?
+        .clone();
+
+    let mut request = hyper::Request::put(parsed_url)
+        .header(hyper::header::HOST, authority.as_str())
+        .header(hyper::header::CONTENT_TYPE, "application/octet-stream")
+        .header(hyper::header::CONTENT_LENGTH, len);
+    if let Some(token) = token {
+        request = request.header(hyper::header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let request = request.body(StreamBody::new(stream))?;
+    let response = sender.send_request(request).await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!("Upload to {url:?} failed with status {status}");
+    }
+    Ok(())
+}
+
+/// Downloads the given URL using HTTP GET request into a new file at `path`.
+///
+/// Unlike [`read_url_blob`], the response is not cached
+/// and redirects are not followed.
+/// The response is streamed to the file
+/// and the download fails if it is larger than `max_bytes`.
+/// Returns the number of bytes downloaded.
+pub(crate) async fn get_to_file(
+    context: &Context,
+    url: &str,
+    path: &Path,
+    max_bytes: u64,
+) -> Result<u64> {
+    let parsed_url = url
+        .parse::<hyper::Uri>()
+        .with_context(|| format!("Failed to parse URL {url:?}"))?;
+    let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
+    if scheme != "https" {
+        bail!("GET requests to non-HTTPS URLs are not allowed");
+    }
+
+    let mut sender = get_http_sender(context, parsed_url.clone(), true).await?;
+    let authority = parsed_url
+        .authority()
+        .context("URL has no authority")?
+        .clone();
+
+    let request = hyper::Request::get(parsed_url)
+        .header(hyper::header::HOST, authority.as_str())
+        .body(http_body_util::Empty::<Bytes>::new())?;
+    let response = sender.send_request(request).await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!("Download from {url:?} failed with status {status}");
+    }
+    if let Some(len) = response
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
+    {
+        ensure!(
+            len <= max_bytes,
+            "Download from {url:?} is too large ({len} bytes)"
+        );
+    }
+
+    let mut file = fs::File::create(path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut body = response.into_body();
+    let mut received: u64 = 0;
+    while let Some(frame) = body.frame().await {
+        let Ok(data) = frame?.into_data() else {
+            continue;
+        };
+        received = received.saturating_add(data.len() as u64);
+        ensure!(
+            received <= max_bytes,
+            "Download from {url:?} is larger than {max_bytes} bytes"
+        );
+        file.write_all(&data).await?;
+    }
+    file.flush().await?;
+    Ok(received)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::time::Duration;
 
     use crate::sql::housekeeping;
-    use crate::test_utils::TestContext;
+    use crate::test_utils::{TestContext, start_http_server};
     use crate::tools::SystemTime;
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_put_file() -> Result<()> {
+        let t = &TestContext::new().await;
+        let path = t.get_blobdir().join("upload.bin");
+        fs::write(&path, b"attachment").await?;
+
+        let (url, bodies) = start_http_server(201).await?;
+        put_file(t, &format!("{url}/abc"), Some("token"), &path).await?;
+        assert_eq!(bodies.recv().await?, b"attachment");
+
+        let (url, bodies) = start_http_server(500).await?;
+        assert!(
+            put_file(t, &format!("{url}/abc"), None, &path)
+                .await
+                .is_err()
+        );
+        assert_eq!(bodies.recv().await?, b"attachment");
+
+        assert!(
+            put_file(t, "ftp://127.0.0.1/abc", None, &path)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_http_cache() -> Result<()> {
         let t = &TestContext::new().await;
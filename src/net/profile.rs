@@ -0,0 +1,91 @@
+//! # Network profile.
+//!
+//! UIs report the type of the current network connection
+//! so the core can adjust its network usage.
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::context::Context;
+
+/// Type of the network the device is connected to.
+///
+/// Set by the UI with [`Context::set_network_profile`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum NetworkProfile {
+    /// Unmetered network, e.g. Wi-Fi.
+    #[default]
+    Wifi = 0,
+
+    /// Metered network, e.g. mobile data.
+    ///
+    /// Network usage is reduced like with [`Config::DataSaver`]
+    /// and less messages are prefetched at once.
+    Metered = 1,
+
+    /// No network.
+    ///
+    /// No connections to IMAP and SMTP servers are attempted.
+    /// Backup transfer and [`Config::LanMessaging`] are not affected
+    /// as they only use the local network.
+    Offline = 2,
+}
+
+impl Context {
+    /// Sets the type of the network the device is connected to.
+    ///
+    /// UIs should call this whenever the network changes.
+    /// The profile is not persisted and is [`NetworkProfile::Wifi`] on startup.
+    pub async fn set_network_profile(&self, profile: NetworkProfile) {
+        let old_profile = std::mem::replace(&mut *self.network_profile.write().await, profile);
+        if old_profile == profile {
+            return;
+        }
+        info!(self, "Network profile changed to {profile:?}.");
+        if profile == NetworkProfile::Offline {
+            self.scheduler.maybe_network_lost(self).await;
+        } else if old_profile == NetworkProfile::Offline {
+            self.maybe_network().await;
+        }
+    }
+
+    /// Returns the network profile set with [`Context::set_network_profile`].
+    pub async fn get_network_profile(&self) -> NetworkProfile {
+        *self.network_profile.read().await
+    }
+
+    /// Returns true if network usage should be reduced,
+    /// because [`Config::DataSaver`] is enabled or the network is metered.
+    pub(crate) async fn is_data_saving(&self) -> Result<bool> {
+        Ok(self.get_network_profile().await == NetworkProfile::Metered
+            || self.get_config_bool(Config::DataSaver).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_network_profile() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert_eq!(t.get_network_profile().await, NetworkProfile::Wifi);
+        assert!(!t.is_data_saving().await?);
+
+        t.set_network_profile(NetworkProfile::Metered).await;
+        assert_eq!(t.get_network_profile().await, NetworkProfile::Metered);
+        assert!(t.is_data_saving().await?);
+        assert_eq!(
+            crate::download::download_limit(&t).await?,
+            Some(crate::download::DATA_SAVER_DOWNLOAD_LIMIT)
+        );
+
+        t.set_network_profile(NetworkProfile::Wifi).await;
+        assert!(!t.is_data_saving().await?);
+        t.set_config_bool(Config::DataSaver, true).await?;
+        assert!(t.is_data_saving().await?);
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@
 use fast_socks5::Socks5Command;
 use fast_socks5::client::Socks5Stream;
 use fast_socks5::util::target_addr::ToTargetAddr;
+use fast_socks5::{ReplyError, SocksError};
 use percent_encoding::{NON_ALPHANUMERIC, percent_encode, utf8_percent_encode};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
@@ -20,10 +21,14 @@
 
 use crate::config::Config;
 use crate::context::Context;
+use crate::events::EventType;
+use crate::log::{LogExt, warn};
 use crate::net::connect_tcp;
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_rustls;
 use crate::sql::Sql;
+use crate::sync::Sync::Nosync;
+use crate::tools::time;
 
 /// Default SOCKS5 port according to [RFC 1928](https://tools.ietf.org/html/rfc1928).
 pub const DEFAULT_SOCKS_PORT: u16 = 1080;
@@ -139,7 +144,17 @@ async fn connect(
         let target_addr = (target_host, target_port).to_target_addr()?;
         socks_stream
             .request(Socks5Command::TCPConnect, target_addr)
-            .await?;
+            .await
+            .map_err(|err| match err {
+                SocksError::ReplyError(
+                    ReplyError::NetworkUnreachable
+                    | ReplyError::HostUnreachable
+                    | ReplyError::ConnectionRefused
+                    | ReplyError::ConnectionTimeout
+                    | ReplyError::TtlExpired,
+                ) => TargetError(format!("SOCKS5 proxy failed to connect: {err}")).into(),
+                err => anyhow::Error::from(err),
+            })?;
 
         Ok(socks_stream)
     }
@@ -189,6 +204,15 @@ fn http_connect_request(host: &str, port: u16, auth: Option<(&str, &str)>) -> St
     res
 }
 
+/// Error reported by the proxy about the connection to the target server,
+/// e.g. because the target host is unreachable.
+///
+/// Such errors show that the proxy itself works,
+/// so they are not counted as failures of the proxy.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct TargetError(String);
+
 /// Sends HTTP/1.1 `CONNECT` request over given connection
 /// to establish an HTTP tunnel.
 ///
@@ -242,6 +266,12 @@ async fn http_tunnel<T>(mut conn: T, host: &str, port: u16, auth: Option<(&str,
     } else if status_code.starts_with(b"2") {
         // Success.
         Ok(conn)
+    } else if matches!(status_code, b"502" | b"503" | b"504") {
+        // Bad Gateway, Service Unavailable or Gateway Timeout.
+        Err(TargetError(format!(
+            "Failed to connect through HTTP CONNECT tunnel: {res:?}"
+        ))
+        .into())
     } else {
         Err(format_err!(
             "Failed to establish HTTP CONNECT tunnel: {res:?}"
@@ -393,12 +423,35 @@ pub async fn load(context: &Context) -> Result<Option<Self>> {
 
     /// If `load_dns_cache` is true, loads cached DNS resolution results.
     /// Use this only if the connection is going to be protected with TLS checks.
+    ///
+    /// The result is recorded in the proxy health,
+    /// see [`Context::get_proxy_health`].
+    /// Errors the proxy reports about the target server
+    /// do not count as failures of the proxy.
     pub(crate) async fn connect(
         &self,
         context: &Context,
         target_host: &str,
         target_port: u16,
         load_dns_cache: bool,
+    ) -> Result<Box<dyn SessionStream>> {
+        let res = self
+            .connect_inner(context, target_host, target_port, load_dns_cache)
+            .await;
+        let error = res.as_ref().err().filter(|err| !err.is::<TargetError>());
+        record_connection_result(context, &self.to_url(), error)
+            .await
+            .log_err(context)
+            .ok();
+        res
+    }
+
+    async fn connect_inner(
+        &self,
+        context: &Context,
+        target_host: &str,
+        target_port: u16,
+        load_dns_cache: bool,
     ) -> Result<Box<dyn SessionStream>> {
         match self {
             ProxyConfig::Http(http_config) => {
@@ -483,6 +536,121 @@ pub(crate) async fn connect(
     }
 }
 
+/// Number of consecutive failed connections through the active proxy
+/// after which the next configured proxy is used.
+const PROXY_FAILOVER_THRESHOLD: u32 = 3;
+
+/// Health of a configured proxy, see [`Context::get_proxy_health`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyHealth {
+    /// Proxy URL.
+    pub url: String,
+
+    /// Whether connections currently go through this proxy.
+    pub active: bool,
+
+    /// Number of failed connections since the last successful one.
+    pub consecutive_failures: u32,
+
+    /// Timestamp of the last successful connection.
+    pub last_success: Option<i64>,
+
+    /// Timestamp of the last failed connection.
+    pub last_failure: Option<i64>,
+
+    /// Error of the last failed connection.
+    pub last_error: Option<String>,
+}
+
+/// Returns the URLs configured in [`Config::ProxyUrl`], normalized.
+async fn get_proxy_urls(context: &Context) -> Result<Vec<String>> {
+    let proxy_urls = context
+        .get_config(Config::ProxyUrl)
+        .await?
+        .unwrap_or_default();
+    Ok(proxy_urls
+        .split('\n')
+        .filter(|url| !url.is_empty())
+        .map(|url| {
+            ProxyConfig::from_url(url).map_or_else(|_| url.to_string(), |config| config.to_url())
+        })
+        .collect())
+}
+
+/// Records the result of a connection through the proxy `url`
+/// and switches to the next configured proxy
+/// if connections through the active one fail repeatedly.
+async fn record_connection_result(
+    context: &Context,
+    url: &str,
+    error: Option<&anyhow::Error>,
+) -> Result<()> {
+    let failover = {
+        let mut proxy_health = context.proxy_health.write().await;
+        let health = proxy_health.entry(url.to_string()).or_default();
+        if let Some(error) = error {
+            health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+            health.last_failure = Some(time());
+            health.last_error = Some(format!("{error:#}"));
+            health.consecutive_failures >= PROXY_FAILOVER_THRESHOLD
+        } else {
+            health.consecutive_failures = 0;
+            health.last_success = Some(time());
+            false
+        }
+    };
+    if !failover {
+        return Ok(());
+    }
+
+    let mut proxy_urls = get_proxy_urls(context).await?;
+    if proxy_urls.len() < 2 || proxy_urls.first().map(String::as_str) != Some(url) {
+        return Ok(());
+    }
+    proxy_urls.rotate_left(1);
+    let Some(next_url) = proxy_urls.first().cloned() else {
+        return Ok(());
+    };
+    context
+        .set_config_ex(Nosync, Config::ProxyUrl, Some(&proxy_urls.join("\n")))
+        .await?;
+    // Give the proxy another chance when it becomes active again.
+    if let Some(health) = context.proxy_health.write().await.get_mut(url) {
+        health.consecutive_failures = 0;
+    }
+    warn!(
+        context,
+        "Connections through the proxy failed {PROXY_FAILOVER_THRESHOLD} times, switching to the next proxy."
+    );
+    context.emit_event(EventType::ProxySwitched { url: next_url });
+    Ok(())
+}
+
+impl Context {
+    /// Returns the health of the proxies configured in [`Config::ProxyUrl`]
+    /// in the order they are tried.
+    ///
+    /// Connection results are only tracked while the context is running,
+    /// they are not persisted.
+    pub async fn get_proxy_health(&self) -> Result<Vec<ProxyHealth>> {
+        let enabled = self.get_config_bool(Config::ProxyEnabled).await?;
+        let proxy_urls = get_proxy_urls(self).await?;
+        let proxy_health = self.proxy_health.read().await;
+        Ok(proxy_urls
+            .into_iter()
+            .enumerate()
+            .map(|(i, url)| {
+                let health = proxy_health.get(&url).cloned().unwrap_or_default();
+                ProxyHealth {
+                    url,
+                    active: enabled && i == 0,
+                    ..health
+                }
+            })
+            .collect())
+    }
+}
+
 impl fmt::Display for Socks5Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -823,4 +991,86 @@ async fn test_socks5_migration_empty() -> Result<()> {
         );
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_http_tunnel_errors() -> Result<()> {
+        async fn tunnel(response: &str) -> Result<()> {
+            let (mut client, mut server) = tokio::io::duplex(4096);
+            server.write_all(response.as_bytes()).await?;
+            http_tunnel(&mut client, "example.org", 443, None).await?;
+            Ok(())
+        }
+
+        tunnel("HTTP/1.1 200 Connection established\r\n\r\n").await?;
+        let err = tunnel("HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await
+            .unwrap_err();
+        assert!(!err.is::<TargetError>());
+        let err = tunnel("HTTP/1.1 403 Forbidden\r\n\r\n").await.unwrap_err();
+        assert!(!err.is::<TargetError>());
+        let err = tunnel("HTTP/1.1 502 Bad Gateway\r\n\r\n")
+            .await
+            .unwrap_err();
+        assert!(err.is::<TargetError>());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_proxy_failover() -> Result<()> {
+        let t = TestContext::new().await;
+        t.set_config_bool(Config::ProxyEnabled, true).await?;
+        t.set_config(
+            Config::ProxyUrl,
+            Some("socks5://127.0.0.1:9050\nsocks5://127.0.0.1:9051"),
+        )
+        .await?;
+
+        let first = "socks5://127.0.0.1:9050";
+        let second = "socks5://127.0.0.1:9051";
+        let error = anyhow::anyhow!("Connection refused");
+        for _ in 1..PROXY_FAILOVER_THRESHOLD {
+            record_connection_result(&t, first, Some(&error)).await?;
+        }
+        let health = t.get_proxy_health().await?;
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[0].url, first);
+        assert!(health[0].active);
+        assert_eq!(health[0].consecutive_failures, PROXY_FAILOVER_THRESHOLD - 1);
+        assert_eq!(health[0].last_error.as_deref(), Some("Connection refused"));
+        assert!(!health[1].active);
+
+        // Success resets the failure counter.
+        record_connection_result(&t, first, None).await?;
+        assert_eq!(t.get_proxy_health().await?[0].consecutive_failures, 0);
+
+        for _ in 0..PROXY_FAILOVER_THRESHOLD {
+            record_connection_result(&t, first, Some(&error)).await?;
+        }
+        let EventType::ProxySwitched { url } = t
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ProxySwitched { .. }))
+            .await
+        else {
+            unreachable!();
+        };
+        assert_eq!(url, second);
+        assert_eq!(
+            t.get_config(Config::ProxyUrl).await?.unwrap(),
+            format!("{second}\n{first}")
+        );
+        let health = t.get_proxy_health().await?;
+        assert_eq!(health[0].url, second);
+        assert!(health[0].active);
+        assert_eq!(health[1].url, first);
+        assert!(!health[1].active);
+        assert!(health[1].last_failure.is_some());
+
+        // Failures of a proxy which is not active do not switch proxies.
+        for _ in 0..PROXY_FAILOVER_THRESHOLD {
+            record_connection_result(&t, first, Some(&error)).await?;
+        }
+        assert_eq!(t.get_proxy_health().await?[0].url, second);
+
+        Ok(())
+    }
 }
@@ -26,6 +26,15 @@ fn peer_addr(&self) -> Result<SocketAddr> {
         self.as_ref().peer_addr()
     }
 }
+impl SessionStream for Box<dyn SessionBufStream> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.as_mut().set_read_timeout(timeout);
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        self.as_ref().peer_addr()
+    }
+}
 impl<T: SessionStream> SessionStream for async_native_tls::TlsStream<T> {
     fn set_read_timeout(&mut self, timeout: Option<Duration>) {
         self.get_mut().set_read_timeout(timeout);
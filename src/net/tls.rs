@@ -169,6 +169,10 @@ pub async fn wrap_rustls<'a>(
         let parsed_certificate = ParsedCertificate::try_from(end_entity)?;
         let spki = parsed_certificate.subject_public_key_info();
         spki_hash_store.save_spki(hostname, &spki, sql, now).await?;
+        if let Ok((_, certificate)) = x509_parser::parse_x509_certificate(end_entity) {
+            spki_hash_store
+                .save_cert_expiry(hostname, certificate.validity().not_after.timestamp());
+        }
     }
 
     Ok(tls_stream)
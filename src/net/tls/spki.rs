@@ -3,6 +3,9 @@
 //! We store hashes of Subject Public Key Info from TLS certificates
 //! after successful connection to allow connecting when
 //! server certificate expires as long as the key is not changed.
+//!
+//! Expiration times of the certificates are also remembered
+//! for the duration of the session to report them in diagnostics.
 
 use std::collections::BTreeMap;
 
@@ -36,15 +39,34 @@ pub fn spki_hash(spki: &SubjectPublicKeyInfoDer) -> String {
 pub struct SpkiHashStore {
     /// Map from hostnames to base64 of SHA-256 hashes.
     pub hash_store: RwLock<BTreeMap<String, String>>,
+
+    /// Map from hostnames to expiration timestamps of the last seen certificates.
+    ///
+    /// Not stored in the database.
+    pub cert_expiry_store: RwLock<BTreeMap<String, i64>>,
 }
 
 impl SpkiHashStore {
     pub fn new() -> Self {
         Self {
             hash_store: RwLock::new(BTreeMap::new()),
+            cert_expiry_store: RwLock::new(BTreeMap::new()),
         }
     }
 
+    /// Returns the expiration timestamp of the certificate
+    /// seen during the last successful connection to the given hostname.
+    pub fn get_cert_expiry(&self, hostname: &str) -> Option<i64> {
+        self.cert_expiry_store.read().get(hostname).copied()
+    }
+
+    /// Remembers the expiration timestamp of the certificate after successful connection.
+    pub fn save_cert_expiry(&self, hostname: &str, not_after: i64) {
+        self.cert_expiry_store
+            .write()
+            .insert(hostname.to_string(), not_after);
+    }
+
     /// Returns base64 of SPKI hash if we have previously successfully connected to given hostname.
     pub async fn get_spki_hash(&self, hostname: &str, sql: &Sql) -> Result<Option<String>> {
         if let Some(hash) = self.hash_store.read().get(hostname).cloned() {
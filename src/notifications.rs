@@ -0,0 +1,451 @@
+//! # Notification items.
+//!
+//! Incoming messages, reactions and webxdc notifications
+//! that should be shown as OS notifications are recorded in the `notifications` table,
+//! so that notification extensions can get everything they need
+//! with a single call to [`Context::get_notification_items()`].
+//!
+//! Notification extensions running while the main process may be fetching
+//! can instead render a notification for a single message on the server
+//! with [`Context::render_notification_for_uid()`].
+
+use anyhow::Result;
+use async_channel as channel;
+use deltachat_derive::{FromSql, ToSql};
+
+use crate::chat::{Chat, ChatId, get_chat_id_by_grpid};
+use crate::constants::{Blocked, Chattype, DC_CHAT_ID_TRASH};
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::headerdef::HeaderDef;
+use crate::imap::Imap;
+use crate::message::{Message, MessageState, MsgId};
+use crate::mimeparser::MimeMessage;
+use crate::stock_str;
+use crate::tools::{time, truncate};
+
+/// Notification items older than this are removed during housekeeping.
+const NOTIFICATIONS_MAX_AGE: i64 = 7 * 24 * 60 * 60;
+
+/// Approximate length of [`NotificationItem::snippet`] in graphemes.
+const SNIPPET_LEN: usize = 160;
+
+/// Kind of a [`NotificationItem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql)]
+#[repr(u32)]
+pub enum NotificationKind {
+    /// Incoming message.
+    Message = 1,
+
+    /// Incoming group message replying to a message sent by us.
+    Mention = 2,
+
+    /// Reaction to a message sent by us.
+    Reaction = 3,
+
+    /// Webxdc app asked to notify us.
+    WebxdcNotify = 4,
+}
+
+/// Item to be shown as an OS notification,
+/// see [`Context::get_notification_items()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationItem {
+    /// Token to pass to [`Context::get_notification_items()`]
+    /// to get only the items added after this one.
+    pub token: u32,
+
+    /// Kind of the notification.
+    pub kind: NotificationKind,
+
+    /// Chat the notification belongs to.
+    pub chat_id: ChatId,
+
+    /// Contact who sent the message, reaction or webxdc update.
+    pub contact_id: ContactId,
+
+    /// Incoming message,
+    /// message reacted to or webxdc info message.
+    pub msg_id: MsgId,
+
+    /// Text to show in the notification,
+    /// truncated to about 160 characters.
+    pub snippet: String,
+
+    /// Time the notification item was added.
+    pub timestamp: i64,
+}
+
+/// Notification rendered from a message on the server,
+/// see [`Context::render_notification_for_uid()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedNotification {
+    /// Chat the message belongs to, if the chat exists already.
+    pub chat_id: Option<ChatId>,
+
+    /// Name of the chat, if known.
+    pub chat_name: Option<String>,
+
+    /// Contact who sent the message, if the contact exists already.
+    pub contact_id: Option<ContactId>,
+
+    /// Display name of the sender.
+    pub sender_name: String,
+
+    /// Email address of the sender.
+    pub sender_addr: String,
+
+    /// Text to show in the notification,
+    /// truncated to about 160 characters.
+    pub snippet: String,
+}
+
+/// Records a notification item.
+pub(crate) async fn add_notification(
+    context: &Context,
+    kind: NotificationKind,
+    chat_id: ChatId,
+    contact_id: ContactId,
+    msg_id: MsgId,
+    text: &str,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT INTO notifications (kind, chat_id, contact_id, msg_id, text, timestamp)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            (kind, chat_id, contact_id, msg_id, text, time()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records a notification item for an incoming message.
+///
+/// Group messages replying to our own messages are recorded as [`NotificationKind::Mention`].
+pub(crate) async fn add_msg_notification(context: &Context, msg_id: MsgId) -> Result<()> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let chat = Chat::load_from_db(context, msg.chat_id).await?;
+    let kind = if chat.typ == Chattype::Group
+        && msg
+            .quoted_message(context)
+            .await?
+            .is_some_and(|quote| quote.from_id == ContactId::SELF)
+    {
+        NotificationKind::Mention
+    } else {
+        NotificationKind::Message
+    };
+    add_notification(context, kind, msg.chat_id, msg.from_id, msg_id, "").await
+}
+
+/// Removes notification items older than [`NOTIFICATIONS_MAX_AGE`].
+pub(crate) async fn prune_notifications(context: &Context) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "DELETE FROM notifications WHERE timestamp<?",
+            (time().saturating_sub(NOTIFICATIONS_MAX_AGE),),
+        )
+        .await?;
+    Ok(())
+}
+
+impl Context {
+    /// Returns the items to show as OS notifications,
+    /// added after the item with the token `since_token`.
+    ///
+    /// Pass 0 to get all items.
+    /// Pass the token of the last returned item on the next call
+    /// to get only new items.
+    ///
+    /// Messages are only returned while they are not seen yet
+    /// and are skipped in muted chats, like in [`Context::get_fresh_msgs()`].
+    /// Items of blocked chats and contacts and items for deleted messages are skipped.
+    /// Items are kept for a week.
+    pub async fn get_notification_items(&self, since_token: u32) -> Result<Vec<NotificationItem>> {
+        let rows = self
+            .sql
+            .query_map_vec(
+                "SELECT n.id, n.kind, n.chat_id, n.contact_id, n.msg_id, n.text, n.timestamp
+                 FROM notifications n
+                 INNER JOIN msgs m ON n.msg_id=m.id
+                 INNER JOIN chats c ON n.chat_id=c.id
+                 LEFT JOIN contacts ct ON n.contact_id=ct.id
+                 WHERE n.id>?
+                 AND m.chat_id!=?
+                 AND c.blocked=0
+                 AND IFNULL(ct.blocked, 0)=0
+                 AND (n.kind!=? OR NOT(c.muted_until=-1 OR c.muted_until>?))
+                 AND (n.kind NOT IN (?, ?) OR m.state=?)
+                 ORDER BY n.id",
+                (
+                    since_token,
+                    DC_CHAT_ID_TRASH,
+                    NotificationKind::Message,
+                    time(),
+                    NotificationKind::Message,
+                    NotificationKind::Mention,
+                    MessageState::InFresh,
+                ),
+                |row| {
+                    let token: u32 = row.get(0)?;
+                    let kind: NotificationKind = row.get(1)?;
+                    let chat_id: ChatId = row.get(2)?;
+                    let contact_id: ContactId = row.get(3)?;
+                    let msg_id: MsgId = row.get(4)?;
+                    let text: String = row.get(5)?;
+                    let timestamp: i64 = row.get(6)?;
+                    Ok((token, kind, chat_id, contact_id, msg_id, text, timestamp))
+                },
+            )
+            .await?;
+
+        let mut items = Vec::with_capacity(rows.len());
+        for (token, kind, chat_id, contact_id, msg_id, text, timestamp) in rows {
+            let snippet = match kind {
+                NotificationKind::Message | NotificationKind::Mention => {
+                    let msg = Message::load_from_db(self, msg_id).await?;
+                    msg.get_summary_text(self).await
+                }
+                NotificationKind::Reaction => {
+                    let msg = Message::load_from_db(self, msg_id).await?;
+                    let summary = msg.get_summary_text_without_prefix(self).await;
+                    stock_str::msg_reacted(self, contact_id, &text, &summary).await
+                }
+                NotificationKind::WebxdcNotify => text,
+            };
+            let snippet = truncate(&snippet, SNIPPET_LEN).into_owned();
+            items.push(NotificationItem {
+                token,
+                kind,
+                chat_id,
+                contact_id,
+                msg_id,
+                snippet,
+                timestamp,
+            });
+        }
+        Ok(items)
+    }
+
+    /// Fetches the message with the given UID from the server
+    /// and renders a notification for it.
+    ///
+    /// Meant for notification extensions such as the iOS Notification Service Extension,
+    /// which show a notification while the main process may be receiving the same message.
+    /// The message is fetched on a dedicated connection and decrypted,
+    /// but is not added to the database and is not marked as seen on the server,
+    /// so it is received only once, by the main process.
+    ///
+    /// Returns `None` if there is no such message
+    /// or it should not be notified about,
+    /// e.g. because it is sent by us, is not a visible message,
+    /// or its sender or chat is blocked or muted.
+    pub async fn render_notification_for_uid(
+        &self,
+        folder: &str,
+        uid: u32,
+    ) -> Result<Option<RenderedNotification>> {
+        let mut connection = Imap::new_configured(self, channel::bounded(1).1).await?;
+        let mut session = connection.prepare(self).await?;
+        let Some(body) = session.peek_single_msg(self, folder, uid).await? else {
+            return Ok(None);
+        };
+        render_notification(self, &body).await
+    }
+}
+
+/// Renders a notification for the raw message
+/// without adding it to the database.
+async fn render_notification(
+    context: &Context,
+    body: &[u8],
+) -> Result<Option<RenderedNotification>> {
+    let mime_message = MimeMessage::from_bytes(context, body).await?;
+    if !mime_message.incoming
+        || mime_message.sync_items.is_some()
+        || !mime_message.mdn_reports.is_empty()
+        || mime_message.delivery_report.is_some()
+    {
+        return Ok(None);
+    }
+    let Some(part) = mime_message.parts.first() else {
+        return Ok(None);
+    };
+    if part.is_reaction {
+        return Ok(None);
+    }
+
+    let from = &mime_message.from;
+    let contact_id = match &mime_message.signature {
+        Some((fingerprint, _)) => {
+            context
+                .sql
+                .query_get_value(
+                    "SELECT id FROM contacts WHERE fingerprint=? AND id>?",
+                    (fingerprint.hex(), ContactId::LAST_SPECIAL),
+                )
+                .await?
+        }
+        None => Contact::lookup_id_by_addr_ex(context, &from.addr, Origin::Unknown, None).await?,
+    };
+    let contact = match contact_id {
+        Some(contact_id) => Some(Contact::get_by_id(context, contact_id).await?),
+        None => None,
+    };
+    if contact.as_ref().is_some_and(|contact| contact.is_blocked()) {
+        return Ok(None);
+    }
+
+    let chat_id = if let Some(grpid) = mime_message.get_chat_group_id() {
+        match get_chat_id_by_grpid(context, grpid).await? {
+            Some((_, Blocked::Yes)) => return Ok(None),
+            Some((chat_id, _)) => Some(chat_id),
+            None => None,
+        }
+    } else if let Some(contact_id) = contact_id {
+        ChatId::lookup_by_contact(context, contact_id).await?
+    } else {
+        None
+    };
+    let chat_name = if let Some(chat_id) = chat_id {
+        let chat = Chat::load_from_db(context, chat_id).await?;
+        if chat.is_muted() {
+            return Ok(None);
+        }
+        Some(chat.get_name().to_string())
+    } else {
+        mime_message
+            .get_header(HeaderDef::ChatGroupName)
+            .map(|name| name.to_string())
+    };
+
+    let sender_name = match &contact {
+        Some(contact) => contact.get_display_name().to_string(),
+        None => from
+            .display_name
+            .clone()
+            .unwrap_or_else(|| from.addr.clone()),
+    };
+    let msg = Message {
+        viewtype: part.typ,
+        text: part.msg.clone(),
+        param: part.param.clone(),
+        ..Default::default()
+    };
+    let snippet = msg.get_summary_text_without_prefix(context).await;
+    let snippet = truncate(&snippet, SNIPPET_LEN).into_owned();
+
+    Ok(Some(RenderedNotification {
+        chat_id,
+        chat_name,
+        contact_id,
+        sender_name,
+        sender_addr: from.addr.clone(),
+        snippet,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::send_text_msg;
+    use crate::reaction::send_reaction;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_notification_items() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(alice_chat_id, "Hi Bob!").await;
+        let alice_sent_msg_id = sent.sender_msg_id;
+        let bob_msg = bob.recv_msg(&sent).await;
+        bob_msg.chat_id.accept(bob).await?;
+
+        let items = bob.get_notification_items(0).await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, NotificationKind::Message);
+        assert_eq!(items[0].chat_id, bob_msg.chat_id);
+        assert_eq!(items[0].msg_id, bob_msg.id);
+        assert_eq!(items[0].snippet, "Hi Bob!");
+        let token = items[0].token;
+        assert!(bob.get_notification_items(token).await?.is_empty());
+
+        send_reaction(bob, bob_msg.id, "👍").await?;
+        let sent = bob.pop_sent_msg().await;
+        alice.recv_msg_hidden(&sent).await;
+        send_text_msg(bob, bob_msg.chat_id, "Hello Alice!".to_string()).await?;
+        let sent = bob.pop_sent_msg().await;
+        let alice_msg = alice.recv_msg(&sent).await;
+
+        let items = alice.get_notification_items(0).await?;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].kind, NotificationKind::Reaction);
+        assert_eq!(items[0].msg_id, alice_sent_msg_id);
+        assert_eq!(
+            items[0].snippet,
+            "bob@example.net reacted 👍 to \"Hi Bob!\""
+        );
+        assert_eq!(items[1].kind, NotificationKind::Message);
+        assert_eq!(items[1].msg_id, alice_msg.id);
+
+        // Seen messages are not returned anymore.
+        crate::message::markseen_msgs(alice, vec![alice_msg.id]).await?;
+        let items = alice.get_notification_items(0).await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, NotificationKind::Reaction);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_render_notification() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(alice_chat_id, "Hi Bob!").await;
+        let notification = render_notification(bob, sent.payload().as_bytes())
+            .await?
+            .unwrap();
+        assert_eq!(notification.chat_id, None);
+        assert_eq!(notification.sender_addr, "alice@example.org");
+        assert_eq!(notification.sender_name, "alice@example.org");
+        assert_eq!(notification.snippet, "Hi Bob!");
+
+        // Rendering does not add the message to the database.
+        assert!(bob.get_fresh_msgs().await?.is_empty());
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.text, "Hi Bob!");
+
+        let sent = alice.send_text(alice_chat_id, "Hello again").await;
+        let notification = render_notification(bob, sent.payload().as_bytes())
+            .await?
+            .unwrap();
+        assert_eq!(notification.chat_id, Some(bob_msg.chat_id));
+        assert!(notification.contact_id.is_some());
+        assert_eq!(notification.snippet, "Hello again");
+
+        // Own messages are not notified about.
+        assert!(
+            render_notification(alice, sent.payload().as_bytes())
+                .await?
+                .is_none()
+        );
+
+        // Muted chats are not notified about.
+        crate::chat::set_muted(bob, bob_msg.chat_id, crate::chat::MuteDuration::Forever).await?;
+        assert!(
+            render_notification(bob, sent.payload().as_bytes())
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
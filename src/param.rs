@@ -209,6 +209,10 @@ pub enum Param {
     /// the List-Id of the mailing list (which is also used as the group id of the chat).
     ListId = b's',
 
+    /// For Chats: If this is a mailing list chat, contains the address from the
+    /// `List-Owner` header, used to report messages to the owner of the mailing list.
+    ListOwner = b';',
+
     /// For Contacts: timestamp of status (aka signature or footer) update.
     StatusTimestamp = b'j',
 
@@ -257,6 +261,26 @@ pub enum Param {
     /// For messages: Message text was edited.
     IsEdited = b'L',
 
+    /// For messages: Message was removed from the server without our involvement,
+    /// see [`crate::config::Config::MarkExpungedMsgs`].
+    RemovedFromServer = b'@',
+
+    /// For messages: Message was received as a part of the group history
+    /// shared by another member when we joined the group.
+    FromSharedHistory = b'_',
+
+    /// For messages: ISO 639-1 code of the language detected in the received text,
+    /// see [`crate::message::Message::get_language()`].
+    Language = b'-',
+
+    /// For messages: Alternative text describing the attachment for screen readers,
+    /// see [`crate::message::Message::set_alt_text()`].
+    AltText = b'|',
+
+    /// For messages: JSON list of custom headers to send,
+    /// see [`crate::message::Message::set_extra_header()`].
+    ExtraHeaders = b',',
+
     /// For info messages: Contact ID in added or removed to a group.
     ContactAddedRemoved = b'5',
 
@@ -266,6 +290,61 @@ pub enum Param {
 
     /// For (pre-)Message: File byte size of Post-Message attachment
     PostMessageFileBytes = b'9',
+
+    /// For Messages: URL the encrypted attachment was uploaded to
+    /// instead of sending it by email.
+    UploadUrl = b'X',
+
+    /// For Messages: Secret the uploaded attachment is encrypted with.
+    UploadKey = b'!',
+
+    /// For Messages: BLAKE3 hash of the attachment offered over iroh
+    /// instead of sending it by email.
+    P2pHash = b'#',
+
+    /// For Messages: Iroh node address the attachment is offered at, serialized as JSON.
+    P2pNodeAddr = b'$',
+
+    /// For Chats: If set, unencrypted messages sent to the chat
+    /// carry a detached OpenPGP signature.
+    SignUnencrypted = b'%',
+
+    /// For Chats: If set, keys are not gossiped periodically in the chat.
+    SuppressGossip = b'*',
+
+    /// For Chats: [`crate::chat::EncryptionPreference`] of outgoing messages,
+    /// not set for the default.
+    EncryptionPreference = b'+',
+
+    /// For Groups: If set, recent messages are shared with members
+    /// joining the group via QR code.
+    ShareHistory = b'^',
+
+    /// For Groups: Join timestamp of the membership
+    /// for which the shared history was received already.
+    HistoryReceived = b'{',
+
+    /// For Messages: display name of the author of the quoted message.
+    QuoteAuthor = b'Z',
+
+    /// For Messages: timestamp of the quoted message.
+    QuoteTimestamp = b'&',
+
+    /// For Messages: summary of the quoted message, see [`crate::tools::truncate`].
+    QuoteSnippet = b'(',
+
+    /// For Messages: base64-encoded JPEG micro-thumbnail of the quoted image.
+    QuoteThumbnail = b')',
+
+    /// For Messages: [`crate::message::AttachmentWarning`] of a suspicious received attachment.
+    AttachmentWarning = b'?',
+
+    /// For Chats: wallpaper image, see [`crate::chat::ChatId::set_wallpaper`].
+    Wallpaper = b'<',
+
+    /// For Messages: maximum width/height the attached image was already checked or recoded for,
+    /// so that it is not processed again when the message is forwarded or resent.
+    ImageRecodedWh = b'>',
 }
 
 /// An object for handling key=value parameter lists.
@@ -520,8 +599,8 @@ fn test_roundtrip() {
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_params_unknown_key() -> Result<()> {
-        // 'Z' is used as a key that is known to be unused; these keys should be ignored silently by definition.
-        let p = Params::from_str("w=12\nZ=13\nh=14")?;
+        // '~' is used as a key that is known to be unused; these keys should be ignored silently by definition.
+        let p = Params::from_str("w=12\n~=13\nh=14")?;
         assert_eq!(p.len(), 2);
         assert_eq!(p.get(Param::Width), Some("12"));
         assert_eq!(p.get(Param::Height), Some("14"));
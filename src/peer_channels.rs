@@ -30,8 +30,10 @@
 use iroh_gossip::net::{Event, GOSSIP_ALPN, Gossip, GossipEvent, JoinOptions};
 use iroh_gossip::proto::TopicId;
 use parking_lot::Mutex;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{RwLock, oneshot};
 use tokio::task::JoinHandle;
 use url::Url;
@@ -40,14 +42,23 @@
 use crate::chat::send_msg;
 use crate::config::Config;
 use crate::context::Context;
+use crate::download::p2p::{P2P_FILE_ALPN, P2pFileProvider, P2pOffers};
+use crate::lan::{LAN_MSG_ALPN, LanMessageReceiver, LanPeers};
 use crate::log::warn;
 use crate::message::{Message, MsgId, Viewtype};
 use crate::mimeparser::SystemMessage;
+use crate::net::bandwidth::ConnectionType;
 
 /// The length of an ed25519 `PublicKey`, in bytes.
 const PUBLIC_KEY_LENGTH: usize = 32;
 const PUBLIC_KEY_STUB: &[u8] = "static_string".as_bytes();
 
+/// Maximum size of realtime data stored per gossip channel while there are no peers.
+const REALTIME_STORE_MAX_BYTES: usize = 256 * 1024;
+
+/// Time after which stored realtime data expires.
+const REALTIME_STORE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
 /// Store Iroh peer channels for the context.
 #[derive(Debug)]
 pub struct Iroh {
@@ -67,6 +78,12 @@ pub struct Iroh {
     ///
     /// This is attached to every message to work around `iroh_gossip` deduplication.
     pub(crate) public_key: PublicKey,
+
+    /// Attachments offered over iroh, see [`crate::download::p2p`].
+    pub(crate) p2p_offers: P2pOffers,
+
+    /// Contacts discovered on the local network, see [`crate::lan`].
+    pub(crate) lan_peers: LanPeers,
 }
 
 impl Iroh {
@@ -126,14 +143,25 @@ async fn join_and_subscribe_gossip(
             .subscribe_with_opts(topic, JoinOptions::with_bootstrap(node_ids))
             .split();
 
+        let queue = Arc::new(Mutex::new(RealtimeQueue::default()));
         let ctx = ctx.clone();
-        let subscribe_loop = tokio::spawn(async move {
-            if let Err(e) = subscribe_loop(&ctx, gossip_receiver, topic, msg_id, join_tx).await {
-                warn!(ctx, "subscribe_loop failed: {e}")
-            }
-        });
+        let subscribe_loop = {
+            let sender = gossip_sender.clone();
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move {
+                if let Err(e) =
+                    subscribe_loop(&ctx, gossip_receiver, sender, queue, topic, msg_id, join_tx)
+                        .await
+                {
+                    warn!(ctx, "subscribe_loop failed: {e}")
+                }
+            })
+        };
 
-        iroh_channels.insert(topic, ChannelState::new(subscribe_loop, gossip_sender));
+        iroh_channels.insert(
+            topic,
+            ChannelState::new(subscribe_loop, gossip_sender, queue),
+        );
 
         Ok(Some(join_rx))
     }
@@ -168,6 +196,7 @@ pub async fn send_webxdc_realtime_data(
         data.extend(seq_num.to_le_bytes());
         data.extend(self.public_key.as_bytes());
 
+        ctx.bandwidth.get(ConnectionType::Iroh).add_sent(data.len());
         state.sender.broadcast(data.into()).await?;
 
         if env::var("REALTIME_DEBUG").is_ok() {
@@ -177,6 +206,74 @@ pub async fn send_webxdc_realtime_data(
         Ok(())
     }
 
+    /// Sends realtime data to the gossip swarm
+    /// or stores it until a peer appears if there are no peers.
+    ///
+    /// Returns the ID used in [`EventType::WebxdcRealtimeDataForwarded`]
+    /// and [`EventType::WebxdcRealtimeDataExpired`].
+    pub async fn send_webxdc_realtime_data_stored(
+        &self,
+        ctx: &Context,
+        msg_id: MsgId,
+        mut data: Vec<u8>,
+    ) -> Result<u32> {
+        let topic = get_iroh_topic_for_msg(ctx, msg_id)
+            .await?
+            .with_context(|| format!("Message {msg_id} has no gossip topic"))?;
+        self.join_and_subscribe_gossip(ctx, msg_id).await?;
+
+        let seq_num = self.get_and_incr(&topic);
+        data.extend(seq_num.to_le_bytes());
+        data.extend(self.public_key.as_bytes());
+
+        let iroh_channels = self.iroh_channels.read().await;
+        let state = iroh_channels
+            .get(&topic)
+            .context("Just created state does not exist")?;
+        let stored = {
+            let mut queue = state.queue.lock();
+            if queue.neighbors.is_empty() {
+                Ok(queue.store(data))
+            } else {
+                Err((queue.next_id(), data))
+            }
+        };
+        let (id, dropped) = match stored {
+            Ok(stored) => stored,
+            Err((id, data)) => {
+                ctx.bandwidth.get(ConnectionType::Iroh).add_sent(data.len());
+                state.sender.broadcast(data.into()).await?;
+                ctx.emit_event(EventType::WebxdcRealtimeDataForwarded {
+                    msg_id,
+                    data_id: id,
+                });
+                return Ok(id);
+            }
+        };
+        info!(
+            ctx,
+            "IROH_REALTIME: Stored realtime data {id} until a peer appears."
+        );
+        for data_id in dropped {
+            ctx.emit_event(EventType::WebxdcRealtimeDataExpired { msg_id, data_id });
+        }
+
+        let context = ctx.get_weak_context();
+        let queue = Arc::clone(&state.queue);
+        tokio::spawn(async move {
+            tokio::time::sleep(REALTIME_STORE_TIMEOUT).await;
+            if queue.lock().remove(id)
+                && let Ok(context) = context.upgrade()
+            {
+                context.emit_event(EventType::WebxdcRealtimeDataExpired {
+                    msg_id,
+                    data_id: id,
+                });
+            }
+        });
+        Ok(id)
+    }
+
     fn get_and_incr(&self, topic: &TopicId) -> i32 {
         let mut sequence_numbers = self.sequence_numbers.lock();
         let entry = sequence_numbers.entry(*topic).or_default();
@@ -220,14 +317,77 @@ pub(crate) struct ChannelState {
     subscribe_loop: JoinHandle<()>,
 
     sender: iroh_gossip::net::GossipSender,
+
+    /// Store-and-forward queue shared with the subscribe loop.
+    queue: Arc<Mutex<RealtimeQueue>>,
 }
 
 impl ChannelState {
-    fn new(subscribe_loop: JoinHandle<()>, sender: iroh_gossip::net::GossipSender) -> Self {
+    fn new(
+        subscribe_loop: JoinHandle<()>,
+        sender: iroh_gossip::net::GossipSender,
+        queue: Arc<Mutex<RealtimeQueue>>,
+    ) -> Self {
         Self {
             subscribe_loop,
             sender,
+            queue,
+        }
+    }
+}
+
+/// Realtime data stored until a peer appears,
+/// see [`send_webxdc_realtime_data_stored`].
+#[derive(Debug)]
+struct StoredRealtimeData {
+    /// ID reported in [`EventType::WebxdcRealtimeDataForwarded`]
+    /// and [`EventType::WebxdcRealtimeDataExpired`].
+    id: u32,
+
+    /// Data including sequence number and public key.
+    data: Vec<u8>,
+}
+
+/// Neighbors of a gossip channel and realtime data waiting for them.
+#[derive(Debug, Default)]
+pub(crate) struct RealtimeQueue {
+    neighbors: HashSet<NodeId>,
+    stored: VecDeque<StoredRealtimeData>,
+    next_id: u32,
+}
+
+impl RealtimeQueue {
+    /// Returns a new ID for stored data.
+    fn next_id(&mut self) -> u32 {
+        self.next_id = self.next_id.wrapping_add(1);
+        self.next_id
+    }
+
+    /// Stores the data, dropping the oldest stored data
+    /// to stay within [`REALTIME_STORE_MAX_BYTES`].
+    ///
+    /// Returns the ID of the stored data and the IDs of dropped data.
+    fn store(&mut self, data: Vec<u8>) -> (u32, Vec<u32>) {
+        let id = self.next_id();
+        self.stored.push_back(StoredRealtimeData { id, data });
+        let mut dropped = Vec::new();
+        while self.stored.len() > 1 && self.stored_bytes() > REALTIME_STORE_MAX_BYTES {
+            if let Some(item) = self.stored.pop_front() {
+                dropped.push(item.id);
+            }
         }
+        (id, dropped)
+    }
+
+    fn stored_bytes(&self) -> usize {
+        self.stored.iter().map(|item| item.data.len()).sum()
+    }
+
+    /// Removes the stored data, returns true if it was still stored.
+    fn remove(&mut self, id: u32) -> bool {
+        let len = self.stored.len();
+        self.stored.retain(|item| item.id != id);
+        self.stored.len() != len
     }
 }
 
@@ -270,8 +430,15 @@ async fn init_peer_channels(&self) -> Result<Iroh> {
             .spawn(endpoint.clone())
             .await?;
 
+        let p2p_offers = P2pOffers::default();
+        let lan_peers = LanPeers::default();
         let router = iroh::protocol::Router::builder(endpoint)
             .accept(GOSSIP_ALPN, gossip.clone())
+            .accept(P2P_FILE_ALPN, P2pFileProvider::new(p2p_offers.clone()))
+            .accept(
+                LAN_MSG_ALPN,
+                LanMessageReceiver::new(self.get_weak_context(), lan_peers.clone()),
+            )
             .spawn();
 
         Ok(Iroh {
@@ -280,6 +447,8 @@ async fn init_peer_channels(&self) -> Result<Iroh> {
             sequence_numbers: Mutex::new(HashMap::new()),
             iroh_channels: RwLock::new(HashMap::new()),
             public_key,
+            p2p_offers,
+            lan_peers,
         })
     }
 
@@ -311,7 +480,15 @@ pub async fn get_or_try_init_peer_channel(
         ) {
             Ok(lock) => Ok(lock),
             Err(mut lock) => {
-                let iroh = self.init_peer_channels().await?;
+                self.iroh_connectivity.set_connecting(self);
+                let iroh = match self.init_peer_channels().await {
+                    Ok(iroh) => iroh,
+                    Err(err) => {
+                        self.iroh_connectivity.set_err(self, format!("{err:#}"));
+                        return Err(err);
+                    }
+                };
+                self.iroh_connectivity.set_idle(self);
                 *lock = Some(iroh);
                 tokio::sync::RwLockWriteGuard::<'_, std::option::Option<Iroh>>::try_downgrade_map(
                     lock,
@@ -489,6 +666,29 @@ pub async fn send_webxdc_realtime_data(ctx: &Context, msg_id: MsgId, data: Vec<u
     Ok(())
 }
 
+/// Send realtime data to other peers using iroh
+/// or store it until a peer appears.
+///
+/// Unlike [`send_webxdc_realtime_data`], data sent while no peer is connected
+/// is not dropped but stored for up to 5 minutes, limited to 256 KiB per webxdc.
+/// Returns an ID for the data.
+/// [`EventType::WebxdcRealtimeDataForwarded`] is emitted with this ID
+/// once the data is sent to peers,
+/// [`EventType::WebxdcRealtimeDataExpired`] if the data is dropped instead.
+pub async fn send_webxdc_realtime_data_stored(
+    ctx: &Context,
+    msg_id: MsgId,
+    data: Vec<u8>,
+) -> Result<u32> {
+    if !ctx.get_config_bool(Config::WebxdcRealtimeEnabled).await? {
+        bail!("Realtime is disabled");
+    }
+
+    let iroh = ctx.get_or_try_init_peer_channel().await?;
+    iroh.send_webxdc_realtime_data_stored(ctx, msg_id, data)
+        .await
+}
+
 /// Leave the gossip of the webxdc with given [MsgId].
 ///
 /// NB: When this is called before closing a webxdc app in UIs, it must be guaranteed that
@@ -533,10 +733,38 @@ pub(crate) fn iroh_topic_from_str(topic: &str) -> Result<TopicId> {
     Ok(topic)
 }
 
+/// Broadcasts realtime data stored while there were no peers.
+async fn forward_stored_data(
+    context: &Context,
+    sender: &iroh_gossip::net::GossipSender,
+    queue: &Mutex<RealtimeQueue>,
+    msg_id: MsgId,
+) -> Result<()> {
+    let stored: Vec<StoredRealtimeData> = queue.lock().stored.drain(..).collect();
+    for item in stored {
+        context
+            .bandwidth
+            .get(ConnectionType::Iroh)
+            .add_sent(item.data.len());
+        sender.broadcast(item.data.into()).await?;
+        info!(
+            context,
+            "IROH_REALTIME: Forwarded stored realtime data {}.", item.id
+        );
+        context.emit_event(EventType::WebxdcRealtimeDataForwarded {
+            msg_id,
+            data_id: item.id,
+        });
+    }
+    Ok(())
+}
+
 #[expect(clippy::arithmetic_side_effects)]
 async fn subscribe_loop(
     context: &Context,
     mut stream: iroh_gossip::net::GossipReceiver,
+    sender: iroh_gossip::net::GossipSender,
+    queue: Arc<Mutex<RealtimeQueue>>,
     topic: TopicId,
     msg_id: MsgId,
     join_tx: oneshot::Sender<()>,
@@ -555,15 +783,25 @@ async fn subscribe_loop(
 
                     for node in nodes {
                         iroh_add_peer_for_topic(context, msg_id, topic, node, None).await?;
+                        queue.lock().neighbors.insert(node);
                     }
+                    forward_stored_data(context, &sender, &queue, msg_id).await?;
                 }
                 GossipEvent::NeighborUp(node) => {
                     info!(context, "IROH_REALTIME: NeighborUp: {}", node.to_string());
                     iroh_add_peer_for_topic(context, msg_id, topic, node, None).await?;
+                    queue.lock().neighbors.insert(node);
+                    forward_stored_data(context, &sender, &queue, msg_id).await?;
+                }
+                GossipEvent::NeighborDown(node) => {
+                    queue.lock().neighbors.remove(&node);
                 }
-                GossipEvent::NeighborDown(_node) => {}
                 GossipEvent::Received(message) => {
                     info!(context, "IROH_REALTIME: Received realtime data");
+                    context
+                        .bandwidth
+                        .get(ConnectionType::Iroh)
+                        .add_received(message.content.len());
                     context.emit_event(EventType::WebxdcRealtimeData {
                         msg_id,
                         data: message
@@ -593,6 +831,22 @@ mod tests {
         test_utils::{TestContext, TestContextManager},
     };
 
+    #[test]
+    fn test_realtime_queue_limit() {
+        let mut queue = RealtimeQueue::default();
+        let (id1, dropped) = queue.store(vec![0; 100 * 1024]);
+        assert!(dropped.is_empty());
+        let (id2, dropped) = queue.store(vec![0; 100 * 1024]);
+        assert!(dropped.is_empty());
+        let (id3, dropped) = queue.store(vec![0; 100 * 1024]);
+        assert_eq!(dropped, vec![id1]);
+        assert!(queue.stored_bytes() <= REALTIME_STORE_MAX_BYTES);
+
+        assert!(queue.remove(id2));
+        assert!(!queue.remove(id2));
+        assert_eq!(queue.stored.front().unwrap().id, id3);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_can_communicate() {
         let mut tcm = TestContextManager::new();
@@ -1,14 +1,16 @@
 //! OpenPGP helper module using [rPGP facilities](https://github.com/rpgp/rpgp).
 
 use std::collections::{HashMap, HashSet};
-use std::io::Cursor;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Write as _};
+use std::path::Path;
 
 use anyhow::{Context as _, Result, ensure};
 use deltachat_contact_tools::{EmailAddress, may_be_valid_addr};
 use pgp::composed::{
-    Deserializable, DetachedSignature, EncryptionCaps, KeyType as PgpKeyType, MessageBuilder,
-    SecretKeyParamsBuilder, SignedKeyDetails, SignedPublicKey, SignedPublicSubKey, SignedSecretKey,
-    SubkeyParamsBuilder, SubpacketConfig,
+    Deserializable, DetachedSignature, EncryptionCaps, KeyType as PgpKeyType, Message,
+    MessageBuilder, SecretKeyParamsBuilder, SignedKeyDetails, SignedPublicKey, SignedPublicSubKey,
+    SignedSecretKey, SubkeyParamsBuilder, SubpacketConfig,
 };
 use pgp::crypto::aead::{AeadAlgorithm, ChunkSize};
 use pgp::crypto::ecc_curve::ECCCurve;
@@ -16,11 +18,12 @@
 use pgp::crypto::sym::SymmetricKeyAlgorithm;
 use pgp::packet::{Signature, Subpacket, SubpacketData};
 use pgp::types::{
-    CompressionAlgorithm, Imprint, KeyDetails, KeyVersion, Password, SignedUser, SigningKey as _,
-    StringToKey,
+    CompressionAlgorithm, Imprint, KeyDetails, KeyVersion, Mpi, Password, SignatureBytes,
+    SignedUser, SigningKey as _, StringToKey, VerifyingKey as _,
 };
 use rand_old::{Rng as _, thread_rng};
-use sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 use tokio::runtime::Handle;
 
 use crate::key::{DcKey, Fingerprint};
@@ -232,6 +235,23 @@ pub fn valid_signature_fingerprints(
     ret_signature_fingerprints
 }
 
+/// Calculates detached signature of `plain` with the primary key.
+pub fn pk_calc_signature(
+    plain: &[u8],
+    private_key_for_signing: &SignedSecretKey,
+) -> Result<String> {
+    let mut rng = thread_rng();
+    let signature = DetachedSignature::sign_binary_data(
+        &mut rng,
+        &private_key_for_signing.primary_key,
+        &Password::empty(),
+        private_key_for_signing.hash_alg(),
+        plain,
+    )?;
+    let signature = signature.to_armored_string(Default::default())?;
+    Ok(signature)
+}
+
 /// Validates detached signature.
 pub fn pk_validate(
     content: &[u8],
@@ -291,6 +311,132 @@ pub fn symm_encrypt_message(
     Ok(encoded_msg)
 }
 
+/// Encrypts the message with a passphrase chosen by the user.
+///
+/// Unlike [`symm_encrypt_message`], which is used with random shared secrets,
+/// this derives the key with Argon2 to make guessing the passphrase expensive.
+pub(crate) fn passphrase_encrypt_message(plain: Vec<u8>, passphrase: &str) -> Result<String> {
+    let mut rng = thread_rng();
+    // Second recommended parameter choice of RFC 9106, using 64 MiB of memory.
+    let s2k = StringToKey::new_argon2(&mut rng, 3, 4, 16);
+    let mut msg = MessageBuilder::from_bytes("", plain).seipd_v2(
+        &mut rng,
+        SYMMETRIC_KEY_ALGORITHM,
+        AeadAlgorithm::Ocb,
+        ChunkSize::C8KiB,
+    );
+    msg.encrypt_with_password(&mut rng, s2k, &Password::from(passphrase))?;
+
+    let encoded_msg = msg.to_armored_string(&mut rng, Default::default())?;
+    Ok(encoded_msg)
+}
+
+/// Decrypts the message encrypted with [`symm_encrypt_message`]
+/// or [`passphrase_encrypt_message`]
+/// and returns the plaintext.
+///
+/// Signatures are not checked.
+pub(crate) fn symm_decrypt_message(ctext: Vec<u8>, shared_secret: &str) -> Result<Vec<u8>> {
+    let (msg, _headers) = Message::from_armor(Cursor::new(ctext))?;
+    let msg = msg
+        .decrypt_with_password(&Password::from(shared_secret))
+        .context("Failed to decrypt with the shared secret")?;
+    let mut msg = msg.decompress()?;
+    Ok(msg.as_data_vec()?)
+}
+
+/// Symmetrically encrypts the file at `src` with `shared_secret`
+/// like [`symm_encrypt_message`] without signing and compression
+/// and writes the result to `dest`.
+///
+/// The file is streamed, so it is never loaded into memory completely.
+pub(crate) fn symm_encrypt_file(src: &Path, dest: &Path, shared_secret: String) -> Result<()> {
+    let shared_secret = Password::from(shared_secret);
+
+    let msg = MessageBuilder::from_reader("", File::open(src)?);
+    let mut rng = thread_rng();
+    let mut salt = [0u8; 8];
+    rng.fill(&mut salt[..]);
+    let s2k = StringToKey::Salted {
+        hash_alg: HashAlgorithm::default(),
+        salt,
+    };
+    let mut msg = msg.seipd_v2(
+        &mut rng,
+        SYMMETRIC_KEY_ALGORITHM,
+        AeadAlgorithm::Ocb,
+        ChunkSize::C8KiB,
+    );
+    msg.encrypt_with_password(&mut rng, s2k, &shared_secret)?;
+
+    let mut out = BufWriter::new(File::create(dest)?);
+    msg.to_armored_writer(&mut rng, Default::default(), &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Decrypts the file at `src` encrypted with [`symm_encrypt_file`]
+/// or [`symm_encrypt_message`] and writes the plaintext to `dest`.
+///
+/// The file is streamed, so it is never loaded into memory completely.
+/// Returns the size of the plaintext.
+pub(crate) fn symm_decrypt_file(src: &Path, dest: &Path, shared_secret: &str) -> Result<u64> {
+    let (msg, _headers) = Message::from_armor(BufReader::new(File::open(src)?))?;
+    let msg = msg
+        .decrypt_with_password(&Password::from(shared_secret))
+        .context("Failed to decrypt with the shared secret")?;
+    let mut msg = msg.decompress()?;
+    let mut out = BufWriter::new(File::create(dest)?);
+    let len = std::io::copy(&mut msg, &mut out)?;
+    out.flush()?;
+    Ok(len)
+}
+
+/// Raw cryptographic signature created by [`pk_sign_raw`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum RawSignature {
+    /// Signature represented as a set of MPIs, e.g. for RSA and legacy Ed25519 keys.
+    Mpis(Vec<Vec<u8>>),
+
+    /// Signature in native format used by Ed25519 and Ed448 keys.
+    Native(Vec<u8>),
+}
+
+/// Signs SHA-256 digest of `data` with the primary key of `key`.
+///
+/// Unlike [`pk_calc_signature`], the result is not an OpenPGP signature packet,
+/// so it contains neither the fingerprint of the key nor a timestamp.
+pub(crate) fn pk_sign_raw(key: &SignedSecretKey, data: &[u8]) -> Result<RawSignature> {
+    let digest = Sha256::digest(data);
+    let signature =
+        key.primary_key
+            .sign(&Password::empty(), HashAlgorithm::Sha256, digest.as_slice())?;
+    Ok(match signature {
+        SignatureBytes::Mpis(mpis) => {
+            RawSignature::Mpis(mpis.iter().map(|mpi| mpi.as_ref().to_vec()).collect())
+        }
+        SignatureBytes::Native(native) => RawSignature::Native(native.to_vec()),
+    })
+}
+
+/// Verifies the signature created by [`pk_sign_raw`] with the primary key of `key`.
+pub(crate) fn pk_verify_raw(
+    key: &SignedPublicKey,
+    data: &[u8],
+    signature: &RawSignature,
+) -> Result<()> {
+    let digest = Sha256::digest(data);
+    let signature = match signature {
+        RawSignature::Mpis(mpis) => {
+            SignatureBytes::Mpis(mpis.iter().map(|mpi| Mpi::from_slice(mpi)).collect())
+        }
+        RawSignature::Native(native) => SignatureBytes::Native(native.clone().into()),
+    };
+    key.primary_key
+        .verify(HashAlgorithm::Sha256, digest.as_slice(), &signature)?;
+    Ok(())
+}
+
 /// Merges and minimizes OpenPGP certificates.
 ///
 /// Keeps at most one direct key signature and
@@ -840,6 +986,16 @@ fn test_merge_openpgp_certificates() {
         assert!(merge_openpgp_certificates(bob.clone(), alice.clone()).is_err());
     }
 
+    #[test]
+    fn test_raw_signature() -> Result<()> {
+        let alice = alice_keypair();
+        let signature = pk_sign_raw(&alice, b"data")?;
+        pk_verify_raw(&alice.to_public_key(), b"data", &signature)?;
+        assert!(pk_verify_raw(&alice.to_public_key(), b"other data", &signature).is_err());
+        assert!(pk_verify_raw(&bob_keypair().to_public_key(), b"data", &signature).is_err());
+        Ok(())
+    }
+
     /// Test PQC support.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_pqc() -> Result<()> {
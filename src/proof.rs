@@ -0,0 +1,147 @@
+//! # Message integrity proofs.
+//!
+//! Exports a message together with cryptographic evidence that it was sent
+//! by the holder of a given key at a given time,
+//! so that it can be handed to a third party.
+//!
+//! The bundle is a tar file containing:
+//! - `original.eml`: the message as stored on the server, still encrypted,
+//! - `signed.asc`: the decrypted message with the signature of the sender
+//!   as an ASCII-armored OpenPGP signed message,
+//! - `signer.asc`: the public key of the sender,
+//! - `report.txt`: a human-readable verification report.
+//!
+//! The signed message can be verified independently of Delta Chat,
+//! e.g. with `sqv --keyring signer.asc signed.asc`
+//! or by importing `signer.asc` into GnuPG and running `gpg --verify signed.asc`.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use async_channel as channel;
+use pgp::armor::{self, BlockType};
+use pgp::packet::{LiteralData, Packet, Signature};
+use tokio::fs;
+
+use crate::contact::Contact;
+use crate::context::Context;
+use crate::decrypt;
+use crate::download::get_server_location;
+use crate::imap::Imap;
+use crate::key::{DcKey, SignedPublicKey};
+use crate::message::{Message, MsgId};
+use crate::tools::time;
+
+impl MsgId {
+    /// Exports the message with a proof of its integrity as a tar file to `path`.
+    ///
+    /// The original message is downloaded from the server again
+    /// on a dedicated connection, decrypted,
+    /// and its signature is verified with the key of the sender.
+    /// See the [module documentation](crate::proof) for the contents of the bundle.
+    ///
+    /// Fails if the message is not available on the server anymore,
+    /// e.g. because of `delete_server_after`,
+    /// or is not signed by the sender.
+    pub async fn export_with_proof(self, context: &Context, path: &Path) -> Result<()> {
+        let msg = Message::load_from_db(context, self).await?;
+        let mut connection = Imap::new_configured(context, channel::bounded(1).1).await?;
+        let mut session = connection.prepare(context).await?;
+        let (server_uid, server_folder) =
+            get_server_location(context, msg.rfc724_mid(), session.transport_id())
+                .await?
+                .context("Message is not available on the server anymore.")?;
+        let body = session
+            .peek_single_msg(context, &server_folder, server_uid)
+            .await?
+            .context("Message is not available on the server anymore.")?;
+        let bundle = build_proof(context, &msg, &body).await?;
+        fs::write(path, bundle)
+            .await
+            .with_context(|| format!("Failed to write proof to {}", path.display()))
+    }
+}
+
+/// Builds the proof bundle for `msg` from the raw original message `body`.
+async fn build_proof(context: &Context, msg: &Message, body: &[u8]) -> Result<Vec<u8>> {
+    let contact = Contact::get_by_id(context, msg.from_id).await?;
+    let signer_key = contact
+        .public_key(context)
+        .await?
+        .context("Key of the sender is unknown.")?;
+
+    let mail = mailparse::parse_mail(body)?;
+    let (signed, signature) = {
+        let (mut plain, _) = decrypt::decrypt(context, &mail)
+            .await?
+            .context("Message is not encrypted.")?;
+        let data = plain.as_data_vec()?;
+        let signature = plain
+            .verify(&signer_key.primary_key)
+            .context("Message is not signed by the sender.")?
+            .clone();
+        let signed = armored_signed_message(signature.clone(), data)?;
+        (signed, signature)
+    };
+    let report = verification_report(&contact, msg, &signer_key, &signature);
+
+    let mut builder = tokio_tar::Builder::new(Vec::new());
+    for (name, data) in [
+        ("original.eml", body),
+        ("signed.asc", signed.as_bytes()),
+        ("signer.asc", signer_key.to_asc(None).as_bytes()),
+        ("report.txt", report.as_bytes()),
+    ] {
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_size(data.len().try_into()?);
+        header.set_mode(0o644);
+        header.set_mtime(time().try_into().unwrap_or_default());
+        header.set_cksum();
+        builder.append_data(&mut header, name, data).await?;
+    }
+    Ok(builder.into_inner().await?)
+}
+
+/// Returns an ASCII-armored OpenPGP signed message
+/// consisting of the `signature` followed by the signed `data`.
+fn armored_signed_message(signature: Signature, data: Vec<u8>) -> Result<String> {
+    let packets: Vec<Packet> = vec![
+        signature.into(),
+        LiteralData::from_bytes("", data.into())?.into(),
+    ];
+    let mut buf = Vec::new();
+    armor::write(&packets, BlockType::Message, &mut buf, None, true)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn verification_report(
+    contact: &Contact,
+    msg: &Message,
+    signer_key: &SignedPublicKey,
+    signature: &Signature,
+) -> String {
+    let format_time = |timestamp: i64| {
+        chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
+            .map_or_else(|| "?".to_string(), |date| date.to_rfc3339())
+    };
+    let signature_created = signature.created().map_or_else(
+        || "?".to_string(),
+        |created| format_time(created.as_secs().into()),
+    );
+    format!(
+        "Message-ID: {}\n\
+         From: {} <{}>\n\
+         Signer fingerprint: {}\n\
+         Signature created: {signature_created}\n\
+         Signature verified: yes\n\
+         Exported: {}\n",
+        msg.rfc724_mid(),
+        contact.get_display_name(),
+        contact.get_addr(),
+        signer_key.dc_fingerprint().hex(),
+        format_time(time()),
+    )
+}
+
+#[cfg(test)]
+mod proof_tests;
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use futures::TryStreamExt;
+use tokio::io::AsyncReadExt;
+
+use super::*;
+use crate::key::load_self_public_key;
+use crate::test_utils::TestContextManager;
+
+async fn unpack(bundle: Vec<u8>) -> Result<HashMap<String, Vec<u8>>> {
+    let mut files = HashMap::new();
+    let mut entries = tokio_tar::Archive::new(&bundle[..]).entries()?;
+    while let Some(mut entry) = entries.try_next().await? {
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).await?;
+        files.insert(name, data);
+    }
+    Ok(files)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_build_proof() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let chat_id = alice.create_chat_id(bob).await;
+    let sent = alice.send_text(chat_id, "Signed by Alice").await;
+    let msg = bob.recv_msg(&sent).await;
+    let bundle = build_proof(bob, &msg, sent.payload().as_bytes()).await?;
+    let files = unpack(bundle).await?;
+    assert_eq!(files["original.eml"], sent.payload().as_bytes());
+
+    let alice_key = load_self_public_key(alice).await?;
+    let signer_key = SignedPublicKey::from_asc(std::str::from_utf8(&files["signer.asc"])?)?;
+    assert_eq!(signer_key.dc_fingerprint(), alice_key.dc_fingerprint());
+
+    // The signed message can be verified without decrypting the original message.
+    let (mut signed, _) = pgp::composed::Message::from_armor(Cursor::new(&files["signed.asc"]))?;
+    let data = signed.as_data_string()?;
+    assert!(data.contains("Signed by Alice"));
+    signed.verify(&alice_key.primary_key)?;
+
+    let report = String::from_utf8(files["report.txt"].clone())?;
+    assert!(report.contains(&format!("Message-ID: {}", msg.rfc724_mid())));
+    assert!(report.contains(&alice_key.dc_fingerprint().hex()));
+    assert!(report.contains("Signature verified: yes"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_build_proof_wrong_signer() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let chat_id = alice.create_chat_id(bob).await;
+    let sent = alice.send_text(chat_id, "Hi").await;
+    let mut msg = bob.recv_msg(&sent).await;
+    msg.from_id = bob.add_or_lookup_contact_id(fiona).await;
+    assert!(
+        build_proof(bob, &msg, sent.payload().as_bytes())
+            .await
+            .is_err()
+    );
+    Ok(())
+}
@@ -5,16 +5,29 @@
 //!
 //! It provides [`PushSubscriber`] type
 //! which holds push notification token for the device,
-//! shared by all accounts.
+//! shared by all accounts unless an account has its own token.
+//!
+//! Applications using UnifiedPush instead set
+//! [`Config::UnifiedPushEndpoint`] for each account.
+//! The endpoint is registered on the chatmail server as a web push subscription
+//! and received messages are decrypted with [`Context::decrypt_unified_push_message`].
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
-use anyhow::{Context as _, Result};
+use aes_gcm::Aes128Gcm;
+use aes_gcm::aead::{Aead, KeyInit};
+use anyhow::{Context as _, Result, anyhow, ensure};
 use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hkdf::Hkdf;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
 use pgp::crypto::aead::{AeadAlgorithm, ChunkSize};
 use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use sha2::Sha256;
 use tokio::sync::RwLock;
 
+use crate::config::Config;
 use crate::context::Context;
 use crate::key::DcKey;
 
@@ -27,6 +40,9 @@
 ///
 /// Each account (context) can then retrieve device token
 /// from this structure and give it to the email server.
+/// Applications running a separate process per account
+/// can set a token per account instead,
+/// so that notification servers wake up only the process of the relevant account.
 /// If email server does not support push notifications,
 /// account can call `subscribe` method
 /// to register device token with the heartbeat
@@ -53,6 +69,12 @@ pub struct PushSubscriber {
 =5jvt
 -----END PGP PUBLIC KEY BLOCK-----";
 
+/// Maximum length of the UnifiedPush endpoint URL.
+///
+/// Web push token containing the endpoint and the keys
+/// should fit into the padded length of device tokens.
+pub(crate) const MAX_UNIFIED_PUSH_ENDPOINT_LEN: usize = 384;
+
 /// Pads the token with spaces.
 ///
 /// This makes it impossible to tell
@@ -107,7 +129,22 @@ pub(crate) async fn set_device_token(&self, token: &str) {
         self.inner.write().await.device_token = Some(token.to_string());
     }
 
-    /// Retrieves device token.
+    /// Sets device token used only by the account with the given ID
+    /// instead of the token set with [`PushSubscriber::set_device_token`].
+    ///
+    /// `None` removes the token of the account.
+    pub(crate) async fn set_device_token_for(&self, account_id: u32, token: Option<&str>) {
+        let mut state = self.inner.write().await;
+        if let Some(token) = token {
+            state
+                .account_device_tokens
+                .insert(account_id, token.to_string());
+        } else {
+            state.account_device_tokens.remove(&account_id);
+        }
+    }
+
+    /// Retrieves device token of the account with the given ID.
     ///
     /// The token is encrypted with OpenPGP.
     ///
@@ -117,8 +154,8 @@ pub(crate) async fn set_device_token(&self, token: &str) {
     ///
     /// IMAP loop should periodically check if device token is available
     /// and send the token to the email server if it supports push notifications.
-    pub(crate) async fn device_token(&self) -> Option<String> {
-        self.inner.read().await.device_token.clone()
+    pub(crate) async fn device_token(&self, account_id: u32) -> Option<String> {
+        self.inner.read().await.device_token(account_id).cloned()
     }
 
     /// Subscribes for heartbeat notifications with previously set device token.
@@ -128,13 +165,13 @@ pub(crate) async fn subscribe(&self, context: &Context) -> Result<()> {
 
         let mut state = self.inner.write().await;
 
-        if state.heartbeat_subscribed {
+        let Some(token) = state.device_token(context.id).cloned() else {
             return Ok(());
-        }
+        };
 
-        let Some(ref token) = state.device_token else {
+        if state.heartbeat_subscribed.contains(&token) {
             return Ok(());
-        };
+        }
 
         info!(context, "Subscribing for heartbeat notifications.");
         if http::post_string(
@@ -145,21 +182,28 @@ pub(crate) async fn subscribe(&self, context: &Context) -> Result<()> {
         .await?
         {
             info!(context, "Subscribed for heartbeat notifications.");
-            state.heartbeat_subscribed = true;
+            state.heartbeat_subscribed.insert(token);
         }
         Ok(())
     }
 
     /// Placeholder to skip subscribing to heartbeat notifications outside iOS.
     #[cfg(not(target_os = "ios"))]
-    pub(crate) async fn subscribe(&self, _context: &Context) -> Result<()> {
+    pub(crate) async fn subscribe(&self, context: &Context) -> Result<()> {
         let mut state = self.inner.write().await;
-        state.heartbeat_subscribed = true;
+        if let Some(token) = state.device_token(context.id).cloned() {
+            state.heartbeat_subscribed.insert(token);
+        }
         Ok(())
     }
 
-    pub(crate) async fn heartbeat_subscribed(&self) -> bool {
-        self.inner.read().await.heartbeat_subscribed
+    /// Returns true if the device token of the account with the given ID
+    /// is subscribed to heartbeat notifications.
+    pub(crate) async fn heartbeat_subscribed(&self, account_id: u32) -> bool {
+        let state = self.inner.read().await;
+        state
+            .device_token(account_id)
+            .is_some_and(|token| state.heartbeat_subscribed.contains(token))
     }
 }
 
@@ -168,8 +212,19 @@ pub(crate) struct PushSubscriberState {
     /// Device token.
     device_token: Option<String>,
 
-    /// If subscribed to heartbeat push notifications.
-    heartbeat_subscribed: bool,
+    /// Device tokens of accounts which override `device_token`, indexed by account ID.
+    account_device_tokens: BTreeMap<u32, String>,
+
+    /// Device tokens subscribed to heartbeat push notifications.
+    heartbeat_subscribed: BTreeSet<String>,
+}
+
+impl PushSubscriberState {
+    fn device_token(&self, account_id: u32) -> Option<&String> {
+        self.account_device_tokens
+            .get(&account_id)
+            .or(self.device_token.as_ref())
+    }
 }
 
 /// Push notification state
@@ -192,7 +247,7 @@ impl Context {
     pub async fn push_state(&self) -> NotifyState {
         if self.push_subscribed.load(Ordering::Relaxed) {
             NotifyState::Connected
-        } else if self.push_subscriber.heartbeat_subscribed().await {
+        } else if self.push_subscriber.heartbeat_subscribed(self.id).await {
             NotifyState::Heartbeat
         } else {
             NotifyState::NotConnected
@@ -200,6 +255,143 @@ pub async fn push_state(&self) -> NotifyState {
     }
 }
 
+impl Context {
+    /// Generates UnifiedPush private key and authentication secret
+    /// if they do not exist yet.
+    pub(crate) async fn ensure_unified_push_keys(&self) -> Result<()> {
+        if self
+            .get_config(Config::UnifiedPushPrivateKey)
+            .await?
+            .is_some()
+            && self.get_config(Config::UnifiedPushAuth).await?.is_some()
+        {
+            return Ok(());
+        }
+        let private_key = p256::SecretKey::random(&mut rand_old::thread_rng());
+        let auth: [u8; 16] = rand::random();
+        self.sql
+            .set_raw_config(
+                Config::UnifiedPushPrivateKey.as_ref(),
+                Some(&URL_SAFE_NO_PAD.encode(private_key.to_bytes())),
+            )
+            .await?;
+        self.sql
+            .set_raw_config(
+                Config::UnifiedPushAuth.as_ref(),
+                Some(&URL_SAFE_NO_PAD.encode(auth)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns UnifiedPush private key and authentication secret.
+    async fn unified_push_keys(&self) -> Result<Option<(p256::SecretKey, Vec<u8>)>> {
+        let (Some(private_key), Some(auth)) = (
+            self.get_config(Config::UnifiedPushPrivateKey).await?,
+            self.get_config(Config::UnifiedPushAuth).await?,
+        ) else {
+            return Ok(None);
+        };
+        let private_key = p256::SecretKey::from_slice(&URL_SAFE_NO_PAD.decode(private_key)?)
+            .context("Invalid UnifiedPush private key")?;
+        let auth = URL_SAFE_NO_PAD.decode(auth)?;
+        Ok(Some((private_key, auth)))
+    }
+
+    /// Returns the token to store on the chatmail server.
+    ///
+    /// If UnifiedPush endpoint is set, this is a web push subscription
+    /// consisting of the public key, authentication secret and the endpoint.
+    /// Otherwise this is the device token.
+    pub(crate) async fn push_token(&self) -> Result<Option<String>> {
+        let Some(endpoint) = self.get_config(Config::UnifiedPushEndpoint).await? else {
+            return Ok(self.push_subscriber.device_token(self.id).await);
+        };
+        let (private_key, auth) = self
+            .unified_push_keys()
+            .await?
+            .context("UnifiedPush keys are missing")?;
+        let public_key = private_key.public_key().to_encoded_point(false);
+        Ok(Some(format!(
+            "webpush:{}:{}:{endpoint}",
+            URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+            URL_SAFE_NO_PAD.encode(auth)
+        )))
+    }
+
+    /// Decrypts a message received from the UnifiedPush distributor.
+    ///
+    /// Returns the decrypted payload.
+    /// Failure to decrypt means that the message was not sent
+    /// by the chatmail server and should be ignored.
+    pub async fn decrypt_unified_push_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let (private_key, auth) = self
+            .unified_push_keys()
+            .await?
+            .context("UnifiedPush is not set up")?;
+        decrypt_web_push(&private_key, &auth, message)
+    }
+}
+
+fn hkdf_expand<const N: usize>(salt: &[u8], ikm: &[u8], info: &[u8]) -> Result<[u8; N]> {
+    let mut okm = [0; N];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut okm)
+        .map_err(|_| anyhow!("Invalid HKDF output length"))?;
+    Ok(okm)
+}
+
+/// Decrypts web push message encrypted as described in RFC 8291
+/// with `aes128gcm` content coding from RFC 8188.
+///
+/// Only single-record messages are supported,
+/// web push messages do not exceed 4096 bytes anyway.
+fn decrypt_web_push(private_key: &p256::SecretKey, auth: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    let (salt, rest) = message.split_at_checked(16).context("No salt")?;
+    let (record_size, rest) = rest.split_at_checked(4).context("No record size")?;
+    let (&key_id_len, rest) = rest.split_first().context("No key ID length")?;
+    let (key_id, ciphertext) = rest
+        .split_at_checked(key_id_len.into())
+        .context("No key ID")?;
+    let record_size = u32::from_be_bytes(record_size.try_into()?);
+    ensure!(
+        ciphertext.len() <= usize::try_from(record_size)?,
+        "Multi-record messages are not supported"
+    );
+
+    let sender_public_key =
+        p256::PublicKey::from_sec1_bytes(key_id).context("Invalid sender public key")?;
+    let shared_secret = p256::ecdh::diffie_hellman(
+        private_key.to_nonzero_scalar(),
+        sender_public_key.as_affine(),
+    );
+    let key_info = [
+        b"WebPush: info\0".as_slice(),
+        private_key.public_key().to_encoded_point(false).as_bytes(),
+        sender_public_key.to_encoded_point(false).as_bytes(),
+    ]
+    .concat();
+    let ikm: [u8; 32] = hkdf_expand(auth, shared_secret.raw_secret_bytes(), &key_info)?;
+    let key: [u8; 16] = hkdf_expand(salt, &ikm, b"Content-Encoding: aes128gcm\0")?;
+    let nonce: [u8; 12] = hkdf_expand(salt, &ikm, b"Content-Encoding: nonce\0")?;
+
+    let mut plaintext = Aes128Gcm::new(&key.into())
+        .decrypt(&nonce.into(), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt web push message"))?;
+
+    // The last record is terminated with 0x02 delimiter followed by zero padding.
+    let delimiter_pos = plaintext
+        .iter()
+        .rposition(|&b| b != 0)
+        .context("No padding delimiter")?;
+    ensure!(
+        plaintext.get(delimiter_pos) == Some(&2),
+        "Invalid padding delimiter"
+    );
+    plaintext.truncate(delimiter_pos);
+    Ok(plaintext)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,13 +399,95 @@ mod tests {
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_set_device_token() {
         let push_subscriber = PushSubscriber::new();
-        assert_eq!(push_subscriber.device_token().await, None);
+        assert_eq!(push_subscriber.device_token(1).await, None);
 
         push_subscriber.set_device_token("some-token").await;
-        let device_token = push_subscriber.device_token().await.unwrap();
+        let device_token = push_subscriber.device_token(1).await.unwrap();
         assert_eq!(device_token, "some-token");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_set_device_token_for() {
+        let push_subscriber = PushSubscriber::new();
+        push_subscriber
+            .set_device_token_for(2, Some("account-token"))
+            .await;
+        assert_eq!(push_subscriber.device_token(1).await, None);
+        assert_eq!(
+            push_subscriber.device_token(2).await.unwrap(),
+            "account-token"
+        );
+
+        push_subscriber.set_device_token("some-token").await;
+        assert_eq!(push_subscriber.device_token(1).await.unwrap(), "some-token");
+        assert_eq!(
+            push_subscriber.device_token(2).await.unwrap(),
+            "account-token"
+        );
+
+        push_subscriber.set_device_token_for(2, None).await;
+        assert_eq!(push_subscriber.device_token(2).await.unwrap(), "some-token");
+    }
+
+    /// Tests decryption of the example message from RFC 8291 appendix A.
+    #[test]
+    fn test_decrypt_web_push() {
+        let private_key = p256::SecretKey::from_slice(
+            &URL_SAFE_NO_PAD
+                .decode("q1dXpw3UpT5VOmu_cf_v6ih07Aems3njxI-JWgLcM94")
+                .unwrap(),
+        )
+        .unwrap();
+        let auth = URL_SAFE_NO_PAD.decode("BTBZMqHH6r4Tts7J_aSIgg").unwrap();
+        let message = URL_SAFE_NO_PAD.decode("DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN").unwrap();
+
+        let plaintext = decrypt_web_push(&private_key, &auth, &message).unwrap();
+        assert_eq!(plaintext, b"When I grow up, I want to be a watermelon");
+
+        let wrong_auth = URL_SAFE_NO_PAD.decode("AAAAAAAAAAAAAAAAAAAAAA").unwrap();
+        assert!(decrypt_web_push(&private_key, &wrong_auth, &message).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_unified_push_token() -> Result<()> {
+        let t = crate::test_utils::TestContext::new_alice().await;
+        t.push_subscriber.set_device_token("some-token").await;
+        assert_eq!(t.push_token().await?.unwrap(), "some-token");
+
+        assert!(
+            t.set_config(Config::UnifiedPushEndpoint, Some("http://example.org/"))
+                .await
+                .is_err()
+        );
+        t.set_config(
+            Config::UnifiedPushEndpoint,
+            Some("https://push.example.org/up/abc"),
+        )
+        .await?;
+        let token = t.push_token().await?.unwrap();
+        assert!(token.starts_with("webpush:"));
+        assert!(token.ends_with(":https://push.example.org/up/abc"));
+        assert_eq!(
+            encrypt_device_token(&token)?.len(),
+            encrypt_device_token("some-token")?.len()
+        );
+
+        // Keys are not regenerated when the endpoint changes.
+        t.set_config(
+            Config::UnifiedPushEndpoint,
+            Some("https://push.example.org/up/def"),
+        )
+        .await?;
+        assert_eq!(
+            t.push_token().await?.unwrap(),
+            token.replace("/abc", "/def")
+        );
+
+        t.set_config(Config::UnifiedPushEndpoint, None).await?;
+        assert_eq!(t.push_token().await?.unwrap(), "some-token");
+        Ok(())
+    }
+
     #[test]
     fn test_pad_device_token() {
         let apple_token = "0155b93b7eb867a0d8b7328b978bb15bf22f70867e39e168d03f199af9496894";
@@ -7,7 +7,9 @@
 use anyhow::{Context as _, Result, anyhow, bail, ensure};
 pub use dclogin_scheme::LoginOptions;
 pub(crate) use dclogin_scheme::login_param_from_login_qr;
-use deltachat_contact_tools::{ContactAddress, addr_normalize, may_be_valid_addr};
+use deltachat_contact_tools::{
+    self as contact_tools, ContactAddress, addr_normalize, may_be_valid_addr,
+};
 use percent_encoding::{NON_ALPHANUMERIC, percent_decode_str, percent_encode};
 use rand::TryRngCore as _;
 use rand::distr::{Alphanumeric, SampleString};
@@ -17,7 +19,8 @@
 use crate::config::Config;
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
-use crate::key::Fingerprint;
+use crate::key::{DcKey, Fingerprint, SignedPublicKey};
+use crate::log::warn;
 use crate::login_param::{EnteredCertificateChecks, EnteredImapLoginParam, EnteredLoginParam};
 use crate::net::http::post_empty;
 use crate::net::proxy::{DEFAULT_SOCKS_PORT, ProxyConfig};
@@ -374,6 +377,8 @@ pub async fn check_qr(context: &Context, qr: &str) -> Result<Qr> {
         decode_ideltachat(context, IDELTACHAT_SCHEME, qr).await?
     } else if qr.starts_with(IDELTACHAT_NOSLASH_SCHEME) {
         decode_ideltachat(context, IDELTACHAT_NOSLASH_SCHEME, qr).await?
+    } else if let Some(prefix) = https_invite_link_prefix(qr) {
+        decode_ideltachat(context, prefix, qr).await?
     } else if starts_with_ignore_case(qr, DCACCOUNT_SCHEME) {
         decode_account(qr)?
     } else if starts_with_ignore_case(qr, DCLOGIN_SCHEME) {
@@ -628,7 +633,13 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
                 })
             }
         } else if context.is_self_addr(&addr).await? {
-            if token::exists(context, token::Namespace::Auth, &authcode).await? {
+            ensure!(
+                !token::exists(context, token::Namespace::UsedSingleUseAuth, &authcode).await?,
+                "The single-use invite code was already used"
+            );
+            if token::exists(context, token::Namespace::Auth, &authcode).await?
+                || token::exists(context, token::Namespace::SingleUseAuth, &authcode).await?
+            {
                 Ok(Qr::WithdrawVerifyContact {
                     contact_id,
                     fingerprint,
@@ -690,6 +701,23 @@ fn decode_name(param: &BTreeMap<&str, &str>, key: &str) -> Result<Option<String>
     }
 }
 
+/// Returns the prefix `https://DOMAIN[/]#` if `qr` is an invite link
+/// `https://DOMAIN[/]#FINGERPRINT&...` using a landing page on any domain,
+/// see [`Config::InviteLinkDomain`].
+fn https_invite_link_prefix(qr: &str) -> Option<&str> {
+    let rest = qr.strip_prefix(HTTPS_SCHEME)?;
+    let (domain, fragment) = rest.split_once('#')?;
+    let domain = domain.strip_suffix('/').unwrap_or(domain);
+    if domain.is_empty() || domain.contains(['/', '?', '@']) {
+        return None;
+    }
+    let (fingerprint, _params) = fragment.split_once('&')?;
+    if fingerprint.len() != 40 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    qr.strip_suffix(fragment)
+}
+
 /// scheme: `https://i.delta.chat[/]#FINGERPRINT&a=ADDR[&OPTIONAL_PARAMS]`
 async fn decode_ideltachat(context: &Context, prefix: &str, qr: &str) -> Result<Qr> {
     let qr = qr.replacen(prefix, OPENPGP4FPR_SCHEME, 1);
@@ -1106,6 +1134,10 @@ async fn decode_matmsg(context: &Context, qr: &str) -> Result<Qr> {
 ///
 /// Scheme: `VCARD:BEGIN\nN:last name;first name;...;\nEMAIL;<type>:addr...;`
 async fn decode_vcard(context: &Context, qr: &str) -> Result<Qr> {
+    if let Some(qr) = decode_contact_card(context, qr).await? {
+        return Ok(qr);
+    }
+
     let name = VCARD_NAME_RE
         .captures(qr)
         .and_then(|caps| {
@@ -1125,6 +1157,34 @@ async fn decode_vcard(context: &Context, qr: &str) -> Result<Qr> {
     Qr::from_address(context, &name, &addr, None).await
 }
 
+/// Decodes the invite link of a contact card
+/// created by [`Context::export_self_contact_card`].
+///
+/// Returns `None` if the vCard does not contain a single contact
+/// with an invite link matching the key of the contact.
+async fn decode_contact_card(context: &Context, qr: &str) -> Result<Option<Qr>> {
+    let [contact] = &contact_tools::parse_vcard(qr)[..] else {
+        return Ok(None);
+    };
+    let (Some(key), Some(invite_link)) = (&contact.key, &contact.invite_link) else {
+        return Ok(None);
+    };
+    let Some(prefix) = https_invite_link_prefix(invite_link) else {
+        return Ok(None);
+    };
+    let key = SignedPublicKey::from_base64(key).context("Invalid key in contact card")?;
+    let qr = decode_ideltachat(context, prefix, invite_link).await?;
+    match qr {
+        Qr::AskVerifyContact {
+            ref fingerprint, ..
+        } if *fingerprint == key.dc_fingerprint() => Ok(Some(qr)),
+        _ => {
+            warn!(context, "Invite link does not match the contact card.");
+            Ok(None)
+        }
+    }
+}
+
 impl Qr {
     /// Creates a new scanned QR code of a contact address.
     ///
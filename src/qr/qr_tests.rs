@@ -3,6 +3,7 @@
 use super::*;
 use crate::chat::{Chat, create_broadcast, create_group, get_chat_contacts};
 use crate::config::Config;
+use crate::key::load_self_public_key;
 use crate::login_param::EnteredCertificateChecks;
 use crate::provider::Socket;
 use crate::securejoin::get_securejoin_qr;
@@ -142,6 +143,44 @@ async fn test_decode_vcard() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_decode_contact_card() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice
+        .set_config(Config::Selfstatus, Some("Find me here"))
+        .await?;
+
+    let path = alice.get_blobdir().join("contact-card.vcf");
+    alice.export_self_contact_card(&path).await?;
+    let vcard = tokio::fs::read_to_string(&path).await?;
+    let contacts = contact_tools::parse_vcard(&vcard);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].addr, "alice@example.org");
+    assert_eq!(contacts[0].biography.as_deref(), Some("Find me here"));
+    let invite_link = contacts[0].invite_link.as_deref().unwrap();
+    assert!(invite_link.starts_with("https://i.delta.chat/#"));
+
+    let qr = check_qr(bob, &vcard).await?;
+    let Qr::AskVerifyContact { fingerprint, .. } = qr else {
+        bail!("Wrong QR code type: {qr:?}");
+    };
+    assert_eq!(
+        fingerprint,
+        load_self_public_key(alice).await?.dc_fingerprint()
+    );
+
+    // Invite link of another key is not accepted.
+    let charlie = &tcm.charlie().await;
+    let charlie_link = get_securejoin_qr(charlie, None).await?;
+    let forged = vcard.replace(invite_link, &charlie_link);
+    let qr = check_qr(bob, &forged).await?;
+    assert!(matches!(qr, Qr::Addr { .. }));
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_decode_matmsg() -> Result<()> {
     let ctx = TestContext::new().await;
@@ -242,6 +281,64 @@ async fn test_decode_ideltachat_link() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_decode_https_invite_link_custom_domain() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    // Only bare hostnames are accepted, so generated links can be parsed.
+    for domain in [
+        "https://invite.example.org/",
+        "invite.example.org/page",
+        "invite example.org",
+        "invite.example.org:443",
+        "invite..example.org",
+    ] {
+        assert!(
+            alice
+                .set_config(Config::InviteLinkDomain, Some(domain))
+                .await
+                .is_err()
+        );
+    }
+    alice
+        .set_config(Config::InviteLinkDomain, Some("invite.example.org"))
+        .await?;
+    let qr = get_securejoin_qr(alice, None).await?;
+    assert!(qr.starts_with("https://invite.example.org/#"));
+    let Qr::AskVerifyContact { contact_id, .. } = check_qr(bob, &qr).await? else {
+        bail!("Wrong QR code type");
+    };
+    assert_eq!(contact_id, bob.add_or_lookup_contact_id(alice).await);
+
+    let qr = check_qr(
+        bob,
+        "https://invite.example.org#79252762C34C5096AF57958F4FC3D21A81B0F0A7&a=cli%40deltachat.de&g=test%20%3F+test%20%21&x=h-0oKQf2CDK&i=9JEXlxAqGM0&s=0V7LzL9cxRL",
+    )
+    .await?;
+    assert!(matches!(qr, Qr::AskVerifyGroup { .. }));
+
+    // Links without a fingerprint in the fragment are not invites.
+    for url in [
+        "https://example.org/#79252762C34C5096AF57958F4FC3D21A81B0F0A7",
+        "https://example.org/#foo&a=cli%40deltachat.de",
+        "https://example.org/page#79252762C34C5096AF57958F4FC3D21A81B0F0A7&a=bar",
+    ] {
+        assert_eq!(
+            check_qr(bob, url).await?,
+            Qr::Url {
+                url: url.to_string()
+            }
+        );
+    }
+
+    alice.set_config(Config::InviteLinkDomain, None).await?;
+    let qr = get_securejoin_qr(alice, None).await?;
+    assert!(qr.starts_with("https://i.delta.chat/#"));
+    Ok(())
+}
+
 // macOS and iOS sometimes replace the # with %23 (uri encode it), we should be able to parse this wrong format too.
 // see issue https://github.com/deltachat/deltachat-core-rust/issues/1969 for more info
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
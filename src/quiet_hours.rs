@@ -0,0 +1,119 @@
+//! # Quiet hours.
+//!
+//! During the daily quiet hours configured with [`Config::QuietHours`],
+//! UIs should not notify about [`EventType::IncomingMsg`] events,
+//! see [`Context::is_quiet_hours()`],
+//! and background work such as housekeeping is deferred.
+//!
+//! The setting has the form `HH:MM-HH:MM`, e.g. `22:00-07:00`,
+//! optionally followed by a UTC offset such as `22:00-07:00 +01:00`.
+//! Without an offset, the local time of the device is used.
+//! Quiet hours may wrap around midnight.
+//! An empty value disables quiet hours.
+//!
+//! [`Config::QuietHours`]: crate::config::Config::QuietHours
+//! [`EventType::IncomingMsg`]: crate::EventType::IncomingMsg
+
+use std::str::FromStr;
+
+use anyhow::{Context as _, Error, Result, bail, ensure};
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Timelike, Utc};
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::tools::time;
+
+/// Parsed value of [`Config::QuietHours`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QuietHours {
+    /// Start of the quiet hours in minutes after midnight.
+    start: u32,
+
+    /// End of the quiet hours in minutes after midnight, exclusive.
+    end: u32,
+
+    /// Time zone of `start` and `end`, `None` for the local time zone.
+    offset: Option<FixedOffset>,
+}
+
+impl FromStr for QuietHours {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let range = parts.next().context("Empty quiet hours")?;
+        let offset = parts.next().map(parse_offset).transpose()?;
+        ensure!(parts.next().is_none(), "Trailing data in quiet hours {s:?}");
+        let (start, end) = range
+            .split_once('-')
+            .with_context(|| format!("Quiet hours {s:?} are not a range"))?;
+        let start = parse_minutes(start)?;
+        let end = parse_minutes(end)?;
+        ensure!(start != end, "Quiet hours {s:?} are empty");
+        Ok(Self { start, end, offset })
+    }
+}
+
+impl QuietHours {
+    /// Returns true if `timestamp` is within the quiet hours.
+    pub(crate) fn contains(&self, timestamp: i64) -> bool {
+        let Some(utc) = DateTime::<Utc>::from_timestamp(timestamp, 0) else {
+            return false;
+        };
+        let time = match self.offset {
+            Some(offset) => utc.with_timezone(&offset).time(),
+            None => utc.with_timezone(&Local).time(),
+        };
+        let minutes = time.hour().saturating_mul(60).saturating_add(time.minute());
+        if self.start < self.end {
+            self.start <= minutes && minutes < self.end
+        } else {
+            self.start <= minutes || minutes < self.end
+        }
+    }
+}
+
+fn parse_minutes(s: &str) -> Result<u32> {
+    let time = NaiveTime::parse_from_str(s, "%H:%M")
+        .with_context(|| format!("Invalid time {s:?} in quiet hours"))?;
+    Ok(time.hour().saturating_mul(60).saturating_add(time.minute()))
+}
+
+fn parse_offset(s: &str) -> Result<FixedOffset> {
+    let (sign, hhmm) = match s.strip_prefix('+') {
+        Some(hhmm) => (1, hhmm),
+        None => match s.strip_prefix('-') {
+            Some(hhmm) => (-1, hhmm),
+            None => bail!("UTC offset {s:?} must start with + or -"),
+        },
+    };
+    let minutes = parse_minutes(hhmm)?;
+    let seconds = i32::try_from(minutes)?
+        .saturating_mul(60)
+        .saturating_mul(sign);
+    FixedOffset::east_opt(seconds).with_context(|| format!("Invalid UTC offset {s:?}"))
+}
+
+impl Context {
+    /// Returns true if the current time is within the quiet hours
+    /// configured with [`Config::QuietHours`].
+    pub fn is_quiet_hours(&self) -> bool {
+        self.quiet_hours
+            .read()
+            .expect("RwLock is poisoned")
+            .is_some_and(|quiet_hours| quiet_hours.contains(time()))
+    }
+
+    /// Loads [`Config::QuietHours`] into the cache used by [`Context::is_quiet_hours()`].
+    pub(crate) async fn update_quiet_hours(&self) -> Result<()> {
+        let quiet_hours = match self.get_config(Config::QuietHours).await? {
+            Some(value) if !value.is_empty() => Some(value.parse()?),
+            _ => None,
+        };
+        *self.quiet_hours.write().expect("RwLock is poisoned") = quiet_hours;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod quiet_hours_tests;
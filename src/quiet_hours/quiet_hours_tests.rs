@@ -0,0 +1,126 @@
+use chrono::TimeDelta;
+
+use super::*;
+use crate::EventType;
+use crate::test_utils::TestContextManager;
+
+/// Returns quiet hours ending in 12 hours and starting a minute later.
+///
+/// Other tests may shift the time used by the core forward,
+/// so the quiet hours cover all but a minute of the day.
+fn almost_always_quiet() -> String {
+    let now = DateTime::<Utc>::from_timestamp(time(), 0).unwrap();
+    let end = now + TimeDelta::hours(12);
+    let start = end + TimeDelta::minutes(1);
+    format!("{}-{} +00:00", start.format("%H:%M"), end.format("%H:%M"))
+}
+
+#[test]
+fn test_parse() -> Result<()> {
+    assert_eq!(
+        "22:00-07:00".parse::<QuietHours>()?,
+        QuietHours {
+            start: 22 * 60,
+            end: 7 * 60,
+            offset: None,
+        }
+    );
+    assert_eq!(
+        "12:30-13:45 -05:30".parse::<QuietHours>()?,
+        QuietHours {
+            start: 12 * 60 + 30,
+            end: 13 * 60 + 45,
+            offset: FixedOffset::west_opt(5 * 3600 + 30 * 60),
+        }
+    );
+    for invalid in [
+        "",
+        "22:00",
+        "22:00-",
+        "25:00-07:00",
+        "22:00-07:60",
+        "07:00-07:00",
+        "22:00-07:00 01:00",
+        "22:00-07:00 +01:00 x",
+    ] {
+        assert!(invalid.parse::<QuietHours>().is_err(), "{invalid:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_contains() -> Result<()> {
+    // 2024-01-01 23:30:00 UTC.
+    let late = 1704151800;
+    // 2024-01-01 06:59:00 UTC.
+    let early = 1704092340;
+    // 2024-01-01 07:00:00 UTC.
+    let morning = 1704092400;
+
+    let quiet_hours: QuietHours = "22:00-07:00 +00:00".parse()?;
+    assert!(quiet_hours.contains(late));
+    assert!(quiet_hours.contains(early));
+    assert!(!quiet_hours.contains(morning));
+
+    let quiet_hours: QuietHours = "06:00-07:00 +00:00".parse()?;
+    assert!(!quiet_hours.contains(late));
+    assert!(quiet_hours.contains(early));
+    assert!(!quiet_hours.contains(morning));
+
+    // 23:30 UTC is 01:30 at UTC+02:00.
+    let quiet_hours: QuietHours = "01:00-02:00 +02:00".parse()?;
+    assert!(quiet_hours.contains(late));
+    assert!(!quiet_hours.contains(early));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_config() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+
+    assert!(!alice.is_quiet_hours());
+    assert!(
+        alice
+            .set_config(Config::QuietHours, Some("22:00"))
+            .await
+            .is_err()
+    );
+    assert_eq!(alice.get_config(Config::QuietHours).await?, None);
+
+    alice
+        .set_config(Config::QuietHours, Some(&almost_always_quiet()))
+        .await?;
+    assert!(alice.is_quiet_hours());
+
+    alice.set_config(Config::QuietHours, Some("")).await?;
+    assert!(!alice.is_quiet_hours());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_incoming_msg_quiet_hours() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let chat = alice.create_chat(bob).await;
+    bob.create_chat(alice).await;
+    let sent = alice.send_text(chat.id, "Hi Bob!").await;
+    bob.recv_msg(&sent).await;
+    bob.evtracker
+        .get_matching(|evt| matches!(evt, EventType::IncomingMsg { .. }))
+        .await;
+    assert!(!bob.is_quiet_hours());
+
+    bob.set_config(Config::QuietHours, Some(&almost_always_quiet()))
+        .await?;
+    let sent = alice.send_text(chat.id, "Good night!").await;
+    bob.recv_msg(&sent).await;
+    // The event is still emitted, the UI checks for quiet hours itself.
+    bob.evtracker
+        .get_matching(|evt| matches!(evt, EventType::IncomingMsg { .. }))
+        .await;
+    assert!(bob.is_quiet_hours());
+    Ok(())
+}
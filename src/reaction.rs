@@ -27,6 +27,7 @@
 use crate::context::Context;
 use crate::events::EventType;
 use crate::message::{Message, MsgId, rfc724_mid_exists};
+use crate::notifications::{NotificationKind, add_notification};
 use crate::param::Param;
 
 /// A single reaction.
@@ -328,6 +329,15 @@ pub(crate) async fn set_msg_reaction_if_present(
                 msg_id,
                 reaction: reaction.clone(),
             });
+            add_notification(
+                context,
+                NotificationKind::Reaction,
+                chat_id,
+                contact_id,
+                msg_id,
+                reaction.as_str(),
+            )
+            .await?;
         }
         return Ok(true);
     }
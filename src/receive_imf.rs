@@ -12,26 +12,36 @@
     sanitize_single_line,
 };
 use mailparse::SingleInfo;
+use num_traits::ToPrimitive as _;
 use regex::Regex;
+use sha2::{Digest, Sha256};
 
 use crate::chat::{
-    self, Chat, ChatId, ChatIdBlocked, ChatVisibility, is_contact_in_chat, save_broadcast_secret,
-    admin_group_fingerprint,
+    self, Chat, ChatId, ChatIdBlocked, ChatVisibility, admin_group_fingerprint, is_contact_in_chat,
+    save_broadcast_secret,
 };
 use crate::config::Config;
 use crate::constants::{self, Blocked, Chattype, DC_CHAT_ID_TRASH, EDITED_PREFIX};
-use crate::contact::{self, Contact, ContactId, Origin, mark_contact_id_as_verified};
+use crate::contact::{
+    self, Contact, ContactId, Origin, is_addr_domain_blocked, mark_contact_id_as_verified,
+    set_blocked,
+};
 use crate::context::Context;
 use crate::debug_logging::maybe_set_logging_xdc_inner;
+use crate::download::p2p::P2pAttachment;
+use crate::download::upload::UploadedAttachment;
 use crate::download::{DownloadState, msg_is_downloaded_for};
 use crate::ephemeral::{Timer as EphemeralTimer, stock_ephemeral_timer_changed};
 use crate::events::EventType;
+use crate::filters::{self, FilterAction};
+use crate::group_history;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::imap::{GENERATED_PREFIX, markseen_on_imap_table};
 use crate::key::{DcKey, Fingerprint};
 use crate::key::{
     load_self_public_key, load_self_public_key_opt, self_fingerprint, self_fingerprint_opt,
 };
+use crate::language;
 use crate::log::{LogExt as _, warn};
 use crate::message::{
     self, Message, MessageState, MessengerMessage, MsgId, Viewtype, insert_tombstone,
@@ -40,6 +50,8 @@
 use crate::mimeparser::{
     AvatarAction, GossipedKey, MimeMessage, PreMessageMode, SystemMessage, parse_message_ids,
 };
+use crate::moderation;
+use crate::notifications;
 use crate::param::{Param, Params};
 use crate::peer_channels::{add_gossip_peer_from_header, insert_topic_stub, iroh_topic_from_str};
 use crate::reaction::{Reaction, set_msg_reaction};
@@ -49,6 +61,7 @@
 };
 use crate::simplify;
 use crate::smtp::msg_has_pending_smtp_job;
+use crate::split_msg;
 use crate::stats::STATISTICS_BOT_EMAIL;
 use crate::stock_str;
 use crate::sync::Sync::*;
@@ -603,6 +616,36 @@ pub(crate) async fn receive_imf_inner(
         }
     };
 
+    let content_hash = if mime_parser.incoming
+        && !from_id.is_special()
+        && mime_parser.pre_message != mimeparser::PreMessageMode::Post
+        && mime_parser.get_header(HeaderDef::SecureJoin).is_none()
+        && mime_parser.get_header(HeaderDef::Date).is_some()
+        && !mime_parser.has_chat_version()
+    {
+        content_hash(&mime_parser)
+    } else {
+        None
+    };
+    if let Some(hash) = &content_hash
+        && let Some(original_msg_id) = get_msg_id_by_content_hash(context, hash).await?
+    {
+        // The same message may arrive under a different Message-ID,
+        // e.g. once directly and once via a mailing list rewriting Message-IDs.
+        info!(
+            context,
+            "Message {rfc724_mid_orig:?} is a duplicate of {original_msg_id} (TRASH)."
+        );
+        context
+            .sql
+            .execute(
+                "INSERT OR REPLACE INTO msgs_duplicates (rfc724_mid, msg_id) VALUES (?, ?)",
+                (rfc724_mid_orig, original_msg_id),
+            )
+            .await?;
+        return trash().await;
+    }
+
     // Lookup parent message.
     //
     // This may be useful to assign the message to
@@ -622,6 +665,21 @@ pub(crate) async fn receive_imf_inner(
 
     let mut chat_assignment =
         decide_chat_assignment(context, &mime_parser, &parent_message, rfc724_mid, from_id).await?;
+    let filter_action = if mime_parser.incoming
+        && !from_id.is_special()
+        && !matches!(chat_assignment, ChatAssignment::Trash)
+    {
+        filters::get_filter_action(context, &mime_parser).await?
+    } else {
+        None
+    };
+    if matches!(
+        filter_action,
+        Some(FilterAction::MoveToSpam | FilterAction::Delete)
+    ) {
+        info!(context, "Message matches a filter (TRASH).");
+        chat_assignment = ChatAssignment::Trash;
+    }
     let (to_ids, past_ids) = get_to_and_past_contact_ids(
         context,
         &mime_parser,
@@ -683,7 +741,7 @@ pub(crate) async fn receive_imf_inner(
     }
 
     let is_old_contact_request;
-    let received_msg = if let Some(received_msg) = received_msg {
+    let mut received_msg = if let Some(received_msg) = received_msg {
         is_old_contact_request = false;
         received_msg
     } else {
@@ -750,10 +808,47 @@ pub(crate) async fn receive_imf_inner(
         msg
     };
 
+    if let Some(hash) = &content_hash
+        && !received_msg.chat_id.is_trash()
+        && let Some(msg_id) = received_msg.msg_ids.first()
+    {
+        context
+            .sql
+            .execute(
+                "INSERT OR IGNORE INTO msgs_content_hashes (msg_id, hash) VALUES (?, ?)",
+                (msg_id, hash),
+            )
+            .await?;
+    }
+
     if !from_id.is_special() {
         contact::update_last_seen(context, from_id, mime_parser.timestamp_sent).await?;
     }
 
+    match filter_action {
+        Some(FilterAction::MuteNotification) if received_msg.state == MessageState::InFresh => {
+            for msg_id in &received_msg.msg_ids {
+                message::update_msg_state(context, *msg_id, MessageState::InNoticed).await?;
+            }
+            received_msg.state = MessageState::InNoticed;
+        }
+        Some(FilterAction::Archive) if !received_msg.chat_id.is_special() => {
+            received_msg
+                .chat_id
+                .set_visibility_ex(context, Nosync, ChatVisibility::Archived)
+                .await?;
+            // Archiving marks fresh messages of the chat as noticed in the database.
+            if received_msg.state == MessageState::InFresh {
+                received_msg.state = MessageState::InNoticed;
+            }
+        }
+        Some(FilterAction::MoveToSpam) => {
+            filters::move_to_spam_on_imap_table(context, rfc724_mid_orig).await?;
+        }
+        Some(FilterAction::Delete) => received_msg.needs_delete_job = true,
+        _ => {}
+    }
+
     // Update gossiped timestamp for the chat if someone else or our other device sent
     // Autocrypt-Gossip header to avoid sending Autocrypt-Gossip ourselves
     // and waste traffic.
@@ -840,6 +935,37 @@ pub(crate) async fn receive_imf_inner(
         }
     }
 
+    if let Some(ref group_history) = mime_parser.group_history
+        && from_id != ContactId::SELF
+    {
+        if mime_parser.was_encrypted() {
+            group_history::receive_history(
+                context,
+                from_id,
+                mime_parser.timestamp_sent,
+                group_history,
+            )
+            .await
+            .log_err(context)
+            .ok();
+        } else {
+            warn!(context, "Group history is not encrypted.");
+        }
+    }
+
+    if let Some(ref abuse_report) = mime_parser.abuse_report
+        && from_id != ContactId::SELF
+    {
+        if mime_parser.was_encrypted() {
+            moderation::receive_report(context, from_id, abuse_report)
+                .await
+                .log_err(context)
+                .ok();
+        } else {
+            warn!(context, "Abuse report is not encrypted.");
+        }
+    }
+
     if let Some(ref status_update) = mime_parser.webxdc_status_update
         && !matches!(mime_parser.pre_message, PreMessageMode::Pre { .. })
     {
@@ -1035,6 +1161,7 @@ pub(crate) async fn receive_imf_inner(
         let is_pre_message = matches!(mime_parser.pre_message, PreMessageMode::Pre { .. });
         let skip_bot_notify = is_bot && is_pre_message;
         let is_empty = !is_pre_message
+            && mime_parser.get_header(HeaderDef::ChatUploadUrl).is_none()
             && mime_parser.parts.first().is_none_or(|p| {
                 p.typ == Viewtype::Text && p.msg.is_empty() && p.param.get(Param::Quote).is_none()
             });
@@ -1046,6 +1173,17 @@ pub(crate) async fn receive_imf_inner(
 
         for msg_id in &received_msg.msg_ids {
             chat_id.emit_msg_event(context, *msg_id, important);
+            if important {
+                notifications::add_msg_notification(context, *msg_id).await?;
+            }
+        }
+        for (part, msg_id) in mime_parser.parts.iter().zip(&received_msg.msg_ids) {
+            if part.param.exists(Param::AttachmentWarning) {
+                context.emit_event(EventType::AttachmentWarning {
+                    chat_id,
+                    msg_id: *msg_id,
+                });
+            }
         }
     }
     context.new_msgs_notify.notify_one();
@@ -1150,9 +1288,15 @@ pub async fn from_field_to_contact_id(
         Ok(Some((ContactId::SELF, false, Origin::OutgoingBcc)))
     } else {
         let contact = Contact::get_by_id(context, from_id).await?;
-        let from_id_blocked = contact.blocked;
+        let mut from_id_blocked = contact.blocked;
         let incoming_origin = contact.origin;
 
+        if !from_id_blocked && is_addr_domain_blocked(context, &from_addr).await? {
+            info!(context, "Blocking {from_id} because its domain is blocked.");
+            set_blocked(context, Nosync, from_id, true).await?;
+            from_id_blocked = true;
+        }
+
         context
             .sql
             .execute(
@@ -1183,9 +1327,15 @@ async fn decide_chat_assignment(
     } else if mime_parser.get_header(HeaderDef::ChatEdit).is_some()
         || mime_parser.get_header(HeaderDef::ChatDelete).is_some()
         || mime_parser.get_header(HeaderDef::IrohNodeAddr).is_some()
+        || mime_parser.get_header(HeaderDef::ChatSplitOf).is_some()
         || mime_parser.sync_items.is_some()
+        || mime_parser.group_history.is_some()
+        || mime_parser.abuse_report.is_some()
     {
-        info!(context, "Chat edit/delete/iroh/sync message (TRASH).");
+        info!(
+            context,
+            "Chat edit/delete/iroh/split/sync/history/report message (TRASH)."
+        );
         true
     } else if mime_parser.is_system_message == SystemMessage::CallAccepted
         || mime_parser.is_system_message == SystemMessage::CallEnded
@@ -1782,6 +1932,24 @@ async fn add_parts(
         }
     }
 
+    if mime_parser.incoming {
+        for part in &mut mime_parser.parts {
+            let warning = part.param.get(Param::Filename).and_then(|filename| {
+                message::check_attachment(filename, part.param.get(Param::MimeType))
+            });
+            if let Some(warning) = warning {
+                info!(
+                    context,
+                    "Attachment of {rfc724_mid} looks suspicious: {warning:?}."
+                );
+                part.param.set_int(Param::AttachmentWarning, warning as i32);
+            }
+            if let Some(language) = language::detect(&part.msg) {
+                part.param.set(Param::Language, language);
+            }
+        }
+    }
+
     let mut chat = Chat::load_from_db(context, chat_id).await?;
 
     if mime_parser.incoming && !chat_id.is_trash() {
@@ -1814,8 +1982,7 @@ async fn add_parts(
     // Sort message to the bottom if we are not in the chat
     // so if we are added via QR code scan
     // the message about our addition goes after all the info messages.
-    // Info messages are sorted by local smeared_timestamp()
-    // which advances quickly during SecureJoin,
+    // Info messages are sorted by local time,
     // while "member added" message may have older timestamp
     // corresponding to the sender clock.
     // In practice inviter clock may even be slightly in the past.
@@ -2003,6 +2170,8 @@ async fn add_parts(
     )
     .await?;
 
+    split_msg::handle_split_part(context, mime_parser, rfc724_mid_orig, from_id).await?;
+
     let mime_in_reply_to = mime_parser
         .get_header(HeaderDef::InReplyTo)
         .unwrap_or_default();
@@ -2028,7 +2197,9 @@ async fn add_parts(
     // `true` finally.
     let mut save_mime_modified = false;
 
-    let mime_headers = if mime_parser.is_mime_modified {
+    // Bots may retain the full MIME of all messages, see `MsgId::get_raw_mime()`.
+    let save_mime_headers = context.get_config_bool(Config::SaveMimeHeaders).await?;
+    let mime_headers = if mime_parser.is_mime_modified || save_mime_headers {
         let headers = if !mime_parser.decoded_data.is_empty() {
             mime_parser.decoded_data.clone()
         } else {
@@ -2062,7 +2233,7 @@ async fn add_parts(
     };
 
     for (group_changes_msg, cmd, added_removed_id) in group_changes.extra_msgs {
-        chat::add_info_msg_with_cmd(
+        let msg_id = chat::add_info_msg_with_cmd(
             context,
             chat_id,
             &group_changes_msg,
@@ -2074,6 +2245,9 @@ async fn add_parts(
             added_removed_id,
         )
         .await?;
+        if is_member_change(cmd) && !chat_id.is_trash() {
+            coalesce_member_changes(context, chat_id, msg_id).await?;
+        }
     }
 
     if let Some(node_addr) = mime_parser.get_header(HeaderDef::IrohNodeAddr) {
@@ -2109,7 +2283,11 @@ async fn add_parts(
         }
     }
 
-    handle_edit_delete(context, mime_parser, from_id, &mime_headers, &grpid).await?;
+    let edit_mime_headers = match mime_parser.is_mime_modified {
+        true => mime_headers.as_slice(),
+        false => &[],
+    };
+    handle_edit_delete(context, mime_parser, from_id, edit_mime_headers, &grpid).await?;
     handle_post_message(context, mime_parser, from_id, state).await?;
 
     if mime_parser.is_system_message == SystemMessage::CallAccepted
@@ -2130,7 +2308,24 @@ async fn add_parts(
         }
     }
 
-    let hidden = mime_parser.parts.iter().all(|part| part.is_reaction);
+    // Metadata of the quoted message, stored in case it is deleted later.
+    // It is created only once as it may contain a thumbnail of the quoted image.
+    let quote_metadata = if !mime_in_reply_to.is_empty()
+        && mime_parser
+            .parts
+            .iter()
+            .any(|part| part.param.exists(Param::Quote))
+        && let Some(quoted_msg_id) = rfc724_mid_exists(context, mime_in_reply_to).await?
+        && let Some(quoted_msg) = Message::load_from_db_optional(context, quoted_msg_id).await?
+    {
+        let mut quote_metadata = Params::new();
+        message::set_quote_metadata(context, &mut quote_metadata, &quoted_msg).await?;
+        Some(quote_metadata)
+    } else {
+        None
+    };
+
+    let mut hidden = mime_parser.parts.iter().all(|part| part.is_reaction);
     let mut parts = mime_parser.parts.iter().peekable();
     while let Some(part) = parts.next() {
         let hidden = part.is_reaction;
@@ -2150,6 +2345,11 @@ async fn add_parts(
         }
 
         let mut param = part.param.clone();
+        if let Some(quote_metadata) = &quote_metadata
+            && param.exists(Param::Quote)
+        {
+            param.merge_in_params(quote_metadata.clone());
+        }
         if is_system_message != SystemMessage::Unknown {
             param.set_int(Param::Cmd, is_system_message as i32);
         }
@@ -2187,6 +2387,22 @@ async fn add_parts(
         {
             param.apply_post_msg_metadata(metadata);
         };
+        if created_db_entries.is_empty()
+            && let Some(uploaded_attachment) =
+                UploadedAttachment::from_mime_parser(context, mime_parser)
+        {
+            param
+                .apply_post_msg_metadata(&uploaded_attachment.metadata)
+                .set(Param::UploadUrl, &uploaded_attachment.url)
+                .set(Param::UploadKey, &uploaded_attachment.key);
+        } else if created_db_entries.is_empty()
+            && let Some(p2p_attachment) = P2pAttachment::from_mime_parser(context, mime_parser)
+        {
+            param
+                .apply_post_msg_metadata(&p2p_attachment.metadata)
+                .set(Param::P2pHash, &p2p_attachment.hash)
+                .set(Param::P2pNodeAddr, &p2p_attachment.node_addr);
+        }
 
         // If you change which information is skipped if the message is trashed,
         // also change `MsgId::trash()` and `delete_expired_messages()`
@@ -2268,7 +2484,7 @@ async fn add_parts(
                     },
                     !trash && hidden,
                     if trash { 0 } else { part.bytes as isize },
-                    if save_mime_modified && !(trash || hidden) {
+                    if (save_mime_modified || save_mime_headers) && !(trash || hidden) {
                         mime_headers.clone()
                     } else {
                         Vec::new()
@@ -2289,6 +2505,8 @@ async fn add_parts(
                         DownloadState::Undecipherable
                     } else if let PreMessageMode::Pre { .. } = mime_parser.pre_message {
                         DownloadState::Available
+                    } else if param.exists(Param::UploadUrl) {
+                        DownloadState::Available
                     } else {
                         DownloadState::Done
                     },
@@ -2302,6 +2520,13 @@ async fn add_parts(
         created_db_entries.push(row_id);
     }
 
+    if is_member_change(is_system_message)
+        && !chat_id.is_trash()
+        && let [msg_id] = created_db_entries[..]
+    {
+        hidden |= coalesce_member_changes(context, chat_id, msg_id).await?;
+    }
+
     // Maybe set logging xdc and add gossip topics for webxdcs.
     for (part, msg_id) in mime_parser.parts.iter().zip(&created_db_entries) {
         if mime_parser.pre_message != PreMessageMode::Post
@@ -2322,6 +2547,22 @@ async fn add_parts(
         .await?;
     }
 
+    // Attachments are not downloaded automatically for contact requests and blocked chats.
+    if !chat_id.is_trash()
+        && chat_id_blocked == Blocked::Not
+        && let Some(msg_id) = created_db_entries.first()
+    {
+        if let Some(uploaded_attachment) =
+            UploadedAttachment::from_mime_parser(context, mime_parser)
+        {
+            uploaded_attachment
+                .schedule_download(context, *msg_id)
+                .await?;
+        } else if let Some(p2p_attachment) = P2pAttachment::from_mime_parser(context, mime_parser) {
+            p2p_attachment.schedule_download(context, *msg_id).await?;
+        }
+    }
+
     let unarchive = match mime_parser.get_header(HeaderDef::ChatGroupMemberRemoved) {
         Some(addr) => context.is_self_addr(addr).await?,
         None => true,
@@ -2523,6 +2764,8 @@ async fn handle_post_message(
     let mut new_params = original_msg.param.clone();
     new_params
         .merge_in_params(part.param.clone())
+        .remove(Param::P2pHash)
+        .remove(Param::P2pNodeAddr)
         .remove(Param::PostMessageFileBytes)
         .remove(Param::PostMessageViewtype);
     // Don't update `chat_id`: even if it differs from pre-message's one somehow so the result
@@ -3545,6 +3788,126 @@ async fn group_changes_msgs(
     Ok(group_changes_msgs)
 }
 
+fn is_member_change(cmd: SystemMessage) -> bool {
+    matches!(
+        cmd,
+        SystemMessage::MemberAddedToGroup | SystemMessage::MemberRemovedFromGroup
+    )
+}
+
+/// Maximum time between two group member changes to merge them into a single info message.
+const COALESCE_MEMBER_CHANGES_WINDOW: i64 = 10 * 60;
+
+/// Merges the group member change message `msg_id`
+/// with the immediately preceding member change message or summary
+/// if [`Config::CoalesceMemberChanges`] is enabled.
+///
+/// Merged messages are hidden and replaced by a summarizing info message,
+/// see [`MsgId::get_coalesced_msgs`].
+/// Returns whether `msg_id` was merged and hidden.
+async fn coalesce_member_changes(
+    context: &Context,
+    chat_id: ChatId,
+    msg_id: MsgId,
+) -> Result<bool> {
+    if !context
+        .get_config_bool(Config::CoalesceMemberChanges)
+        .await?
+    {
+        return Ok(false);
+    }
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let Some(prev_id) = context
+        .sql
+        .query_get_value::<MsgId>(
+            "SELECT id FROM msgs
+             WHERE chat_id=?1 AND hidden=0 AND id!=?3
+             AND (timestamp<?2 OR (timestamp=?2 AND id<?3))
+             ORDER BY timestamp DESC, id DESC LIMIT 1",
+            (chat_id, msg.timestamp_sort, msg_id),
+        )
+        .await?
+    else {
+        return Ok(false);
+    };
+    let prev = Message::load_from_db(context, prev_id).await?;
+    if prev.from_id == ContactId::SELF {
+        return Ok(false);
+    }
+
+    let (summary_id, last_timestamp) = if let Some(last_timestamp) = context
+        .sql
+        .query_get_value::<Option<i64>>(
+            "SELECT MAX(m.timestamp) FROM msgs_coalesced c
+             INNER JOIN msgs m ON m.id=c.msg_id
+             WHERE c.summary_msg_id=?",
+            (prev_id,),
+        )
+        .await?
+        .flatten()
+    {
+        (Some(prev_id), last_timestamp)
+    } else if is_member_change(prev.param.get_cmd()) {
+        (None, prev.timestamp_sort)
+    } else {
+        return Ok(false);
+    };
+    if msg.timestamp_sort.saturating_sub(last_timestamp) > COALESCE_MEMBER_CHANGES_WINDOW {
+        return Ok(false);
+    }
+
+    let summary_id = match summary_id {
+        Some(summary_id) => summary_id,
+        None => {
+            let summary_id = chat::add_info_msg_with_cmd(
+                context,
+                chat_id,
+                &stock_str::msg_member_changes(context, 2),
+                SystemMessage::Unknown,
+                Some(prev.timestamp_sort),
+                prev.timestamp_sent,
+                None,
+                None,
+                None,
+            )
+            .await?;
+            hide_coalesced_msg(context, summary_id, prev_id).await?;
+            summary_id
+        }
+    };
+    hide_coalesced_msg(context, summary_id, msg_id).await?;
+
+    let count = summary_id.get_coalesced_msgs(context).await?.len();
+    let text = stock_str::msg_member_changes(context, count);
+    context
+        .sql
+        .execute(
+            "UPDATE msgs SET txt=?, txt_normalized=? WHERE id=?",
+            (&text, normalize_text(&text), summary_id),
+        )
+        .await?;
+    context.emit_msgs_changed(chat_id, summary_id);
+    Ok(true)
+}
+
+/// Hides the member change message `msg_id` merged into the summary `summary_id`.
+async fn hide_coalesced_msg(context: &Context, summary_id: MsgId, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "INSERT OR REPLACE INTO msgs_coalesced (msg_id, summary_msg_id) VALUES (?, ?)",
+                (msg_id, summary_id),
+            )?;
+            transaction.execute(
+                "UPDATE msgs SET hidden=1, state=IIF(state=?, ?, state) WHERE id=?",
+                (MessageState::InFresh, MessageState::InNoticed, msg_id),
+            )?;
+            Ok(())
+        })
+        .await
+}
+
 static LIST_ID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+)<(.+)>$").unwrap());
 
 fn mailinglist_header_listid(list_id_header: &str) -> Result<String> {
@@ -3716,7 +4079,7 @@ fn compute_mailinglist_name(
     sanitize_single_line(&name)
 }
 
-/// Set ListId param on the contact and ListPost param the chat.
+/// Set ListId param on the contact and ListPost and ListOwner params the chat.
 /// Only called for incoming messages since outgoing messages never have a
 /// List-Post header, anyway.
 async fn apply_mailinglist_changes(
@@ -3732,6 +4095,18 @@ async fn apply_mailinglist_changes(
     if chat.typ != Chattype::Mailinglist {
         return Ok(());
     }
+    if let Some(list_owner) = mime_parser.get_list_owner() {
+        match ContactAddress::new(&list_owner) {
+            Ok(list_owner) => {
+                if chat.param.get(Param::ListOwner) != Some(list_owner.as_ref()) {
+                    chat.param.set(Param::ListOwner, list_owner);
+                    chat.update_param(context).await?;
+                }
+            }
+            Err(err) => warn!(context, "Invalid List-Owner: {:#}.", err),
+        }
+    }
+
     let listid = &chat.grpid;
 
     let new_name = compute_mailinglist_name(mailinglist_header, listid, mime_parser);
@@ -4145,6 +4520,67 @@ async fn get_previous_message(
     Ok(None)
 }
 
+/// Returns a hash of the message content
+/// used to detect duplicates arriving with different Message-IDs,
+/// e.g. when a mailing list rewrites the Message-ID.
+///
+/// The hash covers the sender, the recipients, the `Date` and the full text and attachments
+/// of all parts, so that identical emails sent to different recipients
+/// within the same second are not considered duplicates.
+///
+/// Returns `None` if the message has no content to compare.
+fn content_hash(mime_parser: &MimeMessage) -> Option<String> {
+    // The Subject may be modified by mailing lists, so it is not part of the hash.
+    let subject_prefix = mime_parser
+        .get_subject()
+        .map(|subject| format!("{subject} – "))
+        .unwrap_or_default();
+    let mut has_content = false;
+    let mut hasher = Sha256::new();
+    hasher.update(addr_normalize(&mime_parser.from.addr).to_lowercase());
+    hasher.update(mime_parser.timestamp_sent.to_be_bytes());
+    let mut recipients: Vec<String> = mime_parser
+        .recipients
+        .iter()
+        .map(|recipient| addr_normalize(&recipient.addr).to_lowercase())
+        .collect();
+    recipients.sort_unstable();
+    recipients.dedup();
+    for recipient in recipients {
+        hasher.update([0]);
+        hasher.update(recipient);
+    }
+    for part in &mime_parser.parts {
+        let file = part.param.get(Param::File).unwrap_or_default();
+        let text = part
+            .msg
+            .strip_prefix(&subject_prefix)
+            .unwrap_or(&part.msg)
+            .trim();
+        has_content |= !text.is_empty() || !file.is_empty();
+        hasher.update([0]);
+        hasher.update(part.typ.to_u32().unwrap_or_default().to_be_bytes());
+        hasher.update(text);
+        hasher.update([0]);
+        hasher.update(part.msg_raw.as_deref().unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(file);
+    }
+    has_content.then(|| format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the ID of a previously received message with the given content hash,
+/// see [`content_hash`].
+async fn get_msg_id_by_content_hash(context: &Context, hash: &str) -> Result<Option<MsgId>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT msg_id FROM msgs_content_hashes WHERE hash=? ORDER BY msg_id LIMIT 1",
+            (hash,),
+        )
+        .await
+}
+
 /// Returns the last message referenced from References: header found in the database.
 ///
 /// If none found, tries In-Reply-To: as a fallback for classic MUAs that don't set the
@@ -4334,14 +4770,17 @@ async fn lookup_key_contact_by_address(
     Ok(contact_id)
 }
 
-async fn get_contact_fingerprint(context: &Context, contact_id: ContactId) -> Result<Option<String>> {
+async fn get_contact_fingerprint(
+    context: &Context,
+    contact_id: ContactId,
+) -> Result<Option<String>> {
     if contact_id == ContactId::SELF {
         Ok(self_fingerprint_opt(context).await?.map(|s| s.to_string()))
     } else {
         Ok(Contact::get_by_id(context, contact_id)
             .await?
-           .fingerprint()
-           .map(|f| f.hex()))
+            .fingerprint()
+            .map(|f| f.hex()))
     }
 }
 
@@ -588,6 +588,38 @@ async fn test_parse_dsn_relayed() {
     .await;
 }
 
+/// Test that DSN with `Action: delivered` marks the message as received by the server.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_parse_dsn_delivered() -> Result<()> {
+    let t = TestContext::new().await;
+    t.configure_addr("anon_1@posteo.de").await;
+    t.allow_unencrypted().await?;
+    receive_imf(
+        &t,
+        b"From: anon_1@posteo.de\n\
+          To: anon_2@gmx.at\n\
+          Subject: foo\n\
+          Message-ID: <8b7b1a9d0c8cc588c7bcac47f5687634@posteo.de>\n\
+          Chat-Version: 1.0\n\
+          Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+          \n\
+          hello\n",
+        false,
+    )
+    .await?;
+    let msg = t.get_last_msg().await;
+    assert_eq!(msg.state, MessageState::OutDelivered);
+
+    let raw_dsn =
+        String::from_utf8_lossy(include_bytes!("../../test-data/message/dsn_relayed.eml"))
+            .replace("Action: relayed", "Action: delivered");
+    receive_imf(&t, raw_dsn.as_bytes(), false).await?;
+    let msg = Message::load_from_db(&t, msg.id).await?;
+    assert_eq!(msg.state, MessageState::OutServerRcvd);
+    assert!(msg.is_sent());
+    Ok(())
+}
+
 // ndn = Non Delivery Notification
 async fn test_parse_ndn(
     self_addr: &str,
@@ -2125,6 +2157,109 @@ async fn test_duplicate_message() -> Result<()> {
     Ok(())
 }
 
+/// Tests that a message arriving a second time with a rewritten Message-ID,
+/// e.g. via a mailing list, is detected as a duplicate by its content.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_duplicate_message_content_hash() -> Result<()> {
+    let alice = &TestContext::new_alice().await;
+    alice.allow_unencrypted().await?;
+
+    let direct = b"Subject: Hello
+Message-ID: <direct@example.org>
+Date: Sun, 14 Nov 2021 00:10:00 +0000
+To: alice@example.org
+From: bob@example.org
+Content-Type: text/plain
+
+Hello Alice!
+";
+    let rewritten = b"Subject: [list] Hello
+Message-ID: <rewritten@lists.example.org>
+Date: Sun, 14 Nov 2021 00:10:00 +0000
+To: alice@example.org
+From: bob@example.org
+Content-Type: text/plain
+
+Hello Alice!
+";
+
+    let received = receive_imf(alice, direct, false).await?.unwrap();
+    let msg = Message::load_from_db(alice, received.msg_ids[0]).await?;
+    assert_eq!(msg.get_text(), "Hello – Hello Alice!");
+
+    let received = receive_imf(alice, rewritten, false).await?.unwrap();
+    assert!(received.chat_id.is_trash());
+    assert_eq!(
+        message::get_duplicate_of(alice, "rewritten@lists.example.org").await?,
+        Some(msg.id)
+    );
+    assert_eq!(msg.chat_id.get_msg_cnt(alice).await?, 1);
+
+    // A reply to the duplicate is a reply to the original message.
+    let reply = receive_imf(
+        alice,
+        b"Subject: Re: Hello
+Message-ID: <reply@example.org>
+In-Reply-To: <rewritten@lists.example.org>
+Date: Sun, 14 Nov 2021 00:11:00 +0000
+To: alice@example.org
+From: bob@example.org
+Content-Type: text/plain
+
+Hello again!
+",
+        false,
+    )
+    .await?
+    .unwrap();
+    assert_eq!(reply.chat_id, msg.chat_id);
+    assert_eq!(
+        message::get_by_rfc724_mids(alice, &["rewritten@lists.example.org".to_string()])
+            .await?
+            .unwrap()
+            .id,
+        msg.id
+    );
+
+    // Same content sent at a different time is not a duplicate.
+    let received = receive_imf(
+        alice,
+        b"Subject: Hello
+Message-ID: <later@example.org>
+Date: Sun, 14 Nov 2021 00:12:00 +0000
+To: alice@example.org
+From: bob@example.org
+Content-Type: text/plain
+
+Hello Alice!
+",
+        false,
+    )
+    .await?
+    .unwrap();
+    assert_eq!(received.chat_id, msg.chat_id);
+
+    // Same content sent at the same time to other recipients is not a duplicate.
+    let received = receive_imf(
+        alice,
+        b"Subject: Hello
+Message-ID: <other-recipients@example.org>
+Date: Sun, 14 Nov 2021 00:10:00 +0000
+To: alice@example.org, claire@example.org
+From: bob@example.org
+Content-Type: text/plain
+
+Hello Alice!
+",
+        false,
+    )
+    .await?
+    .unwrap();
+    assert!(!received.chat_id.is_trash());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_ignore_footer_status_from_mailinglist() -> Result<()> {
     let t = TestContext::new_alice().await;
@@ -2955,6 +3090,87 @@ async fn check_message(msg: &Message, t: &TestContext, filename: &str, content:
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_attachment_warning() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat = alice.create_chat(bob).await;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(alice, "invoice.pdf.exe", b"MZ", None)?;
+    let sent = alice.send_msg(alice_chat.id, &mut msg).await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(msg.get_attachment_warning(), None);
+
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(
+        msg.get_attachment_warning(),
+        Some(message::AttachmentWarning::DoubleExtension)
+    );
+    bob.evtracker
+        .get_matching(
+            |evt| matches!(evt, EventType::AttachmentWarning { msg_id, .. } if *msg_id == msg.id),
+        )
+        .await;
+
+    let msg = tcm.send_recv(alice, bob, "No attachment").await;
+    assert_eq!(msg.get_attachment_warning(), None);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_language_detection() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat = alice.create_chat(bob).await;
+    let sent = alice
+        .send_text(
+            alice_chat.id,
+            "Hallo Bob, wie geht es dir? Ich habe dich lange nicht gesehen.",
+        )
+        .await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(msg.get_language(), None);
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.get_language(), Some("de"));
+
+    let msg = tcm.send_recv(alice, bob, "Hi!").await;
+    assert_eq!(msg.get_language(), None);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_alt_text() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat = alice.create_chat(bob).await;
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_from_bytes(
+        alice,
+        "logo.png",
+        include_bytes!("../../test-data/image/logo.png"),
+        None,
+    )?;
+    let alt_text = "Delta Chat logo: a blue and white delta – «Δ»";
+    msg.set_alt_text(Some(alt_text.to_string()));
+    let sent = alice.send_msg(alice_chat.id, &mut msg).await;
+    let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    assert_eq!(msg.get_alt_text(), Some(alt_text));
+
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.get_viewtype(), Viewtype::Image);
+    assert_eq!(msg.get_alt_text(), Some(alt_text));
+
+    let msg = tcm.send_recv(alice, bob, "No attachment").await;
+    assert_eq!(msg.get_alt_text(), None);
+    Ok(())
+}
+
 /// Tests that contact request is accepted automatically on outgoing message.
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_accept_outgoing() -> Result<()> {
@@ -3318,6 +3534,36 @@ async fn test_blocked_contact_creates_group() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_block_domain() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+    alice.block_domain(" @Example.NET").await?;
+    assert_eq!(alice.get_blocked_domains().await?, ["example.net"]);
+    assert!(Contact::is_blocked_load(alice, fiona_id).await?);
+
+    let sent = bob
+        .send_text(bob.create_chat(alice).await.id, "Hi Alice")
+        .await;
+    let rcvd = alice.recv_msg(&sent).await;
+    assert_eq!(rcvd.chat_blocked, Blocked::Yes);
+    assert!(Contact::is_blocked_load(alice, rcvd.from_id).await?);
+
+    alice.unblock_domain("example.net").await?;
+    assert!(alice.get_blocked_domains().await?.is_empty());
+    // Contacts stay blocked.
+    assert!(Contact::is_blocked_load(alice, rcvd.from_id).await?);
+
+    assert!(alice.block_domain("").await.is_err());
+    assert!(alice.block_domain("bob@example.net").await.is_err());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_outgoing_undecryptable() -> Result<()> {
     let alice = &TestContext::new().await;
@@ -4718,6 +4964,55 @@ async fn test_no_op_member_added_is_trash() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_coalesce_member_changes() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let charlie = &tcm.charlie().await;
+    let fiona = &tcm.fiona().await;
+    bob.set_config_bool(Config::CoalesceMemberChanges, true)
+        .await?;
+
+    let alice_chat_id = alice.create_group_with_members("foos", &[bob]).await;
+    send_text_msg(alice, alice_chat_id, "populate".to_string()).await?;
+    let bob_chat_id = bob.recv_msg(&alice.pop_sent_msg().await).await.chat_id;
+    bob_chat_id.accept(bob).await?;
+
+    let charlie_id = alice.add_or_lookup_contact_id(charlie).await;
+    let fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+    add_contact_to_chat(alice, alice_chat_id, charlie_id).await?;
+    let first = bob.recv_msg(&alice.pop_sent_msg().await).await;
+    add_contact_to_chat(alice, alice_chat_id, fiona_id).await?;
+    bob.recv_msg_hidden(&alice.pop_sent_msg().await).await;
+    remove_contact_from_chat(alice, alice_chat_id, charlie_id).await?;
+    let last = bob.recv_msg_hidden(&alice.pop_sent_msg().await).await;
+
+    let summary = bob.get_last_msg_in(bob_chat_id).await;
+    assert!(summary.is_info());
+    assert_eq!(summary.get_text(), "3 changes to the member list.");
+    let coalesced = summary.id.get_coalesced_msgs(bob).await?;
+    assert_eq!(coalesced.len(), 3);
+    assert_eq!(coalesced[0], first.id);
+    assert_eq!(coalesced[2], last.id);
+    let first = Message::load_from_db(bob, first.id).await?;
+    assert!(first.hidden);
+    assert_eq!(first.get_info_type(), SystemMessage::MemberAddedToGroup);
+    let chat_msgs = get_chat_msgs(bob, bob_chat_id).await?;
+    for msg_id in coalesced {
+        assert!(!chat_msgs.contains(&ChatItem::Message { msg_id }));
+    }
+
+    // Other messages end the series of member changes.
+    send_text_msg(alice, alice_chat_id, "hi".to_string()).await?;
+    bob.recv_msg(&alice.pop_sent_msg().await).await;
+    add_contact_to_chat(alice, alice_chat_id, charlie_id).await?;
+    let msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+    assert!(!msg.hidden);
+    assert!(msg.id.get_coalesced_msgs(bob).await?.is_empty());
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_forged_from() -> Result<()> {
     let mut tcm = TestContextManager::new();
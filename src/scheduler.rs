@@ -16,16 +16,18 @@
 use crate::context::Context;
 use crate::download::{download_known_post_messages_without_pre_message, download_msgs};
 use crate::ephemeral;
-use crate::events::EventType;
+use crate::events::{ErrorCode, EventType};
 use crate::imap::{Imap, session::Session};
+use crate::lan;
 use crate::location;
 use crate::log::{LogExt, warn};
+use crate::net::bandwidth;
 use crate::smtp::{Smtp, send_smtp_messages};
 use crate::sql;
+use crate::stats;
 use crate::stats::maybe_send_stats;
-use crate::tools::{self, duration_to_str, maybe_add_time_based_warnings, time, time_elapsed};
+use crate::tools::{self, duration_to_str, maybe_add_time_based_warnings, time_elapsed};
 use crate::transport::ConfiguredLoginParam;
-use crate::{constants, stats};
 
 pub(crate) mod connectivity;
 
@@ -76,7 +78,12 @@ async fn do_start(inner: &mut InnerSchedulerState, context: &Context) {
                 *inner = InnerSchedulerState::Started(scheduler);
                 context.emit_event(EventType::ConnectivityChanged);
             }
-            Err(err) => error!(context, "Failed to start IO: {:#}", err),
+            Err(err) => error!(
+                context,
+                code = ErrorCode::from_error(&err),
+                "Failed to start IO: {:#}",
+                err
+            ),
         }
     }
 
@@ -333,6 +340,8 @@ pub(crate) struct Scheduler {
     ephemeral_interrupt_send: Sender<()>,
     location_handle: task::JoinHandle<()>,
     location_interrupt_send: Sender<()>,
+    lan_handle: task::JoinHandle<()>,
+    bandwidth_handle: task::JoinHandle<()>,
 
     recently_seen_loop: RecentlySeenLoop,
 }
@@ -432,23 +441,23 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
 
     maybe_add_time_based_warnings(ctx).await;
 
-    match ctx.get_config_i64(Config::LastHousekeeping).await {
-        Ok(last_housekeeping_time) => {
-            let next_housekeeping_time =
-                last_housekeeping_time.saturating_add(constants::HOUSEKEEPING_PERIOD);
-            if next_housekeeping_time <= time() {
+    // Non-urgent background work is deferred until the quiet hours end.
+    if !ctx.is_quiet_hours() {
+        match sql::housekeeping_due(ctx).await {
+            Ok(true) => {
                 sql::housekeeping(ctx).await.log_err(ctx).ok();
             }
-        }
-        Err(err) => {
-            warn!(
-                ctx,
-                "Transport {transport_id}: Failed to get last housekeeping time: {err:#}"
-            );
-        }
-    };
+            Ok(false) => {}
+            Err(err) => {
+                warn!(
+                    ctx,
+                    "Transport {transport_id}: Failed to check if housekeeping is due: {err:#}"
+                );
+            }
+        };
 
-    maybe_send_stats(ctx).await.log_err(ctx).ok();
+        maybe_send_stats(ctx).await.log_err(ctx).ok();
+    }
 
     session
         .update_metadata(ctx)
@@ -465,6 +474,28 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
     Ok(session)
 }
 
+/// Fetches new messages from the extra `folder`
+/// and moves or deletes messages in it.
+async fn fetch_extra_folder(
+    ctx: &Context,
+    connection: &mut Imap,
+    session: &mut Session,
+    folder: &str,
+) -> Result<()> {
+    let msgs_fetched = connection
+        .fetch_new_messages(ctx, session, folder)
+        .await
+        .context("fetch_new_messages")?;
+    if msgs_fetched && ctx.get_config_delete_device_after().await?.is_some() {
+        ctx.scheduler.interrupt_ephemeral_task().await;
+    }
+    session
+        .move_delete_messages(ctx, folder)
+        .await
+        .context("move_delete_messages")?;
+    Ok(())
+}
+
 /// Implement a single iteration of IMAP loop.
 ///
 /// This function performs all IMAP operations on a single folder, selecting it if necessary and
@@ -486,6 +517,21 @@ async fn fetch_idle(ctx: &Context, connection: &mut Imap, mut session: Session)
         .await
         .context("fetch_move_delete")?;
 
+    // Fetch extra folders, messages there are not announced by IDLE.
+    // Transport-wide work is already done for the watched folder above,
+    // and a missing or broken extra folder should not stop the IMAP loop.
+    for folder in ctx.get_watch_extra_folders().await? {
+        if folder == watch_folder {
+            continue;
+        }
+        if let Err(err) = fetch_extra_folder(ctx, connection, &mut session, &folder).await {
+            warn!(
+                ctx,
+                "Transport {transport_id}: Failed to fetch extra folder {folder:?}: {err:#}."
+            );
+        }
+    }
+
     download_known_post_messages_without_pre_message(ctx, &mut session).await?;
     download_msgs(ctx, &mut session)
         .await
@@ -677,6 +723,20 @@ pub async fn start(ctx: &Context) -> Result<Self> {
             })
         };
 
+        let lan_handle = {
+            let ctx = ctx.clone();
+            task::spawn(async move {
+                lan::lan_loop(&ctx).await;
+            })
+        };
+
+        let bandwidth_handle = {
+            let ctx = ctx.clone();
+            task::spawn(async move {
+                bandwidth::bandwidth_loop(&ctx).await;
+            })
+        };
+
         let recently_seen_loop = RecentlySeenLoop::new(ctx.clone());
 
         let res = Self {
@@ -687,6 +747,8 @@ pub async fn start(ctx: &Context) -> Result<Self> {
             ephemeral_interrupt_send,
             location_handle,
             location_interrupt_send,
+            lan_handle,
+            bandwidth_handle,
             recently_seen_loop,
         };
 
@@ -781,6 +843,10 @@ pub(crate) async fn stop(self, context: &Context) {
         self.ephemeral_handle.await.ok();
         self.location_handle.abort();
         self.location_handle.await.ok();
+        self.lan_handle.abort();
+        self.lan_handle.await.ok();
+        self.bandwidth_handle.abort();
+        self.bandwidth_handle.await.ok();
         self.recently_seen_loop.abort().await;
     }
 }
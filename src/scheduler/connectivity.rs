@@ -40,6 +40,42 @@ pub enum Connectivity {
     Connected = 4000,
 }
 
+/// Type of the connection in [`ConnectionConnectivity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionKind {
+    /// IMAP connection watching a folder.
+    Imap {
+        /// Address of the transport.
+        addr: String,
+
+        /// Watched folder, e.g. `INBOX`.
+        folder: String,
+    },
+
+    /// SMTP connection used to send messages.
+    Smtp,
+
+    /// Iroh endpoint used for peer channels.
+    Iroh,
+}
+
+/// Connectivity of a single connection,
+/// see [`Context::get_connectivity_per_connection()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionConnectivity {
+    /// Type of the connection.
+    pub kind: ConnectionKind,
+
+    /// Current connectivity of the connection.
+    pub connectivity: Connectivity,
+
+    /// Last error that happened on the connection, if any.
+    ///
+    /// The error is kept after the connection recovers
+    /// so it can be shown to the user.
+    pub last_error: Option<String>,
+}
+
 // The order of the connectivities is important: worse connectivities (i.e. those at
 // the top) take priority. This means that e.g. if any folder has an error - usually
 // because there is no internet connection - the connectivity for the whole
@@ -147,17 +183,23 @@ fn all_work_done(&self) -> bool {
 }
 
 #[derive(Clone, Default)]
-pub(crate) struct ConnectivityStore(Arc<parking_lot::Mutex<DetailedConnectivity>>);
+pub(crate) struct ConnectivityStore {
+    state: Arc<parking_lot::Mutex<DetailedConnectivity>>,
+
+    /// Last error stored with [`ConnectivityStore::set_err`].
+    last_error: Arc<parking_lot::Mutex<Option<String>>>,
+}
 
 impl ConnectivityStore {
     fn set(&self, context: &Context, v: DetailedConnectivity) {
         {
-            *self.0.lock() = v;
+            *self.state.lock() = v;
         }
         context.emit_event(EventType::ConnectivityChanged);
     }
 
     pub(crate) fn set_err(&self, context: &Context, e: String) {
+        *self.last_error.lock() = Some(e.clone());
         self.set(context, DetailedConnectivity::Error(e));
     }
     pub(crate) fn set_connecting(&self, context: &Context) {
@@ -174,13 +216,16 @@ pub(crate) fn set_idle(&self, context: &Context) {
     }
 
     fn get_detailed(&self) -> DetailedConnectivity {
-        self.0.lock().deref().clone()
+        self.state.lock().deref().clone()
     }
     fn get_basic(&self) -> Connectivity {
-        self.0.lock().to_basic()
+        self.state.lock().to_basic()
     }
     fn get_all_work_done(&self) -> bool {
-        self.0.lock().all_work_done()
+        self.state.lock().all_work_done()
+    }
+    fn get_last_error(&self) -> Option<String> {
+        self.last_error.lock().clone()
     }
 }
 
@@ -189,7 +234,7 @@ fn get_all_work_done(&self) -> bool {
 /// returns false immediately after `dc_maybe_network()`.
 pub(crate) fn idle_interrupted(inboxes: Vec<ConnectivityStore>) {
     for inbox in inboxes {
-        let mut connectivity_lock = inbox.0.lock();
+        let mut connectivity_lock = inbox.state.lock();
         if *connectivity_lock == DetailedConnectivity::Idle {
             *connectivity_lock = DetailedConnectivity::InterruptingIdle;
         }
@@ -204,7 +249,7 @@ pub(crate) fn idle_interrupted(inboxes: Vec<ConnectivityStore>) {
 /// after `maybe_network_lost()` was called.
 pub(crate) fn maybe_network_lost(context: &Context, stores: Vec<ConnectivityStore>) {
     for store in &stores {
-        let mut connectivity_lock = store.0.lock();
+        let mut connectivity_lock = store.state.lock();
         if !matches!(
             *connectivity_lock,
             DetailedConnectivity::Uninitialized | DetailedConnectivity::Error(_)
@@ -217,7 +262,7 @@ pub(crate) fn maybe_network_lost(context: &Context, stores: Vec<ConnectivityStor
 
 impl fmt::Debug for ConnectivityStore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(guard) = self.0.try_lock() {
+        if let Some(guard) = self.state.try_lock() {
             write!(f, "ConnectivityStore {:?}", *guard)
         } else {
             write!(f, "ConnectivityStore [LOCKED]")
@@ -253,6 +298,53 @@ pub fn get_connectivity(&self) -> Connectivity {
             .unwrap_or(Connectivity::NotConnected)
     }
 
+    /// Get the connectivity of each connection separately,
+    /// together with the last error that happened on it.
+    ///
+    /// Contains one IMAP entry per watched folder and an SMTP entry
+    /// while I/O is started, and always an entry for the Iroh endpoint.
+    /// Meant for connectivity views that show exactly which connection is failing.
+    ///
+    /// If the connectivity changes, a DC_EVENT_CONNECTIVITY_CHANGED will be emitted.
+    pub async fn get_connectivity_per_connection(&self) -> Vec<ConnectionConnectivity> {
+        let mut ret = Vec::new();
+
+        let lock = self.scheduler.inner.read().await;
+        if let InnerSchedulerState::Started(ref sched) = *lock {
+            for b in sched.boxes() {
+                let store = &b.conn_state.state.connectivity;
+                ret.push(ConnectionConnectivity {
+                    kind: ConnectionKind::Imap {
+                        addr: b.addr.clone(),
+                        folder: b.folder.clone(),
+                    },
+                    connectivity: store.get_basic(),
+                    last_error: store.get_last_error(),
+                });
+            }
+            let store = &sched.smtp.state.connectivity;
+            ret.push(ConnectionConnectivity {
+                kind: ConnectionKind::Smtp,
+                connectivity: store.get_basic(),
+                last_error: store.get_last_error(),
+            });
+        }
+        drop(lock);
+
+        let connectivity = if self.iroh.read().await.is_some() {
+            self.iroh_connectivity.get_basic()
+        } else {
+            Connectivity::NotConnected
+        };
+        ret.push(ConnectionConnectivity {
+            kind: ConnectionKind::Iroh,
+            connectivity,
+            last_error: self.iroh_connectivity.get_last_error(),
+        });
+
+        ret
+    }
+
     pub(crate) fn update_connectivities(&self, sched: &InnerSchedulerState) {
         let stores: Vec<_> = match sched {
             InnerSchedulerState::Started(sched) => sched
@@ -565,3 +657,37 @@ pub async fn wait_for_all_work_done(&self) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_connectivity_per_connection() -> Result<()> {
+        let t = TestContext::new_alice().await;
+
+        // I/O is not started, so there are no IMAP and SMTP connections.
+        let connectivities = t.get_connectivity_per_connection().await;
+        assert_eq!(
+            connectivities,
+            vec![ConnectionConnectivity {
+                kind: ConnectionKind::Iroh,
+                connectivity: Connectivity::NotConnected,
+                last_error: None,
+            }]
+        );
+
+        t.iroh_connectivity
+            .set_err(&t, "Failed to bind socket".to_string());
+        t.iroh_connectivity.set_connecting(&t);
+        let connectivities = t.get_connectivity_per_connection().await;
+        assert_eq!(connectivities.len(), 1);
+        assert_eq!(
+            connectivities[0].last_error.as_deref(),
+            Some("Failed to bind socket")
+        );
+
+        Ok(())
+    }
+}
@@ -5,8 +5,8 @@
 use percent_encoding::{AsciiSet, utf8_percent_encode};
 
 use crate::chat::{
-    self, Chat, ChatId, ChatIdBlocked, add_info_msg, get_chat_id_by_grpid, load_broadcast_secret,
-    admin_group_base_id, admin_group_fingerprint,
+    self, Chat, ChatId, ChatIdBlocked, add_info_msg, admin_group_base_id, admin_group_fingerprint,
+    get_chat_id_by_grpid, load_broadcast_secret,
 };
 use crate::config::Config;
 use crate::constants::{
@@ -16,6 +16,7 @@
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::events::EventType;
+use crate::group_history;
 use crate::headerdef::HeaderDef;
 use crate::key;
 use crate::key::{DcKey, Fingerprint, load_self_public_key, self_fingerprint};
@@ -88,6 +89,26 @@ fn shorten_name(name: &str, length: usize) -> String {
 /// With `chat` set to `None` this generates a setup-contact QR code, with `chat` set to a
 /// [`ChatId`] generates a join-group/join-broadcast-channel QR code for the given chat.
 pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Result<String> {
+    get_securejoin_qr_ex(context, chat, false).await
+}
+
+/// Generates a setup-contact QR code that can only be used once.
+///
+/// After the first successful setup-contact, the auth code of the QR code is invalidated
+/// and further attempts to use it are rejected with an error.
+/// This prevents reuse of invites leaked e.g. in screenshots.
+///
+/// The auth code is not synchronized to other devices,
+/// so the QR code can only be used while this device is running.
+pub async fn get_securejoin_qr_single_use(context: &Context) -> Result<String> {
+    get_securejoin_qr_ex(context, None, true).await
+}
+
+async fn get_securejoin_qr_ex(
+    context: &Context,
+    chat: Option<ChatId>,
+    single_use: bool,
+) -> Result<String> {
     /*=======================================================
     ====             Alice - the inviter side            ====
     ====   Step 1 in "Setup verified contact" protocol   ====
@@ -140,7 +161,12 @@ pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Resul
     // and can only be used to join groups
     // without verification afterwards.
     let auth = create_id();
-    token::save(context, Namespace::Auth, grpid, &auth, time()).await?;
+    let auth_namespace = if single_use {
+        Namespace::SingleUseAuth
+    } else {
+        Namespace::Auth
+    };
+    token::save(context, auth_namespace, grpid, &auth, time()).await?;
 
     let fingerprint = self_fingerprint(context).await?;
 
@@ -152,6 +178,8 @@ pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Resul
         .await?
         .unwrap_or_default();
 
+    let invite_link_prefix = get_invite_link_prefix(context).await?;
+
     let qr = if let Some(chat) = chat {
         context
             .sync_qr_code_tokens(Some(chat.grpid.as_str()))
@@ -173,17 +201,17 @@ pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Resul
         if chat.typ == Chattype::OutBroadcast {
             // For historic reansons, broadcasts currently use j instead of i for the invitenumber.
             format!(
-                "https://i.delta.chat/#{fingerprint}&v=3&x={grpid}&j={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&b={chat_name_urlencoded}",
+                "{invite_link_prefix}{fingerprint}&v=3&x={grpid}&j={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&b={chat_name_urlencoded}",
             )
         } else if admin_group_fingerprint(grpid).is_some() {
             // Admin group: put only the base (random) grpid in x=, use z= for the group name
             let base_grpid = admin_group_base_id(grpid);
             format!(
-                "https://i.delta.chat/#{fingerprint}&v=3&x={base_grpid}&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&z={chat_name_urlencoded}",
+                "{invite_link_prefix}{fingerprint}&v=3&x={base_grpid}&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&z={chat_name_urlencoded}",
             )
         } else {
             format!(
-                "https://i.delta.chat/#{fingerprint}&v=3&x={grpid}&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&g={chat_name_urlencoded}",
+                "{invite_link_prefix}{fingerprint}&v=3&x={grpid}&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}&g={chat_name_urlencoded}",
             )
         }
     } else {
@@ -192,11 +220,13 @@ pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Resul
             .to_string()
             .replace("%20", "+");
 
-        context.sync_qr_code_tokens(None).await?;
-        context.scheduler.interrupt_smtp().await;
+        if !single_use {
+            context.sync_qr_code_tokens(None).await?;
+            context.scheduler.interrupt_smtp().await;
+        }
 
         format!(
-            "https://i.delta.chat/#{fingerprint}&v=3&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}",
+            "{invite_link_prefix}{fingerprint}&v=3&i={invitenumber}&s={auth}&a={self_addr_urlencoded}&n={self_name_urlencoded}",
         )
     };
 
@@ -204,6 +234,55 @@ pub async fn get_securejoin_qr(context: &Context, chat: Option<ChatId>) -> Resul
     Ok(qr)
 }
 
+/// Returns the prefix of generated invite links,
+/// `https://<domain>/#` with the domain from [`Config::InviteLinkDomain`].
+async fn get_invite_link_prefix(context: &Context) -> Result<String> {
+    let domain = context
+        .get_config(Config::InviteLinkDomain)
+        .await?
+        .unwrap_or_default();
+    let domain = if domain.is_empty() {
+        "i.delta.chat"
+    } else {
+        &domain
+    };
+    Ok(format!("https://{domain}/#"))
+}
+
+/// Result of checking the auth code of a received handshake message.
+#[derive(Debug, PartialEq, Eq)]
+enum AuthCheck {
+    /// The auth code is valid.
+    Valid,
+
+    /// The auth code belongs to a single-use QR code which was already used.
+    Used,
+
+    /// The auth code is unknown.
+    Invalid,
+}
+
+/// Checks the auth code of a received handshake message.
+///
+/// Reports the reason if the auth code is not valid.
+async fn check_auth_token(context: &Context, auth: &str) -> Result<AuthCheck> {
+    if token::exists(context, Namespace::Auth, auth).await?
+        || token::exists(context, Namespace::SingleUseAuth, auth).await?
+    {
+        return Ok(AuthCheck::Valid);
+    }
+    if token::exists(context, Namespace::UsedSingleUseAuth, auth).await? {
+        warn!(
+            context,
+            "Secure-join denied: the single-use invite code was already used."
+        );
+        Ok(AuthCheck::Used)
+    } else {
+        warn!(context, "Secure-join denied (bad auth).");
+        Ok(AuthCheck::Invalid)
+    }
+}
+
 async fn get_self_fingerprint(context: &Context) -> Result<Fingerprint> {
     let key = load_self_public_key(context)
         .await
@@ -297,6 +376,38 @@ async fn send_alice_handshake_msg(
     Ok(())
 }
 
+/// Send symmetrically encrypted handshake message from Alice's device
+/// in reply to `vc-request-pubkey`.
+///
+/// The message is encrypted with the auth code
+/// because Alice does not know Bob's key yet.
+async fn send_alice_symm_encrypted_handshake_msg(
+    context: &Context,
+    mime_message: &MimeMessage,
+    step: &str,
+    auth: &str,
+) -> Result<()> {
+    let rfc724_mid = create_outgoing_rfc724_mid();
+    let addr = ContactAddress::new(&mime_message.from.addr)?;
+    let attach_self_pubkey = true;
+    let self_fp = self_fingerprint(context).await?;
+    let shared_secret = format!("securejoin/{self_fp}/{auth}");
+    let rendered_message = mimefactory::render_symm_encrypted_securejoin_message(
+        context,
+        step,
+        &rfc724_mid,
+        attach_self_pubkey,
+        auth,
+        &shared_secret,
+    )
+    .await?;
+
+    let msg_id = message::insert_tombstone(context, &rfc724_mid).await?;
+    insert_into_smtp(context, &rfc724_mid, &addr, rendered_message, msg_id).await?;
+    context.scheduler.interrupt_smtp().await;
+    Ok(())
+}
+
 /// Get an unblocked chat that can be used for info messages.
 async fn info_chat_id(context: &Context, contact_id: ContactId) -> Result<ChatId> {
     let chat_id_blocked = ChatIdBlocked::get_for_contact(context, contact_id, Blocked::Not).await?;
@@ -364,6 +475,10 @@ pub(crate) enum SecureJoinStep {
     /// vc-request-with-auth or vg-request-with-auth
     RequestWithAuth,
 
+    /// vc-auth-used, sent instead of vc-pubkey or vc-contact-confirm
+    /// if the auth code of a single-use QR code was already used
+    AuthUsed,
+
     /// vc-contact-confirm
     ContactConfirm,
 
@@ -397,6 +512,7 @@ pub(crate) fn get_secure_join_step(mime_message: &MimeMessage) -> Option<SecureJ
                 Some(SecureJoinStep::RequestWithAuth)
             }
             "vc-contact-confirm" => Some(SecureJoinStep::ContactConfirm),
+            "vc-auth-used" => Some(SecureJoinStep::AuthUsed),
             "vg-member-added" => Some(SecureJoinStep::MemberAdded),
             "vg-member-added-received" | "vc-contact-confirm-received" => {
                 Some(SecureJoinStep::Deprecated)
@@ -452,7 +568,10 @@ pub(crate) async fn handle_securejoin_handshake(
     // https://www.rfc-editor.org/rfc/rfc9580.html#name-surreptitious-forwarding
     if !matches!(
         step,
-        SecureJoinStep::Request { .. } | SecureJoinStep::RequestPubkey | SecureJoinStep::Pubkey
+        SecureJoinStep::Request { .. }
+            | SecureJoinStep::RequestPubkey
+            | SecureJoinStep::Pubkey
+            | SecureJoinStep::AuthUsed
     ) {
         let mut self_found = false;
         let self_fingerprint = load_self_public_key(context).await?.dc_fingerprint();
@@ -536,9 +655,20 @@ pub(crate) async fn handle_securejoin_handshake(
                 );
                 return Ok(HandshakeMessage::Ignore);
             };
-            if !token::exists(context, token::Namespace::Auth, auth).await? {
-                warn!(context, "Secure-join denied (bad auth).");
-                return Ok(HandshakeMessage::Ignore);
+            match check_auth_token(context, auth).await? {
+                AuthCheck::Valid => {}
+                AuthCheck::Used => {
+                    // Tell Bob that the QR code cannot be used anymore.
+                    send_alice_symm_encrypted_handshake_msg(
+                        context,
+                        mime_message,
+                        "vc-auth-used",
+                        auth,
+                    )
+                    .await?;
+                    return Ok(HandshakeMessage::Done);
+                }
+                AuthCheck::Invalid => return Ok(HandshakeMessage::Ignore),
             }
             if Contact::lookup_id_by_addr_ex(
                 context,
@@ -553,24 +683,8 @@ pub(crate) async fn handle_securejoin_handshake(
                 return Ok(HandshakeMessage::Ignore);
             }
 
-            let rfc724_mid = create_outgoing_rfc724_mid();
-            let addr = ContactAddress::new(&mime_message.from.addr)?;
-            let attach_self_pubkey = true;
-            let self_fp = self_fingerprint(context).await?;
-            let shared_secret = format!("securejoin/{self_fp}/{auth}");
-            let rendered_message = mimefactory::render_symm_encrypted_securejoin_message(
-                context,
-                "vc-pubkey",
-                &rfc724_mid,
-                attach_self_pubkey,
-                auth,
-                &shared_secret,
-            )
-            .await?;
-
-            let msg_id = message::insert_tombstone(context, &rfc724_mid).await?;
-            insert_into_smtp(context, &rfc724_mid, &addr, rendered_message, msg_id).await?;
-            context.scheduler.interrupt_smtp().await;
+            send_alice_symm_encrypted_handshake_msg(context, mime_message, "vc-pubkey", auth)
+                .await?;
 
             Ok(HandshakeMessage::Done)
         }
@@ -612,15 +726,28 @@ pub(crate) async fn handle_securejoin_handshake(
                 );
                 return Ok(HandshakeMessage::Ignore);
             };
-            let Some((grpid, timestamp)) = context
+            match check_auth_token(context, auth).await? {
+                AuthCheck::Valid => {}
+                AuthCheck::Used => {
+                    // Tell Bob that the QR code cannot be used anymore.
+                    send_alice_handshake_msg(context, contact_id, "vc-auth-used")
+                        .await
+                        .context("failed sending vc-auth-used message")?;
+                    return Ok(HandshakeMessage::Ignore);
+                }
+                AuthCheck::Invalid => return Ok(HandshakeMessage::Ignore),
+            }
+            let Some((grpid, timestamp, namespace)) = context
                 .sql
                 .query_row_optional(
-                    "SELECT foreign_key, timestamp FROM tokens WHERE namespc=? AND token=?",
-                    (Namespace::Auth, auth),
+                    "SELECT foreign_key, timestamp, namespc FROM tokens
+                     WHERE namespc IN (?, ?) AND token=?",
+                    (Namespace::Auth, Namespace::SingleUseAuth, auth),
                     |row| {
                         let foreign_key: String = row.get(0)?;
                         let timestamp: i64 = row.get(1)?;
-                        Ok((foreign_key, timestamp))
+                        let namespace: Namespace = row.get(2)?;
+                        Ok((foreign_key, timestamp, namespace))
                     },
                 )
                 .await?
@@ -654,6 +781,16 @@ pub(crate) async fn handle_securejoin_handshake(
                 return Ok(HandshakeMessage::Ignore);
             }
             info!(context, "Fingerprint verified via Auth code.",);
+            if namespace == Namespace::SingleUseAuth {
+                context
+                    .sql
+                    .execute(
+                        "UPDATE tokens SET namespc=? WHERE namespc=? AND token=?",
+                        (Namespace::UsedSingleUseAuth, Namespace::SingleUseAuth, auth),
+                    )
+                    .await?;
+                info!(context, "Single-use auth code is used now.");
+            }
 
             // Mark the contact as verified if auth code is less than VERIFICATION_TIMEOUT_SECONDS seconds old.
             if time() < timestamp + VERIFICATION_TIMEOUT_SECONDS {
@@ -699,6 +836,11 @@ pub(crate) async fn handle_securejoin_handshake(
                     context.emit_event(EventType::ContactsChanged(Some(contact_id)));
                 }
 
+                group_history::share_history(context, &chat, contact_id)
+                    .await
+                    .log_err(context)
+                    .ok();
+
                 inviter_progress(context, contact_id, joining_chat_id, chat.typ)?;
                 // IMAP-delete the message to avoid handling it by another device and adding the
                 // member twice. Another device will know the member's key from Autocrypt-Gossip.
@@ -725,6 +867,11 @@ pub(crate) async fn handle_securejoin_handshake(
             });
             Ok(HandshakeMessage::Ignore)
         }
+        /*=======================================================
+        ====             Bob - the joiner's side             ====
+        ====  Alice rejected the used single-use auth code   ====
+        =======================================================*/
+        SecureJoinStep::AuthUsed => bob::handle_auth_used(context, mime_message, contact_id).await,
         SecureJoinStep::MemberAdded => {
             let Some(member_added) = mime_message.get_header(HeaderDef::ChatGroupMemberAdded)
             else {
@@ -810,6 +957,7 @@ pub(crate) async fn observe_securejoin_on_other_device(
         | SecureJoinStep::AuthRequired
         | SecureJoinStep::RequestPubkey
         | SecureJoinStep::Pubkey
+        | SecureJoinStep::AuthUsed
         | SecureJoinStep::Deprecated
         | SecureJoinStep::Unknown { .. } => {
             return Ok(HandshakeMessage::Ignore);
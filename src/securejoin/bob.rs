@@ -7,9 +7,10 @@
 use super::qrinvite::QrInvite;
 use crate::chat::{self, ChatId, is_contact_in_chat};
 use crate::constants::{Blocked, Chattype};
-use crate::contact::Origin;
+use crate::contact::{Contact, Origin};
 use crate::context::Context;
-use crate::events::EventType;
+use crate::events::{ErrorCode, EventType};
+use crate::headerdef::HeaderDef;
 use crate::key::{DcKey as _, self_fingerprint};
 use crate::log::LogExt;
 use crate::message::{self, Message, MsgId, Viewtype};
@@ -316,6 +317,67 @@ pub(super) async fn handle_auth_required_or_pubkey(
     }
 }
 
+/// Handles `vc-auth-used` handshake message
+/// which Alice sends if the single-use QR code scanned by Bob was already used.
+///
+/// # Bob - the joiner's side
+pub(super) async fn handle_auth_used(
+    context: &Context,
+    message: &MimeMessage,
+    contact_id: ContactId,
+) -> Result<HandshakeMessage> {
+    // Alice replies to `vc-request-pubkey` with a message encrypted with the auth code.
+    let bob_states = context
+        .sql
+        .query_map_vec("SELECT id, invite FROM bobstate", (), |row| {
+            let row_id: i64 = row.get(0)?;
+            let invite: QrInvite = row.get(1)?;
+            Ok((row_id, invite))
+        })
+        .await?;
+    let mut inviters = Vec::new();
+    for (bobstate_row_id, invite) in bob_states {
+        if message.get_header(HeaderDef::SecureJoinAuth) != Some(invite.authcode())
+            || !encrypted_and_signed(context, message, invite.fingerprint())
+        {
+            continue;
+        }
+        context
+            .sql
+            .execute("DELETE FROM bobstate WHERE id=?", (bobstate_row_id,))
+            .await?;
+        inviters.push(invite.contact_id());
+    }
+
+    // Alice replies to `vc-request-with-auth` with a regular encrypted message
+    // if Bob already had her key and sent the auth code right away.
+    if inviters.is_empty() {
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        if contact
+            .fingerprint()
+            .is_some_and(|fp| encrypted_and_signed(context, message, &fp))
+        {
+            inviters.push(contact_id);
+        }
+    }
+
+    if inviters.is_empty() {
+        return Ok(HandshakeMessage::Ignore);
+    }
+    for contact_id in inviters {
+        error!(
+            context,
+            code = ErrorCode::SecurejoinCodeUsed,
+            "Secure-join failed: the single-use invite code was already used."
+        );
+        context.emit_event(EventType::SecurejoinJoinerProgress {
+            contact_id,
+            progress: JoinerProgress::Failed.into_u16(),
+        });
+    }
+    Ok(HandshakeMessage::Done)
+}
+
 /// Sends the requested handshake message to Alice.
 pub(crate) async fn send_handshake_message(
     context: &Context,
@@ -495,6 +557,10 @@ async fn joining_chat_id(
 /// This has an `From<JoinerProgress> for usize` impl yielding numbers between 0 and a 1000
 /// which can be shown as a progress bar.
 pub(crate) enum JoinerProgress {
+    /// Alice rejected the handshake.
+    ///
+    /// The reason is returned by [`Context::get_last_error_code`].
+    Failed,
     /// vg-vc-request-with-auth sent.
     ///
     /// Typically shows as "alice@addr verified, introducing myself."
@@ -506,6 +572,7 @@ pub(crate) enum JoinerProgress {
 impl JoinerProgress {
     pub(crate) fn into_u16(self) -> u16 {
         match self {
+            JoinerProgress::Failed => 0,
             JoinerProgress::RequestWithAuthSent => 400,
             JoinerProgress::Succeeded => 1000,
         }
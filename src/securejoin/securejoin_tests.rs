@@ -8,6 +8,7 @@
 use crate::chatlist::Chatlist;
 use crate::constants::Chattype;
 use crate::constants::DC_CHAT_ID_TRASH;
+use crate::events::ErrorCode;
 use crate::key::self_fingerprint;
 use crate::mimeparser::{GossipedKey, SystemMessage};
 use crate::qr::Qr;
@@ -410,6 +411,58 @@ async fn test_setup_contact_bob_knows_alice() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_setup_contact_single_use() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let charlie = &tcm.charlie().await;
+
+    let qr = get_securejoin_qr_single_use(alice).await?;
+    assert!(matches!(
+        check_qr(alice, &qr).await?,
+        Qr::WithdrawVerifyContact { .. }
+    ));
+    tcm.exec_securejoin_qr(bob, alice, &qr).await;
+    let alice_contact = bob.add_or_lookup_contact(alice).await;
+    assert!(alice_contact.is_verified(bob).await?);
+
+    // The QR code cannot be used again, the joiner is told about it.
+    charlie.evtracker.clear_events();
+    tcm.exec_securejoin_qr(charlie, alice, &qr).await;
+    let charlie_contact = alice.add_or_lookup_contact(charlie).await;
+    assert!(!charlie_contact.is_verified(alice).await?);
+    let alice_contact = charlie.add_or_lookup_contact(alice).await;
+    assert!(!alice_contact.is_verified(charlie).await?);
+    charlie
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::SecurejoinJoinerProgress { progress: 0, .. }))
+        .await;
+    assert_eq!(charlie.get_last_error_code(), ErrorCode::SecurejoinCodeUsed);
+
+    // The QR code can't be revived either.
+    assert!(check_qr(alice, &qr).await.is_err());
+
+    // Regular QR codes still work.
+    let regular_qr = get_securejoin_qr(alice, None).await?;
+    tcm.exec_securejoin_qr(charlie, alice, &regular_qr).await;
+    let alice_contact = charlie.add_or_lookup_contact(alice).await;
+    assert!(alice_contact.is_verified(charlie).await?);
+
+    // The joiner is also told if it already has the inviter's key
+    // and sends the used auth code right away.
+    charlie.set_last_error("");
+    charlie.evtracker.clear_events();
+    tcm.exec_securejoin_qr(charlie, alice, &qr).await;
+    charlie
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::SecurejoinJoinerProgress { progress: 0, .. }))
+        .await;
+    assert_eq!(charlie.get_last_error_code(), ErrorCode::SecurejoinCodeUsed);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_setup_contact_concurrent_calls() -> Result<()> {
     let mut tcm = TestContextManager::new();
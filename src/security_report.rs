@@ -0,0 +1,137 @@
+//! Module to summarize the security posture of a profile.
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use pgp::composed::SignedPublicKey;
+use pgp::types::KeyDetails as _;
+
+use crate::constants::DC_CHAT_ID_LAST_SPECIAL;
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::key::{DcKey as _, load_self_public_key_opt};
+use crate::param::{Param, Params};
+use crate::tools::time;
+use crate::transport::{ConfiguredLoginParam, ConnectionSecurity};
+
+/// Age in seconds after which a key is considered outdated.
+pub const OUTDATED_KEY_AGE: i64 = 2 * 365 * 24 * 60 * 60;
+
+/// Security Report
+/// Points out what the user may want to check or change.
+#[derive(Debug)]
+pub struct SecurityReport {
+    /// Age of the own key in days, `None` if no key was generated yet.
+    pub self_key_age_days: Option<i64>,
+    /// Number of contacts whose key was created more than [`OUTDATED_KEY_AGE`] ago.
+    pub peers_with_outdated_keys: usize,
+    /// Server folders containing unencrypted chat messages and the number of such messages.
+    pub folders_with_plaintext: BTreeMap<String, usize>,
+    /// Addresses of transports accepting invalid TLS certificates
+    /// or using unencrypted connections.
+    pub transports_with_weak_tls: Vec<String>,
+}
+
+impl std::fmt::Display for SecurityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Security Report:")?;
+        match self.self_key_age_days {
+            Some(days) => writeln!(f, "[Own Key Age]: {days} days")?,
+            None => writeln!(f, "[Own Key Age]: no key")?,
+        }
+        writeln!(
+            f,
+            "[Contacts With Outdated Keys]: {}",
+            self.peers_with_outdated_keys
+        )?;
+        writeln!(f, "[Folders With Unencrypted Messages]:")?;
+        for (folder, count) in &self.folders_with_plaintext {
+            writeln!(f, "   {folder}: {count} messages")?;
+        }
+        writeln!(f, "[Transports With Weak TLS]:")?;
+        for addr in &self.transports_with_weak_tls {
+            writeln!(f, "   {addr}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Context {
+    /// Returns a summary of the security posture of the profile.
+    pub async fn get_security_report(&self) -> Result<SecurityReport> {
+        let now = time();
+
+        let self_key_age_days = load_self_public_key_opt(self).await?.map(|key| {
+            let created = i64::from(key.created_at().as_secs());
+            now.saturating_sub(created) / (24 * 60 * 60)
+        });
+
+        let peer_keys = self
+            .sql
+            .query_map_vec(
+                "SELECT pk.public_key FROM contacts c
+                 JOIN public_keys pk ON c.fingerprint=pk.fingerprint
+                 WHERE c.id>?",
+                (ContactId::LAST_SPECIAL,),
+                |row| {
+                    let key: Vec<u8> = row.get(0)?;
+                    Ok(key)
+                },
+            )
+            .await?;
+        let peers_with_outdated_keys = peer_keys
+            .iter()
+            .filter_map(|key| SignedPublicKey::from_slice(key).ok())
+            .filter(|key| {
+                let created = i64::from(key.created_at().as_secs());
+                now.saturating_sub(created) > OUTDATED_KEY_AGE
+            })
+            .count();
+
+        let server_msgs = self
+            .sql
+            .query_map_vec(
+                "SELECT i.folder, m.param FROM imap i
+                 JOIN msgs m ON m.rfc724_mid=i.rfc724_mid
+                 WHERE m.chat_id>? AND i.target!=''",
+                (DC_CHAT_ID_LAST_SPECIAL,),
+                |row| {
+                    let folder: String = row.get(0)?;
+                    let param: String = row.get(1)?;
+                    Ok((folder, param))
+                },
+            )
+            .await?;
+        let mut folders_with_plaintext = BTreeMap::new();
+        for (folder, param) in server_msgs {
+            let param: Params = param.parse().unwrap_or_default();
+            if param.get_int(Param::GuaranteeE2ee).unwrap_or_default() == 0 {
+                let count = folders_with_plaintext.entry(folder).or_insert(0usize);
+                *count = count.saturating_add(1);
+            }
+        }
+
+        let transports_with_weak_tls = ConfiguredLoginParam::load_all(self)
+            .await?
+            .into_iter()
+            .filter(|(_, param)| {
+                !param.strict_tls(false)
+                    || param
+                        .imap
+                        .iter()
+                        .chain(&param.smtp)
+                        .any(|server| server.connection.security == ConnectionSecurity::Plain)
+            })
+            .map(|(_, param)| param.addr)
+            .collect();
+
+        Ok(SecurityReport {
+            self_key_age_days,
+            peers_with_outdated_keys,
+            folders_with_plaintext,
+            transports_with_weak_tls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod security_report_tests;
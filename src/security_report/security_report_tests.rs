@@ -0,0 +1,56 @@
+use super::*;
+use crate::config::Config;
+use crate::key::load_self_public_key;
+use crate::receive_imf::receive_imf;
+use crate::test_utils::TestContextManager;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_security_report() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    // Test keys are created long ago.
+    let created = i64::from(load_self_public_key(alice).await?.created_at().as_secs());
+    let report = alice.get_security_report().await?;
+    assert_eq!(
+        report.self_key_age_days,
+        Some((time() - created) / (24 * 60 * 60))
+    );
+    assert!(time() - created > OUTDATED_KEY_AGE);
+    assert_eq!(report.peers_with_outdated_keys, 0);
+    assert!(report.folders_with_plaintext.is_empty());
+    assert!(report.transports_with_weak_tls.is_empty());
+
+    tcm.send_recv_accept(bob, alice, "Hi!").await;
+    alice
+        .set_config_bool(Config::ForceEncryption, false)
+        .await?;
+    receive_imf(
+        alice,
+        b"From: Claire <claire@example.org>\n\
+          To: alice@example.org\n\
+          Subject: Plain\n\
+          Message-ID: <plain@example.org>\n\
+          Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+          \n\
+          Hello\n",
+        false,
+    )
+    .await?;
+    alice
+        .sql
+        .execute(
+            "INSERT INTO imap (transport_id, rfc724_mid, folder, target, uid, uidvalidity)
+             VALUES (1, 'plain@example.org', 'INBOX', 'INBOX', 1, 1)",
+            (),
+        )
+        .await?;
+
+    let report = alice.get_security_report().await?;
+    assert_eq!(report.peers_with_outdated_keys, 1);
+    assert_eq!(report.folders_with_plaintext.get("INBOX"), Some(&1));
+    assert!(report.to_string().contains("INBOX: 1 messages"));
+
+    Ok(())
+}
@@ -0,0 +1,596 @@
+//! # Server-side search via IMAP SEARCH.
+//!
+//! Local search with [`Context::search_msgs`] only covers downloaded messages.
+//! Old messages that were never downloaded
+//! or messages deferred because of the download limit
+//! can be found on the server and downloaded on demand.
+
+use std::collections::HashSet;
+
+use anyhow::{Context as _, Result, ensure};
+use async_channel as channel;
+use async_imap::imap_proto::{MailboxDatum, Response, Status};
+use futures::TryStreamExt;
+
+use crate::chat::{Chat, ChatId, get_chat_contacts};
+use crate::constants::Chattype;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::headerdef::{HeaderDef, HeaderDefMap};
+use crate::imap::session::Session;
+use crate::imap::{
+    Imap, build_sequence_sets, build_since_criteria, get_uid_next, get_uidvalidity,
+    prefetch_get_message_id,
+};
+use crate::log::warn;
+use crate::message::{self, MsgId};
+use crate::mimeparser;
+use crate::tools::time;
+use crate::transport::ConfiguredLoginParam;
+
+/// Maximum number of results returned per searched folder.
+///
+/// If more messages match, only the ones with the largest UIDs,
+/// usually the most recent ones, are returned.
+const MAX_RESULTS_PER_FOLDER: usize = 100;
+
+/// Headers fetched for each search result.
+const SEARCH_RESULT_FLAGS: &str = "(UID RFC822.SIZE BODY.PEEK[HEADER.FIELDS (\
+                                   MESSAGE-ID \
+                                   X-MICROSOFT-ORIGINAL-MESSAGE-ID \
+                                   FROM \
+                                   SUBJECT \
+                                   DATE\
+                                   )])";
+
+/// Message found on the server by [`Context::search_server`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSearchResult {
+    /// ID of the transport the message was found on.
+    pub transport_id: u32,
+
+    /// Folder containing the message.
+    pub folder: String,
+
+    /// UID of the message in the folder.
+    pub uid: u32,
+
+    /// UIDVALIDITY of the folder at the time of the search.
+    pub uid_validity: u32,
+
+    /// Message-ID of the message.
+    pub rfc724_mid: String,
+
+    /// Address of the sender.
+    pub from_addr: String,
+
+    /// Display name of the sender, may be empty.
+    pub from_name: String,
+
+    /// Subject of the message.
+    pub subject: String,
+
+    /// Timestamp from the `Date` header.
+    pub timestamp: i64,
+
+    /// Size of the message on the server in bytes.
+    pub size: u32,
+
+    /// ID of the message if it is downloaded already.
+    pub msg_id: Option<MsgId>,
+}
+
+/// Formats `s` as a string in `UID SEARCH` criteria.
+///
+/// ASCII strings are quoted.
+/// Quoted strings cannot contain 8-bit characters,
+/// so other strings are sent as a synchronizing literal,
+/// i.e. `{N}` followed by CRLF and the contents.
+/// CR and LF are removed, so CRLF only appears after literal announcements.
+fn quote(s: &str) -> String {
+    let s: String = s.chars().filter(|c| *c != '\r' && *c != '\n').collect();
+    if s.is_ascii() {
+        let quoted = s.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{quoted}\"")
+    } else {
+        format!("{{{}}}\r\n{s}", s.len())
+    }
+}
+
+/// Adds the `CHARSET` to `criteria` if needed.
+fn with_charset(criteria: &str) -> String {
+    if criteria.is_ascii() {
+        criteria.to_string()
+    } else {
+        format!("CHARSET UTF-8 {criteria}")
+    }
+}
+
+/// Returns the length of the literal announced at the end of `part`.
+fn literal_len(part: &str) -> Option<usize> {
+    part.strip_suffix('}')?.rsplit_once('{')?.1.parse().ok()
+}
+
+/// Turns the synchronizing literals of `criteria` into non-synchronizing ones.
+fn to_non_sync_literals(criteria: &str) -> String {
+    criteria.replace("}\r\n", "+}\r\n")
+}
+
+/// Builds the criteria for `UID SEARCH` matching `query` in headers and body.
+///
+/// Returns `None` if the query is empty.
+fn build_search_criteria(query: &str) -> Option<String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return None;
+    }
+    Some(format!("TEXT {}", quote(query)))
+}
+
+/// Builds the criteria for `UID SEARCH` matching messages from or to any of `addrs`.
+///
+/// Returns `None` if there are no addresses.
+fn build_participants_criteria(addrs: &[String]) -> Option<String> {
+    let keys: Vec<String> = addrs
+        .iter()
+        .flat_map(|addr| {
+            [
+                format!("FROM {}", quote(addr)),
+                format!("TO {}", quote(addr)),
+            ]
+        })
+        .collect();
+    let (last, rest) = keys.split_last()?;
+    let mut criteria = String::new();
+    for key in rest {
+        criteria += &format!("OR {key} ");
+    }
+    criteria += last;
+    Some(criteria)
+}
+
+impl Session {
+    /// Runs `UID SEARCH` with `criteria` which may contain literals, see [`quote`].
+    ///
+    /// Literals are sent as non-synchronizing literals if the server supports it,
+    /// otherwise the continuation request is awaited before sending each literal.
+    async fn uid_search_criteria(&mut self, criteria: &str) -> Result<HashSet<u32>> {
+        let criteria = with_charset(criteria);
+        let parts: Vec<&str> = criteria.split("\r\n").collect();
+        // All parts but the last one end with a literal announcement.
+        let announcing = parts
+            .get(..parts.len().saturating_sub(1))
+            .unwrap_or_default();
+        if announcing
+            .iter()
+            .all(|part| literal_len(part).is_some_and(|len| self.can_non_sync_literal(len)))
+        {
+            return Ok(self.uid_search(to_non_sync_literals(&criteria)).await?);
+        }
+
+        let (first, rest) = parts.split_first().context("Empty search criteria")?;
+        let id = self.run_command(format!("UID SEARCH {first}")).await?;
+        let mut rest = rest.iter();
+        let mut uids = HashSet::new();
+        loop {
+            let response = self
+                .read_response()
+                .await?
+                .context("Connection closed during UID SEARCH")?;
+            match response.parsed() {
+                Response::Continue { .. } => {
+                    let part = rest.next().context("Unexpected continuation request")?;
+                    self.run_command_untagged(part).await?;
+                }
+                Response::MailboxData(MailboxDatum::Search(found)) => uids.extend(found),
+                Response::Done {
+                    tag,
+                    status,
+                    information,
+                    ..
+                } if *tag == id => {
+                    ensure!(*status == Status::Ok, "UID SEARCH failed: {information:?}");
+                    return Ok(uids);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Searches `folder` for messages matching the search criteria.
+    async fn search_folder(
+        &mut self,
+        context: &Context,
+        folder: &str,
+        criteria: &str,
+    ) -> Result<Vec<ServerSearchResult>> {
+        let transport_id = self.transport_id();
+        if !self.select_with_uidvalidity(context, folder).await? {
+            return Ok(Vec::new());
+        }
+        let uid_validity = get_uidvalidity(context, transport_id, folder).await?;
+
+        let mut uids: Vec<u32> = self
+            .uid_search_criteria(criteria)
+            .await
+            .with_context(|| format!("UID SEARCH in {folder:?} failed"))?
+            .into_iter()
+            .collect();
+        uids.sort_unstable();
+        let uids = uids
+            .get(uids.len().saturating_sub(MAX_RESULTS_PER_FOLDER)..)
+            .unwrap_or_default();
+        info!(
+            context,
+            "Transport {transport_id}: Found {} messages in {folder:?} on the server.",
+            uids.len()
+        );
+
+        let mut results = Vec::new();
+        for (_, set) in build_sequence_sets(uids)? {
+            let fetches: Vec<_> = self
+                .uid_fetch(&set, SEARCH_RESULT_FLAGS)
+                .await?
+                .try_collect()
+                .await?;
+            for fetch in fetches {
+                let (Some(uid), Some(header)) = (fetch.uid, fetch.header()) else {
+                    continue;
+                };
+                let (headers, _) = mailparse::parse_headers(header)?;
+                let Some(rfc724_mid) = prefetch_get_message_id(&headers) else {
+                    // Messages without Message-ID cannot be matched against the database.
+                    continue;
+                };
+                let from = mimeparser::get_from(&headers);
+                let timestamp = headers
+                    .get_header_value(HeaderDef::Date)
+                    .and_then(|date| mailparse::dateparse(&date).ok())
+                    .unwrap_or_default();
+                results.push(ServerSearchResult {
+                    transport_id,
+                    folder: folder.to_string(),
+                    uid,
+                    uid_validity,
+                    msg_id: message::rfc724_mid_exists(context, &rfc724_mid).await?,
+                    rfc724_mid,
+                    from_addr: from
+                        .as_ref()
+                        .map(|from| from.addr.clone())
+                        .unwrap_or_default(),
+                    from_name: from.and_then(|from| from.display_name).unwrap_or_default(),
+                    subject: headers
+                        .get_header_value(HeaderDef::Subject)
+                        .unwrap_or_default(),
+                    timestamp,
+                    size: fetch.size.unwrap_or_default(),
+                });
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Returns the folders of `imap` searched on the server:
+/// the watched folder and [`Config::WatchExtraFolders`](crate::config::Config::WatchExtraFolders).
+async fn searched_folders(context: &Context, imap: &Imap) -> Result<Vec<String>> {
+    let mut folders = vec![imap.folder.clone()];
+    for folder in context.get_watch_extra_folders().await? {
+        if !folders.contains(&folder) {
+            folders.push(folder);
+        }
+    }
+    Ok(folders)
+}
+
+impl Context {
+    /// Searches the watched folder and the extra folders of all transports on the server.
+    ///
+    /// This finds messages which were never downloaded,
+    /// which [`Context::search_msgs`] cannot find.
+    /// A dedicated IMAP connection is used for each transport.
+    /// Results are sorted by timestamp, newest last.
+    ///
+    /// Use [`Context::download_server_search_result`] to download a result.
+    pub async fn search_server(&self, query: &str) -> Result<Vec<ServerSearchResult>> {
+        let Some(criteria) = build_search_criteria(query) else {
+            return Ok(Vec::new());
+        };
+
+        let mut results = Vec::new();
+        for (transport_id, param) in ConfiguredLoginParam::load_all(self).await? {
+            let mut imap = Imap::new(self, transport_id, param, channel::bounded(1).1).await?;
+            let mut session = imap.prepare(self).await?;
+            for folder in searched_folders(self, &imap).await? {
+                results.extend(
+                    session
+                        .search_folder(self, &folder, &criteria)
+                        .await
+                        .with_context(|| {
+                            format!("Failed to search {folder:?} of transport {transport_id}")
+                        })?,
+                );
+            }
+        }
+        results.sort_by_key(|result| result.timestamp);
+        Ok(results)
+    }
+
+    /// Schedules download of a message found with [`Context::search_server`].
+    ///
+    /// The message is downloaded by the IMAP loop of its transport
+    /// and then appears in the corresponding chat.
+    pub async fn download_server_search_result(&self, result: &ServerSearchResult) -> Result<()> {
+        if result.msg_id.is_some()
+            || message::rfc724_mid_exists(self, &result.rfc724_mid)
+                .await?
+                .is_some()
+        {
+            info!(self, "{} is downloaded already.", result.rfc724_mid);
+            return Ok(());
+        }
+        schedule_download(
+            self,
+            result.transport_id,
+            &result.folder,
+            result.uid,
+            result.uid_validity,
+            &result.rfc724_mid,
+        )
+        .await?;
+        self.scheduler.interrupt_inbox().await;
+        Ok(())
+    }
+
+    /// Fetches messages not older than `days` days from `folder` of the primary transport
+    /// which existed on the server before the folder was selected for the first time,
+    /// see [`Config::InitialFetchSinceDays`](crate::config::Config::InitialFetchSinceDays).
+    ///
+    /// The progress is reported with [`EventType::FetchOlderMsgsProgress`] events.
+    /// The messages are downloaded by the IMAP loop afterwards.
+    /// Returns the number of messages scheduled for download.
+    pub async fn fetch_older_messages(&self, folder: &str, days: u32) -> Result<usize> {
+        self.emit_event(EventType::FetchOlderMsgsProgress(1));
+        let res = self.fetch_older_messages_inner(folder, days).await;
+        match &res {
+            Ok(_) => self.emit_event(EventType::FetchOlderMsgsProgress(1000)),
+            Err(err) => {
+                warn!(
+                    self,
+                    "Failed to fetch older messages from {folder:?}: {err:#}."
+                );
+                self.emit_event(EventType::FetchOlderMsgsProgress(0));
+            }
+        }
+        res
+    }
+
+    #[expect(clippy::arithmetic_side_effects)]
+    async fn fetch_older_messages_inner(&self, folder: &str, days: u32) -> Result<usize> {
+        ensure!(days > 0, "Number of days must be positive");
+        let mut imap = Imap::new_configured(self, channel::bounded(1).1).await?;
+        let mut session = imap.prepare(self).await?;
+        let transport_id = session.transport_id();
+        ensure!(
+            session.select_with_uidvalidity(self, folder).await?,
+            "Folder {folder:?} does not exist"
+        );
+        let uid_validity = get_uidvalidity(self, transport_id, folder).await?;
+        let uid_next = get_uid_next(self, transport_id, folder).await?;
+
+        // Newer messages are fetched by the IMAP loop.
+        let mut uids: Vec<u32> = session
+            .uid_search(build_since_criteria(days, time()))
+            .await
+            .with_context(|| format!("UID SEARCH in {folder:?} failed"))?
+            .into_iter()
+            .filter(|uid| *uid < uid_next)
+            .collect();
+        uids.sort_unstable();
+        info!(
+            self,
+            "Transport {transport_id}: Found {} older messages in {folder:?}.",
+            uids.len()
+        );
+
+        let sets = build_sequence_sets(&uids)?;
+        let sets_cnt = sets.len();
+        let mut scheduled = 0;
+        for (i, (_, set)) in sets.into_iter().enumerate() {
+            let fetches: Vec<_> = session
+                .uid_fetch(&set, SEARCH_RESULT_FLAGS)
+                .await?
+                .try_collect()
+                .await?;
+            for fetch in fetches {
+                let (Some(uid), Some(header)) = (fetch.uid, fetch.header()) else {
+                    continue;
+                };
+                let (headers, _) = mailparse::parse_headers(header)?;
+                let Some(rfc724_mid) = prefetch_get_message_id(&headers) else {
+                    continue;
+                };
+                if message::rfc724_mid_exists(self, &rfc724_mid)
+                    .await?
+                    .is_some()
+                {
+                    continue;
+                }
+                schedule_download(self, transport_id, folder, uid, uid_validity, &rfc724_mid)
+                    .await?;
+                scheduled += 1;
+            }
+            let progress = (i + 1) * 999 / sets_cnt;
+            self.emit_event(EventType::FetchOlderMsgsProgress(
+                u16::try_from(progress).unwrap_or(999).max(1),
+            ));
+        }
+        if scheduled > 0 {
+            self.scheduler.interrupt_inbox().await;
+        }
+        Ok(scheduled)
+    }
+}
+
+impl ChatId {
+    /// Searches the folders of all transports on the server
+    /// for messages of the chat which were never downloaded,
+    /// e.g. because they arrived before the account was configured,
+    /// and schedules download of at most `limit` most recent of them.
+    ///
+    /// Messages are searched by the `List-Id` for mailing lists
+    /// and by the addresses of the chat members otherwise,
+    /// so the messages may end up in other chats with the same members.
+    /// Returns the number of messages scheduled for download.
+    #[expect(clippy::arithmetic_side_effects)]
+    pub async fn fetch_history_from_server(self, context: &Context, limit: usize) -> Result<usize> {
+        let chat = Chat::load_from_db(context, self).await?;
+        let criteria = if chat.typ == Chattype::Mailinglist {
+            Some(format!("HEADER List-Id {}", quote(&chat.grpid)))
+        } else {
+            let mut addrs = Vec::new();
+            for contact_id in get_chat_contacts(context, self).await? {
+                if contact_id == ContactId::SELF {
+                    continue;
+                }
+                let contact = Contact::get_by_id(context, contact_id).await?;
+                addrs.push(contact.get_addr().to_string());
+            }
+            build_participants_criteria(&addrs)
+        };
+        let Some(criteria) = criteria else {
+            info!(context, "{self} has no participants to fetch history for.");
+            return Ok(0);
+        };
+
+        let mut results = Vec::new();
+        for (transport_id, param) in ConfiguredLoginParam::load_all(context).await? {
+            let mut imap = Imap::new(context, transport_id, param, channel::bounded(1).1).await?;
+            let mut session = imap.prepare(context).await?;
+            for folder in searched_folders(context, &imap).await? {
+                // Messages with larger UIDs than the first one known
+                // were fetched by the IMAP loop already.
+                let first_known_uid: Option<u32> = context
+                    .sql
+                    .query_get_value(
+                        "SELECT MIN(uid) FROM imap WHERE transport_id=? AND folder=?",
+                        (transport_id, &folder),
+                    )
+                    .await?;
+                let criteria = match first_known_uid {
+                    None => criteria.clone(),
+                    Some(uid) => match uid.checked_sub(1) {
+                        Some(0) | None => continue,
+                        Some(last_uid) => format!("UID 1:{last_uid} {criteria}"),
+                    },
+                };
+                results.extend(
+                    session
+                        .search_folder(context, &folder, &criteria)
+                        .await
+                        .with_context(|| {
+                            format!("Failed to search {folder:?} of transport {transport_id}")
+                        })?,
+                );
+            }
+        }
+        results.retain(|result| result.msg_id.is_none());
+        results.sort_by_key(|result| result.timestamp);
+        let mut scheduled = 0;
+        for result in results.iter().rev().take(limit) {
+            context.download_server_search_result(result).await?;
+            scheduled += 1;
+        }
+        info!(
+            context,
+            "Scheduled download of {scheduled} messages from the server for {self}."
+        );
+        Ok(scheduled)
+    }
+}
+
+/// Schedules download of the message with the given UID by the IMAP loop.
+async fn schedule_download(
+    context: &Context,
+    transport_id: u32,
+    folder: &str,
+    uid: u32,
+    uid_validity: u32,
+    rfc724_mid: &str,
+) -> Result<()> {
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "INSERT INTO imap (transport_id, rfc724_mid, folder, uid, uidvalidity, target)
+                 VALUES         (?,            ?,          ?,      ?,   ?,           ?)
+                 ON CONFLICT(transport_id, folder, uid, uidvalidity)
+                 DO UPDATE SET rfc724_mid=excluded.rfc724_mid",
+                (transport_id, rfc724_mid, folder, uid, uid_validity, folder),
+            )?;
+            transaction.execute(
+                "INSERT OR IGNORE INTO download (rfc724_mid, msg_id) VALUES (?,0)",
+                (rfc724_mid,),
+            )?;
+            Ok(())
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_search_criteria() {
+        assert_eq!(build_search_criteria("  "), None);
+        assert_eq!(
+            build_search_criteria("hello"),
+            Some("TEXT \"hello\"".to_string())
+        );
+        assert_eq!(
+            build_search_criteria("say \"hi\"\r\n"),
+            Some("TEXT \"say \\\"hi\\\"\"".to_string())
+        );
+        // 8-bit strings are sent as literals.
+        let criteria = build_search_criteria("привет\r\n").unwrap();
+        assert_eq!(criteria, "TEXT {12}\r\nпривет");
+        assert_eq!(with_charset(&criteria), "CHARSET UTF-8 TEXT {12}\r\nпривет");
+        assert_eq!(with_charset("TEXT \"hello\""), "TEXT \"hello\"");
+    }
+
+    #[test]
+    fn test_literals() {
+        let criteria = format!("OR FROM {} TO {}", quote("jörg@example.net"), quote("ä"));
+        assert_eq!(criteria, "OR FROM {17}\r\njörg@example.net TO {2}\r\nä");
+        let parts: Vec<&str> = criteria.split("\r\n").collect();
+        assert_eq!(literal_len(parts[0]), Some(17));
+        assert_eq!(literal_len(parts[1]), Some(2));
+        assert_eq!(literal_len(parts[2]), None);
+        assert_eq!(
+            to_non_sync_literals(&criteria),
+            "OR FROM {17+}\r\njörg@example.net TO {2+}\r\nä"
+        );
+    }
+
+    #[test]
+    fn test_build_participants_criteria() {
+        assert_eq!(build_participants_criteria(&[]), None);
+        assert_eq!(
+            build_participants_criteria(&["bob@example.net".to_string()]),
+            Some("OR FROM \"bob@example.net\" TO \"bob@example.net\"".to_string())
+        );
+        assert_eq!(
+            build_participants_criteria(&[
+                "bob@example.net".to_string(),
+                "fiona@example.net".to_string()
+            ]),
+            Some(
+                "OR FROM \"bob@example.net\" OR TO \"bob@example.net\" \
+                 OR FROM \"fiona@example.net\" TO \"fiona@example.net\""
+                    .to_string()
+            )
+        );
+    }
+}
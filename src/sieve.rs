@@ -0,0 +1,413 @@
+//! # ManageSieve client.
+//!
+//! Sieve scripts are server-side mail filters,
+//! managed with the ManageSieve protocol defined in [RFC 5804].
+//! A Sieve rule can move chat messages to the watched folder on delivery,
+//! so there is no need to move them with IMAP afterwards.
+//!
+//! The client logs in with the IMAP password using SASL `PLAIN`,
+//! there is no OAuth2 support as IMAP and SMTP only use password login as well.
+//!
+//! [RFC 5804]: https://datatracker.ietf.org/doc/html/rfc5804
+
+use anyhow::{Context as _, Result, bail, ensure};
+use base64::Engine as _;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream,
+};
+
+use crate::context::Context;
+use crate::net::connect_tcp;
+use crate::net::proxy::ProxyConfig;
+use crate::net::session::SessionBufStream;
+use crate::net::tls::wrap_tls;
+use crate::transport::ConfiguredLoginParam;
+
+/// Standard ManageSieve port.
+const SIEVE_PORT: u16 = 4190;
+
+/// Maximum size of a literal accepted from the server.
+const MAX_LITERAL_SIZE: usize = 1024 * 1024;
+
+/// Name of the script installed by [`Context::install_chat_sieve_script`].
+pub const CHAT_SCRIPT_NAME: &str = "deltachat";
+
+/// Sieve script stored on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SieveScript {
+    /// Name of the script.
+    pub name: String,
+
+    /// True if the script is the active one.
+    ///
+    /// At most one script is active at a time.
+    pub is_active: bool,
+
+    /// Content of the script.
+    pub content: String,
+}
+
+/// Quotes a string for sending it to the server.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parses a quoted string at the beginning of `s`.
+///
+/// Returns the unquoted string and the rest of `s`.
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut res = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => res.push(chars.next()?.1),
+            '"' => return Some((res, s.get(i.checked_add(2)?..)?)),
+            c => res.push(c),
+        }
+    }
+    None
+}
+
+/// Returns the line without literal marker and the literal size
+/// if the line ends with `{N}` or `{N+}`.
+fn parse_literal_marker(line: &str) -> Option<(&str, usize)> {
+    let (prefix, size) = line.strip_suffix('}')?.rsplit_once('{')?;
+    let size = size.strip_suffix('+').unwrap_or(size).parse().ok()?;
+    Some((prefix, size))
+}
+
+/// Reads a response line, replacing literals with quoted strings.
+async fn read_line<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<String> {
+    let mut res = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            bail!("ManageSieve connection closed");
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let Some((prefix, size)) = parse_literal_marker(line) else {
+            res.push_str(line);
+            return Ok(res);
+        };
+        ensure!(
+            size <= MAX_LITERAL_SIZE,
+            "Literal of {size} bytes is too large"
+        );
+        let mut literal = vec![0; size];
+        stream.read_exact(&mut literal).await?;
+        res.push_str(prefix);
+        res.push_str(&quote(&String::from_utf8_lossy(&literal)));
+    }
+}
+
+/// Reads a response, returning the data lines preceding `OK`.
+async fn read_response<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    loop {
+        let line = read_line(stream).await?;
+        let status = line
+            .split_ascii_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        match status.as_str() {
+            "OK" => return Ok(lines),
+            "NO" | "BYE" => bail!("ManageSieve server responded: {line}"),
+            _ => lines.push(line),
+        }
+    }
+}
+
+/// Returns the value of the capability `name` from the capability response lines,
+/// e.g. `PLAIN LOGIN` for `SASL`, or `None` if the capability is not supported.
+fn get_capability(capabilities: &[String], name: &str) -> Option<String> {
+    capabilities.iter().find_map(|line| {
+        let (capability, rest) = parse_quoted(line)?;
+        if !capability.eq_ignore_ascii_case(name) {
+            return None;
+        }
+        Some(
+            parse_quoted(rest.trim_start())
+                .map(|(value, _)| value)
+                .unwrap_or_default(),
+        )
+    })
+}
+
+/// Parses a `LISTSCRIPTS` response line.
+fn parse_script_line(line: &str) -> Option<(String, bool)> {
+    let (name, rest) = parse_quoted(line)?;
+    Some((name, rest.trim().eq_ignore_ascii_case("ACTIVE")))
+}
+
+/// Returns a script filing chat messages into `folder`.
+fn chat_script(folder: &str) -> String {
+    format!(
+        "require [\"fileinto\"];\r\n\
+         # Installed by Delta Chat.\r\n\
+         if exists \"Chat-Version\" {{\r\n\
+         \x20   fileinto {};\r\n\
+         }}\r\n",
+        quote(folder)
+    )
+}
+
+/// Authenticated ManageSieve connection.
+struct SieveClient {
+    stream: Box<dyn SessionBufStream>,
+}
+
+impl SieveClient {
+    /// Connects to the ManageSieve server of the primary transport and logs in.
+    ///
+    /// The connection is always upgraded with STARTTLS before logging in.
+    async fn connect(context: &Context, param: &ConfiguredLoginParam) -> Result<Self> {
+        ensure!(
+            ProxyConfig::load(context).await?.is_none(),
+            "ManageSieve is not supported when using a proxy"
+        );
+        let server = param.imap.first().context("No IMAP server configured")?;
+        ensure!(
+            !param.imap_password.is_empty(),
+            "ManageSieve requires password authentication"
+        );
+        let host = &server.connection.host;
+        let strict_tls = param.strict_tls(false);
+
+        let tcp_stream = connect_tcp(context, host, SIEVE_PORT, strict_tls).await?;
+        let mut buffered_stream = BufStream::new(tcp_stream);
+        let capabilities = read_response(&mut buffered_stream)
+            .await
+            .context("Failed to read ManageSieve greeting")?;
+        ensure!(
+            get_capability(&capabilities, "STARTTLS").is_some(),
+            "ManageSieve server does not support STARTTLS"
+        );
+        buffered_stream.write_all(b"STARTTLS\r\n").await?;
+        buffered_stream.flush().await?;
+        read_response(&mut buffered_stream).await?;
+
+        let use_sni = true;
+        let tls_stream = wrap_tls(
+            strict_tls,
+            host,
+            SIEVE_PORT,
+            use_sni,
+            "",
+            buffered_stream.into_inner(),
+            &context.tls_session_store,
+            &context.spki_hash_store,
+            &context.sql,
+        )
+        .await
+        .context("STARTTLS upgrade failed")?;
+        let mut client = Self {
+            stream: Box::new(BufStream::new(tls_stream)),
+        };
+
+        // Capabilities are sent again after STARTTLS.
+        let capabilities = read_response(&mut client.stream).await?;
+        ensure!(
+            get_capability(&capabilities, "SASL")
+                .unwrap_or_default()
+                .split_ascii_whitespace()
+                .any(|mechanism| mechanism.eq_ignore_ascii_case("PLAIN")),
+            "ManageSieve server does not support PLAIN authentication"
+        );
+        let credentials = base64::engine::general_purpose::STANDARD
+            .encode(format!("\0{}\0{}", server.user, param.imap_password));
+        client
+            .command(&format!("AUTHENTICATE \"PLAIN\" {}", quote(&credentials)))
+            .await
+            .context("ManageSieve login failed")?;
+        Ok(client)
+    }
+
+    /// Sends a command and returns the response data lines.
+    async fn command(&mut self, command: &str) -> Result<Vec<String>> {
+        write_command(&mut self.stream, command).await?;
+        read_response(&mut self.stream).await
+    }
+
+    async fn list_scripts(&mut self) -> Result<Vec<(String, bool)>> {
+        Ok(self
+            .command("LISTSCRIPTS")
+            .await?
+            .iter()
+            .filter_map(|line| parse_script_line(line))
+            .collect())
+    }
+
+    async fn get_script(&mut self, name: &str) -> Result<String> {
+        let lines = self.command(&format!("GETSCRIPT {}", quote(name))).await?;
+        let line = lines.first().context("Empty GETSCRIPT response")?;
+        let (content, _) = parse_quoted(line).context("Invalid GETSCRIPT response")?;
+        Ok(content)
+    }
+
+    async fn put_script(&mut self, name: &str, content: &str) -> Result<()> {
+        self.command(&format!(
+            "PUTSCRIPT {} {{{}+}}\r\n{content}",
+            quote(name),
+            content.len()
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn set_active(&mut self, name: &str) -> Result<()> {
+        self.command(&format!("SETACTIVE {}", quote(name))).await?;
+        Ok(())
+    }
+
+    async fn logout(mut self) -> Result<()> {
+        self.command("LOGOUT").await?;
+        Ok(())
+    }
+}
+
+async fn write_command<S: AsyncWrite + Unpin>(stream: &mut S, command: &str) -> Result<()> {
+    stream.write_all(command.as_bytes()).await?;
+    stream.write_all(b"\r\n").await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+impl Context {
+    /// Returns the Sieve scripts stored on the server of the primary transport.
+    ///
+    /// Requires ManageSieve support on the IMAP server host.
+    pub async fn get_sieve_scripts(&self) -> Result<Vec<SieveScript>> {
+        let (_, param) = ConfiguredLoginParam::load(self)
+            .await?
+            .context("Not configured")?;
+        let mut client = SieveClient::connect(self, &param).await?;
+        let mut scripts = Vec::new();
+        for (name, is_active) in client.list_scripts().await? {
+            let content = client.get_script(&name).await?;
+            scripts.push(SieveScript {
+                name,
+                is_active,
+                content,
+            });
+        }
+        client.logout().await.ok();
+        Ok(scripts)
+    }
+
+    /// Uploads a Sieve script to the server of the primary transport,
+    /// replacing the script with the same name.
+    ///
+    /// If `activate` is true, the script becomes the active one,
+    /// deactivating the previously active script.
+    pub async fn set_sieve_script(&self, name: &str, content: &str, activate: bool) -> Result<()> {
+        let (_, param) = ConfiguredLoginParam::load(self)
+            .await?
+            .context("Not configured")?;
+        let mut client = SieveClient::connect(self, &param).await?;
+        client.put_script(name, content).await?;
+        if activate {
+            client.set_active(name).await?;
+        }
+        client.logout().await.ok();
+        Ok(())
+    }
+
+    /// Installs and activates a Sieve script filing chat messages into the watched folder,
+    /// so they do not have to be moved there by the client.
+    ///
+    /// Fails if the watched folder is INBOX
+    /// or if another script is active, which would be deactivated.
+    pub async fn install_chat_sieve_script(&self) -> Result<()> {
+        let (_, param) = ConfiguredLoginParam::load(self)
+            .await?
+            .context("Not configured")?;
+        let folder = param
+            .imap_folder
+            .clone()
+            .filter(|folder| !folder.eq_ignore_ascii_case("INBOX"))
+            .context("Chat messages are delivered to INBOX already")?;
+
+        let mut client = SieveClient::connect(self, &param).await?;
+        if let Some((active_name, _)) = client
+            .list_scripts()
+            .await?
+            .into_iter()
+            .find(|(name, is_active)| *is_active && name != CHAT_SCRIPT_NAME)
+        {
+            bail!("Sieve script {active_name:?} is active, add the chat rule to it instead");
+        }
+        client
+            .put_script(CHAT_SCRIPT_NAME, &chat_script(&folder))
+            .await?;
+        client.set_active(CHAT_SCRIPT_NAME).await?;
+        client.logout().await.ok();
+        info!(
+            self,
+            "Installed Sieve script filing chat messages into {folder:?}."
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::BufReader;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_response() -> Result<()> {
+        let greeting = b"\"IMPLEMENTATION\" \"Dovecot Pigeonhole\"\r\n\
+                         \"SIEVE\" \"fileinto reject envelope\"\r\n\
+                         \"SASL\" \"PLAIN LOGIN\"\r\n\
+                         \"STARTTLS\"\r\n\
+                         OK \"Dovecot ready.\"\r\n";
+        let capabilities = read_response(&mut BufReader::new(&greeting[..])).await?;
+        assert_eq!(capabilities.len(), 4);
+        assert_eq!(
+            get_capability(&capabilities, "sasl").unwrap(),
+            "PLAIN LOGIN"
+        );
+        assert_eq!(get_capability(&capabilities, "STARTTLS").unwrap(), "");
+        assert!(get_capability(&capabilities, "NOTIFY").is_none());
+
+        let list = b"\"vacation\"\r\n\"deltachat\" ACTIVE\r\n{6}\r\nfoo\"ba ACTIVE\r\nOK\r\n";
+        let lines = read_response(&mut BufReader::new(&list[..])).await?;
+        let scripts: Vec<_> = lines
+            .iter()
+            .filter_map(|line| parse_script_line(line))
+            .collect();
+        assert_eq!(
+            scripts,
+            vec![
+                ("vacation".to_string(), false),
+                ("deltachat".to_string(), true),
+                ("foo\"ba".to_string(), true)
+            ]
+        );
+
+        let script = b"{26}\r\nrequire \"fileinto\";\r\nstop;\r\nOK\r\n";
+        let lines = read_response(&mut BufReader::new(&script[..])).await?;
+        let (content, _) = parse_quoted(&lines[0]).unwrap();
+        assert_eq!(content, "require \"fileinto\";\r\nstop;");
+
+        let error = b"NO \"Script does not exist.\"\r\n";
+        assert!(
+            read_response(&mut BufReader::new(&error[..]))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_script() {
+        assert_eq!(
+            chat_script("DeltaChat"),
+            "require [\"fileinto\"];\r\n\
+             # Installed by Delta Chat.\r\n\
+             if exists \"Chat-Version\" {\r\n    fileinto \"DeltaChat\";\r\n}\r\n"
+        );
+    }
+}
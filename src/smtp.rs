@@ -3,7 +3,7 @@
 mod connect;
 pub mod send;
 
-use anyhow::{Context as _, Error, Result, bail, format_err};
+use anyhow::{Context as _, Error, Result, bail, ensure, format_err};
 use async_smtp::response::{Category, Code, Detail};
 use async_smtp::{EmailAddress, SmtpTransport};
 use tokio::task;
@@ -12,11 +12,13 @@
 use crate::config::Config;
 use crate::contact::{Contact, ContactId};
 use crate::context::Context;
-use crate::events::EventType;
+use crate::download::upload;
+use crate::events::{ErrorCode, EventType};
 use crate::log::{LogExt, warn};
 use crate::message::Message;
 use crate::message::{self, MsgId};
 use crate::mimefactory::MimeFactory;
+use crate::net::NetworkProfile;
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionBufStream;
 use crate::scheduler::connectivity::ConnectivityStore;
@@ -43,6 +45,9 @@ pub(crate) struct Smtp {
 
     /// If sending the last message failed, contains the error message.
     pub(crate) last_send_error: Option<String>,
+
+    /// Maximum message size announced by the server with the SIZE extension.
+    max_message_size: Option<u64>,
 }
 
 impl Smtp {
@@ -87,9 +92,12 @@ pub async fn connect_configured(&mut self, context: &Context) -> Result<()> {
         if self.is_connected() {
             return Ok(());
         }
+        if context.get_network_profile().await == NetworkProfile::Offline {
+            bail!("Not connecting to SMTP server, network profile is offline.");
+        }
 
         self.connectivity.set_connecting(context);
-        let (_transport_id, lp) = ConfiguredLoginParam::load(context)
+        let (transport_id, lp) = ConfiguredLoginParam::load(context)
             .await?
             .context("Not configured")?;
         let proxy_config = ProxyConfig::load(context).await?;
@@ -101,6 +109,12 @@ pub async fn connect_configured(&mut self, context: &Context) -> Result<()> {
             &lp.addr,
             lp.strict_tls(proxy_config.is_some()),
         )
+        .await?;
+        set_max_message_size(
+            context,
+            transport_id,
+            self.max_message_size.unwrap_or_default(),
+        )
         .await
     }
 
@@ -128,7 +142,7 @@ pub async fn connect(
         let mut first_error = None;
         for lp in login_params {
             info!(context, "SMTP trying to connect to {}.", &lp.connection);
-            let transport = match connect::connect_and_auth(
+            let (transport, max_message_size) = match connect::connect_and_auth(
                 context,
                 proxy_config,
                 strict_tls,
@@ -138,7 +152,7 @@ pub async fn connect(
             )
             .await
             {
-                Ok(transport) => transport,
+                Ok(res) => res,
                 Err(err) => {
                     warn!(context, "SMTP failed to connect and authenticate: {err:#}.");
                     first_error.get_or_insert(err);
@@ -147,6 +161,7 @@ pub async fn connect(
             };
 
             self.transport = Some(transport);
+            self.max_message_size = max_message_size;
             self.last_success = Some(tools::Time::now());
 
             context.emit_event(EventType::SmtpConnected(format!(
@@ -160,6 +175,37 @@ pub async fn connect(
     }
 }
 
+/// Returns the maximum message size in bytes announced by the SMTP server
+/// of the primary transport, 0 if unknown or unlimited.
+pub(crate) async fn get_max_message_size(context: &Context) -> Result<u64> {
+    let max_size = context
+        .sql
+        .query_get_value(
+            "SELECT smtp_max_message_size FROM transports
+             WHERE addr=(SELECT value FROM config WHERE keyname='configured_addr')",
+            (),
+        )
+        .await?;
+    Ok(max_size.unwrap_or_default())
+}
+
+/// Stores the maximum message size announced by the SMTP server of the transport,
+/// 0 if unknown or unlimited.
+pub(crate) async fn set_max_message_size(
+    context: &Context,
+    transport_id: u32,
+    max_size: u64,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE transports SET smtp_max_message_size=? WHERE id=?",
+            (max_size, transport_id),
+        )
+        .await?;
+    Ok(())
+}
+
 pub(crate) enum SendResult {
     /// Message was sent successfully.
     Success,
@@ -205,6 +251,17 @@ pub(crate) async fn smtp_send(
             // Remote error, retry later.
             info!(context, "SMTP failed to send: {:?}.", &err);
 
+            if let async_smtp::error::Error::Permanent(ref response)
+            | async_smtp::error::Error::Transient(ref response) = err
+            {
+                let code = ErrorCode::from_smtp_response(response);
+                if code != ErrorCode::Other {
+                    // Report errors that need the user's attention,
+                    // such as exceeded quota or rate limiting by the server.
+                    error!(context, code = code, "SMTP failed to send: {err}.");
+                }
+            }
+
             let res = match err {
                 async_smtp::error::Error::Permanent(ref response) => {
                     // Workaround for incorrectly configured servers returning permanent errors
@@ -335,6 +392,7 @@ pub(crate) async fn send_msg_to_smtp(
     smtp: &mut Smtp,
     rowid: i64,
 ) -> anyhow::Result<()> {
+    context.set_last_activity();
     if let Err(err) = smtp
         .connect_configured(context)
         .await
@@ -506,6 +564,11 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
         true
     };
 
+    // Uploading creates the SMTP jobs, so it is done first.
+    let uploaded = upload::upload_attachments(context)
+        .await
+        .context("Failed to upload attachments")?;
+
     let rowids = context
         .sql
         .query_map_vec("SELECT id FROM smtp ORDER BY id ASC", (), |row| {
@@ -529,6 +592,7 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
             .await
             .context("Failed to send MDNs")?;
     }
+    ensure!(uploaded, "Some attachments failed to upload");
     Ok(())
 }
 
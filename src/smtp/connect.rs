@@ -1,16 +1,23 @@
 //! SMTP connection establishment.
 
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
 
 use anyhow::{Context as _, Result, bail};
 use async_smtp::{SmtpClient, SmtpTransport};
-use tokio::io::{AsyncBufRead, AsyncWrite, BufStream};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufStream, ReadBuf};
 
 use crate::context::Context;
 use crate::log::warn;
+use crate::net::bandwidth::{ConnectionType, MeteredStream};
 use crate::net::dns::{lookup_host_with_cache, update_connect_timestamp};
 use crate::net::proxy::ProxyConfig;
-use crate::net::session::SessionBufStream;
+use crate::net::session::{SessionBufStream, SessionStream};
 use crate::net::tls::{SpkiHashStore, TlsSessionStore, wrap_tls};
 use crate::net::{
     connect_tcp_inner, connect_tls_inner, run_connection_attempts, update_connection_history,
@@ -47,6 +54,120 @@ async fn new_smtp_transport<S: AsyncBufRead + AsyncWrite + Unpin>(
     Ok(transport)
 }
 
+/// Stream recording the data read from it,
+/// used to get the EHLO response which is not returned by `async_smtp`.
+#[derive(Debug)]
+#[pin_project]
+struct RecordingStream<S> {
+    #[pin]
+    inner: S,
+
+    /// Copy of the buffer returned by the last `poll_fill_buf()` call.
+    filled: Vec<u8>,
+
+    /// Data read so far, `None` after the recording was taken.
+    recorded: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl<S: AsyncRead> AsyncRead for RecordingStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let old_filled = buf.filled().len();
+        let res = this.inner.poll_read(cx, buf);
+        if let Some(recorded) = this.recorded.lock().as_mut() {
+            recorded.extend_from_slice(buf.filled().get(old_filled..).unwrap_or_default());
+        }
+        res
+    }
+}
+
+impl<S: AsyncBufRead> AsyncBufRead for RecordingStream<S> {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.project();
+        let res = this.inner.poll_fill_buf(cx);
+        if let Poll::Ready(Ok(buf)) = &res
+            && this.recorded.lock().is_some()
+        {
+            this.filled.clear();
+            this.filled.extend_from_slice(buf);
+        }
+        res
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+        if let Some(recorded) = this.recorded.lock().as_mut() {
+            let amt = amt.min(this.filled.len());
+            recorded.extend(this.filled.drain(..amt));
+        }
+        this.inner.consume(amt)
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for RecordingStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+impl<S: SessionStream> SessionStream for RecordingStream<S> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+}
+
+/// Returns the maximum message size from the EHLO response,
+/// `None` if there is no limit.
+fn parse_max_message_size(ehlo_response: &str) -> Option<u64> {
+    ehlo_response.lines().find_map(|line| {
+        // Skip the reply code, e.g. "250-".
+        let mut words = line.get(4..)?.split_ascii_whitespace();
+        if !words.next()?.eq_ignore_ascii_case("SIZE") {
+            return None;
+        }
+        words.next()?.parse().ok().filter(|&size| size > 0)
+    })
+}
+
+/// Connects to the SMTP server and authenticates.
+///
+/// Returns the transport and the maximum message size
+/// announced in the response to the EHLO command sent when creating the transport.
 pub(crate) async fn connect_and_auth(
     context: &Context,
     proxy_config: &Option<ProxyConfig>,
@@ -54,11 +175,21 @@ pub(crate) async fn connect_and_auth(
     candidate: ConnectionCandidate,
     user: &str,
     password: &str,
-) -> Result<SmtpTransport<Box<dyn SessionBufStream>>> {
+) -> Result<(SmtpTransport<Box<dyn SessionBufStream>>, Option<u64>)> {
     let session_stream = connect_stream(context, proxy_config.clone(), strict_tls, candidate)
         .await
         .context("SMTP failed to connect")?;
+    let recorded = Arc::new(Mutex::new(Some(Vec::new())));
+    let session_stream: Box<dyn SessionBufStream> = Box::new(RecordingStream {
+        inner: MeteredStream::new(context, ConnectionType::Smtp, session_stream),
+        filled: Vec::new(),
+        recorded: Arc::clone(&recorded),
+    });
     let mut transport = new_smtp_transport(session_stream).await?;
+    // The SIZE extension is not parsed by `async_smtp`,
+    // so read it from the recorded EHLO response.
+    let ehlo_response = recorded.lock().take().unwrap_or_default();
+    let max_message_size = parse_max_message_size(&String::from_utf8_lossy(&ehlo_response));
 
     // Authenticate.
     let (creds, mechanism) = (
@@ -72,7 +203,7 @@ pub(crate) async fn connect_and_auth(
         .try_login(&creds, &mechanism)
         .await
         .context("SMTP failed to login")?;
-    Ok(transport)
+    Ok((transport, max_message_size))
 }
 
 async fn connection_attempt(
@@ -374,4 +505,46 @@ async fn test_skip_smtp_greeting() -> Result<()> {
         let mut buffered_stream = BufReader::new(&greeting[..]);
         skip_smtp_greeting(&mut buffered_stream).await
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_recording_stream() -> Result<()> {
+        use tokio::io::AsyncBufReadExt;
+
+        let recorded = Arc::new(Mutex::new(Some(Vec::new())));
+        let mut stream = RecordingStream {
+            inner: BufReader::new(&b"250-mail.example.org\r\n250 SIZE 100\r\n235 OK\r\n"[..]),
+            filled: Vec::new(),
+            recorded: Arc::clone(&recorded),
+        };
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        stream.read_line(&mut line).await?;
+        let ehlo_response = recorded.lock().take().unwrap();
+        assert_eq!(ehlo_response, b"250-mail.example.org\r\n250 SIZE 100\r\n");
+
+        // Nothing is recorded after taking the recording.
+        line.clear();
+        stream.read_line(&mut line).await?;
+        assert_eq!(line, "235 OK\r\n");
+        assert!(recorded.lock().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_max_message_size() {
+        let ehlo_response = "250-mail.example.org\r\n\
+                             250-PIPELINING\r\n\
+                             250-SIZE 31457280\r\n\
+                             250 AUTH PLAIN LOGIN\r\n";
+        assert_eq!(parse_max_message_size(ehlo_response), Some(31457280));
+        assert_eq!(
+            parse_max_message_size("250-mail.example.org\r\n250 SIZE\r\n"),
+            None
+        );
+        assert_eq!(
+            parse_max_message_size("250-mail.example.org\r\n250 SIZE 0\r\n"),
+            None
+        );
+        assert_eq!(parse_max_message_size("250 mail.example.org\r\n"), None);
+    }
 }
@@ -0,0 +1,354 @@
+//! # Splitting of oversized text messages.
+//!
+//! Texts too large for the size limit announced by the SMTP server
+//! are sent as several messages, each containing a part of the text.
+//! The first part has the Message-ID of the original message
+//! and the `Chat-Split-Part: 1/<count>` header,
+//! other parts have own Message-IDs, the `Chat-Split-Part: <index>/<count>` header
+//! and refer to the first part with the `Chat-Split-Of` header.
+//!
+//! The text is split at line breaks if possible.
+//! The line break a part ends at is not sent,
+//! instead the header is `Chat-Split-Part: <index>/<count>; newline`,
+//! so receivers can restore the line break
+//! and do not insert one where an over-long line was cut.
+//! Receivers show the first part as the message
+//! and append the text of other parts to it as they arrive,
+//! the messages containing other parts are trashed.
+//! Parts must have the same sender and encryption as the first part.
+//! Until all parts are received, the text ends with an "incomplete" marker.
+
+use anyhow::Result;
+
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::headerdef::HeaderDef;
+use crate::log::warn;
+use crate::message::{Message, Viewtype, rfc724_mid_exists};
+use crate::mimefactory::{MimeFactory, RenderedEmail};
+use crate::mimeparser::{MimeMessage, SystemMessage};
+use crate::param::Param;
+use crate::stock_str;
+use crate::tools::{normalize_text, time};
+
+/// Size reserved for headers, the footer and encryption overhead of each part.
+const PART_OVERHEAD_BYTES: u64 = 20_000;
+
+/// Returns the maximum text size of a part if the message text can be split
+/// to fit into messages of at most `max_message_size` bytes.
+pub(crate) fn max_part_size(msg: &Message, max_message_size: u64) -> Option<usize> {
+    if msg.viewtype != Viewtype::Text
+        || msg.has_html()
+        || msg.param.get_cmd() != SystemMessage::Unknown
+        || msg.param.exists(Param::TextEditFor)
+    {
+        return None;
+    }
+
+    // Base64 inside the encrypted and armored message
+    // roughly doubles the size of the text in the worst case.
+    let max_part_size = max_message_size.checked_sub(PART_OVERHEAD_BYTES)? / 2;
+    usize::try_from(max_part_size).ok().filter(|&size| size > 0)
+}
+
+/// Splits the text into parts of at most `max_bytes` bytes.
+///
+/// The text is split after line breaks, which are kept at the end of the parts,
+/// so concatenating the parts results in the original text.
+/// Lines longer than `max_bytes` are split at character boundaries.
+pub(crate) fn split_text(text: &str, max_bytes: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    for line in text.split_inclusive('\n') {
+        if !part.is_empty() && part.len().saturating_add(line.len()) > max_bytes {
+            parts.push(std::mem::take(&mut part));
+        }
+        for c in line.chars() {
+            if !part.is_empty() && part.len().saturating_add(c.len_utf8()) > max_bytes {
+                parts.push(std::mem::take(&mut part));
+            }
+            part.push(c);
+        }
+    }
+    parts.push(part);
+    parts
+}
+
+/// Renders the text of the message split into several messages
+/// of at most `max_message_size` bytes.
+///
+/// Returns the first part and the remaining parts
+/// or `None` if the message cannot be split this way.
+pub(crate) async fn render_split_parts(
+    context: &Context,
+    msg: &Message,
+    max_message_size: u64,
+) -> Result<Option<(RenderedEmail, Vec<RenderedEmail>)>> {
+    let Some(max_part_size) = max_part_size(msg, max_message_size) else {
+        return Ok(None);
+    };
+    let texts = split_text(&msg.text, max_part_size);
+    let count = texts.len();
+    info!(
+        context,
+        "Splitting text of message {} into {count} parts.", msg.id
+    );
+    let mut rendered_parts = Vec::with_capacity(count);
+    for (index, text) in (1..).zip(texts) {
+        let (text, newline) = match text.strip_suffix('\n') {
+            Some(text) => (text.to_string(), true),
+            None => (text, false),
+        };
+        let mut mimefactory = MimeFactory::from_msg(context, msg.clone()).await?;
+        mimefactory.set_as_split_part(index, count, newline, text);
+        let rendered = Box::pin(mimefactory.render(context)).await?;
+        if u64::try_from(rendered.message.len()).unwrap_or(u64::MAX) > max_message_size {
+            return Ok(None);
+        }
+        rendered_parts.push(rendered);
+    }
+    let mut rendered_parts = rendered_parts.into_iter();
+    Ok(rendered_parts
+        .next()
+        .map(|first| (first, rendered_parts.collect())))
+}
+
+/// Parses the `Chat-Split-Part` header value `<index>/<count>[; newline]`.
+///
+/// Returns the index, the count and whether the part ends at a line break.
+fn parse_split_part(value: &str) -> Option<(u32, u32, bool)> {
+    let (value, newline) = match value.split_once(';') {
+        Some((value, param)) if param.trim() == "newline" => (value, true),
+        Some(_) => return None,
+        None => (value, false),
+    };
+    let (index, count) = value.trim().split_once('/')?;
+    let index: u32 = index.trim().parse().ok()?;
+    let count: u32 = count.trim().parse().ok()?;
+    if index == 0 || index > count {
+        return None;
+    }
+    Some((index, count, newline))
+}
+
+/// Stores the part of a split text and returns the text of all parts received so far.
+///
+/// The line break a part ends at is stored with the part.
+/// Only parts with the same sender, encryption and count as the first part are used
+/// and only as long as there is no gap.
+/// If some parts are missing, the returned text ends with an "incomplete" marker.
+async fn store_part(
+    context: &Context,
+    rfc724_mid: &str,
+    index: u32,
+    count: u32,
+    from_id: ContactId,
+    encrypted: bool,
+    text: &str,
+) -> Result<String> {
+    context
+        .sql
+        .execute(
+            "INSERT OR REPLACE INTO msg_text_parts
+             (rfc724_mid, part, count, from_id, encrypted, txt, timestamp)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (rfc724_mid, index, count, from_id, encrypted, text, time()),
+        )
+        .await?;
+    let parts = context
+        .sql
+        .query_map_vec(
+            "SELECT part, txt FROM msg_text_parts
+             WHERE rfc724_mid=? AND from_id=? AND encrypted=? AND count=?
+             ORDER BY part",
+            (rfc724_mid, from_id, encrypted, count),
+            |row| {
+                let part: u32 = row.get(0)?;
+                let text: String = row.get(1)?;
+                Ok((part, text))
+            },
+        )
+        .await?;
+    let mut text = String::new();
+    let mut received: u32 = 0;
+    for (part, part_text) in parts {
+        if part != received.saturating_add(1) {
+            break;
+        }
+        text.push_str(&part_text);
+        received = part;
+    }
+    if received < count {
+        // The last part received so far may end at a line break
+        // to be followed by parts that have not arrived yet.
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        text.push_str("\n\n");
+        text.push_str(&stock_str::split_msg_incomplete(context, received, count));
+    }
+    Ok(text)
+}
+
+/// Handles a received part of a split text.
+///
+/// For the first part, the text of the part is replaced
+/// with the text of all parts received so far.
+/// For other parts, the text is appended to the message containing the first part
+/// if it was received already
+/// and the part has the same sender and encryption as the first part.
+pub(crate) async fn handle_split_part(
+    context: &Context,
+    mime_parser: &mut MimeMessage,
+    rfc724_mid: &str,
+    from_id: ContactId,
+) -> Result<()> {
+    let Some(split_part) = mime_parser.get_header(HeaderDef::ChatSplitPart) else {
+        return Ok(());
+    };
+    let Some((index, count, newline)) = parse_split_part(split_part) else {
+        warn!(context, "Invalid Chat-Split-Part header {split_part:?}.");
+        return Ok(());
+    };
+    let first_rfc724_mid = match mime_parser.get_header(HeaderDef::ChatSplitOf) {
+        Some(first_rfc724_mid) if index > 1 => first_rfc724_mid
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string(),
+        None if index == 1 => rfc724_mid.to_string(),
+        _ => {
+            warn!(
+                context,
+                "Split text part {index} of {rfc724_mid:?} is invalid."
+            );
+            return Ok(());
+        }
+    };
+    let Some(part) = mime_parser.parts.first_mut() else {
+        return Ok(());
+    };
+    let encrypted = part
+        .param
+        .get_bool(Param::GuaranteeE2ee)
+        .unwrap_or_default();
+
+    let part_text = if newline {
+        format!("{}\n", part.msg)
+    } else {
+        part.msg.clone()
+    };
+    if index == 1 {
+        part.msg = store_part(
+            context,
+            &first_rfc724_mid,
+            index,
+            count,
+            from_id,
+            encrypted,
+            &part_text,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let original_msg = match rfc724_mid_exists(context, &first_rfc724_mid).await? {
+        Some(original_msg_id) => Message::load_from_db_optional(context, original_msg_id).await?,
+        None => None,
+    };
+    let Some(original_msg) = original_msg else {
+        // The first part has not arrived yet,
+        // it is checked against the stored parts when it arrives.
+        store_part(
+            context,
+            &first_rfc724_mid,
+            index,
+            count,
+            from_id,
+            encrypted,
+            &part_text,
+        )
+        .await?;
+        return Ok(());
+    };
+    if original_msg.from_id != from_id {
+        warn!(context, "Split text part: Bad sender.");
+        return Ok(());
+    }
+    if encrypted != original_msg.get_showpadlock() {
+        warn!(
+            context,
+            "Split text part: Encryption differs from the first part."
+        );
+        return Ok(());
+    }
+    let text = store_part(
+        context,
+        &first_rfc724_mid,
+        index,
+        count,
+        from_id,
+        encrypted,
+        &part_text,
+    )
+    .await?;
+    context
+        .sql
+        .execute(
+            "UPDATE msgs SET txt=?, txt_normalized=? WHERE id=?",
+            (&text, normalize_text(&text), original_msg.id),
+        )
+        .await?;
+    context.emit_msgs_changed(original_msg.chat_id, original_msg.id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_text() {
+        assert_eq!(split_text("", 10), vec![""]);
+        assert_eq!(split_text("short", 10), vec!["short"]);
+        assert_eq!(split_text("line 1\nline 2", 10), vec!["line 1\n", "line 2"]);
+        assert_eq!(split_text("a\nb\n\nc", 4), vec!["a\nb\n", "\nc"]);
+        assert_eq!(split_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(split_text("äöü", 4), vec!["äö", "ü"]);
+
+        let text = "Lorem ipsum dolor sit amet,\nconsectetur adipiscing elit,\n\nsed do eiusmod.";
+        for max_bytes in [5, 10, 30, 40, 100] {
+            let parts = split_text(text, max_bytes);
+            assert!(parts.iter().all(|part| part.len() <= max_bytes));
+            assert_eq!(parts.concat(), text);
+        }
+    }
+
+    #[test]
+    fn test_split_text_long_line() {
+        let text = "short line\nthis line is much longer than a part\nend";
+        let parts = split_text(text, 12);
+        assert_eq!(
+            parts,
+            vec![
+                "short line\n",
+                "this line is",
+                " much longer",
+                " than a part",
+                "\nend"
+            ]
+        );
+        assert_eq!(parts.concat(), text);
+    }
+
+    #[test]
+    fn test_parse_split_part() {
+        assert_eq!(parse_split_part("1/3"), Some((1, 3, false)));
+        assert_eq!(parse_split_part(" 3 / 3 "), Some((3, 3, false)));
+        assert_eq!(parse_split_part("2/3; newline"), Some((2, 3, true)));
+        assert_eq!(parse_split_part("2/3; foo"), None);
+        assert_eq!(parse_split_part("0/3"), None);
+        assert_eq!(parse_split_part("4/3"), None);
+        assert_eq!(parse_split_part("1"), None);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use anyhow::{Context as _, Result, bail};
@@ -10,10 +11,11 @@
 
 use crate::blob::BlobObject;
 use crate::config::Config;
-use crate::constants::DC_CHAT_ID_TRASH;
+use crate::constants::{self, DC_CHAT_ID_TRASH};
 use crate::context::Context;
 use crate::debug_logging::set_debug_logging_xdc;
 use crate::ephemeral::start_ephemeral_timers;
+use crate::events::EventType;
 use crate::imex::BLOBS_BACKUP_NAME;
 use crate::location;
 use crate::log::{LogExt, warn};
@@ -50,6 +52,12 @@ macro_rules! params_slice {
 /// How long a hidden and unused transport should be kept in the database before being deleted.
 pub const UNPUBLISHED_TRANSPORT_KEEP_TIME: i64 = 90 * 24 * 60 * 60;
 
+/// Number of prepared statements cached per connection.
+///
+/// Frequently used queries, e.g. for loading the chatlist or messages,
+/// are then only compiled once per connection.
+const STATEMENT_CACHE_CAPACITY: usize = 128;
+
 /// A wrapper around the underlying Sqlite3 object.
 #[derive(Debug)]
 pub struct Sql {
@@ -271,6 +279,7 @@ pub async fn open(&self, context: &Context, passphrase: String) -> Result<()> {
         {
             set_debug_logging_xdc(context, Some(MsgId::new(xdc_id))).await?;
         }
+        context.update_quiet_hours().await.log_err(context).ok();
         Ok(())
     }
 
@@ -334,7 +343,7 @@ pub async fn execute(
         params: impl rusqlite::Params + Send,
     ) -> Result<usize> {
         self.call_write(move |conn| {
-            let res = conn.execute(query, params)?;
+            let res = conn.prepare_cached(query)?.execute(params)?;
             Ok(res)
         })
         .await
@@ -343,7 +352,7 @@ pub async fn execute(
     /// Executes the given query, returning the last inserted row ID.
     pub async fn insert(&self, query: &str, params: impl rusqlite::Params + Send) -> Result<i64> {
         self.call_write(move |conn| {
-            conn.execute(query, params)?;
+            conn.prepare_cached(query)?.execute(params)?;
             Ok(conn.last_insert_rowid())
         })
         .await
@@ -366,7 +375,7 @@ pub async fn query_map<T, F, G, H>(
     {
         let query_only = true;
         self.call(query_only, move |conn| {
-            let mut stmt = conn.prepare(sql)?;
+            let mut stmt = conn.prepare_cached(sql)?;
             let res = stmt.query_and_then(params, f)?;
             g(res)
         })
@@ -435,7 +444,7 @@ pub async fn query_row<T, F>(
     {
         let query_only = true;
         self.call(query_only, move |conn| {
-            let res = conn.query_row(query, params, f)?;
+            let res = conn.prepare_cached(query)?.query_row(params, f)?;
             Ok(res)
         })
         .await
@@ -540,7 +549,7 @@ pub async fn query_row_optional<T, F>(
     {
         let query_only = true;
         self.call(query_only, move |conn| {
-            match conn.query_row(sql.as_ref(), params, f) {
+            match conn.prepare_cached(sql)?.query_row(params, f) {
                 Ok(res) => Ok(Some(res)),
                 Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
                 Err(err) => Err(err.into()),
@@ -698,6 +707,7 @@ fn new_connection(path: &Path, passphrase: &str) -> Result<Connection> {
         | OpenFlags::SQLITE_OPEN_READ_WRITE
         | OpenFlags::SQLITE_OPEN_CREATE;
     let conn = Connection::open_with_flags(path, flags)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
     conn.execute_batch(
         "PRAGMA cipher_memory_security = OFF; -- Too slow on Android
          PRAGMA secure_delete=on;
@@ -778,12 +788,39 @@ async fn incremental_vacuum(context: &Context) -> Result<()> {
         .await
 }
 
+/// Returns true if scheduled housekeeping should run now.
+///
+/// Housekeeping runs once per [`constants::HOUSEKEEPING_PERIOD`],
+/// but not right after IO was started and not while messages are sent or received
+/// to avoid slowing down the app while it is used.
+/// It is postponed for at most [`constants::HOUSEKEEPING_MAX_POSTPONEMENT`] though.
+pub(crate) async fn housekeeping_due(context: &Context) -> Result<bool> {
+    let now = time();
+    let last_housekeeping = context.get_config_i64(Config::LastHousekeeping).await?;
+    let due = last_housekeeping.saturating_add(constants::HOUSEKEEPING_PERIOD);
+    if due > now {
+        return Ok(false);
+    }
+    if due.saturating_add(constants::HOUSEKEEPING_MAX_POSTPONEMENT) <= now {
+        return Ok(true);
+    }
+    let io_start = context.io_start_timestamp.load(Ordering::Relaxed);
+    if io_start.saturating_add(constants::HOUSEKEEPING_START_IO_DELAY) > now {
+        return Ok(false);
+    }
+    let last_activity = context.last_activity_timestamp.load(Ordering::Relaxed);
+    Ok(last_activity.saturating_add(constants::HOUSEKEEPING_IDLE_PERIOD) <= now)
+}
+
 /// Cleanup the account to restore some storage and optimize the database.
+///
+/// Emits [`EventType::HousekeepingProgress`] events while running.
 pub async fn housekeeping(context: &Context) -> Result<()> {
     let Ok(_housekeeping_lock) = context.housekeeping_mutex.try_lock() else {
         // Housekeeping is already running in another thread, do nothing.
         return Ok(());
     };
+    context.emit_event(EventType::HousekeepingProgress(1));
     // Setting `Config::LastHousekeeping` at the beginning avoids endless loops when things do not
     // work out for whatever reason or are interrupted by the OS.
     if let Err(e) = context
@@ -798,18 +835,26 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .context("Failed to cleanup HTTP cache")
         .log_err(context)
         .ok();
+    crate::net::bandwidth::flush_bandwidth_stats(context)
+        .await
+        .context("Failed to store bandwidth stats")
+        .log_err(context)
+        .ok();
     migrations::msgs_to_key_contacts(context)
         .await
         .context("migrations::msgs_to_key_contacts")
         .log_err(context)
         .ok();
 
+    context.emit_event(EventType::HousekeepingProgress(100));
+
     if let Err(err) = remove_unused_files(context).await {
         warn!(
             context,
             "Housekeeping: cannot remove unused files: {:#}.", err
         );
     }
+    context.emit_event(EventType::HousekeepingProgress(400));
 
     if let Err(err) = start_ephemeral_timers(context).await {
         warn!(
@@ -825,6 +870,8 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         );
     }
 
+    context.emit_event(EventType::HousekeepingProgress(500));
+
     if let Err(err) = incremental_vacuum(context).await {
         warn!(context, "Failed to run incremental vacuum: {err:#}.");
     }
@@ -836,6 +883,7 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         warn!(context, "wal_checkpoint() failed: {err:#}.");
         debug_assert!(false);
     }
+    context.emit_event(EventType::HousekeepingProgress(700));
 
     context
         .sql
@@ -861,6 +909,69 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .log_err(context)
         .ok();
 
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_annotations WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old message annotations")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_reminders WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove reminders of deleted messages")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM chats_ui_properties WHERE chat_id NOT IN (SELECT id FROM chats)",
+            (),
+        )
+        .await
+        .context("failed to remove UI properties of deleted chats")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_content_hashes WHERE msg_id NOT IN (SELECT id FROM msgs)",
+            (),
+        )
+        .await
+        .context("failed to remove old content hashes")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_duplicates WHERE msg_id NOT IN (SELECT id FROM msgs)",
+            (),
+        )
+        .await
+        .context("failed to remove old duplicate references")
+        .log_err(context)
+        .ok();
+
+    crate::notifications::prune_notifications(context)
+        .await
+        .context("Failed to prune notification items")
+        .log_err(context)
+        .ok();
+
     prune_connection_history(context)
         .await
         .context("Failed to prune connection history")
@@ -897,6 +1008,31 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         "DELETE FROM imap_sync WHERE transport_id NOT IN (SELECT transports.id FROM transports)",
         (),
     ).await.log_err(context).ok();
+    context
+        .sql
+        .execute(
+            "DELETE FROM gmail_labels
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM imap
+                 WHERE imap.transport_id=gmail_labels.transport_id
+                 AND imap.rfc724_mid=gmail_labels.rfc724_mid
+             )",
+            (),
+        )
+        .await
+        .log_err(context)
+        .ok();
+
+    // Delete parts of split texts that were not completed within a month.
+    context
+        .sql
+        .execute(
+            "DELETE FROM msg_text_parts WHERE timestamp<?",
+            (time().saturating_sub(30 * 24 * 60 * 60),),
+        )
+        .await
+        .log_err(context)
+        .ok();
 
     // Delete POI locations
     // which don't have corresponding message.
@@ -919,6 +1055,7 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .ok();
 
     info!(context, "Housekeeping done.");
+    context.emit_event(EventType::HousekeepingProgress(1000));
     Ok(())
 }
 
@@ -995,6 +1132,13 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         Param::File,
     )
     .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM draft_history;",
+        Param::File,
+    )
+    .await?;
     maybe_add_from_param(
         &context.sql,
         &mut files_in_use,
@@ -1002,6 +1146,13 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         Param::ProfileImage,
     )
     .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM chats;",
+        Param::Wallpaper,
+    )
+    .await?;
     maybe_add_from_param(
         &context.sql,
         &mut files_in_use,
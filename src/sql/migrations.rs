@@ -2522,6 +2522,369 @@ pub async fn run(context: &Context, sql: &Sql) -> Result<bool> {
         .await?;
     }
 
+    inc_and_check(&mut migration_version, 159)?;
+    if dbversion < migration_version {
+        // Attachments of partially downloaded messages which are still on the server.
+        sql.execute_migration(
+            "CREATE TABLE download_parts (
+                msg_id INTEGER PRIMARY KEY,
+                section TEXT NOT NULL,
+                mimetype TEXT NOT NULL,
+                filename TEXT NOT NULL DEFAULT '',
+                encoding TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                FOREIGN KEY(msg_id) REFERENCES msgs(id) ON DELETE CASCADE
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 160)?;
+    if dbversion < migration_version {
+        // Gmail message IDs and labels, see `imap::gmail`.
+        sql.execute_migration(
+            "CREATE TABLE gmail_labels (
+                transport_id INTEGER NOT NULL,
+                rfc724_mid TEXT NOT NULL,
+                gm_msgid TEXT NOT NULL,
+                labels TEXT NOT NULL DEFAULT '',
+                PRIMARY KEY(transport_id, rfc724_mid)
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 161)?;
+    if dbversion < migration_version {
+        // Parts of texts split into several messages, see `split_msg`.
+        sql.execute_migration(
+            "CREATE TABLE msg_text_parts (
+                rfc724_mid TEXT NOT NULL, -- Message-ID of the first part.
+                part INTEGER NOT NULL,
+                count INTEGER NOT NULL,
+                from_id INTEGER NOT NULL,
+                txt TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                PRIMARY KEY(rfc724_mid, part)
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 162)?;
+    if dbversion < migration_version {
+        // Previous versions of drafts, see `ChatId::get_draft_history()`.
+        sql.execute_migration(
+            "CREATE TABLE draft_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                type INTEGER NOT NULL,
+                txt TEXT NOT NULL,
+                param TEXT NOT NULL,
+                mime_in_reply_to TEXT NOT NULL
+            ) STRICT;
+            CREATE INDEX draft_history_index1 ON draft_history (chat_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 163)?;
+    if dbversion < migration_version {
+        // Group member changes merged into a summarizing info message,
+        // see `MsgId::get_coalesced_msgs()`.
+        sql.execute_migration(
+            "CREATE TABLE msgs_coalesced (
+                msg_id INTEGER PRIMARY KEY, -- Hidden member change message.
+                summary_msg_id INTEGER NOT NULL
+            ) STRICT;
+            CREATE INDEX msgs_coalesced_index1 ON msgs_coalesced (summary_msg_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 164)?;
+    if dbversion < migration_version {
+        // Daily network traffic, see `Context::get_bandwidth_stats()`.
+        sql.execute_migration(
+            "CREATE TABLE bandwidth_stats (
+                day INTEGER NOT NULL, -- Timestamp of the day start (UTC).
+                connection_type INTEGER NOT NULL,
+                bytes_sent INTEGER NOT NULL DEFAULT 0,
+                bytes_received INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(day, connection_type)
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 165)?;
+    if dbversion < migration_version {
+        // Items for OS notification extensions, see `Context::get_notification_items()`.
+        sql.execute_migration(
+            "CREATE TABLE notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, -- Token returned to the UI.
+                kind INTEGER NOT NULL,
+                chat_id INTEGER NOT NULL,
+                contact_id INTEGER NOT NULL,
+                msg_id INTEGER NOT NULL,
+                text TEXT NOT NULL DEFAULT '', -- Reaction or webxdc notification text.
+                timestamp INTEGER NOT NULL
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 166)?;
+    if dbversion < migration_version {
+        // Read marker, see `ChatId::get_first_unread_msg()`.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN last_read_msg_id INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 167)?;
+    if dbversion < migration_version {
+        // Manual unread flag, see `ChatId::mark_unread()`.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN marked_unread INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 168)?;
+    if dbversion < migration_version {
+        // Blocked domains, see `Context::block_domain()`.
+        sql.execute_migration(
+            "CREATE TABLE blocked_domains (
+                domain TEXT PRIMARY KEY -- Lowercased domain name.
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 169)?;
+    if dbversion < migration_version {
+        // Incoming message filters, see `Context::add_msg_filter()`,
+        // and messages to move to the Spam folder because of them.
+        sql.execute_migration(
+            "CREATE TABLE msg_filters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                field INTEGER NOT NULL,
+                pattern TEXT NOT NULL,
+                action INTEGER NOT NULL
+            ) STRICT;
+            CREATE TABLE imap_spam (
+                id INTEGER PRIMARY KEY NOT NULL REFERENCES imap(id) ON DELETE CASCADE
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 170)?;
+    if dbversion < migration_version {
+        // Sent messages to append to the Sent folder of the transport with the `addr`,
+        // see `Config::SentFolderStrategy`.
+        sql.execute_migration(
+            "CREATE TABLE imap_append (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                addr TEXT NOT NULL,
+                mime TEXT NOT NULL
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 171)?;
+    if dbversion < migration_version {
+        // Content hashes of received messages and Message-IDs of the messages
+        // detected as their duplicates, see `receive_imf::content_hash()`.
+        sql.execute_migration(
+            "CREATE TABLE msgs_content_hashes (
+                msg_id INTEGER PRIMARY KEY,
+                hash TEXT NOT NULL
+            ) STRICT;
+            CREATE INDEX msgs_content_hashes_index1 ON msgs_content_hashes (hash);
+            CREATE TABLE msgs_duplicates (
+                rfc724_mid TEXT PRIMARY KEY,
+                msg_id INTEGER NOT NULL
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 172)?;
+    if dbversion < migration_version {
+        // Local annotations of messages, see `MsgId::set_annotation()`.
+        sql.execute_migration(
+            "CREATE TABLE msgs_annotations (
+                msg_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (msg_id, key)
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 173)?;
+    if dbversion < migration_version {
+        // UI-specific chat properties, see `ChatId::set_ui_property()`.
+        sql.execute_migration(
+            "CREATE TABLE chats_ui_properties (
+                chat_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (chat_id, key)
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 174)?;
+    if dbversion < migration_version {
+        // Covering index for `ChatId::get_statistics()`.
+        sql.execute_migration(
+            "CREATE INDEX msgs_index10 ON msgs (chat_id, hidden, from_id, type, timestamp);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 175)?;
+    if dbversion < migration_version {
+        // Message reminders, see `MsgId::set_reminder()`.
+        sql.execute_migration(
+            "CREATE TABLE msgs_reminders (
+                msg_id INTEGER PRIMARY KEY,
+                timestamp INTEGER NOT NULL
+            ) STRICT;
+            CREATE INDEX msgs_reminders_index1 ON msgs_reminders (timestamp);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 176)?;
+    if dbversion < migration_version {
+        // Reports about messages of outgoing broadcast channels,
+        // see `MsgId::report_to_owner()`.
+        sql.execute_migration(
+            "CREATE TABLE abuse_reports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                msg_id INTEGER NOT NULL,
+                contact_id INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                UNIQUE(msg_id, contact_id)
+            ) STRICT;
+            CREATE INDEX abuse_reports_index1 ON abuse_reports (chat_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 177)?;
+    if dbversion < migration_version {
+        // Maximum message size announced by the SMTP server with the SIZE extension.
+        sql.execute_migration(
+            "ALTER TABLE transports
+             ADD COLUMN smtp_max_message_size INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 178)?;
+    if dbversion < migration_version {
+        // Folder names are stored decoded from modified UTF-7 now.
+        sql.execute_migration_transaction(
+            |transaction| {
+                for (table, column) in [
+                    ("imap", "folder"),
+                    ("imap", "target"),
+                    ("imap_sync", "folder"),
+                ] {
+                    let names = transaction
+                        .prepare(&format!(
+                            "SELECT DISTINCT {column} FROM {table} WHERE {column} LIKE '%&%'"
+                        ))?
+                        .query_map((), |row| row.get::<_, String>(0))?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    for name in names {
+                        let decoded = crate::imap::utf7::from_server_name(&name);
+                        if decoded != name {
+                            transaction.execute(
+                                &format!(
+                                    "UPDATE OR IGNORE {table} SET {column}=? WHERE {column}=?"
+                                ),
+                                (decoded, name),
+                            )?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 179)?;
+    if dbversion < migration_version {
+        // Messages waiting for their attachment to be uploaded before sending,
+        // see `upload::upload_attachments()`.
+        sql.execute_migration(
+            "CREATE TABLE uploads (
+                msg_id INTEGER PRIMARY KEY,
+                retries INTEGER NOT NULL DEFAULT 0
+            ) STRICT",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 180)?;
+    if dbversion < migration_version {
+        // Store whether split text parts were encrypted
+        // and do not let parts from other senders replace each other.
+        sql.execute_migration(
+            "CREATE TABLE new_msg_text_parts (
+                rfc724_mid TEXT NOT NULL, -- Message-ID of the first part.
+                part INTEGER NOT NULL,
+                count INTEGER NOT NULL,
+                from_id INTEGER NOT NULL,
+                encrypted INTEGER NOT NULL,
+                txt TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                PRIMARY KEY(rfc724_mid, from_id, encrypted, part)
+            ) STRICT;
+            INSERT INTO new_msg_text_parts
+            SELECT rfc724_mid, part, count, from_id, 1, txt, timestamp FROM msg_text_parts;
+            DROP TABLE msg_text_parts;
+            ALTER TABLE new_msg_text_parts RENAME TO msg_text_parts;",
+            migration_version,
+        )
+        .await?;
+    }
+
     let new_version = sql
         .get_raw_config_int(VERSION_CFG)
         .await?
@@ -176,3 +176,42 @@ async fn test_key_contacts_migration_verified() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_decode_folder_names_migration() -> Result<()> {
+    let t = STOP_MIGRATIONS_AT
+        .scope(177, async move { TestContext::new_alice().await })
+        .await;
+
+    t.sql
+        .execute(
+            "INSERT INTO imap (transport_id, rfc724_mid, folder, target, uid, uidvalidity)
+             VALUES (1, 'foo@example.org', '&BCEEPwQwBDw-', 'Tom &- Jerry', 1, 1)",
+            (),
+        )
+        .await?;
+    t.sql
+        .execute(
+            "INSERT INTO imap_sync (transport_id, folder) VALUES (1, '&BCEEPwQwBDw-')",
+            (),
+        )
+        .await?;
+    t.sql.run_migrations(&t).await?;
+
+    let (folder, target): (String, String) = t
+        .sql
+        .query_row("SELECT folder, target FROM imap", (), |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .await?;
+    assert_eq!(folder, "Спам");
+    assert_eq!(target, "Tom & Jerry");
+    let folder: String = t
+        .sql
+        .query_get_value("SELECT folder FROM imap_sync", ())
+        .await?
+        .unwrap();
+    assert_eq!(folder, "Спам");
+
+    Ok(())
+}
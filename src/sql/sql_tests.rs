@@ -479,3 +479,145 @@ async fn assert_transport_removed(
 
     Ok(())
 }
+
+/// Tests that cached statements are recompiled after schema changes.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_statement_cache_schema_change() -> Result<()> {
+    let t = TestContext::new().await;
+    t.sql.execute("CREATE TABLE foo (a INTEGER)", ()).await?;
+    t.sql.insert("INSERT INTO foo (a) VALUES (?)", (1,)).await?;
+    let query = "SELECT * FROM foo";
+    let count_columns = |row: &rusqlite::Row| Ok(row.as_ref().column_count());
+    assert_eq!(t.sql.query_row(query, (), count_columns).await?, 1);
+
+    t.sql
+        .execute("ALTER TABLE foo ADD COLUMN b INTEGER DEFAULT 2", ())
+        .await?;
+    assert_eq!(t.sql.query_row(query, (), count_columns).await?, 2);
+    Ok(())
+}
+
+/// Returns the `EXPLAIN QUERY PLAN` output for `query`, one line per step.
+///
+/// All query parameters are bound to `NULL`.
+async fn query_plan(t: &TestContext, query: &str) -> Result<String> {
+    let query = format!("EXPLAIN QUERY PLAN {query}");
+    t.sql
+        .call(true, move |conn| {
+            let mut stmt = conn.prepare(&query)?;
+            for i in 1..=stmt.parameter_count() {
+                stmt.raw_bind_parameter(i, rusqlite::types::Null)?;
+            }
+            let mut rows = stmt.raw_query();
+            let mut plan = Vec::new();
+            while let Some(row) = rows.next()? {
+                plan.push(row.get::<_, String>(3)?);
+            }
+            Ok(plan.join("\n"))
+        })
+        .await
+}
+
+/// Tests that hot queries use the expected indices
+/// so that performance regressions are noticed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_query_plans() -> Result<()> {
+    let t = TestContext::new().await;
+    for (query, index) in [
+        // Last visible message of a chat, used by the chatlist.
+        (
+            "SELECT id FROM msgs WHERE
+             state=19 AND hidden=1 AND chat_id=?
+             OR state IN (10,13,16,20,24,26,27) AND hidden=0 AND chat_id=?
+             ORDER BY timestamp DESC, id DESC LIMIT 1",
+            "msgs_index7",
+        ),
+        // `get_chat_msgs()`.
+        (
+            "SELECT m.id AS id, m.timestamp AS timestamp
+             FROM msgs m
+             WHERE m.chat_id=?
+             AND m.hidden=0",
+            "msgs_index10",
+        ),
+        // `marknoticed_chat()`.
+        (
+            "UPDATE msgs SET state=13 WHERE state=10 AND hidden=0 AND chat_id=?",
+            "msgs_index7",
+        ),
+        // `markseen_msgs()`.
+        (
+            "SELECT m.chat_id, m.state, c.archived
+             FROM msgs m LEFT JOIN chats c ON c.id=m.chat_id
+             WHERE m.id=? AND m.chat_id>9",
+            "INTEGER PRIMARY KEY",
+        ),
+        (
+            "INSERT OR IGNORE INTO imap_markseen (id)
+             SELECT id FROM imap WHERE rfc724_mid=?",
+            "imap_only_rfc724_mid",
+        ),
+        // Message lookup by Message-ID, e.g. in `receive_imf()`.
+        ("SELECT id FROM msgs WHERE rfc724_mid=?", "msgs_index1"),
+    ] {
+        let plan = query_plan(&t, query).await?;
+        assert!(
+            plan.contains(index),
+            "Query\n{query}\ndoes not use {index}:\n{plan}"
+        );
+        assert!(!plan.contains("SCAN"), "Query\n{query}\nscans:\n{plan}");
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_housekeeping_due() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    let last_housekeeping = time() - constants::HOUSEKEEPING_PERIOD;
+    t.set_config_internal(
+        Config::LastHousekeeping,
+        Some(&last_housekeeping.to_string()),
+    )
+    .await?;
+    assert!(housekeeping_due(&t).await?);
+
+    // Housekeeping does not run right after starting IO.
+    t.io_start_timestamp.store(time(), Ordering::Relaxed);
+    assert!(!housekeeping_due(&t).await?);
+    t.io_start_timestamp.store(
+        time() - constants::HOUSEKEEPING_START_IO_DELAY,
+        Ordering::Relaxed,
+    );
+    assert!(housekeeping_due(&t).await?);
+
+    // Housekeeping does not run while messages are sent or received.
+    t.set_last_activity();
+    assert!(!housekeeping_due(&t).await?);
+    t.last_activity_timestamp.store(
+        time() - constants::HOUSEKEEPING_IDLE_PERIOD,
+        Ordering::Relaxed,
+    );
+    assert!(housekeeping_due(&t).await?);
+
+    // Housekeeping is not postponed forever.
+    t.set_last_activity();
+    assert!(!housekeeping_due(&t).await?);
+    let last_housekeeping = last_housekeeping - constants::HOUSEKEEPING_MAX_POSTPONEMENT;
+    t.set_config_internal(
+        Config::LastHousekeeping,
+        Some(&last_housekeeping.to_string()),
+    )
+    .await?;
+    assert!(housekeeping_due(&t).await?);
+
+    t.evtracker.clear_events();
+    t.run_housekeeping_now().await?;
+    t.evtracker
+        .get_matching(|evt| matches!(evt, EventType::HousekeepingProgress(1)))
+        .await;
+    t.evtracker
+        .get_matching(|evt| matches!(evt, EventType::HousekeepingProgress(1000)))
+        .await;
+    assert!(!housekeeping_due(&t).await?);
+    Ok(())
+}
@@ -30,8 +30,13 @@
 // const SENDING_INTERVAL_SECONDS: i64 = 60; // 1 minute (for testing)
 const MESSAGE_STATS_UPDATE_INTERVAL_SECONDS: i64 = 4 * 60; // 4 minutes (less than the lowest ephemeral messages timeout)
 
+/// Version of the statistics JSON schema.
+/// Must be increased whenever fields are removed or change their meaning.
+const STATISTICS_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Serialize)]
 struct Statistics {
+    schema_version: u32,
     core_version: String,
     number_of_transports: usize,
     key_create_timestamps: Vec<u32>,
@@ -41,7 +46,10 @@ struct Statistics {
     key_algorithm: String,
     /// Size of the public key in bytes (encoded in binary, not base64).
     pubkey_size: usize,
-    stats_id: String,
+    /// Random ID identifying the sender of the statistics.
+    /// Not set if the statistics were never sent and are only viewed locally.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats_id: Option<String>,
     is_chatmail: bool,
     contact_stats: Vec<ContactStat>,
     message_stats: BTreeMap<Chattype, MessageStats>,
@@ -214,6 +222,7 @@ pub(crate) async fn pre_sending_config_change(
 /// to Delta Chat's developers.
 pub async fn maybe_send_stats(context: &Context) -> Result<Option<ChatId>> {
     if should_send_stats(context).await?
+        && !context.is_data_saving().await?
         && time_has_passed(context, Config::StatsLastSent, SENDING_INTERVAL_SECONDS).await?
     {
         let chat_id = send_stats(context).await?;
@@ -345,7 +354,27 @@ async fn ensure_last_old_contact_id(context: &Context) -> Result<()> {
     Ok(())
 }
 
+impl Context {
+    /// Returns the usage statistics that would be sent
+    /// if "Send statistics to the developers of Delta Chat" is enabled,
+    /// as a JSON string, so that UIs can show them to the user.
+    ///
+    /// This does not send anything and works regardless of the setting,
+    /// however, message statistics are only collected while sending is enabled.
+    pub async fn get_usage_stats(&self) -> Result<String> {
+        let stats_id = self.get_config(Config::StatsId).await?;
+        let stats = collect_stats(self, stats_id).await?;
+        Ok(serde_json::to_string_pretty(&stats)?)
+    }
+}
+
 async fn get_stats(context: &Context) -> Result<String> {
+    let stats_id = stats_id(context).await?;
+    let stats = collect_stats(context, Some(stats_id)).await?;
+    Ok(serde_json::to_string_pretty(&stats)?)
+}
+
+async fn collect_stats(context: &Context, stats_id: Option<String>) -> Result<Statistics> {
     // The Id of the last contact that already existed when the user enabled the setting.
     // Newer contacts will get the `new` flag set.
     let last_old_contact = context
@@ -368,6 +397,7 @@ async fn get_stats(context: &Context) -> Result<String> {
         get_timestamps(context, "stats_sending_disabled_events").await?;
 
     let stats = Statistics {
+        schema_version: STATISTICS_SCHEMA_VERSION,
         core_version: DC_VERSION_STR.to_string(),
         number_of_transports: context.count_transports().await?,
         key_create_timestamps,
@@ -375,7 +405,7 @@ async fn get_stats(context: &Context) -> Result<String> {
         key_version: self_public_key.primary_key.version().into(),
         key_algorithm: format!("{:?}", self_public_key.algorithm()),
         pubkey_size: DcKey::to_bytes(&self_public_key).len(),
-        stats_id: stats_id(context).await?,
+        stats_id,
         is_chatmail: context.is_chatmail().await?,
         contact_stats: get_contact_stats(context, last_old_contact).await?,
         message_stats: get_message_stats(context).await?,
@@ -386,7 +416,7 @@ async fn get_stats(context: &Context) -> Result<String> {
         sending_disabled_timestamps,
     };
 
-    Ok(serde_json::to_string_pretty(&stats)?)
+    Ok(stats)
 }
 
 async fn get_timestamps(context: &Context, sql_table: &str) -> Result<Vec<i64>> {
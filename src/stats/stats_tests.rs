@@ -4,6 +4,7 @@
 use crate::chat::{
     Chat, create_broadcast, create_group, create_group_unencrypted, get_chat_contacts,
 };
+use crate::chatlist::Chatlist;
 use crate::mimeparser::SystemMessage;
 use crate::qr::check_qr;
 use crate::securejoin::{get_securejoin_qr, join_securejoin, join_securejoin_with_ux_info};
@@ -53,6 +54,32 @@ async fn test_maybe_send_stats() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_usage_stats() -> Result<()> {
+    let alice = &TestContext::new_alice().await;
+    let chats_before = Chatlist::try_load(alice, 0, None, None).await?.len();
+
+    let stats = alice.get_usage_stats().await?;
+    let r: serde_json::Value = serde_json::from_str(&stats)?;
+    assert_eq!(r.get("schema_version").unwrap(), STATISTICS_SCHEMA_VERSION);
+    assert_eq!(r.get("core_version").unwrap(), DC_VERSION_STR);
+    assert!(r.get("stats_id").is_none());
+
+    // Viewing the statistics locally neither sends them nor prepares sending.
+    assert!(!alice.config_exists(Config::StatsId).await?);
+    assert_eq!(
+        Chatlist::try_load(alice, 0, None, None).await?.len(),
+        chats_before
+    );
+
+    alice.set_config_bool(Config::StatsSending, true).await?;
+    let stats_id = alice.get_config(Config::StatsId).await?.unwrap();
+    let r: serde_json::Value = serde_json::from_str(&alice.get_usage_stats().await?)?;
+    assert_eq!(r.get("stats_id").unwrap(), &stats_id);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_rewound_time() -> Result<()> {
     let alice = &TestContext::new_alice().await;
@@ -6,7 +6,7 @@
 use anyhow::{Result, bail};
 use parking_lot::RwLock;
 use strum::EnumProperty as EnumPropertyTrait;
-use strum_macros::EnumProperty;
+use strum_macros::{Display, EnumProperty, EnumString};
 
 use crate::accounts::Accounts;
 use crate::blob::BlobObject;
@@ -22,6 +22,130 @@
 pub struct StockStrings {
     /// Map from stock string ID to the translation.
     translated_stockstrings: Arc<RwLock<HashMap<usize, String>>>,
+
+    /// Map from stock string ID and plural category to the translation.
+    translated_plurals: Arc<RwLock<HashMap<(usize, PluralCategory), String>>>,
+
+    /// Language of the translations, used to select plural forms.
+    language: Arc<RwLock<String>>,
+}
+
+/// Plural category of a number as defined by CLDR,
+/// see <https://cldr.unicode.org/index/cldr-spec/plural-rules>.
+///
+/// Languages use different subsets of the categories,
+/// e.g. English uses only `One` and `Other`,
+/// while Polish uses `One`, `Few`, `Many` and `Other`.
+#[allow(missing_docs)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive, EnumString, Display,
+)]
+#[strum(serialize_all = "lowercase")]
+#[repr(u32)]
+pub enum PluralCategory {
+    Zero = 0,
+    One = 1,
+    Two = 2,
+    Few = 3,
+    Many = 4,
+    Other = 5,
+}
+
+impl PluralCategory {
+    /// Returns the plural category of `count` in `language`,
+    /// given as ISO 639-1 code, e.g. `pl`.
+    ///
+    /// Languages without known rules use the rules of English.
+    pub fn for_count(language: &str, count: usize) -> Self {
+        let n10 = count % 10;
+        let n100 = count % 100;
+        let slavic = || {
+            if n10 == 1 && n100 != 11 {
+                Self::One
+            } else if (2..=4).contains(&n10) && !(12..=14).contains(&n100) {
+                Self::Few
+            } else {
+                Self::Many
+            }
+        };
+        let language = language.split(['-', '_']).next().unwrap_or_default();
+        match language {
+            "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" | "lo" | "my" | "km" => Self::Other,
+            "fr" | "pt" | "hi" | "fa" | "bn" => {
+                if count <= 1 {
+                    Self::One
+                } else {
+                    Self::Other
+                }
+            }
+            "ru" | "uk" | "be" => slavic(),
+            "bs" | "hr" | "sr" => match slavic() {
+                Self::Many => Self::Other,
+                category => category,
+            },
+            "pl" => match count {
+                1 => Self::One,
+                _ => match slavic() {
+                    Self::One => Self::Many,
+                    category => category,
+                },
+            },
+            "cs" | "sk" => match count {
+                1 => Self::One,
+                2..=4 => Self::Few,
+                _ => Self::Other,
+            },
+            "ar" => match count {
+                0 => Self::Zero,
+                1 => Self::One,
+                2 => Self::Two,
+                _ if (3..=10).contains(&n100) => Self::Few,
+                _ if (11..=99).contains(&n100) => Self::Many,
+                _ => Self::Other,
+            },
+            "he" => match count {
+                1 => Self::One,
+                2 => Self::Two,
+                _ => Self::Other,
+            },
+            "lt" => {
+                if (11..=19).contains(&n100) {
+                    Self::Other
+                } else if n10 == 1 {
+                    Self::One
+                } else if n10 >= 2 {
+                    Self::Few
+                } else {
+                    Self::Other
+                }
+            }
+            "lv" => {
+                if n10 == 0 || (11..=19).contains(&n100) {
+                    Self::Zero
+                } else if n10 == 1 {
+                    Self::One
+                } else {
+                    Self::Other
+                }
+            }
+            "ro" => {
+                if count == 1 {
+                    Self::One
+                } else if count == 0 || (2..=19).contains(&n100) {
+                    Self::Few
+                } else {
+                    Self::Other
+                }
+            }
+            _ => {
+                if count == 1 {
+                    Self::One
+                } else {
+                    Self::Other
+                }
+            }
+        }
+    }
 }
 
 /// Stock strings
@@ -421,6 +545,25 @@ pub enum StockMessage {
 
     #[strum(props(fallback = "Messages are end-to-end encrypted."))]
     MessagesAreE2ee = 242,
+
+    #[strum(props(fallback = "%1$s changes to the member list."))]
+    MsgMemberChanges = 243,
+
+    #[strum(props(
+        fallback = "iCloud requires an app-specific password. Create one at https://account.apple.com under \"Sign-In and Security\" and use it instead of your Apple Account password."
+    ))]
+    AppPasswordHintIcloud = 244,
+
+    #[strum(props(
+        fallback = "Microsoft Exchange may reject your regular password. If two-factor authentication is enabled, create an app password at https://account.microsoft.com/security, otherwise ask your administrator to enable IMAP and SMTP authentication."
+    ))]
+    AppPasswordHintExchange = 245,
+
+    #[strum(props(fallback = "Message size %1$s exceeds the limit of %2$s set by the server."))]
+    MsgTooLarge = 246,
+
+    #[strum(props(fallback = "[Incomplete message, %1$s of %2$s parts received]"))]
+    SplitMsgIncomplete = 247,
 }
 
 impl StockMessage {
@@ -443,6 +586,8 @@ impl StockStrings {
     pub fn new() -> Self {
         Self {
             translated_stockstrings: Arc::new(RwLock::new(Default::default())),
+            translated_plurals: Arc::new(RwLock::new(Default::default())),
+            language: Arc::new(RwLock::new(Default::default())),
         }
     }
 
@@ -455,32 +600,96 @@ fn translated(&self, id: StockMessage) -> String {
             .to_string()
     }
 
+    /// Returns the plural form of the translation for `count`.
+    ///
+    /// Falls back to the `Other` plural form
+    /// and then to the translation set without plural category.
+    fn translated_plural(&self, id: StockMessage, count: usize) -> String {
+        let category = PluralCategory::for_count(&self.language.read(), count);
+        let plurals = self.translated_plurals.read();
+        plurals
+            .get(&(id as usize, category))
+            .or_else(|| plurals.get(&(id as usize, PluralCategory::Other)))
+            .cloned()
+            .unwrap_or_else(|| self.translated(id))
+    }
+
     fn set_stock_translation(&self, id: StockMessage, stockstring: String) -> Result<()> {
-        if stockstring.contains("%1") && !id.fallback().contains("%1") {
+        check_placeholders(id, &stockstring)?;
+        self.translated_stockstrings
+            .write()
+            .insert(id as usize, stockstring);
+        Ok(())
+    }
+
+    fn set_stock_translation_plural(
+        &self,
+        id: StockMessage,
+        category: PluralCategory,
+        stockstring: String,
+    ) -> Result<()> {
+        check_placeholders(id, &stockstring)?;
+        self.translated_plurals
+            .write()
+            .insert((id as usize, category), stockstring);
+        Ok(())
+    }
+
+    fn set_stock_language(&self, language: &str) {
+        *self.language.write() = language.to_string();
+    }
+}
+
+/// Checks that all placeholders of the translation `stockstring`
+/// are well-formed, e.g. `%1$s`, and exist in the default string.
+fn check_placeholders(id: StockMessage, stockstring: &str) -> Result<()> {
+    let allowed = placeholders(id.fallback())?;
+    for index in placeholders(stockstring)? {
+        if !allowed.contains(&index) {
             bail!(
-                "translation {} contains invalid %1 placeholder, default is {}",
+                "translation {} contains invalid %{} placeholder, default is {}",
                 stockstring,
+                index,
                 id.fallback()
             );
         }
-        if stockstring.contains("%2") && !id.fallback().contains("%2") {
+    }
+    Ok(())
+}
+
+/// Returns the indices of the placeholders in `s`, e.g. 2 for `%2$s`.
+fn placeholders(s: &str) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for (pos, _) in s.match_indices('%') {
+        let rest = s.get(pos.saturating_add(1)..).unwrap_or_default();
+        let digits = rest
+            .len()
+            .saturating_sub(rest.trim_start_matches(|c: char| c.is_ascii_digit()).len());
+        if digits == 0 {
+            continue;
+        }
+        let (index, rest) = rest.split_at(digits);
+        let mut chars = rest.chars();
+        if chars.next() != Some('$') || !matches!(chars.next(), Some('s' | 'd' | '@')) {
             bail!(
-                "translation {} contains invalid %2 placeholder, default is {}",
-                stockstring,
-                id.fallback()
+                "translation {s} contains malformed placeholder %{index}, expected e.g. %{index}$s"
             );
         }
-        self.translated_stockstrings
-            .write()
-            .insert(id as usize, stockstring);
-        Ok(())
+        indices.push(index.parse()?);
     }
+    Ok(indices)
 }
 
 fn translated(context: &Context, id: StockMessage) -> String {
     context.translated_stockstrings.translated(id)
 }
 
+/// Returns the translation of `id` in the plural form for `count`,
+/// see [`Context::set_stock_translation_plural()`].
+fn stock_str_plural(context: &Context, id: StockMessage, count: usize) -> String {
+    context.translated_stockstrings.translated_plural(id, count)
+}
+
 /// Helper trait only meant to be implemented for [`String`].
 trait StockStringMods: AsRef<str> + Sized {
     /// Substitutes the first replacement value if one is present.
@@ -1003,6 +1212,30 @@ pub(crate) fn error_no_network(context: &Context) -> String {
     translated(context, StockMessage::ErrorNoNetwork)
 }
 
+/// Stock string: `iCloud requires an app-specific password...`.
+pub(crate) fn app_password_hint_icloud(context: &Context) -> String {
+    translated(context, StockMessage::AppPasswordHintIcloud)
+}
+
+/// Stock string: `Microsoft Exchange may reject your regular password...`.
+pub(crate) fn app_password_hint_exchange(context: &Context) -> String {
+    translated(context, StockMessage::AppPasswordHintExchange)
+}
+
+/// Stock string: `Message size %1$s exceeds the limit of %2$s set by the server.`.
+pub(crate) fn msg_too_large(context: &Context, size: &str, max_size: &str) -> String {
+    translated(context, StockMessage::MsgTooLarge)
+        .replace1(size)
+        .replace2(max_size)
+}
+
+/// Stock string: `[Incomplete message, %1$s of %2$s parts received]`.
+pub(crate) fn split_msg_incomplete(context: &Context, received: u32, count: u32) -> String {
+    translated(context, StockMessage::SplitMsgIncomplete)
+        .replace1(&received.to_string())
+        .replace2(&count.to_string())
+}
+
 /// Stock string: `Messages are end-to-end encrypted.`, used in info-messages, UI may add smth. as `Tap to learn more.`
 pub(crate) fn messages_e2ee_info_msg(context: &Context) -> String {
     translated(context, StockMessage::ChatProtectionEnabled)
@@ -1013,6 +1246,11 @@ pub(crate) fn messages_are_e2ee(context: &Context) -> String {
     translated(context, StockMessage::MessagesAreE2ee)
 }
 
+/// Stock string: `%1$s changes to the member list.`.
+pub(crate) fn msg_member_changes(context: &Context, count: usize) -> String {
+    stock_str_plural(context, StockMessage::MsgMemberChanges, count).replace1(&count.to_string())
+}
+
 /// Stock string: `Reply`.
 pub(crate) fn reply_noun(context: &Context) -> String {
     translated(context, StockMessage::ReplyNoun)
@@ -1224,6 +1462,29 @@ pub fn set_stock_translation(&self, id: StockMessage, stockstring: String) -> Re
         Ok(())
     }
 
+    /// Set the stock string for the [StockMessage] used for numbers of the plural `category`.
+    ///
+    /// If no string is set for a category, the string of [`PluralCategory::Other`] is used,
+    /// then the string set with [`Context::set_stock_translation()`].
+    /// The category of a number depends on the language set with
+    /// [`Context::set_stock_language()`].
+    pub fn set_stock_translation_plural(
+        &self,
+        id: StockMessage,
+        category: PluralCategory,
+        stockstring: String,
+    ) -> Result<()> {
+        self.translated_stockstrings
+            .set_stock_translation_plural(id, category, stockstring)
+    }
+
+    /// Set the language of the stock strings as ISO 639-1 code, e.g. `pl`.
+    ///
+    /// The language is used to select the plural forms of the stock strings.
+    pub fn set_stock_language(&self, language: &str) {
+        self.translated_stockstrings.set_stock_language(language);
+    }
+
     pub(crate) async fn update_device_chats(&self) -> Result<()> {
         if self.get_config_bool(Config::Bot).await? {
             return Ok(());
@@ -1260,6 +1521,23 @@ pub fn set_stock_translation(&self, id: StockMessage, stockstring: String) -> Re
         self.stockstrings.set_stock_translation(id, stockstring)?;
         Ok(())
     }
+
+    /// Set the stock string for the [StockMessage] used for numbers of the plural `category`,
+    /// see [`Context::set_stock_translation_plural()`].
+    pub fn set_stock_translation_plural(
+        &self,
+        id: StockMessage,
+        category: PluralCategory,
+        stockstring: String,
+    ) -> Result<()> {
+        self.stockstrings
+            .set_stock_translation_plural(id, category, stockstring)
+    }
+
+    /// Set the language of the stock strings as ISO 639-1 code, e.g. `pl`.
+    pub fn set_stock_language(&self, language: &str) {
+        self.stockstrings.set_stock_language(language);
+    }
 }
 
 #[cfg(test)]
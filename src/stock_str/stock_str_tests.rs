@@ -1,4 +1,5 @@
-use num_traits::ToPrimitive;
+use anyhow::Result;
+use num_traits::{FromPrimitive, ToPrimitive};
 
 use super::*;
 use crate::chat::delete_and_reset_all_device_msgs;
@@ -39,6 +40,105 @@ async fn test_set_stock_translation_wrong_replacements() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_set_stock_translation_malformed_placeholders() {
+    let t = TestContext::new().await;
+    for translation in [
+        "%1 Änderungen",
+        "%1s Änderungen",
+        "%1$ Änderungen",
+        "%1$x Änderungen",
+    ] {
+        assert!(
+            t.set_stock_translation(StockMessage::MsgMemberChanges, translation.to_string())
+                .is_err(),
+            "{translation}"
+        );
+    }
+    assert!(
+        t.set_stock_translation(
+            StockMessage::MsgMemberChanges,
+            "%3$s Änderungen".to_string()
+        )
+        .is_err()
+    );
+    t.set_stock_translation(
+        StockMessage::MsgMemberChanges,
+        "100% %1$d Änderungen".to_string(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_fallback_placeholders() {
+    for id in (0..1000).filter_map(StockMessage::from_u32) {
+        assert!(placeholders(id.fallback()).is_ok(), "{id:?}");
+    }
+}
+
+#[test]
+fn test_plural_category() {
+    use PluralCategory::*;
+    for (language, counts) in [
+        ("en", [Other, One, Other, Other, Other, Other, Other]),
+        ("de", [Other, One, Other, Other, Other, Other, Other]),
+        ("fr", [One, One, Other, Other, Other, Other, Other]),
+        ("ja", [Other, Other, Other, Other, Other, Other, Other]),
+        ("ru", [Many, One, Few, Many, Many, One, Few]),
+        ("pl", [Many, One, Few, Many, Many, Many, Few]),
+        ("cs", [Other, One, Few, Other, Other, Other, Other]),
+        ("ar", [Zero, One, Two, Few, Many, Many, Other]),
+    ] {
+        for (count, category) in [0, 1, 2, 5, 11, 21, 102].into_iter().zip(counts) {
+            assert_eq!(
+                PluralCategory::for_count(language, count),
+                category,
+                "{language} {count}"
+            );
+        }
+    }
+    assert_eq!(PluralCategory::for_count("pt-BR", 0), One);
+    assert_eq!(PluralCategory::for_count("unknown", 1), One);
+    assert_eq!("few".parse::<PluralCategory>().unwrap(), Few);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stock_str_plural() -> Result<()> {
+    let t = TestContext::new().await;
+    assert_eq!(msg_member_changes(&t, 3), "3 changes to the member list.");
+
+    t.set_stock_language("pl");
+    t.set_stock_translation(
+        StockMessage::MsgMemberChanges,
+        "%1$s zmian listy członków.".to_string(),
+    )?;
+    assert_eq!(msg_member_changes(&t, 3), "3 zmian listy członków.");
+
+    t.set_stock_translation_plural(
+        StockMessage::MsgMemberChanges,
+        PluralCategory::Few,
+        "%1$s zmiany listy członków.".to_string(),
+    )?;
+    t.set_stock_translation_plural(
+        StockMessage::MsgMemberChanges,
+        PluralCategory::Other,
+        "%1$s zmian listy członków.".to_string(),
+    )?;
+    assert_eq!(msg_member_changes(&t, 3), "3 zmiany listy członków.");
+    assert_eq!(msg_member_changes(&t, 5), "5 zmian listy członków.");
+    assert_eq!(msg_member_changes(&t, 22), "22 zmiany listy członków.");
+
+    assert!(
+        t.set_stock_translation_plural(
+            StockMessage::MsgMemberChanges,
+            PluralCategory::Many,
+            "%2$s zmian".to_string(),
+        )
+        .is_err()
+    );
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_stock_str() {
     let t = TestContext::new().await;
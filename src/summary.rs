@@ -17,7 +17,7 @@
 use crate::param::Param;
 use crate::stock_str;
 use crate::stock_str::msg_reacted;
-use crate::tools::truncate;
+use crate::tools::{isolate_bidi, truncate};
 
 /// Prefix displayed before message and separated by ":" in the chatlist.
 #[derive(Debug)]
@@ -148,27 +148,66 @@ pub async fn new(
         })
     }
 
-    /// Returns the [`Summary::text`] attribute truncated to an approximate length.
-    pub fn truncated_text(&self, approx_chars: usize) -> Cow<'_, str> {
-        truncate(&self.text, approx_chars)
+    /// Returns the [`Summary::text`] attribute truncated to an approximate length,
+    /// see [`truncate()`].
+    pub fn truncated_text(&self, approx_len: usize) -> Cow<'_, str> {
+        truncate(&self.text, approx_len)
     }
 }
 
+/// Parts the summary of a message is composed of.
+struct SummaryParts {
+    /// Emoji describing the type of the message.
+    emoji: Option<&'static str>,
+
+    /// Name of the message type, such as "Image".
+    type_name: Option<String>,
+
+    /// Name of the attached file or similar.
+    type_file: Option<String>,
+
+    /// Whether the message text should be appended.
+    append_text: bool,
+
+    /// Whether the message has an attachment.
+    is_attachment: bool,
+}
+
 impl Message {
     /// Returns a summary text.
     pub(crate) async fn get_summary_text(&self, context: &Context) -> String {
         let summary = self.get_summary_text_without_prefix(context).await;
 
         if self.is_forwarded() {
-            format!("{}: {}", stock_str::forwarded(context), summary)
+            format!(
+                "{}: {}",
+                stock_str::forwarded(context),
+                isolate_bidi(&summary)
+            )
         } else {
             summary
         }
     }
 
-    /// Returns a summary text without "Forwarded:" prefix.
-    async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
+    /// Returns a descriptor of the attachment, such as "📷 Image" or "📎 report.pdf".
+    ///
+    /// Returns `None` if the message has no attachment.
+    pub async fn get_file_type_descriptor(&self, context: &Context) -> Option<String> {
+        let parts = self.get_summary_parts(context).await;
+        if !parts.is_attachment {
+            return None;
+        }
+        let descriptor = parts.type_file.or(parts.type_name)?;
+        Some(match parts.emoji {
+            Some(emoji) => format!("{emoji} {descriptor}"),
+            None => descriptor,
+        })
+    }
+
+    /// Returns the parts the summary text is composed of, depending on the viewtype.
+    async fn get_summary_parts(&self, context: &Context) -> SummaryParts {
         let (emoji, type_name, type_file, append_text);
+        let mut is_attachment = true;
         let viewtype = match self
             .param
             .get_i64(Param::PostMessageViewtype)
@@ -265,10 +304,12 @@ async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
                     CallState::Canceled => stock_str::canceled_call(context),
                 });
                 type_file = None;
-                append_text = false
+                append_text = false;
+                is_attachment = false;
             }
             Viewtype::Text | Viewtype::Unknown => {
                 emoji = None;
+                is_attachment = false;
                 if self.param.get_cmd() == SystemMessage::LocationOnly {
                     type_name = Some(stock_str::location(context));
                     type_file = None;
@@ -281,11 +322,32 @@ async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
             }
         };
 
+        SummaryParts {
+            emoji,
+            type_name,
+            type_file,
+            append_text,
+            is_attachment,
+        }
+    }
+
+    /// Returns a summary text without "Forwarded:" prefix.
+    pub(crate) async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
+        let SummaryParts {
+            emoji,
+            type_name,
+            type_file,
+            append_text,
+            ..
+        } = self.get_summary_parts(context).await;
+
         let text = self.text.clone();
 
+        // Filenames and texts are isolated from each other
+        // so that right-to-left text does not reorder the parts.
         let summary = if let Some(type_file) = type_file {
             if append_text && !text.is_empty() {
-                format!("{type_file} – {text}")
+                format!("{} – {}", isolate_bidi(&type_file), isolate_bidi(&text))
             } else {
                 type_file
             }
@@ -293,7 +355,7 @@ async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
             if emoji.is_some() {
                 text
             } else if let Some(type_name) = type_name {
-                format!("{type_name} – {text}")
+                format!("{type_name} – {}", isolate_bidi(&text))
             } else {
                 text
             }
@@ -491,4 +553,56 @@ async fn write_file_to_blobdir(d: &TestContext) -> PathBuf {
             "📎 foo.bar \u{2013} bla bla"
         ); // skipping prefix used for reactions summaries
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_summary_text_bidi() -> Result<()> {
+        let t = TestContext::new_alice().await;
+
+        // Right-to-left filename does not swap places with the text.
+        let file = t.get_blobdir().join("report.pdf");
+        tokio::fs::write(&file, b"PDF").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_text("see attachment".to_string());
+        msg.set_file_and_deduplicate(&t, &file, Some("דוח.pdf"), None)?;
+        assert_summary_texts(&msg, &t, "📎 \u{2068}דוח.pdf\u{2069} – see attachment").await;
+
+        // Right-to-left text is isolated from the filename.
+        msg.param.set(Param::Filename, "report.pdf");
+        msg.set_text("ראה קובץ מצורף".to_string());
+        assert_summary_texts(&msg, &t, "📎 report.pdf – \u{2068}ראה קובץ מצורף\u{2069}").await;
+
+        // Right-to-left text is isolated from the "Forwarded:" prefix.
+        let mut msg = Message::new_text("שלום".to_string());
+        msg.param.set_int(Param::Forwarded, 1);
+        assert_eq!(
+            msg.get_summary_text(&t).await,
+            "Forwarded: \u{2068}שלום\u{2069}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_file_type_descriptor() -> Result<()> {
+        let t = TestContext::new_alice().await;
+
+        let msg = Message::new_text("bla".to_string());
+        assert_eq!(msg.get_file_type_descriptor(&t).await, None);
+
+        let file = t.get_blobdir().join("report.pdf");
+        tokio::fs::write(&file, b"PDF").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_text("bla".to_string());
+        msg.set_file_and_deduplicate(&t, &file, Some("report.pdf"), None)?;
+        assert_eq!(
+            msg.get_file_type_descriptor(&t).await.as_deref(),
+            Some("📎 report.pdf")
+        );
+
+        let msg = Message::new(Viewtype::Image);
+        assert_eq!(
+            msg.get_file_type_descriptor(&t).await.as_deref(),
+            Some("📷 Image")
+        );
+        Ok(())
+    }
 }
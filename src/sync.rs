@@ -99,6 +99,10 @@ pub(crate) enum SyncData {
     DeleteMessages {
         msgs: Vec<String>, // RFC724 id (i.e. "Message-Id" header)
     },
+    SetMsgReminder {
+        msg: String, // RFC724 id (i.e. "Message-Id" header)
+        timestamp: i64,
+    },
 
     /// Update transport configuration.
     ///
@@ -176,6 +180,48 @@ async fn add_sync_item_with_timestamp(&self, data: SyncData, timestamp: i64) ->
         Ok(())
     }
 
+    /// Adds a [`chat::SyncAction::SetLastRead`] item for the chat `id`
+    /// replacing the one not sent yet, if any,
+    /// so that at most one read marker per chat is synchronized with the next sync message.
+    /// The caller should call `SchedulerState::interrupt_smtp()` on its own to trigger sending.
+    pub(crate) async fn replace_last_read_sync_item(
+        &self,
+        id: chat::SyncId,
+        rfc724_mid: String,
+    ) -> Result<()> {
+        if !self.should_send_sync_msgs().await? {
+            return Ok(());
+        }
+        let pending = self
+            .sql
+            .query_map_vec("SELECT id, item FROM multi_device_sync", (), |row| {
+                let row_id: u32 = row.get(0)?;
+                let item: String = row.get(1)?;
+                Ok((row_id, item))
+            })
+            .await?;
+        for (row_id, item) in pending {
+            let Ok(item) = serde_json::from_str::<SyncItem>(&item) else {
+                continue;
+            };
+            if let SyncDataOrUnknown::SyncData(AlterChat {
+                id: item_id,
+                action: chat::SyncAction::SetLastRead(_),
+            }) = item.data
+                && item_id == id
+            {
+                self.sql
+                    .execute("DELETE FROM multi_device_sync WHERE id=?", (row_id,))
+                    .await?;
+            }
+        }
+        self.add_sync_item(AlterChat {
+            id,
+            action: chat::SyncAction::SetLastRead(rfc724_mid),
+        })
+        .await
+    }
+
     /// Adds most recent qr-code tokens for the given group or self-contact to the list of items to
     /// be synced. If device synchronization is disabled,
     /// no tokens exist or the chat is unpromoted, the function does nothing.
@@ -324,6 +370,9 @@ pub(crate) async fn execute_sync_items(&self, items: &SyncItems, timestamp_sent:
                     SyncData::Config { key, val } => self.sync_config(key, val).await,
                     SyncData::SaveMessage { src, dest } => self.save_message(src, dest).await,
                     SyncData::DeleteMessages { msgs } => self.sync_message_deletion(msgs).await,
+                    SyncData::SetMsgReminder { msg, timestamp } => {
+                        self.sync_msg_reminder(msg, *timestamp).await
+                    }
                     SyncData::Transports {
                         transports,
                         removed_transports,
@@ -401,6 +450,17 @@ async fn sync_message_deletion(&self, msgs: &Vec<String>) -> Result<()> {
         message::delete_msgs_locally_done(self, &msg_ids, modified_chat_ids).await?;
         Ok(())
     }
+
+    async fn sync_msg_reminder(&self, rfc724_mid: &str, timestamp: i64) -> Result<()> {
+        if let Some(msg_id) = message::rfc724_mid_exists(self, rfc724_mid).await? {
+            msg_id
+                .set_reminder_ex(self, Sync::Nosync, timestamp)
+                .await?;
+        } else {
+            warn!(self, "Sync message reminder: {rfc724_mid:?} not found.");
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -23,7 +23,8 @@
 use uuid::Uuid;
 
 use crate::chat::{
-    self, Chat, ChatId, ChatIdBlocked, MessageListOptions, add_to_chat_contacts_table, create_group,
+    self, Chat, ChatId, ChatIdBlocked, DaymarkerTimezone, MessageListOptions,
+    add_to_chat_contacts_table, create_group,
 };
 use crate::chatlist::Chatlist;
 use crate::config::Config;
@@ -1120,6 +1121,7 @@ async fn display_chat(&self, chat_id: ChatId) -> String {
             chat_id,
             MessageListOptions {
                 add_daymarker: false,
+                daymarker_timezone: DaymarkerTimezone::Local,
             },
         )
         .await
@@ -1824,6 +1826,48 @@ fn drop(&mut self) {
     }
 }
 
+/// Starts an HTTP server on localhost answering all requests with `status`.
+///
+/// Returns the URL of the server
+/// and a receiver for the bodies of the received requests.
+pub(crate) async fn start_http_server(status: u16) -> Result<(String, Receiver<Vec<u8>>)> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let (body_sender, body_receiver) = channel::unbounded();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut stream = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap_or_default() == 0 {
+                    break;
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap_or_default();
+                }
+            }
+            let mut body = vec![0; content_length];
+            if stream.read_exact(&mut body).await.is_err() {
+                continue;
+            }
+            body_sender.try_send(body).ok();
+            let response = format!("HTTP/1.1 {status} Test\r\nContent-Length: 0\r\n\r\n");
+            stream.write_all(response.as_bytes()).await.ok();
+            stream.flush().await.ok();
+        }
+    });
+    Ok((url, body_receiver))
+}
+
 /// Method to create a test image file
 pub(crate) fn create_test_image(width: u32, height: u32) -> Result<Vec<u8>> {
     use image::{ImageBuffer, Rgb, RgbImage};
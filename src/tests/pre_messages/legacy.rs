@@ -25,7 +25,7 @@ async fn test_download_stub_message() -> Result<()> {
         .execute(
             r#"INSERT INTO chats VALUES(
                     11001,100,'bob@example.com',0,'',2,'',
-                    replace('C=1763151754\nt=foo','\n',char(10)),0,0,0,0,0,1763151754,0,NULL,0,'');
+                    replace('C=1763151754\nt=foo','\n',char(10)),0,0,0,0,0,1763151754,0,NULL,0,'',0,0);
                 "#,
             (),
         )
@@ -3,6 +3,7 @@
 use pretty_assertions::assert_eq;
 
 use crate::EventType;
+use crate::blob::BlobIssueKind;
 use crate::chat;
 use crate::chat::send_msg;
 use crate::config::Config;
@@ -1007,3 +1008,49 @@ async fn test_bot_pre_message_notifications() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests that a damaged attachment received in a Post-Message
+/// is restored when `verify_blobs` downloads the Post-Message again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_verify_blobs_redownloads_post_message() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let alice_group_id = alice.create_group_with_members("test group", &[bob]).await;
+
+    let content = vec![1u8; 1_000_000];
+    let (pre_message, post_message, _alice_msg_id) =
+        send_large_file_message(alice, alice_group_id, Viewtype::File, &content).await?;
+    let msg = bob.recv_msg(&pre_message).await;
+    bob.recv_msg_trash(&post_message).await;
+    let msg = Message::load_from_db(bob, msg.id).await?;
+    assert_eq!(msg.download_state(), DownloadState::Done);
+    let path = msg.get_file(bob).context("No file")?;
+    tokio::fs::write(&path, b"damaged").await?;
+
+    // The Post-Message is still on the server.
+    bob.sql
+        .execute(
+            "INSERT INTO imap (transport_id, rfc724_mid, folder, uid, uidvalidity, target)
+             VALUES (1, ?, 'INBOX', 1, 1, 'INBOX')",
+            (msg.rfc724_mid(),),
+        )
+        .await?;
+    let report = bob.verify_blobs(true).await?;
+    assert_eq!(report.issues.len(), 1);
+    let issue = &report.issues[0];
+    assert_eq!(issue.msg_id, msg.id);
+    assert_eq!(issue.kind, BlobIssueKind::Corrupted);
+    assert!(issue.redownloaded);
+    let msg = Message::load_from_db(bob, msg.id).await?;
+    assert_eq!(msg.download_state(), DownloadState::InProgress);
+
+    // The IMAP loop downloads the Post-Message.
+    bob.recv_msg_trash(&post_message).await;
+    let msg = Message::load_from_db(bob, msg.id).await?;
+    assert_eq!(msg.download_state(), DownloadState::Done);
+    assert_eq!(msg.get_viewtype(), Viewtype::File);
+    assert_eq!(tokio::fs::read(msg.get_file(bob).unwrap()).await?, content);
+    assert!(bob.verify_blobs(false).await?.issues.is_empty());
+    Ok(())
+}
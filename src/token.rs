@@ -20,6 +20,13 @@ pub enum Namespace {
     Unknown = 0,
     Auth = 110,
     InviteNumber = 100,
+
+    /// Auth token of a single-use setup-contact QR code,
+    /// see [`crate::securejoin::get_securejoin_qr_single_use`].
+    SingleUseAuth = 111,
+
+    /// Single-use auth token that was already used.
+    UsedSingleUseAuth = 112,
 }
 
 /// Saves a token to the database.
@@ -33,6 +33,7 @@
 use mailparse::headers::Headers;
 use num_traits::PrimInt;
 use tokio::{fs, io};
+use unicode_segmentation::UnicodeSegmentation;
 use url::Url;
 use uuid::Uuid;
 
@@ -40,43 +41,105 @@
 use crate::config::Config;
 use crate::constants::{self, DC_ELLIPSIS, DC_OUTDATED_WARNING_DAYS};
 use crate::context::Context;
+use crate::diagnostics::MAX_CLOCK_SKEW;
 use crate::events::EventType;
-use crate::log::warn;
+use crate::log::{LogExt, warn};
 use crate::message::{Message, Viewtype};
+use crate::net::http::get_server_time;
 use crate::stock_str;
+use crate::transport::ConfiguredLoginParam;
+
+/// Returns the characters needed to close bidirectional embeddings, overrides and isolates
+/// left open in `text`.
+pub(crate) fn close_bidi_controls(text: &str) -> String {
+    const PDF: char = '\u{202C}';
+    const PDI: char = '\u{2069}';
+
+    let mut open = Vec::new();
+    for c in text.chars() {
+        match c {
+            // LRE, RLE, LRO, RLO
+            '\u{202A}' | '\u{202B}' | '\u{202D}' | '\u{202E}' => open.push(PDF),
+            // LRI, RLI, FSI
+            '\u{2066}' | '\u{2067}' | '\u{2068}' => open.push(PDI),
+            PDF if open.last() == Some(&PDF) => {
+                open.pop();
+            }
+            PDI => {
+                // PDI also closes all embeddings within the isolate.
+                if let Some(pos) = open.iter().rposition(|&c| c == PDI) {
+                    open.truncate(pos);
+                }
+            }
+            _ => {}
+        }
+    }
+    open.into_iter().rev().collect()
+}
 
-/// Shortens a string to a specified length and adds "[...]" to the
+/// Wraps `text` into a first strong isolate (FSI ... PDI)
+/// if it contains right-to-left characters or bidirectional controls,
+/// so it does not affect the order of the text surrounding it.
+pub(crate) fn isolate_bidi(text: &str) -> Cow<'_, str> {
+    const FSI: char = '\u{2068}';
+    const PDI: char = '\u{2069}';
+
+    let needs_isolation = text.chars().any(|c| {
+        matches!(c,
+            // Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, Mandaic and their extensions
+            '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+            // Bidirectional marks, embeddings, overrides and isolates
+            | '\u{200E}' | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}'
+        )
+    });
+    if needs_isolation {
+        Cow::Owned(format!("{FSI}{text}{}{PDI}", close_bidi_controls(text)))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Shortens a string to approximately `approx_len` graphemes and adds "[...]" to the
 /// end of the shortened string.
-#[expect(clippy::arithmetic_side_effects)]
-pub(crate) fn truncate(buf: &str, approx_chars: usize) -> Cow<'_, str> {
-    let count = buf.chars().count();
-    if count <= approx_chars + DC_ELLIPSIS.len() {
+///
+/// The string is only cut between grapheme clusters,
+/// so emoji sequences and combining characters stay intact,
+/// and, if possible, at the last space or newline.
+/// Bidirectional embeddings and isolates left open by cutting the string are closed
+/// so right-to-left text does not affect the ellipsis and the surrounding text.
+pub(crate) fn truncate(buf: &str, approx_len: usize) -> Cow<'_, str> {
+    let count = buf.graphemes(true).count();
+    if count <= approx_len.saturating_add(DC_ELLIPSIS.len()) {
         return Cow::Borrowed(buf);
     }
     let end_pos = buf
-        .char_indices()
-        .nth(approx_chars)
+        .grapheme_indices(true)
+        .nth(approx_len)
         .map(|(n, _)| n)
         .unwrap_or_default();
-
-    if let Some(index) = buf.get(..end_pos).and_then(|s| s.rfind([' ', '\n'])) {
-        Cow::Owned(format!(
-            "{}{}",
-            buf.get(..=index).unwrap_or_default(),
-            DC_ELLIPSIS
-        ))
-    } else {
-        Cow::Owned(format!(
-            "{}{}",
-            buf.get(..end_pos).unwrap_or_default(),
-            DC_ELLIPSIS
-        ))
-    }
+    let truncated = buf.get(..end_pos).unwrap_or_default();
+    let truncated = match truncated.rfind([' ', '\n']) {
+        Some(index) => truncated.get(..=index).unwrap_or_default(),
+        None => truncated,
+    };
+    Cow::Owned(format!(
+        "{truncated}{}{DC_ELLIPSIS}",
+        close_bidi_controls(truncated)
+    ))
 }
 
 /// Shortens a string to a specified line count and adds "[...]" to the
 /// end of the shortened string.
 ///
+/// Bidirectional embeddings and isolates left open by cutting the text are closed
+/// before "[...]", see [`close_bidi_controls()`].
+///
 /// returns tuple with the String and a boolean whether is was truncated
 #[expect(clippy::arithmetic_side_effects)]
 pub(crate) fn truncate_by_lines(
@@ -120,7 +183,13 @@ pub(crate) fn truncate_by_lines(
         };
 
         if let Some(truncated_text) = text {
-            (format!("{truncated_text}{DC_ELLIPSIS}"), true)
+            (
+                format!(
+                    "{truncated_text}{}{DC_ELLIPSIS}",
+                    close_bidi_controls(truncated_text)
+                ),
+                true,
+            )
         } else {
             // In case of indexing/slicing error, we return an error
             // message as a preview and add HTML version. This should
@@ -199,6 +268,7 @@ pub(crate) async fn maybe_add_time_based_warnings(context: &Context) {
     if !maybe_warn_on_bad_time(context, time(), get_release_timestamp()).await {
         maybe_warn_on_outdated(context, time(), get_release_timestamp()).await;
     }
+    maybe_update_clock_skew(context).await.log_err(context).ok();
 }
 
 async fn maybe_warn_on_bad_time(context: &Context, now: i64, known_past_timestamp: i64) -> bool {
@@ -256,6 +326,96 @@ async fn maybe_warn_on_outdated(context: &Context, now: i64, approx_compile_time
     }
 }
 
+/// Returns the number of seconds the local clock is ahead of the server clock,
+/// negative if it is behind.
+pub(crate) async fn clock_skew(context: &Context) -> Result<i64> {
+    context.get_config_i64(Config::ClockSkew).await
+}
+
+/// Returns the current time as a unix timestamp, corrected by the measured clock skew.
+///
+/// Should be used instead of [`time()`] for timestamps that are compared
+/// with timestamps from other devices, e.g. the timestamps of outgoing messages.
+pub(crate) async fn corrected_time(context: &Context) -> Result<i64> {
+    Ok(time().saturating_sub(clock_skew(context).await?))
+}
+
+/// Clock skew in seconds below which the local clock is not corrected.
+///
+/// The `Date` header has a resolution of one second
+/// and the measurement includes the network delay,
+/// so smaller differences are measurement noise.
+const MIN_CLOCK_SKEW: i64 = 5;
+
+/// Returns the clock skew to compensate for the measured `skew`,
+/// ignoring skews below [`MIN_CLOCK_SKEW`].
+fn significant_clock_skew(skew: i64) -> i64 {
+    if skew.abs() < MIN_CLOCK_SKEW { 0 } else { skew }
+}
+
+/// Compares the local clock with the clock of the primary IMAP server host
+/// and stores the difference in [`Config::ClockSkew`]
+/// unless it is below [`MIN_CLOCK_SKEW`].
+///
+/// Returns the measured difference.
+///
+/// Emits [`EventType::ClockSkewDetected`] if the difference exceeds [`MAX_CLOCK_SKEW`].
+pub(crate) async fn measure_clock_skew(
+    context: &Context,
+    param: &ConfiguredLoginParam,
+) -> Result<i64> {
+    let host = &param
+        .imap
+        .first()
+        .context("No IMAP server configured")?
+        .connection
+        .host;
+    let server_time = get_server_time(context, &format!("https://{host}/")).await?;
+    let skew = time().saturating_sub(server_time);
+    context
+        .set_config_internal(
+            Config::ClockSkew,
+            Some(&significant_clock_skew(skew).to_string()),
+        )
+        .await?;
+    if skew.abs() > MAX_CLOCK_SKEW {
+        warn!(
+            context,
+            "Local clock differs from the clock of {host:?} by {skew} seconds."
+        );
+        context.emit_event(EventType::ClockSkewDetected { skew });
+    }
+    Ok(skew)
+}
+
+/// Measures the clock skew once a day
+/// and after network changes, see [`reset_clock_skew_check`].
+async fn maybe_update_clock_skew(context: &Context) -> Result<()> {
+    let now = time();
+    let last_check = context.get_config_i64(Config::LastClockSkewCheck).await?;
+    if (last_check..last_check.saturating_add(24 * 60 * 60)).contains(&now) {
+        return Ok(());
+    }
+    let Some((_transport_id, param)) = ConfiguredLoginParam::load(context).await? else {
+        return Ok(());
+    };
+    // Set the timestamp before measuring so that unreachable hosts are not retried
+    // in every inbox loop iteration.
+    context
+        .set_config_internal(Config::LastClockSkewCheck, Some(&now.to_string()))
+        .await?;
+    measure_clock_skew(context, &param).await?;
+    Ok(())
+}
+
+/// Makes the next inbox loop iteration measure the clock skew again,
+/// e.g. because the network changed and the previous measurement may be wrong.
+pub(crate) async fn reset_clock_skew_check(context: &Context) -> Result<()> {
+    context
+        .set_config_internal(Config::LastClockSkewCheck, None)
+        .await
+}
+
 /// Generate an unique ID.
 ///
 /// The generated ID should be short but unique:
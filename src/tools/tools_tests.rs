@@ -111,16 +111,53 @@ fn test_truncate_edge() {
 
     assert_eq!(truncate("\n  hello \n world", 4), "\n  [...]");
 
-    assert_eq!(truncate("𐠈0Aᝮa𫝀®!ꫛa¡0A𐢧00𐹠®A  丽ⷐએ", 1), "𐠈[...]");
-    assert_eq!(truncate("𐠈0Aᝮa𫝀®!ꫛa¡0A𐢧00𐹠®A  丽ⷐએ", 0), "[...]");
+    assert_eq!(truncate("𐠈0Aᝮa𫝀®!ꫛa¡0A𐢧00𐹠®A  丽ⷐએ", 1), "𐠈[...]");
+    assert_eq!(truncate("𐠈0Aᝮa𫝀®!ꫛa¡0A𐢧00𐹠®A  丽ⷐએ", 0), "[...]");
 
-    // 9 characters, so no truncation
+    // 8 graphemes, so no truncation
     assert_eq!(truncate("𑒀ὐ￠🜀\u{1e01b}A a🟠", 6), "𑒀ὐ￠🜀\u{1e01b}A a🟠",);
 
-    // 12 characters, truncation
+    // 11 graphemes, so no truncation either, as combining characters are not counted
     assert_eq!(
         truncate("𑒀ὐ￠🜀\u{1e01b}A a🟠bcd", 6),
-        "𑒀ὐ￠🜀\u{1e01b}A[...]",
+        "𑒀ὐ￠🜀\u{1e01b}A a🟠bcd",
+    );
+
+    // 12 graphemes, truncation
+    assert_eq!(
+        truncate("𑒀ὐ￠🜀\u{1e01b}A a🟠bcde", 6),
+        "𑒀ὐ￠🜀\u{1e01b}A [...]",
+    );
+}
+
+#[test]
+fn test_truncate_graphemes() {
+    // Family emoji consisting of several code points joined with ZWJ.
+    let family = "👨\u{200d}👩\u{200d}👧";
+    let text = family.repeat(20);
+    assert_eq!(truncate(&text, 2), format!("{family}{family}[...]"));
+
+    // Flags consist of two regional indicator symbols.
+    let flags = "🇩🇪🇫🇷🇮🇹🇪🇸🇳🇱🇧🇪🇦🇹🇨🇭";
+    assert_eq!(truncate(flags, 1), "🇩🇪[...]");
+    assert_eq!(truncate(flags, 3), flags);
+}
+
+#[test]
+fn test_truncate_bidi() {
+    // Right-to-left isolate is closed before the ellipsis.
+    let text = "Hi \u{2067}שלום עולם מה שלומך היום\u{2069}!";
+    assert_eq!(truncate(text, 10), "Hi \u{2067}שלום \u{2069}[...]");
+
+    // Closed isolates are not closed again.
+    let text = "\u{2067}שלום\u{2069} and some more text";
+    assert_eq!(truncate(text, 12), "\u{2067}שלום\u{2069} and [...]");
+
+    // Nested embeddings are closed in reverse order.
+    let text = "\u{202B}abc \u{2066}def ghi jkl mno\u{2069}\u{202C}";
+    assert_eq!(
+        truncate(text, 10),
+        "\u{202B}abc \u{2066}def \u{2069}\u{202C}[...]"
     );
 }
 
@@ -195,6 +232,33 @@ fn test_edge() {
             ("𑒀ὐ￠🜀\u{1e01b}A [...]".to_string(), true),
         );
     }
+
+    #[test]
+    fn test_bidi() {
+        // Right-to-left isolate is closed before the ellipsis.
+        let s = "Quote: \u{2067}שלום עולם מה שלומך היום\u{2069}".to_string();
+        assert_eq!(
+            truncate_by_lines(s, 2, 12),
+            (
+                "Quote: \u{2067}שלום עולם מה \u{2069}[...]".to_string(),
+                true
+            )
+        );
+    }
+}
+
+#[test]
+fn test_isolate_bidi() {
+    assert_eq!(isolate_bidi("report.pdf"), "report.pdf");
+    assert_eq!(isolate_bidi(""), "");
+    assert_eq!(isolate_bidi("שלום.pdf"), "\u{2068}שלום.pdf\u{2069}");
+    assert_eq!(isolate_bidi("مرحبا"), "\u{2068}مرحبا\u{2069}");
+
+    // Open embeddings are closed within the isolate.
+    assert_eq!(
+        isolate_bidi("\u{202E}abc"),
+        "\u{2068}\u{202E}abc\u{202C}\u{2069}"
+    );
 }
 
 #[test]
@@ -245,20 +309,24 @@ fn test_create_outgoing_rfc724_mid() {
     #[test]
     fn test_truncate(
         buf: String,
-        approx_chars in 0..100usize
+        approx_len in 0..100usize
     ) {
-        let res = truncate(&buf, approx_chars);
+        let res = truncate(&buf, approx_len);
         let el_len = 5;
-        let l = res.chars().count();
+        let l = res.graphemes(true).count();
+        // Closing bidi controls may be added, one for each opened control.
+        let bidi_len = buf
+            .chars()
+            .filter(|c| matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'))
+            .count();
         assert!(
-            l <= approx_chars + el_len,
+            l <= approx_len + el_len + bidi_len,
             "buf: '{}' - res: '{}' - len {}, approx {}",
-            buf, res, res.len(), approx_chars
+            buf, res, res.len(), approx_len
         );
 
-        if buf.chars().count() > approx_chars + el_len {
-            let l = res.len();
-            assert_eq!(&res[l-5..l], "[...]", "missing ellipsis in {res}");
+        if buf.graphemes(true).count() > approx_len + el_len {
+            assert!(res.ends_with("[...]"), "missing ellipsis in {res}");
         }
     }
 }
@@ -611,3 +679,22 @@ fn test_sanitize_filename() {
     let name = sanitize_filename("Guia_uso_GNB (v0.8).pdf");
     assert_eq!(name, "Guia_uso_GNB (v0.8).pdf");
 }
+
+#[test]
+fn test_significant_clock_skew() {
+    assert_eq!(significant_clock_skew(0), 0);
+    assert_eq!(significant_clock_skew(1), 0);
+    assert_eq!(significant_clock_skew(-4), 0);
+    assert_eq!(significant_clock_skew(5), 5);
+    assert_eq!(significant_clock_skew(-600), -600);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_maybe_network_resets_clock_skew_check() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    t.set_config_internal(Config::LastClockSkewCheck, Some(&time().to_string()))
+        .await?;
+    t.maybe_network().await;
+    assert_eq!(t.get_config_i64(Config::LastClockSkewCheck).await?, 0);
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! # Chat transcripts.
+//!
+//! Renders the messages of a chat into a standalone file
+//! for printing or archiving.
+//! Images are embedded as thumbnails,
+//! so the file can be viewed without access to the blob directory.
+//! The output is the same on all platforms.
+
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context as _, Result, ensure};
+use chrono::{Local, TimeZone};
+use humansize::{BINARY, format_size};
+use tokio::fs;
+
+use crate::blob;
+use crate::chat::{Chat, ChatId, ChatItem, get_chat_msgs};
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::log::warn;
+use crate::message::{Message, Viewtype};
+use crate::tools::time;
+
+/// Maximum width and height of thumbnails embedded into transcripts.
+const TRANSCRIPT_THUMBNAIL_SIZE: u32 = 320;
+
+/// Style sheet of HTML transcripts.
+/// Messages are not split across pages when printing.
+const TRANSCRIPT_CSS: &str = "body { font-family: sans-serif; max-width: 50em; margin: auto; }
+.meta { color: #666; }
+.msg { margin: 0.5em 0; padding: 0.5em; border-radius: 0.5em; background: #eee; break-inside: avoid; }
+.msg.out { background: #dfe; margin-left: 3em; }
+.sender { font-weight: bold; }
+.time { color: #666; font-weight: normal; font-size: smaller; }
+.quote { border-left: 3px solid #999; padding-left: 0.5em; color: #444; }
+.info { text-align: center; font-style: italic; color: #666; margin: 0.5em 0; break-inside: avoid; }
+img { max-width: 100%; display: block; margin: 0.25em 0; }";
+
+/// Format of a chat transcript, see [`ChatId::render_transcript()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    /// Standalone HTML file with embedded thumbnails.
+    ///
+    /// The file contains a print style sheet,
+    /// so it can be converted to PDF by printing it.
+    Html,
+}
+
+impl ChatId {
+    /// Renders the messages of the chat sent within `range`
+    /// into a transcript file at `path`.
+    ///
+    /// `range` is a range of timestamps, use `0..i64::MAX` for all messages.
+    /// Hidden messages are not included.
+    pub async fn render_transcript(
+        self,
+        context: &Context,
+        range: Range<i64>,
+        format: TranscriptFormat,
+        path: &Path,
+    ) -> Result<()> {
+        let transcript = match format {
+            TranscriptFormat::Html => render_html(context, self, range).await?,
+        };
+        fs::write(path, transcript)
+            .await
+            .with_context(|| format!("Failed to write transcript to {}", path.display()))
+    }
+}
+
+async fn render_html(context: &Context, chat_id: ChatId, range: Range<i64>) -> Result<String> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    let chat_name = escaper::encode_minimal(chat.get_name());
+
+    let mut body = String::new();
+    let mut msg_count: usize = 0;
+    for item in get_chat_msgs(context, chat_id).await? {
+        let ChatItem::Message { msg_id } = item else {
+            continue;
+        };
+        let msg = Message::load_from_db(context, msg_id).await?;
+        if !range.contains(&msg.get_timestamp()) {
+            continue;
+        }
+        render_msg(context, &msg, &mut body).await?;
+        msg_count = msg_count.saturating_add(1);
+    }
+
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{chat_name}</title>\n\
+         <style>\n{TRANSCRIPT_CSS}\n</style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{chat_name}</h1>\n\
+         <p class=\"meta\">{msg_count} messages, exported {}.</p>",
+        format_time(time())
+    )?;
+    html += &body;
+    html += "</body>\n</html>\n";
+    Ok(html)
+}
+
+async fn render_msg(context: &Context, msg: &Message, html: &mut String) -> Result<()> {
+    let text = format_text(&msg.get_text());
+    if msg.is_info() {
+        writeln!(
+            html,
+            "<div class=\"info\">{text} <span class=\"time\">{}</span></div>",
+            format_time(msg.get_timestamp())
+        )?;
+        return Ok(());
+    }
+
+    let contact = Contact::get_by_id(context, msg.get_from_id()).await?;
+    let (class, sender) = if msg.get_from_id() == ContactId::SELF {
+        ("msg out", contact.get_display_name().to_string())
+    } else {
+        ("msg in", msg.get_sender_name(&contact))
+    };
+    writeln!(
+        html,
+        "<div class=\"{class}\">\n\
+         <div class=\"sender\">{} <span class=\"time\">{}</span></div>",
+        escaper::encode_minimal(&sender),
+        format_time(msg.get_timestamp())
+    )?;
+    if let Some(quote) = msg.quoted_text() {
+        writeln!(html, "<div class=\"quote\">{}</div>", format_text(&quote))?;
+    }
+    if let Some(thumbnail) = thumbnail(context, msg) {
+        writeln!(html, "<img src=\"data:image/jpeg;base64,{thumbnail}\">")?;
+    }
+    if !matches!(
+        msg.get_viewtype(),
+        Viewtype::Text | Viewtype::Image | Viewtype::Gif | Viewtype::Sticker
+    ) && let Some(filename) = msg.get_filename()
+    {
+        let size = msg.get_filebytes(context).await?.unwrap_or_default();
+        writeln!(
+            html,
+            "<div class=\"file\">{} ({})</div>",
+            escaper::encode_minimal(&filename),
+            format_size(size, BINARY)
+        )?;
+    }
+    if !text.is_empty() {
+        writeln!(html, "<div class=\"text\">{text}</div>")?;
+    }
+    html.push_str("</div>\n");
+    Ok(())
+}
+
+/// Returns a base64-encoded JPEG thumbnail of the attachment if it is an image.
+fn thumbnail(context: &Context, msg: &Message) -> Option<String> {
+    let path = match msg.get_viewtype() {
+        Viewtype::Image | Viewtype::Gif | Viewtype::Sticker => msg.get_file(context)?,
+        _ => return None,
+    };
+    match tokio::task::block_in_place(|| blob::create_thumbnail(&path, TRANSCRIPT_THUMBNAIL_SIZE)) {
+        Ok(thumbnail) => Some(thumbnail),
+        Err(err) => {
+            warn!(
+                context,
+                "Failed to create thumbnail for {}: {err:#}.", msg.id
+            );
+            None
+        }
+    }
+}
+
+fn format_text(text: &str) -> String {
+    escaper::encode_minimal(text).replace('\n', "<br>\n")
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local.timestamp_opt(timestamp, 0).single().map_or_else(
+        || "?".to_string(),
+        |time| time.format("%Y-%m-%d %H:%M").to_string(),
+    )
+}
+
+#[cfg(test)]
+mod transcript_tests;
@@ -0,0 +1,48 @@
+use super::*;
+use crate::test_utils::TestContextManager;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_render_transcript() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let chat_id = alice.create_chat_id(bob).await;
+    alice
+        .send_text(chat_id, "Hello <b>Bob</b>\nHow are you?")
+        .await;
+    let mut msg = Message::new(Viewtype::Image);
+    msg.set_file_from_bytes(
+        alice,
+        "logo.png",
+        include_bytes!("../../test-data/image/logo.png"),
+        None,
+    )?;
+    msg.set_text("A logo".to_string());
+    alice.send_msg(chat_id, &mut msg).await;
+    let mut msg = Message::new(Viewtype::File);
+    msg.set_file_from_bytes(alice, "notes.txt", b"Some notes", None)?;
+    alice.send_msg(chat_id, &mut msg).await;
+
+    let path = alice.get_blobdir().join("transcript.html");
+    chat_id
+        .render_transcript(alice, 0..i64::MAX, TranscriptFormat::Html, &path)
+        .await?;
+    let html = fs::read_to_string(&path).await?;
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>bob@example.net</h1>"));
+    assert!(html.contains("4 messages"));
+    assert!(html.contains("Hello &lt;b&gt;Bob&lt;/b&gt;<br>\nHow are you?"));
+    assert!(html.contains("<img src=\"data:image/jpeg;base64,"));
+    assert!(html.contains("A logo"));
+    assert!(html.contains("notes.txt (10 B)"));
+
+    // Messages outside of the range are not included.
+    chat_id
+        .render_transcript(alice, 0..1, TranscriptFormat::Html, &path)
+        .await?;
+    let html = fs::read_to_string(&path).await?;
+    assert!(html.contains("0 messages"));
+    assert!(!html.contains("Hello"));
+    Ok(())
+}
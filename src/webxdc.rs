@@ -44,6 +44,7 @@
 use crate::message::{Message, MessageState, MsgId, Viewtype};
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
 use crate::mimeparser::SystemMessage;
+use crate::notifications::{NotificationKind, add_notification};
 use crate::param::Param;
 use crate::param::Params;
 use crate::tools::{create_id, get_abs_path, time};
@@ -435,6 +436,15 @@ async fn create_status_update_record(
                 None
             };
             if let Some(notify_text) = notify_text {
+                add_notification(
+                    self,
+                    NotificationKind::WebxdcNotify,
+                    instance.chat_id,
+                    from_id,
+                    notify_msg_id,
+                    notify_text,
+                )
+                .await?;
                 self.emit_event(EventType::IncomingWebxdcNotify {
                     chat_id: instance.chat_id,
                     contact_id: from_id,